@@ -0,0 +1,130 @@
+use crate::{read_world_file, ZztConsole};
+use crate::draw_batch::{DrawBatch, RunCache};
+
+use ruzzt_engine::compatibility::{CompatibilityFixture, ComparisonRunner};
+use ruzzt_engine::console::{ConsoleChar, ConsoleColour};
+use zzt_file_format::dosstring::DosString;
+
+use sdl2::event::Event;
+use sdl2::image::INIT_PNG;
+use sdl2::keyboard::Keycode;
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::video::WindowContext;
+
+use std::path::Path;
+
+/// The width of each of the two boards, in characters.
+const BOARD_VIEW_WIDTH: usize = 60;
+/// The height of each of the two boards, in characters.
+const BOARD_VIEW_HEIGHT: usize = 25;
+/// The number of characters between the two boards.
+const GAP_WIDTH: usize = 1;
+/// The width of the comparison window, in characters.
+const COMPARISON_WIDTH: usize = BOARD_VIEW_WIDTH * 2 + GAP_WIDTH;
+
+/// Read the compatibility fixture at `fixture_path`, and the world it was recorded on (which is
+/// named relative to the fixture), ready to compare.
+pub fn load_comparison_runner(fixture_path: &Path) -> Result<ComparisonRunner, String> {
+	let text = std::fs::read_to_string(fixture_path).map_err(|err| err.to_string())?;
+	let fixture = CompatibilityFixture::parse(&text)?;
+	let world_path = fixture_path.parent().unwrap_or_else(|| Path::new(".")).join(&fixture.world_file_name);
+	let world = read_world_file(&world_path).map_err(|err| format!("{}: {}", world_path.display(), err))?;
+	Ok(ComparisonRunner::new(&world, fixture))
+}
+
+impl ZztConsole {
+	/// Show RUZZT's board on the left and the board recorded in the fixture on the right, with the
+	/// tiles that are different highlighted in red, instead of running the game. Right or Space
+	/// plays one step of the fixture, Enter plays until a step doesn't match, and Escape quits.
+	pub fn run_comparison(&mut self, mut runner: ComparisonRunner) {
+		let scale = 2;
+		let render_width = (COMPARISON_WIDTH * 8) as u32;
+		let render_height = ((BOARD_VIEW_HEIGHT + 1) * 14) as u32;
+
+		let sdl_context = sdl2::init().unwrap();
+		let sdl_video = sdl_context.video().unwrap();
+		let _sdl_image = sdl2::image::init(INIT_PNG).unwrap();
+		let window = sdl_video.window("RUZZT comparison", render_width * scale, render_height * scale)
+			.position_centered()
+			.build()
+			.unwrap();
+
+		let mut canvas = window.into_canvas().software().present_vsync().build().unwrap();
+		let texture_creator = canvas.texture_creator();
+		let mut dosfont_tex = self.create_font_texture(&texture_creator);
+		let mut run_cache = RunCache::new(&texture_creator);
+		canvas.set_scale(scale as f32, scale as f32).ok();
+
+		let mut running = true;
+		while running {
+			for event in sdl_context.event_pump().unwrap().poll_iter() {
+				match event {
+					Event::Quit{..} => {
+						running = false;
+					}
+					Event::KeyDown {keycode: Some(keycode), ..} => {
+						match keycode {
+							Keycode::Escape => {
+								running = false;
+							}
+							Keycode::Right | Keycode::Space => {
+								runner.step();
+							}
+							Keycode::Return => {
+								while runner.step() {}
+							}
+							_ => {}
+						}
+					}
+					_ => {}
+				}
+			}
+
+			self.draw_comparison(&mut canvas, &mut dosfont_tex, &mut run_cache, &mut runner);
+			canvas.present();
+		}
+	}
+
+	/// Draw both boards and the status line under them.
+	fn draw_comparison(&self, canvas: &mut WindowCanvas, dosfont_tex: &mut Texture, run_cache: &mut RunCache<WindowContext>, runner: &mut ComparisonRunner) {
+		runner.engine.update_screen();
+		let divergent_tiles = runner.divergent_tiles();
+		let reference_chars = runner.reference_board_chars();
+		let mut batch = DrawBatch::new(dosfont_tex.query().height);
+
+		for y in 0 .. BOARD_VIEW_HEIGHT {
+			for x in 0 .. BOARD_VIEW_WIDTH {
+				let is_divergent = divergent_tiles.contains(&(x, y));
+				let highlight = |screen_char: ConsoleChar| {
+					if is_divergent {
+						ConsoleChar::new(screen_char.char_code, ConsoleColour::Red, ConsoleColour::White)
+					} else {
+						screen_char
+					}
+				};
+
+				self.draw_char(&mut batch, x, y, highlight(runner.engine.console_state.get_char(x, y)));
+
+				// Fixtures without the board bytes can only be compared by their hashes.
+				let reference_char = match reference_chars {
+					Some(ref rows) => highlight(rows[y][x]),
+					None => ConsoleChar::new(0xb0, ConsoleColour::Black, ConsoleColour::DarkGray),
+				};
+				self.draw_char(&mut batch, BOARD_VIEW_WIDTH + GAP_WIDTH + x, y, reference_char);
+			}
+		}
+
+		let mismatch_text = match runner.first_mismatch() {
+			Some(mismatch) => format!("First mismatch: step {}", mismatch.step_index + 1),
+			None => "No mismatches".to_string(),
+		};
+		let status_text = format!(" Step {}/{}  {}  {} different   Right: step  Enter: run  Esc: quit",
+			runner.steps_played(), runner.step_count(), mismatch_text, divergent_tiles.len());
+		let status_text = DosString::from_str(&status_text);
+		for x in 0 .. COMPARISON_WIDTH {
+			let char_code = status_text.data.get(x).cloned().unwrap_or(b' ');
+			self.draw_char(&mut batch, x, BOARD_VIEW_HEIGHT, ConsoleChar::new(char_code, ConsoleColour::Blue, ConsoleColour::Yellow));
+		}
+		batch.draw(canvas, dosfont_tex, run_cache);
+	}
+}
@@ -0,0 +1,193 @@
+use serde_derive::{Serialize, Deserialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// The name of the file the config is stored in, within the user's config directory.
+const CONFIG_FILE_NAME: &str = "config.json";
+/// The name that a config file that couldn't be parsed is moved to, so it isn't overwritten.
+const BAD_CONFIG_FILE_NAME: &str = "config.json.bad";
+
+/// Things remembered about a world between runs of RUZZT.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldRecord {
+	/// The highest score reached in a game of the world.
+	pub high_score: i16,
+	/// Every flag that has been set by the end of a game of the world. Worlds often set a flag when
+	/// they are completed, so this can be used to tell which endings have been reached.
+	pub completed_flags: BTreeSet<String>,
+}
+
+/// The user's settings, which are loaded when RUZZT starts and saved whenever they change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+	/// Maps the name of a key (as SDL names it, eg. "A" or "Left") to the name of the key it should
+	/// behave as. For example, `{"A": "Left"}` makes the A key move the player left.
+	pub key_bindings: BTreeMap<String, String>,
+	/// The name of the palette to use: "standard", "high-contrast" or "colour-blind".
+	pub palette: String,
+	/// The path to a PNG with a font to use instead of the built-in one. It must have the same
+	/// layout as `res/dosfont.png`.
+	pub font: Option<PathBuf>,
+	/// The path to a PNG with an 8x8 font to use in the 80x50 text mode, in the same layout as
+	/// `font`. When it isn't set, the usual font is squashed to fit.
+	pub font_8x8: Option<PathBuf>,
+	/// When false, no sound is played.
+	pub sound_enabled: bool,
+	/// The game speed picked in the title screen, from fastest (0) to slowest (8).
+	pub game_speed: u8,
+	/// The number of steps a movement key has to be held before it starts repeating.
+	pub key_repeat_delay: u8,
+	/// The file name of the world that was most recently opened, which is loaded on startup when no
+	/// world is given on the command line.
+	pub last_played_world: Option<String>,
+	/// Records for each world, keyed by the world's name.
+	pub worlds: BTreeMap<String, WorldRecord>,
+	/// The game is autosaved after this many boards have been entered. 0 turns this off.
+	pub autosave_boards: usize,
+	/// The game is autosaved after this many minutes of play. 0 turns this off.
+	pub autosave_minutes: f64,
+	/// The sample rate to open the audio device with, in samples per second.
+	pub audio_sample_rate: i32,
+	/// The number of samples in the audio device's buffer, which is usually a power of 2. Bigger
+	/// buffers add latency but stop the sound from breaking up on slow or wireless output. When it
+	/// isn't set, SDL picks the size.
+	pub audio_buffer_samples: Option<u16>,
+	/// True if the config file exists but couldn't be loaded (and couldn't be moved out of the way),
+	/// so `save` doesn't overwrite it with the default settings.
+	#[serde(skip)]
+	pub saving_disabled: bool,
+}
+
+impl Default for UserConfig {
+	fn default() -> UserConfig {
+		UserConfig {
+			key_bindings: BTreeMap::new(),
+			palette: "standard".to_string(),
+			font: None,
+			font_8x8: None,
+			sound_enabled: true,
+			game_speed: 4,
+			key_repeat_delay: 2,
+			last_played_world: None,
+			worlds: BTreeMap::new(),
+			autosave_boards: 5,
+			autosave_minutes: 5.,
+			audio_sample_rate: 44100,
+			audio_buffer_samples: None,
+			saving_disabled: false,
+		}
+	}
+}
+
+impl UserConfig {
+	/// Get the path to the config file, or None if the user's config directory can't be found.
+	pub fn config_path() -> Option<PathBuf> {
+		directories::ProjectDirs::from("", "", "ruzzt")
+			.map(|project_dirs| project_dirs.config_dir().join(CONFIG_FILE_NAME))
+	}
+
+	/// Get the directory autosaves are written to, or None if the user's data directory can't be
+	/// found.
+	pub fn autosave_directory() -> Option<PathBuf> {
+		directories::ProjectDirs::from("", "", "ruzzt")
+			.map(|project_dirs| project_dirs.data_dir().join("autosave"))
+	}
+
+	/// Load the user's config. If it doesn't exist yet the default config is returned. If it can't be
+	/// parsed, an error is printed, the file is moved to `BAD_CONFIG_FILE_NAME` so it can be fixed by
+	/// hand, and the default config is returned. If it can't be read or moved, the default config is
+	/// returned with `saving_disabled` set, so the file is left alone.
+	pub fn load() -> UserConfig {
+		let config_path = if let Some(config_path) = UserConfig::config_path() {
+			config_path
+		} else {
+			return UserConfig::default();
+		};
+
+		match std::fs::read_to_string(&config_path) {
+			Ok(config_json) => {
+				match serde_json::from_str::<UserConfig>(&config_json) {
+					Ok(mut config) => {
+						config.replace_invalid_settings();
+						config
+					}
+					Err(err) => {
+						eprintln!("Couldn't parse {:?}: {}", config_path, err);
+						let bad_config_path = config_path.with_file_name(BAD_CONFIG_FILE_NAME);
+						match std::fs::rename(&config_path, &bad_config_path) {
+							Ok(()) => {
+								eprintln!("It was moved to {:?}, and the default settings are used", bad_config_path);
+								UserConfig::default()
+							}
+							Err(err) => {
+								eprintln!("Couldn't move it to {:?} ({}), so settings won't be saved", bad_config_path, err);
+								UserConfig::unsaved_default()
+							}
+						}
+					}
+				}
+			}
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => UserConfig::default(),
+			Err(err) => {
+				eprintln!("Couldn't read {:?}: {}, so settings won't be saved", config_path, err);
+				UserConfig::unsaved_default()
+			}
+		}
+	}
+
+	/// Get the default config with `saving_disabled` set.
+	fn unsaved_default() -> UserConfig {
+		UserConfig {
+			saving_disabled: true,
+			.. UserConfig::default()
+		}
+	}
+
+	/// Put the settings that are out of range (eg. from editing the config file by hand) back to
+	/// their defaults, printing an error for each, the same as invalid command line arguments.
+	fn replace_invalid_settings(&mut self) {
+		let default_config = UserConfig::default();
+		if self.audio_sample_rate <= 0 {
+			eprintln!("Invalid sample rate in the config: {}", self.audio_sample_rate);
+			self.audio_sample_rate = default_config.audio_sample_rate;
+		}
+		if self.audio_buffer_samples == Some(0) {
+			eprintln!("Invalid audio buffer size in the config: 0");
+			self.audio_buffer_samples = default_config.audio_buffer_samples;
+		}
+	}
+
+	/// Write the config to the user's config directory, creating the directory if it doesn't exist.
+	/// Fails without writing anything if `saving_disabled` is set.
+	pub fn save(&self) -> Result<(), String> {
+		let config_path = UserConfig::config_path().ok_or("Couldn't find the config directory")?;
+		if self.saving_disabled {
+			return Err(format!("Settings aren't saved, because {:?} couldn't be loaded", config_path));
+		}
+		if let Some(config_dir) = config_path.parent() {
+			std::fs::create_dir_all(config_dir).map_err(|err| format!("Couldn't create {:?}: {}", config_dir, err))?;
+		}
+		let config_json = serde_json::to_string_pretty(self).map_err(|err| format!("{}", err))?;
+		std::fs::write(&config_path, config_json).map_err(|err| format!("Couldn't write {:?}: {}", config_path, err))
+	}
+
+	/// Save the config, printing an error if it fails.
+	pub fn save_or_print_error(&self) {
+		if let Err(err) = self.save() {
+			eprintln!("{}", err);
+		}
+	}
+
+	/// Get the name of the key that the key called `key_name` should behave as.
+	pub fn bound_key_name<'a>(&'a self, key_name: &'a str) -> &'a str {
+		self.key_bindings.get(key_name).map(|bound_name| bound_name.as_str()).unwrap_or(key_name)
+	}
+
+	/// Get the record for the world with the given `world_name`, creating it if it doesn't exist.
+	pub fn world_record_mut(&mut self, world_name: &str) -> &mut WorldRecord {
+		self.worlds.entry(world_name.to_string()).or_insert_with(WorldRecord::default)
+	}
+}
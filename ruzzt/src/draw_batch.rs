@@ -0,0 +1,187 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, Texture, TextureCreator};
+
+use std::collections::HashMap;
+
+/// A red, green and blue colour.
+type Rgb = (u8, u8, u8);
+
+/// A character on the screen: its character code, its background colour, and its foreground colour
+/// if it isn't hidden (eg. by blinking).
+type Cell = (u8, Rgb, Option<Rgb>);
+
+/// The most characters that a `RunCache` keeps textures for, all its runs put together. This is
+/// four screens of 80x25 characters.
+const RUN_CACHE_CELL_CAPACITY: usize = 8000;
+
+/// The characters of a run and how high they are drawn, which is everything that decides what its
+/// texture looks like.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct RunKey {
+	char_height: u32,
+	cells: Vec<Cell>,
+}
+
+/// Textures of runs of characters (see `DrawBatch`) that have already been drawn, so when the same
+/// run comes up again it is copied to the screen in one go. This happens a lot: blinking characters
+/// flip between the same two runs, the side bar hardly changes, and going back to a board draws
+/// the same rows as last time. A character on its own is a run too, so this also keeps coloured
+/// glyphs for the few characters that change each step.
+///
+/// When the textures hold more than `RUN_CACHE_CELL_CAPACITY` characters, the ones that were used
+/// least recently are dropped.
+pub struct RunCache<'a, U> {
+	texture_creator: &'a TextureCreator<U>,
+	/// The texture of each run, and the `draw_count` when it was last used.
+	textures: HashMap<RunKey, (Texture<'a>, u64)>,
+	/// The number of characters in all the runs in `textures`.
+	cell_count: usize,
+	/// Counts up each time a run is drawn, to find the least recently used runs.
+	draw_count: u64,
+}
+
+impl<'a, U> RunCache<'a, U> {
+	/// Make an empty cache, that makes its textures with `texture_creator`.
+	pub fn new(texture_creator: &'a TextureCreator<U>) -> RunCache<'a, U> {
+		RunCache {
+			texture_creator,
+			textures: HashMap::new(),
+			cell_count: 0,
+			draw_count: 0,
+		}
+	}
+
+	/// Drop all the textures. This must be called when the font texture changes.
+	pub fn clear(&mut self) {
+		self.textures.clear();
+		self.cell_count = 0;
+	}
+
+	/// Get the texture of the run with the given `key`, drawing it from `cells` if it isn't in the
+	/// cache yet. Returns None if the texture couldn't be made, eg. if the renderer doesn't support
+	/// drawing to textures.
+	fn texture<T: RenderTarget>(&mut self, canvas: &mut Canvas<T>, dosfont_tex: &mut Texture, font_char_height: u32, key: RunKey, cells: &[(Rect, Cell)]) -> Option<&Texture<'a>> {
+		self.draw_count += 1;
+
+		if !self.textures.contains_key(&key) {
+			if !canvas.render_target_supported() {
+				return None;
+			}
+			let width = 8 * cells.len() as u32;
+			let mut texture = self.texture_creator.create_texture_target(self.texture_creator.default_pixel_format(), width, key.char_height).ok()?;
+			let (origin_x, origin_y) = (cells[0].0.x(), cells[0].0.y());
+			canvas.with_texture_canvas(&mut texture, |texture_canvas| {
+				draw_cells(texture_canvas, dosfont_tex, font_char_height, cells, origin_x, origin_y);
+			}).ok()?;
+
+			self.cell_count += key.cells.len();
+			while self.cell_count > RUN_CACHE_CELL_CAPACITY {
+				let oldest_key = match self.textures.iter().min_by_key(|(_, (_, last_used))| *last_used) {
+					Some((oldest_key, _)) => oldest_key.clone(),
+					None => break,
+				};
+				self.textures.remove(&oldest_key);
+				self.cell_count -= oldest_key.cells.len();
+			}
+			self.textures.insert(key.clone(), (texture, 0));
+		}
+
+		let (texture, last_used) = self.textures.get_mut(&key)?;
+		*last_used = self.draw_count;
+		Some(&*texture)
+	}
+}
+
+/// Characters waiting to be drawn. When the batch is drawn, characters next to each other in a row
+/// are joined into runs, and each run is copied to the screen in one go from its texture in a
+/// `RunCache`. On a full redraw this is one copy for each row instead of one for each of the 2000
+/// characters, as long as the rows have been seen before.
+///
+/// A run that isn't in the cache yet (or every run, if the renderer can't draw to textures) is
+/// drawn a character at a time, grouped by colour, so the draw colour and the colour mod of the
+/// font texture only change once for each colour in the run instead of once for every character.
+pub struct DrawBatch {
+	/// The height of a character in the font texture.
+	font_char_height: u32,
+	/// The characters to draw, with where to draw them on the screen.
+	cells: Vec<(Rect, Cell)>,
+}
+
+impl DrawBatch {
+	/// Make an empty batch for drawing from a font texture with characters `font_char_height`
+	/// pixels high.
+	pub fn new(font_char_height: u32) -> DrawBatch {
+		DrawBatch {
+			font_char_height,
+			cells: vec![],
+		}
+	}
+
+	/// Add a character at `dest_rect` on the screen, with the given background colour and, if it
+	/// isn't hidden (eg. by blinking), the given foreground colour.
+	pub fn add_char(&mut self, char_code: u8, dest_rect: Rect, back_rgb: Rgb, fore_rgb: Option<Rgb>) {
+		self.cells.push((dest_rect, (char_code, back_rgb, fore_rgb)));
+	}
+
+	/// Draw all the characters added to the batch to `canvas`, and empty the batch. The textures of
+	/// the runs are kept in `run_cache` for next time.
+	pub fn draw<T: RenderTarget, U>(&mut self, canvas: &mut Canvas<T>, dosfont_tex: &mut Texture, run_cache: &mut RunCache<U>) {
+		let mut cells = std::mem::take(&mut self.cells);
+		cells.sort_by_key(|(dest_rect, _)| (dest_rect.y(), dest_rect.x()));
+
+		let mut run_start = 0;
+		while run_start < cells.len() {
+			let mut run_end = run_start + 1;
+			while run_end < cells.len() && continues_run(cells[run_end - 1].0, cells[run_end].0) {
+				run_end += 1;
+			}
+			let run = &cells[run_start .. run_end];
+			run_start = run_end;
+
+			let first_rect = run[0].0;
+			let run_rect = Rect::new(first_rect.x(), first_rect.y(), 8 * run.len() as u32, first_rect.height());
+			let key = RunKey {
+				char_height: first_rect.height(),
+				cells: run.iter().map(|(_, cell)| *cell).collect(),
+			};
+			match run_cache.texture(canvas, dosfont_tex, self.font_char_height, key, run) {
+				Some(texture) => canvas.copy(texture, None, Some(run_rect)).expect("Render failed"),
+				None => draw_cells(canvas, dosfont_tex, self.font_char_height, run, 0, 0),
+			}
+		}
+	}
+}
+
+/// True if a character drawn at `next_rect` carries on the run that ends with a character drawn at
+/// `prev_rect`, because it is just to the right of it.
+fn continues_run(prev_rect: Rect, next_rect: Rect) -> bool {
+	next_rect.y() == prev_rect.y() && next_rect.height() == prev_rect.height() && next_rect.x() == prev_rect.right()
+}
+
+/// Draw `cells` to `canvas` a character at a time, moved up and left by `origin_x` and `origin_y`.
+/// The characters are grouped by colour, and all the backgrounds are drawn before the foregrounds,
+/// which is fine because characters never overlap.
+fn draw_cells<T: RenderTarget>(canvas: &mut Canvas<T>, dosfont_tex: &mut Texture, font_char_height: u32, cells: &[(Rect, Cell)], origin_x: i32, origin_y: i32) {
+	let mut backgrounds: HashMap<Rgb, Vec<Rect>> = HashMap::new();
+	let mut glyphs: HashMap<Rgb, Vec<(Rect, Rect)>> = HashMap::new();
+	for &(dest_rect, (char_code, back_rgb, fore_rgb)) in cells {
+		let dest_rect = Rect::new(dest_rect.x() - origin_x, dest_rect.y() - origin_y, dest_rect.width(), dest_rect.height());
+		backgrounds.entry(back_rgb).or_default().push(dest_rect);
+		if let Some(fore_rgb) = fore_rgb {
+			let char_rect = Rect::new(8 * (char_code as i32), 0, 8, font_char_height);
+			glyphs.entry(fore_rgb).or_default().push((char_rect, dest_rect));
+		}
+	}
+
+	for (back_rgb, rects) in backgrounds {
+		canvas.set_draw_color(sdl2::pixels::Color::RGB(back_rgb.0, back_rgb.1, back_rgb.2));
+		canvas.fill_rects(&rects).ok();
+	}
+
+	for (fore_rgb, rects) in glyphs {
+		dosfont_tex.set_color_mod(fore_rgb.0, fore_rgb.1, fore_rgb.2);
+		for (char_rect, dest_rect) in rects {
+			canvas.copy(dosfont_tex, Some(char_rect), Some(dest_rect)).expect("Render failed");
+		}
+	}
+}
@@ -0,0 +1,61 @@
+use crate::ZztConsole;
+
+use ruzzt_engine::compatibility::board_hash;
+use ruzzt_engine::event::Event;
+
+use std::time::Instant;
+
+/// The settings for running the game without a window (see `ZztConsole::run_headless`).
+pub struct HeadlessRun {
+	/// The number of game cycles to simulate.
+	pub steps: usize,
+	/// The seed for the random number generator, so runs can be reproduced.
+	pub seed: u64,
+}
+
+impl ZztConsole {
+	/// Simulate the loaded world for `run.steps` game cycles as fast as possible with no input,
+	/// without opening a window or playing any sound, then print the hash of the final board (see
+	/// `ruzzt_engine::compatibility::board_hash`) and how long it took. Scrolls are closed as soon
+	/// as they open so they don't stop the game.
+	pub fn run_headless(&mut self, run: HeadlessRun) {
+		if self.engine.in_title_screen {
+			self.engine.set_in_title_screen(false);
+		}
+		self.engine.is_paused = false;
+		self.engine.seed_rng(run.seed);
+
+		let start_time = Instant::now();
+		let mut slowest_step_ms = 0.;
+		for _ in 0 .. run.steps {
+			let step_start_time = Instant::now();
+
+			self.engine.step_whole_cycle(Event::None, 0., |engine, mut board_messages| {
+				while !board_messages.is_empty() {
+					for board_message in std::mem::take(&mut board_messages) {
+						board_messages.extend(engine.process_board_message(board_message));
+					}
+				}
+				engine.scroll_state = None;
+			});
+
+			let step_ms = step_start_time.elapsed().as_secs_f64() * 1000.;
+			slowest_step_ms = f64::max(slowest_step_ms, step_ms);
+		}
+		let total_seconds = start_time.elapsed().as_secs_f64();
+
+		println!("Board: {}", self.engine.board_simulator.world_header.player_board);
+		println!("Board hash: {:016x}", board_hash(&self.engine.board_simulator));
+		println!("Steps: {}", run.steps);
+		println!("Total time: {:.3} s", total_seconds);
+		if run.steps > 0 {
+			println!("Average step: {:.3} ms", total_seconds * 1000. / run.steps as f64);
+			println!("Slowest step: {:.3} ms", slowest_step_ms);
+			println!("Steps per second: {:.0}", run.steps as f64 / total_seconds.max(f64::EPSILON));
+		}
+
+		if let Some(profile) = self.engine.take_profile() {
+			println!("{}", profile);
+		}
+	}
+}
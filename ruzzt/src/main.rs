@@ -1,20 +1,46 @@
+#[cfg(feature = "compare")]
+mod compare;
+mod config;
+mod draw_batch;
+mod headless;
+mod screenshots;
+mod smoke_test;
 mod sound;
 
+use draw_batch::{DrawBatch, RunCache};
+
 use sdl2::image::{LoadTexture, INIT_PNG};
-use sdl2::event::Event;
+use sdl2::event::Event as SdlEvent;
 use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
-use sdl2::render::{WindowCanvas, Texture};
+use sdl2::render::{TextureCreator, WindowCanvas, Texture};
+use sdl2::video::WindowContext;
 use sdl2::audio::AudioSpecDesired;
 
 use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
-use num::FromPrimitive;
 
-use ruzzt_engine::board_message::BoardMessage;
-use ruzzt_engine::engine::RuzztEngine;
-use ruzzt_engine::console::{ConsoleState, SCREEN_HEIGHT, SCREEN_WIDTH};
-use zzt_file_format::dosstring::DosString;
+use ruzzt_engine::prelude::*;
+use ruzzt_engine::ambient_music::{parse_ambient_music, AMBIENT_MUSIC_FILE_EXTENSION};
+use ruzzt_engine::attract_mode::{AttractMode, ATTRACT_DEMO_FILE_EXTENSION, DEFAULT_ATTRACT_IDLE_STEPS};
+use ruzzt_engine::autosave::{Autosave, AutosaveOptions, AUTOSAVE_FILE_NAME};
+use ruzzt_engine::engine::ExtensionLevel;
+use ruzzt_engine::event::{EventContext, EVENT_HELP};
+use ruzzt_engine::board_simulator::{screen_pos_to_sim_pos, CompatibilityVersion};
+use ruzzt_engine::campaign::{CampaignCarryOver, CAMPAIGN_MANIFEST_FILE_NAME};
+use ruzzt_engine::compatibility::CompatibilityFixture;
+use ruzzt_engine::dos_files::{find_file, find_related_file, load_world_metadata};
+use ruzzt_engine::file_browser::FileBrowser;
+use ruzzt_engine::palette::Palette;
+use ruzzt_engine::speedrun::{parse_splits, SpeedrunTimer};
+use ruzzt_engine::weave::{WeaveFont, WeaveSidecars};
+use ruzzt_engine::high_scores::HighScoreList;
+use ruzzt_engine::lighting::LightingOptions;
+use ruzzt_engine::machine_speed::{MachineSpeed, NORMAL_CYCLES_PER_SECOND};
+use ruzzt_engine::savestate::decompress_save;
+use ruzzt_engine::screenshot::ScreenshotFormat;
+use ruzzt_engine::shareware::SharewareFlavour;
+use ruzzt_engine::zzt_config::ZztConfig;
 
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -23,36 +49,109 @@ fn get_ms_from_duration(duration: std::time::Duration) -> usize {
 	(duration.as_secs() * 1000) as usize + duration.subsec_millis() as usize
 }
 
-fn world_selection_info(world_name: &[u8]) -> &[u8] {
-	match world_name {
-		b"CAVES" => b"The Caves of ZZT",
-		b"CITY" => b"Underground City of ZZT",
-		b"DUNGEONS" => b"The Dungeons of ZZT",
-		b"TOUR" => b"Guided Tour ZZT's Other Worlds",
-		b"TOWN" => b"The Town of ZZT",
-		_ => b"",
+/// Parse a comma separated list of key colours, eg. `blue,red`, into the flags stored in
+/// `WorldHeader::player_keys`.
+fn parse_key_names(key_names: &str) -> Result<[bool; 7], String> {
+	let mut keys = [false; 7];
+	for key_name in key_names.split(',').map(|key_name| key_name.trim()).filter(|key_name| !key_name.is_empty()) {
+		let key_index = match key_name.to_ascii_lowercase().as_str() {
+			"blue" => 0,
+			"green" => 1,
+			"cyan" => 2,
+			"red" => 3,
+			"purple" => 4,
+			"yellow" => 5,
+			"white" => 6,
+			_ => return Err(format!("Unknown key colour: {}", key_name)),
+		};
+		keys[key_index] = true;
+	}
+	Ok(keys)
+}
+
+/// Read the world file at `path`. Returns an error describing why it couldn't be read.
+/// Get the digit typed by the given number pad `keycode`, if it is one.
+fn keypad_digit(keycode: Keycode) -> Option<u32> {
+	match keycode {
+		Keycode::Kp0 => Some(0),
+		Keycode::Kp1 => Some(1),
+		Keycode::Kp2 => Some(2),
+		Keycode::Kp3 => Some(3),
+		Keycode::Kp4 => Some(4),
+		Keycode::Kp5 => Some(5),
+		Keycode::Kp6 => Some(6),
+		Keycode::Kp7 => Some(7),
+		Keycode::Kp8 => Some(8),
+		Keycode::Kp9 => Some(9),
+		_ => None,
 	}
 }
 
-struct WorldSelectionState {
-	entries: Vec<std::path::PathBuf>,
+/// Read the world or saved game at `path`. Compressed savestates (see
+/// `savestate::compress_save`) can be read as saved games too.
+fn read_world_file(path: &Path) -> Result<zzt_file_format::World, String> {
+	let bytes = decompress_save(std::fs::read(path).map_err(|e| e.to_string())?)?;
+	zzt_file_format::World::parse_with_options(&mut std::io::Cursor::new(bytes), zzt_file_format::padding::ParseOptions::lenient())
 }
 
-enum CustomScrollState {
+enum CustomBrowserState {
 	None,
-	WorldSelection{world_selection_state: WorldSelectionState, play_immediately: bool},
+	WorldSelection{play_immediately: bool},
 }
 
 struct ZztConsole {
 	engine: RuzztEngine,
-	current_console_state: ConsoleState,
+	config: config::UserConfig,
 	current_run_time_ms: usize,
-	custom_scroll_state: CustomScrollState,
+	custom_browser_state: CustomBrowserState,
+	/// The directory that the world and saved game browsers start in. This is the directory the
+	/// last file was picked from.
+	browse_directory: std::path::PathBuf,
+	/// The file the speedrun splits were loaded from, if there are any.
+	splits_path: Option<std::path::PathBuf>,
+	/// The file the message timeline is written to when RUZZT exits, if it is being recorded.
+	message_timeline_path: Option<std::path::PathBuf>,
+	/// The palette picked by the user, which is used for worlds that don't come with their own.
+	base_palette: Palette,
+	/// The font that came with the current world, if it has one (see `ruzzt_engine::weave`).
+	world_font: Option<WeaveFont>,
+	/// True when `world_font` changed, so the font texture has to be made again.
+	font_changed: bool,
+	/// The character code being typed with Alt and the number pad, like DOS. It is typed when Alt
+	/// is released.
+	alt_char_code: Option<u32>,
+	/// When set, the window compares RUZZT against a recorded fixture instead of running the game
+	/// (see `compare`).
+	#[cfg(feature = "compare")]
+	comparison_runner: Option<ruzzt_engine::compatibility::ComparisonRunner>,
+	/// When set, the game is simulated without a window and the results are printed (see
+	/// `headless`).
+	headless_run: Option<headless::HeadlessRun>,
+	/// When set, a demo is replayed without a window and screenshots of it are written (see
+	/// `screenshots`).
+	screenshot_run: Option<screenshots::ScreenshotRun>,
+	/// When set, the boards of some worlds are run without a window to check they work (see
+	/// `smoke_test`).
+	smoke_test_run: Option<smoke_test::SmokeTestRun>,
+	/// The machine speed given on the command line, which is used instead of the one in the
+	/// metadata file of each world.
+	machine_speed_override: Option<MachineSpeed>,
+	/// The board RNG seed given on the command line, which is used instead of the one in the
+	/// metadata file of each world.
+	board_rng_seed_override: Option<u64>,
+	/// The number of steps the title screen has to be left alone before the demo next to the world
+	/// is played, or 0 to never play it.
+	attract_idle_steps: usize,
+	/// The sample rate to open the audio device with, from the config or the command line.
+	audio_sample_rate: i32,
+	/// The size of the audio device's buffer, from the config or the command line, or None to let
+	/// SDL pick it.
+	audio_buffer_samples: Option<u16>,
 }
 
 impl ZztConsole {
 	fn new() -> ZztConsole {
-		let command_arguments = clap::App::new("ruzzt")
+		let command_line = clap::App::new("ruzzt")
 			.about("A ZZT clone")
 			.arg(clap::Arg::with_name("WORLD_FILE")
 				.help("A ZZT world file to load on startup.")
@@ -62,133 +161,792 @@ impl ZztConsole {
 				.short("b")
 				.value_name("BOARD")
 				.help("Starts on the given board number"))
-			.get_matches();
-
+			.arg(clap::Arg::with_name("health")
+				.long("health")
+				.value_name("HEALTH")
+				.help("Starts the game with the given health"))
+			.arg(clap::Arg::with_name("ammo")
+				.long("ammo")
+				.value_name("AMMO")
+				.help("Starts the game with the given amount of ammo"))
+			.arg(clap::Arg::with_name("torches")
+				.long("torches")
+				.value_name("TORCHES")
+				.help("Starts the game with the given number of torches"))
+			.arg(clap::Arg::with_name("gems")
+				.long("gems")
+				.value_name("GEMS")
+				.help("Starts the game with the given number of gems"))
+			.arg(clap::Arg::with_name("score")
+				.long("score")
+				.value_name("SCORE")
+				.help("Starts the game with the given score"))
+			.arg(clap::Arg::with_name("keys")
+				.long("keys")
+				.value_name("KEYS")
+				.help("Starts the game with the given comma separated keys (blue, green, cyan, red, purple, yellow, white)"))
+			.arg(clap::Arg::with_name("flag")
+				.long("flag")
+				.value_name("FLAG")
+				.multiple(true)
+				.number_of_values(1)
+				.help("Starts the game with the given flag set (can be given more than once)"))
+			.arg(clap::Arg::with_name("palette")
+				.long("palette")
+				.value_name("PALETTE")
+				.possible_values(&["standard", "high-contrast", "colour-blind"])
+				.help("The colour palette to display the game with"))
+			.arg(clap::Arg::with_name("machine-speed")
+				.long("machine-speed")
+				.value_name("MACHINE")
+				.possible_values(&["modern", "386", "turbo-xt"])
+				.help("The DOS machine to model the game's speed on, so busy boards slow down like they did on it. This overrides the machine named in a world's metadata file"))
+			.arg(clap::Arg::with_name("board-seed")
+				.long("board-seed")
+				.value_name("SEED")
+				.help("Makes random things on each board happen the same way every time the board is entered, using randomness worked out from this number. This overrides the seed in a world's metadata file"))
+			.arg(clap::Arg::with_name("savestate-dir")
+				.long("savestate-dir")
+				.value_name("DIRECTORY")
+				.help("Keeps savestates in files in the given directory, so they are still there the next time RUZZT is run"))
+			.arg(clap::Arg::with_name("compress-savestates")
+				.long("compress-savestates")
+				.requires("savestate-dir")
+				.help("Compresses the savestate files, which keeps them small for big worlds. They are saved with the .SAVZ extension, and can only be read by RUZZT"))
+			.arg(clap::Arg::with_name("screenshot-dir")
+				.long("screenshot-dir")
+				.value_name("DIRECTORY")
+				.help("The directory to save screenshots taken with F12 in (defaults to the current directory)"))
+			.arg(clap::Arg::with_name("attract-delay")
+				.long("attract-delay")
+				.value_name("SECONDS")
+				.help("How long the title screen has to be left alone before the world's demo (a .DEM file next to it) is played, or 0 to never play it. Defaults to 30 seconds"))
+			.arg(clap::Arg::with_name("sample-rate")
+				.long("sample-rate")
+				.value_name("HZ")
+				.help("The sample rate to play sound at (44100 by default, or the one in the config)"))
+			.arg(clap::Arg::with_name("audio-buffer")
+				.long("audio-buffer")
+				.value_name("SAMPLES")
+				.help("The number of samples in the audio buffer, usually a power of 2 like 1024. Smaller buffers make the sound play sooner, and bigger ones stop it from breaking up on Bluetooth or busy systems (SDL picks the size by default)"))
+			.arg(clap::Arg::with_name("splits")
+				.long("splits")
+				.value_name("SPLITS_FILE")
+				.help("Time a speedrun with the splits listed in the given file. The times are saved next to it with the .lss extension"))
+			.arg(clap::Arg::with_name("unlimited-status-elements")
+				.long("unlimited-status-elements")
+				.help("Lets boards have any number of status elements, instead of the limit that DOS ZZT has"))
+			.arg(clap::Arg::with_name("compact-boards")
+				.long("compact-boards")
+				.help("Keeps the boards that aren't being played compressed, to use less memory in big worlds"))
+			.arg(clap::Arg::with_name("oop-instruction-limit")
+				.long("oop-instruction-limit")
+				.value_name("COUNT")
+				.help("The most OOP instructions an object can run in one step (64 by default, like DOS ZZT)"))
+			.arg(clap::Arg::with_name("sanitize-key-colours")
+				.long("sanitize-key-colours")
+				.help("Stops black keys and doors from changing the player's gem count like they do in DOS ZZT"))
+			.arg(clap::Arg::with_name("item-overflow")
+				.long("item-overflow")
+				.value_name("POLICY")
+				.possible_values(&["wrap", "saturate", "error"])
+				.help("What happens when a player item goes past 32767: wrap around like DOS ZZT (the default), saturate, or leave it and print an error"))
+			.arg(clap::Arg::with_name("zzt-version")
+				.long("zzt-version")
+				.value_name("VERSION")
+				.possible_values(&["3.0", "3.2"])
+				.help("The version of DOS ZZT to behave like for the #endgame command and shooting over water (3.2 by default). Use 3.0 for old worlds that have an :endgame label or rely on water stopping bullets, since ZZT 3.0 and 3.1 do both differently"))
+			.arg(clap::Arg::with_name("profile")
+				.long("profile")
+				.help("Prints how long each element type took to simulate when the game is closed"))
+			.arg(clap::Arg::with_name("message-timeline")
+				.long("message-timeline")
+				.value_name("JSON_FILE")
+				.help("Records the messages objects send each other, and writes the most recent ones to the given file as JSON when the game is closed"))
+			.arg(clap::Arg::with_name("extensions")
+				.long("extensions")
+				.help("Sets reserved flags (like RUZZT) so worlds can detect RUZZT and use its extensions with #if"))
+			.arg(clap::Arg::with_name("shareware")
+				.long("shareware")
+				.help("Shows the order form scroll like shareware DOS ZZT did, when the title screen is left alone and when quitting"))
+			.arg(clap::Arg::with_name("lighting")
+				.long("lighting")
+				.help("Lights dark boards with passages as well as the torch, in the passage's colour"))
+			.arg(clap::Arg::with_name("headless")
+				.long("headless")
+				.requires("WORLD_FILE")
+				.help("Simulates the world without a window or sound, then prints the final board hash and how long it took"))
+			.arg(clap::Arg::with_name("steps")
+				.long("steps")
+				.value_name("STEPS")
+				.requires("headless")
+				.help("The number of game cycles to simulate with --headless (defaults to 1000)"))
+			.arg(clap::Arg::with_name("seed")
+				.long("seed")
+				.value_name("SEED")
+				.requires("headless")
+				.help("The random number seed to use with --headless (defaults to 0)"))
+			.subcommand(clap::SubCommand::with_name("screenshots")
+				.about("Replays a demo (a compatibility fixture) and writes a PNG screenshot every few game cycles")
+				.arg(clap::Arg::with_name("DEMO_FILE")
+					.help("The demo to replay")
+					.required(true)
+					.index(1))
+				.arg(clap::Arg::with_name("world")
+					.long("world")
+					.value_name("WORLD_FILE")
+					.help("The world to replay the demo in (defaults to the world named in the demo)"))
+				.arg(clap::Arg::with_name("every")
+					.long("every")
+					.value_name("STEPS")
+					.help("The number of game cycles between screenshots (defaults to 10)"))
+				.arg(clap::Arg::with_name("output")
+					.long("output")
+					.short("o")
+					.value_name("DIRECTORY")
+					.help("The directory to write the screenshots to (defaults to screenshots)")))
+			.subcommand(clap::SubCommand::with_name("keys")
+				.about("Lists the keys used to play, and what they do"))
+			.subcommand(clap::SubCommand::with_name("smoke-test")
+				.about("Runs every board of the given worlds for a while with no input, and reports the boards that panic or log warnings")
+				.arg(clap::Arg::with_name("WORLD_FILES")
+					.help("The worlds to run")
+					.required(true)
+					.multiple(true)
+					.index(1))
+				.arg(clap::Arg::with_name("steps")
+					.long("steps")
+					.value_name("STEPS")
+					.help("The number of game cycles to run on each board (defaults to 100)"))
+				.arg(clap::Arg::with_name("seed")
+					.long("seed")
+					.value_name("SEED")
+					.help("The random number seed to use (defaults to 0)")));
+		#[cfg(feature = "compare")]
+		let command_line = command_line
+			.arg(clap::Arg::with_name("compare")
+				.long("compare")
+				.value_name("FIXTURE")
+				.help("Plays a compatibility fixture and shows RUZZT's board next to the recorded one, instead of running the game"));
+		let command_arguments = command_line.get_matches();
+
+		let config = config::UserConfig::load();
 		let mut console = ZztConsole {
 			engine: RuzztEngine::new(),
-			current_console_state: ConsoleState::new(),
+			audio_sample_rate: config.audio_sample_rate,
+			audio_buffer_samples: config.audio_buffer_samples,
+			config,
 			current_run_time_ms: 0,
-			custom_scroll_state: CustomScrollState::None,
+			custom_browser_state: CustomBrowserState::None,
+			browse_directory: std::path::PathBuf::from("."),
+			splits_path: None,
+			message_timeline_path: None,
+			base_palette: Palette::standard(),
+			world_font: None,
+			font_changed: false,
+			alt_char_code: None,
+			#[cfg(feature = "compare")]
+			comparison_runner: None,
+			headless_run: None,
+			screenshot_run: None,
+			smoke_test_run: None,
+			machine_speed_override: command_arguments.value_of("machine-speed").and_then(MachineSpeed::from_name),
+			board_rng_seed_override: None,
+			attract_idle_steps: DEFAULT_ATTRACT_IDLE_STEPS,
 		};
 
-		let board_index = if let Some(board_name) = command_arguments.value_of("board") {
-			if let Ok(board_index) = board_name.parse() {
-				Some(board_index)
+		#[cfg(feature = "compare")]
+		{
+			if let Some(fixture_path) = command_arguments.value_of("compare") {
+				match compare::load_comparison_runner(Path::new(fixture_path)) {
+					Ok(runner) => console.comparison_runner = Some(runner),
+					Err(err) => eprintln!("Can't load {}: {}", fixture_path, err),
+				}
+			}
+		}
+
+		console.engine.set_unlimited_status_elements(command_arguments.is_present("unlimited-status-elements"));
+		console.engine.set_compact_idle_boards(command_arguments.is_present("compact-boards"));
+		if let Some(limit) = command_arguments.value_of("oop-instruction-limit") {
+			match limit.parse() {
+				Ok(limit) => console.engine.set_oop_instruction_limit(limit),
+				Err(_) => {
+					eprintln!("The OOP instruction limit must be a positive integer");
+					std::process::exit(1);
+				}
+			}
+		}
+		if command_arguments.is_present("sanitize-key-colours") {
+			console.engine.set_key_colour_policy(KeyColourPolicy::Sanitized);
+		}
+		match command_arguments.value_of("item-overflow") {
+			Some("saturate") => console.engine.set_item_overflow_policy(ItemOverflowPolicy::Saturate),
+			Some("error") => console.engine.set_item_overflow_policy(ItemOverflowPolicy::Error),
+			_ => {}
+		}
+		if command_arguments.value_of("zzt-version") == Some("3.0") {
+			console.engine.set_compatibility_version(CompatibilityVersion::Zzt30);
+		}
+		console.engine.set_profiling_enabled(command_arguments.is_present("profile"));
+		if let Some(message_timeline_path) = command_arguments.value_of("message-timeline") {
+			console.engine.set_message_timeline_enabled(true);
+			console.message_timeline_path = Some(std::path::PathBuf::from(message_timeline_path));
+		}
+		if command_arguments.is_present("extensions") {
+			console.engine.set_extension_level(ExtensionLevel::Ruzzt);
+		}
+		if command_arguments.is_present("shareware") {
+			console.engine.shareware_flavour = Some(SharewareFlavour::new());
+		}
+		if command_arguments.is_present("lighting") {
+			console.engine.lighting_options = Some(LightingOptions {
+				passage_radius: 4,
+				coloured_passages: true,
+				.. LightingOptions::new()
+			});
+		}
+		console.engine.game_speed = console.config.game_speed;
+		console.engine.machine_speed = console.machine_speed_override.unwrap_or(MachineSpeed::Modern);
+		if let Some(seed) = command_arguments.value_of("board-seed") {
+			match seed.parse() {
+				Ok(seed) => {
+					console.board_rng_seed_override = Some(seed);
+					console.engine.board_rng_seed = Some(seed);
+				}
+				Err(_) => {
+					eprintln!("Invalid board seed: {}", seed);
+				}
+			}
+		}
+		if let Some(directory) = command_arguments.value_of("savestate-dir") {
+			match std::fs::create_dir_all(directory) {
+				Ok(()) => console.engine.savestate_directory = Some(std::path::PathBuf::from(directory)),
+				Err(err) => eprintln!("Couldn't create {:?}: {}", directory, err),
+			}
+		}
+		console.engine.compress_savestates = command_arguments.is_present("compress-savestates");
+		if let Some(directory) = command_arguments.value_of("screenshot-dir") {
+			match std::fs::create_dir_all(directory) {
+				Ok(()) => console.engine.screenshot_directory = std::path::PathBuf::from(directory),
+				Err(err) => eprintln!("Couldn't create {:?}: {}", directory, err),
+			}
+		}
+		if let Some(seconds) = command_arguments.value_of("attract-delay") {
+			match seconds.parse::<f64>() {
+				Ok(seconds) => {
+					console.attract_idle_steps = (seconds * NORMAL_CYCLES_PER_SECOND).round() as usize;
+				}
+				Err(_) => {
+					eprintln!("Invalid attract delay: {}", seconds);
+				}
+			}
+		}
+		if let Some(sample_rate) = command_arguments.value_of("sample-rate") {
+			match sample_rate.parse::<i32>() {
+				Ok(sample_rate) if sample_rate > 0 => console.audio_sample_rate = sample_rate,
+				_ => eprintln!("Invalid sample rate: {}", sample_rate),
+			}
+		}
+		if let Some(buffer_samples) = command_arguments.value_of("audio-buffer") {
+			match buffer_samples.parse::<u16>() {
+				Ok(buffer_samples) if buffer_samples > 0 => console.audio_buffer_samples = Some(buffer_samples),
+				_ => eprintln!("Invalid audio buffer size: {}", buffer_samples),
+			}
+		}
+		// Screenshots are drawn with the window's font instead of saved as ANSI art by the engine.
+		console.engine.screenshot_format = ScreenshotFormat::Png;
+		console.engine.key_repeat.repeat_delay = console.config.key_repeat_delay as usize;
+
+		let palette_name = command_arguments.value_of("palette").unwrap_or(&console.config.palette).to_string();
+		match palette_name.as_str() {
+			"high-contrast" => console.engine.display_options = DisplayOptions::high_contrast(),
+			"colour-blind" => console.engine.display_options = DisplayOptions::colour_blind(),
+			_ => {}
+		}
+		console.base_palette = console.engine.display_options.palette.clone();
+
+		// Worlds that come with a campaign manifest carry some of the player's state over when one
+		// opens another.
+		if let Some(manifest_path) = find_file(Path::new("."), CAMPAIGN_MANIFEST_FILE_NAME) {
+			match std::fs::read_to_string(&manifest_path).map_err(|err| err.to_string()).and_then(|text| CampaignCarryOver::parse(&text)) {
+				Ok(carry_over) => console.engine.campaign_carry_over = Some(carry_over),
+				Err(err) => eprintln!("{}: {}", manifest_path.display(), err),
+			}
+		}
+
+		if let Some(splits_path) = command_arguments.value_of("splits") {
+			match std::fs::read_to_string(splits_path).map_err(|err| err.to_string()).and_then(|text| parse_splits(&text)) {
+				Ok(splits) => {
+					console.engine.speedrun_timer = Some(SpeedrunTimer::new(splits));
+					console.splits_path = Some(std::path::PathBuf::from(splits_path));
+				}
+				Err(err) => {
+					eprintln!("Couldn't load splits from {}: {}", splits_path, err);
+				}
+			}
+		}
+
+		if command_arguments.is_present("headless") {
+			let steps = command_arguments.value_of("steps").unwrap_or("1000").parse();
+			let seed = command_arguments.value_of("seed").unwrap_or("0").parse();
+			match (steps, seed) {
+				(Ok(steps), Ok(seed)) => console.headless_run = Some(headless::HeadlessRun{steps, seed}),
+				_ => {
+					eprintln!("The steps and seed must be positive integers");
+					std::process::exit(1);
+				}
+			}
+		}
+
+		if let Some(screenshot_arguments) = command_arguments.subcommand_matches("screenshots") {
+			let every_steps = match screenshot_arguments.value_of("every").unwrap_or("10").parse() {
+				Ok(every_steps) if every_steps > 0 => every_steps,
+				_ => {
+					eprintln!("The number of game cycles between screenshots must be a positive integer");
+					std::process::exit(1);
+				}
+			};
+			console.screenshot_run = Some(screenshots::ScreenshotRun {
+				demo_path: std::path::PathBuf::from(screenshot_arguments.value_of("DEMO_FILE").unwrap()),
+				world_path: screenshot_arguments.value_of("world").map(std::path::PathBuf::from),
+				every_steps,
+				output_directory: std::path::PathBuf::from(screenshot_arguments.value_of("output").unwrap_or("screenshots")),
+			});
+			// The demo names the world to play, so nothing else is loaded.
+			return console;
+		}
+
+		if command_arguments.subcommand_matches("keys").is_some() {
+			for event_help in EVENT_HELP {
+				let context = match event_help.context {
+					EventContext::Game => " (in a game)",
+					EventContext::TitleScreen => " (in the title screen)",
+					EventContext::Anywhere => "",
+				};
+				println!("{:<12} {}: {}{}", event_help.default_key, event_help.name, event_help.description, context);
+			}
+			std::process::exit(0);
+		}
+
+		if let Some(smoke_test_arguments) = command_arguments.subcommand_matches("smoke-test") {
+			let steps = smoke_test_arguments.value_of("steps").unwrap_or("100").parse();
+			let seed = smoke_test_arguments.value_of("seed").unwrap_or("0").parse();
+			match (steps, seed) {
+				(Ok(steps_per_board), Ok(seed)) => {
+					console.smoke_test_run = Some(smoke_test::SmokeTestRun {
+						world_paths: smoke_test_arguments.values_of("WORLD_FILES").unwrap().map(std::path::PathBuf::from).collect(),
+						steps_per_board,
+						seed,
+					});
+				}
+				_ => {
+					eprintln!("The steps and seed must be positive integers");
+					std::process::exit(1);
+				}
+			}
+			return console;
+		}
+
+		let parse_number_arg = |arg_name: &str| -> Option<i16> {
+			let value = command_arguments.value_of(arg_name)?;
+			if let Ok(number) = value.parse() {
+				Some(number)
 			} else {
-				eprintln!("Board index must be an integer");
+				eprintln!("The {} must be an integer", arg_name);
 				None
 			}
-		} else {
-			None
 		};
 
-		if let Some(init_world_name) = command_arguments.value_of("WORLD_FILE") {
-			let mut file = std::fs::File::open(init_world_name).unwrap();
-			let world = zzt_file_format::World::parse(&mut file).unwrap();
-
-			console.engine.load_world(world, board_index);
+		let mut start_state = StartState::new();
+		start_state.board = parse_number_arg("board");
+		start_state.health = parse_number_arg("health");
+		start_state.ammo = parse_number_arg("ammo");
+		start_state.torches = parse_number_arg("torches");
+		start_state.gems = parse_number_arg("gems");
+		start_state.score = parse_number_arg("score");
+		if let Some(key_names) = command_arguments.value_of("keys") {
+			match parse_key_names(key_names) {
+				Ok(keys) => start_state.keys = Some(keys),
+				Err(err) => eprintln!("{}", err),
+			}
+		}
+		if let Some(flags) = command_arguments.values_of("flag") {
+			start_state.flags = flags.map(DosString::from_str).collect();
+		}
 
-			if board_index.is_some() {
-				console.engine.set_in_title_screen(false);
-				let mut board_messages = vec![];
-				console.engine.board_simulator.on_player_entered_board(&mut board_messages);
+		if let Some(init_world_name) = command_arguments.value_of("WORLD_FILE") {
+			let loaded = read_world_file(Path::new(init_world_name)).and_then(|world| {
+				// Practice mode starts playing straight away, skipping the title screen.
+				if start_state != StartState::new() {
+					console.engine.load_world_with_state(world, &start_state)
+				} else {
+					console.engine.load_world(world, None)
+				}
+			});
+			match loaded {
+				Ok(()) => {
+					console.load_related_files(Path::new(init_world_name));
+					if console.headless_run.is_none() {
+						console.remember_last_played_world(Path::new(init_world_name));
+					}
+				}
+				Err(error) => {
+					eprintln!("Can't load {}: {}", init_world_name, error);
+					if console.headless_run.is_some() {
+						std::process::exit(1);
+					}
+					console.engine.process_board_message(BoardMessage::WorldLoadFailed{
+						filename: DosString::from_str(init_world_name),
+						error,
+					});
+				}
+			}
+		} else if let Some(world_file_name) = console.zzt_config_world_file_name() {
+			// Like DOS ZZT, the world named in ZZT.CFG is loaded when no world is given.
+			let filename = DosString::from_str(&world_file_name);
+			if let Err(error) = console.open_world(&filename) {
+				console.engine.process_board_message(BoardMessage::WorldLoadFailed{filename, error});
 			}
 		} else {
-			console.open_world(&DosString::from_slice(b"TOWN.ZZT"));
+			let last_played_world = console.config.last_played_world.clone();
+			let last_world_path_opt = last_played_world.and_then(|file_path| {
+				let world = read_world_file(Path::new(&file_path)).ok()?;
+				console.engine.load_world(world, None).ok()?;
+				Some(file_path)
+			});
+			if let Some(file_path) = last_world_path_opt {
+				console.load_related_files(Path::new(&file_path));
+			} else {
+				let filename = DosString::from_slice(b"TOWN.ZZT");
+				if let Err(error) = console.open_world(&filename) {
+					console.engine.process_board_message(BoardMessage::WorldLoadFailed{filename, error});
+				}
+			}
+		}
+
+		if console.headless_run.is_none() {
+			console.start_autosave();
 		}
 
 		console
 	}
 
-	fn draw_screen(&mut self, canvas: &mut WindowCanvas, dosfont_tex: &mut Texture, redraw_all: bool) {
-		for y in 0 .. SCREEN_HEIGHT {
-			for x in 0 .. SCREEN_WIDTH {
-				let ref screen_char = self.engine.console_state.screen_chars[y][x];
-				let ref old_screen_char = self.current_console_state.screen_chars[y][x];
+	/// Start autosaving as often as the config says, and ask whether to resume the autosave left
+	/// behind if RUZZT didn't exit normally last time.
+	fn start_autosave(&mut self) {
+		if self.config.autosave_boards == 0 && self.config.autosave_minutes <= 0. {
+			return;
+		}
+		let directory = match config::UserConfig::autosave_directory() {
+			Some(directory) => directory,
+			None => return,
+		};
+		if let Err(err) = std::fs::create_dir_all(&directory) {
+			eprintln!("Couldn't create {:?}: {}", directory, err);
+			return;
+		}
 
-				let mut blinking = false;
+		self.engine.autosave = Some(Autosave::new(directory, AutosaveOptions {
+			boards_between_autosaves: self.config.autosave_boards,
+			minutes_between_autosaves: self.config.autosave_minutes,
+		}));
+		self.engine.offer_autosave_resume();
+	}
 
-				let mut back_num = screen_char.background as u8;
-				if back_num >= 8 {
-					back_num -= 8;
-					blinking = true;
-				}
+	/// Draw the characters on the screen that changed (or all of them if `redraw_all` is true) to
+	/// `screen_tex` if it is given, or straight to `canvas` otherwise.
+	fn draw_screen(&mut self, canvas: &mut WindowCanvas, dosfont_tex: &mut Texture, run_cache: &mut RunCache<WindowContext>, screen_tex: Option<&mut Texture>, redraw_all: bool) {
+		if redraw_all {
+			self.engine.console_state.mark_all_dirty();
+		}
 
-				if screen_char != old_screen_char || redraw_all || blinking {
-					let back_rgb = ruzzt_engine::console::ConsoleColour::from_u8(back_num).unwrap().to_rgb();
+		let mut batch = DrawBatch::new(dosfont_tex.query().height);
+		for (x, y, screen_char) in self.engine.console_state.take_dirty_cells() {
+			self.draw_char(&mut batch, x, y, screen_char);
+		}
+		match screen_tex {
+			Some(screen_tex) => {
+				canvas.with_texture_canvas(screen_tex, |screen_canvas| batch.draw(screen_canvas, dosfont_tex, run_cache)).expect("Render failed");
+			}
+			None => batch.draw(canvas, dosfont_tex, run_cache),
+		}
+	}
 
-					let fore_rgb = screen_char.foreground.to_rgb();
+	/// Add a character at `x`, `y` on the screen to `batch`, in the colours it should be shown in.
+	fn draw_char(&self, batch: &mut DrawBatch, x: usize, y: usize, screen_char: ConsoleChar) {
+		let colour = ruzzt_engine::console::Colour::from_console_colours(screen_char.foreground, screen_char.background);
+		let blinking = colour.blinking;
 
-					let char_rect = Rect::new(8 * (screen_char.char_code as i32), 0, 8, 14);
+		// Extended colours (see `ConsoleChar::extended_colours`) aren't changed by the palette.
+		let palette = &self.engine.display_options.palette;
+		let back_rgb = screen_char.extended_background_rgb().unwrap_or_else(|| palette.to_rgb(colour.background));
 
-					let dest_rect = Rect::new(8 * (x as i32), 14 * (y as i32), 8, 14);
+		let fore_rgb = screen_char.extended_foreground_rgb().unwrap_or_else(|| palette.to_rgb(colour.foreground));
 
-					// Draw the character background:
-					canvas.set_draw_color(sdl2::pixels::Color::RGB(back_rgb.0, back_rgb.1, back_rgb.2));
-					canvas.fill_rect(dest_rect).ok();
+		// Fonts can have a different character height to the screen mode (eg. fonts that come with a
+		// world, or the 8x14 font in 80x50 mode), so they're stretched.
+		let dest_char_height = self.engine.console_state.screen_mode().char_height() as u32;
+		let dest_rect = Rect::new(8 * (x as i32), (dest_char_height * y as u32) as i32, 8, dest_char_height);
 
-					if !blinking || self.current_run_time_ms % 450 < 225 {
-						// Draw the character foreground:
-						dosfont_tex.set_color_mod(fore_rgb.0, fore_rgb.1, fore_rgb.2);
-						canvas.copy(&dosfont_tex, Some(char_rect), Some(dest_rect)).expect("Render failed");
-					}
+		let foreground_visible = !blinking || self.engine.console_state.blink_visible();
+		batch.add_char(screen_char.char_code, dest_rect, back_rgb, if foreground_visible { Some(fore_rgb) } else { None });
+	}
 
-					self.current_console_state.screen_chars[y][x] = *screen_char;
-				}
-			}
+	fn open_world_browser(&mut self, browser_title: &[u8], file_extension: &str, play_immediately: bool) {
+		let file_browser = FileBrowser::new(DosString::from_slice(browser_title), &self.browse_directory, file_extension);
+		self.engine.open_file_browser(file_browser);
+		self.custom_browser_state = CustomBrowserState::WorldSelection{play_immediately};
+	}
+
+	/// Load the files that can come with the world at `world_path`, like its ambient music, high
+	/// scores, Weave ZZT palette and font, and title screen demo.
+	fn load_related_files(&mut self, world_path: &Path) {
+		self.load_ambient_music(world_path);
+		self.load_high_scores(world_path);
+		self.load_weave_files(world_path);
+		self.load_metadata_settings(world_path);
+		self.load_attract_demo(world_path);
+	}
+
+	/// Play the demo next to the world at `world_path` when the title screen is left alone, if it
+	/// has one.
+	fn load_attract_demo(&mut self, world_path: &Path) {
+		self.engine.attract_mode = None;
+		if self.attract_idle_steps == 0 {
+			return;
+		}
+		let demo_path = match find_related_file(world_path, ATTRACT_DEMO_FILE_EXTENSION) {
+			Some(demo_path) => demo_path,
+			None => return,
+		};
+		let demo = std::fs::read_to_string(&demo_path).map_err(|err| err.to_string()).and_then(|text| CompatibilityFixture::parse(&text));
+		match demo {
+			Ok(demo) => self.engine.attract_mode = Some(AttractMode::new(demo, self.attract_idle_steps)),
+			Err(err) => eprintln!("{}: {}", demo_path.display(), err),
 		}
 	}
 
-	fn open_world_selection_scroll(&mut self, scroll_title: &[u8], file_extension: &str, play_immediately: bool) {
-		let mut files = vec![];
-		let mut world_selection_state = WorldSelectionState{entries: vec![]};
-
-		// TODO: Sort this list.
-		if let Ok(read_dir) = std::fs::read_dir(".") {
-			for dir_file in read_dir {
-				if let Ok(dir_file_entry) = dir_file {
-					if let Ok(mut dir_file_entry_name) = dir_file_entry.file_name().into_string() {
-						dir_file_entry_name.make_ascii_uppercase();
-						if dir_file_entry_name.ends_with(file_extension) {
-							dir_file_entry_name.truncate(dir_file_entry_name.len() - file_extension.len());
-							let world_name = DosString::from_str(&dir_file_entry_name);
-							let mut scroll_line = world_name.clone();
-							while scroll_line.len() < 11 {
-								scroll_line += b" ";
-							}
-							scroll_line += world_selection_info(&world_name.data);
-							world_selection_state.entries.push(dir_file_entry.path());
-							files.push(scroll_line);
-						}
+	/// Model the game's speed on the machine named in the metadata file of the world at
+	/// `world_path`, and use the board RNG seed in it, unless they were given on the command line.
+	fn load_metadata_settings(&mut self, world_path: &Path) {
+		let metadata = load_world_metadata(world_path).unwrap_or_else(|err| {
+			eprintln!("{}", err);
+			None
+		}).unwrap_or_default();
+		if self.machine_speed_override.is_none() {
+			self.engine.machine_speed = match metadata.machine_speed {
+				Some(name) => MachineSpeed::from_name(&name).unwrap_or_else(|| {
+					eprintln!("Unknown machine speed in the world's metadata: {}", name);
+					MachineSpeed::Modern
+				}),
+				None => MachineSpeed::Modern,
+			};
+		}
+		if self.board_rng_seed_override.is_none() {
+			self.engine.board_rng_seed = metadata.board_rng_seed;
+		}
+	}
+
+	/// Load the high scores from the world's `.HI` file into the engine, if it has one.
+	fn load_high_scores(&mut self, world_path: &Path) {
+		match HighScoreList::load(world_path) {
+			Ok(Some(high_scores)) => self.engine.high_scores = high_scores,
+			Ok(None) => {}
+			Err(err) => eprintln!("{}", err),
+		}
+	}
+
+	/// Use the Weave ZZT palette and font that come with the world at `world_path`, or go back to the
+	/// usual ones if it doesn't have them.
+	fn load_weave_files(&mut self, world_path: &Path) {
+		let sidecars = WeaveSidecars::load(world_path).unwrap_or_else(|err| {
+			eprintln!("{}", err);
+			WeaveSidecars::default()
+		});
+		self.engine.display_options.palette = self.base_palette.clone();
+		sidecars.apply_display_options(&mut self.engine.display_options);
+		if self.world_font != sidecars.font {
+			self.world_font = sidecars.font;
+			self.font_changed = true;
+		}
+	}
+
+	/// Centre the screen in the window, for the current screen mode and `scale`.
+	fn fit_viewport(&self, canvas: &mut WindowCanvas, scale: u32) {
+		let (render_width, render_height) = self.engine.console_state.screen_mode().pixel_size();
+		let (window_width, window_height) = canvas.window().size();
+		let viewport_x = (window_width / scale) as i32 / 2 - render_width as i32 / 2;
+		let viewport_y = (window_height / scale) as i32 / 2 - render_height as i32 / 2;
+		canvas.set_viewport(Rect::new(viewport_x, viewport_y, render_width as u32, render_height as u32));
+	}
+
+	/// Make a texture the size of the current screen mode, for the screen to be drawn to when the
+	/// window is presented every frame (see `run`).
+	fn create_screen_texture<'a, T>(&self, texture_creator: &'a TextureCreator<T>) -> Texture<'a> {
+		let (render_width, render_height) = self.engine.console_state.screen_mode().pixel_size();
+		texture_creator.create_texture_target(texture_creator.default_pixel_format(), render_width as u32, render_height as u32).unwrap()
+	}
+
+	/// Make the texture to draw characters from, in the same layout as `res/dosfont.png`: every
+	/// character side by side in one row.
+	fn create_font_texture<'a, T>(&self, texture_creator: &'a TextureCreator<T>) -> Texture<'a> {
+		let font = match self.world_font {
+			Some(ref font) => font,
+			None => {
+				let mode_font = match self.engine.console_state.screen_mode() {
+					ScreenMode::Text80x25 => self.config.font.clone(),
+					ScreenMode::Text80x50 => self.config.font_8x8.clone(),
+				};
+				let dosfont_file = mode_font
+					.unwrap_or_else(|| Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/res/dosfont.png")).to_path_buf());
+				return texture_creator.load_texture(dosfont_file).unwrap();
+			}
+		};
+
+		let width = 8 * 256;
+		let height = font.char_height;
+		// White pixels where the character is drawn and transparent pixels elsewhere, so the colour
+		// mod gives the foreground colour.
+		let mut pixels = vec![0u8; width * height * 4];
+		for char_code in 0 ..= 255u8 {
+			for y in 0 .. height {
+				for x in 0 .. 8 {
+					if font.pixel_is_set(char_code, x, y) {
+						let offset = (y * width + char_code as usize * 8 + x) * 4;
+						pixels[offset .. offset + 4].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
 					}
 				}
 			}
 		}
-		files.push(DosString::from_slice(b"Exit"));
-		self.engine.open_scroll(DosString::from_slice(scroll_title), files);
-		self.custom_scroll_state = CustomScrollState::WorldSelection{world_selection_state, play_immediately};
+
+		let mut texture = texture_creator.create_texture_static(sdl2::pixels::PixelFormatEnum::ABGR8888, width as u32, height as u32).unwrap();
+		texture.update(None, &pixels, width * 4).unwrap();
+		texture.set_blend_mode(sdl2::render::BlendMode::Blend);
+		texture
 	}
 
-	pub fn open_world(&mut self, filename: &DosString) {
-		let filename_str = filename.to_string(false);
-		if let Ok(read_dir) = std::fs::read_dir(".") {
-			for dir_file in read_dir {
-				if let Ok(dir_file_entry) = dir_file {
-					if let Ok(mut dir_file_entry_name) = dir_file_entry.file_name().into_string() {
-						dir_file_entry_name.make_ascii_uppercase();
-						if dir_file_entry_name == filename_str {
-							let mut file = std::fs::File::open(dir_file_entry.path()).unwrap();
-							let world = zzt_file_format::World::parse(&mut file).unwrap();
-							self.engine.load_world(world, None);
-							break;
-						}
-					}
-				}
+	/// Load the ambient music for the world at `world_path` into the engine, if there is an ambient
+	/// music file next to it (see `ruzzt_engine::ambient_music`).
+	fn load_ambient_music(&mut self, world_path: &Path) {
+		let music_path = match find_related_file(world_path, AMBIENT_MUSIC_FILE_EXTENSION) {
+			Some(music_path) => music_path,
+			None => return,
+		};
+		if let Ok(text) = std::fs::read_to_string(&music_path) {
+			match parse_ambient_music(&text) {
+				Ok(board_music) => self.engine.board_ambient_music = board_music,
+				Err(err) => eprintln!("{}: {}", music_path.display(), err),
+			}
+		}
+	}
+
+	/// Get the file name of the world named in the `ZZT.CFG` file in the current directory, if there
+	/// is one.
+	fn zzt_config_world_file_name(&self) -> Option<String> {
+		let zzt_config = ZztConfig::load(Path::new(".")).unwrap_or_else(|err| {
+			eprintln!("{}", err);
+			None
+		});
+		zzt_config.and_then(|zzt_config| zzt_config.world_file_name())
+	}
+
+	/// Remember that the world at `file_path` was the last one played, so it is opened next time.
+	fn remember_last_played_world(&mut self, file_path: &Path) {
+		self.config.last_played_world = Some(file_path.to_string_lossy().into_owned());
+		self.config.save_or_print_error();
+	}
+
+	/// Open a scroll showing the current settings and where they can be changed.
+	fn open_settings_scroll(&mut self) {
+		let mut content_lines = vec![
+			DosString::from_str(&format!("Palette: {}", self.config.palette)),
+			DosString::from_str(&format!("Sound: {}", if self.config.sound_enabled { "on" } else { "off" })),
+			DosString::from_str(&format!("Key bindings: {}", self.config.key_bindings.len())),
+			DosString::from_str(&format!("Game speed: {}", self.config.game_speed + 1)),
+			DosString::from_str(&format!("Key repeat delay: {}", self.config.key_repeat_delay)),
+			DosString::new(),
+			DosString::from_slice(b"Settings can be changed in:"),
+		];
+		if let Some(config_path) = config::UserConfig::config_path() {
+			content_lines.push(DosString::from_str(&config_path.to_string_lossy()));
+		}
+		self.engine.open_scroll(DosString::from_slice(b"Settings"), content_lines);
+	}
+
+	/// Update the config's record of the current world when the game ends.
+	fn record_end_of_game(&mut self) {
+		let world_header = &self.engine.board_simulator.world_header;
+		let world_name = world_header.world_name.to_string(false);
+		let record = self.config.world_record_mut(&world_name);
+		record.high_score = record.high_score.max(world_header.player_score);
+		for flag_name in &world_header.flag_names {
+			if flag_name.len() > 0 {
+				record.completed_flags.insert(flag_name.to_string(false));
 			}
 		}
+		self.config.save_or_print_error();
+	}
+
+	/// Save the speedrun splits in the LiveSplit format, next to the file they were loaded from.
+	fn export_splits(&self) {
+		if let (Some(splits_path), Some(speedrun_timer)) = (&self.splits_path, &self.engine.speedrun_timer) {
+			let world_name = self.engine.board_simulator.world_header.world_name.to_string(false);
+			let livesplit_data = speedrun_timer.to_livesplit("ZZT", &world_name);
+			let output_path = splits_path.with_extension("lss");
+			if let Err(err) = std::fs::write(&output_path, livesplit_data) {
+				eprintln!("Couldn't write {:?}: {}", output_path, err);
+			}
+		}
+	}
+
+	/// Get the key that a press of `keycode` should act as, according to the key bindings in the
+	/// config.
+	fn bound_keycode(&self, keycode: Keycode) -> Keycode {
+		let key_name = keycode.name();
+		Keycode::from_name(self.config.bound_key_name(&key_name)).unwrap_or(keycode)
+	}
+
+	/// Load the world file in the current directory with the given `filename` (see
+	/// `dos_files::find_file`). Returns an error describing why the world couldn't be loaded.
+	pub fn open_world(&mut self, filename: &DosString) -> Result<(), String> {
+		let file_path = find_file(Path::new("."), &filename.to_string(false)).ok_or_else(|| "File not found".to_string())?;
+		let mut world = read_world_file(&file_path)?;
+		self.engine.apply_campaign_carry_over(&mut world);
+		self.engine.load_world(world, None)?;
+		self.load_related_files(&file_path);
+		self.remember_last_played_world(&file_path);
+		Ok(())
 	}
 
 	pub fn run(&mut self) {
+		#[cfg(feature = "compare")]
+		{
+			if let Some(runner) = self.comparison_runner.take() {
+				self.run_comparison(runner);
+				return;
+			}
+		}
+
+		if let Some(run) = self.headless_run.take() {
+			self.run_headless(run);
+			return;
+		}
+
+		if let Some(run) = self.smoke_test_run.take() {
+			if !self.run_smoke_test(run) {
+				std::process::exit(1);
+			}
+			return;
+		}
+
+		if let Some(run) = self.screenshot_run.take() {
+			if let Err(err) = self.run_screenshots(run) {
+				eprintln!("{}", err);
+				std::process::exit(1);
+			}
+			return;
+		}
+
 		println!("");
 		println!("  Corroded version -- Thank you for playing RUZZT.");
 		let scale = 2;
@@ -202,10 +960,10 @@ impl ZztConsole {
 		let audio_subsystem = sdl_context.audio().unwrap();
 
 		let desired_spec = AudioSpecDesired {
-			freq: Some(44100),
+			freq: Some(self.audio_sample_rate),
 			channels: Some(1),
-			// Use the default default sample count.
-			samples: None,
+			// SDL picks the buffer size when it isn't set.
+			samples: self.audio_buffer_samples,
 		};
 
 		let mut audio_device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
@@ -219,69 +977,160 @@ impl ZztConsole {
 		// Init video.
 		//
 
-		let render_width = 640;
-		let render_height = 350;
+		let (render_width, render_height) = self.engine.console_state.screen_mode().pixel_size();
+		let (render_width, render_height) = (render_width as u32, render_height as u32);
 
 		let sdl_video = sdl_context.video().unwrap();
 		let _sdl_image = sdl2::image::init(INIT_PNG).unwrap();
-		let window = sdl_video.window("RUZZT", render_width * scale, render_height * scale)
-			.position_centered()
-			//.fullscreen_desktop()
-			.build()
-			.unwrap();
-
-		let (window_width, window_height) = window.size();
+		let make_window = || {
+			sdl_video.window("RUZZT", render_width * scale, render_height * scale)
+				.position_centered()
+				//.fullscreen_desktop()
+				.build()
+				.unwrap()
+		};
 
-		let mut canvas = window.into_canvas().software().build().unwrap();
+		// The software renderer ignores `present_vsync`, so an accelerated renderer is tried first.
+		// Making the canvas uses up the window, so the software renderer needs a new one.
+		let mut canvas = match make_window().into_canvas().accelerated().present_vsync().target_texture().build() {
+			Ok(canvas) => canvas,
+			Err(err) => {
+				eprintln!("Couldn't make an accelerated renderer, so the software renderer is used: {}", err);
+				make_window().into_canvas().software().build().unwrap()
+			}
+		};
+		let vsync = canvas.info().flags & (sdl2::sys::SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32) != 0;
 		let texture_creator = canvas.texture_creator();
 
-		let dosfont_file = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/res/dosfont.png"));
-		let mut dosfont_tex = texture_creator.load_texture(dosfont_file).unwrap();
+		let mut dosfont_tex = self.create_font_texture(&texture_creator);
+		let mut run_cache = RunCache::new(&texture_creator);
+		self.font_changed = false;
+
+		// With vsync, the window is presented every frame, and what was drawn on it isn't kept after
+		// it is presented. So the screen is drawn to this texture instead, and the whole texture is
+		// copied to the window each frame.
+		let mut screen_tex = if vsync { Some(self.create_screen_texture(&texture_creator)) } else { None };
 
 		let mut running = true;
 
 		canvas.set_scale(scale as f32, scale as f32).ok();
-		canvas.set_viewport(Rect::new(((window_width / scale) as i32 / 2 - render_width as i32 / 2) as i32, ((window_height / scale) as i32 / 2 - render_height as i32 / 2) as i32, render_width, render_height));
-
-		sdl_context.mouse().show_cursor(false);
+		self.fit_viewport(&mut canvas, scale);
 
 		let start_time_ms = get_ms_from_duration(SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
 		let mut last_time_ms = start_time_ms;
 
-		// Rough calculation: 161 cycles happens in about 17.3 seconds:
-		// 0.10745341614906832 seconds per cycle.
-		// 9.306358381502891 Hz
-		// Round that to 9.3 Hz
-		let rate_hz: f64 = 9.3;
+		// The amount of real time that hasn't been simulated yet. The simulation always advances in
+		// whole steps, so rendering as often as the display allows doesn't change the game speed.
+		let mut sim_accumulator_ms: f64 = 0.;
+
+		// The event to pass to the next simulation step. This is kept between frames, because a
+		// frame doesn't always have a simulation step in it.
+		let mut engine_event = Event::None;
 
-		self.draw_screen(&mut canvas, &mut dosfont_tex, true);
+		// Used to notice when the game ends, so the world's record in the config can be updated.
+		let mut was_end_of_game = self.engine.is_end_of_game();
+		// Used to notice when the last speedrun split is reached, so the splits can be saved.
+		let mut was_speedrun_finished = false;
+		// The window title is only set when it changes (see `RuzztEngine::window_title`).
+		let mut window_title = String::new();
+
+		self.draw_screen(&mut canvas, &mut dosfont_tex, &mut run_cache, screen_tex.as_mut(), true);
 
 		while running {
+			if self.font_changed {
+				dosfont_tex = self.create_font_texture(&texture_creator);
+				run_cache.clear();
+				// The screen mode could have changed too.
+				if vsync {
+					screen_tex = Some(self.create_screen_texture(&texture_creator));
+				}
+				self.font_changed = false;
+				self.draw_screen(&mut canvas, &mut dosfont_tex, &mut run_cache, screen_tex.as_mut(), true);
+			}
+
 			let in_typing_mode = self.engine.in_typing_mode();
-			let mut engine_event = ruzzt_engine::event::Event::None;
-			let mut engine_typing_event = ruzzt_engine::event::TypingEvent::None;
+			let mut engine_typing_event = TypingEvent::None;
 
 			for event in sdl_context.event_pump().unwrap().poll_iter() {
 				match event {
-					Event::Quit{..} => {
+					SdlEvent::Quit{..} => {
 						running = false;
 					}
-					Event::Window{..} => {
-						self.draw_screen(&mut canvas, &mut dosfont_tex, true);
+					SdlEvent::Window{win_event: sdl2::event::WindowEvent::FocusLost, ..} => {
+						self.engine.key_repeat.release_all();
 					}
-					Event::KeyDown {keycode: keycode_opt, keymod, ..} => {
+					SdlEvent::Window{..} => {
+						self.draw_screen(&mut canvas, &mut dosfont_tex, &mut run_cache, screen_tex.as_mut(), true);
+					}
+					SdlEvent::KeyUp {keycode: Some(keycode), ..} => {
+						if keycode == Keycode::LAlt || keycode == Keycode::RAlt {
+							if let Some(alt_char_code) = self.alt_char_code.take() {
+								if in_typing_mode {
+									engine_typing_event = TypingEvent::Char(alt_char_code as u8);
+								}
+							}
+						}
+
+						let released_event = match self.bound_keycode(keycode) {
+							Keycode::Left => Event::Left,
+							Keycode::Right => Event::Right,
+							Keycode::Up => Event::Up,
+							Keycode::Down => Event::Down,
+							Keycode::Space => Event::ShootFlow,
+							_ => Event::None,
+						};
+						self.engine.key_repeat.release(released_event);
+					}
+					SdlEvent::MouseButtonDown {mouse_btn, x, y, ..} => {
+						// Convert the window position into a pixel position on the unscaled screen.
+						let viewport = canvas.viewport();
+						let pixel_x = x / scale as i32 - viewport.x();
+						let pixel_y = y / scale as i32 - viewport.y();
+
+						let sim_pos = self.engine.console_state.screen_mode().pixel_to_screen_pos(pixel_x, pixel_y)
+							.and_then(|(screen_x, screen_y)| screen_pos_to_sim_pos(screen_x, screen_y));
+
+						if let Some(pos) = sim_pos {
+							let shoot = match mouse_btn {
+								sdl2::mouse::MouseButton::Left => Some(false),
+								sdl2::mouse::MouseButton::Right => Some(true),
+								_ => None,
+							};
+							if let Some(shoot) = shoot {
+								engine_event = Event::Click{pos, shoot};
+							}
+						}
+					}
+					SdlEvent::KeyDown {keycode: keycode_opt, keymod, repeat, ..} => {
 						if let Some(keycode) = keycode_opt {
 							match keycode {
 								Keycode::F1 => {
 									running = false;
 								}
+								Keycode::F4 => {
+									let screen_mode = self.engine.console_state.screen_mode().toggled();
+									self.engine.set_screen_mode(screen_mode);
+									let (render_width, render_height) = screen_mode.pixel_size();
+									canvas.window_mut().set_size(render_width as u32 * scale, render_height as u32 * scale).ok();
+									self.fit_viewport(&mut canvas, scale);
+									// The font for the new mode is loaded and the screen is redrawn on the
+									// next frame.
+									self.font_changed = true;
+								}
+								Keycode::F12 => {
+									engine_event = Event::Screenshot;
+								}
 								_ => {}
 							}
 
 							let shift_held = keymod.contains(sdl2::keyboard::LSHIFTMOD);
 
+							let alt_held = keymod.intersects(sdl2::keyboard::LALTMOD | sdl2::keyboard::RALTMOD);
+
 							if in_typing_mode {
-								if keycode as i32 >= 0x20 && keycode as i32 <= 0x7e {
+								if let (true, Some(digit)) = (alt_held, keypad_digit(keycode)) {
+									self.alt_char_code = Some((self.alt_char_code.unwrap_or(0) * 10 + digit) % 256);
+								} else if keycode as i32 >= 0x20 && keycode as i32 <= 0x7e {
 									let mut char_code = keycode as u8;
 									if shift_held {
 										// Very friendly for non-US keyboards:
@@ -298,95 +1147,173 @@ impl ZztConsole {
 											}
 										}
 									}
-									engine_typing_event = ruzzt_engine::event::TypingEvent::Char(char_code);
+									engine_typing_event = TypingEvent::Char(char_code);
 								} else {
 									match keycode {
 										Keycode::Escape => {
-											engine_typing_event = ruzzt_engine::event::TypingEvent::Escape;
+											engine_typing_event = TypingEvent::Escape;
 										}
 										Keycode::Return => {
-											engine_typing_event = ruzzt_engine::event::TypingEvent::Enter;
+											engine_typing_event = TypingEvent::Enter;
+										}
+										Keycode::Backspace => {
+											engine_typing_event = TypingEvent::Backspace;
+										}
+										Keycode::Left => {
+											engine_typing_event = TypingEvent::Left;
+										}
+										Keycode::Right => {
+											engine_typing_event = TypingEvent::Right;
+										}
+										Keycode::Up => {
+											engine_typing_event = TypingEvent::Up;
+										}
+										Keycode::Down => {
+											engine_typing_event = TypingEvent::Down;
 										}
-										Keycode::Left | Keycode::Backspace => {
-											engine_typing_event = ruzzt_engine::event::TypingEvent::Backspace;
+										Keycode::PageUp => {
+											engine_typing_event = TypingEvent::PageUp;
+										}
+										Keycode::PageDown => {
+											engine_typing_event = TypingEvent::PageDown;
+										}
+										Keycode::Home => {
+											engine_typing_event = TypingEvent::Home;
+										}
+										Keycode::End => {
+											engine_typing_event = TypingEvent::End;
+										}
+										Keycode::Delete => {
+											engine_typing_event = TypingEvent::Delete;
+										}
+										Keycode::F10 => {
+											engine_typing_event = TypingEvent::OpenCharPicker;
 										}
 										_ => {}
 									}
 								}
 							} else {
-								match keycode {
+								match self.bound_keycode(keycode) {
 									Keycode::Escape => {
-										engine_event = ruzzt_engine::event::Event::Escape;
+										engine_event = Event::Escape;
 									}
 									Keycode::Left => {
 										engine_event = if shift_held {
-											ruzzt_engine::event::Event::ShootLeft
+											Event::ShootLeft
 										} else {
-											ruzzt_engine::event::Event::Left
+											Event::Left
 										}
 									}
 									Keycode::Right => {
 										engine_event = if shift_held {
-											ruzzt_engine::event::Event::ShootRight
+											Event::ShootRight
 										} else {
-											ruzzt_engine::event::Event::Right
+											Event::Right
 										}
 									}
 									Keycode::Up => {
 										engine_event = if shift_held {
-											ruzzt_engine::event::Event::ShootUp
+											Event::ShootUp
 										} else {
-											ruzzt_engine::event::Event::Up
+											Event::Up
 										}
 									}
 									Keycode::Down => {
 										engine_event = if shift_held {
-											ruzzt_engine::event::Event::ShootDown
+											Event::ShootDown
 										} else {
-											ruzzt_engine::event::Event::Down
+											Event::Down
 										}
 									}
+									Keycode::L => {
+										engine_event = Event::OpenMessageLog;
+									}
+									Keycode::M => {
+										engine_event = Event::OpenWorldMap;
+									}
 									Keycode::P => {
 										if self.engine.in_title_screen {
-											engine_event = ruzzt_engine::event::Event::PlayGame;
+											engine_event = Event::PlayGame;
 										} else {
-											engine_event = ruzzt_engine::event::Event::PauseGame;
+											engine_event = Event::PauseGame;
 										}
 									}
 									Keycode::Q => {
-										engine_event = ruzzt_engine::event::Event::Quit;
+										engine_event = Event::Quit;
 									}
 									Keycode::PageUp => {
-										engine_event = ruzzt_engine::event::Event::PageUp;
+										engine_event = Event::PageUp;
 									}
 									Keycode::PageDown => {
-										engine_event = ruzzt_engine::event::Event::PageDown;
+										engine_event = Event::PageDown;
 									}
 									Keycode::R => {
-										engine_event = ruzzt_engine::event::Event::RestoreGame;
+										engine_event = Event::RestoreGame;
 									}
 									Keycode::Return => {
-										engine_event = ruzzt_engine::event::Event::Enter;
+										engine_event = Event::Enter;
 									}
 									Keycode::Space => {
-										engine_event = ruzzt_engine::event::Event::ShootFlow;
+										engine_event = Event::ShootFlow;
+									}
+									Keycode::A => {
+										engine_event = Event::OpenAbout;
+									}
+									Keycode::E => {
+										engine_event = Event::OpenEditor;
+									}
+									Keycode::K => {
+										engine_event = Event::ChangeKeyboardConfig;
+									}
+									Keycode::H => {
+										engine_event = Event::OpenHighScores;
 									}
 									Keycode::S => {
-										engine_event = ruzzt_engine::event::Event::SaveGame;
+										if self.engine.in_title_screen {
+											engine_event = Event::ChangeGameSpeed;
+										} else {
+											engine_event = Event::SaveGame;
+										}
 									}
 									Keycode::Slash => {
 										if shift_held {
-											engine_event = ruzzt_engine::event::Event::Debug;
+											engine_event = Event::Debug;
 										}
 									}
 									Keycode::T => {
-										engine_event = ruzzt_engine::event::Event::LightTorch;
+										engine_event = Event::LightTorch;
 									}
 									Keycode::W => {
-										engine_event = ruzzt_engine::event::Event::OpenWorldSelection;
+										engine_event = Event::OpenWorldSelection;
+									}
+									Keycode::F3 => {
+										engine_event = Event::ToggleExecutionOrder;
+									}
+									Keycode::F6 => {
+										engine_event = Event::ToggleShootLock;
+									}
+									Keycode::F5 => {
+										engine_event = Event::QuickSave;
+									}
+									Keycode::F9 => {
+										engine_event = Event::QuickLoad;
+									}
+									slot_keycode @ (Keycode::Num0 | Keycode::Num1 | Keycode::Num2 | Keycode::Num3 | Keycode::Num4 |
+									Keycode::Num5 | Keycode::Num6 | Keycode::Num7 | Keycode::Num8 | Keycode::Num9) => {
+										let slot = slot_keycode as i32 - Keycode::Num0 as i32;
+										engine_event = Event::SelectSavestateSlot(slot as u8);
 									}
 									_ => {}
 								}
+
+								// Held movement keys are repeated by the engine at a fixed number of
+								// steps, so the operating system's key repeats are ignored.
+								if engine_event.is_repeatable() {
+									if !repeat {
+										self.engine.key_repeat.press(engine_event);
+									}
+									engine_event = Event::None;
+								}
 							}
 						}
 					}
@@ -394,61 +1321,132 @@ impl ZztConsole {
 				}
 			}
 
+			let current_time_ms = get_ms_from_duration(SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
+			// Don't try to catch up on more than a quarter of a second at once, so a long hitch (or
+			// the machine going to sleep) doesn't make the game run a burst of steps afterwards.
+			let elapsed_ms = (current_time_ms - last_time_ms) as f64;
+			sim_accumulator_ms += elapsed_ms.min(250.);
+			last_time_ms = current_time_ms;
+			// The engine decides which sound is heard, so the sound player is only told when it changes.
+			let mut sound_changed = self.engine.advance_sound(elapsed_ms);
+			self.current_run_time_ms = current_time_ms - start_time_ms;
+			self.engine.console_state.set_blink_time_ms(self.current_run_time_ms);
+
+			let step_length_ms = if self.engine.should_simulate_fast() {
+				10. / 3.
+			} else {
+				self.engine.step_length_ms()
+			};
+
 			let mut board_messages = if in_typing_mode {
+				// Typing doesn't wait for the next step, and time doesn't pass while typing.
+				sim_accumulator_ms = 0.;
 				self.engine.process_typing(engine_typing_event)
 			} else {
 				let mut board_messages = vec![];
-				for _ in 0 ..= if self.engine.should_simulate_fast() { 2 } else { 0 } {
+				let mut simulated = false;
+				while sim_accumulator_ms >= step_length_ms && !self.engine.in_typing_mode() {
+					sim_accumulator_ms -= step_length_ms;
 					let global_time_passed_seconds: f64 = self.current_run_time_ms as f64 / 1000.;
+					let repeat_event = self.engine.key_repeat.next_event();
+					if engine_event == Event::None {
+						engine_event = repeat_event;
+					}
 					board_messages.extend(self.engine.step(engine_event, global_time_passed_seconds));
-					engine_event = ruzzt_engine::event::Event::None;
+					engine_event = Event::None;
+					simulated = true;
+				}
+				if simulated {
+					self.engine.update_screen();
 				}
-				self.engine.update_screen();
 				board_messages
 			};
 
-			let mut new_sounds_list = vec![];
-			let mut should_clear_sound = false;
-
-			let applied_board_message = !board_messages.is_empty();
+			let board_messages_were_empty = board_messages.is_empty();
 
 			while !board_messages.is_empty() {
 				let processing_board_messages = std::mem::replace(&mut board_messages, vec![]);
 				for board_message in processing_board_messages {
 					match board_message {
-						BoardMessage::PlaySoundArray(ref sound_array, priority) => {
-							new_sounds_list.push((sound_array.clone(), priority));
-						}
-						BoardMessage::ClearPlayingSound => {
-							should_clear_sound = true;
+						BoardMessage::RestoreSoundQueue(..) => {
+							sound_changed = true;
 						}
 						BoardMessage::Quit => {
 							running = false;
 						}
 						BoardMessage::OpenWorldSelection => {
-							self.open_world_selection_scroll(b"RUZZT Worlds", ".ZZT", false);
+							self.open_world_browser(b"RUZZT Worlds", "ZZT", false);
 						}
 						BoardMessage::OpenSaveSelection => {
-							self.open_world_selection_scroll(b"Saved Games", ".SAV", true);
+							self.open_world_browser(b"Saved Games", "SAV", true);
 						}
-						BoardMessage::EnterPressedInScroll{line_index} => {
-							match self.custom_scroll_state {
-								CustomScrollState::None => {}
-								CustomScrollState::WorldSelection{ref world_selection_state, play_immediately} => {
-									if let Some(file_path) = world_selection_state.entries.get(line_index) {
-										let mut file = std::fs::File::open(file_path).unwrap();
-										let world = zzt_file_format::World::parse(&mut file).unwrap();
-										self.engine.load_world(world, None);
+						BoardMessage::FileBrowserFileSelected{ref file_path} => {
+							// The browser state isn't cleared when the world browser closes, because it
+							// closes and opens again whenever the file browser changes directory.
+							let custom_browser_state = std::mem::replace(&mut self.custom_browser_state, CustomBrowserState::None);
+							match custom_browser_state {
+								CustomBrowserState::None => {}
+								CustomBrowserState::WorldSelection{play_immediately} => {
+									if let Some(directory) = file_path.parent() {
+										self.browse_directory = directory.to_path_buf();
+									}
+									let loaded = read_world_file(file_path).and_then(|world| {
 										if play_immediately {
-											self.engine.set_in_title_screen(false);
+											self.engine.restore_saved_game(world)
+										} else {
+											self.engine.load_world(world, None)
+										}
+									});
+									match loaded {
+										Ok(()) => {
+											// Saved games aren't remembered, only worlds.
+											if !play_immediately {
+												self.remember_last_played_world(file_path);
+											}
+											self.load_related_files(file_path);
+										}
+										Err(error) => {
+											let filename = file_path.file_name().map(|name| name.to_string_lossy().to_uppercase()).unwrap_or_default();
+											board_messages.push(BoardMessage::WorldLoadFailed{filename: DosString::from_str(&filename), error});
 										}
 									}
 								}
 							}
-							self.custom_scroll_state = CustomScrollState::None;
+						}
+						BoardMessage::ResumeAutosave => {
+							if let Some(file_path) = self.engine.autosave.as_ref().and_then(|autosave| Autosave::find(&autosave.directory)) {
+								if let Err(error) = read_world_file(&file_path).and_then(|world| self.engine.restore_saved_game(world)) {
+									board_messages.push(BoardMessage::WorldLoadFailed{filename: DosString::from_str(AUTOSAVE_FILE_NAME), error});
+								}
+							}
 						}
 						BoardMessage::OpenWorld{ref filename} => {
-							self.open_world(filename);
+							if let Err(error) = self.open_world(filename) {
+								board_messages.push(BoardMessage::WorldLoadFailed{filename: filename.clone(), error});
+							}
+						}
+						BoardMessage::OpenSettings => {
+							self.open_settings_scroll();
+						}
+						BoardMessage::SaveScreenshot{ref path} if ScreenshotFormat::from_path(path) == Some(ScreenshotFormat::Png) => {
+							match self.save_screenshot(path) {
+								Ok(()) => {
+									let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+									self.engine.queue_caption(DosString::from_str(&format!("Saved screenshot {}", file_name)));
+								}
+								Err(error) => eprintln!("{}", error),
+							}
+						}
+						BoardMessage::EngineLog(level, ref message) => {
+							eprintln!("{:?}: {}", level, message);
+						}
+						BoardMessage::SetGameSpeed(game_speed) => {
+							self.config.game_speed = game_speed;
+							self.config.save_or_print_error();
+						}
+						BoardMessage::SetKeyRepeatDelay(key_repeat_delay) => {
+							self.config.key_repeat_delay = key_repeat_delay;
+							self.config.save_or_print_error();
 						}
 						_ => {}
 					}
@@ -457,37 +1455,84 @@ impl ZztConsole {
 				}
 			}
 
-			for (new_sounds, priority) in new_sounds_list {
-				audio_device.lock().play_sounds(new_sounds, priority);
+			// Start any sound that replaced the playing one straight away.
+			sound_changed |= self.engine.advance_sound(0.);
+			if sound_changed {
+				let playing_entry = if self.config.sound_enabled {
+					self.engine.sound_queue.playing_entry().cloned()
+				} else {
+					None
+				};
+				audio_device.lock().play_entry(playing_entry);
 			}
 
-			if should_clear_sound {
-				audio_device.lock().clear_sound_queue();
+			let is_end_of_game = self.engine.is_end_of_game();
+			if is_end_of_game && !was_end_of_game {
+				self.record_end_of_game();
 			}
+			was_end_of_game = is_end_of_game;
 
-			self.draw_screen(&mut canvas, &mut dosfont_tex, false);
-
-			canvas.present();
+			let is_speedrun_finished = self.engine.speedrun_timer.as_ref().map_or(false, |speedrun_timer| speedrun_timer.is_finished());
+			if is_speedrun_finished && !was_speedrun_finished {
+				self.export_splits();
+			}
+			was_speedrun_finished = is_speedrun_finished;
 
-			let current_time_ms = get_ms_from_duration(SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
+			let new_window_title = self.engine.window_title();
+			if new_window_title != window_title {
+				canvas.window_mut().set_title(&new_window_title).ok();
+				window_title = new_window_title;
+			}
 
-			if !applied_board_message {
-				let max_delay = (1000. / rate_hz) as usize;
-				let frame_length_ms = current_time_ms - last_time_ms;
-				if frame_length_ms < max_delay {
-					let delay = max_delay - frame_length_ms;
-					let delay_duration = std::time::Duration::from_millis(if self.engine.should_simulate_fast() { 10 } else { delay as u64 });
-					//let delay_duration = std::time::Duration::from_millis(0);
-					// TODO: This could intelligently wait for the minimum of the time to the next
-					// simulation step, and the time till the next screen blink.
-					std::thread::sleep(delay_duration);
+			// Nothing needs drawing on most frames while the game is paused, or between steps.
+			if self.engine.screen_dirty() {
+				self.draw_screen(&mut canvas, &mut dosfont_tex, &mut run_cache, screen_tex.as_mut(), false);
+				if !vsync {
+					canvas.present();
 				}
 			}
 
-			let time_since_start_ms = current_time_ms - start_time_ms;
-			self.current_run_time_ms = time_since_start_ms;
+			// With vsync, `present` waits for the display, which paces the loop at one frame per
+			// refresh.
+			if let Some(ref screen_tex) = screen_tex {
+				canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+				canvas.clear();
+				canvas.copy(screen_tex, None, None).expect("Render failed");
+				canvas.present();
+			}
+
+			// Without vsync, when the engine had nothing to say this frame, sleep until whichever comes
+			// first out of the next simulation step, the next time the blinking characters change and
+			// the next sound entry, so the loop doesn't spin.
+			if !vsync && board_messages_were_empty {
+				let frame_end_ms = get_ms_from_duration(SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
+				let frame_time_ms = (frame_end_ms - current_time_ms) as f64;
+				let time_to_next_step_ms = (step_length_ms - sim_accumulator_ms - frame_time_ms).max(0.) as usize;
+				let time_to_next_blink_ms = self.engine.console_state.ms_until_blink_change().saturating_sub(frame_end_ms - current_time_ms);
+				let time_to_next_sound_ms = self.engine.sound_queue.ms_until_next_entry().map_or(usize::MAX, |ms| (ms - frame_time_ms).max(0.) as usize);
+				let wait_ms = time_to_next_step_ms.min(time_to_next_blink_ms).min(time_to_next_sound_ms);
+				std::thread::sleep(std::time::Duration::from_millis(wait_ms as u64));
+			}
+		}
 
-			last_time_ms = get_ms_from_duration(SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
+		// RUZZT is exiting normally, so there is nothing to resume next time.
+		if let Some(ref autosave) = self.engine.autosave {
+			Autosave::remove_files(&autosave.directory);
+		}
+
+		// Save the splits of an unfinished run too, so the times reached so far aren't lost.
+		if !was_speedrun_finished {
+			self.export_splits();
+		}
+
+		if let Some(profile) = self.engine.take_profile() {
+			println!("{}", profile);
+		}
+
+		if let Some(ref message_timeline_path) = self.message_timeline_path {
+			if let Err(err) = self.engine.write_message_timeline(message_timeline_path) {
+				eprintln!("{}", err);
+			}
 		}
 	}
 }
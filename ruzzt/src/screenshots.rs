@@ -0,0 +1,77 @@
+use crate::{read_world_file, ZztConsole};
+use crate::draw_batch::{DrawBatch, RunCache};
+
+use ruzzt_engine::compatibility::CompatibilityFixture;
+
+use sdl2::image::{SaveSurface, INIT_PNG};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::Canvas;
+use sdl2::surface::Surface;
+
+use std::path::{Path, PathBuf};
+
+/// The settings for writing screenshots of a demo (see `ZztConsole::run_screenshots`).
+pub struct ScreenshotRun {
+	/// The compatibility fixture with the recorded input to replay (see
+	/// `ruzzt_engine::compatibility::CompatibilityFixture`).
+	pub demo_path: PathBuf,
+	/// The world to replay the demo in, or None for the world named in the demo.
+	pub world_path: Option<PathBuf>,
+	/// The number of game cycles between screenshots.
+	pub every_steps: usize,
+	/// The directory the screenshots are written to, which is made if it doesn't exist.
+	pub output_directory: PathBuf,
+}
+
+impl ZztConsole {
+	/// Replay a demo without a window or sound, and write a PNG of the screen to
+	/// `run.output_directory` before the first game cycle and then every `run.every_steps` cycles.
+	/// The files are named after the number of cycles played (eg. `step_000120.png`), so they sort
+	/// in order. The screen is drawn with the same palette and font as it would be in the window.
+	pub fn run_screenshots(&mut self, run: ScreenshotRun) -> Result<(), String> {
+		let text = std::fs::read_to_string(&run.demo_path).map_err(|err| format!("{}: {}", run.demo_path.display(), err))?;
+		let demo = CompatibilityFixture::parse(&text).map_err(|err| format!("{}: {}", run.demo_path.display(), err))?;
+		let world_path = run.world_path.clone().unwrap_or_else(|| {
+			run.demo_path.parent().unwrap_or_else(|| Path::new(".")).join(&demo.world_file_name)
+		});
+		let world = read_world_file(&world_path).map_err(|err| format!("{}: {}", world_path.display(), err))?;
+		self.load_related_files(&world_path);
+
+		std::fs::create_dir_all(&run.output_directory).map_err(|err| format!("Couldn't create {}: {}", run.output_directory.display(), err))?;
+		let _sdl_image = sdl2::image::init(INIT_PNG)?;
+
+		let mut result = Ok(());
+		let mut screenshot_count = 0;
+		demo.replay_screenshots(&world, run.every_steps, |steps_played, console_state| {
+			if result.is_err() {
+				return;
+			}
+			self.engine.console_state = console_state.clone();
+			let path = run.output_directory.join(format!("step_{:06}.png", steps_played));
+			result = self.save_screenshot(&path);
+			screenshot_count += 1;
+		});
+		result?;
+
+		println!("Wrote {} screenshots to {}", screenshot_count, run.output_directory.display());
+		Ok(())
+	}
+
+	/// Draw the whole of the engine's console to a PNG file at `path`.
+	pub(crate) fn save_screenshot(&self, path: &Path) -> Result<(), String> {
+		let (render_width, render_height) = self.engine.console_state.screen_mode().pixel_size();
+		let surface = Surface::new(render_width as u32, render_height as u32, PixelFormatEnum::RGB888)?;
+		let mut canvas = Canvas::from_surface(surface)?;
+		let texture_creator = canvas.texture_creator();
+		let mut dosfont_tex = self.create_font_texture(&texture_creator);
+
+		let mut batch = DrawBatch::new(dosfont_tex.query().height);
+		for (x, y, screen_char) in self.engine.console_state.iter_cells() {
+			self.draw_char(&mut batch, x, y, *screen_char);
+		}
+		batch.draw(&mut canvas, &mut dosfont_tex, &mut RunCache::new(&texture_creator));
+		canvas.present();
+
+		canvas.into_surface().save(path).map_err(|err| format!("Couldn't write {}: {}", path.display(), err))
+	}
+}
@@ -0,0 +1,48 @@
+use crate::{read_world_file, ZztConsole};
+
+use ruzzt_engine::smoke_test::smoke_test_world;
+
+use std::path::PathBuf;
+
+/// The settings for checking that every board of some worlds loads and runs (see
+/// `ZztConsole::run_smoke_test`).
+pub struct SmokeTestRun {
+	pub world_paths: Vec<PathBuf>,
+	/// The number of game cycles to run on each board.
+	pub steps_per_board: usize,
+	/// The seed for the random number generator, so failures can be reproduced.
+	pub seed: u64,
+}
+
+impl ZztConsole {
+	/// Run every board of each world in `run.world_paths` without a window or sound (see
+	/// `ruzzt_engine::smoke_test::smoke_test_world`), printing a line for each board and a summary
+	/// at the end. Returns true if every board passed.
+	pub fn run_smoke_test(&mut self, run: SmokeTestRun) -> bool {
+		let mut board_count = 0;
+		let mut failed_count = 0;
+		for world_path in &run.world_paths {
+			let world = match read_world_file(world_path) {
+				Ok(world) => world,
+				Err(err) => {
+					println!("{}: Couldn't load the world: {}", world_path.display(), err);
+					failed_count += 1;
+					continue;
+				}
+			};
+
+			for result in smoke_test_world(&world, run.steps_per_board, run.seed) {
+				board_count += 1;
+				if !result.passed() {
+					failed_count += 1;
+				}
+				for line in result.description_lines() {
+					println!("{}: {}", world_path.display(), line);
+				}
+			}
+		}
+
+		println!("{} boards run, {} failed", board_count, failed_count);
+		failed_count == 0
+	}
+}
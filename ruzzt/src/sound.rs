@@ -16,92 +16,68 @@ fn generate_sound_code_frequencies() -> Vec<u16> {
 	result
 }
 
+/// The sample rate that sounds are generated at. They are resampled to the rate of the audio
+/// device, so notes stay in tune and the low-pass filter sounds the same whatever rate the device
+/// is opened with (see `UserConfig::audio_sample_rate`).
+const SYNTH_SAMPLE_RATE: i32 = 44100;
+
+/// Plays the entry that the engine's sound queue is on (see `RuzztEngine::advance_sound`). The
+/// engine decides when each entry starts and stops, so this only has to make the noise.
 pub struct SoundPlayer {
-	spec: AudioSpec,
+	/// The sample rate of the audio device.
+	output_rate: i32,
 	current_magnitude: f32,
 	volume: f32,
 	lowpass_level: f32,
-	whole_note_samples: usize,
 	//last_wave_up: Option<bool>,
 	sound_code_frequencies: Vec<u16>,
-	sound_entry_queue: VecDeque<SoundEntry>,
 	current_frequency: u16,
-	current_sound_remaining_samples: usize,
+	/// The number of samples of the current note that have been played.
+	current_sound_samples: usize,
 	rendered_samples_to_play: VecDeque<bool>,
-	current_priority: SoundPriority,
+	/// The last two samples generated at `SYNTH_SAMPLE_RATE`, which the samples sent to the audio
+	/// device are interpolated between.
+	previous_sample: f32,
+	next_sample: f32,
+	/// How far the next sample sent to the audio device is from `previous_sample` to
+	/// `next_sample`, from 0 to 1. When it reaches 1, another sample is generated.
+	resample_position: f64,
 }
 
 impl SoundPlayer {
 	pub fn new(spec: AudioSpec) -> SoundPlayer {
-		let whole_note_samples = (spec.freq as f32 * 1.8) as usize;
+		SoundPlayer::with_output_rate(spec.freq)
+	}
 
+	/// Make a player for an audio device with the sample rate `output_rate`.
+	fn with_output_rate(output_rate: i32) -> SoundPlayer {
 		SoundPlayer {
-			spec,
+			output_rate,
 			current_magnitude: 0.,
 			volume: 0.25,
 			lowpass_level: 3.,
-			whole_note_samples,
 			sound_code_frequencies: generate_sound_code_frequencies(),
-			sound_entry_queue: VecDeque::new(),
 			current_frequency: 0,
-			current_sound_remaining_samples: 0,
+			current_sound_samples: 0,
 			rendered_samples_to_play: VecDeque::new(),
-			current_priority: SoundPriority::Level(0),
+			previous_sample: 0.,
+			next_sample: 0.,
+			resample_position: 0.,
 		}
 	}
 
-	fn is_sound_playing(&self) -> bool {
-		!self.sound_entry_queue.is_empty() || self.current_frequency != 0 || !self.rendered_samples_to_play.is_empty()
-	}
-
-	pub fn clear_sound_queue(&mut self) {
-		self.sound_entry_queue.clear();
+	/// Cut off whatever is playing and start playing `entry` on the next sample, or go silent if
+	/// it's None.
+	pub fn play_entry(&mut self, entry: Option<SoundEntry>) {
 		self.rendered_samples_to_play.clear();
 		self.current_frequency = 0;
-		self.current_sound_remaining_samples = 0;
-	}
-
-	pub fn play_sounds(&mut self, sound_entries: Vec<SoundEntry>, priority: SoundPriority) {
-		enum PlayAction {
-			None,
-			Append,
-			Replace,
-		};
-
-		let play_action = if self.is_sound_playing() {
-			if priority.is_higher_priority_than(&self.current_priority) {
-				if priority == SoundPriority::Music {
-					PlayAction::Append
-				} else {
-					PlayAction::Replace
-				}
-			} else {
-				PlayAction::None
-			}
-		} else {
-			PlayAction::Replace
-		};
+		self.current_sound_samples = 0;
 
-		match play_action {
-			PlayAction::None => {}
-			PlayAction::Append => {
-				self.sound_entry_queue.extend(sound_entries);
-			}
-			PlayAction::Replace => {
-				self.current_priority = priority;
-				self.sound_entry_queue.clear();
-				self.sound_entry_queue.extend(sound_entries);
-			}
-		}
-	}
-
-	fn play_next_sound(&mut self) {
-		if let Some(next_sound) = self.sound_entry_queue.pop_front() {
-			if next_sound.sound_code >= 240 {
-				let effect_index = next_sound.sound_code - 240;
-				self.rendered_samples_to_play.clear();
+		if let Some(entry) = entry {
+			if entry.sound_code >= 240 {
+				let effect_index = entry.sound_code - 240;
 				for freq in &SOUND_EFFECT_WAVES[effect_index as usize] {
-					let half_sample_length = self.spec.freq / *freq as i32 / 2;
+					let half_sample_length = SYNTH_SAMPLE_RATE / *freq as i32 / 2;
 					for _ in 0..half_sample_length {
 						self.rendered_samples_to_play.push_back(true);
 					}
@@ -110,15 +86,36 @@ impl SoundPlayer {
 					}
 				}
 			} else {
-				self.current_frequency = self.sound_code_frequencies[next_sound.sound_code as usize];
+				self.current_frequency = self.sound_code_frequencies[entry.sound_code as usize];
 			}
+		}
+	}
 
-			let length_of_32nd_note = self.whole_note_samples / 32;
-			self.current_sound_remaining_samples = length_of_32nd_note * next_sound.length_multiplier as usize;
+	/// Generate the next sample at `SYNTH_SAMPLE_RATE`.
+	fn synth_sample(&mut self) -> f32 {
+		let dest_mag;
+		if let Some(is_up) = self.rendered_samples_to_play.pop_front() {
+			dest_mag = if is_up {
+				self.volume
+			} else {
+				-self.volume
+			};
+		} else if self.current_frequency != 0 {
+			let period = SYNTH_SAMPLE_RATE as usize / self.current_frequency as usize;
+			let is_up = (self.current_sound_samples % period) > (period / 2);
+
+			dest_mag = if is_up {
+				self.volume
+			} else {
+				-self.volume
+			};
 		} else {
-			self.current_frequency = 0;
-			self.current_sound_remaining_samples = 0;
+			dest_mag = 0.0;
 		}
+
+		self.current_magnitude -= (self.current_magnitude - dest_mag) / self.lowpass_level;
+		self.current_sound_samples += 1;
+		self.current_magnitude
 	}
 }
 
@@ -126,35 +123,17 @@ impl AudioCallback for SoundPlayer {
 	type Channel = f32;
 
 	fn callback(&mut self, out: &mut [f32]) {
+		// Resample from `SYNTH_SAMPLE_RATE` to the device's rate by interpolating between the
+		// generated samples. When the rates are the same, this passes the samples straight through.
+		let step = SYNTH_SAMPLE_RATE as f64 / self.output_rate as f64;
 		for sample in out.iter_mut() {
-			let dest_mag;
-			if let Some(is_up) = self.rendered_samples_to_play.pop_front() {
-				dest_mag = if is_up {
-					self.volume
-				} else {
-					-self.volume
-				};
-			} else if self.current_frequency != 0 {
-				let period = self.spec.freq as usize / self.current_frequency as usize;
-				let is_up = (self.current_sound_remaining_samples % period) > (period / 2);
-
-				dest_mag = if is_up {
-					self.volume
-				} else {
-					-self.volume
-				};
-			} else {
-				dest_mag = 0.0;
-			}
-
-			self.current_magnitude -= (self.current_magnitude - dest_mag) / self.lowpass_level;
-			*sample = self.current_magnitude;
-
-			if self.current_sound_remaining_samples == 0 {
-				self.play_next_sound();
-			} else {
-				self.current_sound_remaining_samples -= 1;
+			while self.resample_position >= 1. {
+				self.previous_sample = self.next_sample;
+				self.next_sample = self.synth_sample();
+				self.resample_position -= 1.;
 			}
+			*sample = self.previous_sample + (self.next_sample - self.previous_sample) * self.resample_position as f32;
+			self.resample_position += step;
 		}
 
 		// Generate a square wave
@@ -193,3 +172,36 @@ impl AudioCallback for SoundPlayer {
 		}*/
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Play `sound_code` for a second at `output_rate`, and get the number of samples generated at
+	/// `SYNTH_SAMPLE_RATE` and the number of times the output rose through zero (its pitch).
+	fn play_one_second(output_rate: i32, sound_code: u8) -> (usize, usize) {
+		let mut sound_player = SoundPlayer::with_output_rate(output_rate);
+		sound_player.play_entry(Some(SoundEntry {
+			sound_code,
+			length_multiplier: 1,
+		}));
+		let mut out = vec![0.; output_rate as usize];
+		sound_player.callback(&mut out);
+		let rising_zero_crossings = out.windows(2).filter(|pair| pair[0] < 0. && pair[1] >= 0.).count();
+		(sound_player.current_sound_samples, rising_zero_crossings)
+	}
+
+	#[test]
+	fn resampling_keeps_the_pitch() {
+		// Sound code 0x40 is a C at 512Hz, which is 513Hz after rounding to whole samples.
+		let (synth_samples, pitch) = play_one_second(SYNTH_SAMPLE_RATE, 0x40);
+		assert!((SYNTH_SAMPLE_RATE as usize - 1 ..= SYNTH_SAMPLE_RATE as usize).contains(&synth_samples));
+		assert!((512 ..= 513).contains(&pitch));
+
+		for &output_rate in &[22050, 48000] {
+			let (resampled_synth_samples, resampled_pitch) = play_one_second(output_rate, 0x40);
+			assert!((resampled_synth_samples as i64 - synth_samples as i64).abs() <= 1, "{} samples at {}Hz", resampled_synth_samples, output_rate);
+			assert!((resampled_pitch as i64 - pitch as i64).abs() <= 1, "{}Hz at {}Hz", resampled_pitch, output_rate);
+		}
+	}
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo fuzz run step_loop` in the ruzzt_engine folder. Putting some world files in
+// `fuzz/corpus/step_loop` first (with a byte for the number of events in front) helps it find
+// its way into the game much sooner.
+fuzz_target!(|data: &[u8]| {
+	ruzzt_engine::smoke_test::fuzz_world_data(data);
+});
@@ -0,0 +1,188 @@
+use crate::board_message::BoardMessage;
+use crate::direction::Direction;
+use crate::engine::RuzztEngine;
+use crate::event::Event;
+
+use zzt_file_format::World;
+
+/// Something an agent can do in a single step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AgentAction {
+	/// Do nothing.
+	Idle,
+	/// Move one tile in the given direction.
+	Move(Direction),
+	/// Shoot in the given direction.
+	Shoot(Direction),
+	/// Light a torch.
+	LightTorch,
+}
+
+impl AgentAction {
+	/// All the actions an agent can take, so they can be numbered for a discrete action space.
+	pub fn all() -> Vec<AgentAction> {
+		let directions = [Direction::North, Direction::South, Direction::West, Direction::East];
+		let mut actions = vec![AgentAction::Idle];
+		actions.extend(directions.iter().map(|dir| AgentAction::Move(*dir)));
+		actions.extend(directions.iter().map(|dir| AgentAction::Shoot(*dir)));
+		actions.push(AgentAction::LightTorch);
+		actions
+	}
+
+	/// Get the input `Event` that performs this action.
+	pub fn to_event(self) -> Event {
+		match self {
+			AgentAction::Idle => Event::None,
+			AgentAction::Move(Direction::North) => Event::Up,
+			AgentAction::Move(Direction::South) => Event::Down,
+			AgentAction::Move(Direction::West) => Event::Left,
+			AgentAction::Move(Direction::East) => Event::Right,
+			AgentAction::Shoot(Direction::North) => Event::ShootUp,
+			AgentAction::Shoot(Direction::South) => Event::ShootDown,
+			AgentAction::Shoot(Direction::West) => Event::ShootLeft,
+			AgentAction::Shoot(Direction::East) => Event::ShootRight,
+			AgentAction::Move(Direction::Idle) | AgentAction::Shoot(Direction::Idle) => Event::None,
+			AgentAction::LightTorch => Event::LightTorch,
+		}
+	}
+}
+
+/// What an agent can see of the game after each step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+	/// The (element ID, colour) of every tile on the current board, not including the board edges,
+	/// in rows from the top-left. There are `BOARD_WIDTH - 2` columns and `BOARD_HEIGHT - 2` rows.
+	pub tiles: Vec<(u8, u8)>,
+	/// The player's position on the board (1-based, like `StatusElement` locations).
+	pub player_x: i16,
+	pub player_y: i16,
+	/// The index of the current board.
+	pub board_index: i16,
+	pub health: i16,
+	pub ammo: i16,
+	pub gems: i16,
+	pub torches: i16,
+	pub score: i16,
+	pub keys: [bool; 7],
+}
+
+/// A "gym" style environment for training agents to play a ZZT world. Each call to `step` takes an
+/// `AgentAction`, simulates one game cycle, and returns an `Observation`, a reward, and whether the
+/// episode is over. The reward is the change in score, plus the change in health multiplied by
+/// `health_reward_scale`.
+#[derive(Clone)]
+pub struct EngineEnv {
+	/// The world as it was given to `new`, which every episode starts from.
+	world: World,
+	/// The engine simulating the current episode.
+	pub engine: RuzztEngine,
+	/// The number of steps taken in the current episode.
+	pub step_count: usize,
+	/// When set, the episode ends after this many steps.
+	pub max_steps: Option<usize>,
+	/// How much each point of health gained or lost adds to or takes away from the reward.
+	pub health_reward_scale: f64,
+	/// When true, scrolls are closed as soon as they open, because agents can't read them anyway.
+	pub auto_close_scrolls: bool,
+	/// When set, the random number generator is seeded with this at the start of every episode, so
+	/// the same actions always play out the same way.
+	pub seed: Option<u64>,
+}
+
+impl EngineEnv {
+	/// Make an environment for playing the given `world`. Call `reset` before the first `step`.
+	pub fn new(world: World) -> EngineEnv {
+		let engine = EngineEnv::start_engine(&world, None);
+		EngineEnv {
+			world,
+			engine,
+			step_count: 0,
+			max_steps: None,
+			health_reward_scale: 1.,
+			auto_close_scrolls: true,
+			seed: None,
+		}
+	}
+
+	/// Make an engine with a new game of `world` in progress and unpaused, seeded with `seed` if it
+	/// is set. If `world` can't be loaded, the game is played on the empty world the engine starts
+	/// with.
+	fn start_engine(world: &World, seed: Option<u64>) -> RuzztEngine {
+		let mut engine = RuzztEngine::new();
+		if let Some(seed) = seed {
+			engine.seed_rng(seed);
+		}
+		engine.load_world(world.clone(), None).ok();
+		engine.set_in_title_screen(false);
+		engine.is_paused = false;
+		engine
+	}
+
+	/// Start a new episode from the beginning of the world, and get the first observation.
+	pub fn reset(&mut self) -> Observation {
+		self.engine = EngineEnv::start_engine(&self.world, self.seed);
+		self.step_count = 0;
+		self.observe()
+	}
+
+	/// Get what the agent can currently see.
+	pub fn observe(&self) -> Observation {
+		let sim = &self.engine.board_simulator;
+		let tiles = sim.board_tiles().map(|(_, _, tile)| (tile.element_id, tile.colour)).collect();
+
+		let (player_x, player_y) = sim.get_player_location();
+		let world_header = &sim.world_header;
+		Observation {
+			tiles,
+			player_x,
+			player_y,
+			board_index: world_header.player_board,
+			health: world_header.player_health,
+			ammo: world_header.player_ammo,
+			gems: world_header.player_gems,
+			torches: world_header.player_torches.unwrap_or(0),
+			score: world_header.player_score,
+			keys: world_header.player_keys,
+		}
+	}
+
+	/// True when the episode is over, because the game ended or `max_steps` was reached.
+	pub fn is_done(&self) -> bool {
+		self.engine.is_end_of_game() || self.max_steps.is_some_and(|max_steps| self.step_count >= max_steps)
+	}
+
+	/// Perform the given `action` and simulate one whole game cycle. Returns the new observation,
+	/// the reward for the step, and true if the episode is over.
+	pub fn step(&mut self, action: AgentAction) -> (Observation, f64, bool) {
+		let old_score = self.engine.board_simulator.world_header.player_score;
+		let old_health = self.engine.board_simulator.world_header.player_health;
+
+		let auto_close_scrolls = self.auto_close_scrolls;
+		self.engine.step_whole_cycle(action.to_event(), 0., |engine, board_messages| {
+			process_board_messages(engine, board_messages, auto_close_scrolls);
+		});
+
+		self.step_count += 1;
+
+		let world_header = &self.engine.board_simulator.world_header;
+		let score_change = (world_header.player_score - old_score) as f64;
+		let health_change = (world_header.player_health - old_health) as f64;
+		let reward = score_change + health_change * self.health_reward_scale;
+
+		(self.observe(), reward, self.is_done())
+	}
+}
+
+/// Apply the default handling of all the `board_messages`, and any messages that causes. Any scroll
+/// that opens is closed straight away if `auto_close_scrolls` is true.
+fn process_board_messages(engine: &mut RuzztEngine, mut board_messages: Vec<BoardMessage>, auto_close_scrolls: bool) {
+	while !board_messages.is_empty() {
+		let processing_board_messages = std::mem::take(&mut board_messages);
+		for board_message in processing_board_messages {
+			board_messages.extend(engine.process_board_message(board_message));
+		}
+	}
+	if auto_close_scrolls {
+		engine.scroll_state = None;
+	}
+}
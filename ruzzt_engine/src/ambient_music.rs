@@ -0,0 +1,31 @@
+use zzt_file_format::dosstring::DosString;
+
+use std::collections::BTreeMap;
+
+/// The file extension of ambient music files. The ambient music for `TOWN.ZZT` is read from
+/// `TOWN.MUS` in the same folder, if there is one.
+pub const AMBIENT_MUSIC_FILE_EXTENSION: &str = "MUS";
+
+/// Parse an ambient music file, which says which music loops in the background of each board. This
+/// is a RUZZT extension, so worlds without an ambient music file play the same as in DOS ZZT.
+///
+/// Blank lines and lines starting with `#` are ignored. Every other line is a board index, a space,
+/// then the music to loop on that board, written the same way as for `#play` (eg. `1 tcdefgab`).
+///
+/// Returns the music keyed by board index.
+pub fn parse_ambient_music(text: &str) -> Result<BTreeMap<u8, DosString>, String> {
+	let mut board_music = BTreeMap::new();
+
+	for (line_index, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let (board_index_str, music) = line.split_once(char::is_whitespace).ok_or_else(|| format!("Line {}: Expected a board index followed by music", line_index + 1))?;
+		let board_index = board_index_str.parse::<u8>().map_err(|e| format!("Line {}: Invalid board index: {}", line_index + 1, e))?;
+		board_music.insert(board_index, DosString::from_str(music.trim()));
+	}
+
+	Ok(board_music)
+}
@@ -0,0 +1,229 @@
+use crate::board_simulator::{BOARD_HEIGHT, BOARD_WIDTH};
+use crate::console::{Colour, ConsoleChar, ConsoleColour};
+use crate::engine::RuzztEngine;
+
+use num::FromPrimitive;
+use zzt_file_format::{Board, BoardTile, ElementType, World};
+
+/// The file extension of ANSI art files.
+pub const ANSI_FILE_EXTENSION: &str = "ANS";
+
+/// The width of a board on the screen, without the board edges.
+const BOARD_SCREEN_WIDTH: usize = BOARD_WIDTH - 2;
+/// The height of a board on the screen, without the board edges.
+const BOARD_SCREEN_HEIGHT: usize = BOARD_HEIGHT - 2;
+
+/// Get the byte to write to an ANSI art file for the DOS character `char_code`. Characters that
+/// ANSI viewers treat as control codes instead of drawing them (eg. escape and the end-of-file
+/// marker) are written as spaces.
+fn ansi_char_byte(char_code: u8) -> u8 {
+	match char_code {
+		0x00 | 0x07 | 0x08 | 0x09 | 0x0a | 0x0d | 0x1a | 0x1b => b' ',
+		_ => char_code,
+	}
+}
+
+/// Convert `rows` of characters to ANSI art: the characters are written as CP437 bytes, with
+/// escape codes to set the colours. Bright foreground colours use the bold attribute and blinking
+/// backgrounds use the blink attribute, like ANSI art made on DOS. Each row ends with a new line.
+pub fn console_chars_to_ansi(rows: &[Vec<ConsoleChar>]) -> Vec<u8> {
+	let mut result = b"\x1b[0m".to_vec();
+	for row in rows {
+		let mut current_colour = None;
+		for console_char in row {
+			let colour = Colour::from_console_colours(console_char.foreground, console_char.background);
+			if current_colour != Some(colour) {
+				let foreground = colour.foreground.ansi_index();
+				let mut escape_code = String::from("\x1b[0");
+				if foreground >= 8 {
+					escape_code += ";1";
+				}
+				if colour.blinking {
+					escape_code += ";5";
+				}
+				escape_code += &format!(";{};{}m", 30 + foreground % 8, 40 + colour.background.ansi_index());
+				result.extend_from_slice(escape_code.as_bytes());
+				current_colour = Some(colour);
+			}
+			result.push(ansi_char_byte(console_char.char_code));
+		}
+		result.extend_from_slice(b"\x1b[0m\r\n");
+	}
+	result
+}
+
+/// Draw the board at `board_index` in `world` the same way the game does (including objects, line
+/// walls joining up, and so on) and get the characters for each row of the board.
+///
+/// Dark boards are drawn as if they were lit, so the whole board shows up.
+pub fn render_board(world: &World, board_index: usize) -> Result<Vec<Vec<ConsoleChar>>, String> {
+	if board_index >= world.boards.len() {
+		return Err(format!("There is no board {}", board_index));
+	}
+
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world.clone(), Some(board_index as i16))?;
+	engine.set_in_title_screen(false);
+	engine.is_paused = false;
+	engine.board_simulator.board_meta_data.is_dark = false;
+	engine.update_screen();
+
+	Ok((0 .. BOARD_SCREEN_HEIGHT).map(|y| {
+		(0 .. BOARD_SCREEN_WIDTH).map(|x| engine.console_state.get_char(x, y)).collect()
+	}).collect())
+}
+
+/// Convert the board at `board_index` in `world` to ANSI art (see `render_board` and
+/// `console_chars_to_ansi`), to save as an `.ANS` file.
+pub fn board_to_ansi(world: &World, board_index: usize) -> Result<Vec<u8>, String> {
+	Ok(console_chars_to_ansi(&render_board(world, board_index)?))
+}
+
+/// The width of the screen that ANSI art is drawn for. Text wraps onto the next row after this.
+const ANSI_SCREEN_WIDTH: usize = 80;
+
+/// Read ANSI art (or plain CP437 text, which is ANSI art without any escape codes) and get the
+/// characters for each row. Colour escape codes and cursor movement to the right are understood,
+/// and everything after the end-of-file marker (eg. a SAUCE record) is ignored. Every row is
+/// `ANSI_SCREEN_WIDTH` characters wide, filled with black spaces.
+pub fn parse_ansi_art(data: &[u8]) -> Vec<Vec<ConsoleChar>> {
+	let blank_char = ConsoleChar::new(b' ', ConsoleColour::Black, ConsoleColour::Black);
+	let mut rows: Vec<Vec<ConsoleChar>> = vec![];
+	let (mut x, mut y) = (0usize, 0usize);
+	let (mut foreground, mut background, mut bold, mut blinking) = (7u8, 0u8, false, false);
+
+	let mut pos = 0;
+	while pos < data.len() {
+		let byte = data[pos];
+		pos += 1;
+		match byte {
+			0x1a => break,
+			b'\r' => x = 0,
+			b'\n' => {
+				x = 0;
+				y += 1;
+			}
+			b'\t' => x = (x / 8 + 1) * 8,
+			0x1b if data.get(pos) == Some(&b'[') => {
+				pos += 1;
+				let params_start = pos;
+				while pos < data.len() && !data[pos].is_ascii_alphabetic() {
+					pos += 1;
+				}
+				let params = String::from_utf8_lossy(&data[params_start .. pos]).into_owned();
+				let params: Vec<usize> = params.split(';').map(|param| param.parse().unwrap_or(0)).collect();
+				match data.get(pos) {
+					Some(b'm') => {
+						for &param in &params {
+							match param {
+								0 => {
+									foreground = 7;
+									background = 0;
+									bold = false;
+									blinking = false;
+								}
+								1 => bold = true,
+								5 => blinking = true,
+								22 => bold = false,
+								25 => blinking = false,
+								30 ..= 37 => foreground = (param - 30) as u8,
+								40 ..= 47 => background = (param - 40) as u8,
+								_ => {}
+							}
+						}
+					}
+					Some(b'C') => x += params[0].max(1),
+					Some(b'D') => x = x.saturating_sub(params[0].max(1)),
+					Some(b'A') => y = y.saturating_sub(params[0].max(1)),
+					Some(b'B') => y += params[0].max(1),
+					Some(b'H') | Some(b'f') => {
+						y = params[0].max(1) - 1;
+						x = params.get(1).map_or(1, |&column| column.max(1)) - 1;
+					}
+					_ => {}
+				}
+				pos += 1;
+			}
+			_ => {
+				if x >= ANSI_SCREEN_WIDTH {
+					x = 0;
+					y += 1;
+				}
+				while rows.len() <= y {
+					rows.push(vec![blank_char; ANSI_SCREEN_WIDTH]);
+				}
+				// The ANSI palette has red and blue swapped compared to DOS, and `ansi_index` swaps
+				// them back.
+				let dos_foreground = ConsoleColour::from_u8(foreground + if bold { 8 } else { 0 }).unwrap().ansi_index();
+				let dos_background = ConsoleColour::from_u8(background).unwrap().ansi_index();
+				let colour = Colour::new(ConsoleColour::from_u8(dos_foreground).unwrap(), ConsoleColour::from_u8(dos_background).unwrap(), blinking);
+				rows[y][x] = ConsoleChar::new(byte, colour.console_background(), colour.foreground);
+				x += 1;
+			}
+		}
+	}
+
+	rows
+}
+
+/// Get the `Text*` element that draws white text on the given `background` colour. Light gray
+/// backgrounds don't have a text element, so black is used instead.
+fn text_element_type(background: ConsoleColour) -> ElementType {
+	match background {
+		ConsoleColour::Blue => ElementType::TextBlue,
+		ConsoleColour::Green => ElementType::TextGreen,
+		ConsoleColour::Cyan => ElementType::TextCyan,
+		ConsoleColour::Red => ElementType::TextRed,
+		ConsoleColour::Magenta => ElementType::TextPurple,
+		ConsoleColour::Brown => ElementType::TextBrown,
+		_ => ElementType::TextBlack,
+	}
+}
+
+/// Get the tile that looks most like `console_char`:
+/// - Black spaces become empty tiles.
+/// - Full blocks, and spaces on a coloured background, become solid walls.
+/// - Shaded blocks (`0xb2`) become normal walls, which keep both colours.
+/// - Everything else becomes a text element. Text elements are always white on one of the 7 text
+///   background colours, so the foreground colour is lost.
+pub fn console_char_to_tile(console_char: ConsoleChar) -> BoardTile {
+	let colour = Colour::from_console_colours(console_char.foreground, console_char.background);
+	let is_blank = matches!(console_char.char_code, 0 | b' ' | 0xff) || colour.foreground == colour.background;
+	if is_blank {
+		if colour.background == ConsoleColour::Black {
+			BoardTile::new(ElementType::Empty, 0)
+		} else {
+			BoardTile::new(ElementType::Solid, colour.background as u8)
+		}
+	} else if console_char.char_code == 0xdb {
+		BoardTile::new(ElementType::Solid, colour.foreground as u8)
+	} else if console_char.char_code == 0xb2 {
+		BoardTile::new(ElementType::Normal, (colour.background as u8) << 4 | colour.foreground as u8)
+	} else {
+		BoardTile::new(text_element_type(colour.background), console_char.char_code)
+	}
+}
+
+/// Replace the tiles of `board` with the ANSI art (or plain text) in `data` (see
+/// `parse_ansi_art`). The art is clipped to the size of the board, and tiles that aren't covered
+/// by it are left empty. The tiles under status elements (eg. the player) are kept, so the board
+/// stays valid.
+pub fn import_ansi_art(board: &mut Board, data: &[u8]) {
+	let rows = parse_ansi_art(data);
+	let status_tiles: Vec<_> = board.status_elements.iter()
+		.map(|status| (status.location_x as usize, status.location_y as usize))
+		.collect();
+
+	for y in 0 .. BOARD_SCREEN_HEIGHT {
+		for x in 0 .. BOARD_SCREEN_WIDTH {
+			// Status element locations are 1-based.
+			if status_tiles.contains(&(x + 1, y + 1)) {
+				continue;
+			}
+			board.tiles[y * BOARD_SCREEN_WIDTH + x] = match rows.get(y) {
+				Some(row) => console_char_to_tile(row[x]),
+				None => BoardTile::new(ElementType::Empty, 0),
+			};
+		}
+	}
+}
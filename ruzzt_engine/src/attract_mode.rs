@@ -0,0 +1,104 @@
+use crate::compatibility::CompatibilityFixture;
+use crate::event::Event;
+
+use zzt_file_format::World;
+
+/// The number of steps the title screen has to be left alone before the demo starts by default.
+/// This is about 30 seconds at the normal game speed.
+pub const DEFAULT_ATTRACT_IDLE_STEPS: usize = 280;
+
+/// The file extension of the demo played in the title screen of a world, which is a compatibility
+/// fixture (see `CompatibilityFixture`) next to the world file with the same name.
+pub const ATTRACT_DEMO_FILE_EXTENSION: &str = "DEM";
+
+/// What the engine should do in a step of the title screen or the demo, decided by
+/// `AttractMode::title_step` and `AttractMode::demo_step`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttractAction {
+	/// Carry on as normal with the given event.
+	Continue(Event),
+	/// The title screen has been left alone for long enough, so the demo should start.
+	StartDemo,
+	/// The demo has finished or a key was pressed, so the title screen should come back.
+	StopDemo,
+}
+
+/// Plays a recorded demo in the title screen when it is left alone, like the attract mode of an
+/// arcade game, and goes back to the title screen when any key is pressed. See
+/// `RuzztEngine::attract_mode`.
+#[derive(Debug, Clone)]
+pub struct AttractMode {
+	/// The demo that is played. The game starts the same way it does after pressing P in the title
+	/// screen, and each step of the demo is one game cycle.
+	demo: CompatibilityFixture,
+	/// The number of steps the title screen has to be left alone before the demo starts.
+	pub idle_steps_before_demo: usize,
+	/// The number of steps since the last event in the title screen.
+	idle_steps: usize,
+	/// The world as it was in the title screen when the demo started, so it can be put back when
+	/// the demo stops. This is None when the demo isn't playing.
+	title_world: Option<World>,
+	/// The number of steps of the demo that have been played so far.
+	steps_played: usize,
+}
+
+impl AttractMode {
+	/// Make an attract mode that plays `demo` after the title screen has been left alone for
+	/// `idle_steps_before_demo` steps.
+	pub fn new(demo: CompatibilityFixture, idle_steps_before_demo: usize) -> AttractMode {
+		AttractMode {
+			demo,
+			idle_steps_before_demo,
+			idle_steps: 0,
+			title_world: None,
+			steps_played: 0,
+		}
+	}
+
+	/// True while the demo is being played.
+	pub fn is_playing(&self) -> bool {
+		self.title_world.is_some()
+	}
+
+	/// Count a step of the title screen with the given `event`.
+	pub fn title_step(&mut self, event: Event) -> AttractAction {
+		if event != Event::None {
+			self.idle_steps = 0;
+			return AttractAction::Continue(event);
+		}
+		self.idle_steps += 1;
+		if self.idle_steps >= self.idle_steps_before_demo && !self.demo.steps.is_empty() {
+			self.idle_steps = 0;
+			AttractAction::StartDemo
+		} else {
+			AttractAction::Continue(event)
+		}
+	}
+
+	/// Start playing the demo. `title_world` is put back when it stops (see `stop`).
+	pub fn start(&mut self, title_world: World) {
+		self.title_world = Some(title_world);
+		self.steps_played = 0;
+	}
+
+	/// Get the event to play in the next game cycle of the demo, given the `event` the player
+	/// pressed. Any event stops the demo, as does reaching the end of it.
+	pub fn demo_step(&mut self, event: Event) -> AttractAction {
+		if event != Event::None {
+			return AttractAction::StopDemo;
+		}
+		match self.demo.steps.get(self.steps_played) {
+			Some(step) => {
+				self.steps_played += 1;
+				AttractAction::Continue(step.event)
+			}
+			None => AttractAction::StopDemo,
+		}
+	}
+
+	/// Stop playing the demo, and get back the world from when it started.
+	pub fn stop(&mut self) -> Option<World> {
+		self.idle_steps = 0;
+		self.title_world.take()
+	}
+}
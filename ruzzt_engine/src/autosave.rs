@@ -0,0 +1,109 @@
+use zzt_file_format::World;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// The name of the file the latest autosave is written to.
+pub const AUTOSAVE_FILE_NAME: &str = "AUTOSAVE.SAV";
+/// The name of the file the autosave before the latest one is kept in, so there is still a good
+/// autosave if the game crashes while the latest one is being written.
+pub const PREVIOUS_AUTOSAVE_FILE_NAME: &str = "AUTOSAVE.BAK";
+/// The fewest seconds between two autosaves, so running back and forth between boards doesn't
+/// write the world over and over.
+const MIN_SECONDS_BETWEEN_AUTOSAVES: f64 = 10.;
+
+/// How often the game is autosaved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutosaveOptions {
+	/// Autosave after this many boards have been entered since the last autosave. 0 means the
+	/// number of boards entered doesn't matter.
+	pub boards_between_autosaves: usize,
+	/// Autosave after this many minutes have passed since the last autosave. 0 means the time
+	/// doesn't matter.
+	pub minutes_between_autosaves: f64,
+}
+
+/// Writes the game in progress to `AUTOSAVE_FILE_NAME` in a directory every so often (see
+/// `AutosaveOptions`), so it can be resumed if RUZZT crashes. The autosave is a saved game file,
+/// so it can also be loaded like any other saved game. Front-ends should call `remove_files` when
+/// RUZZT exits normally, so an autosave being there at startup means the last run didn't.
+#[derive(Debug, Clone)]
+pub struct Autosave {
+	/// The directory the autosave files are written to.
+	pub directory: PathBuf,
+	/// How often the game is autosaved.
+	pub options: AutosaveOptions,
+	/// The number of boards entered since the last autosave.
+	boards_entered: usize,
+	/// The wall-clock time (as given to `RuzztEngine::step`) of the last autosave, or None if the
+	/// game hasn't stepped since autosaving started.
+	last_autosave_seconds: Option<f64>,
+}
+
+impl Autosave {
+	/// Start autosaving to `directory` as often as `options` says.
+	pub fn new(directory: PathBuf, options: AutosaveOptions) -> Autosave {
+		Autosave {
+			directory,
+			options,
+			boards_entered: 0,
+			last_autosave_seconds: None,
+		}
+	}
+
+	/// Get the path of the latest autosave within `directory`.
+	pub fn file_path(directory: &Path) -> PathBuf {
+		directory.join(AUTOSAVE_FILE_NAME)
+	}
+
+	/// Get the path of the latest autosave in `directory` if there is one.
+	pub fn find(directory: &Path) -> Option<PathBuf> {
+		let file_path = Autosave::file_path(directory);
+		if file_path.is_file() {
+			Some(file_path)
+		} else {
+			None
+		}
+	}
+
+	/// Remove the autosave files from `directory`, if there are any.
+	pub fn remove_files(directory: &Path) {
+		std::fs::remove_file(Autosave::file_path(directory)).ok();
+		std::fs::remove_file(directory.join(PREVIOUS_AUTOSAVE_FILE_NAME)).ok();
+	}
+
+	/// Count a board being entered towards `boards_between_autosaves`.
+	pub fn board_entered(&mut self) {
+		self.boards_entered += 1;
+	}
+
+	/// True if enough boards have been entered or enough time has passed to autosave at
+	/// `time_seconds`. The first call only starts the clock.
+	pub fn is_due(&mut self, time_seconds: f64) -> bool {
+		let last_autosave_seconds = *self.last_autosave_seconds.get_or_insert(time_seconds);
+		let seconds_since_autosave = time_seconds - last_autosave_seconds;
+		if seconds_since_autosave < MIN_SECONDS_BETWEEN_AUTOSAVES {
+			return false;
+		}
+
+		let boards_due = self.options.boards_between_autosaves > 0 && self.boards_entered >= self.options.boards_between_autosaves;
+		let time_due = self.options.minutes_between_autosaves > 0. && seconds_since_autosave >= self.options.minutes_between_autosaves * 60.;
+		boards_due || time_due
+	}
+
+	/// Write `world` to the autosave file, keeping the previous autosave as
+	/// `PREVIOUS_AUTOSAVE_FILE_NAME`, and start counting towards the next autosave from
+	/// `time_seconds`.
+	pub fn write(&mut self, world: &World, time_seconds: f64) -> Result<(), String> {
+		self.boards_entered = 0;
+		self.last_autosave_seconds = Some(time_seconds);
+
+		let file_path = Autosave::file_path(&self.directory);
+		if file_path.is_file() {
+			let previous_path = self.directory.join(PREVIOUS_AUTOSAVE_FILE_NAME);
+			std::fs::rename(&file_path, &previous_path).map_err(|e| format!("Couldn't move {} to {}: {}", file_path.display(), previous_path.display(), e))?;
+		}
+		let mut file = File::create(&file_path).map_err(|e| format!("Couldn't create {}: {}", file_path.display(), e))?;
+		world.write(&mut file).map_err(|e| format!("Couldn't write {}: {}", file_path.display(), e))
+	}
+}
@@ -1,14 +1,19 @@
 use crate::board_message::*;
 use crate::board_simulator::*;
+use crate::constants::*;
 use crate::event::*;
 use crate::sounds::*;
 
 use zzt_file_format::*;
 use zzt_file_format::dosstring::DosString;
+use zzt_file_format::extended_colour::ExtendedColours;
+use serde_derive::{Serialize, Deserialize};
 use std::fmt::Debug;
 
-/// This is a description of one mutating operation to perform on the BoardSimulator.
-#[derive(Debug, Clone)]
+/// This is a description of one mutating operation to perform on the BoardSimulator. Actions can be
+/// serialized (eg. to record a trace of what happened on a board), so changing the existing
+/// variants breaks recordings that were already made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Action {
 	/// Set just the tile `colour` of the tile at `x` by `y`.
 	SetColour {
@@ -120,14 +125,14 @@ pub enum Action {
 	/// informed of this failure to take an item.
 	ModifyPlayerItem{
 		item_type: PlayerItemType,
-		offset: i16,
+		offset: i32,
 		/// When this is true, if the offset is going to take more than the current value of the
 		/// item, then it will fail to do so, and respond with `Response::TakePlayerItemFailed`.
 		/// Note that values will never go below zero. This will only matter for negative offsets.
 		require_exact_amount: bool,
 	},
 	/// Give (when `value` is true) or take (when `value` is false) the key with the given key
-	// `index` from the player.
+	/// `index` from the player. See `BoardSimulator::key_index_for_colour` for what the index means.
 	ModifyPlayerKeys{
 		index: u8,
 		value: bool,
@@ -165,17 +170,27 @@ pub enum Action {
 	/// Set the cycle of the given status index (the number of game steps between each
 	/// time the status is processed).
 	SetCycle{status_index: usize, cycle: i16},
+	/// Set the player's health to 0, ending the game, the same as `#endgame`. Unlike hurting the
+	/// player, this doesn't update the health shown in the side bar (see
+	/// `BoardSimulator::side_bar_health`).
+	EndGame,
+	/// Set the `extended_colours` of the status with the given `status_index`, or turn them off
+	/// when `extended_colours` is None. This is only applied when `#colour` is enabled (see
+	/// `SimulatorSettings::extended_colours_enabled`).
+	SetExtendedColours{status_index: usize, extended_colours: Option<ExtendedColours>},
 }
 
 /// Player items are all integers that can be added to or subtracted from. This enum describes one
 /// of those items.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PlayerItemType {
 	Ammo,
 	Torches,
 	Gems,
 	Health,
 	Score,
+	/// Only exists in Super ZZT worlds.
+	Stones,
 	// NOTE: Modifying the time item actually modifies time_passed, so the OOP actually
 	// negates the argument before trying to modify the time.
 	Time,
@@ -190,6 +205,7 @@ impl PlayerItemType {
 			PlayerItemType::Gems => Some(world_header.player_gems),
 			PlayerItemType::Health => Some(world_header.player_health),
 			PlayerItemType::Score => Some(world_header.player_score),
+			PlayerItemType::Stones => world_header.player_stones,
 			PlayerItemType::Time => Some(world_header.time_passed),
 		}
 	}
@@ -203,13 +219,14 @@ impl PlayerItemType {
 			PlayerItemType::Gems => Some(&mut world_header.player_gems),
 			PlayerItemType::Health => Some(&mut world_header.player_health),
 			PlayerItemType::Score => Some(&mut world_header.player_score),
+			PlayerItemType::Stones => world_header.player_stones.as_mut(),
 			PlayerItemType::Time => Some(&mut world_header.time_passed),
 		}
 	}
 }
 
 /// The particular operation to perform when working with object labels.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum LabelOperation {
 	/// Jump to a given label.
 	Jump,
@@ -233,9 +250,44 @@ pub enum BlockedStatus {
 	NotBlocked,
 }
 
+/// Who fired a bullet or star. Like DOS ZZT, this is kept in the shot's param1 (see
+/// `from_status`), so it stays with the shot when it ricochets, gets pushed or duplicated, or the
+/// world is saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShotSource {
+	Player,
+	/// Anything other than the player, eg. an object or a tiger.
+	Enemy,
+}
+
+impl ShotSource {
+	/// Get the source of a shot from its `param1`. 0 means the player fired it, and anything else
+	/// means something else did, the same as DOS ZZT.
+	pub fn from_param1(param1: u8) -> ShotSource {
+		if param1 == SHOT_SOURCE_PLAYER_PARAM1 {
+			ShotSource::Player
+		} else {
+			ShotSource::Enemy
+		}
+	}
+
+	/// Get who fired the bullet or star with the given `status`.
+	pub fn from_status(status: &StatusElement) -> ShotSource {
+		ShotSource::from_param1(status.param1)
+	}
+
+	/// Get the `param1` that a shot fired by this source starts with.
+	pub fn param1(self) -> u8 {
+		match self {
+			ShotSource::Player => SHOT_SOURCE_PLAYER_PARAM1,
+			ShotSource::Enemy => SHOT_SOURCE_ENEMY_PARAM1,
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum DamageType {
-	Shot{by_player: bool},
+	Shot{source: ShotSource},
 	Bombed,
 	Other,
 }
@@ -402,7 +454,7 @@ pub fn default_damage_impl(is_destructable: bool, x: i16, y: i16, damage_type: D
 	let should_die = match damage_type {
 		DamageType::Shot{..} => {
 			if is_destructable {
-				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"t-c"), SoundPriority::Level(2))));
+				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"t-c"), SoundPriority::Level(2))));
 			}
 			is_destructable
 		}
@@ -437,6 +489,13 @@ pub fn default_damage_impl(is_destructable: bool, x: i16, y: i16, damage_type: D
 	}
 }
 
+/// Get which of the 4 frames of an animation to show for a status with the given `cycle` at
+/// `global_cycle`. The animation moves on a frame every time the status cycles.
+pub fn animation_frame(global_cycle: usize, cycle: i16) -> usize {
+	let cycle = cycle.max(1) as usize;
+	(global_cycle % (4 * cycle)) / cycle
+}
+
 /// A description of the Behaviour of a particular element type.
 pub trait Behaviour: Debug {
 	/// Called every time a status element cycles.
@@ -447,6 +506,13 @@ pub trait Behaviour: Debug {
 		}
 	}
 
+	/// The character that a tile of this type with the given `status` is drawn with, at
+	/// `global_cycle`, eg. for animations and objects that choose their own character. None means
+	/// the tile is drawn with the usual character for its type.
+	fn status_char_code(&self, _status: &StatusElement, _global_cycle: usize) -> Option<u8> {
+		None
+	}
+
 	/// This is called when applying certain movement actions to try and push something out of the
 	/// way so that another thing can move there. See `PushResult`.
 	/// `x` and `y` are the location of the tile being pushed.
@@ -464,8 +530,8 @@ pub trait Behaviour: Debug {
 		BlockedStatus::Blocked
 	}
 
-	/// Whether this type blocks the path of bullets.
-	fn blocked_for_bullets(&self) -> BlockedStatus {
+	/// Whether this type blocks the path of bullets (and stars).
+	fn blocked_for_bullets(&self, _sim: &BoardSimulator) -> BlockedStatus {
 		self.blocked(false)
 	}
 
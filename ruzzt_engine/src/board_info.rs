@@ -0,0 +1,137 @@
+use zzt_file_format::{Board, BoardMetaData};
+use zzt_file_format::dosstring::DosString;
+
+/// The board settings shown in the board information panel, in the order they are shown. This is
+/// the same information as DOS ZZT's board information screen in the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardInfoField {
+	Name,
+	MaxShots,
+	IsDark,
+	ExitNorth,
+	ExitSouth,
+	ExitWest,
+	ExitEast,
+	RestartOnZap,
+	TimeLimit,
+}
+
+impl BoardInfoField {
+	/// Every field, in the order they are shown.
+	pub fn all() -> [BoardInfoField; 9] {
+		[
+			BoardInfoField::Name,
+			BoardInfoField::MaxShots,
+			BoardInfoField::IsDark,
+			BoardInfoField::ExitNorth,
+			BoardInfoField::ExitSouth,
+			BoardInfoField::ExitWest,
+			BoardInfoField::ExitEast,
+			BoardInfoField::RestartOnZap,
+			BoardInfoField::TimeLimit,
+		]
+	}
+
+	/// Get the field with the given `name` (see `name`), or None if there isn't one.
+	pub fn from_name(name: &str) -> Option<BoardInfoField> {
+		BoardInfoField::all().iter().copied().find(|field| field.name() == name)
+	}
+
+	/// The name of the field, used to refer to it from outside of the engine (eg. in the web
+	/// editor).
+	pub fn name(self) -> &'static str {
+		match self {
+			BoardInfoField::Name => "name",
+			BoardInfoField::MaxShots => "max_shots",
+			BoardInfoField::IsDark => "dark",
+			BoardInfoField::ExitNorth => "exit_north",
+			BoardInfoField::ExitSouth => "exit_south",
+			BoardInfoField::ExitWest => "exit_west",
+			BoardInfoField::ExitEast => "exit_east",
+			BoardInfoField::RestartOnZap => "restart_on_zap",
+			BoardInfoField::TimeLimit => "time_limit",
+		}
+	}
+
+	/// The label shown next to the value in the panel.
+	pub fn label(self) -> &'static str {
+		match self {
+			BoardInfoField::Name => "Title",
+			BoardInfoField::MaxShots => "Can fire",
+			BoardInfoField::IsDark => "Board is dark",
+			BoardInfoField::ExitNorth => "Board north",
+			BoardInfoField::ExitSouth => "Board south",
+			BoardInfoField::ExitWest => "Board west",
+			BoardInfoField::ExitEast => "Board east",
+			BoardInfoField::RestartOnZap => "Re-enter when zapped",
+			BoardInfoField::TimeLimit => "Time limit",
+		}
+	}
+
+	/// Get the text shown for the value of the field in `meta_data`. `boards` is used to show the
+	/// names of the boards that exits lead to.
+	pub fn value_text(self, meta_data: &BoardMetaData, boards: &[Board]) -> DosString {
+		let exit_text = |exit: u8| {
+			match boards.get(exit as usize) {
+				Some(board) if exit != 0 => board.meta_data.board_name.clone(),
+				_ => DosString::from_slice(b"None"),
+			}
+		};
+		let yes_no = |value: bool| DosString::from_slice(if value { b"Yes" } else { b"No" });
+
+		match self {
+			BoardInfoField::Name => meta_data.board_name.clone(),
+			BoardInfoField::MaxShots => DosString::from_str(&format!("{} shots", meta_data.max_player_shots)),
+			BoardInfoField::IsDark => yes_no(meta_data.is_dark),
+			BoardInfoField::ExitNorth => exit_text(meta_data.exit_north),
+			BoardInfoField::ExitSouth => exit_text(meta_data.exit_south),
+			BoardInfoField::ExitWest => exit_text(meta_data.exit_west),
+			BoardInfoField::ExitEast => exit_text(meta_data.exit_east),
+			BoardInfoField::RestartOnZap => yes_no(meta_data.restart_on_zap),
+			BoardInfoField::TimeLimit => {
+				if meta_data.time_limit > 0 {
+					DosString::from_str(&format!("{} sec", meta_data.time_limit))
+				} else {
+					DosString::from_slice(b"None")
+				}
+			}
+		}
+	}
+
+	/// Set the field in `meta_data` to the value described by `value`. Numbers are written in
+	/// decimal, exits are board indices (0 for no exit), and flags are "yes" or "no".
+	pub fn set_value(self, meta_data: &mut BoardMetaData, value: &str) -> Result<(), String> {
+		let value = value.trim();
+		let parse_number = |value: &str| value.parse::<u8>().map_err(|e| format!("Invalid {}: {}", self.name(), e));
+		let parse_flag = |value: &str| match value.to_lowercase().as_str() {
+			"yes" | "true" | "1" => Ok(true),
+			"no" | "false" | "0" => Ok(false),
+			_ => Err(format!("Invalid {}: expected yes or no", self.name())),
+		};
+
+		match self {
+			BoardInfoField::Name => meta_data.board_name = DosString::from_str(value),
+			BoardInfoField::MaxShots => meta_data.max_player_shots = parse_number(value)?,
+			BoardInfoField::IsDark => meta_data.is_dark = parse_flag(value)?,
+			BoardInfoField::ExitNorth => meta_data.exit_north = parse_number(value)?,
+			BoardInfoField::ExitSouth => meta_data.exit_south = parse_number(value)?,
+			BoardInfoField::ExitWest => meta_data.exit_west = parse_number(value)?,
+			BoardInfoField::ExitEast => meta_data.exit_east = parse_number(value)?,
+			BoardInfoField::RestartOnZap => meta_data.restart_on_zap = parse_flag(value)?,
+			BoardInfoField::TimeLimit => {
+				meta_data.time_limit = value.parse::<i16>().map_err(|e| format!("Invalid {}: {}", self.name(), e))?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Get the lines of the board information panel for the board with the given `meta_data`, to be
+/// shown in a scroll. `boards` is used to show the names of the boards that exits lead to.
+pub fn board_info_lines(meta_data: &BoardMetaData, boards: &[Board]) -> Vec<DosString> {
+	BoardInfoField::all().iter().map(|field| {
+		let mut line = DosString::from_str(&format!("{}: ", field.label()));
+		line += &field.value_text(meta_data, boards).data;
+		line
+	}).collect()
+}
@@ -0,0 +1,52 @@
+use crate::behaviour::Action;
+use crate::board_simulator::*;
+
+use zzt_file_format::{CodeSource, ElementType};
+
+/// Find everything wrong with the board in `sim` that the simulation should never cause, and
+/// return a description of each problem. This is empty if the board is fine. The problems are:
+///
+/// - The player's status isn't on a player tile (see `BoardSimulator::player_is_misplaced`).
+/// - A status is outside of the board, including its border of `BoardEdge` tiles.
+/// - A status is bound to the code of a status that doesn't exist.
+/// - A status is on top of a player tile.
+///
+/// Boards from world files can already have these problems, so this is only useful for checking
+/// that simulating a board that starts out fine keeps it that way.
+pub fn find_invariant_violations(sim: &BoardSimulator) -> Vec<String> {
+	let mut violations = vec![];
+
+	if sim.player_is_misplaced() {
+		let player_status = &sim.status_elements[0];
+		let player_tile = sim.get_tile(player_status.location_x as i16, player_status.location_y as i16);
+		violations.push(format!("Player status at {}x{} is on {:?}, not a player", player_status.location_x, player_status.location_y, player_tile));
+	}
+
+	for (status_index, status) in sim.status_elements.iter().enumerate() {
+		if status.location_x as usize >= BOARD_WIDTH || status.location_y as usize >= BOARD_HEIGHT {
+			violations.push(format!("Status {} is outside of the board at {}x{}", status_index, status.location_x, status.location_y));
+		}
+
+		if let CodeSource::Bound(bound_index) = status.code_source {
+			if bound_index >= sim.status_elements.len() {
+				violations.push(format!("Status {} is bound to status {}, which doesn't exist", status_index, bound_index));
+			}
+		}
+
+		if status.under_element_id == ElementType::Player as u8 {
+			violations.push(format!("Status {} at {}x{} is on top of a player", status_index, status.location_x, status.location_y));
+		}
+	}
+
+	violations
+}
+
+/// Panic if the board in `sim` has any of the problems from `find_invariant_violations` after
+/// `action` was applied to it. This is called after every action when the `check-invariants`
+/// feature is turned on, so the action that broke the board is the one in the message.
+pub fn check_invariants_after_action(sim: &BoardSimulator, action: &Action) {
+	let violations = find_invariant_violations(sim);
+	if !violations.is_empty() {
+		panic!("Board invariants broken after applying {:?}:\n{}", action, violations.join("\n"));
+	}
+}
@@ -0,0 +1,178 @@
+use crate::direction::Direction;
+
+use zzt_file_format::{BoardMetaData, World};
+
+/// The directions that boards can exit in, in the order their exits are stored in
+/// `BoardMetaData`.
+pub(crate) const EXIT_DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::West, Direction::East];
+
+/// Get the exit of `meta_data` in the given `direction`. 0 means there is no exit.
+pub fn get_exit(meta_data: &BoardMetaData, direction: Direction) -> u8 {
+	match direction {
+		Direction::North => meta_data.exit_north,
+		Direction::South => meta_data.exit_south,
+		Direction::West => meta_data.exit_west,
+		Direction::East => meta_data.exit_east,
+		Direction::Idle => 0,
+	}
+}
+
+/// Set the exit of `meta_data` in the given `direction` to `board_index`.
+pub fn set_exit(meta_data: &mut BoardMetaData, direction: Direction, board_index: u8) {
+	match direction {
+		Direction::North => meta_data.exit_north = board_index,
+		Direction::South => meta_data.exit_south = board_index,
+		Direction::West => meta_data.exit_west = board_index,
+		Direction::East => meta_data.exit_east = board_index,
+		Direction::Idle => {}
+	}
+}
+
+/// An arrangement of boards in a grid, where walking off the edge of a board leads to the board
+/// next to it in the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardLayout {
+	/// The board index in each cell of the grid, by row then column. None is a gap in the grid.
+	pub rows: Vec<Vec<Option<u8>>>,
+}
+
+impl BoardLayout {
+	/// Arrange `board_count` boards, starting at `first_board`, in rows of `columns` boards, in
+	/// order from left to right and top to bottom.
+	pub fn grid(first_board: u8, columns: usize, board_count: usize) -> BoardLayout {
+		let board_indices: Vec<Option<u8>> = (0 .. board_count).map(|offset| Some(first_board + offset as u8)).collect();
+		BoardLayout {
+			rows: board_indices.chunks(columns.max(1)).map(|row| row.to_vec()).collect(),
+		}
+	}
+
+	/// Read a layout file. Each line is a row of the grid, with board numbers separated by spaces,
+	/// and `.` for a gap. Blank lines and everything after a `#` are ignored, eg:
+	///
+	/// ```text
+	/// # The town is in the middle.
+	/// .  1  .
+	/// 2  3  4
+	/// ```
+	pub fn parse(text: &str) -> Result<BoardLayout, String> {
+		let mut rows = vec![];
+		for (line_index, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("");
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			let row = line.split_whitespace().map(|cell| {
+				if cell == "." {
+					Ok(None)
+				} else {
+					cell.parse().map(Some).map_err(|_| format!("Line {}: invalid board number: {}", line_index + 1, cell))
+				}
+			}).collect::<Result<Vec<_>, String>>()?;
+			rows.push(row);
+		}
+		Ok(BoardLayout {
+			rows,
+		})
+	}
+
+	/// Get the board index at column `x` of row `y`, or None if it's a gap or outside the grid.
+	pub fn get(&self, x: isize, y: isize) -> Option<u8> {
+		if x < 0 || y < 0 {
+			return None;
+		}
+		self.rows.get(y as usize).and_then(|row| row.get(x as usize)).cloned().flatten()
+	}
+}
+
+/// Set the exits of every board in `layout` so they lead to the boards next to them in the grid.
+/// Exits that lead off the edge of the grid (or into a gap) are removed. Boards that aren't in the
+/// layout are left alone.
+///
+/// The title board (0) can't be in the layout because an exit to board 0 means there is no exit,
+/// and each board can only be in the layout once.
+pub fn link_exits(world: &mut World, layout: &BoardLayout) -> Result<(), String> {
+	let mut seen_boards = vec![];
+	for &board_index in layout.rows.iter().flatten().flatten() {
+		if board_index == 0 {
+			return Err("The title board can't be linked to other boards".to_string());
+		}
+		if board_index as usize >= world.boards.len() {
+			return Err(format!("There is no board {}", board_index));
+		}
+		if seen_boards.contains(&board_index) {
+			return Err(format!("Board {} is in the layout more than once", board_index));
+		}
+		seen_boards.push(board_index);
+	}
+
+	for (y, row) in layout.rows.iter().enumerate() {
+		for (x, &board_index) in row.iter().enumerate() {
+			if let Some(board_index) = board_index {
+				let meta_data = &mut world.boards[board_index as usize].meta_data;
+				for &direction in &EXIT_DIRECTIONS {
+					let (offset_x, offset_y) = direction.to_offset();
+					let neighbour = layout.get(x as isize + offset_x as isize, y as isize + offset_y as isize);
+					set_exit(meta_data, direction, neighbour.unwrap_or(0));
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// An exit that doesn't lead back the way it came: going through it and then straight back out
+/// the opposite edge doesn't return to the board it started on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExitMismatch {
+	/// The board the exit is on.
+	pub board_index: u8,
+	/// The edge of the board the exit is on.
+	pub direction: Direction,
+	/// The board the exit leads to.
+	pub destination: u8,
+	/// The exit on the opposite edge of the destination board, or None if the destination board
+	/// doesn't exist.
+	pub return_exit: Option<u8>,
+}
+
+impl ExitMismatch {
+	/// Describe the mismatch, eg. "Board 1 east exit leads to board 2, but its west exit leads to
+	/// board 3".
+	pub fn description(&self) -> String {
+		let direction_name = format!("{:?}", self.direction).to_lowercase();
+		let opposite_name = format!("{:?}", self.direction.opposite()).to_lowercase();
+		match self.return_exit {
+			None => format!("Board {} {} exit leads to board {}, which doesn't exist", self.board_index, direction_name, self.destination),
+			Some(0) => format!("Board {} {} exit leads to board {}, but it has no {} exit", self.board_index, direction_name, self.destination, opposite_name),
+			Some(return_exit) => format!("Board {} {} exit leads to board {}, but its {} exit leads to board {}",
+				self.board_index, direction_name, self.destination, opposite_name, return_exit),
+		}
+	}
+}
+
+/// Find every exit in `world` that doesn't lead back the way it came (see `ExitMismatch`), in
+/// board order. One-way exits are sometimes intended, so these aren't necessarily mistakes.
+pub fn check_exit_reciprocity(world: &World) -> Vec<ExitMismatch> {
+	let mut mismatches = vec![];
+	for (board_index, board) in world.boards.iter().enumerate() {
+		for &direction in &EXIT_DIRECTIONS {
+			let destination = get_exit(&board.meta_data, direction);
+			if destination == 0 {
+				continue;
+			}
+
+			let return_exit = world.boards.get(destination as usize)
+				.map(|destination_board| get_exit(&destination_board.meta_data, direction.opposite()));
+			if return_exit != Some(board_index as u8) {
+				mismatches.push(ExitMismatch {
+					board_index: board_index as u8,
+					direction,
+					destination,
+					return_exit,
+				});
+			}
+		}
+	}
+	mismatches
+}
@@ -2,10 +2,15 @@ use crate::direction::*;
 use crate::sounds::*;
 use zzt_file_format::dosstring::DosString;
 
+use serde_derive::{Serialize, Deserialize};
+
+use std::path::PathBuf;
+
 /// Board messages will be applied after the current status is finished being processed. They are
 /// sent all the way out to the front-end, which are then intercepted and/or passed back into
-/// `ZztEngine::process_board_message`.
-#[derive(Debug, Clone)]
+/// `ZztEngine::process_board_message`. Like `Action`s, board messages can be serialized, so changing
+/// the existing variants breaks recordings that were already made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BoardMessage {
 	/// The board should be switched to the board with the given `new_board_index`, when the player
 	/// walked off the side of the board in the given `direction`.
@@ -31,6 +36,10 @@ pub enum BoardMessage {
 	},
 	/// Any open scroll should be closed.
 	CloseScroll,
+	/// A scroll showing the map of the boards the player has visited should be opened.
+	OpenWorldMap,
+	/// A scroll showing the recent captions and scrolls (see `MessageLog`) should be opened.
+	OpenMessageLog,
 	/// Enter was pressed while a scroll was open, on the line given by `line_index`.
 	EnterPressedInScroll{line_index: usize},
 	/// The sounds in the given array should be played from the system speaker.
@@ -39,12 +48,21 @@ pub enum BoardMessage {
 	PlaySoundArray(Vec<SoundEntry>, SoundPriority),
 	/// If there is any sound playing, this should clear it.
 	ClearPlayingSound,
+	/// The given sound should be repeated in the background whenever nothing else is playing,
+	/// replacing any previous ambient sound. An empty list means there should be no ambient sound.
+	/// This is only sent for boards with music in `RuzztEngine::board_ambient_music`. Front-ends
+	/// can pass it to `SoundQueue::set_ambient_loop`.
+	SetAmbientMusic(Vec<SoundEntry>),
 	/// An input for entering a filename to save to should be shown.
 	OpenSaveGameInput,
 	/// The current state of the game world should be saved to a file with the given name.
 	SaveGameToFile(DosString),
 	/// The debug command line input should be shown.
 	OpenDebugInput,
+	/// A scroll listing every board in the world should be opened, to pick a board to warp to.
+	OpenBoardList,
+	/// The player should be moved to the enter position of the board with the given `board_index`.
+	WarpToBoard{board_index: usize},
 	/// The given debug command should be applied. (eg. `zap`, `health` etc.).
 	DebugCommand(DosString),
 	/// A scroll was open and a link was clicked within the scroll with the given destination text.
@@ -56,23 +74,192 @@ pub enum BoardMessage {
 	PlayGame,
 	/// Should open the scroll with all the .ZZT files.
 	OpenWorldSelection,
+	/// Should open the scroll with information about RUZZT (see `engine::about_scroll_lines`).
+	OpenAbout,
 	/// Should open the scroll with all the .SAV files.
 	OpenSaveSelection,
 	/// Should load the world with the given name, and load it into the engine.
 	OpenWorld{filename: DosString},
-	/// The input to end the current game should be shown.
+	/// The world with the given `filename` couldn't be loaded because of `error`. The engine goes
+	/// back to the title screen and shows the error in a scroll.
+	WorldLoadFailed{filename: DosString, error: String},
+	/// Any open world browser should be closed.
+	CloseWorldBrowser,
+	/// Enter was pressed in the world browser while the entry at `entry_index` was highlighted.
+	WorldBrowserEntrySelected{entry_index: usize},
+	/// The in-game pause menu should be opened.
+	OpenPauseMenu,
+	/// Any open pause menu should be closed.
+	ClosePauseMenu,
+	/// Should show the front-end's settings.
+	OpenSettings,
+	/// The game speed slider should be shown in the side bar.
+	OpenGameSpeedInput,
+	/// The game speed was picked in the side bar, from fastest (0) to slowest (8). Front-ends
+	/// should use `RuzztEngine::game_speed` to decide how long each step takes, and can save this
+	/// so it is remembered next time.
+	SetGameSpeed(u8),
+	/// The keyboard config slider should be shown in the side bar.
+	OpenKeyboardConfigInput,
+	/// The number of steps a key has to be held before it starts repeating was picked in the side
+	/// bar. Front-ends can save this so it is remembered next time.
+	SetKeyRepeatDelay(u8),
+	/// The stuck watchdog found that the player appears to have no way to make progress on the
+	/// current board (see `RuzztEngine::stuck_watchdog_enabled`). Front-ends can use this to give
+	/// the player a hint.
+	PlayerAppearsStuck,
+	/// Something went wrong in a way that DOS ZZT would silently ignore, but that world authors
+	/// might want to know about. Front-ends can show or print these.
+	EngineLog(LogLevel, String),
+	/// The input to end the current game should be shown. This is what Escape and Q do while
+	/// playing, like "End this game?" in DOS ZZT.
 	OpenEndGameConfirmation,
-	/// The input to quit RUZZT should be shown.
+	/// The input to quit RUZZT should be shown. This is what Escape and Q do in the title screen,
+	/// like "Quit ZZT?" in DOS ZZT.
 	OpenQuitConfirmation,
 	/// Should return to the title screen.
 	ReturnToTitleScreen,
 	/// Should stop running altogether.
 	Quit,
+	/// An input for naming a new world should be shown.
+	OpenNewWorldInput,
+	/// A new, empty world with the given `name` should be loaded, and saved to `NAME.ZZT`.
+	CreateWorld{name: DosString},
+	/// A text editor for the code of the status at `status_index` should be shown.
+	OpenStatusCodeEditor{status_index: usize},
+	/// A text editor for typing several debug commands at once should be shown.
+	OpenDebugConsole,
+	/// The code of the status at `status_index` should be replaced with `code`, and it should start
+	/// running from the top.
+	SetStatusCode{status_index: usize, code: DosString},
+	/// The text editor should be closed.
+	CloseTextEditor,
+	/// A savestate that included the sound queue was loaded, so `RuzztEngine::sound_queue` was
+	/// replaced with this one. Front-ends should cut off whatever they were playing and start
+	/// playing the queue's `playing_entry`.
+	RestoreSoundQueue(SoundQueue),
+	/// A file was picked in the file browser opened with `RuzztEngine::open_file_browser`, so it
+	/// should be loaded.
+	FileBrowserFileSelected{file_path: PathBuf},
+	/// The player chose to resume the autosave left behind by a run of RUZZT that didn't exit
+	/// normally (see `RuzztEngine::offer_autosave_resume`), so it should be loaded like a saved game.
+	ResumeAutosave,
+	/// The current game should end: the engine returns to the title screen and sends `OpenWorld`
+	/// so the world is loaded again from its file, throwing away the game in progress. This is
+	/// different to `Quit`, which stops RUZZT.
+	EndGame,
+	/// The scroll listing the world's high scores (see `RuzztEngine::high_scores`) should be
+	/// shown. This is what H does in the title screen.
+	OpenHighScores,
+	/// Enter was pressed on the line given by `line_index` in the scroll opened by the `flags`
+	/// debug command, so that flag should be toggled and the list shown again.
+	ToggleListedFlag{line_index: usize},
+	/// The world should be written to a file named after it (eg. `TOWN.ZZT`) and no longer count
+	/// as having unsaved changes (see `RuzztEngine::is_dirty`).
+	SaveWorld,
+	/// The unsaved changes to the world should be forgotten about, so the message that was held
+	/// back until they were saved can go ahead (see `RuzztEngine::is_dirty`).
+	DiscardWorldChanges,
+	/// A screenshot of the whole screen should be saved to `path`, in the format given by its
+	/// extension (see `screenshot::ScreenshotFormat`). The engine saves ANSI art and text
+	/// screenshots itself, but front-ends have to save images (eg. `.PNG`) because the engine can't
+	/// draw them.
+	SaveScreenshot{path: PathBuf},
+	/// The player bumped into a passage locked with a password (see `PassageLock`), so an input
+	/// for typing the password should be shown.
+	OpenPassagePasswordInput{status_index: usize},
+	/// The given `password` was typed in for the locked passage at `status_index`. If it is right,
+	/// the passage is unlocked and the player goes through it.
+	PassagePasswordEntered{status_index: usize, password: DosString},
+}
+
+impl BoardMessage {
+	/// True if the message closes the world in progress, by quitting or opening another world.
+	/// These are held back to ask about saving when the world has unsaved changes (see
+	/// `RuzztEngine::is_dirty`).
+	pub fn closes_world(&self) -> bool {
+		matches!(self, BoardMessage::Quit
+			| BoardMessage::OpenWorld{..}
+			| BoardMessage::CreateWorld{..}
+			| BoardMessage::FileBrowserFileSelected{..}
+			| BoardMessage::ResumeAutosave)
+	}
+
+	/// Get the name of the variant, eg. "SwitchBoard". This is what gets passed to scripts (see
+	/// `scripting`).
+	pub fn name(&self) -> &'static str {
+		match self {
+			BoardMessage::SwitchBoard{..} => "SwitchBoard",
+			BoardMessage::TeleportToBoard{..} => "TeleportToBoard",
+			BoardMessage::ShowOneTimeNotification(..) => "ShowOneTimeNotification",
+			BoardMessage::OpenScroll{..} => "OpenScroll",
+			BoardMessage::CloseScroll => "CloseScroll",
+			BoardMessage::OpenWorldMap => "OpenWorldMap",
+			BoardMessage::OpenMessageLog => "OpenMessageLog",
+			BoardMessage::EnterPressedInScroll{..} => "EnterPressedInScroll",
+			BoardMessage::PlaySoundArray(..) => "PlaySoundArray",
+			BoardMessage::ClearPlayingSound => "ClearPlayingSound",
+			BoardMessage::SetAmbientMusic(..) => "SetAmbientMusic",
+			BoardMessage::OpenSaveGameInput => "OpenSaveGameInput",
+			BoardMessage::SaveGameToFile(..) => "SaveGameToFile",
+			BoardMessage::OpenDebugInput => "OpenDebugInput",
+			BoardMessage::OpenBoardList => "OpenBoardList",
+			BoardMessage::WarpToBoard{..} => "WarpToBoard",
+			BoardMessage::DebugCommand(..) => "DebugCommand",
+			BoardMessage::LinkClicked(..) => "LinkClicked",
+			BoardMessage::PauseGame => "PauseGame",
+			BoardMessage::PlayGame => "PlayGame",
+			BoardMessage::OpenWorldSelection => "OpenWorldSelection",
+			BoardMessage::OpenAbout => "OpenAbout",
+			BoardMessage::OpenSaveSelection => "OpenSaveSelection",
+			BoardMessage::OpenWorld{..} => "OpenWorld",
+			BoardMessage::WorldLoadFailed{..} => "WorldLoadFailed",
+			BoardMessage::CloseWorldBrowser => "CloseWorldBrowser",
+			BoardMessage::WorldBrowserEntrySelected{..} => "WorldBrowserEntrySelected",
+			BoardMessage::OpenPauseMenu => "OpenPauseMenu",
+			BoardMessage::ClosePauseMenu => "ClosePauseMenu",
+			BoardMessage::OpenSettings => "OpenSettings",
+			BoardMessage::OpenGameSpeedInput => "OpenGameSpeedInput",
+			BoardMessage::SetGameSpeed(..) => "SetGameSpeed",
+			BoardMessage::OpenKeyboardConfigInput => "OpenKeyboardConfigInput",
+			BoardMessage::SetKeyRepeatDelay(..) => "SetKeyRepeatDelay",
+			BoardMessage::PlayerAppearsStuck => "PlayerAppearsStuck",
+			BoardMessage::EngineLog(..) => "EngineLog",
+			BoardMessage::OpenEndGameConfirmation => "OpenEndGameConfirmation",
+			BoardMessage::OpenQuitConfirmation => "OpenQuitConfirmation",
+			BoardMessage::ReturnToTitleScreen => "ReturnToTitleScreen",
+			BoardMessage::Quit => "Quit",
+			BoardMessage::OpenNewWorldInput => "OpenNewWorldInput",
+			BoardMessage::CreateWorld{..} => "CreateWorld",
+			BoardMessage::OpenStatusCodeEditor{..} => "OpenStatusCodeEditor",
+			BoardMessage::OpenDebugConsole => "OpenDebugConsole",
+			BoardMessage::SetStatusCode{..} => "SetStatusCode",
+			BoardMessage::CloseTextEditor => "CloseTextEditor",
+			BoardMessage::RestoreSoundQueue(..) => "RestoreSoundQueue",
+			BoardMessage::FileBrowserFileSelected{..} => "FileBrowserFileSelected",
+			BoardMessage::ResumeAutosave => "ResumeAutosave",
+			BoardMessage::EndGame => "EndGame",
+			BoardMessage::OpenHighScores => "OpenHighScores",
+			BoardMessage::ToggleListedFlag{..} => "ToggleListedFlag",
+			BoardMessage::SaveWorld => "SaveWorld",
+			BoardMessage::DiscardWorldChanges => "DiscardWorldChanges",
+			BoardMessage::SaveScreenshot{..} => "SaveScreenshot",
+			BoardMessage::OpenPassagePasswordInput{..} => "OpenPassagePasswordInput",
+			BoardMessage::PassagePasswordEntered{..} => "PassagePasswordEntered",
+		}
+	}
+}
+
+/// How serious an `EngineLog` board message is.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LogLevel {
+	Info,
+	Warning,
 }
 
 /// Types of "one-time notifications". Each type is displayed once in a caption the first time it is
 /// requested and never shown again on subsequent requests.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum OneTimeNotification {
 	BlockedByWater,
 	NoAmmo,
@@ -98,4 +285,10 @@ impl OneTimeNotification {
 			OneTimeNotification::PickUpEnergizer => DosString::from_slice(b"Energizer - You are invincible"),
 		}
 	}
+
+	/// True if the notification is shown once per board instead of once per game, so it can be
+	/// shown again after the player enters another board.
+	pub fn is_per_board(self) -> bool {
+		self == OneTimeNotification::ShootingNotAllowed
+	}
 }
@@ -0,0 +1,168 @@
+use crate::board_simulator::{BOARD_HEIGHT, BOARD_WIDTH};
+
+use serde_derive::{Serialize, Deserialize};
+use zzt_file_format::dosstring::DosString;
+use zzt_file_format::{Board, BoardTile, CodeSource, StatusElement};
+
+/// The width of a board's tiles, without the board edges.
+const BOARD_TILES_WIDTH: usize = BOARD_WIDTH - 2;
+/// The height of a board's tiles, without the board edges.
+const BOARD_TILES_HEIGHT: usize = BOARD_HEIGHT - 2;
+
+/// Check if the 1-based position `pos_x`/`pos_y` is within the rectangle with its top-left corner
+/// at `x`/`y`.
+fn in_rect(pos_x: usize, pos_y: usize, x: usize, y: usize, width: usize, height: usize) -> bool {
+	pos_x >= x && pos_x < x + width && pos_y >= y && pos_y < y + height
+}
+
+/// A rectangle of tiles copied from a board, along with the status elements on them, for copying
+/// and pasting parts of boards in an editor. Regions can be serialized (eg. as JSON with
+/// `to_json`), so they can be kept on the system clipboard and pasted into another world.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardRegion {
+	/// The width of the region in tiles.
+	pub width: usize,
+	/// The height of the region in tiles.
+	pub height: usize,
+	/// The tiles in the region, by row then column.
+	pub tiles: Vec<BoardTile>,
+	/// The status elements on the tiles in the region. Their locations are 1-based relative to the
+	/// top-left corner of the region, and they always own their code, so `#bind`s don't depend on
+	/// the board they were copied from. Leaders and followers are removed, because the rest of a
+	/// centipede might not be in the region.
+	pub status_elements: Vec<StatusElement>,
+}
+
+impl BoardRegion {
+	/// Copy the `width` by `height` rectangle of tiles of `board` with its top-left corner at the
+	/// 1-based position `x`/`y`. The player isn't copied: the tile under the player is copied in
+	/// its place.
+	pub fn copy(board: &Board, x: usize, y: usize, width: usize, height: usize) -> Result<BoardRegion, String> {
+		if x < 1 || y < 1 || width == 0 || height == 0 || x + width - 1 > BOARD_TILES_WIDTH || y + height - 1 > BOARD_TILES_HEIGHT {
+			return Err(format!("A {}x{} region at {}, {} doesn't fit on the board", width, height, x, y));
+		}
+
+		let mut tiles = Vec::with_capacity(width * height);
+		for tile_y in y .. y + height {
+			for tile_x in x .. x + width {
+				tiles.push(board.tiles[(tile_y - 1) * BOARD_TILES_WIDTH + tile_x - 1]);
+			}
+		}
+
+		let player_index_opt = board.player_status_index();
+		if let Some(player) = player_index_opt.map(|player_index| &board.status_elements[player_index]) {
+			let (player_x, player_y) = (player.location_x as usize, player.location_y as usize);
+			if in_rect(player_x, player_y, x, y, width, height) {
+				tiles[(player_y - y) * width + player_x - x] = BoardTile {
+					element_id: player.under_element_id,
+					colour: player.under_colour,
+				};
+			}
+		}
+
+		let status_elements = board.status_elements.iter().enumerate()
+			.filter(|(status_index, _)| Some(*status_index) != player_index_opt)
+			.filter(|(_, status)| in_rect(status.location_x as usize, status.location_y as usize, x, y, width, height))
+			.map(|(status_index, status)| {
+				let mut status = status.clone();
+				status.location_x -= (x - 1) as u8;
+				status.location_y -= (y - 1) as u8;
+				status.code_source = CodeSource::Owned(board.status_code(status_index).cloned().unwrap_or_else(DosString::new));
+				status.leader = -1;
+				status.follower = -1;
+				status
+			})
+			.collect();
+
+		Ok(BoardRegion {
+			width,
+			height,
+			tiles,
+			status_elements,
+		})
+	}
+
+	/// Paste the region onto `board` with its top-left corner at the 1-based position `x`/`y`. The
+	/// parts of the region that don't fit on the board are left out. Status elements under the
+	/// pasted tiles are removed, apart from the player, which stays where it is along with its tile.
+	///
+	/// The status elements on `board` are given their own copies of any code they are bound to (see
+	/// `Board::materialize_bound_code`), so removing status elements doesn't break their bindings.
+	pub fn paste(&self, board: &mut Board, x: usize, y: usize) -> Result<(), String> {
+		if x < 1 || y < 1 || x > BOARD_TILES_WIDTH || y > BOARD_TILES_HEIGHT {
+			return Err(format!("{}, {} is outside the board", x, y));
+		}
+		if self.tiles.len() != self.width * self.height {
+			return Err(format!("A {}x{} region can't have {} tiles", self.width, self.height, self.tiles.len()));
+		}
+
+		let width = self.width.min(BOARD_TILES_WIDTH + 1 - x);
+		let height = self.height.min(BOARD_TILES_HEIGHT + 1 - y);
+		let player_index_opt = board.player_status_index();
+		let player_pos = player_index_opt.map(|player_index| {
+			let player = &board.status_elements[player_index];
+			(player.location_x as usize, player.location_y as usize)
+		});
+
+		board.materialize_bound_code();
+		let mut new_indices = vec![];
+		let mut kept_status_elements = vec![];
+		for (status_index, status) in board.status_elements.drain(..).enumerate() {
+			if Some(status_index) != player_index_opt && in_rect(status.location_x as usize, status.location_y as usize, x, y, width, height) {
+				new_indices.push(None);
+			} else {
+				new_indices.push(Some(kept_status_elements.len()));
+				kept_status_elements.push(status);
+			}
+		}
+
+		// Leaders and followers that were removed are disconnected, and the others are renumbered.
+		let renumber = |link: i16| {
+			if link < 0 {
+				link
+			} else {
+				new_indices.get(link as usize).cloned().flatten().map_or(-1, |new_index| new_index as i16)
+			}
+		};
+		for status in &mut kept_status_elements {
+			status.leader = renumber(status.leader);
+			status.follower = renumber(status.follower);
+		}
+		board.status_elements = kept_status_elements;
+
+		for region_y in 0 .. height {
+			for region_x in 0 .. width {
+				let (board_x, board_y) = (x + region_x, y + region_y);
+				if player_pos != Some((board_x, board_y)) {
+					board.tiles[(board_y - 1) * BOARD_TILES_WIDTH + board_x - 1] = self.tiles[region_y * self.width + region_x];
+				}
+			}
+		}
+
+		for status in &self.status_elements {
+			let (region_x, region_y) = (status.location_x as usize, status.location_y as usize);
+			if region_x < 1 || region_y < 1 || region_x > width || region_y > height {
+				continue;
+			}
+			let (board_x, board_y) = (x + region_x - 1, y + region_y - 1);
+			if player_pos != Some((board_x, board_y)) {
+				board.status_elements.push(StatusElement {
+					location_x: board_x as u8,
+					location_y: board_y as u8,
+					.. status.clone()
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// Serialize the region as JSON, to put on the clipboard.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// Read a region that was serialized with `to_json`.
+	pub fn from_json(json: &str) -> Result<BoardRegion, String> {
+		serde_json::from_str(json).map_err(|err| err.to_string())
+	}
+}
@@ -5,33 +5,168 @@ use crate::direction::*;
 use crate::behaviour::*;
 use crate::oop_parser::*;
 use crate::board_message::*;
+use crate::sounds::*;
+use crate::compatibility;
+use crate::constants::*;
+use crate::message_timeline::{MessageDelivery, MessageTimeline};
+use crate::profiler::StepProfile;
+use crate::ruleset::{builtin_ruleset, RulesetPlugin};
 
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::rc::Rc;
-use rand::Rng;
+use rand::{FromEntropy, Rng, SeedableRng};
+use rand::distributions::uniform::SampleUniform;
+use rand::rngs::StdRng;
+
+/// Get the seed for the random number generator of the board at `board_index` from the seed of
+/// the whole world (see `RuzztEngine::board_rng_seed`). Each board gets an unrelated seed, so the
+/// randomness on one board doesn't depend on what happened on the others.
+pub fn board_rng_seed(world_seed: u64, board_index: usize) -> u64 {
+	// This is the SplitMix64 mixing function, which spreads the seeds of neighbouring boards
+	// across the whole range.
+	let mut seed = world_seed.wrapping_add((board_index as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+	seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	seed ^ (seed >> 31)
+}
 
 // These are two larger than 60x25 becuase a border of BoardEdge tiles is added.
 pub const BOARD_WIDTH: usize = 62;
 pub const BOARD_HEIGHT: usize = 27;
 
-/// This mask is used for torches and bomb explosions.
-pub const CIRCLE_MASK: [u16; 9] = [
-	0b000111111111000,
-	0b001111111111100,
-	0b011111111111110,
-	0b011111111111110,
-	0b111111111111111,
-	0b011111111111110,
-	0b011111111111110,
-	0b001111111111100,
-	0b000111111111000,
-];
-
-pub const CIRCLE_MASK_WIDTH: usize = 15;
+/// A 0-based position on a `Board` as it is stored in a world file (or JSON), which is 60x25
+/// tiles with no border, so 0x0 is the top-left tile of `Board::tiles`. Use `SimPos` for positions
+/// in the `BoardSimulator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BoardPos {
+	pub x: i16,
+	pub y: i16,
+}
+
+impl BoardPos {
+	pub fn new(x: i16, y: i16) -> BoardPos {
+		BoardPos{x, y}
+	}
+
+	/// Get the same position in the `BoardSimulator`, which is one tile down and to the right
+	/// because of the border of `BoardEdge` tiles.
+	pub fn to_sim_pos(self) -> SimPos {
+		SimPos{x: self.x + 1, y: self.y + 1}
+	}
+
+	/// Get the index of the tile at this position in `Board::tiles`, or None if the position isn't
+	/// on the board.
+	pub fn tile_index(self) -> Option<usize> {
+		if self.x >= 0 && self.y >= 0 && (self.x as usize) < BOARD_WIDTH - 2 && (self.y as usize) < BOARD_HEIGHT - 2 {
+			Some(self.y as usize * (BOARD_WIDTH - 2) + self.x as usize)
+		} else {
+			None
+		}
+	}
+}
+
+impl From<SimPos> for BoardPos {
+	fn from(sim_pos: SimPos) -> BoardPos {
+		sim_pos.to_board_pos()
+	}
+}
+
+/// A position in the `BoardSimulator`, which is 62x27 tiles including the border of `BoardEdge`
+/// tiles around the board, so the top-left tile of the board is 1x1. `StatusElement` locations
+/// (and most of the `BoardSimulator` methods that take an `x` and `y`) use these positions. Use
+/// `BoardPos` for positions in a `Board`'s tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SimPos {
+	pub x: i16,
+	pub y: i16,
+}
+
+impl SimPos {
+	pub fn new(x: i16, y: i16) -> SimPos {
+		SimPos{x, y}
+	}
+
+	/// Get the location of `status`.
+	pub fn of_status(status: &StatusElement) -> SimPos {
+		SimPos{x: status.location_x as i16, y: status.location_y as i16}
+	}
+
+	/// Get the same position on a `Board`, which is one tile up and to the left because a `Board`
+	/// has no border. Positions on the border come out outside of the board.
+	pub fn to_board_pos(self) -> BoardPos {
+		BoardPos{x: self.x - 1, y: self.y - 1}
+	}
+
+	/// True if this is on the border of `BoardEdge` tiles (or outside of it). See
+	/// `is_board_edge_pos`.
+	pub fn is_board_edge(self) -> bool {
+		is_board_edge_pos(self.x, self.y)
+	}
+}
+
+impl From<BoardPos> for SimPos {
+	fn from(board_pos: BoardPos) -> SimPos {
+		board_pos.to_sim_pos()
+	}
+}
+
+/// Get the position in the `BoardSimulator` of the tile drawn at the given `screen_x`/`screen_y`
+/// character position, or None if that position is not part of the board (eg. it's in the side
+/// bar).
+pub fn screen_pos_to_sim_pos(screen_x: usize, screen_y: usize) -> Option<SimPos> {
+	if screen_x < BOARD_WIDTH - 2 && screen_y < BOARD_HEIGHT - 2 {
+		Some(BoardPos::new(screen_x as i16, screen_y as i16).to_sim_pos())
+	} else {
+		None
+	}
+}
+
+/// True if the given board position is on the border of `BoardEdge` tiles around the board (or
+/// outside of it completely). Nothing can be placed on the border, it only exists so things trying
+/// to move off the board have something to bump into.
+pub fn is_board_edge_pos(x: i16, y: i16) -> bool {
+	x < 1 || y < 1 || x >= BOARD_WIDTH as i16 - 1 || y >= BOARD_HEIGHT as i16 - 1
+}
+
+/// Get the number of tiles in each row of `board`. Super ZZT boards are wider than the simulated
+/// area, so only their top left corner is simulated, and the rest is left as it is when the board
+/// is saved.
+fn board_row_width(board: &Board) -> usize {
+	let (super_zzt_width, super_zzt_height) = WorldType::SuperZzt.board_size();
+	if board.tiles.len() == super_zzt_width * super_zzt_height {
+		super_zzt_width
+	} else {
+		BOARD_WIDTH - 2
+	}
+}
+
+/// Get the index into `BoardSimulator::tiles` of the given board position, or None if it's out of
+/// bounds.
+fn tile_index(x: i16, y: i16) -> Option<usize> {
+	if x >= 0 && y >= 0 && (x as usize) < BOARD_WIDTH && (y as usize) < BOARD_HEIGHT {
+		Some(x as usize + y as usize * BOARD_WIDTH)
+	} else {
+		None
+	}
+}
 
 const DEFAULT_BEHAVIOUR: DefaultBehaviour = DefaultBehaviour;
 
+/// A tile on the board that was changed, as recorded in `BoardSimulator::tile_changes`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileChange {
+	/// The position of the tile in the `BoardSimulator` (so 1-based on the board).
+	pub x: i16,
+	pub y: i16,
+	/// The tile before it was changed.
+	pub old: BoardTile,
+	/// The tile after it was changed.
+	pub new: BoardTile,
+}
+
 /// The BoardSimulator simulates a single board in a ZZT game world.
 /// This simulation is independent of the World state, so before you switch boards, you must replace
 /// the respective board data in the World state with the current simulated board state.
@@ -61,6 +196,181 @@ pub struct BoardSimulator {
 	/// The behaviours are loaded into this list via the `set_behaviour` method.
 	/// These are `Rc` so that `Behaviour` doesn't need to impl `Clone`.
 	pub behaviours: Vec<Option<Rc<dyn Behaviour>>>,
+	/// The rules of the game being simulated. This starts as the stock ruleset for the world type
+	/// (see `builtin_ruleset`), and its behaviours are loaded with `load_ruleset_behaviours`.
+	pub ruleset: Rc<dyn RulesetPlugin>,
+	/// When this is set, every action applied by `apply_action` is added to the list. This is used
+	/// to see what a status does without looking at the whole board, eg. in
+	/// `RuzztEngine::dry_run_object`.
+	pub action_log: Option<Vec<Action>>,
+	/// When this is set, every tile that is changed by `set_tile` (which moving tiles and applying
+	/// actions go through) is added to the list, so things like incremental renderers and the
+	/// editor's live preview only have to look at the tiles that changed instead of the whole
+	/// board. Loading a board changes every tile. Take the changes with `take_tile_changes`.
+	pub tile_changes: Option<Vec<TileChange>>,
+	/// The most status elements (including the player) that can be on the board at once. Adding a
+	/// tile with a status when there are already this many fails and sends an `EngineLog` warning,
+	/// like DOS ZZT (but without the warning). If this is None there is no limit. This starts as
+	/// `default_status_element_limit` for the world type.
+	pub status_element_limit: Option<usize>,
+	/// The random number generator used for everything random on the board (eg. creature movement
+	/// and `#go rndp`). It starts with a random seed, but can be seeded with `seed_rng` so that
+	/// the game plays out the same way every time given the same events. This is a `RefCell`
+	/// because behaviours only get a shared reference to the `BoardSimulator`.
+	pub rng: RefCell<StdRng>,
+	/// The settings that change how the board is simulated. See `SimulatorSettings`.
+	pub settings: SimulatorSettings,
+	/// When this is set, the counters in it are updated as the board is simulated. This is a
+	/// `RefCell` for the same reason as `rng`. See `record_profile`.
+	pub profile: RefCell<Option<StepProfile>>,
+	/// When this is set, every label operation delivered to a status is added to it. This is a
+	/// `RefCell` for the same reason as `rng`. See `record_message_delivery`.
+	pub message_timeline: RefCell<Option<MessageTimeline>>,
+	/// The number of actions applied by `apply_action` since the simulator was made. This keeps
+	/// counting across boards, so the number applied in a step is the difference between two counts.
+	pub actions_applied: usize,
+	/// When set, the side bar shows this as the player's health instead of the real health. DOS ZZT
+	/// doesn't redraw the side bar when `#endgame` sets the health to 0, so `Action::EndGame` sets
+	/// this to the health from before the game ended. Changing any of the player's items clears it,
+	/// because DOS ZZT redraws the side bar then.
+	pub side_bar_health: Option<i16>,
+	/// True while the game is paused after the player came onto the board through a passage. Like
+	/// DOS ZZT, the player's status stands on the passage without a player tile until the player
+	/// next moves. The engine keeps this up to date, so `player_is_misplaced` and
+	/// `find_invariant_violations` can allow it.
+	pub player_on_passage: bool,
+}
+
+/// How keys and doors with uncommon colours behave. DOS ZZT works out which key a key or door is
+/// for by taking its colour (the background colour for doors) mod 8, so a dark blue key is the
+/// same as a blue key, and a door with a blinking blue background is a blue door. Black keys and
+/// doors come out as key 0, which doesn't exist: DOS ZZT uses the byte before the keys in memory
+/// instead, which is the high byte of the player's gem count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyColourPolicy {
+	/// Behave like DOS ZZT, including the black key glitch: picking up a black key sets the high
+	/// byte of the gem count to 1 (giving the player 256 gems), and a black door opens when the
+	/// high byte is not 0, then clears it.
+	Dos,
+	/// Behave like DOS ZZT, except black keys and doors can't be picked up or opened, so they
+	/// never change the gem count.
+	Sanitized,
+}
+
+/// What happens when giving the player an item would take it over 32767, or taking it would take
+/// it under -32768, which is as far as the 16-bit counters in the world header go. DOS ZZT wraps
+/// around, which is how a world can give the player a huge negative score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemOverflowPolicy {
+	/// Wrap around like DOS ZZT, so giving 1 gem to a player with 32767 leaves them with -32768.
+	Wrap,
+	/// Stop at 32767 (or -32768).
+	Saturate,
+	/// Leave the item as it was and send an `EngineLog` warning.
+	Error,
+}
+
+impl ItemOverflowPolicy {
+	/// Add `offset` to the player item `value`. This is used for all giving and taking of player
+	/// items. Returns None if the item would overflow with the `Error` policy.
+	pub fn add(self, value: i16, offset: i32) -> Option<i16> {
+		let sum = value as i64 + offset as i64;
+		match self {
+			ItemOverflowPolicy::Wrap => Some(sum as i16),
+			ItemOverflowPolicy::Saturate => Some(sum.clamp(i16::MIN as i64, i16::MAX as i64) as i16),
+			ItemOverflowPolicy::Error => i16::try_from(sum).ok(),
+		}
+	}
+}
+
+/// The version of DOS ZZT to behave like. This covers the `#endgame` command and bullets flying
+/// over water, which were both added in ZZT 3.2, so old worlds that rely on how ZZT 3.0 did things
+/// still work. Other differences between the versions aren't covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityVersion {
+	/// Behave like ZZT 3.0 and 3.1, which don't have the `#endgame` command. `#endgame` sends the
+	/// object to its `:endgame` label instead, or gives a "Bad command" error if it has none. Water
+	/// stops bullets and stars like a wall does.
+	Zzt30,
+	/// Behave like ZZT 3.2, which has the `#endgame` command and lets bullets fly over water.
+	Zzt32,
+}
+
+impl CompatibilityVersion {
+	/// True if the `#endgame` command exists in this version.
+	pub fn has_endgame_command(self) -> bool {
+		self == CompatibilityVersion::Zzt32
+	}
+
+	/// True if bullets and stars can fly over water in this version.
+	pub fn bullets_fly_over_water(self) -> bool {
+		self == CompatibilityVersion::Zzt32
+	}
+}
+
+/// The number of OOP instructions an object can run in one step in DOS ZZT (see
+/// `SimulatorSettings::oop_instruction_limit`).
+pub const DEFAULT_OOP_INSTRUCTION_LIMIT: usize = 64;
+
+/// Get the most status elements that a board can have in DOS ZZT or Super ZZT, for the given
+/// `world_type`. This includes the player, so it is one more than the limit in the original source
+/// code (150 and 128).
+pub fn default_status_element_limit(world_type: WorldType) -> usize {
+	match world_type {
+		WorldType::Zzt => 151,
+		WorldType::SuperZzt => 129,
+	}
+}
+
+/// The settings that change how a `BoardSimulator` simulates boards. These aren't part of the
+/// world, so the engine copies them as a whole to each new simulator it makes (eg. when a different
+/// world is loaded).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatorSettings {
+	/// How keys and doors that aren't one of the seven key colours behave. See `KeyColourPolicy`.
+	pub key_colour_policy: KeyColourPolicy,
+	/// The most OOP instructions an object or scroll can run in one step before it is stopped
+	/// until its next step, so a runaway script can't hang the game. Lines of text don't count.
+	/// Going over the limit sends an `EngineLog` warning naming the object. This starts as
+	/// `DEFAULT_OOP_INSTRUCTION_LIMIT`.
+	pub oop_instruction_limit: usize,
+	/// What happens when giving or taking a player item would take it past what it can hold. See
+	/// `ItemOverflowPolicy`.
+	pub item_overflow_policy: ItemOverflowPolicy,
+	/// Which version of DOS ZZT to behave like where versions differ. See `CompatibilityVersion`.
+	pub compatibility_version: CompatibilityVersion,
+	/// Flags (in upper case) that are always set, and can't be changed with `#set` or `#clear`. They
+	/// aren't stored in the world header, so they don't use up any of the world's flags and aren't
+	/// saved. The engine fills these in so worlds can detect RUZZT (see `ExtensionLevel`).
+	pub reserved_flags: Vec<DosString>,
+	/// When true, objects can use the `#colour` command to set their extended colours (see
+	/// `StatusElement::extended_colours`). The engine sets this from its `ExtensionLevel`, because
+	/// worlds made for DOS ZZT could have a `:colour` label that `#colour` is meant to jump to.
+	pub extended_colours_enabled: bool,
+	/// When true, passages can be locked with a flag or a password in their code (see
+	/// `PassageLock`). The engine sets this from its `ExtensionLevel`, so passages in worlds made for
+	/// DOS ZZT always work, whatever their code is.
+	pub locked_passages_enabled: bool,
+}
+
+impl SimulatorSettings {
+	pub fn new() -> SimulatorSettings {
+		SimulatorSettings {
+			key_colour_policy: KeyColourPolicy::Dos,
+			oop_instruction_limit: DEFAULT_OOP_INSTRUCTION_LIMIT,
+			item_overflow_policy: ItemOverflowPolicy::Wrap,
+			compatibility_version: CompatibilityVersion::Zzt32,
+			reserved_flags: vec![],
+			extended_colours_enabled: false,
+			locked_passages_enabled: false,
+		}
+	}
+}
+
+impl Default for SimulatorSettings {
+	fn default() -> SimulatorSettings {
+		SimulatorSettings::new()
+	}
 }
 
 impl BoardSimulator {
@@ -82,37 +392,148 @@ impl BoardSimulator {
 			}
 		}
 
+		let status_element_limit = Some(default_status_element_limit(world_header.world_type));
+		let ruleset = builtin_ruleset(world_header.world_type);
+
 		BoardSimulator {
 			world_header,
 			board_meta_data: BoardMetaData::default(),
 			status_elements: vec![],
 			tiles,
 			behaviours: vec![],
+			ruleset,
+			action_log: None,
+			tile_changes: None,
+			status_element_limit,
+			rng: RefCell::new(StdRng::from_entropy()),
+			settings: SimulatorSettings::new(),
+			profile: RefCell::new(None),
+			message_timeline: RefCell::new(None),
+			actions_applied: 0,
+			side_bar_health: None,
+			player_on_passage: false,
+		}
+	}
+
+	/// Restart the random number generator with the given `seed`.
+	pub fn seed_rng(&self, seed: u64) {
+		*self.rng.borrow_mut() = StdRng::seed_from_u64(seed);
+	}
+
+	/// Get a random number from `low` to `high`, not including `high`.
+	pub fn random_range<T: SampleUniform>(&self, low: T, high: T) -> T {
+		self.rng.borrow_mut().gen_range(low, high)
+	}
+
+	/// Get true or false at random.
+	pub fn random_bool(&self) -> bool {
+		self.rng.borrow_mut().gen()
+	}
+
+	/// Call `record` with the `profile` if profiling is turned on.
+	pub fn record_profile(&self, record: impl FnOnce(&mut StepProfile)) {
+		if let Some(ref mut profile) = *self.profile.borrow_mut() {
+			record(profile);
+		}
+	}
+
+	/// Add the label `operation` on `label` that the status at `sender_index` (None if it wasn't
+	/// sent by a status) delivered to the status at `receiver_index` to the `message_timeline`, if
+	/// it is being recorded.
+	pub fn record_message_delivery(&self, sender_index: Option<usize>, receiver_index: usize, label: &DosString, operation: LabelOperation) {
+		if let Some(ref mut message_timeline) = *self.message_timeline.borrow_mut() {
+			let status_name = |status_index: usize| {
+				self.status_elements.get(status_index).and_then(|status| OopParser::new(self.get_status_code(status), 0).get_name())
+			};
+			message_timeline.record(MessageDelivery {
+				cycle: message_timeline.current_cycle,
+				board_index: self.world_header.player_board,
+				sender_index,
+				sender_name: sender_index.and_then(status_name),
+				receiver_index,
+				receiver_name: status_name(receiver_index),
+				label: label.clone(),
+				operation,
+			});
+		}
+	}
+
+	/// True if the `profile` is being collected.
+	pub fn is_profiling(&self) -> bool {
+		self.profile.borrow().is_some()
+	}
+
+	/// True if the flag with the given `name` (ignoring case) is set in the world header, or is one
+	/// of the `reserved_flags`.
+	pub fn flag_is_set(&self, name: &DosString) -> bool {
+		let upper_name = name.clone().to_upper();
+		self.settings.reserved_flags.contains(&upper_name) || self.world_header.last_matching_flag(upper_name).is_some()
+	}
+
+	/// Get the index of the key that goes with the given `colour`, which is the foreground colour of
+	/// a key or the background colour of a door. Index 1 to 7 are the keys from blue to white, and
+	/// 0 is black (see `KeyColourPolicy`). Returns None if there is no such key with the current
+	/// `key_colour_policy`.
+	pub fn key_index_for_colour(&self, colour: u8) -> Option<u8> {
+		let key_index = colour % 8;
+		if key_index == 0 && self.settings.key_colour_policy == KeyColourPolicy::Sanitized {
+			None
+		} else {
+			Some(key_index)
+		}
+	}
+
+	/// True if the player has the key with the given `key_index` (see `key_index_for_colour`). The
+	/// player has the black key when the high byte of their gem count isn't 0, like in DOS ZZT.
+	pub fn has_key(&self, key_index: u8) -> bool {
+		if key_index == 0 {
+			(self.world_header.player_gems as u16) >> 8 != 0
+		} else {
+			self.world_header.player_keys[key_index as usize - 1]
 		}
 	}
 
 	/// Assign a `Behaviour` to an `ElementType`. This defines how tiles of this type are simulated.
 	pub fn set_behaviour(&mut self, element_type: ElementType, behaviour: Box<dyn Behaviour>) {
-		let index = element_type as usize;
+		self.set_behaviour_for_id(element_type as u8, behaviour.into());
+	}
+
+	/// Set the behaviour for the given `element_id`. This is for worlds that don't use ZZT's
+	/// element IDs, like Super ZZT worlds.
+	pub fn set_behaviour_for_id(&mut self, element_id: u8, behaviour: Rc<dyn Behaviour>) {
+		let index = element_id as usize;
 		while self.behaviours.len() <= index {
 			self.behaviours.push(None);
 		}
-		self.behaviours[index] = Some(behaviour.into());
+		self.behaviours[index] = Some(behaviour);
+	}
+
+	/// Replace all the behaviours with the ones from `ruleset`.
+	pub fn load_ruleset_behaviours(&mut self) {
+		let ruleset = self.ruleset.clone();
+		self.behaviours.clear();
+		ruleset.load_behaviours(self);
+	}
+
+	/// Get the ID of `element_type` in the ruleset being simulated, for putting new tiles of that
+	/// type on the board. Elements that the ruleset doesn't have are put down as empty.
+	pub fn element_id(&self, element_type: ElementType) -> u8 {
+		self.ruleset.element_id(element_type).unwrap_or(ElementType::Empty as u8)
 	}
 
 	/// Get a random unit vector along a direction (N, S, E, W).
 	pub fn get_random_step(&self) -> (i16, i16) {
-		let mut rng = rand::thread_rng();
-		let step_x = rng.gen_range(0, 3) - 1;
+		let step_x = self.random_range(0, 3) - 1;
 		let step_y = if step_x == 0 {
-			if rng.gen_range(0, 2) == 0 { -1 } else { 1 }
+			if self.random_range(0, 2) == 0 { -1 } else { 1 }
 		} else {
 			0
 		};
 		(step_x, step_y)
 	}
 
-	/// Calls `visit_fn` with every tile on the board.
+	/// Calls `visit_fn` with every tile on the board, including the board edge. The tiles are
+	/// visited from the bottom-right corner, going up each column before moving left to the next.
 	/// `visit_fn` takes the x/y position of each tile, and the tile itself.
 	pub fn visit_all_tiles(&self, visit_fn: &mut dyn FnMut(i16, i16, BoardTile)) {
 		for x in (0 .. BOARD_WIDTH).rev() {
@@ -122,36 +543,69 @@ impl BoardSimulator {
 		}
 	}
 
+	/// Get every tile on the board (not including the board edge) with its x/y position, in
+	/// row-first order from the top-left corner. This is the same order as the tiles in a `Board`.
+	pub fn board_tiles(&self) -> impl Iterator<Item = (i16, i16, BoardTile)> + '_ {
+		(1 .. BOARD_HEIGHT as i16 - 1).flat_map(move |y| {
+			(1 .. BOARD_WIDTH as i16 - 1).map(move |x| (x, y, self.get_tile(x, y).unwrap()))
+		})
+	}
+
+	/// Get every tile on the board with the given `element_id`, in the same order as `board_tiles`.
+	/// Each tile comes with its x/y position and the first status at that position (see
+	/// `get_first_status_for_pos`), if there is one.
+	pub fn find_tiles(&self, element_id: u8) -> impl Iterator<Item = (i16, i16, BoardTile, Option<&StatusElement>)> + '_ {
+		self.board_tiles()
+			.filter(move |(_, _, tile)| tile.element_id == element_id)
+			.map(move |(x, y, tile)| (x, y, tile, self.get_first_status_for_pos(x, y).map(|(_, status)| status)))
+	}
+
+	/// Count the tiles on the board with the given `element_id`.
+	pub fn count_tiles(&self, element_id: u8) -> usize {
+		self.board_tiles().filter(|(_, _, tile)| tile.element_id == element_id).count()
+	}
+
 	/// Sets the tile at the given x/y position on the board to `tile`.
 	/// Returns false if the given position was out of bounds.
 	pub fn set_tile(&mut self, x: i16, y: i16, tile: BoardTile) -> bool {
-		let index = x + (y * BOARD_WIDTH as i16);
-		if index >= 0 && index < self.tiles.len() as i16 {
-			self.tiles[index as usize] = tile;
+		if let Some(index) = tile_index(x, y) {
+			let old_tile = std::mem::replace(&mut self.tiles[index], tile);
+			if let Some(ref mut tile_changes) = self.tile_changes {
+				if old_tile != tile {
+					tile_changes.push(TileChange {
+						x,
+						y,
+						old: old_tile,
+						new: tile,
+					});
+				}
+			}
 			true
 		} else {
 			false
 		}
 	}
 
+	/// Take the tile changes recorded since the last call, in the order they happened. The changes
+	/// keep being recorded if `tile_changes` is set, otherwise this returns nothing.
+	pub fn take_tile_changes(&mut self) -> Vec<TileChange> {
+		match self.tile_changes {
+			Some(ref mut tile_changes) => std::mem::take(tile_changes),
+			None => vec![],
+		}
+	}
+
 	/// Get the tile at the given x/y position, or None if the given position is out of bounds.
 	pub fn get_tile(&self, x: i16, y: i16) -> Option<BoardTile> {
-		let index = x + (y * BOARD_WIDTH as i16);
-		if index >= 0 && index < self.tiles.len() as i16 {
-			Some(self.tiles[index as usize])
-		} else {
-			None
-		}
+		tile_index(x, y).map(|index| self.tiles[index])
 	}
 
 	/// Get a muteable reference to the tile at the given x/y position, or None if the given
 	/// position is out of bounds.
 	pub fn get_tile_mut(&mut self, x: i16, y: i16) -> Option<&mut BoardTile> {
-		let index = x + (y * BOARD_WIDTH as i16);
-		if index >= 0 && index < self.tiles.len() as i16 {
-			self.tiles.get_mut(index as usize)
-		} else {
-			None
+		match tile_index(x, y) {
+			Some(index) => self.tiles.get_mut(index),
+			None => None,
 		}
 	}
 
@@ -199,20 +653,30 @@ impl BoardSimulator {
 				// Step 1: If a status is removed that has another status bound to its code_source,
 				//   and it owns code, move the code from the removed one into the first one that
 				//   references it, then change any other references to the removed status so they
-				//   point to the new one.
+				//   point to the new one. If the removed status is itself bound to another status,
+				//   then anything bound to the removed status is bound to that one instead.
 
 				let removing_code_source = std::mem::replace(&mut self.status_elements[check_removal_index].code_source, CodeSource::Owned(DosString::new()));
 
-				if let CodeSource::Owned(mut removing_code) = removing_code_source {
-					let mut new_bound_index_opt = None;
-
-					for (index, status) in self.status_elements.iter_mut().enumerate() {
-						if status.code_source == CodeSource::Bound(check_removal_index) {
-							if let Some(new_bound_index) = new_bound_index_opt {
-								status.code_source = CodeSource::Bound(new_bound_index);
-							} else {
-								status.code_source = CodeSource::Owned(std::mem::replace(&mut removing_code, DosString::new()));
-								new_bound_index_opt = Some(index);
+				match removing_code_source {
+					CodeSource::Owned(mut removing_code) => {
+						let mut new_bound_index_opt = None;
+
+						for (index, status) in self.status_elements.iter_mut().enumerate() {
+							if status.code_source == CodeSource::Bound(check_removal_index) {
+								if let Some(new_bound_index) = new_bound_index_opt {
+									status.code_source = CodeSource::Bound(new_bound_index);
+								} else {
+									status.code_source = CodeSource::Owned(std::mem::replace(&mut removing_code, DosString::new()));
+									new_bound_index_opt = Some(index);
+								}
+							}
+						}
+					}
+					CodeSource::Bound(removing_bound_index) => {
+						for status in &mut self.status_elements {
+							if status.code_source == CodeSource::Bound(check_removal_index) {
+								status.code_source = CodeSource::Bound(removing_bound_index);
 							}
 						}
 					}
@@ -283,12 +747,32 @@ impl BoardSimulator {
 		}
 	}
 
-	/// Tries to move the tile at `from_x`/`from_y` to `to_x`/`to_y`.
+	/// Tries to move the tile at `from_x`/`from_y` to `to_x`/`to_y`, along with the first status at
+	/// that position (if there is one). Nothing happens if either position is on the board edge.
+	/// See `move_tile_for_status`.
 	pub fn move_tile(&mut self, from_x: i16, from_y: i16, to_x: i16, to_y: i16) {
+		let status_index_opt = self.get_first_status_for_pos(from_x, from_y).map(|(status_index, _)| status_index);
+		self.move_tile_for_status(from_x, from_y, to_x, to_y, status_index_opt);
+	}
+
+	/// Tries to move the tile at `from_x`/`from_y` to `to_x`/`to_y`, along with the status at
+	/// `status_index_opt`, which should be at `from_x`/`from_y`. This works like `MoveStat` in DOS
+	/// ZZT: Only that one status moves, so any other statuses at the same position stay where they
+	/// are. The status's under tile is put back where it was, and the tile it moves onto becomes
+	/// its new under tile. Unless it's the player, the moved tile also takes the background colour
+	/// of the tile it moves onto (so eg. an object on water has a blue background), or no
+	/// background colour if that tile is empty.
+	///
+	/// When there is no status, the tile is just copied, and an empty tile is left behind.
+	pub fn move_tile_for_status(&mut self, from_x: i16, from_y: i16, to_x: i16, to_y: i16, status_index_opt: Option<usize>) {
 		if from_x == to_x && from_y == to_y {
 			return;
 		}
 
+		if is_board_edge_pos(from_x, from_y) || is_board_edge_pos(to_x, to_y) {
+			return;
+		}
+
 		let from_tile = if let Some(tile) = self.get_tile(from_x, from_y) {
 			tile
 		} else {
@@ -301,28 +785,41 @@ impl BoardSimulator {
 			return;
 		};
 
-		self.set_tile(to_x, to_y, from_tile);
-
-		let mut under_element_id = 0;
-		let mut under_colour = 0;
+		let status_element = match status_index_opt.and_then(|status_index| self.status_elements.get_mut(status_index)) {
+			Some(status_element) => status_element,
+			None => {
+				self.set_tile(to_x, to_y, from_tile);
+				self.set_tile(from_x, from_y, BoardTile {
+					element_id: ElementType::Empty as u8,
+					colour: 0,
+				});
+				return;
+			}
+		};
 
-		for status_element in &mut self.status_elements {
-			if status_element.location_x == from_x as u8 && status_element.location_y == from_y as u8 {
-				status_element.location_x = to_x as u8;
-				status_element.location_y = to_y as u8;
+		let under_tile = BoardTile {
+			element_id: status_element.under_element_id,
+			colour: status_element.under_colour,
+		};
 
-				under_element_id = status_element.under_element_id;
-				under_colour = status_element.under_colour;
+		status_element.location_x = to_x as u8;
+		status_element.location_y = to_y as u8;
+		status_element.under_element_id = to_tile.element_id;
+		status_element.under_colour = to_tile.colour;
 
-				status_element.under_element_id = to_tile.element_id;
-				status_element.under_colour = to_tile.colour;
-			}
-		}
+		let moved_colour = if from_tile.element_id == ElementType::Player as u8 {
+			from_tile.colour
+		} else if to_tile.element_id == ElementType::Empty as u8 {
+			from_tile.colour & 0x0f
+		} else {
+			(from_tile.colour & 0x0f) | (to_tile.colour & 0x70)
+		};
 
-		self.set_tile(from_x, from_y, BoardTile {
-			element_id: under_element_id,
-			colour: under_colour,
+		self.set_tile(to_x, to_y, BoardTile {
+			element_id: from_tile.element_id,
+			colour: moved_colour,
 		});
+		self.set_tile(from_x, from_y, under_tile);
 	}
 
 	/// The `push_tile` function is called when one tile tries to move onto another tile, to move
@@ -378,11 +875,54 @@ impl BoardSimulator {
 		}
 	}
 
-	/// Get the location of the player.
-	/// Note: The player is ALWAYS status element 0 in ZZT.
+	/// Get the indices of the statuses that will be processed in the step where the global cycle is
+	/// `global_cycle`, in the order they will be processed (assuming no statuses are added or removed
+	/// during the step).
+	pub fn status_execution_order(&self, global_cycle: usize) -> Vec<usize> {
+		self.status_elements.iter().enumerate()
+			.filter(|(status_index, status)| status_is_processed_in_cycle(*status_index, status.cycle, global_cycle))
+			.map(|(status_index, _)| status_index)
+			.collect()
+	}
+
+	/// Get the index of the status element that is the player. Like DOS ZZT, this is always status
+	/// element 0, even when there are other player tiles (which ZZT treats as clones of status
+	/// element 0), and on broken boards where status element 0 isn't on a player tile at all (see
+	/// `player_is_misplaced`). Returns None if the board has no status elements at all.
+	pub fn player_status_index(&self) -> Option<usize> {
+		if self.status_elements.is_empty() {
+			None
+		} else {
+			Some(0)
+		}
+	}
+
+	/// True if the player's status element (see `player_status_index`) is somewhere other than a
+	/// player tile, a monitor tile in the title screen, or a passage while `player_on_passage` is
+	/// set. DOS ZZT still moves status element 0 as the player on these broken boards, with some
+	/// very weird results, and so does RUZZT. The engine logs a warning when the player enters one.
+	pub fn player_is_misplaced(&self) -> bool {
+		let player_status = match self.status_elements.first() {
+			Some(player_status) => player_status,
+			None => return false,
+		};
+		let player_tile = self.get_tile(player_status.location_x as i16, player_status.location_y as i16);
+		!player_tile.is_some_and(|tile| {
+			tile.element_id == ElementType::Player as u8 || tile.element_id == ElementType::Monitor as u8
+				|| (self.player_on_passage && tile.element_id == ElementType::Passage as u8)
+		})
+	}
+
+	/// Get the location of the player (see `player_status_index`). If the board has no status
+	/// elements, this is the top-left corner of the board edge, where nothing can reach.
 	pub fn get_player_location(&self) -> (i16, i16) {
-		let ref player_status = self.status_elements[0];
-		(player_status.location_x as i16, player_status.location_y as i16)
+		match self.player_status_index() {
+			Some(player_index) => {
+				let player_status = &self.status_elements[player_index];
+				(player_status.location_x as i16, player_status.location_y as i16)
+			}
+			None => (0, 0),
+		}
 	}
 
 	/// Check if there is a player tile at the given x/y location.
@@ -406,6 +946,70 @@ impl BoardSimulator {
 		self.world_header.time_passed = 0;
 	}
 
+	/// Get the number of seconds left before the board's time limit runs out, or None if the board
+	/// doesn't have a time limit. This is negative once the time has run out.
+	pub fn time_left(&self) -> Option<i16> {
+		if self.board_meta_data.time_limit > 0 {
+			Some(self.board_meta_data.time_limit.wrapping_sub(self.world_header.time_passed))
+		} else {
+			None
+		}
+	}
+
+	/// Add the actions that hurt the player to `actions`. This is used for everything that hurts
+	/// the player (monsters, bullets and the board's time limit running out), like DOS ZZT's
+	/// `DamageStat` on the player. The ruleset decides what happens (see
+	/// `RulesetPlugin::add_player_hurt_actions`), which is usually `add_stock_player_hurt_actions`.
+	pub fn add_player_hurt_actions(&self, actions: &mut Vec<Action>) {
+		self.ruleset.add_player_hurt_actions(self, actions);
+	}
+
+	/// Add the actions that hurt the player in DOS ZZT to `actions`. The player isn't hurt while
+	/// they are energized or already dead. If the board has `restart_on_zap`, the player is also
+	/// moved back to where they entered the board, and the game is paused.
+	pub fn add_stock_player_hurt_actions(&self, actions: &mut Vec<Action>) {
+		if self.world_header.energy_cycles > 0 || self.world_header.player_health <= 0 {
+			return;
+		}
+
+		actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"--c+c-d#+d#"), SoundPriority::Level(2))));
+		actions.push(Action::SendBoardMessage(BoardMessage::OpenScroll{title: DosString::new(), content_lines: vec![DosString::from_slice(b"Ouch!")]}));
+		actions.push(Action::ModifyPlayerItem {
+			item_type: PlayerItemType::Health,
+			offset: -(PLAYER_HURT_DAMAGE as i32),
+			require_exact_amount: false,
+		});
+		actions.push(Action::CheckRestartOnZapped);
+	}
+
+	/// Hurt the player immediately (see `add_player_hurt_actions`). Any board messages are added to
+	/// `accumulated_data`.
+	pub fn hurt_player(&mut self, global_cycle: usize, accumulated_data: &mut AccumulatedActionData) {
+		let mut actions = vec![];
+		self.add_player_hurt_actions(&mut actions);
+
+		let (player_x, player_y) = self.get_player_location();
+		let mut report = ApplyActionResultReport::new();
+		for action in actions {
+			self.apply_action(player_x, player_y, action, global_cycle, Some(0), accumulated_data, &mut report);
+		}
+	}
+
+	/// This should be called every time a second passes on a board with a time limit. It warns the
+	/// player when there are 10 seconds left, and hurts them every second after the time runs out
+	/// (unless the board has `restart_on_zap`, in which case the timer is also reset).
+	pub fn on_time_limit_second_passed(&mut self, global_cycle: usize, accumulated_data: &mut AccumulatedActionData) {
+		self.world_header.time_passed = self.world_header.time_passed.wrapping_add(1);
+
+		if let Some(time_left) = self.time_left() {
+			if time_left == 10 {
+				accumulated_data.board_messages.push(BoardMessage::OpenScroll{title: DosString::new(), content_lines: vec![DosString::from_slice(b"Running out of time!")]});
+			} else if time_left < 0 {
+				self.hurt_player(global_cycle, accumulated_data);
+			}
+		}
+	}
+
 	/// Finds the location of the passage associated with the given colour.
 	/// The search starts at the bottom right and goes up, and the function returns the first one it
 	/// finds.
@@ -426,7 +1030,7 @@ impl BoardSimulator {
 
 	/// Attempt to fire a bullet (or a star) from `shoot_start_x`/`shoot_start_y` moving along
 	/// `shoot_step_x`/`shoot_step_y`. Set `shoot_star` to true to fire a star instead of a bullet.
-	/// Set `shot_by_player` to true if the player is firing.
+	/// `source` is who is firing, which the shot remembers in its param1 (see `ShotSource`).
 	/// `actions` is the list of actions to apply. The actions generated by this function will be
 	/// appended to this list.
 	/// Note that, for example, if the player is shooting a `Breakable` tile that is immediately
@@ -438,7 +1042,7 @@ impl BoardSimulator {
 			shoot_step_x: i16,
 			shoot_step_y: i16,
 			shoot_star: bool,
-			shot_by_player: bool,
+			source: ShotSource,
 			actions: &mut Vec<Action>) -> bool {
 		let dest_behaviour = self.behaviour_for_pos(shoot_start_x, shoot_start_y);
 		// This is the tile that the bullet is placed on top of, if it is able to be fired at all.
@@ -449,31 +1053,19 @@ impl BoardSimulator {
 
 		let mut shooting_allowed = true;
 
-		if shot_by_player {
+		if source == ShotSource::Player {
 			if self.board_meta_data.max_player_shots == 0 {
 				actions.push(Action::SendBoardMessage(BoardMessage::ShowOneTimeNotification(OneTimeNotification::ShootingNotAllowed)));
 				shooting_allowed = false;
-			} else {
-				let mut existing_player_bullet_count = 0;
-				// Count number of player bullets on the screen.
-				for status_element in &self.status_elements {
-					if let Some(tile) = self.get_status_tile(status_element) {
-						if tile.element_id == ElementType::Bullet as u8 && status_element.param1 == 0 {
-							existing_player_bullet_count += 1;
-						}
-					}
-				}
-
-				if existing_player_bullet_count >= self.board_meta_data.max_player_shots {
-					shooting_allowed = false;
-				}
+			} else if self.player_bullet_count() >= self.board_meta_data.max_player_shots as usize {
+				shooting_allowed = false;
 			}
 		}
 
 		if shooting_allowed {
-			if dest_behaviour.blocked_for_bullets() == BlockedStatus::Blocked {
+			if dest_behaviour.blocked_for_bullets(self) == BlockedStatus::Blocked {
 				if dest_behaviour.destructable() {
-					dest_behaviour.damage(shoot_start_x, shoot_start_y, DamageType::Shot{by_player: shot_by_player}, self, actions);
+					dest_behaviour.damage(shoot_start_x, shoot_start_y, DamageType::Shot{source}, self, actions);
 					fired_shot = true;
 				}
 			} else {
@@ -482,13 +1074,13 @@ impl BoardSimulator {
 						// Proof that this should use 100 for param2 is that when a tiger shoots a
 						// bullet, param2 is set to 100, as if it were shooting a star.
 						(BoardTile {
-							element_id: ElementType::Star as u8,
+							element_id: self.element_id(ElementType::Star),
 							colour: 0xa,
 						},
 						100)
 					} else {
 						(BoardTile {
-							element_id: ElementType::Bullet as u8,
+							element_id: self.element_id(ElementType::Bullet),
 							colour: 0xf,
 						},
 						0)
@@ -501,7 +1093,7 @@ impl BoardSimulator {
 						step_y: shoot_step_y,
 						cycle: 1,
 						// This is why stars have param1 == 1 when doing #throwstar.
-						param1: if shot_by_player { 0 } else { 1 },
+						param1: source.param1(),
 						param2,
 						under_element_id: under_tile.element_id,
 						under_colour: under_tile.colour,
@@ -526,14 +1118,70 @@ impl BoardSimulator {
 		fired_shot
 	}
 
+	/// Get a hash of the whole state of the game being simulated, to cheaply check whether two
+	/// simulations have diverged (eg. when replaying recorded input). This covers everything in
+	/// `compatibility::board_hash`, plus the player's inventory, flags and timers, and the board's
+	/// settings. The hash is stable, so it can be saved and compared later, but it isn't meant to
+	/// be compared with DOS ZZT (use `compatibility::board_hash` for that).
+	pub fn state_hash(&self) -> u64 {
+		let mut bytes = compatibility::board_hash_bytes(self);
+
+		let header = &self.world_header;
+		for value in &[header.player_ammo, header.player_gems, header.player_health, header.player_board, header.energy_cycles, header.player_score, header.time_passed] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		for value in &[header.player_torches, header.torch_cycles, header.player_stones] {
+			bytes.extend_from_slice(&value.unwrap_or(-1).to_le_bytes());
+		}
+		bytes.extend(header.player_keys.iter().map(|has_key| *has_key as u8));
+		for flag_name in &header.flag_names {
+			bytes.extend_from_slice(&flag_name.data);
+			bytes.push(0);
+		}
+
+		let meta_data = &self.board_meta_data;
+		bytes.extend_from_slice(&[
+			meta_data.max_player_shots,
+			meta_data.is_dark as u8,
+			meta_data.exit_north,
+			meta_data.exit_south,
+			meta_data.exit_west,
+			meta_data.exit_east,
+			meta_data.restart_on_zap as u8,
+			meta_data.player_enter_x,
+			meta_data.player_enter_y,
+		]);
+		bytes.extend_from_slice(&meta_data.time_limit.to_le_bytes());
+
+		compatibility::fnv1a_hash(&bytes)
+	}
+
+	/// Count the bullets on the board that were shot by the player. This is what is limited by the
+	/// board's `max_player_shots`. Stars aren't counted, even when thrown by the player.
+	pub fn player_bullet_count(&self) -> usize {
+		self.status_elements.iter().filter(|status_element| {
+			ShotSource::from_status(status_element) == ShotSource::Player && self.get_status_tile(status_element).is_some_and(|tile| tile.element_id == self.element_id(ElementType::Bullet))
+		}).count()
+	}
+
 	/// Initialises the simulated board with the state of a board from the World.
 	pub fn load_board(&mut self, board: &Board) {
 		self.board_meta_data = board.meta_data.clone();
 		self.status_elements = board.status_elements.clone();
+		self.player_on_passage = false;
+
+		// A binding that loops back on itself or to a status that doesn't exist would leave the
+		// status without any code to run, so it is given empty code instead.
+		for status_index in 0 .. self.status_elements.len() {
+			if board.status_code(status_index).is_none() {
+				self.status_elements[status_index].code_source = CodeSource::Owned(DosString::new());
+			}
+		}
 
+		let row_width = board_row_width(board);
 		for x in 0 .. BOARD_WIDTH - 2 {
 			for y in 0 .. BOARD_HEIGHT - 2 {
-				self.set_tile(x as i16 + 1, y as i16 + 1, board.tiles[(x + y*(BOARD_WIDTH - 2)) as usize]);
+				self.set_tile(x as i16 + 1, y as i16 + 1, board.tiles[x + y * row_width]);
 			}
 		}
 	}
@@ -543,9 +1191,10 @@ impl BoardSimulator {
 		board.meta_data = self.board_meta_data.clone();
 		board.status_elements = self.status_elements.clone();
 
+		let row_width = board_row_width(board);
 		for x in 0 .. BOARD_WIDTH - 2 {
 			for y in 0 .. BOARD_HEIGHT - 2 {
-				board.tiles[(x + y*(BOARD_WIDTH - 2)) as usize] = self.get_tile(x as i16 + 1, y as i16 + 1).unwrap();
+				board.tiles[x + y * row_width] = self.get_tile(x as i16 + 1, y as i16 + 1).unwrap();
 			}
 		}
 	}
@@ -652,6 +1301,7 @@ impl BoardSimulator {
 					}
 
 					//println!("Continuing: {}", processing_status_index);
+					self.record_profile(|profile| profile.continuation_iterations += 1);
 					let status_element = &self.status_elements[processing_status_index];
 					let continue_result = continuation.next_step(report, processing_status_index, status_element, self);
 
@@ -695,6 +1345,9 @@ impl BoardSimulator {
 	/// `processing_status_index` is the (optional) status index of the tile applying the action.
 	/// `accumulated_data`: see `AccumulatedActionData`.
 	/// `report` contains various information about the outcomes of specific actions.
+	///
+	/// With the `check-invariants` feature, this panics if the action leaves the board broken (see
+	/// `board_invariants`).
 	pub fn apply_action(&mut self,
 			current_tile_x: i16,
 			current_tile_y: i16,
@@ -703,21 +1356,71 @@ impl BoardSimulator {
 			processing_status_index: Option<usize>,
 			accumulated_data: &mut AccumulatedActionData,
 			report: &mut ApplyActionResultReport) {
+		#[cfg(feature = "check-invariants")]
+		let checked_action = action.clone();
+
+		self.apply_action_unchecked(current_tile_x, current_tile_y, action, global_cycle, processing_status_index, accumulated_data, report);
+
+		#[cfg(feature = "check-invariants")]
+		crate::board_invariants::check_invariants_after_action(self, &checked_action);
+	}
+
+	/// Applies an individual action without checking the board afterwards. See `apply_action`.
+	#[allow(clippy::too_many_arguments)]
+	fn apply_action_unchecked(&mut self,
+			current_tile_x: i16,
+			current_tile_y: i16,
+			action: Action,
+			global_cycle: usize,
+			processing_status_index: Option<usize>,
+			accumulated_data: &mut AccumulatedActionData,
+			report: &mut ApplyActionResultReport) {
 		//println!("{}x{}: {:?}", current_tile_x, current_tile_y, action);
+		if let Some(ref mut action_log) = self.action_log {
+			action_log.push(action.clone());
+		}
+		self.actions_applied += 1;
+		self.record_profile(|profile| profile.actions_applied += 1);
+
 		match action {
 			Action::SetTile{x, y, tile, status_element} => {
+				// The board edge never changes, so things like bullets fired into it just disappear
+				// instead of leaving statuses behind on it.
+				if is_board_edge_pos(x, y) {
+					return;
+				}
+
+				// A status that replaces another one is on top of whatever the old one was on, like
+				// when an object on water does `#become lion`.
+				let replaced_under_tile_opt = self.get_first_status_for_pos(x, y).map(|(_, replaced_status)| {
+					(replaced_status.under_element_id, replaced_status.under_colour)
+				});
+				// Replacing a tile that already has a status doesn't make the list any longer.
+				let replaces_status = replaced_under_tile_opt.is_some();
+				let at_status_limit = self.status_element_limit.is_some_and(|limit| self.status_elements.len() >= limit);
+				if status_element.is_some() && at_status_limit && !replaces_status {
+					let message = format!("The status at {}, {} couldn't add an element at {}, {}, because the board already has {} status elements",
+						current_tile_x, current_tile_y, x, y, self.status_elements.len());
+					accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, message));
+					return;
+				}
+
 				self.set_tile(x, y, tile);
 				let removed_indices = self.remove_status_for_pos(x, y);
 				for removed_index in removed_indices {
 					report.removed_status_indices.push(removed_index);
 				}
 
-				if let Some(status_element) = status_element {
+				if let Some(mut status_element) = status_element {
+					if let Some((under_element_id, under_colour)) = replaced_under_tile_opt {
+						status_element.under_element_id = under_element_id;
+						status_element.under_colour = under_colour;
+					}
 					self.status_elements.push(status_element);
 				}
 			}
 			Action::SetTileElementIdAndColour{x, y, element_id, colour} => {
-				if let Some(ref mut tile) = self.get_tile_mut(x, y) {
+				if let Some(mut tile) = self.get_tile(x, y) {
 					if let Some(element_id) = element_id {
 						tile.element_id = element_id;
 					}
@@ -725,11 +1428,13 @@ impl BoardSimulator {
 					if let Some(colour) = colour {
 						tile.colour = colour;
 					}
+					self.set_tile(x, y, tile);
 				}
 			}
 			Action::SetColour{x, y, colour} => {
-				if let Some(ref mut tile) = self.get_tile_mut(x, y) {
+				if let Some(mut tile) = self.get_tile(x, y) {
 					tile.colour = colour;
+					self.set_tile(x, y, tile);
 				}
 			}
 			Action::PushTile{x, y, offset_x, offset_y} => {
@@ -742,18 +1447,23 @@ impl BoardSimulator {
 				}
 			}
 			Action::MoveTile{from_x, from_y, to_x, to_y, offset_x, offset_y, check_push, is_player} => {
+				// A status moving itself is the one that moves, even if it shares its position with
+				// another status. Otherwise it's the first status there, like in DOS ZZT.
+				let moving_status_index = processing_status_index
+					.filter(|&status_index| self.status_elements.get(status_index).is_some_and(|status| (status.location_x as i16, status.location_y as i16) == (from_x, from_y)))
+					.or_else(|| self.get_first_status_for_pos(from_x, from_y).map(|(status_index, _)| status_index));
 				if check_push {
 					let current_tile_behaviour = self.behaviour_for_pos(current_tile_x, current_tile_y);
 					let can_squash = current_tile_behaviour.can_squash();
 					let push_blocked = self.push_tile(to_x, to_y, offset_x, offset_y, is_player, can_squash, global_cycle, processing_status_index, accumulated_data);
 
 					if push_blocked == BlockedStatus::NotBlocked {
-						self.move_tile(from_x, from_y, to_x, to_y);
+						self.move_tile_for_status(from_x, from_y, to_x, to_y, moving_status_index);
 					} else {
 						report.move_was_blocked = BlockedStatus::Blocked;
 					}
 				} else {
-					self.move_tile(from_x, from_y, to_x, to_y);
+					self.move_tile_for_status(from_x, from_y, to_x, to_y, moving_status_index);
 				}
 			}
 			Action::SendBoardMessage(board_message) => {
@@ -769,25 +1479,45 @@ impl BoardSimulator {
 				self.status_elements[status_index].code_source = CodeSource::Bound(bind_to_index);
 			}
 			Action::ModifyPlayerItem{item_type, offset, require_exact_amount} => {
+				self.side_bar_health = None;
 				if let Some(current_item_value) = item_type.get_from_world_header_mut(&mut self.world_header) {
-					if offset < 0 && *current_item_value + offset < 0 {
+					if offset < 0 && *current_item_value as i32 + offset < 0 {
 						if require_exact_amount {
 							report.take_player_item_failed = true;
 						} else {
 							*current_item_value = 0;
 						}
+					} else if let Some(new_item_value) = self.settings.item_overflow_policy.add(*current_item_value, offset) {
+						*current_item_value = new_item_value;
 					} else {
-						*current_item_value += offset;
+						let message = format!("Changing the player's {:?} ({}) by {} would overflow, so it was left as it is",
+							item_type, current_item_value, offset);
+						accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, message));
 					}
 				}
 			}
+			Action::EndGame => {
+				if self.world_header.player_health > 0 {
+					self.side_bar_health.get_or_insert(self.world_header.player_health);
+				}
+				self.world_header.player_health = 0;
+			}
+			Action::SetExtendedColours{status_index, extended_colours} => {
+				let status_element = &mut self.status_elements[status_index];
+				status_element.extended_colours = extended_colours;
+			}
 			Action::CheckRestartOnZapped => {
 				if self.board_meta_data.restart_on_zap {
 					self.restart_player_on_board(&mut accumulated_data.board_messages);
 				}
 			}
 			Action::ModifyPlayerKeys{index, value} => {
-				self.world_header.player_keys[index as usize] = value;
+				if index == 0 {
+					let gems_low_byte = self.world_header.player_gems as u16 & 0xff;
+					self.world_header.player_gems = (gems_low_byte | ((value as u16) << 8)) as i16;
+				} else {
+					self.world_header.player_keys[index as usize - 1] = value;
+				}
 			}
 			Action::SetLeader{status_index, leader} => {
 				self.status_elements[status_index].leader = leader;
@@ -849,6 +1579,8 @@ impl BoardSimulator {
 							if let Some(new_code) = new_code_opt {
 								*self.get_status_index_code_mut(status_index) = new_code;
 							}
+
+							self.record_message_delivery(current_status_index, status_index, label, *operation);
 						}
 					}
 				}
@@ -874,8 +1606,8 @@ impl BoardSimulator {
 				self.world_header.energy_cycles = new_energy_cycles;
 			}
 			Action::SetFlag(name) => {
-				// Don't set the same flag twice:
-				if self.world_header.last_matching_flag(name.clone()).is_none() {
+				// Don't set the same flag twice (reserved flags are already set):
+				if !self.flag_is_set(&name) {
 					if let Some(flag_index) = self.world_header.first_empty_flag() {
 						let upper_name = name.to_upper();
 						self.world_header.flag_names[flag_index] = upper_name;
@@ -899,7 +1631,7 @@ impl BoardSimulator {
 				accumulated_data.should_check_time_elapsed = true;
 			}
 			Action::SetAsPlayerTile{x, y} => {
-				let mut player_colour = 0x1f;
+				let mut player_colour = PLAYER_COLOUR;
 
 				if self.world_header.energy_cycles > 0 {
 					// When energised the player's background alternates between black and a colour, when
@@ -908,10 +1640,10 @@ impl BoardSimulator {
 					player_colour = (colours[global_cycle % colours.len()] << 4) | 0xf;
 				}
 
-				if let Some(ref mut tile) = self.get_tile_mut(x, y) {
-					tile.element_id = ElementType::Player as u8;
-					tile.colour = player_colour;
-				}
+				self.set_tile(x, y, BoardTile {
+					element_id: ElementType::Player as u8,
+					colour: player_colour,
+				});
 			}
 		}
 	}
@@ -923,9 +1655,7 @@ impl BoardSimulator {
 		let ord_y = player_y.cmp(&from_y);
 
 		let choose_rnd_direction = |dir_a, dir_b| {
-			let mut rng = rand::thread_rng();
-			let random_bool: bool = rng.gen();
-			if random_bool {
+			if self.random_bool() {
 				dir_a
 			} else {
 				dir_b
@@ -974,6 +1704,15 @@ impl AccumulatedActionData {
 	}
 }
 
+/// True if the status at `status_index` with the given `cycle` is processed in the step where the
+/// global cycle is `global_cycle`. Statuses with a cycle of 0 are never processed.
+pub fn status_is_processed_in_cycle(status_index: usize, cycle: i16, global_cycle: usize) -> bool {
+	// Weird cycle calculation to match the original game. This makes it so if there are a bunch of
+	// statuses with cycle 3, every third one will execute on one frame, then shift across the
+	// starting index, and from that start, every third one from there executes.
+	cycle > 0 && (global_cycle as isize - (status_index as isize % cycle as isize)) % cycle as isize == 0
+}
+
 /// To simulate a frame in a ZZT board, all you have to do is loop over the `status_elements` list
 /// in order and call their respective step functions (or not, if the cycle of the status is > 1,
 /// meaning it is only sometimes simulated).
@@ -1046,7 +1785,12 @@ impl BoardSimulatorStepState {
 						removed_below_or_at += 1;
 					}
 				}
-				*processing_status_index -= removed_below_or_at;
+				// When status 0 removes itself, ZZT's index goes to -1, so the next partial step
+				// starts again from status 0, just like it does at the start of the step.
+				match processing_status_index.checked_sub(removed_below_or_at) {
+					Some(new_processing_status_index) => *processing_status_index = new_processing_status_index,
+					None => self.processing_status_index_opt = None,
+				}
 			}
 			false
 		} else {
@@ -1059,25 +1803,33 @@ impl BoardSimulatorStepState {
 	/// `sim` is a reference to the `BoardSimulator` where the simulation takes place.
 	/// Returns the `ApplyActionResultReport` with various information about the latest actions that
 	/// were just applied.
-	fn process_status(&mut self, status_index: usize, sim: &mut BoardSimulator) -> ApplyActionResultReport {
+	pub fn process_status(&mut self, status_index: usize, sim: &mut BoardSimulator) -> ApplyActionResultReport {
 		let mut step_result = ActionResult::do_nothing();
 
 		let status_element = &sim.status_elements[status_index];
 		let tile_x = status_element.location_x as i16;
 		let tile_y = status_element.location_y as i16;
 
-		if status_element.cycle > 0 {
-			// Weird cycle calculation to match the original game. This makes it so if there are a
-			// bunch of statuses with cycle 3, every third one will execute on one frame, then
-			// shift across the starting index, and from that start, every third one from there
-			// executes.
-			if (self.global_cycle as isize - (status_index as isize % status_element.cycle as isize)) % status_element.cycle as isize == 0 {
-				//println!("processing status: {} {:?}", status_index, status_element);
-				let ref behaviour = sim.behaviour_for_pos(tile_x, tile_y);
-				step_result = behaviour.step(self.event, &status_element, status_index, sim);
-			}
+		let is_processed = status_is_processed_in_cycle(status_index, status_element.cycle, self.global_cycle);
+		let profile_start = if is_processed && sim.is_profiling() {
+			let element_id = sim.get_tile(tile_x, tile_y).map_or(0, |tile| tile.element_id);
+			Some((element_id, std::time::Instant::now()))
+		} else {
+			None
+		};
+
+		if is_processed {
+			//println!("processing status: {} {:?}", status_index, status_element);
+			let ref behaviour = sim.behaviour_for_pos(tile_x, tile_y);
+			step_result = behaviour.step(self.event, &status_element, status_index, sim);
 		}
 
-		sim.apply_action_result(tile_x, tile_y, step_result, self.global_cycle, Some(status_index), &mut self.accumulated_data)
+		let report = sim.apply_action_result(tile_x, tile_y, step_result, self.global_cycle, Some(status_index), &mut self.accumulated_data);
+
+		if let Some((element_id, start_time)) = profile_start {
+			sim.record_profile(|profile| profile.add_status_time(element_id, start_time.elapsed()));
+		}
+
+		report
 	}
 }
@@ -0,0 +1,45 @@
+use zzt_file_format::BoardTile;
+
+/// The tiles of a board, run-length encoded like they are in a world file, so boards that aren't
+/// being played take up less memory (see `RuzztEngine::set_compact_idle_boards`). Most boards are
+/// largely empty or filled with the same wall, so they shrink to a fraction of their size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RleTiles {
+	/// Each run of identical tiles, as the number of tiles in the run and the tile.
+	runs: Vec<(u16, BoardTile)>,
+}
+
+impl RleTiles {
+	/// Encode `tiles`, which are usually in the same order as `Board::tiles`.
+	pub fn encode(tiles: &[BoardTile]) -> RleTiles {
+		let mut runs: Vec<(u16, BoardTile)> = vec![];
+		for tile in tiles {
+			match runs.last_mut() {
+				Some((run_length, run_tile)) if run_tile == tile && *run_length < u16::MAX => *run_length += 1,
+				_ => runs.push((1, *tile)),
+			}
+		}
+		RleTiles {
+			runs,
+		}
+	}
+
+	/// Get the tiles back in the order they were encoded in.
+	pub fn decode(&self) -> Vec<BoardTile> {
+		let mut tiles = Vec::with_capacity(self.tile_count());
+		for &(run_length, tile) in &self.runs {
+			tiles.extend(std::iter::repeat_n(tile, run_length as usize));
+		}
+		tiles
+	}
+
+	/// Get the number of tiles that were encoded.
+	pub fn tile_count(&self) -> usize {
+		self.runs.iter().map(|&(run_length, _)| run_length as usize).sum()
+	}
+
+	/// Get the number of runs of identical tiles, which is what the memory used depends on.
+	pub fn run_count(&self) -> usize {
+		self.runs.len()
+	}
+}
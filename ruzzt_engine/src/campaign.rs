@@ -0,0 +1,91 @@
+use zzt_file_format::WorldHeader;
+use zzt_file_format::dosstring::DosString;
+
+/// The name of the campaign manifest file, which is read from the folder the worlds are in. See
+/// `CampaignCarryOver::parse` for the format.
+pub const CAMPAIGN_MANIFEST_FILE_NAME: &str = "CAMPAIGN.TXT";
+
+/// What is carried over to the next world when a world opens another one during a game (eg. with a
+/// `!-NEXT.ZZT;Go on` scroll link). DOS ZZT doesn't carry anything over, so this is only used for
+/// worlds that come with a campaign manifest.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CampaignCarryOver {
+	/// The names of the flags (in upper case) that stay set in the next world.
+	pub flags: Vec<DosString>,
+	/// When true, every flag stays set in the next world, not just the ones in `flags`.
+	pub all_flags: bool,
+	/// When true, the player keeps their score.
+	pub score: bool,
+	/// When true, the player keeps their health, ammo, gems, torches and keys.
+	pub inventory: bool,
+}
+
+impl CampaignCarryOver {
+	/// Parse a campaign manifest. Blank lines and lines starting with `#` are ignored. Every other
+	/// line is one of:
+	/// - `flags: NAME, NAME, ...` to carry over the listed flags, or `flags: *` for every flag.
+	/// - `score` to carry over the score.
+	/// - `inventory` to carry over health, ammo, gems, torches and keys.
+	pub fn parse(text: &str) -> Result<CampaignCarryOver, String> {
+		let mut carry_over = CampaignCarryOver::default();
+
+		for (line_index, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let (keyword, value) = match line.split_once(':') {
+				Some((keyword, value)) => (keyword.trim(), value.trim()),
+				None => (line, ""),
+			};
+			match keyword.to_ascii_lowercase().as_str() {
+				"flags" => {
+					for flag_name in value.split(',').map(|flag_name| flag_name.trim()).filter(|flag_name| !flag_name.is_empty()) {
+						if flag_name == "*" {
+							carry_over.all_flags = true;
+						} else {
+							carry_over.flags.push(DosString::from_str(flag_name).to_upper());
+						}
+					}
+				}
+				"score" => carry_over.score = true,
+				"inventory" => carry_over.inventory = true,
+				_ => return Err(format!("Line {}: Unknown setting: {}", line_index + 1, keyword)),
+			}
+		}
+
+		Ok(carry_over)
+	}
+
+	/// Copy the state that is carried over from the world header of the game in progress (`from`)
+	/// into the header of the world that is being opened (`to`).
+	pub fn apply(&self, from: &WorldHeader, to: &mut WorldHeader) {
+		for flag_name in &from.flag_names {
+			let is_carried = self.all_flags || self.flags.contains(flag_name);
+			if !flag_name.is_empty() && is_carried && to.last_matching_flag(flag_name.clone()).is_none() {
+				if let Some(flag_index) = to.first_empty_flag() {
+					to.flag_names[flag_index] = flag_name.clone();
+				}
+			}
+		}
+
+		if self.score {
+			to.player_score = from.player_score;
+		}
+
+		if self.inventory {
+			to.player_health = from.player_health;
+			to.player_ammo = from.player_ammo;
+			to.player_gems = from.player_gems;
+			to.player_keys = from.player_keys;
+			// Torches and stones only exist in ZZT and Super ZZT worlds respectively.
+			if let (Some(to_torches), Some(from_torches)) = (&mut to.player_torches, from.player_torches) {
+				*to_torches = from_torches;
+			}
+			if let (Some(to_stones), Some(from_stones)) = (&mut to.player_stones, from.player_stones) {
+				*to_stones = from_stones;
+			}
+		}
+	}
+}
@@ -1,9 +1,19 @@
 use crate::console::*;
+use crate::constants::CAPTION_TIME;
 
 use num::FromPrimitive;
+use serde_derive::{Serialize, Deserialize};
 use zzt_file_format::dosstring::DosString;
 
-#[derive(Clone)]
+/// When a caption is queued while another is showing, the one showing is cut short so it is
+/// shown for at most this many more steps. DOS ZZT replaces it straight away, which means it is
+/// never seen if both were sent in the same step.
+pub const CAPTION_REPLACEMENT_TIME: isize = 8;
+/// The most captions that can be waiting to be shown. When another is queued, the oldest waiting
+/// caption is dropped.
+pub const MAX_PENDING_CAPTIONS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CaptionState {
 	pub text_with_padding: DosString,
 	pub time_left: isize,
@@ -17,10 +27,16 @@ impl CaptionState {
 
 		CaptionState {
 			text_with_padding,
-			time_left: 24,
+			time_left: CAPTION_TIME,
 		}
 	}
 
+	/// Check if this is the caption for `text`.
+	pub fn has_text(&self, text: &DosString) -> bool {
+		let data = &self.text_with_padding.data;
+		data.len() == text.len() + 2 && data[1 .. data.len() - 1] == text.data[..]
+	}
+
 	pub fn draw_caption(&self, console_state: &mut ConsoleState) {
 		let fg_num = ((self.time_left - 9) % 7) + 9;
 		let fg = ConsoleColour::from_u8(fg_num as u8).unwrap();
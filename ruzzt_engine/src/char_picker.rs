@@ -0,0 +1,121 @@
+use crate::console::*;
+use crate::event::*;
+
+/// The number of characters in each row of the picker.
+const PICKER_COLUMNS: usize = 32;
+/// The number of rows of characters in the picker.
+const PICKER_ROWS: usize = 256 / PICKER_COLUMNS;
+/// The width of the picker box, including its border.
+const PICKER_WIDTH: usize = PICKER_COLUMNS + 4;
+/// The height of the picker box, including its border.
+const PICKER_HEIGHT: usize = PICKER_ROWS + 6;
+/// The x position on the screen of the left side of the picker box.
+const PICKER_X: usize = 30 - PICKER_WIDTH / 2;
+/// The y position on the screen of the top of the picker box.
+const PICKER_Y: usize = 5;
+
+/// What happened when a typing event was given to a `CharPickerState`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharPickerAction {
+	/// The picker is still open.
+	None,
+	/// The character with the given code was picked, so the picker should be closed and the
+	/// character typed.
+	Picked(u8),
+	/// The picker should be closed without typing anything.
+	Close,
+}
+
+/// The state of the character picker, which shows all 256 characters of the code page in a grid
+/// so characters that aren't on the keyboard can be typed into text inputs and the text editor.
+/// It is drawn over the middle of the board.
+#[derive(Clone)]
+pub struct CharPickerState {
+	/// The code of the highlighted character.
+	selected: u8,
+}
+
+impl Default for CharPickerState {
+	fn default() -> CharPickerState {
+		CharPickerState::new()
+	}
+}
+
+impl CharPickerState {
+	/// Make a new character picker with the first character after the control characters
+	/// highlighted.
+	pub fn new() -> CharPickerState {
+		CharPickerState {
+			selected: 0x20,
+		}
+	}
+
+	/// Get the code of the highlighted character.
+	pub fn selected(&self) -> u8 {
+		self.selected
+	}
+
+	/// Move the highlight with a single typing `event`. Typing a character on the keyboard
+	/// highlights it.
+	pub fn process_typing(&mut self, event: TypingEvent) -> CharPickerAction {
+		let column = self.selected as usize % PICKER_COLUMNS;
+		let row = self.selected as usize / PICKER_COLUMNS;
+		let mut move_to = |column: usize, row: usize| {
+			self.selected = (row * PICKER_COLUMNS + column) as u8;
+		};
+
+		match event {
+			TypingEvent::Enter => return CharPickerAction::Picked(self.selected),
+			TypingEvent::Escape | TypingEvent::OpenCharPicker => return CharPickerAction::Close,
+			TypingEvent::Char(char_code) => move_to(char_code as usize % PICKER_COLUMNS, char_code as usize / PICKER_COLUMNS),
+			TypingEvent::Left | TypingEvent::Backspace => move_to((column + PICKER_COLUMNS - 1) % PICKER_COLUMNS, row),
+			TypingEvent::Right => move_to((column + 1) % PICKER_COLUMNS, row),
+			TypingEvent::Up => move_to(column, (row + PICKER_ROWS - 1) % PICKER_ROWS),
+			TypingEvent::Down => move_to(column, (row + 1) % PICKER_ROWS),
+			TypingEvent::Home => move_to(0, row),
+			TypingEvent::End => move_to(PICKER_COLUMNS - 1, row),
+			TypingEvent::PageUp => move_to(column, 0),
+			TypingEvent::PageDown => move_to(column, PICKER_ROWS - 1),
+			TypingEvent::Delete | TypingEvent::None => {}
+		}
+		CharPickerAction::None
+	}
+
+	/// Renders the character picker over the middle of the board.
+	pub fn draw_char_picker(&self, console_state: &mut ConsoleState) {
+		let bg = ConsoleColour::Blue;
+
+		for y in 0 .. PICKER_HEIGHT {
+			for x in 0 .. PICKER_WIDTH {
+				let char_code = match (x, y) {
+					(0, 0) => 0xc9,
+					(x, 0) if x == PICKER_WIDTH - 1 => 0xbb,
+					(0, y) if y == PICKER_HEIGHT - 1 => 0xc8,
+					(x, y) if x == PICKER_WIDTH - 1 && y == PICKER_HEIGHT - 1 => 0xbc,
+					(0, _) => 0xba,
+					(x, _) if x == PICKER_WIDTH - 1 => 0xba,
+					(_, 0) => 0xcd,
+					(_, y) if y == PICKER_HEIGHT - 1 => 0xcd,
+					_ => 0,
+				};
+				*console_state.get_char_mut(PICKER_X + x, PICKER_Y + y) = ConsoleChar::new(char_code, bg, ConsoleColour::White);
+			}
+		}
+
+		let title = b"Pick a character";
+		console_state.draw_text_at(PICKER_X + (PICKER_WIDTH - title.len()) / 2, PICKER_Y + 1, title, bg, ConsoleColour::Yellow);
+
+		for char_code in 0 ..= 255u8 {
+			let x = PICKER_X + 2 + char_code as usize % PICKER_COLUMNS;
+			let y = PICKER_Y + 3 + char_code as usize / PICKER_COLUMNS;
+			*console_state.get_char_mut(x, y) = if char_code == self.selected {
+				ConsoleChar::new(char_code, ConsoleColour::Cyan, ConsoleColour::Black)
+			} else {
+				ConsoleChar::new(char_code, bg, ConsoleColour::White)
+			};
+		}
+
+		let code_text = format!("Code: {:3} (0x{:02X})", self.selected, self.selected);
+		console_state.draw_text_at(PICKER_X + 2, PICKER_Y + PICKER_ROWS + 4, code_text.as_bytes(), bg, ConsoleColour::LightCyan);
+	}
+}
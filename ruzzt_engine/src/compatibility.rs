@@ -0,0 +1,405 @@
+use crate::board_message::{BoardMessage, LogLevel};
+use crate::board_simulator::*;
+use crate::console::{ConsoleChar, ConsoleState};
+use crate::engine::RuzztEngine;
+use crate::event::Event;
+
+use zzt_file_format::{BoardTile, World};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Get a hash of the state of the board being simulated, to compare against the same hash taken
+/// from DOS ZZT. This is the 64-bit FNV-1a hash of these bytes, in order:
+/// - The element ID then colour of every tile, in rows from the top-left, not including the board
+///   edges (60x25 tiles).
+/// - For each status element in order: the location x and y, the step x and y (as 16-bit little
+///   endian), the cycle (16-bit little endian), param1, param2, param3, the follower and leader (as
+///   16-bit little endian), the under element ID and colour, and the current code instruction
+///   (16-bit little endian).
+///
+/// Anything that DOS ZZT doesn't keep on the board (like the code itself) isn't included, so the
+/// hash can be calculated by external tools from the memory of a running DOS ZZT.
+pub fn board_hash(sim: &BoardSimulator) -> u64 {
+	fnv1a_hash(&board_hash_bytes(sim))
+}
+
+/// Get the 64-bit FNV-1a hash of `bytes`. Unlike Rust's default hasher, this is guaranteed to give
+/// the same hash on every platform and version.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Get the bytes that are hashed by `board_hash`.
+pub fn board_hash_bytes(sim: &BoardSimulator) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity((BOARD_WIDTH - 2) * (BOARD_HEIGHT - 2) * 2 + sim.status_elements.len() * 20);
+
+	for (_, _, tile) in sim.board_tiles() {
+		bytes.push(tile.element_id);
+		bytes.push(tile.colour);
+	}
+
+	for status in &sim.status_elements {
+		bytes.push(status.location_x);
+		bytes.push(status.location_y);
+		bytes.extend_from_slice(&status.step_x.to_le_bytes());
+		bytes.extend_from_slice(&status.step_y.to_le_bytes());
+		bytes.extend_from_slice(&status.cycle.to_le_bytes());
+		bytes.push(status.param1);
+		bytes.push(status.param2);
+		bytes.push(status.param3);
+		bytes.extend_from_slice(&status.follower.to_le_bytes());
+		bytes.extend_from_slice(&status.leader.to_le_bytes());
+		bytes.push(status.under_element_id);
+		bytes.push(status.under_colour);
+		bytes.extend_from_slice(&status.code_current_instruction.to_le_bytes());
+	}
+
+	bytes
+}
+
+/// Get the character used for `event` in compatibility fixtures, or None if the event can't be
+/// recorded in a fixture.
+fn event_to_fixture_char(event: Event) -> Option<char> {
+	let c = match event {
+		Event::None => '.',
+		Event::Up => 'u',
+		Event::Down => 'd',
+		Event::Left => 'l',
+		Event::Right => 'r',
+		Event::ShootUp => 'U',
+		Event::ShootDown => 'D',
+		Event::ShootLeft => 'L',
+		Event::ShootRight => 'R',
+		Event::LightTorch => 't',
+		_ => return None,
+	};
+	Some(c)
+}
+
+/// Get the `Event` for the given character in a compatibility fixture. See
+/// `event_to_fixture_char`.
+fn fixture_char_to_event(c: char) -> Option<Event> {
+	let event = match c {
+		'.' => Event::None,
+		'u' => Event::Up,
+		'd' => Event::Down,
+		'l' => Event::Left,
+		'r' => Event::Right,
+		'U' => Event::ShootUp,
+		'D' => Event::ShootDown,
+		'L' => Event::ShootLeft,
+		'R' => Event::ShootRight,
+		't' => Event::LightTorch,
+		_ => return None,
+	};
+	Some(event)
+}
+
+/// A single game cycle in a `CompatibilityFixture`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureStep {
+	/// The input given for the cycle.
+	pub event: Event,
+	/// The `board_hash` after the cycle finished.
+	pub board_hash: u64,
+	/// The bytes that were hashed to get `board_hash` (see `board_hash_bytes`), if the recording
+	/// included them. These let the recorded board be shown next to RUZZT's (see
+	/// `ComparisonRunner`).
+	pub board_bytes: Option<Vec<u8>>,
+}
+
+/// A recording of a game session, with the hash of the board after every game cycle, so RUZZT can
+/// be checked against the game that recorded it (usually DOS ZZT).
+///
+/// Fixtures are stored as text. Blank lines and lines starting with `#` are ignored. The first
+/// other line is `world <file name>`, with the name of the world file relative to the fixture.
+/// Every line after that is one game cycle: a character for the input (see
+/// `event_to_fixture_char`), a space, then the `board_hash` as 16 hex digits. A cycle can also end
+/// with a space and the bytes that were hashed (see `board_hash_bytes`) in hex.
+///
+/// The session starts with the world's starting board paused, the same as after pressing P in the
+/// title screen. Scrolls are closed as soon as they open. Random numbers aren't recorded, so only
+/// boards without randomness can be compared for now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompatibilityFixture {
+	/// The name of the world file that the session was recorded on.
+	pub world_file_name: String,
+	pub steps: Vec<FixtureStep>,
+}
+
+/// The first step of a replayed fixture that didn't match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureMismatch {
+	/// The index of the step in `CompatibilityFixture::steps`.
+	pub step_index: usize,
+	pub expected_hash: u64,
+	pub actual_hash: u64,
+}
+
+impl CompatibilityFixture {
+	/// Parse a fixture from its text form.
+	pub fn parse(text: &str) -> Result<CompatibilityFixture, String> {
+		let mut world_file_name = None;
+		let mut steps = vec![];
+
+		for (line_index, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			if world_file_name.is_none() {
+				if let Some(name) = line.strip_prefix("world ") {
+					world_file_name = Some(name.trim().to_string());
+					continue;
+				} else {
+					return Err(format!("Line {}: Expected the world file name", line_index + 1));
+				}
+			}
+
+			let mut parts = line.split_whitespace();
+			let event_str = parts.next().unwrap_or("");
+			let hash_str = parts.next().ok_or_else(|| format!("Line {}: Missing board hash", line_index + 1))?;
+
+			let mut event_chars = event_str.chars();
+			let event = match (event_chars.next(), event_chars.next()) {
+				(Some(c), None) => fixture_char_to_event(c),
+				_ => None,
+			}.ok_or_else(|| format!("Line {}: Unknown input: {}", line_index + 1, event_str))?;
+			let board_hash = u64::from_str_radix(hash_str, 16).map_err(|e| format!("Line {}: Invalid board hash: {}", line_index + 1, e))?;
+			let board_bytes = match parts.next() {
+				Some(bytes_str) => Some(parse_hex_bytes(bytes_str).map_err(|e| format!("Line {}: Invalid board bytes: {}", line_index + 1, e))?),
+				None => None,
+			};
+
+			steps.push(FixtureStep {
+				event,
+				board_hash,
+				board_bytes,
+			});
+		}
+
+		let world_file_name = world_file_name.ok_or_else(|| "Fixture has no world file name".to_string())?;
+		Ok(CompatibilityFixture {
+			world_file_name,
+			steps,
+		})
+	}
+
+	/// Get the text form of the fixture, which can be read back with `parse`.
+	pub fn to_text(&self) -> String {
+		let mut text = format!("world {}\n", self.world_file_name);
+		for step in &self.steps {
+			let c = event_to_fixture_char(step.event).unwrap_or('.');
+			text.push_str(&format!("{} {:016x}", c, step.board_hash));
+			if let Some(ref board_bytes) = step.board_bytes {
+				text.push(' ');
+				text.extend(board_bytes.iter().map(|byte| format!("{:02x}", byte)));
+			}
+			text.push('\n');
+		}
+		text
+	}
+
+	/// Play the given `events` in `world` with RUZZT, and make a fixture of the result. This is
+	/// useful for making regression fixtures, or for comparing against a recording from DOS ZZT.
+	/// Events that can't be stored in a fixture are replaced with `Event::None`.
+	pub fn record(world: &World, world_file_name: &str, events: &[Event]) -> CompatibilityFixture {
+		let mut engine = start_engine(world);
+		let steps = events.iter().map(|event| {
+			let event = if event_to_fixture_char(*event).is_some() { *event } else { Event::None };
+			step_cycle(&mut engine, event);
+			FixtureStep {
+				event,
+				board_hash: board_hash(&engine.board_simulator),
+				board_bytes: None,
+			}
+		}).collect();
+
+		CompatibilityFixture {
+			world_file_name: world_file_name.to_string(),
+			steps,
+		}
+	}
+
+	/// Replay the fixture's input in `world`, and compare the board after every step with the
+	/// recorded hashes. Returns the first step that didn't match, if any.
+	pub fn replay(&self, world: &World) -> Result<(), FixtureMismatch> {
+		let mut engine = start_engine(world);
+		for (step_index, step) in self.steps.iter().enumerate() {
+			step_cycle(&mut engine, step.event);
+			let actual_hash = board_hash(&engine.board_simulator);
+			if actual_hash != step.board_hash {
+				return Err(FixtureMismatch {
+					step_index,
+					expected_hash: step.board_hash,
+					actual_hash,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// Replay the fixture's input in `world` like `replay`, and give the screen to
+	/// `take_screenshot` along with the number of steps played so far, before the first step and
+	/// then after every `every_steps` steps. This turns a demo into a series of screenshots. The
+	/// recorded hashes aren't checked.
+	pub fn replay_screenshots(&self, world: &World, every_steps: usize, mut take_screenshot: impl FnMut(usize, &ConsoleState)) {
+		let every_steps = every_steps.max(1);
+		let mut engine = start_engine(world);
+		engine.update_screen();
+		take_screenshot(0, &engine.console_state);
+		for (step_index, step) in self.steps.iter().enumerate() {
+			step_cycle(&mut engine, step.event);
+			let steps_played = step_index + 1;
+			if steps_played % every_steps == 0 {
+				engine.update_screen();
+				take_screenshot(steps_played, &engine.console_state);
+			}
+		}
+	}
+}
+
+/// Parse a string of hex digits, two for each byte.
+fn parse_hex_bytes(hex_str: &str) -> Result<Vec<u8>, String> {
+	if !hex_str.len().is_multiple_of(2) {
+		return Err("Odd number of hex digits".to_string());
+	}
+	(0 .. hex_str.len()).step_by(2).map(|index| {
+		u8::from_str_radix(&hex_str[index .. index + 2], 16).map_err(|e| e.to_string())
+	}).collect()
+}
+
+/// Runs a `CompatibilityFixture` one step at a time, so RUZZT's board can be shown next to the
+/// recorded one while debugging a mismatch. The recorded board can only be shown for fixtures
+/// that include the board bytes.
+pub struct ComparisonRunner {
+	/// The engine playing the fixture's input.
+	pub engine: RuzztEngine,
+	fixture: CompatibilityFixture,
+	/// The number of fixture steps that have been played so far.
+	steps_played: usize,
+	/// The first step that didn't match, if there has been one.
+	first_mismatch: Option<FixtureMismatch>,
+}
+
+impl ComparisonRunner {
+	/// Start playing `fixture` in `world`.
+	pub fn new(world: &World, fixture: CompatibilityFixture) -> ComparisonRunner {
+		ComparisonRunner {
+			engine: start_engine(world),
+			fixture,
+			steps_played: 0,
+			first_mismatch: None,
+		}
+	}
+
+	/// The number of fixture steps that have been played so far.
+	pub fn steps_played(&self) -> usize {
+		self.steps_played
+	}
+
+	/// The total number of steps in the fixture.
+	pub fn step_count(&self) -> usize {
+		self.fixture.steps.len()
+	}
+
+	/// The first step that didn't match, if there has been one so far.
+	pub fn first_mismatch(&self) -> Option<&FixtureMismatch> {
+		self.first_mismatch.as_ref()
+	}
+
+	/// Play the next step of the fixture. Returns true if the board hash matched, or false if it
+	/// didn't or every step has already been played.
+	pub fn step(&mut self) -> bool {
+		let step = match self.fixture.steps.get(self.steps_played) {
+			Some(step) => step,
+			None => return false,
+		};
+
+		step_cycle(&mut self.engine, step.event);
+		let actual_hash = board_hash(&self.engine.board_simulator);
+		let matches = actual_hash == step.board_hash;
+		if !matches && self.first_mismatch.is_none() {
+			self.first_mismatch = Some(FixtureMismatch {
+				step_index: self.steps_played,
+				expected_hash: step.board_hash,
+				actual_hash,
+			});
+		}
+		self.steps_played += 1;
+		matches
+	}
+
+	/// Get the tiles of the recorded board after the last step that was played, in the same order
+	/// as `BoardSimulator::board_tiles`, or None if the fixture doesn't include the board bytes.
+	pub fn reference_tiles(&self) -> Option<Vec<BoardTile>> {
+		let step = self.fixture.steps.get(self.steps_played.checked_sub(1)?)?;
+		let tile_bytes = step.board_bytes.as_ref()?.get(.. (BOARD_WIDTH - 2) * (BOARD_HEIGHT - 2) * 2)?;
+		Some(tile_bytes.chunks(2).map(|tile| BoardTile {
+			element_id: tile[0],
+			colour: tile[1],
+		}).collect())
+	}
+
+	/// Draw the recorded board after the last step that was played, and get the characters for each
+	/// row of the board (not including the board edges). Only the tiles are recorded, so the
+	/// status elements are drawn as they are in RUZZT.
+	pub fn reference_board_chars(&self) -> Option<Vec<Vec<ConsoleChar>>> {
+		let reference_tiles = self.reference_tiles()?;
+		let mut engine = self.engine.clone();
+		for (tile_index, tile) in reference_tiles.into_iter().enumerate() {
+			let x = (tile_index % (BOARD_WIDTH - 2)) as i16 + 1;
+			let y = (tile_index / (BOARD_WIDTH - 2)) as i16 + 1;
+			engine.board_simulator.set_tile(x, y, tile);
+		}
+		engine.scroll_state = None;
+		engine.update_screen();
+
+		Some((0 .. BOARD_HEIGHT - 2).map(|y| {
+			(0 .. BOARD_WIDTH - 2).map(|x| engine.console_state.get_char(x, y)).collect()
+		}).collect())
+	}
+
+	/// Get the 0-based positions of the tiles that are different on RUZZT's board and the recorded
+	/// board. This is empty if the fixture doesn't include the board bytes.
+	pub fn divergent_tiles(&self) -> Vec<(usize, usize)> {
+		let reference_tiles = self.reference_tiles().unwrap_or_default();
+		self.engine.board_simulator.board_tiles().zip(reference_tiles)
+			.filter(|((_, _, tile), reference_tile)| tile != reference_tile)
+			.map(|((x, y, _), _)| (x as usize - 1, y as usize - 1))
+			.collect()
+	}
+}
+
+/// Make an engine with a new game of `world` in progress, paused on the starting board. If `world`
+/// can't be loaded, the game is played on the empty world the engine starts with, so it won't match
+/// any fixture recorded with a real world.
+fn start_engine(world: &World) -> RuzztEngine {
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world.clone(), None).ok();
+	engine.set_in_title_screen(false);
+	engine
+}
+
+/// Give `event` to `engine` and simulate until the game cycle is complete, applying the default
+/// handling of every board message and closing any scrolls that open. Returns the warnings that
+/// were logged with `BoardMessage::EngineLog` during the cycle.
+pub(crate) fn step_cycle(engine: &mut RuzztEngine, event: Event) -> Vec<String> {
+	let mut warnings = vec![];
+	engine.step_whole_cycle(event, 0., |engine, mut board_messages| {
+		while !board_messages.is_empty() {
+			let processing_board_messages = std::mem::take(&mut board_messages);
+			for board_message in processing_board_messages {
+				match board_message {
+					BoardMessage::OpenScroll{..} => continue,
+					BoardMessage::EngineLog(LogLevel::Warning, ref message) => warnings.push(message.clone()),
+					_ => {}
+				}
+				board_messages.extend(engine.process_board_message(board_message));
+			}
+		}
+	});
+	warnings
+}
@@ -1,8 +1,98 @@
+use num::FromPrimitive;
 use num_derive::FromPrimitive;
+use zzt_file_format::dosstring::dos_char_to_char;
+use zzt_file_format::extended_colour::{ExtendedColour, ExtendedColours};
 
+use std::ops::{Index, IndexMut};
+
+/// The width of the screen. This is the same as `ConsoleState::SCREEN_WIDTH`.
 pub const SCREEN_WIDTH: usize = 80;
+/// The height of the screen in the usual 80x25 text mode. See `ScreenMode` for the other mode.
+/// This is the same as `ConsoleState::SCREEN_HEIGHT`.
 pub const SCREEN_HEIGHT: usize = 25;
 
+/// The width of a character in the DOS font, in pixels.
+pub const CHAR_WIDTH: usize = 8;
+/// The height of a character in the DOS font, in pixels.
+pub const CHAR_HEIGHT: usize = 14;
+/// The number of milliseconds that blinking characters are shown for, and then hidden for.
+pub const BLINK_HALF_PERIOD_MS: usize = 225;
+
+/// The text modes that the console can be in. The board and side bar always take up the top 25
+/// rows, so the taller mode has empty space under the board (apart from the side bar background).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenMode {
+	/// 80x25 characters with the 8x14 font on a 640x350 screen, like DOS ZZT.
+	Text80x25,
+	/// 80x50 characters with an 8x8 font on a 640x400 screen, which some ZZT forks use.
+	Text80x50,
+}
+
+impl ScreenMode {
+	/// Get the number of rows of characters on the screen.
+	pub fn height(self) -> usize {
+		match self {
+			ScreenMode::Text80x25 => SCREEN_HEIGHT,
+			ScreenMode::Text80x50 => 50,
+		}
+	}
+
+	/// Get the height of a character in the font for the mode, in pixels.
+	pub fn char_height(self) -> usize {
+		match self {
+			ScreenMode::Text80x25 => CHAR_HEIGHT,
+			ScreenMode::Text80x50 => 8,
+		}
+	}
+
+	/// Get the width and height of the whole screen in pixels, without any scaling.
+	pub fn pixel_size(self) -> (usize, usize) {
+		(SCREEN_WIDTH * CHAR_WIDTH, self.height() * self.char_height())
+	}
+
+	/// Get the other screen mode, for switching between them.
+	pub fn toggled(self) -> ScreenMode {
+		match self {
+			ScreenMode::Text80x25 => ScreenMode::Text80x50,
+			ScreenMode::Text80x50 => ScreenMode::Text80x25,
+		}
+	}
+
+	/// Get the x/y position of the character on the screen that contains the given pixel position,
+	/// or None if the pixel is outside the screen. The pixel position is relative to the top-left of
+	/// the screen (see `pixel_size`), without any scaling.
+	pub fn pixel_to_screen_pos(self, pixel_x: i32, pixel_y: i32) -> Option<(usize, usize)> {
+		if pixel_x < 0 || pixel_y < 0 {
+			return None;
+		}
+
+		let x = pixel_x as usize / CHAR_WIDTH;
+		let y = pixel_y as usize / self.char_height();
+		if x < SCREEN_WIDTH && y < self.height() {
+			Some((x, y))
+		} else {
+			None
+		}
+	}
+}
+
+/// Get the x/y position of the character on the screen that contains the given pixel position, or
+/// None if the pixel is outside the screen. The pixel position is relative to the top-left of the
+/// 640x350 screen, without any scaling.
+pub fn pixel_to_screen_pos(pixel_x: i32, pixel_y: i32) -> Option<(usize, usize)> {
+	ScreenMode::Text80x25.pixel_to_screen_pos(pixel_x, pixel_y)
+}
+
+/// Get the unicode character to show for the DOS character `char_code` in text versions of the
+/// screen. The null character is shown as a space, the same as it is in DOS.
+fn console_char_to_text_char(char_code: u8) -> char {
+	if char_code == 0 {
+		' '
+	} else {
+		dos_char_to_char(char_code)
+	}
+}
+
 /// A single character in the `ConsoleState`'s buffer.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ConsoleChar {
@@ -11,6 +101,11 @@ pub struct ConsoleChar {
 	/// and off regularly.
 	pub background: ConsoleColour,
 	pub foreground: ConsoleColour,
+	/// The colours to draw the character with instead of `foreground` and `background`, for
+	/// front-ends that can show more than 16 colours. `foreground` and `background` are still set
+	/// to the closest standard colours for front-ends that can't. See
+	/// `StatusElement::extended_colours`.
+	pub extended_colours: Option<ExtendedColours>,
 }
 
 impl ConsoleChar {
@@ -19,6 +114,7 @@ impl ConsoleChar {
 			char_code,
 			background,
 			foreground,
+			extended_colours: None,
 		}
 	}
 	
@@ -28,6 +124,74 @@ impl ConsoleChar {
 			char_code: 0,
 			background: ConsoleColour::Black,
 			foreground: ConsoleColour::Black,
+			extended_colours: None,
+		}
+	}
+	
+	/// True if the character blinks, which is when the blink bit of the background is set.
+	pub fn is_blinking(&self) -> bool {
+		self.background as u8 >= 8
+	}
+	
+	/// Get the (red, green, blue) values of the extended foreground colour, if there is one.
+	pub fn extended_foreground_rgb(&self) -> Option<(u8, u8, u8)> {
+		self.extended_colours.and_then(|colours| colours.foreground).map(ExtendedColour::to_rgb)
+	}
+	
+	/// Get the (red, green, blue) values of the extended background colour, if there is one.
+	pub fn extended_background_rgb(&self) -> Option<(u8, u8, u8)> {
+		self.extended_colours.and_then(|colours| colours.background).map(ExtendedColour::to_rgb)
+	}
+}
+
+/// A rectangle of characters on the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenRect {
+	/// The x position of the left column of the rectangle.
+	pub x: usize,
+	/// The y position of the top row of the rectangle.
+	pub y: usize,
+	/// The number of columns in the rectangle.
+	pub width: usize,
+	/// The number of rows in the rectangle.
+	pub height: usize,
+}
+
+impl ScreenRect {
+	/// Make a rectangle with its top-left corner at `x`/`y`.
+	pub fn new(x: usize, y: usize, width: usize, height: usize) -> ScreenRect {
+		ScreenRect {
+			x,
+			y,
+			width,
+			height,
+		}
+	}
+
+	/// Check if the character at `x`/`y` is within the rectangle.
+	pub fn contains(&self, x: usize, y: usize) -> bool {
+		x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+	}
+
+	/// Get the smallest rectangle that contains this one and the character at `x`/`y`.
+	pub fn including(self, x: usize, y: usize) -> ScreenRect {
+		let left = self.x.min(x);
+		let top = self.y.min(y);
+		let right = (self.x + self.width).max(x + 1);
+		let bottom = (self.y + self.height).max(y + 1);
+		ScreenRect::new(left, top, right - left, bottom - top)
+	}
+
+	/// Get the part of this rectangle that is also in `other`, or None if they don't overlap.
+	pub fn intersection(self, other: ScreenRect) -> Option<ScreenRect> {
+		let left = self.x.max(other.x);
+		let top = self.y.max(other.y);
+		let right = (self.x + self.width).min(other.x + other.width);
+		let bottom = (self.y + self.height).min(other.y + other.height);
+		if left < right && top < bottom {
+			Some(ScreenRect::new(left, top, right - left, bottom - top))
+		} else {
+			None
 		}
 	}
 }
@@ -35,17 +199,146 @@ impl ConsoleChar {
 /// The current state of the characters displayed in the console.
 #[derive(Clone)]
 pub struct ConsoleState {
-	pub screen_chars: [[ConsoleChar; SCREEN_WIDTH]; SCREEN_HEIGHT],
+	/// The rows of characters on the screen. There are `screen_mode.height()` rows.
+	pub screen_chars: Vec<[ConsoleChar; SCREEN_WIDTH]>,
+	/// The text mode the screen is in.
+	screen_mode: ScreenMode,
+	/// A copy of `screen_chars` from the last time `take_dirty_cells` was called, or None if every
+	/// character should be considered changed.
+	taken_screen_chars: Option<Vec<[ConsoleChar; SCREEN_WIDTH]>>,
+	/// The time that blinking is based on, in milliseconds (see `set_blink_time_ms`).
+	blink_time_ms: usize,
+	/// The value of `blink_visible` the last time `take_dirty_cells` was called.
+	taken_blink_visible: bool,
+	/// True if a character may have been changed since the last time `take_dirty_cells` was
+	/// called. See `is_dirty`.
+	changed: bool,
 }
 
 impl ConsoleState {
-	/// Create a new ConsoleState with a completely black buffer.
+	/// The width of the screen, in characters.
+	pub const SCREEN_WIDTH: usize = SCREEN_WIDTH;
+	/// The height of the screen in the usual 80x25 text mode, in characters. Use `height` for the
+	/// height in the current mode.
+	pub const SCREEN_HEIGHT: usize = SCREEN_HEIGHT;
+	
+	/// Create a new ConsoleState in the 80x25 text mode with a completely black buffer.
 	pub fn new() -> ConsoleState {
 		ConsoleState {
-			screen_chars: [[ConsoleChar::black(); SCREEN_WIDTH]; SCREEN_HEIGHT],
+			screen_chars: vec![[ConsoleChar::black(); SCREEN_WIDTH]; SCREEN_HEIGHT],
+			screen_mode: ScreenMode::Text80x25,
+			taken_screen_chars: None,
+			blink_time_ms: 0,
+			taken_blink_visible: true,
+			changed: false,
 		}
 	}
 	
+	/// Get the text mode the screen is in.
+	pub fn screen_mode(&self) -> ScreenMode {
+		self.screen_mode
+	}
+	
+	/// Get the number of rows of characters on the screen.
+	pub fn height(&self) -> usize {
+		self.screen_chars.len()
+	}
+	
+	/// Switch to another text mode. The rows that are in both modes are kept, new rows are black,
+	/// and every character is considered changed.
+	pub fn set_screen_mode(&mut self, screen_mode: ScreenMode) {
+		self.screen_mode = screen_mode;
+		self.screen_chars.resize(screen_mode.height(), [ConsoleChar::black(); SCREEN_WIDTH]);
+		self.mark_all_dirty();
+	}
+	
+	/// Set the time that blinking characters are shown and hidden by, in milliseconds since any
+	/// fixed point. Front-ends should set this from the real time every frame (not the game time),
+	/// so characters keep blinking at the same rate while the game is paused or slowed down.
+	/// Front-ends without a clock can set it from the number of steps instead.
+	pub fn set_blink_time_ms(&mut self, blink_time_ms: usize) {
+		self.blink_time_ms = blink_time_ms;
+	}
+	
+	/// True if blinking characters should currently be drawn with their foreground, or false if
+	/// only their background should be drawn.
+	pub fn blink_visible(&self) -> bool {
+		(self.blink_time_ms / BLINK_HALF_PERIOD_MS).is_multiple_of(2)
+	}
+	
+	/// Get the number of milliseconds after the current blink time until blinking characters are
+	/// next shown or hidden.
+	pub fn ms_until_blink_change(&self) -> usize {
+		BLINK_HALF_PERIOD_MS - self.blink_time_ms % BLINK_HALF_PERIOD_MS
+	}
+	
+	/// Get how `console_char` should be drawn right now: while blinking characters are hidden, a
+	/// blinking character is drawn as a blank character with the same colours.
+	pub fn blinked_char(&self, console_char: ConsoleChar) -> ConsoleChar {
+		if console_char.is_blinking() && !self.blink_visible() {
+			ConsoleChar {
+				char_code: 0,
+				.. console_char
+			}
+		} else {
+			console_char
+		}
+	}
+	
+	/// Get the x/y position and value of every character that changed since the last time this was
+	/// called, so a front-end only has to redraw those characters. The first call (and the first
+	/// call after `mark_all_dirty`) returns every character on the screen. Blinking characters are
+	/// also returned whenever they have been shown or hidden since the last call (see
+	/// `blink_visible`).
+	pub fn take_dirty_cells(&mut self) -> Vec<(usize, usize, ConsoleChar)> {
+		let mut dirty_cells = vec![];
+		let blink_changed = self.taken_blink_visible != self.blink_visible();
+		for y in 0 .. self.height() {
+			for x in 0 .. SCREEN_WIDTH {
+				let screen_char = self.screen_chars[y][x];
+				let changed = match self.taken_screen_chars {
+					Some(ref taken_screen_chars) => taken_screen_chars[y][x] != screen_char || (blink_changed && screen_char.is_blinking()),
+					None => true,
+				};
+				if changed {
+					dirty_cells.push((x, y, screen_char));
+				}
+			}
+		}
+		self.taken_screen_chars = Some(self.screen_chars.clone());
+		self.taken_blink_visible = self.blink_visible();
+		self.changed = false;
+		dirty_cells
+	}
+	
+	/// Like `take_dirty_cells`, but only get the smallest rectangle that contains every character
+	/// that changed within `region`, or None if none of them did. Front-ends that redraw a whole
+	/// rectangle at a time can use this to avoid going through every changed character. Changes
+	/// outside `region` are forgotten, the same as the ones inside it.
+	pub fn take_dirty_rect(&mut self, region: ScreenRect) -> Option<ScreenRect> {
+		let mut dirty_rect: Option<ScreenRect> = None;
+		for (x, y, _) in self.take_dirty_cells() {
+			if region.contains(x, y) {
+				dirty_rect = Some(match dirty_rect {
+					Some(dirty_rect) => dirty_rect.including(x, y),
+					None => ScreenRect::new(x, y, 1, 1),
+				});
+			}
+		}
+		dirty_rect
+	}
+	
+	/// Get the rectangle covering the whole screen.
+	pub fn screen_rect(&self) -> ScreenRect {
+		ScreenRect::new(0, 0, SCREEN_WIDTH, self.height())
+	}
+	
+	/// Make the next call to `take_dirty_cells` return every character, eg. when the front-end's
+	/// window has been redrawn from scratch.
+	pub fn mark_all_dirty(&mut self) {
+		self.taken_screen_chars = None;
+	}
+	
 	/// Get the character on the screen at the `x`x`y` position.
 	pub fn get_char(&self, x: usize, y: usize) -> ConsoleChar {
 		self.screen_chars[y][x]
@@ -54,9 +347,32 @@ impl ConsoleState {
 	/// Get the character on the screen at the `x`x`y` position as &mut so it can be modified
 	/// directly in place.
 	pub fn get_char_mut(&mut self, x: usize, y: usize) -> &mut ConsoleChar {
+		self.changed = true;
 		&mut self.screen_chars[y][x]
 	}
 	
+	/// Go through every character on the screen with its x/y position, a row at a time from the
+	/// top left, so embedders don't need to know how `screen_chars` is laid out. The characters
+	/// can also be indexed directly with an `(x, y)` tuple.
+	pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, &ConsoleChar)> {
+		self.screen_chars.iter().enumerate().flat_map(|(y, row)| {
+			row.iter().enumerate().map(move |(x, console_char)| (x, y, console_char))
+		})
+	}
+	
+	/// True if `take_dirty_cells` might return something, so front-ends can skip drawing the
+	/// screen when it returns false. This is cheap to call: it can be true when the characters
+	/// were drawn again without actually changing, but it is never false when they did change (as
+	/// long as `screen_chars` isn't changed directly), or when blinking characters need to be shown
+	/// or hidden.
+	pub fn is_dirty(&self) -> bool {
+		if self.changed || self.taken_screen_chars.is_none() {
+			return true;
+		}
+		self.taken_blink_visible != self.blink_visible()
+			&& self.screen_chars.iter().any(|row| row.iter().any(|screen_char| screen_char.is_blinking()))
+	}
+	
 	/// Starting at `x`x`y` and moving to the right, place characters of `text` in the console, with
 	/// the given `background`/`foreground` colours for all the characters.
 	pub fn draw_text_at(&mut self, x: usize, y: usize, text: &[u8], background: ConsoleColour, foreground: ConsoleColour) {
@@ -64,6 +380,109 @@ impl ConsoleState {
 			*self.get_char_mut(x + i, y) = ConsoleChar::new(*char_code, background, foreground);
 		}
 	}
+	
+	/// Get the text on the screen without any colours, with a line for each row of the screen.
+	/// Blank characters at the ends of the lines are left out, so this is easy to compare against in
+	/// tests.
+	pub fn to_plain_text(&self) -> String {
+		let mut result = String::new();
+		for row in self.screen_chars.iter() {
+			let line: String = row.iter().map(|console_char| console_char_to_text_char(console_char.char_code)).collect();
+			result.push_str(line.trim_end());
+			result.push('\n');
+		}
+		result
+	}
+	
+	/// Get the text on the screen with ANSI escape codes for the colours, so it can be printed to a
+	/// terminal (eg. to include a screenshot in a bug report).
+	pub fn to_ansi_string(&self) -> String {
+		let mut result = String::new();
+		for row in self.screen_chars.iter() {
+			let mut current_colours = None;
+			for console_char in row.iter() {
+				let colours = (console_char.background.ansi_index() % 8, console_char.foreground.ansi_index());
+				if current_colours != Some(colours) {
+					let (background, foreground) = colours;
+					let foreground_code = if foreground < 8 { 30 + foreground } else { 90 + foreground - 8 };
+					result.push_str(&format!("\x1b[{};{}m", 40 + background, foreground_code));
+					current_colours = Some(colours);
+				}
+				result.push(console_char_to_text_char(console_char.char_code));
+			}
+			result.push_str("\x1b[0m\n");
+		}
+		result
+	}
+}
+
+impl Index<(usize, usize)> for ConsoleState {
+	type Output = ConsoleChar;
+	
+	/// Get the character on the screen at the `(x, y)` position, like `get_char`.
+	fn index(&self, (x, y): (usize, usize)) -> &ConsoleChar {
+		&self.screen_chars[y][x]
+	}
+}
+
+impl IndexMut<(usize, usize)> for ConsoleState {
+	/// Get the character on the screen at the `(x, y)` position so it can be changed, like
+	/// `get_char_mut`.
+	fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut ConsoleChar {
+		self.get_char_mut(x, y)
+	}
+}
+
+/// A ZZT colour attribute byte split into its parts. The low 4 bits are the foreground colour, the
+/// next 3 bits are the background colour, and the top bit makes the character blink.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colour {
+	pub foreground: ConsoleColour,
+	/// This is always one of the first 8 colours, because the top bit is `blinking` instead.
+	pub background: ConsoleColour,
+	pub blinking: bool,
+}
+
+impl Colour {
+	/// Make a colour from its parts. Only the first 8 colours can be used for the `background`, so
+	/// the brighter colours are made darker.
+	pub fn new(foreground: ConsoleColour, background: ConsoleColour, blinking: bool) -> Colour {
+		Colour {
+			foreground,
+			background: ConsoleColour::from_u8(background as u8 & 0b111).unwrap(),
+			blinking,
+		}
+	}
+
+	/// Split a ZZT colour attribute byte (like `BoardTile::colour`) into its parts.
+	pub fn from_u8(colour: u8) -> Colour {
+		Colour {
+			foreground: ConsoleColour::from_u8(colour & 0xf).unwrap(),
+			background: ConsoleColour::from_u8((colour >> 4) & 0b111).unwrap(),
+			blinking: colour & 0x80 != 0,
+		}
+	}
+
+	/// Get the colours of a `ConsoleChar`, where the top bit of the background is the blink bit.
+	pub fn from_console_colours(foreground: ConsoleColour, background: ConsoleColour) -> Colour {
+		Colour::from_u8(((background as u8) << 4) | foreground as u8)
+	}
+
+	/// Get the ZZT colour attribute byte for the colour.
+	pub fn to_u8(self) -> u8 {
+		((self.blinking as u8) << 7) | ((self.background as u8) << 4) | self.foreground as u8
+	}
+
+	/// Get the background colour the way `ConsoleChar` stores it, with the blink bit included (so
+	/// it's one of the last 8 colours when blinking).
+	pub fn console_background(self) -> ConsoleColour {
+		ConsoleColour::from_u8(self.to_u8() >> 4).unwrap()
+	}
+
+	/// Get the (red, green, blue) values of the foreground and the background, in that order.
+	pub fn to_rgb(self) -> ((u8, u8, u8), (u8, u8, u8)) {
+		(self.foreground.to_rgb(), self.background.to_rgb())
+	}
 }
 
 /// The possible colours that can be displayed in the console.
@@ -111,4 +530,13 @@ impl ConsoleColour {
 			ConsoleColour::White        => (0xFF, 0xFF, 0xFF),
 		}
 	}
+
+	/// Get the index of the colour in the ANSI terminal palette (0-15). The ANSI palette has red
+	/// and blue swapped compared to DOS.
+	pub fn ansi_index(self) -> u8 {
+		let dos_index = self as u8;
+		let red = (dos_index >> 2) & 1;
+		let blue = dos_index & 1;
+		(dos_index & 0b1010) | (blue << 2) | red
+	}
 }
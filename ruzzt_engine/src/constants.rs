@@ -0,0 +1,33 @@
+/// The colour of the player tile when the player isn't energised (white on blue).
+pub const PLAYER_COLOUR: u8 = 0x1f;
+/// The health the player loses each time they are hurt (eg. shot, or touching a creature).
+pub const PLAYER_HURT_DAMAGE: i16 = 10;
+/// The number of game cycles a torch lasts once it is lit.
+pub const TORCH_CYCLES: i16 = 200;
+/// The number of game cycles the player stays energised after touching an energizer.
+pub const ENERGIZER_CYCLES: i16 = 75;
+/// The number of steps a caption is shown for, the same as DOS ZZT at the default speed.
+pub const CAPTION_TIME: isize = 24;
+/// The param1 of bullets and stars fired by the player (see `ShotSource`).
+pub const SHOT_SOURCE_PLAYER_PARAM1: u8 = 0;
+/// The param1 of bullets and stars fired by anything other than the player.
+pub const SHOT_SOURCE_ENEMY_PARAM1: u8 = 1;
+/// The most characters in a line of a scroll. Longer lines in object code are cut off.
+pub const SCROLL_LINE_WIDTH: usize = 50;
+
+/// The area lit by a torch and blown up by a bomb, centred on the torch or bomb. Each row is a
+/// bitmask that is `CIRCLE_MASK_WIDTH` tiles wide.
+pub const CIRCLE_MASK: [u16; 9] = [
+	0b000111111111000,
+	0b001111111111100,
+	0b011111111111110,
+	0b011111111111110,
+	0b111111111111111,
+	0b011111111111110,
+	0b011111111111110,
+	0b001111111111100,
+	0b000111111111000,
+];
+
+/// The width of each row in `CIRCLE_MASK`.
+pub const CIRCLE_MASK_WIDTH: usize = 15;
@@ -0,0 +1,102 @@
+use crate::sounds::*;
+
+use zzt_file_format::dosstring::DosString;
+
+use std::collections::VecDeque;
+
+/// A single thing to do in a cutscene.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CutsceneCommand {
+	/// Show a flashy caption at the bottom of the board.
+	ShowCaption(DosString),
+	/// Do nothing for the given number of steps, while the board keeps simulating.
+	Wait(usize),
+	/// Move the camera to the given `x`/`y` board position. ZZT boards always fit on the screen,
+	/// so the camera is the player's status (which is the invisible monitor on the title screen),
+	/// and moving it moves whatever the board centres on, eg. when a torch lights the board. The
+	/// camera only moves if the tile at the position is empty.
+	MoveCamera {
+		x: i16,
+		y: i16,
+	},
+	/// Play the sound described by the given ZZT `#play` notes string.
+	PlaySound(DosString),
+	/// Open a scroll, and wait for it to be closed before carrying on.
+	OpenScroll {
+		title: DosString,
+		content_lines: Vec<DosString>,
+	},
+}
+
+impl CutsceneCommand {
+	/// Make a `PlaySound` command from the given notes.
+	pub fn play_sound(notes: &[u8]) -> CutsceneCommand {
+		CutsceneCommand::PlaySound(DosString::from_slice(notes))
+	}
+
+	/// Get the sounds to play for a `PlaySound` command, or None for other commands.
+	pub fn sound_entries(&self) -> Option<Vec<SoundEntry>> {
+		if let CutsceneCommand::PlaySound(ref notes) = self {
+			Some(process_notes_string_to_sound_entries(&notes.data))
+		} else {
+			None
+		}
+	}
+}
+
+/// A queue of `CutsceneCommand`s that the `RuzztEngine` works through while stepping, so front-ends
+/// can build title screens and intros without OOP. While a cutscene is running the player's input
+/// is ignored, except that escape skips the rest of the cutscene.
+#[derive(Debug, Clone, Default)]
+pub struct CutsceneState {
+	/// The commands that haven't been run yet, in order.
+	commands: VecDeque<CutsceneCommand>,
+	/// The number of steps left to wait before running the next command.
+	wait_steps_left: usize,
+}
+
+impl CutsceneState {
+	/// Make a cutscene state with nothing queued.
+	pub fn new() -> CutsceneState {
+		CutsceneState {
+			commands: VecDeque::new(),
+			wait_steps_left: 0,
+		}
+	}
+
+	/// Add `commands` to the end of the queue.
+	pub fn queue(&mut self, commands: impl IntoIterator<Item = CutsceneCommand>) {
+		self.commands.extend(commands);
+	}
+
+	/// True while there are commands left to run or a wait hasn't finished.
+	pub fn is_active(&self) -> bool {
+		!self.commands.is_empty() || self.wait_steps_left > 0
+	}
+
+	/// Drop every command that hasn't been run yet.
+	pub fn skip(&mut self) {
+		self.commands.clear();
+		self.wait_steps_left = 0;
+	}
+
+	/// Get the next command to run in the current step, or None if there isn't one, either because
+	/// the cutscene is waiting or because it is over. `Wait` commands are handled here, so they
+	/// are never returned. Each wait step counts down when this returns None, so this should be
+	/// called until it returns None once per step.
+	pub fn next_command(&mut self) -> Option<CutsceneCommand> {
+		if self.wait_steps_left > 0 {
+			self.wait_steps_left -= 1;
+			return None;
+		}
+
+		match self.commands.pop_front()? {
+			CutsceneCommand::Wait(steps) => {
+				// The current step is the first step of the wait.
+				self.wait_steps_left = steps.saturating_sub(1);
+				None
+			}
+			command => Some(command),
+		}
+	}
+}
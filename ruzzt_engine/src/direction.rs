@@ -1,5 +1,7 @@
+use serde_derive::{Serialize, Deserialize};
+
 /// A cardinal direction to move in (or Idle).
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
 	West,
 	East,
@@ -0,0 +1,93 @@
+use zzt_file_format::metadata::{WorldMetadata, WORLD_METADATA_FILE_EXTENSION};
+
+use std::path::{Path, PathBuf};
+
+/// The longest file name (not including the extension) that DOS allows.
+pub const DOS_NAME_MAX_LEN: usize = 8;
+/// The longest file extension that DOS allows.
+pub const DOS_EXTENSION_MAX_LEN: usize = 3;
+
+/// Get the DOS 8.3 file name for `name` with the given `extension` (which shouldn't start with a
+/// dot), eg. `TOWNOFZZ.ZZT` for "Town of ZZT" and "zzt". The name is upper-cased, characters that
+/// DOS doesn't allow (including spaces) are removed, and the name and extension are truncated.
+pub fn dos_file_name(name: &str, extension: &str) -> String {
+	let dos_chars = |text: &str, max_len: usize| -> String {
+		text.chars()
+			.filter(|c| c.is_ascii_graphic() && !"\"*+,./:;<=>?[\\]|".contains(*c))
+			.map(|c| c.to_ascii_uppercase())
+			.take(max_len)
+			.collect()
+	};
+
+	let mut file_name = dos_chars(name, DOS_NAME_MAX_LEN);
+	let extension = dos_chars(extension, DOS_EXTENSION_MAX_LEN);
+	if !extension.is_empty() {
+		file_name.push('.');
+		file_name += &extension;
+	}
+	file_name
+}
+
+/// Find the file called `file_name` in `directory`, ignoring case, because worlds refer to files in
+/// upper case (like DOS) but the files on a case-sensitive file system might not be. If there is no
+/// such file, the DOS 8.3 version of `file_name` is tried, because ZZT refers to some files by names
+/// that DOS would have truncated (eg. a world with a long name looks for its own file when the game
+/// ends).
+pub fn find_file(directory: &Path, file_name: &str) -> Option<PathBuf> {
+	let exact_path = directory.join(file_name);
+	if exact_path.is_file() {
+		return Some(exact_path);
+	}
+
+	let (name, extension) = match file_name.rfind('.') {
+		Some(dot_index) => (&file_name[.. dot_index], &file_name[dot_index + 1 ..]),
+		None => (file_name, ""),
+	};
+	let truncated_file_name = dos_file_name(name, extension);
+
+	let entries = std::fs::read_dir(directory).ok()?;
+	let mut truncated_match = None;
+	for entry in entries.flatten() {
+		let entry_name = entry.file_name();
+		let entry_name = entry_name.to_string_lossy();
+		if entry_name.eq_ignore_ascii_case(file_name) {
+			return Some(entry.path());
+		} else if truncated_match.is_none() && entry_name.eq_ignore_ascii_case(&truncated_file_name) {
+			truncated_match = Some(entry.path());
+		}
+	}
+	truncated_match
+}
+
+/// Find the file that goes with the world at `world_path`, which has the same name but the given
+/// `extension` (eg. the `HI` or `MUS` file of a world), ignoring case.
+pub fn find_related_file(world_path: &Path, extension: &str) -> Option<PathBuf> {
+	let directory = world_path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+	let name = world_path.file_stem()?.to_string_lossy();
+	find_file(directory, &format!("{}.{}", name, extension))
+}
+
+/// Read the metadata file next to the world at `world_path` (see `WorldMetadata`), ignoring case.
+/// Returns None if there isn't one, or an error if it can't be read.
+pub fn load_world_metadata(world_path: &Path) -> Result<Option<WorldMetadata>, String> {
+	match find_related_file(world_path, WORLD_METADATA_FILE_EXTENSION) {
+		Some(path) => {
+			let text = std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+			WorldMetadata::parse(&text).map(Some).map_err(|err| format!("{}: {}", path.display(), err))
+		}
+		None => Ok(None),
+	}
+}
+
+/// Get every file in `directory` that has the given `extension`, ignoring case. The files are
+/// sorted by name.
+pub fn files_with_extension(directory: &Path, extension: &str) -> Vec<PathBuf> {
+	let mut paths: Vec<PathBuf> = match std::fs::read_dir(directory) {
+		Ok(entries) => entries.flatten().map(|entry| entry.path()).filter(|path| {
+			path.is_file() && path.extension().is_some_and(|path_extension| path_extension.to_string_lossy().eq_ignore_ascii_case(extension))
+		}).collect(),
+		Err(_) => vec![],
+	};
+	paths.sort_by_key(|path| path.file_name().map(|name| name.to_string_lossy().to_ascii_uppercase()));
+	paths
+}
@@ -1,27 +1,97 @@
+use crate::attract_mode::{AttractAction, AttractMode};
+#[cfg(feature = "file-io")]
+use crate::autosave::Autosave;
 use crate::board_simulator::*;
+use crate::board_store::RleTiles;
 use crate::scroll::*;
 use crate::event::*;
 use crate::caption::*;
+use crate::char_picker::{CharPickerAction, CharPickerState};
+use crate::campaign::CampaignCarryOver;
 use crate::console::*;
+use crate::constants::*;
+use crate::cutscene::{CutsceneCommand, CutsceneState};
 use crate::behaviour::*;
+use crate::board_info;
 use crate::board_message::*;
 use crate::direction::*;
+#[cfg(feature = "file-io")]
+use crate::dos_files;
+#[cfg(feature = "file-io")]
+use crate::file_browser::{FileBrowser, FileBrowserSelection};
+use crate::high_scores::HighScoreList;
+use crate::lighting::{LightMap, LightingOptions};
+use crate::machine_speed::MachineSpeed;
+use crate::message_log::MessageLog;
+use crate::message_timeline::{MessageTimeline, DEFAULT_MESSAGE_TIMELINE_LEN};
+use crate::minimap::{Minimap, MinimapOptions};
+use crate::observer::{ObservedState, SharedGameObserver};
 use crate::oop_parser::OopParser;
-use crate::side_bar::{self, SideBar};
-use crate::zzt_behaviours;
+use crate::palette::DisplayOptions;
+use crate::passage_lock::PassageLock;
+use crate::pause_menu::PauseMenuState;
+use crate::profiler::StepProfile;
+use crate::ruleset::{builtin_ruleset, RulesetPlugin};
+use crate::savestate::{Savestate, SAVESTATE_SLOT_COUNT};
+use crate::screenshot::{next_screenshot_path, ScreenshotFormat};
+use crate::shareware::{self, SharewareFlavour};
+use crate::side_bar::{self, SideBar, SideBarModel};
+use crate::speedrun::SpeedrunTimer;
+use crate::stuck_detector;
+use crate::text_editor::{TextEditorPurpose, TextEditorState};
+use crate::watch::{format_watch_value, Watch, WatchTarget};
+use crate::world_browser::{WorldBrowserEntry, WorldBrowserState};
+use crate::world_map::WorldMap;
 use crate::sounds::*;
 
-use zzt_file_format::{self, ElementType, BoardTile};
+use zzt_file_format::{self, CodeSource, ElementType, SuperZztElementType, BoardTile};
 use zzt_file_format::dosstring::DosString;
 
 use num::FromPrimitive;
 
+#[cfg(feature = "file-io")]
 use std::fs::File;
-use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::rc::Rc;
+
+/// The version of the RUZZT engine, shown in the title screen and the About scroll.
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The number of messages shown by the `messages` debug command.
+const MESSAGE_TIMELINE_SCROLL_LEN: usize = 50;
+
+/// The most calls to `RuzztEngine::step` that `RuzztEngine::step_whole_cycle` makes while waiting
+/// for a single game cycle to finish, in case something keeps pausing the step forever.
+pub const MAX_PARTIAL_STEPS: usize = 100;
+
+/// Get the lines of the About scroll opened from the title screen.
+pub fn about_scroll_lines() -> Vec<DosString> {
+	let lines = [
+		"$RUZZT",
+		"",
+		"A clone of the engine for the DOS game ZZT,",
+		"written in Rust.",
+		"",
+		"ZZT was created by Tim Sweeney, and was",
+		"published by Potomac Computer Systems in",
+		"1991. RUZZT replicates its behaviour by",
+		"watching the original game run.",
+		"",
+		"$Keys",
+		"  Arrows: Move     Shift+Arrows: Shoot",
+		"  T: Torch   P: Pause   M: Map   L: Messages",
+	];
+	let mut content_lines: Vec<DosString> = lines.iter().map(|line| DosString::from_str(line)).collect();
+	content_lines.insert(1, DosString::from_str(&format!("$Version {}", ENGINE_VERSION)));
+	content_lines
+}
 
 /// Get the character code associated with the given element type.
 /// Note that not all types use this function. For those types it doesn't matter what this returns.
-fn element_type_to_char_code(ty: ElementType) -> u8 {
+pub fn element_type_to_char_code(ty: ElementType) -> u8 {
 	use self::ElementType::*;
 	match ty {
 		Empty => 32,
@@ -72,6 +142,28 @@ fn element_type_to_char_code(ty: ElementType) -> u8 {
 	}
 }
 
+/// Get the character code for an element that only exists in Super ZZT, or None for elements that
+/// are shared with ZZT (which use `element_type_to_char_code`).
+pub fn super_zzt_element_type_to_char_code(ty: SuperZztElementType) -> Option<u8> {
+	use self::SuperZztElementType::*;
+	let char_code = match ty {
+		Lava => 0x6f,
+		Floor => 0xb0,
+		WaterN => 0x1e,
+		WaterS => 0x1f,
+		WaterW => 0x11,
+		WaterE => 0x10,
+		Roton => 0x94,
+		DragonPup => 0xeb,
+		Pairer => 0xe5,
+		Spider => 0x0f,
+		Web => 0xc5,
+		Stone => 0x5a,
+		_ => return None,
+	};
+	Some(char_code)
+}
+
 /// Returns true if the given element type is always visible when the room is dark.
 fn type_visible_in_dark(ty: ElementType) -> bool {
 	match ty {
@@ -80,6 +172,159 @@ fn type_visible_in_dark(ty: ElementType) -> bool {
 	}
 }
 
+/// Overrides for the state of a new game, so a world can be started part way through with a given
+/// inventory. This is useful for world authors testing boards late in a world. Anything left as
+/// None keeps the value stored in the world.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StartState {
+	/// The index of the board to start on.
+	pub board: Option<i16>,
+	pub health: Option<i16>,
+	pub ammo: Option<i16>,
+	pub torches: Option<i16>,
+	pub gems: Option<i16>,
+	pub score: Option<i16>,
+	/// Which keys the player has (0 is blue, 6 is white).
+	pub keys: Option<[bool; 7]>,
+	/// Flags to set, in addition to any already set in the world. Flags that don't fit in the
+	/// world's flag slots are ignored.
+	pub flags: Vec<DosString>,
+}
+
+impl StartState {
+	/// Make a start state that doesn't override anything.
+	pub fn new() -> StartState {
+		StartState::default()
+	}
+
+	/// Apply the overrides to the given `world_header`.
+	pub fn apply(&self, world_header: &mut zzt_file_format::WorldHeader) {
+		if let Some(board) = self.board {
+			world_header.player_board = board;
+		}
+		if let Some(health) = self.health {
+			world_header.player_health = health;
+		}
+		if let Some(ammo) = self.ammo {
+			world_header.player_ammo = ammo;
+		}
+		if let Some(torches) = self.torches {
+			world_header.player_torches = Some(torches);
+		}
+		if let Some(gems) = self.gems {
+			world_header.player_gems = gems;
+		}
+		if let Some(score) = self.score {
+			world_header.player_score = score;
+		}
+		if let Some(keys) = self.keys {
+			world_header.player_keys = keys;
+		}
+		for flag in &self.flags {
+			// Don't set the same flag twice, the same as the #set command.
+			if world_header.last_matching_flag(flag.clone()).is_none() {
+				if let Some(flag_index) = world_header.first_empty_flag() {
+					world_header.flag_names[flag_index] = flag.clone().to_upper();
+				}
+			}
+		}
+	}
+}
+
+/// How much of RUZZT's extensions worlds can see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionLevel {
+	/// Worlds can't tell they're running in RUZZT, the same as in DOS ZZT.
+	Dos,
+	/// The flags from `RuzztEngine::reserved_flag_names` are always set, so worlds can check for
+	/// RUZZT and its extensions with `#if`, and fall back to something else in DOS ZZT. Objects can
+	/// also use the `#colour` command to be drawn with extended colours, and passages can be locked
+	/// (see `PassageLock`).
+	Ruzzt,
+}
+
+/// What happened when a single status was simulated on its own with `RuzztEngine::dry_run_object`.
+#[derive(Debug, Clone)]
+pub struct DryRunResult {
+	/// Every action applied while simulating, in order.
+	pub actions: Vec<Action>,
+	/// The board messages sent while simulating. None of them have been processed.
+	pub board_messages: Vec<BoardMessage>,
+	/// The board as it was after the last step.
+	pub board: zzt_file_format::Board,
+	/// The number of steps that were simulated. This is less than the number of steps asked for if
+	/// the status was removed (eg. an object that ran `#die`).
+	pub steps_simulated: usize,
+}
+
+/// A summary of what happened in the last call to `RuzztEngine::step`, so front-ends and tools can
+/// react to it without comparing the whole game before and after. The changes are measured from
+/// the end of the previous call to `step`, so they include anything done by processing that step's
+/// board messages (eg. moving to another board).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepOutcome {
+	/// True if a whole game cycle was simulated. This is false while the game is paused, and when
+	/// the step stopped part way through to send a board message (see `is_part_way_though_step`).
+	pub completed_full_step: bool,
+	/// The `global_cycle` after the step.
+	pub global_cycle: usize,
+	/// True if the player is on a different board.
+	pub board_changed: bool,
+	/// True if the player is somewhere else on the board, or on a different board.
+	pub player_moved: bool,
+	/// True if the player has less health.
+	pub player_hurt: bool,
+	/// The number of actions applied to the board (see `BoardSimulator::apply_action`).
+	pub actions_applied: usize,
+}
+
+/// The parts of the game that `StepOutcome` compares between steps.
+#[derive(Debug, Clone, PartialEq)]
+struct StepOutcomeBaseline {
+	board_index: i16,
+	player_location: Option<(u8, u8)>,
+	health: i16,
+	actions_applied: usize,
+}
+
+impl StepOutcomeBaseline {
+	fn from_board_simulator(board_simulator: &BoardSimulator) -> StepOutcomeBaseline {
+		StepOutcomeBaseline {
+			board_index: board_simulator.world_header.player_board,
+			player_location: board_simulator.player_status_index().map(|player_index| {
+				let player = &board_simulator.status_elements[player_index];
+				(player.location_x, player.location_y)
+			}),
+			health: board_simulator.world_header.player_health,
+			actions_applied: board_simulator.actions_applied,
+		}
+	}
+}
+
+/// Set the behaviours from `RuzztEngine::override_behaviour` (keyed by `ElementType`) in `sim`,
+/// which already has the behaviours of its ruleset loaded. Each behaviour is set for every element
+/// ID that the ruleset says is that `ElementType`.
+fn apply_behaviour_overrides(behaviour_overrides: &BTreeMap<u8, Rc<dyn Behaviour>>, sim: &mut BoardSimulator) {
+	for (&element_type_id, behaviour) in behaviour_overrides {
+		for element_id in 0 ..= 255 {
+			if sim.ruleset.element_type(element_id).map(|ty| ty as u8) == Some(element_type_id) {
+				sim.set_behaviour_for_id(element_id, behaviour.clone());
+			}
+		}
+	}
+}
+
+/// Give `sim` the ruleset from `RuzztEngine::set_ruleset` (if there is one, otherwise it keeps the
+/// stock ruleset for its world type), and load the ruleset's behaviours along with the
+/// `RuzztEngine::override_behaviour` ones.
+fn load_behaviours(ruleset: &Option<Rc<dyn RulesetPlugin>>, behaviour_overrides: &BTreeMap<u8, Rc<dyn Behaviour>>, sim: &mut BoardSimulator) {
+	if let Some(ref ruleset) = *ruleset {
+		sim.ruleset = ruleset.clone();
+	}
+	sim.load_ruleset_behaviours();
+	apply_behaviour_overrides(behaviour_overrides, sim);
+}
+
 #[derive(Clone)]
 pub struct RuzztEngine {
 	/// The `BoardSimulator` used to simulate the current board.
@@ -93,8 +338,10 @@ pub struct RuzztEngine {
 	// TODO: Maybe this should just be replaced with things that aren't already stored in
 	// BoardSimulator, because right now the board simulator's world_header has to be carefully used
 	// all the time, and not the one in this World instance.
-	/// The current state of the `World`.
-	pub world: zzt_file_format::World,
+	/// The current state of the `World`. When idle boards are compacted (see
+	/// `set_compact_idle_boards`), the boards that aren't loaded have no tiles here, so this is
+	/// only read from outside of the engine through `sync_world` or `board`.
+	pub(crate) world: zzt_file_format::World,
 	/// `global_cycle` is the number of simulation steps since the start of the game.
 	pub global_cycle: usize,
 	/// Number of times the `RuzztEngine` `step` function has been called since the game was paused.
@@ -105,11 +352,27 @@ pub struct RuzztEngine {
 	pub scroll_state: Option<ScrollState>,
 	/// If there's a caption being displayed, this contains the state of the caption.
 	pub caption_state: Option<CaptionState>,
+	/// The captions waiting to be shown after `caption_state`, oldest first. See `queue_caption`.
+	pending_captions: VecDeque<DosString>,
+	/// If the world browser is open, this contains the state of the browser.
+	pub world_browser_state: Option<WorldBrowserState>,
+	/// When the world browser was opened with `open_file_browser`, this lists the directory being
+	/// shown, so picking a directory in the world browser moves into it.
+	#[cfg(feature = "file-io")]
+	file_browser: Option<FileBrowser>,
+	/// When set, the pause menu is open, and the game doesn't run until it is closed.
+	pub pause_menu_state: Option<PauseMenuState>,
+	/// If a text editor is open (eg. for a status's code), this contains the state of the editor.
+	/// The engine is in typing mode while it is open.
+	pub text_editor_state: Option<TextEditorState>,
+	/// When set, the character picker is open over the text editor or side bar text input, and
+	/// typing events move its highlight instead of typing.
+	pub char_picker_state: Option<CharPickerState>,
 	/// The state of the sidebar on the right of the screen.
 	pub side_bar: SideBar,
 	/// `OneTimeNotification`s are notifications that are only shown once. When one is shown it is
 	/// added to the set so it doesn't get shown again.
-	pub shown_one_time_notifications: HashSet<OneTimeNotification>,
+	pub shown_one_time_notifications: BTreeSet<OneTimeNotification>,
 	/// When a link in a scroll is pressed, this will be set to that link's target string.
 	/// If `board_simulator_step_state` is set, then when the next partial step is executed this
 	/// will be used to jump to the associated OOP label on the status currently being processed.
@@ -125,6 +388,152 @@ pub struct RuzztEngine {
 	pub accumulated_data: AccumulatedActionData,
 	/// True when in the title screen.
 	pub in_title_screen: bool,
+	/// The palette and element appearance overrides to use when drawing the board.
+	pub display_options: DisplayOptions,
+	/// Keeps track of held movement keys so the front-end can repeat them at a fixed number of
+	/// steps. See `KeyRepeatState`.
+	pub key_repeat: KeyRepeatState,
+	/// The game speed picked in the title screen, from fastest (0) to slowest (8). The engine
+	/// doesn't time steps itself, so front-ends should use `step_length_ms` to decide how long a
+	/// step takes.
+	pub game_speed: u8,
+	/// The DOS machine that the length of steps is modelled on (see `step_length_ms`). Worlds can
+	/// say which machine they were made for in their metadata file.
+	pub machine_speed: MachineSpeed,
+	/// When set, the random number generator is seeded from this and the board's index (see
+	/// `board_simulator::board_rng_seed`) every time the player enters a board, so everything
+	/// random on a board (eg. ruffians and spinning guns) happens the same way every time the board
+	/// is attempted, as long as the player does the same things. This is for puzzle and challenge
+	/// worlds that want fair randomness. Worlds can set it in their metadata file.
+	pub board_rng_seed: Option<u64>,
+	/// The `GameObserver`s that are notified about changes to the game. See `add_observer`.
+	pub observers: Vec<SharedGameObserver>,
+	/// The state the `observers` were last notified about.
+	observed_state: ObservedState,
+	/// What happened in the last step. See `last_step_outcome`.
+	last_step_outcome: StepOutcome,
+	/// The state at the end of the last step, which `last_step_outcome` is measured from.
+	step_outcome_baseline: StepOutcomeBaseline,
+	/// When set, the run is timed and the times are shown in the side bar.
+	pub speedrun_timer: Option<SpeedrunTimer>,
+	/// When set, the game in progress is written to a file every so often so it can be resumed
+	/// after a crash. See `Autosave`.
+	#[cfg(feature = "file-io")]
+	pub autosave: Option<Autosave>,
+	/// When set, a minimap of the current board is drawn over a corner of the board.
+	pub minimap_options: Option<MinimapOptions>,
+	/// When set, dark boards are lit with the modern lighting mode instead of only by the torch
+	/// like in DOS ZZT. See `LightingOptions`.
+	pub lighting_options: Option<LightingOptions>,
+	/// When set, the order form scroll of shareware DOS ZZT is shown when the title screen is left
+	/// alone and when the player quits. This is off by default.
+	pub shareware_flavour: Option<SharewareFlavour>,
+	/// When set, a recorded demo is played when the title screen is left alone, and the title
+	/// screen comes back when any key is pressed. This is off by default.
+	pub attract_mode: Option<AttractMode>,
+	/// The light on the current board, worked out each time the screen is updated while
+	/// `lighting_options` is set.
+	light_map: Option<LightMap>,
+	/// The boards the player has visited in the current game, shown when the world map is opened.
+	pub world_map: WorldMap,
+	/// The captions and scrolls recently shown in the current game, so the player can read them
+	/// again after they disappear.
+	pub message_log: MessageLog,
+	/// When true, the engine checks whether the player appears to be stuck after each step, and
+	/// sends a `PlayerAppearsStuck` message when they become stuck. See
+	/// `stuck_detector::player_appears_stuck`.
+	pub stuck_watchdog_enabled: bool,
+	/// True if the player was stuck the last time the watchdog checked, so the message is only sent
+	/// once each time they become stuck.
+	player_was_stuck: bool,
+	/// True if the player's health was low after the last step, so the warning sound is only
+	/// played once each time it gets low. See `SideBarModel::health_is_low`.
+	health_was_low: bool,
+	/// True if the player's torch was running out after the last step. See
+	/// `SideBarModel::torch_is_running_out`.
+	torch_was_running_out: bool,
+	/// The cutscene being played, if there is one. See `play_cutscene`.
+	pub cutscene_state: CutsceneState,
+	/// When true, boards can have any number of status elements instead of being limited like in
+	/// DOS ZZT. See `set_unlimited_status_elements`.
+	unlimited_status_elements: bool,
+	/// See `set_compact_idle_boards`.
+	compact_idle_boards: bool,
+	/// The run-length encoded tiles of the boards in `world` that have had their tiles taken out
+	/// because they aren't loaded, by board index.
+	compacted_tiles: BTreeMap<usize, RleTiles>,
+	/// Whether worlds can detect RUZZT and its extensions. See `set_extension_level`.
+	extension_level: ExtensionLevel,
+	/// Behaviours that replace the usual ones, keyed by `ElementType`. See `override_behaviour`.
+	behaviour_overrides: BTreeMap<u8, Rc<dyn Behaviour>>,
+	/// The ruleset set with `set_ruleset`, or None to use the stock ruleset for each world's type.
+	ruleset: Option<Rc<dyn RulesetPlugin>>,
+	/// What to do with `PlaySoundArray` messages sent while `should_simulate_fast` is true, so
+	/// front-ends don't get flooded with sounds.
+	pub fast_simulation_sound_policy: FastSimulationSoundPolicy,
+	/// The `global_cycle` in which a sound was last played while simulating fast, so the `Compress`
+	/// policy only plays one sound per full step.
+	last_fast_simulation_sound_cycle: Option<usize>,
+	/// Music to loop in the background of each board, keyed by board index. This is an opt-in
+	/// extension that is empty unless a front-end fills it in (see `ambient_music`), and it is
+	/// cleared when a world is loaded.
+	pub board_ambient_music: BTreeMap<u8, DosString>,
+	/// The high scores of the current world, shown by `OpenHighScores` in the title screen. These
+	/// are empty unless a front-end fills them in (eg. from the world's `.HI` file, see
+	/// `HighScoreList::load`), and they are cleared when a world is loaded.
+	pub high_scores: HighScoreList,
+	/// The flag on each line of the scroll opened by the `flags` debug command, or an empty name
+	/// for empty slots. See `open_flag_list`.
+	flag_list_names: Vec<DosString>,
+	/// The ambient music that front-ends were last told to play, so `SetAmbientMusic` is only sent
+	/// when it changes.
+	current_ambient_music: Option<DosString>,
+	/// The in-memory savestates, made with `save_savestate`. There are `SAVESTATE_SLOT_COUNT`
+	/// slots.
+	pub savestate_slots: Vec<Option<Savestate>>,
+	/// The slot used by the `QuickSave` and `QuickLoad` events.
+	pub selected_savestate_slot: usize,
+	/// The sounds that are playing and waiting to be played. Sound board messages are applied to
+	/// it in `process_board_message`, after the fast simulation and game over policies have
+	/// filtered them, and front-ends move it along with `advance_sound` and play its
+	/// `playing_entry`. It is included in savestates.
+	pub sound_queue: SoundQueue,
+	/// When set, savestates are also written to files in this directory (see
+	/// `Savestate::slot_file_path`), and loading from a slot that is empty in memory reads the file.
+	#[cfg(feature = "file-io")]
+	pub savestate_directory: Option<PathBuf>,
+	/// When true, savestate files are written compressed (see `Savestate::write_compressed`), which
+	/// keeps them small for big worlds. Compressed and plain savestate files can both be read
+	/// whatever this is set to.
+	#[cfg(feature = "file-io")]
+	pub compress_savestates: bool,
+	/// What is carried over when a world opens another world during a game. This is None by
+	/// default, which carries nothing over like DOS ZZT. See `apply_campaign_carry_over`.
+	pub campaign_carry_over: Option<CampaignCarryOver>,
+	/// When true, the statuses that will be processed in the next step are drawn as digits giving
+	/// the order they will be processed in, and the other statuses are greyed out. This is for
+	/// debugging worlds where objects interact in the same cycle.
+	pub show_execution_order: bool,
+	/// Values shown in the top left of the board every frame, added with the `watch` and `break`
+	/// debug commands. Watches of status elements show the status at that index on whichever board
+	/// is loaded. See `update_watches`.
+	pub watches: Vec<Watch>,
+	/// When true, the movement keys shoot in their direction instead of moving the player, for
+	/// players who can't easily hold Shift while pressing them. This is toggled with
+	/// `Event::ToggleShootLock`, and shown in the side bar.
+	pub shoot_lock: bool,
+	/// The format of the screenshots taken with `Event::Screenshot`. This is ANSI art by default,
+	/// but front-ends that can draw the screen as an image can set it to `ScreenshotFormat::Png`
+	/// and save the screenshots themselves.
+	pub screenshot_format: ScreenshotFormat,
+	/// The directory that screenshots taken with `Event::Screenshot` are saved in. This is the
+	/// working directory by default.
+	pub screenshot_directory: PathBuf,
+	/// True if the world has been edited since it was loaded or last saved. See `is_dirty`.
+	world_dirty: bool,
+	/// Set by `restore_saved_game` so the next check of the board's time limit only catches up
+	/// with the front-end's clock instead of counting the time since the game was saved.
+	resync_time_passed_ticks: bool,
 }
 
 impl RuzztEngine {
@@ -133,12 +542,15 @@ impl RuzztEngine {
 		let initial_world = zzt_file_format::World::zzt_default();
 
 		let mut board_simulator = BoardSimulator::new(initial_world.world_header.clone());
-		zzt_behaviours::load_zzt_behaviours(&mut board_simulator);
+		board_simulator.load_ruleset_behaviours();
 
 		board_simulator.load_board(&initial_world.boards[initial_world.world_header.player_board as usize]);
 		let mut accumulated_data = AccumulatedActionData::new();
 		board_simulator.on_player_entered_board(&mut accumulated_data.board_messages);
 
+		let observed_state = ObservedState::from_world_header(&board_simulator.world_header);
+		let step_outcome_baseline = StepOutcomeBaseline::from_board_simulator(&board_simulator);
+
 		let mut engine = RuzztEngine {
 			board_simulator,
 			board_simulator_step_state: None,
@@ -148,13 +560,71 @@ impl RuzztEngine {
 			paused_cycle: 1,
 			scroll_state: None,
 			caption_state: None,
+			pending_captions: VecDeque::new(),
+			world_browser_state: None,
+			#[cfg(feature = "file-io")]
+			file_browser: None,
+			pause_menu_state: None,
+			text_editor_state: None,
+			char_picker_state: None,
 			side_bar: SideBar::new(),
-			shown_one_time_notifications: HashSet::new(),
+			shown_one_time_notifications: BTreeSet::new(),
 			clicked_link_label: None,
 			is_paused: true,
 			board_should_simulate_fast: false,
 			accumulated_data,
 			in_title_screen: true,
+			display_options: DisplayOptions::new(),
+			key_repeat: KeyRepeatState::new(),
+			game_speed: 4,
+			machine_speed: MachineSpeed::Modern,
+			board_rng_seed: None,
+			observers: vec![],
+			observed_state,
+			last_step_outcome: StepOutcome::default(),
+			step_outcome_baseline,
+			speedrun_timer: None,
+			#[cfg(feature = "file-io")]
+			autosave: None,
+			minimap_options: None,
+			lighting_options: None,
+			shareware_flavour: None,
+			attract_mode: None,
+			light_map: None,
+			world_map: WorldMap::new(),
+			message_log: MessageLog::default(),
+			stuck_watchdog_enabled: false,
+			player_was_stuck: false,
+			health_was_low: false,
+			torch_was_running_out: false,
+			cutscene_state: CutsceneState::new(),
+			unlimited_status_elements: false,
+			compact_idle_boards: false,
+			compacted_tiles: BTreeMap::new(),
+			extension_level: ExtensionLevel::Dos,
+			behaviour_overrides: BTreeMap::new(),
+			ruleset: None,
+			fast_simulation_sound_policy: FastSimulationSoundPolicy::Compress,
+			last_fast_simulation_sound_cycle: None,
+			board_ambient_music: BTreeMap::new(),
+			high_scores: HighScoreList::default(),
+			flag_list_names: vec![],
+			current_ambient_music: None,
+			savestate_slots: vec![None; SAVESTATE_SLOT_COUNT],
+			selected_savestate_slot: 0,
+			sound_queue: SoundQueue::new(),
+			#[cfg(feature = "file-io")]
+			savestate_directory: None,
+			#[cfg(feature = "file-io")]
+			compress_savestates: false,
+			campaign_carry_over: None,
+			show_execution_order: false,
+			watches: vec![],
+			shoot_lock: false,
+			screenshot_format: ScreenshotFormat::Ansi,
+			screenshot_directory: PathBuf::from("."),
+			world_dirty: false,
+			resync_time_passed_ticks: false,
 		};
 
 		engine.set_in_title_screen(true);
@@ -166,54 +636,736 @@ impl RuzztEngine {
 	pub fn set_in_title_screen(&mut self, in_title_screen: bool) {
 		self.in_title_screen = in_title_screen;
 		if in_title_screen {
-			self.board_simulator.load_board(&self.world.boards[0]);
+			self.load_world_board(0);
 			self.is_paused = false;
 		} else {
-			self.board_simulator.load_board(&self.world.boards[self.board_simulator.world_header.player_board as usize]);
+			self.load_world_board(self.board_simulator.world_header.player_board as usize);
 			self.is_paused = true;
+			self.on_board_entered();
+		}
+	}
+
+	/// Called whenever the player enters a board (including the one they start on) to update the
+	/// state that is kept per board.
+	fn on_board_entered(&mut self) {
+		if let Some(world_seed) = self.board_rng_seed {
+			self.board_simulator.seed_rng(board_rng_seed(world_seed, self.board_simulator.world_header.player_board as usize));
+		}
+		if !self.in_title_screen && self.board_simulator.player_status_index().is_none() {
+			// There is nobody to wait for, so the board runs straight away like the title screen.
+			self.is_paused = false;
+			self.accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning,
+				format!("Board {} has no status elements, so there is no player", self.board_simulator.world_header.player_board)));
+		} else if !self.in_title_screen && self.board_simulator.player_is_misplaced() {
+			let player_status = &self.board_simulator.status_elements[0];
+			self.accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning,
+				format!("Board {} has status element 0 at {}x{}, which isn't a player tile. Like DOS ZZT, it still moves as the player",
+					self.board_simulator.world_header.player_board, player_status.location_x, player_status.location_y)));
+		}
+		self.world_map.visit_board(&self.board_simulator);
+		self.shown_one_time_notifications.retain(|notification| !notification.is_per_board());
+		#[cfg(feature = "file-io")]
+		{
+			if let Some(ref mut autosave) = self.autosave {
+				autosave.board_entered();
+			}
 		}
 	}
 
 	/// Load the given `world` into the engine to start simulating it. The current `in_title_screen`
 	/// value will not change. The board that is loaded initially can be overridden by setting
-	/// `start_board` to the desired board's index within the world.
-	pub fn load_world(&mut self, mut world: zzt_file_format::World, start_board: Option<i16>) {
+	/// `start_board` to the desired board's index within the world. Returns an error without
+	/// changing anything if the board to start on doesn't exist.
+	pub fn load_world(&mut self, mut world: zzt_file_format::World, start_board: Option<i16>) -> Result<(), String> {
 		if let Some(start_board) = start_board {
 			world.world_header.player_board = start_board;
 		}
+		let player_board_index = world.world_header.player_board;
+		if player_board_index < 0 || player_board_index as usize >= world.boards.len() {
+			return Err(format!("Can't start on board {}, because the world has {} boards", player_board_index, world.boards.len()));
+		}
+
+		// Boards without any status elements (eg. from worlds made by buggy editors) get one for the
+		// player if there is a player or monitor tile to put it on, like DOS ZZT where the player's
+		// status element is left over from the last board. Boards without either run without a
+		// player (see `on_board_entered`).
+		let world_type = world.world_header.world_type;
+		for board in &mut world.boards {
+			if board.player_tile_location(world_type).is_some() {
+				board.add_missing_player_status(world_type);
+			}
+		}
+
+		// Like DOS ZZT when a game starts (or a saved game is restored), the player enters the
+		// board they start on wherever they are standing, so being zapped on a `restart_on_zap`
+		// board puts them back there.
+		let player_board = &mut world.boards[world.world_header.player_board as usize];
+		if let Some(player_index) = player_board.player_status_index() {
+			let player = &player_board.status_elements[player_index];
+			player_board.meta_data.player_enter_x = player.location_x;
+			player_board.meta_data.player_enter_y = player.location_y;
+		}
 
 		let mut board_simulator = BoardSimulator::new(world.world_header.clone());
-		zzt_behaviours::load_zzt_behaviours(&mut board_simulator);
+		load_behaviours(&self.ruleset, &self.behaviour_overrides, &mut board_simulator);
 
+		if self.unlimited_status_elements {
+			board_simulator.status_element_limit = None;
+		}
 		board_simulator.load_board(&world.boards[world.world_header.player_board as usize]);
-
-		let (player_x, player_y) = self.board_simulator.get_player_location();
-		self.board_simulator.board_meta_data.player_enter_x = player_x as u8;
-		self.board_simulator.board_meta_data.player_enter_y = player_y as u8;
+		// Keep using the same random number generator, so a seeded game stays the same after
+		// loading a world.
+		board_simulator.rng = self.board_simulator.rng.clone();
+		board_simulator.settings = self.board_simulator.settings.clone();
+		board_simulator.profile = self.board_simulator.profile.clone();
+		// The statuses in the old world's timeline have nothing to do with the new world.
+		let message_timeline_len = self.board_simulator.message_timeline.borrow().as_ref().map(|message_timeline| message_timeline.max_len);
+		board_simulator.message_timeline = RefCell::new(message_timeline_len.map(MessageTimeline::new));
+		// `campaign_carry_over` can be changed directly, so the reserved flags might be out of date.
+		board_simulator.settings.reserved_flags = self.reserved_flag_names();
 
 		self.board_simulator = board_simulator;
 		self.world = world;
+		self.compacted_tiles.clear();
+		self.world_dirty = false;
+		self.resync_time_passed_ticks = false;
+		self.world_map = WorldMap::new();
+		self.message_log.clear();
+		self.pending_captions.clear();
+		self.board_ambient_music.clear();
+		self.high_scores = HighScoreList::default();
 		self.set_in_title_screen(self.in_title_screen);
 		self.board_should_simulate_fast = false;
+		self.observed_state = ObservedState::from_world_header(&self.board_simulator.world_header);
+		self.step_outcome_baseline = StepOutcomeBaseline::from_board_simulator(&self.board_simulator);
+		self.compact_boards();
+		Ok(())
+	}
+
+	/// Load the saved game `world` (eg. a `.SAV` file picked after pressing R on the title screen)
+	/// and carry on playing it straight away. Unlike starting a new game with `load_world` and
+	/// `set_in_title_screen`, the game isn't paused, and the time passed on the board is kept as it
+	/// was saved, so a board with a time limit carries on counting down from where it was. Returns
+	/// an error if the world can't be loaded (see `load_world`).
+	pub fn restore_saved_game(&mut self, world: zzt_file_format::World) -> Result<(), String> {
+		self.load_world(world, None)?;
+		self.set_in_title_screen(false);
+		self.is_paused = false;
+		self.resync_time_passed_ticks = true;
+		self.accumulated_data.board_messages.push(BoardMessage::ClearPlayingSound);
+		Ok(())
+	}
+
+	/// Copy the state that `campaign_carry_over` says to keep from the game in progress into the
+	/// `world` that is about to be opened with `load_world`. This does nothing if there is no game
+	/// in progress, or no `campaign_carry_over`.
+	pub fn apply_campaign_carry_over(&self, world: &mut zzt_file_format::World) {
+		if let Some(ref campaign_carry_over) = self.campaign_carry_over {
+			if !self.in_title_screen {
+				campaign_carry_over.apply(&self.board_simulator.world_header, &mut world.world_header);
+			}
+		}
+	}
+
+	/// Load the given `world` and start playing it straight away, with the board and inventory
+	/// overridden by `start_state`. This skips the title screen, so it's used for practising or
+	/// testing a particular board. The game is paused on the starting board like when a game starts
+	/// normally.
+	pub fn load_world_with_state(&mut self, mut world: zzt_file_format::World, start_state: &StartState) -> Result<(), String> {
+		start_state.apply(&mut world.world_header);
+		self.load_world(world, None)?;
+		self.set_in_title_screen(false);
+		self.board_simulator.on_player_entered_board(&mut self.accumulated_data.board_messages);
+		Ok(())
+	}
+
+	/// Simulate only the status at `status_index` on the board at `board_index` for the given
+	/// number of `steps`, without changing the real game. The simulation happens on a copy of the
+	/// board, so this is useful for an editor to test what an object's script does. No other
+	/// statuses (including the player) are simulated, and the steps don't process any input.
+	pub fn dry_run_object(&self, board_index: usize, status_index: usize, steps: usize) -> Result<DryRunResult, String> {
+		let mut board = self.board(board_index).ok_or_else(|| format!("There is no board {}", board_index))?.into_owned();
+		if board_index as i16 == self.board_simulator.world_header.player_board {
+			self.board_simulator.save_board(&mut board);
+		}
+		if status_index >= board.status_elements.len() {
+			return Err(format!("There is no status {} on board {}", status_index, board_index));
+		}
+
+		let mut sim = BoardSimulator::new(self.board_simulator.world_header.clone());
+		load_behaviours(&self.ruleset, &self.behaviour_overrides, &mut sim);
+		sim.world_header.player_board = board_index as i16;
+		if self.unlimited_status_elements {
+			sim.status_element_limit = None;
+		}
+		sim.load_board(&board);
+		sim.action_log = Some(vec![]);
+		sim.rng = self.board_simulator.rng.clone();
+		sim.settings = self.board_simulator.settings.clone();
+
+		let mut board_messages = vec![];
+		let mut steps_simulated = 0;
+		// The status moves down the list when statuses before it are removed, and it's gone when it
+		// removes itself, so this is None once it's been removed.
+		let mut status_index_opt = Some(status_index);
+		while let Some(status_index) = status_index_opt {
+			if steps_simulated >= steps {
+				break;
+			}
+			let mut step_state = BoardSimulatorStepState::new(Event::None, self.global_cycle + steps_simulated);
+			let report = step_state.process_status(status_index, &mut sim);
+			board_messages.append(&mut step_state.accumulated_data.board_messages);
+			steps_simulated += 1;
+
+			for &removed_index in report.removed_status_indices.indices() {
+				status_index_opt = match status_index_opt {
+					Some(index) if removed_index == index => None,
+					Some(index) if removed_index < index => Some(index - 1),
+					other => other,
+				};
+			}
+		}
+
+		sim.save_board(&mut board);
+		Ok(DryRunResult {
+			actions: sim.action_log.take().unwrap_or_default(),
+			board_messages,
+			board,
+			steps_simulated,
+		})
+	}
+
+	/// Get the labels in the code of the status at `status_index` on the current board, with the
+	/// code position of each one (see `OopParser::labels`). Returns an empty list if there is no
+	/// such status.
+	pub fn labels_of(&self, status_index: usize) -> Vec<(DosString, i16)> {
+		if let Some(status) = self.board_simulator.status_elements.get(status_index) {
+			OopParser::new(self.board_simulator.get_status_code(status), 0).labels()
+		} else {
+			vec![]
+		}
+	}
+
+	/// Make the status at `status_index` on the current board jump to `label`, the same as if
+	/// another object did `#send` to it. This is ignored by locked objects, like a real `#send`.
+	pub fn send_label(&mut self, status_index: usize, label: &DosString) -> Result<(), String> {
+		let status = self.board_simulator.status_elements.get(status_index).ok_or_else(|| format!("There is no status {}", status_index))?;
+		let behaviour = self.board_simulator.behaviour_for_pos(status.location_x as i16, status.location_y as i16);
+		if behaviour.locked(status) {
+			return Err(format!("Status {} is locked", status_index));
+		}
+
+		let parser = OopParser::new(self.board_simulator.get_status_code(status), 0);
+		let label_pos = parser.find_label(&label.clone().to_lower()).ok_or_else(|| format!("Status {} has no label {}", status_index, label.to_string(false)))?;
+		self.board_simulator.status_elements[status_index].code_current_instruction = label_pos;
+		Ok(())
+	}
+
+	/// Apply a `command` typed into the debug prompt, which works like the cheat prompt in DOS ZZT:
+	/// - `+FLAG` sets the flag called `FLAG`, and `-FLAG` clears it. The rest of the command is then
+	///   checked like any other command, so `-dark` clears the `DARK` flag and lights the board.
+	/// - `health` adds 50 health, `ammo` adds 5 ammo, `gems` adds 5 gems, and `torches` adds 3
+	///   torches.
+	/// - `keys` gives the player every key.
+	/// - `time` adds 30 seconds to the board's time limit.
+	/// - `dark` makes the board dark (or lights it with `-dark`).
+	/// - `zap` removes the tiles next to the player.
+	///
+	/// Like DOS ZZT, every command (even an unknown one) is accepted with the same beep. RUZZT also
+	/// has these commands, which DOS ZZT doesn't:
+	/// - `warp` opens a list of boards to go to.
+	/// - `info` shows the board's settings.
+	/// - `code` opens a text editor for the code of a status next to the player.
+	/// - `console` opens a text editor for typing several commands at once, one per line.
+	/// - `flags` lists the world's flag slots, and Enter toggles the selected flag.
+	/// - `watch TARGET` shows the value of `TARGET` on the board every frame, eg.
+	///   `watch status 12 param1` or `watch flag secret` (see `WatchTarget::parse`).
+	/// - `break TARGET` is the same as `watch`, but also pauses the game when the value changes.
+	/// - `unwatch` removes every watch.
+	/// - `messages` shows the last messages sent between statuses, and starts recording them if
+	///   they aren't being recorded yet (see `set_message_timeline_enabled`).
+	fn process_debug_command(&mut self, command: DosString, board_messages: &mut Vec<BoardMessage>) {
+		let mut command = command.to_lower();
+		let mut toggle = true;
+		match command.data.first() {
+			Some(b'+') | Some(b'-') => {
+				toggle = command.data[0] == b'+';
+				command.data.remove(0);
+				if toggle {
+					self.set_flag(&command);
+				} else {
+					self.clear_flag(&command);
+				}
+			}
+			_ => {}
+		}
+
+		let world_header = &mut self.board_simulator.world_header;
+		match command.data.as_slice() {
+			b"health" => {
+				world_header.player_health += 50;
+			}
+			b"ammo" => {
+				world_header.player_ammo += 5;
+			}
+			b"keys" => {
+				world_header.player_keys = [true; 7];
+			}
+			b"torches" => {
+				if let Some(ref mut player_torches) = world_header.player_torches {
+					*player_torches += 3;
+				}
+			}
+			b"time" => {
+				world_header.time_passed -= 30;
+			}
+			b"gems" => {
+				world_header.player_gems += 5;
+			}
+			b"dark" => {
+				self.board_simulator.board_meta_data.is_dark = toggle;
+			}
+				b"zap" => {
+				let player_pos = self.board_simulator.get_player_location();
+				let mut report = ApplyActionResultReport::new();
+				let mut zap_at_offset = |off_x, off_y| {
+					let action = Action::SetTile{
+						x: player_pos.0 + off_x,
+						y: player_pos.1 + off_y,
+						tile: BoardTile {
+							element_id: ElementType::Empty as u8,
+							colour: 0,
+						},
+						status_element: None,
+					};
+					self.board_simulator.apply_action(player_pos.0 + off_x, player_pos.1 + off_y, action, 0, None, &mut self.accumulated_data, &mut report);
+				};
+				zap_at_offset(-1, 0);
+				zap_at_offset(1, 0);
+				zap_at_offset(0, -1);
+				zap_at_offset(0, 1);
+			}
+			b"warp" => {
+				board_messages.push(BoardMessage::OpenBoardList);
+			}
+			b"info" => {
+				let content_lines = board_info::board_info_lines(&self.board_simulator.board_meta_data, &self.world.boards);
+				self.open_scroll(DosString::from_slice(b"Board Info"), content_lines);
+			}
+			b"code" => {
+				let (player_x, player_y) = self.board_simulator.get_player_location();
+				let next_to_player = self.board_simulator.status_elements.iter().enumerate().skip(1).find(|(_, status)| {
+					(status.location_x as i16 - player_x).abs() + (status.location_y as i16 - player_y).abs() == 1
+				});
+				if let Some((status_index, _)) = next_to_player {
+					board_messages.push(BoardMessage::OpenStatusCodeEditor{status_index});
+				}
+			}
+			b"console" => {
+				board_messages.push(BoardMessage::OpenDebugConsole);
+			}
+			b"flags" => {
+				self.flag_list_names.clear();
+				self.open_flag_list(0);
+			}
+			b"unwatch" => {
+				self.watches.clear();
+			}
+			b"messages" => {
+				self.set_message_timeline_enabled(true);
+				let mut content_lines = self.message_timeline().map(|message_timeline| message_timeline.recent_lines(MESSAGE_TIMELINE_SCROLL_LEN)).unwrap_or_default();
+				if content_lines.is_empty() {
+					content_lines.push(DosString::from_slice(b"No messages have been sent yet."));
+				}
+				self.open_scroll(DosString::from_slice(b"Messages"), content_lines);
+			}
+			_ if command.data.starts_with(b"watch ") || command.data.starts_with(b"break ") => {
+				let break_on_change = command.data.starts_with(b"break ");
+				match WatchTarget::parse(&command.to_string(false)[6 ..]) {
+					Ok(target) => self.watches.push(Watch::new(target, break_on_change, &self.board_simulator)),
+					Err(err) => board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, err)),
+				}
+			}
+			_ => {}
+		}
+
+		board_messages.push(BoardMessage::PlaySoundArray(vec![SoundEntry{sound_code: 39, length_multiplier: 4}], SoundPriority::Level(10)));
+	}
+
+	/// Get the world's flags that are set, with the index of the slot each one is in. The
+	/// `reserved_flag_names` aren't included because they aren't kept in a slot.
+	pub fn get_flags(&self) -> Vec<(usize, DosString)> {
+		self.board_simulator.world_header.flag_names.iter().enumerate()
+			.filter(|(_, flag_name)| !flag_name.is_empty())
+			.map(|(slot, flag_name)| (slot, flag_name.clone()))
+			.collect()
+	}
+
+	/// Set the flag with the given `name`, the same as `#set` in ZZT-OOP. Nothing happens if every
+	/// flag slot is taken.
+	pub fn set_flag(&mut self, name: &DosString) {
+		let mut report = ApplyActionResultReport::new();
+		self.board_simulator.apply_action(0, 0, Action::SetFlag(name.clone()), 0, None, &mut self.accumulated_data, &mut report);
+	}
+
+	/// Clear the flag with the given `name`, the same as `#clear` in ZZT-OOP.
+	pub fn clear_flag(&mut self, name: &DosString) {
+		let mut report = ApplyActionResultReport::new();
+		self.board_simulator.apply_action(0, 0, Action::ClearFlag(name.clone()), 0, None, &mut self.accumulated_data, &mut report);
+	}
+
+	/// Get the number of seconds left before the current board's time limit runs out, or None if
+	/// the board doesn't have a time limit (see `BoardSimulator::time_left`).
+	pub fn board_time_left(&self) -> Option<i16> {
+		self.board_simulator.time_left()
+	}
+
+	/// Set the number of seconds left before the current board's time limit runs out, eg. to give
+	/// the player extra time. This changes the world's `time_passed` rather than the board's time
+	/// limit, so the board has its usual limit again the next time the player enters it. Fails if
+	/// the board doesn't have a time limit.
+	pub fn set_board_time_left(&mut self, time_left: i16) -> Result<(), String> {
+		let time_limit = self.board_simulator.board_meta_data.time_limit;
+		if time_limit <= 0 {
+			return Err("The current board doesn't have a time limit".to_string());
+		}
+		self.board_simulator.world_header.time_passed = time_limit.wrapping_sub(time_left);
+		Ok(())
+	}
+
+	/// Get the number of seconds that have passed since the player entered the current board (the
+	/// world header's `time_passed`). This counts up on boards with a time limit, and is also the
+	/// `time` item that `#give` and `#take` change.
+	pub fn time_passed(&self) -> i16 {
+		self.board_simulator.world_header.time_passed
+	}
+
+	/// Set the number of seconds that have passed since the player entered the current board. See
+	/// `time_passed`.
+	pub fn set_time_passed(&mut self, time_passed: i16) {
+		self.board_simulator.world_header.time_passed = time_passed;
+	}
+
+	/// Open a scroll listing every flag slot with the flag in it, with `selected_line` selected.
+	/// Flags that were cleared from the list stay on the line of the slot they were in (as long as
+	/// it's still empty), so they can be set again.
+	fn open_flag_list(&mut self, selected_line: usize) {
+		let flag_names = self.board_simulator.world_header.flag_names.clone();
+		let mut flag_lines = vec![];
+		let mut flag_list_names = vec![];
+		for (slot, flag_name) in flag_names.iter().enumerate() {
+			let (listed_name, state) = if !flag_name.is_empty() {
+				(flag_name.clone(), "On ")
+			} else {
+				match self.flag_list_names.get(slot) {
+					Some(cleared_name) if !cleared_name.is_empty() && !self.board_simulator.flag_is_set(cleared_name) => (cleared_name.clone(), "Off"),
+					_ => (DosString::new(), "   "),
+				}
+			};
+			let mut line = DosString::from_str(&format!("{:2}: {} ", slot + 1, state));
+			line += listed_name.data.as_slice();
+			flag_lines.push(line);
+			flag_list_names.push(listed_name);
+		}
+		self.flag_list_names = flag_list_names;
+
+		let mut scroll_state = ScrollState::new_flag_list(flag_lines);
+		scroll_state.select_line(selected_line);
+		self.scroll_state = Some(scroll_state);
+	}
+
+	/// Restart the random number generator with the given `seed`. After this, the game plays out
+	/// the same way every time it is given the same events.
+	pub fn seed_rng(&mut self, seed: u64) {
+		self.board_simulator.seed_rng(seed);
+	}
+
+	/// Save the game in progress to the savestate `slot`, and to a file if `savestate_directory`
+	/// is set.
+	pub fn save_savestate(&mut self, slot: usize) -> Result<(), String> {
+		if slot >= self.savestate_slots.len() {
+			return Err(format!("There is no savestate slot {}", slot));
+		}
+
+		self.sync_world();
+		let savestate = Savestate {
+			world: self.world.clone(),
+			global_cycle: self.global_cycle,
+			shown_one_time_notifications: self.shown_one_time_notifications.clone(),
+			caption_state: self.caption_state.clone(),
+			scroll_state: self.scroll_state.clone(),
+			sound_queue: Some(self.sound_queue.clone()),
+		};
+
+		self.write_savestate_file(slot, &savestate)?;
+		self.savestate_slots[slot] = Some(savestate);
+		Ok(())
+	}
+
+	/// Play the `sound_queue` for `elapsed_ms` milliseconds of real time. Returns true if the entry
+	/// that should be heard changed, so front-ends should start playing
+	/// `sound_queue.playing_entry()` (or stop if it's None).
+	pub fn advance_sound(&mut self, elapsed_ms: f64) -> bool {
+		self.sound_queue.advance(elapsed_ms)
+	}
+
+	/// Restore the game saved in the savestate `slot`, including the caption and scroll that were
+	/// showing. The game is paused afterwards, like when a saved game is restored.
+	pub fn load_savestate(&mut self, slot: usize) -> Result<(), String> {
+		let savestate = match self.savestate_slots.get(slot) {
+			Some(Some(savestate)) => savestate.clone(),
+			Some(None) => self.read_savestate_file(slot)?,
+			None => return Err(format!("There is no savestate slot {}", slot)),
+		};
+
+		// Loading a world forgets its ambient music and high scores, but it's the same world.
+		let board_ambient_music = std::mem::take(&mut self.board_ambient_music);
+		let high_scores = std::mem::take(&mut self.high_scores);
+		let loaded = self.load_world(savestate.world, None);
+		self.board_ambient_music = board_ambient_music;
+		self.high_scores = high_scores;
+		loaded?;
+		self.set_in_title_screen(false);
+		self.global_cycle = savestate.global_cycle;
+		self.shown_one_time_notifications = savestate.shown_one_time_notifications;
+		self.caption_state = savestate.caption_state;
+		self.scroll_state = savestate.scroll_state;
+		if let Some(sound_queue) = savestate.sound_queue {
+			self.sound_queue = sound_queue.clone();
+			self.accumulated_data.board_messages.push(BoardMessage::RestoreSoundQueue(sound_queue));
+		}
+		Ok(())
+	}
+
+	/// Write `savestate` to the file for `slot` in `savestate_directory`, if it is set.
+	#[cfg(feature = "file-io")]
+	fn write_savestate_file(&self, slot: usize, savestate: &Savestate) -> Result<(), String> {
+		if let Some(ref savestate_directory) = self.savestate_directory {
+			let plain_file_path = Savestate::slot_file_path(savestate_directory, slot);
+			let compressed_file_path = Savestate::slot_compressed_file_path(savestate_directory, slot);
+			let (file_path, old_file_path) = if self.compress_savestates {
+				(compressed_file_path, plain_file_path)
+			} else {
+				(plain_file_path, compressed_file_path)
+			};
+			let mut file = File::create(&file_path).map_err(|e| format!("Couldn't create {}: {}", file_path.display(), e))?;
+			if self.compress_savestates {
+				savestate.write_compressed(&mut file)
+			} else {
+				savestate.write(&mut file)
+			}.map_err(|e| format!("Couldn't write {}: {}", file_path.display(), e))?;
+			// Otherwise the old savestate in the slot could be read instead of this one.
+			if old_file_path.exists() {
+				std::fs::remove_file(&old_file_path).map_err(|e| format!("Couldn't remove {}: {}", old_file_path.display(), e))?;
+			}
+
+			let ui_state_path = Savestate::slot_ui_state_file_path(savestate_directory, slot);
+			let mut ui_state_file = File::create(&ui_state_path).map_err(|e| format!("Couldn't create {}: {}", ui_state_path.display(), e))?;
+			savestate.write_ui_state(&mut ui_state_file).map_err(|e| format!("Couldn't write {}: {}", ui_state_path.display(), e))?;
+		}
+		Ok(())
+	}
+
+	/// Savestates are only kept in memory without the `file-io` feature.
+	#[cfg(not(feature = "file-io"))]
+	fn write_savestate_file(&self, _slot: usize, _savestate: &Savestate) -> Result<(), String> {
+		Ok(())
+	}
+
+	/// Read the savestate for `slot` from `savestate_directory`.
+	#[cfg(feature = "file-io")]
+	fn read_savestate_file(&self, slot: usize) -> Result<Savestate, String> {
+		let savestate_directory = self.savestate_directory.as_ref().ok_or_else(|| format!("Savestate slot {} is empty", slot))?;
+		let compressed_file_path = Savestate::slot_compressed_file_path(savestate_directory, slot);
+		let file_path = if compressed_file_path.exists() {
+			compressed_file_path
+		} else {
+			Savestate::slot_file_path(savestate_directory, slot)
+		};
+		let mut file = File::open(&file_path).map_err(|_| format!("Savestate slot {} is empty", slot))?;
+		let mut savestate = Savestate::parse(&mut file).map_err(|e| format!("Couldn't read {}: {}", file_path.display(), e))?;
+
+		// Savestates written before the rest of the state was saved only have the world.
+		let ui_state_path = Savestate::slot_ui_state_file_path(savestate_directory, slot);
+		if let Ok(mut ui_state_file) = File::open(&ui_state_path) {
+			savestate.parse_ui_state(&mut ui_state_file).map_err(|e| format!("Couldn't read {}: {}", ui_state_path.display(), e))?;
+		}
+		Ok(savestate)
+	}
+
+	/// Savestates are only kept in memory without the `file-io` feature.
+	#[cfg(not(feature = "file-io"))]
+	fn read_savestate_file(&self, slot: usize) -> Result<Savestate, String> {
+		Err(format!("Savestate slot {} is empty", slot))
+	}
+
+	/// Write the world to the file called `file_name` in the current directory (eg. a saved game).
+	/// Returns true if it was written.
+	#[cfg(feature = "file-io")]
+	fn write_world_to_file(&mut self, file_name: &DosString) -> bool {
+		self.sync_world();
+		println!("Save to {:?}", file_name);
+		// Overwrite an existing save with the same name, even if its name isn't in upper case.
+		let file_name_str = file_name.to_string(false);
+		let file_path = dos_files::find_file(Path::new("."), &file_name_str).unwrap_or_else(|| PathBuf::from(file_name_str));
+		if let Ok(mut file) = File::create(file_path) {
+			if let Err(err) = self.world.write(&mut file) {
+				println!("Couldn't write to {:?}: {:?}", file_name, err);
+				return false;
+			}
+			true
+		} else {
+			println!("Couldn't open {:?}", file_name);
+			false
+		}
+	}
+
+	/// Without the `file-io` feature, there is nowhere to write the world to.
+	#[cfg(not(feature = "file-io"))]
+	fn write_world_to_file(&mut self, file_name: &DosString) -> bool {
+		println!("Couldn't save to {:?}: Saving to files isn't supported", file_name);
+		false
+	}
+
+	/// Save a screenshot of the screen to `path` if it is in a format the engine can make (see
+	/// `ScreenshotFormat::screenshot_data`), and show a caption saying where it went.
+	#[cfg(feature = "file-io")]
+	fn write_screenshot(&mut self, path: &Path) -> Result<(), String> {
+		let data = match ScreenshotFormat::from_path(path).and_then(|format| format.screenshot_data(&self.console_state)) {
+			Some(data) => data,
+			None => return Ok(()),
+		};
+		std::fs::write(path, data).map_err(|err| format!("Couldn't write screenshot to {:?}: {}", path, err))?;
+		let file_name = path.file_name().map(|file_name| file_name.to_string_lossy().into_owned()).unwrap_or_default();
+		self.queue_caption(DosString::from_str(&format!("Saved screenshot {}", file_name)));
+		Ok(())
+	}
+
+	/// Without the `file-io` feature, there is nowhere to write the screenshot to.
+	#[cfg(not(feature = "file-io"))]
+	fn write_screenshot(&mut self, path: &Path) -> Result<(), String> {
+		Err(format!("Couldn't save screenshot to {:?}: Saving to files isn't supported", path))
+	}
+
+	/// True if the world has been edited since it was loaded or last saved, so closing it would
+	/// lose work. While this is true, the messages that quit or open another world (see
+	/// `BoardMessage::closes_world`) are held back by `process_typing` and `process_board_message`
+	/// to ask whether to save first. Playing the game doesn't count as editing.
+	pub fn is_dirty(&self) -> bool {
+		self.world_dirty
+	}
+
+	/// Note that the world has been edited. Editors should call this whenever they change the
+	/// world; the engine already does for its own editing (eg. `BoardMessage::SetStatusCode`).
+	pub fn mark_dirty(&mut self) {
+		self.world_dirty = true;
+	}
+
+	/// Note that the world has been saved (or its changes thrown away), so it no longer has
+	/// unsaved changes.
+	pub fn mark_saved(&mut self) {
+		self.world_dirty = false;
+	}
+
+	/// If the world has unsaved changes, take the first message out of `board_messages` that
+	/// closes the world, and ask whether to save the changes before sending it on. Messages that
+	/// already follow the answer to that question go through.
+	fn hold_back_for_unsaved_changes(&mut self, board_messages: &mut Vec<BoardMessage>) {
+		let answered = board_messages.iter().any(|board_message| matches!(board_message, BoardMessage::SaveWorld | BoardMessage::DiscardWorldChanges));
+		if !self.world_dirty || answered {
+			return;
+		}
+		if let Some(message_index) = board_messages.iter().position(BoardMessage::closes_world) {
+			let then = board_messages.remove(message_index);
+			let world_name = self.board_simulator.world_header.world_name.clone();
+			self.side_bar.open_yes_no_input(side_bar::YesNoMode::SaveChanges{world_name, then: Box::new(then)});
+		}
+	}
+
+	/// Handle the savestate events, returning true if `event` was one of them.
+	fn process_savestate_event(&mut self, event: Event) -> bool {
+		let slot = self.selected_savestate_slot;
+		let caption_text = match event {
+			Event::QuickSave => {
+				match self.save_savestate(slot) {
+					Ok(()) => format!("Saved to slot {}", slot),
+					Err(err) => err,
+				}
+			}
+			Event::QuickLoad => {
+				match self.load_savestate(slot) {
+					// The caption that was showing when the game was saved is shown instead.
+					Ok(()) if self.caption_state.is_some() => return true,
+					Ok(()) => format!("Loaded slot {}", slot),
+					Err(err) => err,
+				}
+			}
+			Event::SelectSavestateSlot(new_slot) if (new_slot as usize) < SAVESTATE_SLOT_COUNT => {
+				self.selected_savestate_slot = new_slot as usize;
+				format!("Savestate slot {}", new_slot)
+			}
+			_ => return false,
+		};
+
+		self.caption_state = Some(CaptionState::new(DosString::from_str(&caption_text)));
+		true
+	}
+
+	/// Register a `GameObserver` to be notified about changes to the game, such as the score
+	/// changing or a board being entered. Observers aren't notified while in the title screen or
+	/// while the `attract_mode` demo is playing.
+	pub fn add_observer(&mut self, observer: SharedGameObserver) {
+		self.observers.push(observer);
+	}
+
+	/// Notify the `observers` about anything that changed since they were last notified.
+	fn notify_observers(&mut self) {
+		let new_state = ObservedState::from_world_header(&self.board_simulator.world_header);
+		if !self.in_title_screen && !self.is_playing_attract_demo() {
+			self.observed_state.notify_changes(&new_state, &self.observers);
+		}
+		self.observed_state = new_state;
 	}
 
 	/// This is true if the game is in "typing" mode, which usually means a text input is open, and
 	/// the engine wants `process_typing` to be called instead of `step`.
 	pub fn in_typing_mode(&self) -> bool {
-		self.side_bar.in_typing_mode()
+		self.text_editor_state.is_some() || self.side_bar.in_typing_mode()
 	}
 
-	// TODO: Don't play sounds when the game is over.
 	/// True when the game is over, and all the user can do is press escape to exit to the title
-	/// screen.
+	/// screen. Like DOS ZZT, the board keeps being simulated as fast as possible while the game is
+	/// over, with the player drawn as usual, but the board's sounds are muted.
 	pub fn is_end_of_game(&self) -> bool {
 		self.board_simulator.world_header.player_health <= 0
 	}
 
+	/// Get the title for the window the game is shown in, with the name of the world and, while
+	/// playing, the name of the current board, eg. "RUZZT — TOWN: The Armory". Front-ends can
+	/// compare it with the title they last set to notice when it changes.
+	pub fn window_title(&self) -> String {
+		let world_name = &self.board_simulator.world_header.world_name;
+		let board_name = &self.board_simulator.board_meta_data.board_name;
+		if world_name.is_empty() {
+			"RUZZT".to_string()
+		} else if self.in_title_screen || board_name.is_empty() {
+			format!("RUZZT — {}", world_name.to_string(false))
+		} else {
+			format!("RUZZT — {}: {}", world_name.to_string(false), board_name.to_string(false))
+		}
+	}
+
 	/// See the `board_should_simulate_fast` field in the struct. This doesn't return true if a
 	/// scroll or text input is open.
 	pub fn should_simulate_fast(&self) -> bool {
-		self.board_should_simulate_fast && self.scroll_state.is_none() && !self.side_bar.in_typing_mode()
+		self.board_should_simulate_fast && self.scroll_state.is_none() && self.world_browser_state.is_none() && self.pause_menu_state.is_none() && !self.in_typing_mode()
+	}
+
+	/// Get how long the front-end should wait between steps, in milliseconds, for the `game_speed`
+	/// and `machine_speed` on the current board (see `MachineSpeed::cycle_length_ms`). This doesn't
+	/// take `should_simulate_fast` into account.
+	pub fn step_length_ms(&self) -> f64 {
+		self.machine_speed.cycle_length_ms(self.game_speed, self.board_simulator.status_elements.len())
 	}
 
 	/// Returns true if a board simulation step was paused half-way through, such as when a scroll
@@ -222,10 +1374,43 @@ impl RuzztEngine {
 		self.board_simulator_step_state.is_some()
 	}
 
+	/// Show a one-line caption at the bottom of the board. If another caption is already showing, it
+	/// is cut short (see `CAPTION_REPLACEMENT_TIME`) and `text` is shown after it, so captions sent
+	/// at almost the same time (eg. picking up a key and the message from a door) are all seen.
+	/// Queueing the caption that is showing again restarts it instead, like in DOS ZZT.
+	pub fn queue_caption(&mut self, text: DosString) {
+		match self.caption_state {
+			None => self.caption_state = Some(CaptionState::new(text)),
+			Some(ref mut caption_state) if self.pending_captions.is_empty() && caption_state.has_text(&text) => {
+				caption_state.time_left = CAPTION_TIME;
+			}
+			Some(ref mut caption_state) => {
+				if self.pending_captions.back() == Some(&text) {
+					return;
+				}
+				caption_state.time_left = caption_state.time_left.min(CAPTION_REPLACEMENT_TIME);
+				if self.pending_captions.len() >= MAX_PENDING_CAPTIONS {
+					self.pending_captions.pop_front();
+				}
+				self.pending_captions.push_back(text);
+			}
+		}
+	}
+
+	/// Get the number of captions waiting to be shown after the one that is showing.
+	pub fn pending_caption_count(&self) -> usize {
+		self.pending_captions.len()
+	}
+
+	/// Stop showing the current caption, and show the next one waiting in the queue if there is one.
+	fn next_caption(&mut self) {
+		self.caption_state = self.pending_captions.pop_front().map(CaptionState::new);
+	}
+
 	/// Applies the default action for the given `board_message`. For example, it will switch boards
 	/// on a `SwitchBoard` or `TeleportToBoard` message. This doens't have any effect for anything
-	/// to do with input/output (playing sound, opening worlds from the disk) because those are all
-	/// left up to the front-end.
+	/// to do with input/output (opening worlds from the disk) because those are all left up to the
+	/// front-end. Sounds are added to the `sound_queue`, but the front-end still has to play them.
 	/// Returns any BoardMessages that happen to be created when `board_message` is applied.
 	pub fn process_board_message(&mut self, board_message: BoardMessage) -> Vec<BoardMessage> {
 		let mut extra_accumulated_data = AccumulatedActionData::new();
@@ -253,7 +1438,7 @@ impl RuzztEngine {
 				self.board_simulator.world_header.player_board = new_board_index as i16;
 
 				self.board_simulator.save_board(&mut self.world.boards[original_board_index as usize]);
-				self.board_simulator.load_board(&self.world.boards[self.board_simulator.world_header.player_board as usize]);
+				self.load_world_board(self.board_simulator.world_header.player_board as usize);
 
 				let (off_x, off_y) = direction.to_offset();
 				// Check if where the player is trying to go on the destination board is blocked.
@@ -266,167 +1451,648 @@ impl RuzztEngine {
 				} else {
 					self.board_simulator.save_board(&mut self.world.boards[self.board_simulator.world_header.player_board as usize]);
 					self.board_simulator.world_header.player_board = original_board_index;
-					self.board_simulator.load_board(&self.world.boards[self.board_simulator.world_header.player_board as usize]);
+					self.load_world_board(self.board_simulator.world_header.player_board as usize);
 				}
+				self.on_board_entered();
 			}
 			BoardMessage::TeleportToBoard{destination_board_index, passage_colour} => {
 				self.board_simulator.save_board(&mut self.world.boards[self.board_simulator.world_header.player_board as usize]);
 
 				self.board_simulator.world_header.player_board = destination_board_index as i16;
-				self.board_simulator.load_board(&self.world.boards[self.board_simulator.world_header.player_board as usize]);
+				self.load_world_board(self.board_simulator.world_header.player_board as usize);
 
 				let passage_location_opt = self.board_simulator.get_passage_location(passage_colour);
-				if let Some(passage_location) = passage_location_opt {
+				let player_index_opt = self.board_simulator.player_status_index();
+				if let (Some(passage_location), Some(player_index)) = (passage_location_opt, player_index_opt) {
 					let player_location = self.board_simulator.get_player_location();
 					//self.board_simulator.move_tile(player_location.0, player_location.1, passage_location.0, passage_location.1);
 					// For some reason ZZT manually moves the player when they use a passage, so it
 					// can do weird stuff like pick up the tile underneath a player and put it
 					// somewhere else.
-					self.board_simulator.status_elements[0].location_x = passage_location.0 as u8;
-					self.board_simulator.status_elements[0].location_y = passage_location.1 as u8;
-					if let Some(old_tile) = self.board_simulator.get_tile_mut(player_location.0, player_location.1) {
+					self.board_simulator.status_elements[player_index].location_x = passage_location.0 as u8;
+					self.board_simulator.status_elements[player_index].location_y = passage_location.1 as u8;
+					if let Some(mut old_tile) = self.board_simulator.get_tile(player_location.0, player_location.1) {
 						old_tile.element_id = ElementType::Empty as u8;
+						self.board_simulator.set_tile(player_location.0, player_location.1, old_tile);
 					}
 				}
 				self.board_simulator.on_player_entered_board(&mut extra_accumulated_data.board_messages);
+				self.board_simulator.player_on_passage = true;
 				self.is_paused = true;
+				self.on_board_entered();
 			}
 			BoardMessage::ShowOneTimeNotification(notification_type) => {
 				if !self.shown_one_time_notifications.contains(&notification_type) {
-					self.caption_state = Some(CaptionState::new(notification_type.message_string()));
+					self.message_log.push(DosString::new(), vec![notification_type.message_string()]);
+					self.queue_caption(notification_type.message_string());
 					self.shown_one_time_notifications.insert(notification_type);
 				}
 			}
 			BoardMessage::OpenScroll{title, content_lines} => {
+				self.message_log.push(title.clone(), content_lines.clone());
 				if content_lines.len() > 1 {
 					self.scroll_state = Some(ScrollState::new_title_content(title, content_lines));
 				} else if content_lines.len() == 1 {
-					self.caption_state = Some(CaptionState::new(content_lines[0].clone()));
+					self.queue_caption(content_lines[0].clone());
 				}
 			}
+			BoardMessage::OpenWorldMap => {
+				let current_board_index = self.board_simulator.world_header.player_board as u8;
+				self.sync_world();
+				let content_lines = self.world_map.scroll_lines(&self.world.boards, current_board_index);
+				self.open_scroll(DosString::from_slice(b"World Map"), content_lines);
+			}
+			BoardMessage::OpenMessageLog => {
+				let content_lines = self.message_log.scroll_lines();
+				self.open_scroll(DosString::from_slice(b"Message Log"), content_lines);
+			}
 			BoardMessage::CloseScroll => {
 				self.scroll_state = None;
+				if self.shareware_flavour.as_mut().is_some_and(|shareware_flavour| shareware_flavour.scroll_closed()) {
+					extra_accumulated_data.board_messages.push(BoardMessage::Quit);
+				}
 			}
-			BoardMessage::PlaySoundArray(..) => {
-				// Do nothing. The frontend should handle this itself.
+			BoardMessage::CloseWorldBrowser => {
+				self.world_browser_state = None;
+			}
+			BoardMessage::WorldBrowserEntrySelected{entry_index} => {
+				// Entries in a file browser are handled here, and the rest are up to the frontend.
+				#[cfg(feature = "file-io")]
+				{
+					if let Some(mut file_browser) = self.file_browser.take() {
+						match file_browser.select(entry_index) {
+							FileBrowserSelection::ChangedDirectory => {
+								self.open_file_browser(file_browser);
+							}
+							FileBrowserSelection::File(file_path) => {
+								extra_accumulated_data.board_messages.push(BoardMessage::FileBrowserFileSelected{file_path});
+							}
+							FileBrowserSelection::None => {}
+						}
+					}
+				}
+				#[cfg(not(feature = "file-io"))]
+				let _ = entry_index;
+			}
+			BoardMessage::OpenPauseMenu => {
+				self.pause_menu_state = Some(PauseMenuState::new());
+			}
+			BoardMessage::ClosePauseMenu => {
+				self.pause_menu_state = None;
+			}
+			BoardMessage::PlaySoundArray(sound_entries, priority) => {
+				self.sound_queue.queue(sound_entries, priority);
 			}
 			BoardMessage::ClearPlayingSound => {
-				// Do nothing. The frontend should handle this itself.
+				self.sound_queue.clear();
+			}
+			BoardMessage::SetAmbientMusic(sound_entries) => {
+				self.sound_queue.set_ambient_loop(sound_entries);
+			}
+			BoardMessage::RestoreSoundQueue(..) => {
+				// Do nothing. The `sound_queue` was already restored with the savestate.
 			}
 			BoardMessage::OpenSaveGameInput => {
 				self.side_bar.open_text_input(side_bar::TextInputMode::SaveFile, b"SAVED");
 			}
 			BoardMessage::SaveGameToFile(file_name) => {
-				self.sync_world();
-				println!("Save to {:?}", file_name);
-				if let Ok(mut file) = File::create(file_name.to_string(false)) {
-					if let Err(err) = self.world.write(&mut file) {
-						println!("Couldn't write to {:?}: {:?}", file_name, err);
+				self.write_world_to_file(&file_name);
+			}
+			BoardMessage::OpenNewWorldInput => {
+				self.side_bar.open_text_input(side_bar::TextInputMode::NewWorld, b"");
+			}
+			BoardMessage::CreateWorld{name} => {
+				let mut world = zzt_file_format::World::zzt_default();
+				world.world_header.world_name = name.clone();
+				match self.load_world(world, None) {
+					Ok(()) => {
+						let mut file_name = name;
+						file_name += b".ZZT";
+						self.write_world_to_file(&file_name);
+					}
+					Err(err) => extra_accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, err)),
+				}
+			}
+			BoardMessage::OpenDebugInput => {
+				self.side_bar.open_text_input(side_bar::TextInputMode::Debug, b"");
+			}
+			BoardMessage::DebugCommand(command) => {
+				self.process_debug_command(command, &mut extra_accumulated_data.board_messages);
+			}
+			BoardMessage::OpenStatusCodeEditor{status_index} => {
+				if let Some(status) = self.board_simulator.status_elements.get(status_index) {
+					let code = self.board_simulator.get_status_code(status).clone();
+					let title = DosString::from_str(&format!("Edit code of status {}", status_index));
+					self.text_editor_state = Some(TextEditorState::new(title, TextEditorPurpose::StatusCode{status_index}, &code));
+				}
+			}
+			BoardMessage::OpenDebugConsole => {
+				self.text_editor_state = Some(TextEditorState::new(DosString::from_slice(b"Debug console"), TextEditorPurpose::DebugConsole, &DosString::new()));
+			}
+			BoardMessage::SetStatusCode{status_index, code} => {
+				if let Some(status) = self.board_simulator.status_elements.get_mut(status_index) {
+					status.code_source = CodeSource::Owned(code);
+					status.code_current_instruction = 0;
+					self.world_dirty = true;
+				}
+			}
+			BoardMessage::SaveWorld => {
+				let world_header = &self.board_simulator.world_header;
+				let mut file_name = world_header.world_name.clone().to_upper();
+				if file_name.data.is_empty() {
+					file_name = DosString::from_slice(b"UNTITLED");
+				}
+				file_name += format!(".{}", world_header.world_type.file_extension()).as_bytes();
+				if self.write_world_to_file(&file_name) {
+					self.world_dirty = false;
+				}
+			}
+			BoardMessage::DiscardWorldChanges => {
+				self.world_dirty = false;
+			}
+			BoardMessage::SaveScreenshot{path} => {
+				if let Err(err) = self.write_screenshot(&path) {
+					extra_accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, err));
+				}
+			}
+			BoardMessage::OpenPassagePasswordInput{status_index} => {
+				self.side_bar.open_text_input(side_bar::TextInputMode::PassagePassword{status_index}, b"");
+			}
+			BoardMessage::PassagePasswordEntered{status_index, password} => {
+				let passage_opt = self.board_simulator.status_elements.get(status_index).map(|status| {
+					let lock_opt = PassageLock::from_code(self.board_simulator.get_status_code(status));
+					let tile_opt = self.board_simulator.get_tile(status.location_x as i16, status.location_y as i16);
+					(lock_opt, status.param3, tile_opt.map_or(0, |tile| tile.colour))
+				});
+				if let Some((Some(lock), destination_board_index, passage_colour)) = passage_opt {
+					if lock.password_matches(&password) {
+						// The passage stays unlocked, even after leaving the board.
+						self.board_simulator.status_elements[status_index].code_source = CodeSource::Owned(DosString::new());
+						// This is the same as going through any other passage (see `PassageBehaviour`).
+						extra_accumulated_data.board_messages.push(BoardMessage::PlaySoundArray(
+							process_notes_string_to_sound_entries(b"tcegc#fg#df#ad#ga#eg#+c"), SoundPriority::Level(4)));
+						extra_accumulated_data.board_messages.push(BoardMessage::TeleportToBoard{destination_board_index, passage_colour});
+					} else {
+						extra_accumulated_data.board_messages.push(BoardMessage::PlaySoundArray(
+							process_notes_string_to_sound_entries(b"--tgc"), SoundPriority::Level(3)));
+						extra_accumulated_data.board_messages.push(BoardMessage::OpenScroll {
+							title: DosString::new(),
+							content_lines: vec![DosString::from_slice(b"Wrong password!")],
+						});
 					}
+				}
+			}
+			BoardMessage::CloseTextEditor => {
+				self.text_editor_state = None;
+			}
+			BoardMessage::OpenBoardList => {
+				let board_names = self.world.boards.iter().enumerate().map(|(board_index, board)| {
+					let mut line = DosString::from_str(&format!("{:3}: ", board_index));
+					line += &board.meta_data.board_name.data;
+					line
+				}).collect();
+				self.scroll_state = Some(ScrollState::new_board_list(board_names));
+			}
+			BoardMessage::ToggleListedFlag{line_index} => {
+				if let Some(flag_name) = self.flag_list_names.get(line_index).filter(|flag_name| !flag_name.is_empty()).cloned() {
+					if self.board_simulator.flag_is_set(&flag_name) {
+						self.clear_flag(&flag_name);
+					} else {
+						self.set_flag(&flag_name);
+					}
+				}
+				self.open_flag_list(line_index);
+			}
+			BoardMessage::WarpToBoard{board_index} => {
+				if board_index < self.world.boards.len() {
+					self.board_simulator.save_board(&mut self.world.boards[self.board_simulator.world_header.player_board as usize]);
+
+					self.board_simulator.world_header.player_board = board_index as i16;
+					self.load_world_board(board_index);
+
+					let (player_x, player_y) = self.board_simulator.get_player_location();
+					let enter_x = self.board_simulator.board_meta_data.player_enter_x as i16;
+					let enter_y = self.board_simulator.board_meta_data.player_enter_y as i16;
+					let enter_pos_is_on_board = enter_x >= 1 && enter_x <= BOARD_WIDTH as i16 - 2 && enter_y >= 1 && enter_y <= BOARD_HEIGHT as i16 - 2;
+					if enter_pos_is_on_board && (enter_x, enter_y) != (player_x, player_y) {
+						self.board_simulator.move_tile(player_x, player_y, enter_x, enter_y);
+					}
+					self.board_simulator.on_player_entered_board(&mut extra_accumulated_data.board_messages);
+					self.is_paused = true;
+					self.on_board_entered();
+				}
+			}
+			BoardMessage::LinkClicked(link_label) => {
+				// Links to other worlds (eg. `!-NEXT.ZZT;Go on`) open that world.
+				// TODO: Other links starting with "-" are help files in DOS ZZT.
+				let upper_label = link_label.clone().to_upper();
+				if upper_label.data.first() == Some(&b'-') && upper_label.data.ends_with(b".ZZT") {
+					let filename = DosString::from_slice(&upper_label.data[1 ..]);
+					extra_accumulated_data.board_messages.push(BoardMessage::OpenWorld{filename});
 				} else {
-					println!("Couldn't open {:?}", file_name);
+					self.clicked_link_label = Some(link_label);
+				}
+			}
+			BoardMessage::PauseGame => {
+				if !self.is_end_of_game() {
+					self.is_paused = true;
+				}
+			}
+			BoardMessage::OpenAbout => {
+				self.open_scroll(DosString::from_slice(b"About RUZZT"), about_scroll_lines());
+			}
+			BoardMessage::OpenHighScores => {
+				let mut title = DosString::from_slice(b"High scores for ");
+				title += self.world.world_header.world_name.data.as_slice();
+				self.open_scroll(title, self.high_scores.scroll_lines());
+			}
+			BoardMessage::PlayGame => {
+				self.set_in_title_screen(false);
+				extra_accumulated_data.board_messages.push(BoardMessage::ClearPlayingSound);
+			}
+			BoardMessage::OpenEndGameConfirmation => {
+				self.side_bar.open_yes_no_input(side_bar::YesNoMode::EndGame);
+			}
+			BoardMessage::OpenQuitConfirmation => {
+				self.side_bar.open_yes_no_input(side_bar::YesNoMode::Quit);
+			}
+			BoardMessage::OpenGameSpeedInput => {
+				self.side_bar.open_slider_input(side_bar::SliderMode::GameSpeed, self.game_speed);
+			}
+			BoardMessage::SetGameSpeed(game_speed) => {
+				self.game_speed = game_speed;
+			}
+			BoardMessage::OpenKeyboardConfigInput => {
+				let repeat_delay = self.key_repeat.repeat_delay.min(side_bar::SLIDER_MAX_VALUE as usize) as u8;
+				self.side_bar.open_slider_input(side_bar::SliderMode::KeyRepeatDelay, repeat_delay);
+			}
+			BoardMessage::SetKeyRepeatDelay(repeat_delay) => {
+				self.key_repeat.repeat_delay = repeat_delay as usize;
+			}
+			BoardMessage::ReturnToTitleScreen => {
+				self.set_in_title_screen(true);
+			}
+			BoardMessage::EndGame => {
+				self.set_in_title_screen(true);
+				let world_header = &self.board_simulator.world_header;
+				let mut filename = world_header.world_name.clone().to_upper();
+				filename += format!(".{}", world_header.world_type.file_extension()).as_bytes();
+				extra_accumulated_data.board_messages.push(BoardMessage::OpenWorld{filename});
+			}
+			BoardMessage::WorldLoadFailed{filename, error} => {
+				self.set_in_title_screen(true);
+				let mut message = DosString::from_slice(b"Can't load ");
+				message += &filename.data;
+				self.open_scroll(DosString::from_slice(b"Error"), vec![
+					message,
+					DosString::new(),
+					DosString::from_str(&error),
+				]);
+			}
+			| BoardMessage::Quit
+			| BoardMessage::OpenWorldSelection
+			| BoardMessage::OpenSaveSelection
+			| BoardMessage::OpenSettings
+			| BoardMessage::PlayerAppearsStuck
+			| BoardMessage::EngineLog(..)
+			| BoardMessage::OpenWorld{..}
+			| BoardMessage::EnterPressedInScroll{..}
+			| BoardMessage::FileBrowserFileSelected{..}
+			| BoardMessage::ResumeAutosave => {
+				// Do nothing. The frontend should handle these itself.
+			}
+		}
+
+		self.notify_observers();
+
+		self.hold_back_for_unsaved_changes(&mut extra_accumulated_data.board_messages);
+		self.compact_boards();
+		extra_accumulated_data.board_messages
+	}
+
+	/// Open a scroll with the given `title` and `content_lines`.
+	pub fn open_scroll(&mut self, title: DosString, content_lines: Vec<DosString>) {
+		self.scroll_state = Some(ScrollState::new_title_content(title, content_lines));
+	}
+
+	/// Switch the console to another text mode. The screen is redrawn by the next `update_screen`.
+	pub fn set_screen_mode(&mut self, screen_mode: ScreenMode) {
+		self.console_state.set_screen_mode(screen_mode);
+	}
+
+	/// Set how keys and doors that aren't one of the seven key colours behave. This stays the same
+	/// when a different world is loaded.
+	pub fn set_key_colour_policy(&mut self, policy: KeyColourPolicy) {
+		self.board_simulator.settings.key_colour_policy = policy;
+	}
+
+	/// Set the most OOP instructions an object can run in one step (see
+	/// `SimulatorSettings::oop_instruction_limit`). This stays the same when a different world is
+	/// loaded.
+	pub fn set_oop_instruction_limit(&mut self, limit: usize) {
+		self.board_simulator.settings.oop_instruction_limit = limit;
+	}
+
+	/// Set what happens when giving or taking a player item would take it past what it can hold
+	/// (see `ItemOverflowPolicy`). This stays the same when a different world is loaded.
+	pub fn set_item_overflow_policy(&mut self, policy: ItemOverflowPolicy) {
+		self.board_simulator.settings.item_overflow_policy = policy;
+	}
+
+	/// Set which version of DOS ZZT to behave like where versions differ (see
+	/// `CompatibilityVersion`). This stays the same when a different world is loaded.
+	pub fn set_compatibility_version(&mut self, compatibility_version: CompatibilityVersion) {
+		self.board_simulator.settings.compatibility_version = compatibility_version;
+	}
+
+	/// Turn collecting a `StepProfile` on or off. Profiling stays on when a different world is
+	/// loaded. It is off by default because it slows the simulation down a little.
+	pub fn set_profiling_enabled(&mut self, enabled: bool) {
+		let mut profile = self.board_simulator.profile.borrow_mut();
+		if enabled != profile.is_some() {
+			*profile = if enabled { Some(StepProfile::default()) } else { None };
+		}
+	}
+
+	/// Get the counters collected since profiling was turned on or this was last called, and start
+	/// counting from zero again. Call this after every `step` to get the counters for each frame.
+	/// Returns None if profiling is off.
+	pub fn take_profile(&mut self) -> Option<StepProfile> {
+		self.board_simulator.profile.borrow_mut().as_mut().map(std::mem::take)
+	}
+
+	/// Turn recording a `MessageTimeline` of every label operation delivered between statuses on or
+	/// off. Turning it on starts with an empty timeline that keeps the last
+	/// `DEFAULT_MESSAGE_TIMELINE_LEN` deliveries. It stays on when a different world is loaded,
+	/// but starts again from empty.
+	pub fn set_message_timeline_enabled(&mut self, enabled: bool) {
+		let mut message_timeline = self.board_simulator.message_timeline.borrow_mut();
+		if enabled != message_timeline.is_some() {
+			*message_timeline = if enabled { Some(MessageTimeline::new(DEFAULT_MESSAGE_TIMELINE_LEN)) } else { None };
+		}
+	}
+
+	/// Get a copy of the message timeline, or None if it isn't being recorded.
+	pub fn message_timeline(&self) -> Option<MessageTimeline> {
+		self.board_simulator.message_timeline.borrow().clone()
+	}
+
+	/// Write the message timeline to the file at `path` as JSON (see `MessageTimeline::to_json`).
+	#[cfg(feature = "file-io")]
+	pub fn write_message_timeline(&self, path: &Path) -> Result<(), String> {
+		let message_timeline = self.message_timeline().ok_or_else(|| "The message timeline isn't being recorded".to_string())?;
+		std::fs::write(path, message_timeline.to_json()).map_err(|err| format!("Couldn't write {}: {}", path.display(), err))
+	}
+
+	/// Keep the tiles of the boards that aren't loaded run-length encoded when `compact` is true,
+	/// instead of fully expanded in `world`, to save memory in worlds with lots of boards (see
+	/// `RleTiles`). The boards are expanded again when they are loaded, and all of them are while
+	/// the whole world is needed (eg. when saving). Use `board` to read a board whether it is
+	/// compacted or not.
+	pub fn set_compact_idle_boards(&mut self, compact: bool) {
+		self.compact_idle_boards = compact;
+		if compact {
+			self.compact_boards();
+		} else {
+			self.sync_world();
+		}
+	}
+
+	/// Get the board at `board_index` in the world, with its tiles even if it has been compacted
+	/// (see `set_compact_idle_boards`). The board that is loaded in the simulator is only up to date
+	/// after `sync_world`.
+	pub fn board(&self, board_index: usize) -> Option<Cow<'_, zzt_file_format::Board>> {
+		let board = self.world.boards.get(board_index)?;
+		match self.compacted_tiles.get(&board_index) {
+			Some(compacted_tiles) => {
+				let mut board = board.clone();
+				board.tiles = compacted_tiles.decode();
+				Some(Cow::Owned(board))
+			}
+			None => Some(Cow::Borrowed(board)),
+		}
+	}
+
+	/// Put the tiles of the board at `board_index` back into `world` if it was compacted.
+	fn expand_board(&mut self, board_index: usize) {
+		if let Some(compacted_tiles) = self.compacted_tiles.remove(&board_index) {
+			self.world.boards[board_index].tiles = compacted_tiles.decode();
+		}
+	}
+
+	/// Put the tiles of every compacted board back into `world`.
+	fn expand_all_boards(&mut self) {
+		for board_index in self.compacted_tiles.keys().cloned().collect::<Vec<_>>() {
+			self.expand_board(board_index);
+		}
+	}
+
+	/// Compact the tiles of every board in `world` except the title board and the player's board,
+	/// which are the ones that can be loaded, if `compact_idle_boards` is set.
+	fn compact_boards(&mut self) {
+		if !self.compact_idle_boards {
+			return;
+		}
+		let player_board_index = self.board_simulator.world_header.player_board as usize;
+		for (board_index, board) in self.world.boards.iter_mut().enumerate() {
+			if board_index == 0 || board_index == player_board_index || self.compacted_tiles.contains_key(&board_index) {
+				continue;
+			}
+			self.compacted_tiles.insert(board_index, RleTiles::encode(&board.tiles));
+			board.tiles = vec![];
+		}
+	}
+
+	/// Load the board at `board_index` in `world` into the simulator, expanding it first if it was
+	/// compacted.
+	fn load_world_board(&mut self, board_index: usize) {
+		self.expand_board(board_index);
+		self.board_simulator.load_board(&self.world.boards[board_index]);
+	}
+
+	/// Allow boards to have any number of status elements when `unlimited` is true. Otherwise they
+	/// are limited to the same number as in DOS ZZT, so worlds behave the same as they did there.
+	pub fn set_unlimited_status_elements(&mut self, unlimited: bool) {
+		self.unlimited_status_elements = unlimited;
+		self.board_simulator.status_element_limit = if unlimited {
+			None
+		} else {
+			Some(default_status_element_limit(self.board_simulator.world_header.world_type))
+		};
+		self.board_simulator.settings.reserved_flags = self.reserved_flag_names();
+	}
+
+	/// Set whether worlds can detect RUZZT and its extensions with the reserved flags (see
+	/// `reserved_flag_names`). This stays the same when a different world is loaded.
+	pub fn set_extension_level(&mut self, extension_level: ExtensionLevel) {
+		self.extension_level = extension_level;
+		self.board_simulator.settings.reserved_flags = self.reserved_flag_names();
+		self.board_simulator.settings.extended_colours_enabled = extension_level == ExtensionLevel::Ruzzt;
+		self.board_simulator.settings.locked_passages_enabled = extension_level == ExtensionLevel::Ruzzt;
+	}
+
+	/// Replace the behaviour of every tile of the given `element_type`, eg. to change how lions move
+	/// in a total conversion without changing `zzt_behaviours`. The new behaviour replaces all of
+	/// the old one (including how it is pushed, damaged, etc.), so to only change part of it, keep
+	/// the old behaviour from `board_simulator.behaviours` before overriding it and pass the other
+	/// methods through to it.
+	///
+	/// The override takes effect straight away, and stays when a different world is loaded. In
+	/// Super ZZT worlds it applies to the Super ZZT element that is the same as `element_type`.
+	pub fn override_behaviour(&mut self, element_type: ElementType, behaviour: Box<dyn Behaviour>) {
+		self.behaviour_overrides.insert(element_type as u8, behaviour.into());
+		apply_behaviour_overrides(&self.behaviour_overrides, &mut self.board_simulator);
+	}
+
+	/// Remove every behaviour set with `override_behaviour`. The usual behaviours are used again the
+	/// next time a world is loaded.
+	pub fn clear_behaviour_overrides(&mut self) {
+		self.behaviour_overrides.clear();
+	}
+
+	/// Run every world with the given `ruleset` instead of the stock ruleset for the world's type,
+	/// or go back to the stock rulesets with None. This takes effect straight away, and the
+	/// behaviours from `override_behaviour` still apply on top of the ruleset's.
+	pub fn set_ruleset(&mut self, ruleset: Option<Rc<dyn RulesetPlugin>>) {
+		self.ruleset = ruleset;
+		self.board_simulator.ruleset = builtin_ruleset(self.board_simulator.world_header.world_type);
+		load_behaviours(&self.ruleset, &self.behaviour_overrides, &mut self.board_simulator);
+	}
+
+	/// Get the flags that are always set for the current `ExtensionLevel`. With
+	/// `ExtensionLevel::Ruzzt` these are:
+	/// - `RUZZT`, so a world can check `#if ruzzt` to use RUZZT's extensions.
+	/// - `RUZZTV` followed by the major then minor version number, eg. `RUZZTV01` for version 0.1.
+	/// - `RUZZTNOLIMIT` when boards can have any number of status elements (see
+	///   `set_unlimited_status_elements`).
+	/// - `RUZZTCAMPAIGN` when the world was opened with a campaign manifest (see
+	///   `campaign_carry_over`).
+	/// - `RUZZTCOLOUR`, so a world can check that objects can use `#colour`.
+	/// - `RUZZTLOCK`, so a world can check that passages can be locked (see `PassageLock`).
+	///
+	/// Flag names in OOP can only have letters followed by digits, so these don't have any other
+	/// characters.
+	pub fn reserved_flag_names(&self) -> Vec<DosString> {
+		if self.extension_level == ExtensionLevel::Dos {
+			return vec![];
+		}
+
+		let mut version_parts = ENGINE_VERSION.split('.');
+		let major_version = version_parts.next().unwrap_or("0");
+		let minor_version = version_parts.next().unwrap_or("0");
+		let mut flag_names = vec![
+			DosString::from_slice(b"RUZZT"),
+			DosString::from_str(&format!("RUZZTV{}{}", major_version, minor_version)),
+		];
+		if self.unlimited_status_elements {
+			flag_names.push(DosString::from_slice(b"RUZZTNOLIMIT"));
+		}
+		if self.campaign_carry_over.is_some() {
+			flag_names.push(DosString::from_slice(b"RUZZTCAMPAIGN"));
+		}
+		flag_names.push(DosString::from_slice(b"RUZZTCOLOUR"));
+		flag_names.push(DosString::from_slice(b"RUZZTLOCK"));
+		flag_names
+	}
+
+	/// Queue up `commands` to be run as a cutscene during the following steps. While the cutscene
+	/// runs, input is ignored (except by open scrolls), and escape skips the rest of it.
+	pub fn play_cutscene(&mut self, commands: Vec<CutsceneCommand>) {
+		self.cutscene_state.queue(commands);
+	}
+
+	/// Run the cutscene commands that are due in the current step.
+	fn run_cutscene_commands(&mut self, board_messages: &mut Vec<BoardMessage>) {
+		while let Some(command) = self.cutscene_state.next_command() {
+			match command {
+				CutsceneCommand::ShowCaption(text) => {
+					self.message_log.push(DosString::new(), vec![text.clone()]);
+					self.queue_caption(text);
 				}
-			}
-			BoardMessage::OpenDebugInput => {
-				self.side_bar.open_text_input(side_bar::TextInputMode::Debug, b"");
-			}
-			BoardMessage::DebugCommand(command) => {
-				match command.to_lower().data.as_slice() {
-					b"ammo" => {
-						self.board_simulator.world_header.player_ammo += 5;
-					}
-					b"torches" => {
-						if let Some(ref mut player_torches) = self.board_simulator.world_header.player_torches {
-							*player_torches += 5;
-						}
-					}
-					b"gems" => {
-						self.board_simulator.world_header.player_gems += 5;
-					}
-					b"health" => {
-						self.board_simulator.world_header.player_health += 50;
-					}
-					b"zap" => {
-						let player_pos = self.board_simulator.get_player_location();
-						let mut report = ApplyActionResultReport::new();
-						let mut zap_at_offset = |off_x, off_y| {
-							let action = Action::SetTile{
-								x: player_pos.0 + off_x,
-								y: player_pos.1 + off_y,
-								tile: BoardTile {
-									element_id: ElementType::Empty as u8,
-									colour: 0,
-								},
-								status_element: None,
-							};
-							self.board_simulator.apply_action(player_pos.0 + off_x, player_pos.1 + off_y, action, 0, None, &mut self.accumulated_data, &mut report);
-						};
-						zap_at_offset(-1, 0);
-						zap_at_offset(1, 0);
-						zap_at_offset(0, -1);
-						zap_at_offset(0, 1);
-					}
-					b"dark" => {
-						self.board_simulator.board_meta_data.is_dark = true;
-					}
-					b"-dark" => {
-						self.board_simulator.board_meta_data.is_dark = false;
-					}
-					_ => {
-						self.caption_state = Some(CaptionState::new(DosString::from_slice(b"Unknown debug command")));
+				CutsceneCommand::MoveCamera{x, y} => {
+					let (player_x, player_y) = self.board_simulator.get_player_location();
+					let is_empty = self.board_simulator.get_tile(x, y).is_some_and(|tile| tile.element_id == ElementType::Empty as u8);
+					if is_empty {
+						self.board_simulator.move_tile(player_x, player_y, x, y);
 					}
 				}
-				// TODO: Play a note.
-			}
-			BoardMessage::LinkClicked(link_label) => {
-				// TODO: If link_label starts with "-", then treat it as a file name to load.
-				self.clicked_link_label = Some(link_label);
-			}
-			BoardMessage::PauseGame => {
-				if !self.is_end_of_game() {
-					self.is_paused = true;
+				CutsceneCommand::PlaySound(..) => {
+					let sound_entries = command.sound_entries().unwrap_or_default();
+					board_messages.push(BoardMessage::PlaySoundArray(sound_entries, SoundPriority::Music));
+				}
+				CutsceneCommand::OpenScroll{title, content_lines} => {
+					self.message_log.push(title.clone(), content_lines.clone());
+					self.open_scroll(title, content_lines);
+					// Carry on after the scroll is closed.
+					break;
+				}
+				CutsceneCommand::Wait(..) => {
+					// Waits are handled by `CutsceneState::next_command`.
 				}
-			}
-			BoardMessage::PlayGame => {
-				self.set_in_title_screen(false);
-				extra_accumulated_data.board_messages.push(BoardMessage::ClearPlayingSound);
-			}
-			BoardMessage::OpenEndGameConfirmation => {
-				self.side_bar.open_yes_no_input(side_bar::YesNoMode::EndGame);
-			}
-			BoardMessage::OpenQuitConfirmation => {
-				self.side_bar.open_yes_no_input(side_bar::YesNoMode::Quit);
-			}
-			BoardMessage::ReturnToTitleScreen => {
-				self.set_in_title_screen(true);
-			}
-			| BoardMessage::Quit
-			| BoardMessage::OpenWorldSelection
-			| BoardMessage::OpenSaveSelection
-			| BoardMessage::OpenWorld{..}
-			| BoardMessage::EnterPressedInScroll{..} => {
-				// Do nothing. The frontend should handle these itself.
 			}
 		}
+	}
 
-		extra_accumulated_data.board_messages
+	/// Open the world browser with the given `title`, listing the given `entries`.
+	pub fn open_world_browser(&mut self, title: DosString, entries: Vec<WorldBrowserEntry>) {
+		self.world_browser_state = Some(WorldBrowserState::new(title, entries));
+		#[cfg(feature = "file-io")]
+		{
+			self.file_browser = None;
+		}
 	}
 
-	/// Open a scroll with the given `title` and `content_lines`.
-	pub fn open_scroll(&mut self, title: DosString, content_lines: Vec<DosString>) {
-		self.scroll_state = Some(ScrollState::new_title_content(title, content_lines));
+	/// Open the world browser listing the entries of `file_browser`. Picking a directory moves
+	/// into it, and picking a file sends a `FileBrowserFileSelected` board message for the
+	/// frontend to load it.
+	#[cfg(feature = "file-io")]
+	pub fn open_file_browser(&mut self, file_browser: FileBrowser) {
+		self.world_browser_state = Some(WorldBrowserState::new(file_browser.title.clone(), file_browser.browser_entries()));
+		self.file_browser = Some(file_browser);
 	}
 
-	/// Copy the data out of the `BoardSimulator` back into the `World` instance in `RuzztEngine`.
-	pub fn sync_world(&mut self) {
+	/// Copy the data out of the `BoardSimulator` back into the `World` instance in `RuzztEngine`,
+	/// and get the whole world. Boards that were compacted (see `set_compact_idle_boards`) are
+	/// expanded, and they stay that way until the engine next steps or handles a board message.
+	pub fn sync_world(&mut self) -> &zzt_file_format::World {
+		self.expand_all_boards();
 		let current_board_index = self.board_simulator.world_header.player_board;
 		self.board_simulator.save_board(&mut self.world.boards[current_board_index as usize]);
 		self.world.world_header = self.board_simulator.world_header.clone();
+		&self.world
+	}
+
+	/// The same as `sync_world`, but the world can be changed. Changes to the board that is loaded
+	/// in the simulator only take effect once it is loaded again with `BoardSimulator::load_board`.
+	pub fn sync_world_mut(&mut self) -> &mut zzt_file_format::World {
+		self.sync_world();
+		&mut self.world
+	}
+
+	/// Get a copy of the whole world as `sync_world` would give it, without changing the engine.
+	pub fn world_copy(&self) -> zzt_file_format::World {
+		let mut world = self.world.clone();
+		for (board_index, compacted_tiles) in &self.compacted_tiles {
+			world.boards[*board_index].tiles = compacted_tiles.decode();
+		}
+		let current_board_index = self.board_simulator.world_header.player_board;
+		self.board_simulator.save_board(&mut world.boards[current_board_index as usize]);
+		world.world_header = self.board_simulator.world_header.clone();
+		world
+	}
+
+	/// Get the number of boards in the world.
+	pub fn board_count(&self) -> usize {
+		self.world.boards.len()
 	}
 
 	/// Returns true if the given `x`/`y` position on the board is currently not lit (so it's on a
 	/// dark board, and is not lit by a torch).
 	fn is_position_dark(&self, x: i16, y: i16) -> bool {
+		if let Some(ref light_map) = self.light_map {
+			return self.board_simulator.world_header.torch_cycles.is_some() && !light_map.is_lit(x, y);
+		}
+
 		if let Some(torch_cycles) = self.board_simulator.world_header.torch_cycles {
 			if torch_cycles > 0 {
 				let (player_x, player_y) = self.board_simulator.get_player_location();
@@ -462,7 +2128,18 @@ impl RuzztEngine {
 		let mut background = ConsoleColour::Black;
 		let mut foreground = ConsoleColour::Black;
 
-		if let Some(ty) = ElementType::from_u8(tile.element_id) {
+		if let Some(char_code) = self.board_simulator.ruleset.element_char_code(tile.element_id) {
+			let colour = Colour::from_u8(tile.colour);
+			return ConsoleChar {
+				char_code,
+				background: colour.console_background(),
+				foreground: colour.foreground,
+				extended_colours: None,
+			};
+		}
+		let element_type_opt = self.board_simulator.ruleset.element_type(tile.element_id);
+
+		if let Some(ty) = element_type_opt {
 			use self::ElementType::*;
 
 			let mut override_colours = false;
@@ -473,6 +2150,7 @@ impl RuzztEngine {
 						char_code: 0xb0,
 						background: ConsoleColour::Black,
 						foreground: ConsoleColour::White,
+						extended_colours: None,
 					};
 				}
 			}
@@ -483,6 +2161,14 @@ impl RuzztEngine {
 					background = ConsoleColour::Black;
 					foreground = ConsoleColour::Black;
 					override_colours = true;
+
+					if self.board_simulator.board_meta_data.is_dark {
+						let light_colour = self.light_map.as_ref().and_then(|light_map| light_map.light_colour(tile_x as i16, tile_y as i16));
+						if let Some(light_colour) = light_colour {
+							// Show the colour of the light on the floor.
+							return ConsoleChar::new(0xfa, ConsoleColour::Black, light_colour);
+						}
+					}
 				}
 				TextBlue | TextGreen | TextCyan | TextRed | TextPurple | TextBrown | TextBlack => {
 					char_code = tile.colour;
@@ -567,8 +2253,9 @@ impl RuzztEngine {
 			}
 
 			if !override_colours {
-				background = ConsoleColour::from_u8(tile.colour >> 4).unwrap();
-				foreground = ConsoleColour::from_u8(tile.colour & 0b1111).unwrap();
+				let colour = Colour::from_u8(tile.colour);
+				background = colour.console_background();
+				foreground = colour.foreground;
 			}
 		} else {
 			background = ConsoleColour::Black;
@@ -580,12 +2267,14 @@ impl RuzztEngine {
 			char_code,
 			background,
 			foreground,
+			extended_colours: None,
 		}
 	}
 
 	/// Go through all the `StatusElements` and update their appearance in the console if they
 	/// require special rendering. For example, Object elements use their `param1` to determine the
-	/// console character to use.
+	/// console character to use. Each element's `Behaviour::status_char_code` decides this, except
+	/// for the player, whose look depends on whether the game is paused.
 	fn render_status_element_tiles(&mut self) {
 		// Note that the game seems to draw the even status elements first, then the odd ones (or
 		// maybe the other way around?). This likely doesn't affect the excecution order of objects.
@@ -614,133 +2303,26 @@ impl RuzztEngine {
 						}
 					}
 
-					match ty {
-						ElementType::Bomb => {
-							if status_element.param1 > 1 {
-								self.console_state.get_char_mut(screen_x, screen_y).char_code = b'0' + status_element.param1;
-							}
-						}
-						ElementType::Clockwise => {
-							let frame_index = (self.global_cycle % (4 * status_element.cycle) as usize) / status_element.cycle as usize;
-
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match frame_index {
-								0 => 0x2f,
-								1 => 0xc4,
-								2 => 0x5c,
-								3 => 0xb3,
-								_ => 0,
-							};
-						}
-						ElementType::Counter => {
-							let frame_index = (self.global_cycle % (4 * status_element.cycle) as usize) / status_element.cycle as usize;
-
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match frame_index {
-								0 => 0x5c,
-								1 => 0xc4,
-								2 => 0x2f,
-								3 => 0xb3,
-								_ => 0,
-							};
-						}
-						ElementType::Duplicator => {
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match status_element.param1 {
-								0 => 0xfa,
-								1 => 0xf9,
-								2 => 0xf8,
-								3 => 0x6f,
-								4 => 0x4f,
-								// TODO: Check this with ZZT:
-								_ => 0,
-							};
-						}
-						ElementType::Object => {
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = status_element.param1;
-						}
-						ElementType::Player => {
-							let mut screen_char = self.console_state.get_char_mut(screen_x, screen_y);
-							if self.is_paused {
-								if is_first_status {
-									screen_char.char_code = 0;
-									screen_char.background = ConsoleColour::Black;
-									screen_char.foreground = ConsoleColour::Black;
-								} else {
-									screen_char.char_code = 0x02;
-									screen_char.background = ConsoleColour::Blue;
-									screen_char.foreground = ConsoleColour::White;
-								}
+					if ty == ElementType::Player {
+						let mut screen_char = self.console_state.get_char_mut(screen_x, screen_y);
+						if self.is_paused {
+							if is_first_status {
+								screen_char.char_code = 0;
+								screen_char.background = ConsoleColour::Black;
+								screen_char.foreground = ConsoleColour::Black;
+							} else {
+								screen_char.char_code = 0x02;
+								screen_char.background = ConsoleColour::Blue;
+								screen_char.foreground = ConsoleColour::White;
 							}
 						}
-						ElementType::Pusher => {
-							let facing_dir = match (status_element.step_x, status_element.step_y) {
-								(1, 0) => Direction::East,
-								(-1, 0) => Direction::West,
-								(0, -1) => Direction::North,
-								(0, 1) => Direction::South,
-								_ => Direction::South,
-							};
-
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match facing_dir {
-								Direction::East => 0x10,
-								Direction::West => 0x11,
-								Direction::North => 0x1e,
-								Direction::South => 0x1f,
-								Direction::Idle => 0x1f,
-							};
-						}
-						ElementType::SpinningGun => {
-							let frame_index = (self.global_cycle % (4 * status_element.cycle) as usize) / status_element.cycle as usize;
-
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match frame_index {
-								0 => 0x18,
-								1 => 0x1a,
-								2 => 0x19,
-								3 => 0x1b,
-								_ => 0,
-							};
-						}
-						ElementType::Star => {
-							//let frame_offset = (self.global_cycle + (status_element.param2 as usize)) % 2;
-							//let frame_index = ((self.global_cycle & !1) + frame_offset) % 4;
-							let frame_index = (self.global_cycle % (4 * status_element.cycle) as usize) / status_element.cycle as usize;
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match frame_index {
-								0 => 0x2f,
-								1 => 0xc4,
-								2 => 0x5c,
-								3 => 0xb3,
-								_ => 0,
-							};
+					} else {
+						if let Some(char_code) = self.board_simulator.behaviour_for_pos(x as i16, y as i16).status_char_code(status_element, self.global_cycle) {
+							self.console_state.get_char_mut(screen_x, screen_y).char_code = char_code;
 						}
-						ElementType::Transporter => {
-							// ZZT shows weird animations for this when the step_x/y is > 1.
-							let facing_dir = match (status_element.step_x, status_element.step_y) {
-								(1, 0) => Direction::East,
-								(-1, 0) => Direction::West,
-								(0, -1) => Direction::North,
-								(0, 1) => Direction::South,
-								_ => Direction::East,
-							};
-							let mut frame_index = (self.global_cycle % (4 * status_element.cycle) as usize) / status_element.cycle as usize;
-							if frame_index == 3 {
-								frame_index = 1;
-							}
-
-							self.console_state.get_char_mut(screen_x, screen_y).char_code = match (facing_dir, frame_index) {
-								(Direction::East, 0) => 0x3e,
-								(Direction::East, 1) => 0x29,
-								(Direction::East, 2) => 0xb3,
-								(Direction::West, 0) => 0x3c,
-								(Direction::West, 1) => 0x28,
-								(Direction::West, 2) => 0xb3,
-								(Direction::North, 0) => 0x7e,
-								(Direction::North, 1) => 0x5e,
-								(Direction::North, 2) => 0xc4,
-								(Direction::South, 0) => 0x5f,
-								(Direction::South, 1) => 0x76,
-								(Direction::South, 2) => 0xc4,
-								_ => 0,
-							};
+						if self.board_simulator.settings.extended_colours_enabled {
+							self.console_state.get_char_mut(screen_x, screen_y).extended_colours = status_element.extended_colours;
 						}
-						_ => {}
 					}
 				}
 			}
@@ -756,11 +2338,99 @@ impl RuzztEngine {
 		}
 	}
 
+	/// Draw the order the statuses will be processed in the next step over the board, for
+	/// `show_execution_order`. Statuses that are processed are shown as the last digit of their
+	/// position in the order (starting at 1), and the others are shown in dark grey.
+	fn render_execution_order(&mut self) {
+		let execution_order = self.board_simulator.status_execution_order(self.global_cycle);
+
+		for (status_index, status_element) in self.board_simulator.status_elements.iter().enumerate() {
+			let x = status_element.location_x as usize;
+			let y = status_element.location_y as usize;
+			if x < 1 || y < 1 || x > BOARD_WIDTH - 2 || y > BOARD_HEIGHT - 2 {
+				continue;
+			}
+
+			let screen_char = self.console_state.get_char_mut(x - 1, y - 1);
+			screen_char.extended_colours = None;
+			if let Some(order) = execution_order.iter().position(|&index| index == status_index) {
+				screen_char.char_code = b'0' + ((order + 1) % 10) as u8;
+				screen_char.background = if order < 10 { ConsoleColour::Red } else { ConsoleColour::Magenta };
+				screen_char.foreground = ConsoleColour::White;
+			} else {
+				screen_char.background = ConsoleColour::Black;
+				screen_char.foreground = ConsoleColour::DarkGray;
+			}
+		}
+	}
+
+	/// Update the value of every watch in `watches`. If a watch that breaks on change has a new
+	/// value, the game is paused (like pressing P) and a caption says what changed.
+	fn update_watches(&mut self, board_messages: &mut Vec<BoardMessage>) {
+		let mut changes = vec![];
+		for watch in &mut self.watches {
+			let value = watch.target.value(&self.board_simulator);
+			if value != watch.last_value && watch.break_on_change {
+				changes.push(format!("{}: {} -> {}", watch.target.description(), format_watch_value(watch.last_value), format_watch_value(value)));
+			}
+			watch.last_value = value;
+		}
+
+		if changes.is_empty() || self.in_title_screen || self.is_end_of_game() {
+			return;
+		}
+		self.is_paused = true;
+		for change in changes {
+			board_messages.push(BoardMessage::EngineLog(LogLevel::Info, format!("Watch changed: {}", change)));
+			self.queue_caption(DosString::from_str(&change));
+		}
+	}
+
+	/// Draw a line for each of the `watches` in the top left of the board.
+	fn render_watches(&mut self) {
+		for (y, watch) in self.watches.iter().enumerate().take(BOARD_HEIGHT - 2) {
+			let mut line = watch.overlay_line().into_bytes();
+			line.truncate(BOARD_WIDTH - 2);
+			self.console_state.draw_text_at(0, y, &line, ConsoleColour::Blue, ConsoleColour::White);
+		}
+	}
+
+	/// Get the information shown in the side bar, for frontends that draw their own side bar.
+	pub fn side_bar_model(&self) -> SideBarModel {
+		let mut side_bar_model = SideBarModel::new(&self.board_simulator.world_header, &self.board_simulator.board_meta_data, self.is_paused);
+		if let Some(side_bar_health) = self.board_simulator.side_bar_health {
+			side_bar_model.health = side_bar_health;
+		}
+		side_bar_model.shoot_lock = self.shoot_lock;
+		side_bar_model
+	}
+
+	/// True if the screen needs to be drawn again, because the console state has changed since
+	/// the front-end last took the changed characters (see `ConsoleState::is_dirty`). When this is
+	/// false, eg. while the game is paused between steps, drawing the screen can be skipped.
+	pub fn screen_dirty(&self) -> bool {
+		self.console_state.is_dirty()
+	}
+
 	/// Update the entire console state by drawing the board, side bar, scroll, caption, etc.
 	pub fn update_screen(&mut self) {
-		// TODO: The game gives the appearance of health being the value when #endgame was invoked
-		// because it doesn't redraw the side bar while the game is over.
-		self.side_bar.draw_side_bar(&self.board_simulator.world_header, &self.board_simulator.board_meta_data, self.is_paused, self.in_title_screen, &mut self.console_state, self.paused_cycle);
+		self.light_map = match self.lighting_options {
+			Some(ref lighting_options) if self.board_simulator.board_meta_data.is_dark => Some(LightMap::build(&self.board_simulator, lighting_options)),
+			_ => None,
+		};
+
+		let side_bar_model = self.side_bar_model();
+		self.board_simulator.ruleset.draw_side_bar(&self.side_bar, &side_bar_model, self.in_title_screen, &mut self.console_state, self.paused_cycle, self.global_cycle);
+		if self.in_title_screen {
+			self.side_bar.draw_title_game_speed(self.game_speed, &mut self.console_state);
+		}
+
+		if let Some(ref speedrun_timer) = self.speedrun_timer {
+			// The timer is drawn where text inputs appear, so it's hidden while typing.
+			if !self.in_title_screen && !self.side_bar.in_typing_mode() {
+				speedrun_timer.draw(&mut self.console_state);
+			}
+		}
 
 		for y in 0 .. BOARD_HEIGHT - 2 {
 			for x in 0 .. BOARD_WIDTH - 2 {
@@ -772,6 +2442,35 @@ impl RuzztEngine {
 
 		self.render_status_element_tiles();
 
+		if self.show_execution_order {
+			self.render_execution_order();
+		}
+
+		if self.display_options.modifies_board() {
+			let mut console_state = std::mem::replace(&mut self.console_state, ConsoleState::new());
+			let is_visible = |x: usize, y: usize| {
+				if self.board_simulator.board_meta_data.is_dark {
+					let tile = self.board_simulator.get_tile(x as i16 + 1, y as i16 + 1).unwrap();
+					let visible_in_dark = match ElementType::from_u8(tile.element_id) {
+						Some(ty) => type_visible_in_dark(ty),
+						None => false,
+					};
+					visible_in_dark || !self.is_position_dark(x as i16, y as i16)
+				} else {
+					true
+				}
+			};
+			self.display_options.apply(&self.board_simulator, &mut console_state, &is_visible);
+			self.console_state = console_state;
+		}
+
+		if let Some(minimap_options) = self.minimap_options {
+			let minimap = Minimap::from_console_state(&self.console_state, minimap_options.width, minimap_options.height);
+			minimap.draw_in_corner(minimap_options.corner, &mut self.console_state);
+		}
+
+		self.render_watches();
+
 		if let Some(ref caption_state) = self.caption_state {
 			caption_state.draw_caption(&mut self.console_state);
 		}
@@ -779,28 +2478,189 @@ impl RuzztEngine {
 		if let Some(ref scroll_state) = self.scroll_state {
 			scroll_state.draw_scroll(&mut self.console_state);
 		}
+
+		if let Some(ref pause_menu_state) = self.pause_menu_state {
+			pause_menu_state.draw_pause_menu(&mut self.console_state);
+		}
+
+		if let Some(ref world_browser_state) = self.world_browser_state {
+			world_browser_state.draw_world_browser(&mut self.console_state);
+		}
+
+		if let Some(ref text_editor_state) = self.text_editor_state {
+			text_editor_state.draw_text_editor(&mut self.console_state);
+		}
+
+		if let Some(ref char_picker_state) = self.char_picker_state {
+			char_picker_state.draw_char_picker(&mut self.console_state);
+		}
 	}
 
 	/// When `in_typing_mode()` returns true, this should be called instead of `step`.
 	/// This will add characters to text inputs.
 	/// Note that `event` is not the same as the `event` passed to `step`.
-	pub fn process_typing(&mut self, event: TypingEvent) -> Vec<BoardMessage> {
+	pub fn process_typing(&mut self, mut event: TypingEvent) -> Vec<BoardMessage> {
 		self.paused_cycle += 1;
-		let board_messages = self.side_bar.process_typing(event, &self.board_simulator.world_header);
+
+		if let Some(ref mut char_picker_state) = self.char_picker_state {
+			match char_picker_state.process_typing(event) {
+				CharPickerAction::None => event = TypingEvent::None,
+				CharPickerAction::Picked(char_code) => {
+					self.char_picker_state = None;
+					event = TypingEvent::Char(char_code);
+				}
+				CharPickerAction::Close => {
+					self.char_picker_state = None;
+					event = TypingEvent::None;
+				}
+			}
+		} else if event == TypingEvent::OpenCharPicker {
+			self.char_picker_state = Some(CharPickerState::new());
+			event = TypingEvent::None;
+		}
+
+		let mut board_messages = match self.text_editor_state {
+			Some(ref mut text_editor_state) => text_editor_state.process_typing(event),
+			None => self.side_bar.process_typing(event),
+		};
+		if let Some(ref mut shareware_flavour) = self.shareware_flavour {
+			let is_quit = |board_message: &BoardMessage| matches!(board_message, BoardMessage::Quit);
+			if board_messages.iter().any(is_quit) && shareware_flavour.hold_back_quit() {
+				board_messages.retain(|board_message| !is_quit(board_message));
+				self.open_scroll(DosString::from_slice(b"Order form"), shareware::order_form_lines());
+			}
+		}
+		self.hold_back_for_unsaved_changes(&mut board_messages);
 		self.update_screen();
 		board_messages
 	}
 
+	/// Get the event that moves the player one step towards `pos`, or shoots towards it if `shoot`
+	/// is true. The player moves along the axis that is furthest from
+	/// the position first.
+	fn click_event_towards(&self, pos: SimPos, shoot: bool) -> Event {
+		let (player_x, player_y) = self.board_simulator.get_player_location();
+		let diff_x = pos.x - player_x;
+		let diff_y = pos.y - player_y;
+
+		let direction = if diff_x == 0 && diff_y == 0 {
+			Direction::Idle
+		} else if diff_x.abs() >= diff_y.abs() {
+			Direction::from_offset(diff_x.signum(), 0)
+		} else {
+			Direction::from_offset(0, diff_y.signum())
+		};
+
+		match (direction, shoot) {
+			(Direction::West, false) => Event::Left,
+			(Direction::East, false) => Event::Right,
+			(Direction::North, false) => Event::Up,
+			(Direction::South, false) => Event::Down,
+			(Direction::West, true) => Event::ShootLeft,
+			(Direction::East, true) => Event::ShootRight,
+			(Direction::North, true) => Event::ShootUp,
+			(Direction::South, true) => Event::ShootDown,
+			(Direction::Idle, _) => Event::None,
+		}
+	}
+
+	/// Simulate a whole game cycle, for front-ends that don't show the game between the parts of a
+	/// step (eg. to run the game as fast as possible). `event` is given to `step`, and then `step` is
+	/// called with no event for as long as the cycle is paused part way through, which happens
+	/// whenever a board message is sent, up to `MAX_PARTIAL_STEPS` more times. The board messages
+	/// from each call to `step` are given to `handle_board_messages` before the cycle carries on, so
+	/// it can process them (see `process_board_message`).
+	pub fn step_whole_cycle(&mut self, event: Event, global_time_passed_seconds: f64, mut handle_board_messages: impl FnMut(&mut RuzztEngine, Vec<BoardMessage>)) {
+		let board_messages = self.step(event, global_time_passed_seconds);
+		handle_board_messages(self, board_messages);
+
+		let mut partial_steps = 0;
+		while self.is_part_way_though_step() && partial_steps < MAX_PARTIAL_STEPS {
+			let board_messages = self.step(Event::None, global_time_passed_seconds);
+			handle_board_messages(self, board_messages);
+			partial_steps += 1;
+		}
+	}
+
 	/// Simulate a single game step. A RUZZT front-end will call this over and over, redrawing the
 	/// screen between each call. The latest controller input should be passed as `event`.
 	/// `global_time_passed_seconds` is the wall-clock time passed since the game started,
-	/// regardless of how fast the game is stepping.
+	/// regardless of how fast the game is stepping. It is only used for board time limits, so for
+	/// a game to play out the same way every time (with `seed_rng`), it should be calculated from
+	/// the number of steps instead of read from a clock.
 	pub fn step(&mut self, event: Event, global_time_passed_seconds: f64) -> Vec<BoardMessage> {
 		let was_end_of_game = self.is_end_of_game();
+		let was_simulating_fast = self.should_simulate_fast();
+		let step_cycle = self.global_cycle;
+
+		let event = if let Event::Click{pos, shoot} = event {
+			self.click_event_towards(pos, shoot)
+		} else {
+			event
+		};
+
+		let event = self.attract_mode_event(event);
+
+		let event = if !self.in_title_screen && self.process_savestate_event(event) {
+			Event::None
+		} else {
+			event
+		};
 
 		let mut board_messages = std::mem::replace(&mut self.accumulated_data.board_messages, vec![]);
 
-		if self.is_paused {
+		let event = if event == Event::Screenshot {
+			board_messages.push(BoardMessage::SaveScreenshot{path: next_screenshot_path(&self.screenshot_directory, self.screenshot_format)});
+			Event::None
+		} else {
+			event
+		};
+
+		let event = if event == Event::ToggleExecutionOrder {
+			self.show_execution_order = !self.show_execution_order;
+			Event::None
+		} else {
+			event
+		};
+
+		let event = if event == Event::ToggleShootLock {
+			self.shoot_lock = !self.shoot_lock;
+			Event::None
+		} else {
+			event
+		};
+
+		// Enter is only handled by scrolls, the world browser and the pause menu. Otherwise it just
+		// dismisses the caption (if there is one, showing the next queued caption), and is never
+		// passed on to the board, the same as DOS ZZT where it does nothing while playing.
+		let enter_has_window = self.scroll_state.is_some() || self.world_browser_state.is_some() || self.pause_menu_state.is_some();
+		let event = if event == Event::Enter && !enter_has_window {
+			self.next_caption();
+			Event::None
+		} else {
+			event
+		};
+
+		// Open scrolls still get input during a cutscene, so they can be closed.
+		let event = if self.cutscene_state.is_active() && self.scroll_state.is_none() {
+			if event == Event::Escape {
+				self.cutscene_state.skip();
+			} else {
+				self.run_cutscene_commands(&mut board_messages);
+			}
+			Event::None
+		} else {
+			event
+		};
+
+		if let Some(ref mut pause_menu_state) = self.pause_menu_state {
+			// Nothing else happens while the pause menu is open, not even the player blinking.
+			board_messages.extend(pause_menu_state.step(event));
+		} else if self.is_paused {
+			if event == Event::Escape && !self.in_title_screen && !self.is_end_of_game() {
+				board_messages.push(BoardMessage::OpenPauseMenu);
+			}
+
 			let move_dir = match event {
 				Event::Left => Direction::West,
 				Event::Right => Direction::East,
@@ -809,25 +2669,29 @@ impl RuzztEngine {
 				_ => Direction::Idle,
 			};
 
-			if move_dir != Direction::Idle {
+			let player_index_opt = self.board_simulator.player_status_index().filter(|_| move_dir != Direction::Idle);
+			if let Some(player_index) = player_index_opt {
 				let (off_x, off_y) = move_dir.to_offset();
-				let player_status = &self.board_simulator.status_elements[0];
+				let player_status = &self.board_simulator.status_elements[player_index];
 				let player_x = player_status.location_x as i16;
 				let player_y = player_status.location_y as i16;
 				let blocked = self.board_simulator.push_tile(player_x + off_x, player_y + off_y, off_x, off_y, true, false, 0, None, &mut self.accumulated_data);
 
 				if blocked == BlockedStatus::NotBlocked {
-					let player_status = &mut self.board_simulator.status_elements[0];
+					let player_status = &mut self.board_simulator.status_elements[player_index];
 					let under_element_id = player_status.under_element_id;
 					let under_colour = player_status.under_colour;
 					player_status.location_x = (player_x + off_x) as u8;
 					player_status.location_y = (player_y + off_y) as u8;
-					if let Some(old_tile) = self.board_simulator.get_tile_mut(player_x, player_y) {
+					if let Some(old_tile) = self.board_simulator.get_tile(player_x, player_y) {
 						if old_tile.element_id == ElementType::Player as u8 {
-							old_tile.element_id = under_element_id;
-							old_tile.colour = under_colour;
+							self.board_simulator.set_tile(player_x, player_y, BoardTile {
+								element_id: under_element_id,
+								colour: under_colour,
+							});
 						}
 					}
+					self.board_simulator.player_on_passage = false;
 					self.is_paused = false;
 				}
 			}
@@ -837,18 +2701,25 @@ impl RuzztEngine {
 			let mut caption_is_finished = false;
 			if let Some(ref mut caption_state) = self.caption_state {
 				caption_state.time_left -= 1;
-				if caption_state.time_left == 0 {
+				if caption_state.time_left <= 0 {
 					caption_is_finished = true;
 				}
 			}
 
 			if caption_is_finished {
-				self.caption_state = None;
+				self.next_caption();
 			}
 
-			if let Some(ref mut scroll_state) = self.scroll_state {
+			if let Some(ref mut world_browser_state) = self.world_browser_state {
+				board_messages.extend(world_browser_state.step(event));
+			} else if let Some(ref mut scroll_state) = self.scroll_state {
 				board_messages.extend(scroll_state.step(event));
 			} else {
+				let show_order_form = self.in_title_screen && self.shareware_flavour.as_mut().is_some_and(|shareware_flavour| shareware_flavour.title_step(event));
+				if show_order_form {
+					self.open_scroll(DosString::from_slice(b"Order form"), shareware::order_form_lines());
+				}
+
 				// Force the player status to point at a player tile.
 				let (player_x, player_y) = self.board_simulator.get_player_location();
 				if self.in_title_screen {
@@ -863,7 +2734,17 @@ impl RuzztEngine {
 					});
 				}
 
+				// Moving while paused still unpauses the game, so shoot lock only applies here.
+				let event = if self.shoot_lock && !self.in_title_screen {
+					event.to_shoot_event()
+				} else {
+					event
+				};
+
 				let current_global_cycle = self.global_cycle;
+				if let Some(ref mut message_timeline) = *self.board_simulator.message_timeline.borrow_mut() {
+					message_timeline.current_cycle = current_global_cycle;
+				}
 				let board_simulator_step_state = self.board_simulator_step_state.get_or_insert_with(|| BoardSimulatorStepState::new(event, current_global_cycle));
 
 				let mut process_same_status = false;
@@ -893,28 +2774,18 @@ impl RuzztEngine {
 						board_simulator_step_state.accumulated_data.should_check_time_elapsed = false;
 
 						let new_time_passed_ticks = (global_time_passed_seconds * 100.) as i16 % 6000;
-						let mut diff = new_time_passed_ticks - self.board_simulator.world_header.time_passed_ticks;
+						let mut diff = new_time_passed_ticks.wrapping_sub(self.board_simulator.world_header.time_passed_ticks);
 						if diff < 0 {
 							diff += 6000;
 						}
 
-						if diff >= 100 {
+						if self.resync_time_passed_ticks {
+							self.resync_time_passed_ticks = false;
+							self.board_simulator.world_header.time_passed_ticks = new_time_passed_ticks;
+						} else if diff >= 100 {
 							// At least one second has passed.
-							self.board_simulator.world_header.time_passed += 1;
 							self.board_simulator.world_header.time_passed_ticks = new_time_passed_ticks;
-
-							if self.board_simulator.board_meta_data.time_limit > 0 {
-								let time_left = self.board_simulator.board_meta_data.time_limit - self.board_simulator.world_header.time_passed;
-
-								if time_left == 10 {
-									board_messages.push(BoardMessage::OpenScroll{title: DosString::new(), content_lines: vec![DosString::from_slice(b"Running out of time!")]});
-								}
-
-								if time_left < 0 {
-									self.board_simulator.world_header.player_health = (self.board_simulator.world_header.player_health - 10).max(0);
-									self.board_simulator.restart_player_on_board(&mut board_messages);
-								}
-							}
+							self.board_simulator.on_time_limit_second_passed(current_global_cycle, &mut board_simulator_step_state.accumulated_data);
 						}
 					}
 				}
@@ -922,6 +2793,7 @@ impl RuzztEngine {
 				board_messages.extend(std::mem::replace(&mut board_simulator_step_state.accumulated_data.board_messages, vec![]));
 
 				if is_done {
+					self.board_simulator.record_profile(|profile| profile.step_count += 1);
 					self.board_simulator_step_state = None;
 					// Only increment if the whole step is complete, not when it pauses half way through
 					// to open a scroll for example.
@@ -935,8 +2807,11 @@ impl RuzztEngine {
 		//println!("{} - {}", self.board_simulator.world_header.player_board, self.world.boards[self.board_simulator.world_header.player_board as usize].meta_data.board_name.to_string(true));
 
 		if self.is_end_of_game() {
+			// DOS ZZT stops queueing sounds once the game is over, so eg. the player being hurt
+			// again can't be heard.
+			board_messages.retain(|board_message| !matches!(board_message, BoardMessage::PlaySoundArray(..)));
 			if !was_end_of_game {
-				board_messages.push(BoardMessage::PlaySoundArray(process_notes_string(b"s.-cd#g+c-ga#+dgfg#+cf---hc"), SoundPriority::Level(5)));
+				board_messages.push(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"s.-cd#g+c-ga#+dgfg#+cf---hc"), SoundPriority::Level(5)));
 			}
 
 			if self.global_cycle % 7 == 0 {
@@ -946,6 +2821,221 @@ impl RuzztEngine {
 			self.board_should_simulate_fast = true;
 		}
 
+		self.notify_observers();
+		self.update_watches(&mut board_messages);
+
+		if self.stuck_watchdog_enabled {
+			let is_stuck = !self.in_title_screen && !self.is_playing_attract_demo() && !self.is_end_of_game() && stuck_detector::player_appears_stuck(&self.board_simulator);
+			if is_stuck && !self.player_was_stuck {
+				board_messages.push(BoardMessage::PlayerAppearsStuck);
+			}
+			self.player_was_stuck = is_stuck;
+		}
+
+		let playing = !self.in_title_screen && !self.is_playing_attract_demo() && !self.is_paused;
+		if let Some(ref mut speedrun_timer) = self.speedrun_timer {
+			speedrun_timer.update(&self.board_simulator.world_header, playing, self.global_cycle, global_time_passed_seconds);
+		}
+
+		if was_simulating_fast {
+			self.apply_fast_simulation_sound_policy(step_cycle, &mut board_messages);
+		}
+
+		self.update_ambient_music(&mut board_messages);
+		self.update_low_resource_warnings(&mut board_messages);
+		self.update_autosave(global_time_passed_seconds, &mut board_messages);
+		self.update_step_outcome(step_cycle);
+		// Saving the game or a savestate expands every board.
+		self.compact_boards();
+
 		board_messages
 	}
+
+	/// True while the `attract_mode` demo is being played instead of the title screen.
+	pub fn is_playing_attract_demo(&self) -> bool {
+		self.attract_mode.as_ref().is_some_and(|attract_mode| attract_mode.is_playing())
+	}
+
+	/// Start or stop the `attract_mode` demo if it is time to, and get the event to use for the step
+	/// instead of `event`. While the demo is playing, the player's input is replaced with the demo's,
+	/// and scrolls are closed as soon as they open, like when the demo was recorded.
+	fn attract_mode_event(&mut self, event: Event) -> Event {
+		let is_playing = match self.attract_mode {
+			Some(ref attract_mode) => attract_mode.is_playing(),
+			None => return event,
+		};
+
+		let action = if is_playing {
+			if event == Event::None && self.scroll_state.is_some() {
+				return Event::Escape;
+			}
+			if event == Event::None && self.is_part_way_though_step() {
+				return Event::None;
+			}
+			self.attract_mode.as_mut().map(|attract_mode| attract_mode.demo_step(event))
+		} else {
+			let title_is_idle = self.in_title_screen && self.scroll_state.is_none() && self.world_browser_state.is_none() && self.pause_menu_state.is_none();
+			self.attract_mode.as_mut().filter(|_| title_is_idle).map(|attract_mode| attract_mode.title_step(event))
+		};
+
+		match action {
+			Some(AttractAction::StartDemo) => {
+				self.start_attract_demo();
+				Event::None
+			}
+			Some(AttractAction::StopDemo) => {
+				self.stop_attract_demo();
+				Event::None
+			}
+			Some(AttractAction::Continue(event)) => event,
+			None => event,
+		}
+	}
+
+	/// Leave the title screen and start playing the `attract_mode` demo, the same as pressing P.
+	fn start_attract_demo(&mut self) {
+		// The title screen board is never saved back to the world, so the world is the same as when
+		// it was loaded apart from the compacted boards.
+		self.expand_all_boards();
+		let title_world = self.world.clone();
+		if let Some(ref mut attract_mode) = self.attract_mode {
+			attract_mode.start(title_world);
+		}
+		self.set_in_title_screen(false);
+		self.accumulated_data.board_messages.push(BoardMessage::ClearPlayingSound);
+	}
+
+	/// Stop playing the `attract_mode` demo, and go back to the title screen of the world as it was
+	/// before the demo started.
+	fn stop_attract_demo(&mut self) {
+		let title_world = match self.attract_mode.as_mut().and_then(|attract_mode| attract_mode.stop()) {
+			Some(title_world) => title_world,
+			None => return,
+		};
+
+		// Loading a world forgets its ambient music and high scores, but it's the same world.
+		let board_ambient_music = std::mem::take(&mut self.board_ambient_music);
+		let high_scores = std::mem::take(&mut self.high_scores);
+		self.in_title_screen = true;
+		if let Err(err) = self.load_world(title_world, None) {
+			self.accumulated_data.board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, err));
+		}
+		self.board_ambient_music = board_ambient_music;
+		self.high_scores = high_scores;
+		self.scroll_state = None;
+		self.caption_state = None;
+		self.accumulated_data.board_messages.push(BoardMessage::ClearPlayingSound);
+	}
+
+	/// Get a summary of what happened in the last call to `step`.
+	pub fn last_step_outcome(&self) -> &StepOutcome {
+		&self.last_step_outcome
+	}
+
+	/// Work out `last_step_outcome` for a step that started in `step_cycle`.
+	fn update_step_outcome(&mut self, step_cycle: usize) {
+		let baseline = StepOutcomeBaseline::from_board_simulator(&self.board_simulator);
+		let board_changed = baseline.board_index != self.step_outcome_baseline.board_index;
+		self.last_step_outcome = StepOutcome {
+			completed_full_step: self.global_cycle != step_cycle,
+			global_cycle: self.global_cycle,
+			board_changed,
+			player_moved: board_changed || baseline.player_location != self.step_outcome_baseline.player_location,
+			player_hurt: baseline.health < self.step_outcome_baseline.health,
+			actions_applied: baseline.actions_applied - self.step_outcome_baseline.actions_applied,
+		};
+		self.step_outcome_baseline = baseline;
+	}
+
+	/// Write the autosave if it is due. The game is only autosaved between complete steps while
+	/// it is being played, not in the title screen or after the player has died. If it can't be
+	/// written, an `EngineLog` warning is added to `board_messages`.
+	#[cfg(feature = "file-io")]
+	fn update_autosave(&mut self, global_time_passed_seconds: f64, board_messages: &mut Vec<BoardMessage>) {
+		if self.in_title_screen || self.is_playing_attract_demo() || self.is_end_of_game() || self.board_simulator_step_state.is_some() {
+			return;
+		}
+
+		let is_due = match self.autosave {
+			Some(ref mut autosave) => autosave.is_due(global_time_passed_seconds),
+			None => false,
+		};
+		if is_due {
+			self.sync_world();
+			if let Some(ref mut autosave) = self.autosave {
+				if let Err(err) = autosave.write(&self.world, global_time_passed_seconds) {
+					board_messages.push(BoardMessage::EngineLog(LogLevel::Warning, format!("Couldn't autosave: {}", err)));
+				}
+			}
+		}
+	}
+
+	/// There is nowhere to autosave to without the `file-io` feature.
+	#[cfg(not(feature = "file-io"))]
+	fn update_autosave(&mut self, _global_time_passed_seconds: f64, _board_messages: &mut Vec<BoardMessage>) {}
+
+	/// If there is an autosave in the `autosave` directory (so the last run of RUZZT didn't exit
+	/// normally), ask the player in the side bar whether to load it. A `ResumeAutosave` board
+	/// message is sent if they say yes. Returns true if the question was asked.
+	#[cfg(feature = "file-io")]
+	pub fn offer_autosave_resume(&mut self) -> bool {
+		let has_autosave = self.autosave.as_ref().is_some_and(|autosave| Autosave::find(&autosave.directory).is_some());
+		if has_autosave {
+			self.side_bar.open_yes_no_input(side_bar::YesNoMode::ResumeAutosave);
+		}
+		has_autosave
+	}
+
+	/// Play a warning sound when the player's health gets low or their torch is about to burn out.
+	/// The side bar flashes for as long as this is the case (see `SideBar::draw_side_bar`).
+	fn update_low_resource_warnings(&mut self, board_messages: &mut Vec<BoardMessage>) {
+		let side_bar_model = self.side_bar_model();
+		let in_game = !self.in_title_screen && !self.is_end_of_game();
+
+		let health_is_low = in_game && side_bar_model.health_is_low();
+		if health_is_low && !self.health_was_low {
+			board_messages.push(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"s-c-c-c"), SoundPriority::Level(2)));
+		}
+		self.health_was_low = health_is_low;
+
+		let torch_is_running_out = in_game && side_bar_model.torch_is_running_out();
+		if torch_is_running_out && !self.torch_was_running_out {
+			board_messages.push(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"tc-c"), SoundPriority::Level(2)));
+		}
+		self.torch_was_running_out = torch_is_running_out;
+	}
+
+	/// Send a `SetAmbientMusic` message if the ambient music for the board being shown is different
+	/// to what was last sent.
+	fn update_ambient_music(&mut self, board_messages: &mut Vec<BoardMessage>) {
+		let board_index = if self.in_title_screen { 0 } else { self.board_simulator.world_header.player_board as u8 };
+		let ambient_music = self.board_ambient_music.get(&board_index);
+
+		if ambient_music != self.current_ambient_music.as_ref() {
+			let sound_entries = ambient_music.map(|music| process_notes_string_to_sound_entries(&music.data)).unwrap_or_default();
+			board_messages.push(BoardMessage::SetAmbientMusic(sound_entries));
+			self.current_ambient_music = ambient_music.cloned();
+		}
+	}
+
+	/// Remove the `PlaySoundArray` messages from `board_messages` that shouldn't be played according
+	/// to `fast_simulation_sound_policy`. `step_cycle` is the `global_cycle` the messages were sent
+	/// in.
+	fn apply_fast_simulation_sound_policy(&mut self, step_cycle: usize, board_messages: &mut Vec<BoardMessage>) {
+		let policy = self.fast_simulation_sound_policy;
+		if policy == FastSimulationSoundPolicy::PlayAll {
+			return;
+		}
+
+		let last_sound_cycle = &mut self.last_fast_simulation_sound_cycle;
+		board_messages.retain(|board_message| {
+			if let BoardMessage::PlaySoundArray(..) = board_message {
+				if policy == FastSimulationSoundPolicy::Drop || *last_sound_cycle == Some(step_cycle) {
+					return false;
+				}
+				*last_sound_cycle = Some(step_cycle);
+			}
+			true
+		});
+	}
 }
@@ -1,3 +1,12 @@
+use crate::board_simulator::SimPos;
+use crate::direction::Direction;
+
+use std::collections::VecDeque;
+
+/// The most key presses that `KeyRepeatState` keeps waiting for a step, like the keyboard buffer
+/// in DOS. Presses made while it is full are dropped.
+pub const MAX_PENDING_PRESSES: usize = 4;
+
 /// Represents a game controller input event.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Event {
@@ -15,8 +24,9 @@ pub enum Event {
 	PageUp,
 	/// The page-down key was pressed (used when scrolls are open).
 	PageDown,
-	/// The enter key was pressed (used in scrolls to either click a selected link, or close the
-	/// scroll).
+	/// The enter key was pressed. In scrolls this either clicks the selected link or closes the
+	/// scroll, and in the world browser and pause menu it picks the selected item. Anywhere else it
+	/// only dismisses the caption at the bottom of the screen, and the board never sees it.
 	Enter,
 	/// The escape key was pressed (quit the game, or close a scroll, etc.).
 	Escape,
@@ -38,6 +48,16 @@ pub enum Event {
 	SaveGame,
 	/// The key to open the debug command input box was pressed (usually ?).
 	Debug,
+	/// The key to toggle showing the order statuses will be processed in the next step was pressed
+	/// (usually F3). See `RuzztEngine::show_execution_order`.
+	ToggleExecutionOrder,
+	/// The key to toggle shoot lock was pressed (usually F6). While it is on, the movement keys
+	/// shoot instead of moving. See `RuzztEngine::shoot_lock`.
+	ToggleShootLock,
+	/// The key to open the map of visited boards was pressed (usually M, only applies in-game).
+	OpenWorldMap,
+	/// The key to open the log of recent messages was pressed (usually L, only applies in-game).
+	OpenMessageLog,
 	/// The key to open the world selection scroll was pressed (usually W, only applies in the title
 	/// screen).
 	OpenWorldSelection,
@@ -61,8 +81,299 @@ pub enum Event {
 	/// The key to use the game speed selector was pressed (usually S, only applies in the title
 	/// screen).
 	ChangeGameSpeed,
+	/// The key to configure the keyboard was pressed (usually K, only applies in the title
+	/// screen).
+	ChangeKeyboardConfig,
+	/// The key to quick save was pressed (usually F5, only applies in-game). The game is saved to the
+	/// selected savestate slot.
+	QuickSave,
+	/// The key to quick load was pressed (usually F9, only applies in-game). The game is restored
+	/// from the selected savestate slot.
+	QuickLoad,
+	/// A savestate slot was selected for quick saving and loading (usually with the number keys).
+	SelectSavestateSlot(u8),
+	/// The key to take a screenshot was pressed (usually F12). The engine sends a
+	/// `BoardMessage::SaveScreenshot` with the file to save it to.
+	Screenshot,
+	/// The mouse was clicked on the board at the given position. When playing, the player moves one
+	/// step towards the position, or shoots towards it if `shoot` is true.
+	Click {
+		pos: SimPos,
+		shoot: bool,
+	},
+}
+
+/// Where an `Event` does anything.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EventContext {
+	/// While playing a game.
+	Game,
+	/// In the title screen.
+	TitleScreen,
+	/// Both in the title screen and while playing.
+	Anywhere,
+}
+
+/// A description of an `Event` for the player, so front-ends can show help screens and key
+/// binding menus without keeping their own list of events (see `EVENT_HELP`).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EventHelp {
+	/// The event being described. For events that carry a value, this is one example of the event
+	/// (eg. `SelectSavestateSlot(0)`).
+	pub event: Event,
+	/// A short name for the event, eg. "Torch".
+	pub name: &'static str,
+	/// A sentence saying what the event does.
+	pub description: &'static str,
+	/// The key that RUZZT triggers the event with by default, eg. "T" or "Shift+Left".
+	pub default_key: &'static str,
+	/// Where the event does anything.
+	pub context: EventContext,
 }
 
+/// Every `Event` that the player can trigger with a key, with its help text, in the order they
+/// should be listed. `Event::None` and `Event::Click` are left out, because they aren't keys.
+pub const EVENT_HELP: &[EventHelp] = &[
+	EventHelp {
+		event: Event::Left,
+		name: "Move west",
+		description: "Moves the player west",
+		default_key: "Left",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::Right,
+		name: "Move east",
+		description: "Moves the player east",
+		default_key: "Right",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::Up,
+		name: "Move north",
+		description: "Moves the player north",
+		default_key: "Up",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::Down,
+		name: "Move south",
+		description: "Moves the player south",
+		default_key: "Down",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ShootFlow,
+		name: "Shoot",
+		description: "Shoots in the direction the player is moving",
+		default_key: "Space",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ShootLeft,
+		name: "Shoot west",
+		description: "Shoots west",
+		default_key: "Shift+Left",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ShootRight,
+		name: "Shoot east",
+		description: "Shoots east",
+		default_key: "Shift+Right",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ShootUp,
+		name: "Shoot north",
+		description: "Shoots north",
+		default_key: "Shift+Up",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ShootDown,
+		name: "Shoot south",
+		description: "Shoots south",
+		default_key: "Shift+Down",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::LightTorch,
+		name: "Torch",
+		description: "Lights a torch",
+		default_key: "T",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::PauseGame,
+		name: "Pause",
+		description: "Pauses the game until a movement key is pressed",
+		default_key: "P",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::SaveGame,
+		name: "Save game",
+		description: "Saves the game to a file",
+		default_key: "S",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::OpenWorldMap,
+		name: "World map",
+		description: "Shows a map of the boards that have been visited",
+		default_key: "M",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::OpenMessageLog,
+		name: "Message log",
+		description: "Shows the recent messages",
+		default_key: "L",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::QuickSave,
+		name: "Quick save",
+		description: "Saves the game to the selected savestate slot",
+		default_key: "F5",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::QuickLoad,
+		name: "Quick load",
+		description: "Restores the game from the selected savestate slot",
+		default_key: "F9",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::SelectSavestateSlot(0),
+		name: "Savestate slot",
+		description: "Selects the savestate slot to quick save to and load from",
+		default_key: "0-9",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ToggleShootLock,
+		name: "Shoot lock",
+		description: "Makes the movement keys shoot instead of moving the player",
+		default_key: "F6",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::ToggleExecutionOrder,
+		name: "Execution order",
+		description: "Shows the order the status elements will be processed in next",
+		default_key: "F3",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::Debug,
+		name: "Debug command",
+		description: "Opens the input for typing a debug command",
+		default_key: "?",
+		context: EventContext::Game,
+	},
+	EventHelp {
+		event: Event::OpenWorldSelection,
+		name: "World",
+		description: "Picks the world to play",
+		default_key: "W",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::PlayGame,
+		name: "Play",
+		description: "Starts playing the world",
+		default_key: "P",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::RestoreGame,
+		name: "Restore game",
+		description: "Picks a saved game to carry on playing",
+		default_key: "R",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::ChangeGameSpeed,
+		name: "Game speed",
+		description: "Changes how fast the game runs",
+		default_key: "S",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::ChangeKeyboardConfig,
+		name: "Keyboard",
+		description: "Changes how long keys are held before they repeat",
+		default_key: "K",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::OpenAbout,
+		name: "About",
+		description: "Shows information about RUZZT",
+		default_key: "A",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::OpenHighScores,
+		name: "High scores",
+		description: "Shows the high scores of the world",
+		default_key: "H",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::OpenEditor,
+		name: "Board editor",
+		description: "Opens the board editor",
+		default_key: "E",
+		context: EventContext::TitleScreen,
+	},
+	EventHelp {
+		event: Event::PageUp,
+		name: "Page up",
+		description: "Scrolls up a page in scrolls",
+		default_key: "Page Up",
+		context: EventContext::Anywhere,
+	},
+	EventHelp {
+		event: Event::PageDown,
+		name: "Page down",
+		description: "Scrolls down a page in scrolls",
+		default_key: "Page Down",
+		context: EventContext::Anywhere,
+	},
+	EventHelp {
+		event: Event::Enter,
+		name: "Enter",
+		description: "Picks the selected line in scrolls and menus, or dismisses the caption",
+		default_key: "Enter",
+		context: EventContext::Anywhere,
+	},
+	EventHelp {
+		event: Event::Escape,
+		name: "Escape",
+		description: "Closes the scroll or menu that is open, or opens the pause menu while playing",
+		default_key: "Escape",
+		context: EventContext::Anywhere,
+	},
+	EventHelp {
+		event: Event::Screenshot,
+		name: "Screenshot",
+		description: "Saves a screenshot of the screen",
+		default_key: "F12",
+		context: EventContext::Anywhere,
+	},
+	EventHelp {
+		event: Event::Quit,
+		name: "Quit",
+		description: "Quits the game, or RUZZT in the title screen",
+		default_key: "Q",
+		context: EventContext::Anywhere,
+	},
+];
+
 /// Represents a text input event.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TypingEvent {
@@ -72,8 +383,227 @@ pub enum TypingEvent {
 	Char(u8),
 	/// The Backspace key was pressed.
 	Backspace,
+	/// The left arrow key was pressed.
+	Left,
+	/// The right arrow key was pressed.
+	Right,
 	/// The Enter key was pressed.
 	Enter,
 	/// The Escape key was pressed.
 	Escape,
+	/// The up arrow key was pressed.
+	Up,
+	/// The down arrow key was pressed.
+	Down,
+	/// The page-up key was pressed.
+	PageUp,
+	/// The page-down key was pressed.
+	PageDown,
+	/// The Home key was pressed.
+	Home,
+	/// The End key was pressed.
+	End,
+	/// The Delete key was pressed.
+	Delete,
+	/// Open the character picker, to type a character that isn't on the keyboard (see
+	/// `CharPickerState`).
+	OpenCharPicker,
+}
+
+impl Event {
+	/// True for the events that should repeat while their key is held down (moving and shooting).
+	pub fn is_repeatable(self) -> bool {
+		self.held_direction().is_some()
+	}
+
+	/// Get the shooting event in the same direction as a moving event. Other events are returned
+	/// as they are.
+	pub fn to_shoot_event(self) -> Event {
+		match self {
+			Event::Left => Event::ShootLeft,
+			Event::Right => Event::ShootRight,
+			Event::Up => Event::ShootUp,
+			Event::Down => Event::ShootDown,
+			_ => self,
+		}
+	}
+
+	/// Get the help text for the event from `EVENT_HELP`, or None if it isn't triggered with a key.
+	/// Events that carry a value all have the same help.
+	pub fn help(self) -> Option<&'static EventHelp> {
+		EVENT_HELP.iter().find(|event_help| std::mem::discriminant(&event_help.event) == std::mem::discriminant(&self))
+	}
+
+	/// Get the direction associated with a moving or shooting event, regardless of whether it
+	/// moves or shoots. `ShootFlow` doesn't have a direction of its own, so it returns Idle.
+	fn held_direction(self) -> Option<Direction> {
+		match self {
+			Event::Left | Event::ShootLeft => Some(Direction::West),
+			Event::Right | Event::ShootRight => Some(Direction::East),
+			Event::Up | Event::ShootUp => Some(Direction::North),
+			Event::Down | Event::ShootDown => Some(Direction::South),
+			Event::ShootFlow => Some(Direction::Idle),
+			_ => None,
+		}
+	}
+}
+
+/// The movement and shooting keys that are held down, oldest first. Gamepads and some keyboards
+/// can hold several directions at once, so this decides which one the player follows, instead of
+/// it depending on the order the front-end happens to get the events in:
+/// - The direction pressed last wins, so holding a second direction (eg. a gamepad's diagonal)
+///   moves the player in that direction only, and letting go of it goes back to the first.
+/// - Opposite directions cancel each other out, so holding left and right doesn't move the player
+///   at all.
+#[derive(Clone, Debug, Default)]
+pub struct HeldDirections {
+	/// The repeatable events of the keys that are held down, with at most one for each direction.
+	held_events: Vec<Event>,
+}
+
+impl HeldDirections {
+	pub fn new() -> HeldDirections {
+		HeldDirections::default()
+	}
+
+	/// Call this when the key for the repeatable `event` is pressed down.
+	pub fn press(&mut self, event: Event) {
+		self.release(event);
+		self.held_events.push(event);
+	}
+
+	/// Call this when a key is released. Any held event with the same direction as `event` is
+	/// let go of (so releasing an arrow key stops shooting in that direction too).
+	pub fn release(&mut self, event: Event) {
+		self.held_events.retain(|held_event| held_event.held_direction() != event.held_direction());
+	}
+
+	/// Let go of every held key.
+	pub fn release_all(&mut self) {
+		self.held_events.clear();
+	}
+
+	/// True if the opposite direction of `event` is held, so the two cancel each other out.
+	pub fn is_cancelled(&self, event: Event) -> bool {
+		match event.held_direction() {
+			Some(direction) if direction != Direction::Idle => {
+				self.held_events.iter().any(|held_event| held_event.held_direction() == Some(direction.opposite()))
+			}
+			_ => false,
+		}
+	}
+
+	/// Get the event that the held keys add up to, or None if nothing is held or the last pressed
+	/// direction is cancelled out by its opposite.
+	pub fn resolve(&self) -> Option<Event> {
+		self.held_events.last().cloned().filter(|held_event| !self.is_cancelled(*held_event))
+	}
+}
+
+/// Turns key presses and releases into one `Event` per simulation step, repeating the held key at
+/// a fixed number of steps like the keyboard auto-repeat did in DOS. Because the repeating is
+/// counted in steps instead of wall-clock time, a recording of the events returned by
+/// `next_event` plays back exactly the same regardless of how fast the front-end is running.
+///
+/// Keys pressed faster than the steps run are queued and given out one per step, like DOS ZZT
+/// reading the keyboard buffer, so quickly tapping two directions moves the player twice instead
+/// of losing the first tap. When several directions are held at once, they are resolved with
+/// `HeldDirections`.
+#[derive(Clone, Debug)]
+pub struct KeyRepeatState {
+	/// The number of steps a key has to be held before it starts repeating.
+	pub repeat_delay: usize,
+	/// The number of steps between each repeated event once the key is repeating. If this is 0
+	/// then held keys never repeat.
+	pub repeat_interval: usize,
+	/// The events that were pressed but haven't been returned from `next_event` yet, oldest first.
+	pending_presses: VecDeque<Event>,
+	/// The movement and shooting keys that are currently held down.
+	held_directions: HeldDirections,
+	/// The event that `held_directions` resolved to when it last changed.
+	held_event: Option<Event>,
+	/// The number of steps since `held_event` changed.
+	held_steps: usize,
+}
+
+impl Default for KeyRepeatState {
+	fn default() -> KeyRepeatState {
+		KeyRepeatState::new()
+	}
+}
+
+impl KeyRepeatState {
+	/// Make a new `KeyRepeatState` with nothing held, that repeats keys on every step after they
+	/// have been held for 2 steps.
+	pub fn new() -> KeyRepeatState {
+		KeyRepeatState {
+			repeat_delay: 2,
+			repeat_interval: 1,
+			pending_presses: VecDeque::new(),
+			held_directions: HeldDirections::new(),
+			held_event: None,
+			held_steps: 0,
+		}
+	}
+
+	/// Call this when a key is first pressed down (not for the operating system's own repeated key
+	/// presses). The `event` will be returned from `next_event` after any other presses that are
+	/// still waiting, unless there are already `MAX_PENDING_PRESSES` of them. Pressing the
+	/// opposite direction of a key that is held down cancels out both presses instead.
+	pub fn press(&mut self, event: Event) {
+		let is_repeatable = event.is_repeatable();
+		if is_repeatable && self.held_directions.is_cancelled(event) {
+			let opposite_direction = event.held_direction().map(Direction::opposite);
+			self.pending_presses.retain(|pending_press| pending_press.held_direction() != opposite_direction);
+		} else if self.pending_presses.len() < MAX_PENDING_PRESSES {
+			self.pending_presses.push_back(event);
+		}
+
+		if is_repeatable {
+			self.held_directions.press(event);
+			self.update_held_event();
+		}
+	}
+
+	/// Call this when a key is released. If `event` has the same direction as a held key, that key
+	/// stops repeating (so releasing an arrow key stops shooting in that direction too), and any
+	/// other key that is still held takes over (see `HeldDirections`).
+	pub fn release(&mut self, event: Event) {
+		self.held_directions.release(event);
+		self.update_held_event();
+	}
+
+	/// Forget about any held or pending keys, eg. when the window loses focus.
+	pub fn release_all(&mut self) {
+		self.pending_presses.clear();
+		self.held_directions.release_all();
+		self.update_held_event();
+	}
+
+	/// Resolve the held keys again after they changed, and start counting the repeat delay again if
+	/// a different event is now held.
+	fn update_held_event(&mut self) {
+		let held_event = self.held_directions.resolve();
+		if held_event != self.held_event {
+			self.held_event = held_event;
+			self.held_steps = 0;
+		}
+	}
+
+	/// Get the event to pass to the next simulation step. This should be called exactly once per
+	/// step. When nothing is pressed this returns `Event::None`.
+	pub fn next_event(&mut self) -> Event {
+		if let Some(pending_press) = self.pending_presses.pop_front() {
+			return pending_press;
+		}
+
+		if let Some(held_event) = self.held_event {
+			self.held_steps += 1;
+			if self.repeat_interval > 0 && self.held_steps >= self.repeat_delay && (self.held_steps - self.repeat_delay) % self.repeat_interval == 0 {
+				return held_event;
+			}
+		}
+
+		Event::None
+	}
 }
@@ -0,0 +1,181 @@
+use crate::dos_files::{files_with_extension, load_world_metadata};
+use crate::world_browser::WorldBrowserEntry;
+
+use zzt_file_format::World;
+use zzt_file_format::dosstring::DosString;
+use zzt_file_format::padding::ParseOptions;
+
+use std::path::{Path, PathBuf};
+
+/// Get the description shown in the browser for the world file with the given name (without the
+/// extension), for the worlds that came with ZZT.
+fn world_file_description(file_stem: &[u8]) -> &'static [u8] {
+	match file_stem {
+		b"CAVES" => b"The Caves of ZZT",
+		b"CITY" => b"Underground City of ZZT",
+		b"DUNGEONS" => b"The Dungeons of ZZT",
+		b"TOUR" => b"Guided Tour ZZT's Other Worlds",
+		b"TOWN" => b"The Town of ZZT",
+		_ => b"",
+	}
+}
+
+/// What an entry in a `FileBrowser` leads to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileBrowserEntryKind {
+	/// The directory that the current directory is in.
+	ParentDirectory,
+	/// A directory inside the current directory.
+	Directory,
+	/// The root directory of a drive (only on Windows).
+	Drive,
+	/// A world or saved game file that can be loaded.
+	File,
+}
+
+/// A single entry in a `FileBrowser`.
+#[derive(Clone)]
+pub struct FileBrowserEntry {
+	/// What the entry leads to.
+	pub kind: FileBrowserEntryKind,
+	/// The path of the file or directory.
+	pub path: PathBuf,
+	/// How the entry is shown in the world browser.
+	pub browser_entry: WorldBrowserEntry,
+}
+
+/// What happened when an entry was picked in a `FileBrowser`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileBrowserSelection {
+	/// The browser moved to another directory, so its entries should be shown again.
+	ChangedDirectory,
+	/// The file at the given path was picked.
+	File(PathBuf),
+	/// There is no entry at the given index.
+	None,
+}
+
+/// Lists the world (or saved game) files in a directory, along with the directories around it, so
+/// the player can move between directories and pick a file, like the file selection scrolls in DOS
+/// ZZT. The entries are shown with the engine's world browser (see
+/// `RuzztEngine::open_file_browser`). Files that can't be loaded as worlds are left out.
+#[derive(Clone)]
+pub struct FileBrowser {
+	/// The title displayed at the top of the browser.
+	pub title: DosString,
+	/// The directory being listed.
+	directory: PathBuf,
+	/// The extension of the files that are listed (eg. `ZZT`), ignoring case.
+	extension: String,
+	/// The directories and then files in `directory`.
+	entries: Vec<FileBrowserEntry>,
+}
+
+impl FileBrowser {
+	/// Make a browser with the given `title`, listing the files in `directory` with the given
+	/// `extension`.
+	pub fn new(title: DosString, directory: &Path, extension: &str) -> FileBrowser {
+		let mut file_browser = FileBrowser {
+			title,
+			directory: directory.to_path_buf(),
+			extension: extension.to_string(),
+			entries: vec![],
+		};
+		file_browser.refresh();
+		file_browser
+	}
+
+	/// The directory being listed.
+	pub fn directory(&self) -> &Path {
+		&self.directory
+	}
+
+	/// The directories and files that can be picked: the parent directory first (if there is one),
+	/// then the subdirectories, then the files, then the drives.
+	pub fn entries(&self) -> &[FileBrowserEntry] {
+		&self.entries
+	}
+
+	/// Get how every entry is shown in the world browser, in the same order as `entries`.
+	pub fn browser_entries(&self) -> Vec<WorldBrowserEntry> {
+		self.entries.iter().map(|entry| entry.browser_entry.clone()).collect()
+	}
+
+	/// List the current directory again.
+	pub fn refresh(&mut self) {
+		self.entries.clear();
+
+		// Relative paths like `.` have no parent until they are made absolute.
+		let absolute_directory = self.directory.canonicalize().unwrap_or_else(|_| self.directory.clone());
+		if let Some(parent) = absolute_directory.parent() {
+			self.entries.push(FileBrowserEntry {
+				kind: FileBrowserEntryKind::ParentDirectory,
+				path: parent.to_path_buf(),
+				browser_entry: WorldBrowserEntry::directory(DosString::from_slice(b"..")),
+			});
+		}
+
+		let mut subdirectories: Vec<PathBuf> = match std::fs::read_dir(&self.directory) {
+			Ok(entries) => entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect(),
+			Err(_) => vec![],
+		};
+		subdirectories.sort_by_key(|path| path.file_name().map(|name| name.to_string_lossy().to_ascii_uppercase()));
+		for path in subdirectories {
+			let name = path.file_name().map(|name| name.to_string_lossy().to_ascii_uppercase()).unwrap_or_default();
+			self.entries.push(FileBrowserEntry {
+				kind: FileBrowserEntryKind::Directory,
+				path,
+				browser_entry: WorldBrowserEntry::directory(DosString::from_str(&format!("{}\\", name))),
+			});
+		}
+
+		for path in files_with_extension(&self.directory, &self.extension) {
+			let world = match std::fs::File::open(&path).map_err(|err| err.to_string()).and_then(|mut file| World::parse_with_options(&mut file, ParseOptions::lenient())) {
+				Ok(world) => world,
+				Err(_) => continue,
+			};
+			let file_stem = path.file_stem().map(|stem| stem.to_string_lossy().to_ascii_uppercase()).unwrap_or_default();
+			let description = DosString::from_slice(world_file_description(file_stem.as_bytes()));
+			let mut browser_entry = WorldBrowserEntry::new(DosString::from_str(&file_stem), description, &world);
+			// A broken metadata file shouldn't hide the world.
+			browser_entry.metadata = load_world_metadata(&path).unwrap_or(None);
+			self.entries.push(FileBrowserEntry {
+				kind: FileBrowserEntryKind::File,
+				browser_entry,
+				path,
+			});
+		}
+
+		#[cfg(windows)]
+		{
+			for drive_letter in b'A' ..= b'Z' {
+				let drive_path = PathBuf::from(format!("{}:\\", drive_letter as char));
+				if drive_path.is_dir() {
+					self.entries.push(FileBrowserEntry {
+						kind: FileBrowserEntryKind::Drive,
+						browser_entry: WorldBrowserEntry::directory(DosString::from_str(&format!("{}:", drive_letter as char))),
+						path: drive_path,
+					});
+				}
+			}
+		}
+	}
+
+	/// Pick the entry at `entry_index`. Directories and drives are moved into, and files are given
+	/// back to be loaded.
+	pub fn select(&mut self, entry_index: usize) -> FileBrowserSelection {
+		let entry = match self.entries.get(entry_index) {
+			Some(entry) => entry,
+			None => return FileBrowserSelection::None,
+		};
+
+		match entry.kind {
+			FileBrowserEntryKind::File => FileBrowserSelection::File(entry.path.clone()),
+			FileBrowserEntryKind::ParentDirectory | FileBrowserEntryKind::Directory | FileBrowserEntryKind::Drive => {
+				self.directory = entry.path.clone();
+				self.refresh();
+				FileBrowserSelection::ChangedDirectory
+			}
+		}
+	}
+}
@@ -0,0 +1,83 @@
+use zzt_file_format::dosstring::DosString;
+
+#[cfg(feature = "file-io")]
+use std::path::Path;
+
+/// The file extension of the high score files that ZZT keeps next to each world, eg. `TOWN.HI` for
+/// `TOWN.ZZT`.
+pub const HIGH_SCORE_FILE_EXTENSION: &str = "HI";
+/// The number of entries in a high score file.
+pub const HIGH_SCORE_COUNT: usize = 30;
+/// The longest name a high score entry can have.
+pub const HIGH_SCORE_NAME_MAX_LEN: usize = 50;
+/// The number of bytes each entry takes up in a high score file: the name as a Pascal string with
+/// room for `HIGH_SCORE_NAME_MAX_LEN` characters, then the score.
+const HIGH_SCORE_ENTRY_SIZE: usize = 1 + HIGH_SCORE_NAME_MAX_LEN + 2;
+
+/// A single entry in a `HighScoreList`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighScoreEntry {
+	/// The name the player typed in when they got the score.
+	pub name: DosString,
+	pub score: i16,
+}
+
+/// The high scores of a world, as they are kept in its `.HI` file.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct HighScoreList {
+	/// The entries from the highest score to the lowest. Unused entries are left out.
+	pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScoreList {
+	/// Parse the contents of a `.HI` file, which has `HIGH_SCORE_COUNT` entries. Entries with empty
+	/// names are unused.
+	pub fn parse(data: &[u8]) -> Result<HighScoreList, String> {
+		if data.len() < HIGH_SCORE_COUNT * HIGH_SCORE_ENTRY_SIZE {
+			return Err(format!("High score file is {} bytes long, but should be {}", data.len(), HIGH_SCORE_COUNT * HIGH_SCORE_ENTRY_SIZE));
+		}
+
+		let mut entries = vec![];
+		for entry_data in data.chunks_exact(HIGH_SCORE_ENTRY_SIZE).take(HIGH_SCORE_COUNT) {
+			let name_len = (entry_data[0] as usize).min(HIGH_SCORE_NAME_MAX_LEN);
+			if name_len == 0 {
+				continue;
+			}
+			entries.push(HighScoreEntry {
+				name: DosString::from_slice(&entry_data[1 .. 1 + name_len]),
+				score: i16::from_le_bytes([entry_data[1 + HIGH_SCORE_NAME_MAX_LEN], entry_data[2 + HIGH_SCORE_NAME_MAX_LEN]]),
+			});
+		}
+		Ok(HighScoreList {
+			entries,
+		})
+	}
+
+	/// Get the lines of the scroll that shows the high scores, like the one in DOS ZZT.
+	pub fn scroll_lines(&self) -> Vec<DosString> {
+		let mut lines = vec![
+			DosString::from_slice(b"Score  Name"),
+			DosString::from_slice(b"-----  ----------------------------------"),
+		];
+		for entry in &self.entries {
+			let mut line = DosString::from_str(&format!("{:5}  ", entry.score));
+			line += entry.name.data.as_slice();
+			lines.push(line);
+		}
+		lines
+	}
+
+	/// Read the high score file that goes with the world at `world_path` (see
+	/// `dos_files::find_related_file`). Returns None if there isn't one, or an error if it can't be
+	/// read.
+	#[cfg(feature = "file-io")]
+	pub fn load(world_path: &Path) -> Result<Option<HighScoreList>, String> {
+		match crate::dos_files::find_related_file(world_path, HIGH_SCORE_FILE_EXTENSION) {
+			Some(path) => {
+				let data = std::fs::read(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+				HighScoreList::parse(&data).map(Some).map_err(|err| format!("{}: {}", path.display(), err))
+			}
+			None => Ok(None),
+		}
+	}
+}
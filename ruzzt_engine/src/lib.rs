@@ -1,14 +1,67 @@
+pub mod agent_env;
+pub mod ambient_music;
+pub mod attract_mode;
+pub mod ansi_art;
+#[cfg(feature = "file-io")]
+pub mod autosave;
 pub mod behaviour;
+pub mod board_info;
+pub mod board_invariants;
+pub mod board_links;
 pub mod board_message;
+pub mod board_region;
 pub mod board_simulator;
-pub mod caption;
+pub mod board_store;
+pub mod campaign;
+pub(crate) mod caption;
+pub(crate) mod char_picker;
+pub mod compatibility;
+pub mod constants;
 pub mod console;
+pub(crate) mod cutscene;
 pub mod direction;
+#[cfg(feature = "file-io")]
+pub mod dos_files;
 pub mod engine;
 pub mod event;
-pub mod oop_parser;
-pub mod scroll;
-pub mod side_bar;
+#[cfg(feature = "file-io")]
+pub mod file_browser;
+pub mod high_scores;
+pub mod lighting;
+pub mod machine_speed;
+pub mod message_log;
+pub(crate) mod message_timeline;
+pub mod minimap;
+pub mod observer;
+pub mod oop_lint;
+pub(crate) mod oop_parser;
+pub mod palette;
+pub(crate) mod passage_lock;
+pub(crate) mod pause_menu;
+/// The types that a frontend needs to run the engine, display the screen and play sounds, so they
+/// can all be imported with `use ruzzt_engine::prelude::*;`. The rest of the modules are for
+/// frontends that go further, eg. editing boards or adding new behaviours.
+pub mod prelude;
+pub mod profiler;
+pub mod random_fill;
+pub mod ruleset;
+pub mod savestate;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod screenshot;
+pub(crate) mod scroll;
+pub mod shareware;
+pub(crate) mod side_bar;
+pub mod smoke_test;
 pub mod sounds;
+pub mod speedrun;
+pub(crate) mod stuck_detector;
+pub(crate) mod text_editor;
+pub mod watch;
+pub mod weave;
+pub mod world_browser;
+pub(crate) mod world_map;
+pub mod world_trim;
 pub mod zzt_behaviours;
+pub mod zzt_config;
 mod tests;
@@ -0,0 +1,154 @@
+use crate::board_simulator::*;
+use crate::console::*;
+
+use zzt_file_format::ElementType;
+
+use num::FromPrimitive;
+
+/// The width of the area of the board that can be lit.
+const LIGHT_MAP_WIDTH: usize = BOARD_WIDTH - 2;
+/// The height of the area of the board that can be lit.
+const LIGHT_MAP_HEIGHT: usize = BOARD_HEIGHT - 2;
+
+/// How dark boards are lit when the modern lighting mode is on (see
+/// `RuzztEngine::lighting_options`). With the default options, the torch lights exactly the same
+/// area as it does in DOS ZZT, and nothing else gives off light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightingOptions {
+	/// How far the player's torch lights, in tiles across. Tiles are twice as tall as they are
+	/// wide, so the lit area is half as high. 7 is the torch from DOS ZZT.
+	pub torch_radius: u8,
+	/// How far each passage lights, in the same way as `torch_radius`. 0 means passages give off
+	/// no light.
+	pub passage_radius: u8,
+	/// When true, the empty floor lit by a passage is tinted with the passage's colour.
+	pub coloured_passages: bool,
+}
+
+impl Default for LightingOptions {
+	fn default() -> LightingOptions {
+		LightingOptions::new()
+	}
+}
+
+impl LightingOptions {
+	/// Make options that light dark boards like DOS ZZT does.
+	pub fn new() -> LightingOptions {
+		LightingOptions {
+			torch_radius: 7,
+			passage_radius: 0,
+			coloured_passages: false,
+		}
+	}
+}
+
+/// A single light on the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LightSource {
+	/// The 0-based position of the light on the board.
+	x: i16,
+	y: i16,
+	/// See `LightingOptions::torch_radius`.
+	radius: u8,
+	/// The colour of the light, or None for plain white light.
+	colour: Option<ConsoleColour>,
+}
+
+impl LightSource {
+	/// True if the light reaches the 0-based `x`/`y` position on the board.
+	fn lights(&self, x: i16, y: i16) -> bool {
+		let dx = (x - self.x) as i32;
+		let dy = (y - self.y) as i32;
+		let radius = self.radius as i32;
+		// Tiles are twice as tall as they are wide. With a radius of 7, this is the same as
+		// `CIRCLE_MASK`.
+		dx * dx + 2 * dy * dy <= radius * radius
+	}
+}
+
+/// Which tiles of a dark board are lit, and by what colour of light, worked out from the light
+/// sources on the board with `LightingOptions`.
+#[derive(Debug, Clone)]
+pub struct LightMap {
+	/// For each tile from the top left, None if the tile is dark, otherwise the colour of the last
+	/// light that reaches it (or None within the Some for plain white light).
+	tiles: Vec<Option<Option<ConsoleColour>>>,
+}
+
+impl LightMap {
+	/// Work out the light on the current board of `sim`. The player's torch is only a light source
+	/// while it is burning.
+	pub fn build(sim: &BoardSimulator, options: &LightingOptions) -> LightMap {
+		let mut light_sources = vec![];
+
+		if sim.world_header.torch_cycles.unwrap_or(0) > 0 {
+			let (player_x, player_y) = sim.get_player_location();
+			light_sources.push(LightSource {
+				x: player_x - 1,
+				y: player_y - 1,
+				radius: options.torch_radius,
+				colour: None,
+			});
+		}
+
+		if options.passage_radius > 0 {
+			for status in &sim.status_elements {
+				let x = status.location_x as i16;
+				let y = status.location_y as i16;
+				if let Some(tile) = sim.get_tile(x, y) {
+					if ElementType::from_u8(tile.element_id) == Some(ElementType::Passage) {
+						let colour = if options.coloured_passages {
+							Some(Colour::from_u8(tile.colour).foreground)
+						} else {
+							None
+						};
+						light_sources.push(LightSource {
+							x: x - 1,
+							y: y - 1,
+							radius: options.passage_radius,
+							colour,
+						});
+					}
+				}
+			}
+		}
+
+		let mut tiles = vec![None; LIGHT_MAP_WIDTH * LIGHT_MAP_HEIGHT];
+		for y in 0 .. LIGHT_MAP_HEIGHT {
+			for x in 0 .. LIGHT_MAP_WIDTH {
+				for light_source in &light_sources {
+					if light_source.lights(x as i16, y as i16) {
+						// White light washes out coloured light, so it always wins.
+						let tile = &mut tiles[y * LIGHT_MAP_WIDTH + x];
+						if *tile != Some(None) {
+							*tile = Some(light_source.colour);
+						}
+					}
+				}
+			}
+		}
+
+		LightMap {
+			tiles,
+		}
+	}
+
+	/// Get the light at the 0-based `x`/`y` position on the board, if there is any.
+	fn get(&self, x: i16, y: i16) -> Option<Option<ConsoleColour>> {
+		if x < 0 || y < 0 || x as usize >= LIGHT_MAP_WIDTH || y as usize >= LIGHT_MAP_HEIGHT {
+			return None;
+		}
+		self.tiles[y as usize * LIGHT_MAP_WIDTH + x as usize]
+	}
+
+	/// True if any light reaches the 0-based `x`/`y` position on the board.
+	pub fn is_lit(&self, x: i16, y: i16) -> bool {
+		self.get(x, y).is_some()
+	}
+
+	/// Get the colour of the light at the 0-based `x`/`y` position on the board, or None if it is
+	/// dark or lit with plain white light.
+	pub fn light_colour(&self, x: i16, y: i16) -> Option<ConsoleColour> {
+		self.get(x, y).and_then(|colour| colour)
+	}
+}
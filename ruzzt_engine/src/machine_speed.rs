@@ -0,0 +1,59 @@
+/// The number of game cycles per second at the normal game speed (4) in DOS ZZT. 161 cycles take
+/// about 17.3 seconds, which is 9.3 cycles per second.
+pub const NORMAL_CYCLES_PER_SECOND: f64 = 9.3;
+
+/// The DOS machine that the game's timing is modelled on (see `RuzztEngine::machine_speed`).
+///
+/// DOS ZZT waits for the timer between cycles, so it runs at the same speed on every machine as
+/// long as the machine can process a cycle in time. Slow machines couldn't keep up on busy boards,
+/// so those boards ran slower than the game speed asked for, and some worlds were made to be
+/// played that way (eg. with objects that were only dodgeable on an XT). The sounds are timed by
+/// the PC speaker's timer, so they play at the same speed on every machine.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MachineSpeed {
+	/// A machine fast enough that every cycle runs at the speed the game speed asks for.
+	Modern,
+	/// A 33 MHz 386, which only falls behind on the busiest boards at the fastest game speeds.
+	I386,
+	/// An 8 MHz turbo XT, which falls behind on any board with more than a few dozen status
+	/// elements.
+	TurboXt,
+}
+
+impl MachineSpeed {
+	/// Get the machine speed with the given `name` (see `name`), ignoring case.
+	pub fn from_name(name: &str) -> Option<MachineSpeed> {
+		[MachineSpeed::Modern, MachineSpeed::I386, MachineSpeed::TurboXt].iter().cloned()
+			.find(|machine_speed| machine_speed.name().eq_ignore_ascii_case(name))
+	}
+
+	/// The name of the machine speed, eg. "turbo-xt".
+	pub fn name(self) -> &'static str {
+		match self {
+			MachineSpeed::Modern => "modern",
+			MachineSpeed::I386 => "386",
+			MachineSpeed::TurboXt => "turbo-xt",
+		}
+	}
+
+	/// Get roughly how long the machine takes to process one cycle of a board with
+	/// `status_count` status elements, in milliseconds, including drawing the changes to the
+	/// screen.
+	pub fn cycle_processing_ms(self, status_count: usize) -> f64 {
+		let (cycle_ms, status_ms) = match self {
+			MachineSpeed::Modern => (0., 0.),
+			MachineSpeed::I386 => (2., 0.4),
+			MachineSpeed::TurboXt => (15., 3.),
+		};
+		cycle_ms + status_ms * status_count as f64
+	}
+
+	/// Get how long a cycle takes at the given `game_speed` (from fastest (0) to slowest (8)) on a
+	/// board with `status_count` status elements, in milliseconds. This is the time the game speed
+	/// waits for, unless the machine takes longer than that to process the cycle.
+	pub fn cycle_length_ms(self, game_speed: u8, status_count: usize) -> f64 {
+		// Speed 4 is the normal speed, and each step away from it is a fifth faster or slower.
+		let timer_ms = 1000. / NORMAL_CYCLES_PER_SECOND * (game_speed as f64 + 1.) / 5.;
+		timer_ms.max(self.cycle_processing_ms(status_count))
+	}
+}
@@ -0,0 +1,103 @@
+use zzt_file_format::dosstring::DosString;
+
+use std::collections::VecDeque;
+
+/// The number of messages kept in a `MessageLog` by default.
+pub const DEFAULT_MESSAGE_LOG_CAPACITY: usize = 50;
+
+/// A caption or scroll that was shown to the player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedMessage {
+	/// The title of the scroll, which is empty for captions and scrolls without a title.
+	pub title: DosString,
+	/// The text of the caption (one line), or the content lines of the scroll.
+	pub lines: Vec<DosString>,
+}
+
+/// Keeps the most recent captions and scrolls shown to the player, so they can be read again after
+/// they disappear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageLog {
+	/// The logged messages from oldest to newest.
+	pub messages: VecDeque<LoggedMessage>,
+	/// The most messages to keep. When another message is added, the oldest one is removed.
+	pub capacity: usize,
+}
+
+impl Default for MessageLog {
+	fn default() -> MessageLog {
+		MessageLog::new(DEFAULT_MESSAGE_LOG_CAPACITY)
+	}
+}
+
+impl MessageLog {
+	/// Make an empty log that keeps at most `capacity` messages.
+	pub fn new(capacity: usize) -> MessageLog {
+		MessageLog {
+			messages: VecDeque::new(),
+			capacity,
+		}
+	}
+
+	/// Add a message to the log, removing the oldest messages if the log is full. Messages with no
+	/// text are ignored.
+	pub fn push(&mut self, title: DosString, lines: Vec<DosString>) {
+		if lines.iter().all(|line| line.is_empty()) || self.capacity == 0 {
+			return;
+		}
+
+		while self.messages.len() >= self.capacity {
+			self.messages.pop_front();
+		}
+		self.messages.push_back(LoggedMessage {
+			title,
+			lines,
+		});
+	}
+
+	/// Remove every message from the log.
+	pub fn clear(&mut self) {
+		self.messages.clear();
+	}
+
+	/// Get the lines of a scroll that shows the logged messages, from oldest to newest. Links in
+	/// logged scrolls are shown as plain text so they can't be clicked again.
+	pub fn scroll_lines(&self) -> Vec<DosString> {
+		let mut lines = vec![];
+
+		for message in &self.messages {
+			if !lines.is_empty() {
+				lines.push(DosString::new());
+			}
+
+			if !message.title.is_empty() {
+				let mut heading = DosString::from_slice(b"$");
+				heading += &message.title.data;
+				lines.push(heading);
+			}
+
+			for line in &message.lines {
+				lines.push(plain_scroll_line(line));
+			}
+		}
+
+		if lines.is_empty() {
+			lines.push(DosString::from_slice(b"No messages yet."));
+		}
+
+		lines
+	}
+}
+
+/// Get a scroll line that shows the text of `line` without it being a link. Other lines are left
+/// alone.
+fn plain_scroll_line(line: &DosString) -> DosString {
+	if line.data.first() == Some(&b'!') {
+		let text_start = line.data.iter().position(|c| *c == b';').map(|pos| pos + 1).unwrap_or(line.len());
+		let mut plain_line = DosString::from_slice(b"  ");
+		plain_line += &line.data[text_start ..];
+		plain_line
+	} else {
+		line.clone()
+	}
+}
@@ -0,0 +1,95 @@
+use crate::behaviour::LabelOperation;
+
+use zzt_file_format::dosstring::DosString;
+
+use serde_derive::{Serialize, Deserialize};
+
+use std::collections::VecDeque;
+
+/// The number of deliveries a `MessageTimeline` keeps by default before it starts dropping the
+/// oldest ones.
+pub const DEFAULT_MESSAGE_TIMELINE_LEN: usize = 1000;
+
+/// A label operation (`#send`, `#zap` or `#restore`) that was delivered to a status element.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageDelivery {
+	/// The global cycle the message was delivered in.
+	pub cycle: usize,
+	/// The index of the board the message was delivered on.
+	pub board_index: i16,
+	/// The index of the status that sent the message, or None if it wasn't sent by a status (eg.
+	/// when a pushed object is sent to its `:touch` label).
+	pub sender_index: Option<usize>,
+	/// The `@name` of the sender, if it has one.
+	pub sender_name: Option<DosString>,
+	/// The index of the status the message was delivered to.
+	pub receiver_index: usize,
+	/// The `@name` of the receiver, if it has one.
+	pub receiver_name: Option<DosString>,
+	/// The label that was sent, zapped or restored.
+	pub label: DosString,
+	/// What was done with the label.
+	pub operation: LabelOperation,
+}
+
+impl MessageDelivery {
+	/// Describe the delivery on one line, eg. "  12 @lever -> @door #send open".
+	pub fn description(&self) -> String {
+		let status_desc = |index: Option<usize>, name: &Option<DosString>| match (index, name) {
+			(_, Some(name)) => format!("@{}", name.to_string(false)),
+			(Some(index), None) => format!("status {}", index),
+			(None, None) => "board".to_string(),
+		};
+		let operation = match self.operation {
+			LabelOperation::Jump => "send",
+			LabelOperation::Zap => "zap",
+			LabelOperation::RestoreZztStyle => "restore",
+		};
+		format!("{:4} {} -> {} #{} {}", self.cycle, status_desc(self.sender_index, &self.sender_name), status_desc(Some(self.receiver_index), &self.receiver_name), operation, self.label.to_string(false))
+	}
+}
+
+/// The most recent messages delivered between status elements, recorded by the `BoardSimulator`
+/// while it is turned on (see `RuzztEngine::set_message_timeline_enabled`). This is for debugging
+/// worlds where lots of objects `#send` messages to each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageTimeline {
+	/// The global cycle that deliveries are recorded in. The engine keeps this up to date.
+	pub current_cycle: usize,
+	/// The most deliveries that are kept.
+	pub max_len: usize,
+	/// The deliveries, oldest first.
+	pub deliveries: VecDeque<MessageDelivery>,
+}
+
+impl MessageTimeline {
+	/// Make an empty timeline that keeps the last `max_len` deliveries.
+	pub fn new(max_len: usize) -> MessageTimeline {
+		MessageTimeline {
+			current_cycle: 0,
+			max_len,
+			deliveries: VecDeque::new(),
+		}
+	}
+
+	/// Add a delivery to the end of the timeline, dropping the oldest one if it is full.
+	pub fn record(&mut self, delivery: MessageDelivery) {
+		if self.deliveries.len() >= self.max_len {
+			self.deliveries.pop_front();
+		}
+		self.deliveries.push_back(delivery);
+	}
+
+	/// Get a line describing each of the last `count` deliveries, oldest first, for showing in a
+	/// scroll.
+	pub fn recent_lines(&self, count: usize) -> Vec<DosString> {
+		self.deliveries.iter().skip(self.deliveries.len().saturating_sub(count))
+			.map(|delivery| DosString::from_str(&delivery.description()))
+			.collect()
+	}
+
+	/// Serialize the deliveries as a JSON array, oldest first.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(&self.deliveries).unwrap()
+	}
+}
@@ -0,0 +1,165 @@
+use crate::board_simulator::*;
+use crate::console::*;
+
+use num::FromPrimitive;
+
+/// The width of the part of the screen that the board is drawn in.
+const BOARD_SCREEN_WIDTH: usize = BOARD_WIDTH - 2;
+/// The height of the part of the screen that the board is drawn in.
+const BOARD_SCREEN_HEIGHT: usize = BOARD_HEIGHT - 2;
+
+/// Get the colour that stands out the most when looking at `screen_char`. Blank characters show
+/// their background colour, and everything else shows its foreground colour.
+fn visible_colour(screen_char: ConsoleChar) -> ConsoleColour {
+	match screen_char.char_code {
+		0 | b' ' | 255 => {
+			// Background colours 0x8-0xf are the blinking versions of 0x0-0x7.
+			ConsoleColour::from_u8(screen_char.background as u8 & 0b111).unwrap()
+		}
+		_ => screen_char.foreground,
+	}
+}
+
+/// Which corner of the board a `Minimap` is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MinimapCorner {
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	BottomRight,
+}
+
+/// A shrunk down view of a board, where each block is the most common colour in the region of the
+/// board it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimap {
+	/// The number of blocks in each row.
+	pub width: usize,
+	/// The number of rows of blocks.
+	pub height: usize,
+	/// The colour of each block, in rows from the top-left.
+	pub blocks: Vec<ConsoleColour>,
+}
+
+impl Minimap {
+	/// Make a `width`x`height` minimap of the board as it is drawn in `console_state`, so it should
+	/// be called after `RuzztEngine::update_screen`. The size is clamped to the size of the board.
+	pub fn from_console_state(console_state: &ConsoleState, width: usize, height: usize) -> Minimap {
+		let width = width.clamp(1, BOARD_SCREEN_WIDTH);
+		let height = height.clamp(1, BOARD_SCREEN_HEIGHT);
+
+		let mut blocks = Vec::with_capacity(width * height);
+		for block_y in 0 .. height {
+			let min_y = block_y * BOARD_SCREEN_HEIGHT / height;
+			let max_y = (block_y + 1) * BOARD_SCREEN_HEIGHT / height;
+			for block_x in 0 .. width {
+				let min_x = block_x * BOARD_SCREEN_WIDTH / width;
+				let max_x = (block_x + 1) * BOARD_SCREEN_WIDTH / width;
+
+				let mut colour_counts = [0usize; 16];
+				for y in min_y .. max_y {
+					for x in min_x .. max_x {
+						colour_counts[visible_colour(console_state.get_char(x, y)) as usize] += 1;
+					}
+				}
+
+				// When colours are equally common the lowest numbered colour wins, so the result
+				// doesn't depend on the order of the tiles.
+				let mut dominant_colour = 0;
+				for (colour, count) in colour_counts.iter().enumerate() {
+					if *count > colour_counts[dominant_colour] {
+						dominant_colour = colour;
+					}
+				}
+				blocks.push(ConsoleColour::from_u8(dominant_colour as u8).unwrap());
+			}
+		}
+
+		Minimap {
+			width,
+			height,
+			blocks,
+		}
+	}
+
+	/// Get the colour of the block at the `x`x`y` position.
+	pub fn get_block(&self, x: usize, y: usize) -> ConsoleColour {
+		self.blocks[y * self.width + x]
+	}
+
+	/// Draw the minimap into `console_state` with its top-left at `x`x`y`, surrounded by a single
+	/// line border. Anything that doesn't fit in the part of the screen the board is drawn in is cut
+	/// off, so the minimap never covers the side bar.
+	pub fn draw(&self, x: usize, y: usize, console_state: &mut ConsoleState) {
+		let border_colour = ConsoleColour::LightGray;
+		for draw_y in 0 .. self.height + 2 {
+			for draw_x in 0 .. self.width + 2 {
+				let screen_x = x + draw_x;
+				let screen_y = y + draw_y;
+				if screen_x >= BOARD_SCREEN_WIDTH || screen_y >= BOARD_SCREEN_HEIGHT {
+					continue;
+				}
+
+				let is_left = draw_x == 0;
+				let is_right = draw_x == self.width + 1;
+				let is_top = draw_y == 0;
+				let is_bottom = draw_y == self.height + 1;
+				let screen_char = match (is_left, is_right, is_top, is_bottom) {
+					(true, _, true, _) => ConsoleChar::new(218, ConsoleColour::Black, border_colour),
+					(_, true, true, _) => ConsoleChar::new(191, ConsoleColour::Black, border_colour),
+					(true, _, _, true) => ConsoleChar::new(192, ConsoleColour::Black, border_colour),
+					(_, true, _, true) => ConsoleChar::new(217, ConsoleColour::Black, border_colour),
+					(_, _, true, _) | (_, _, _, true) => ConsoleChar::new(196, ConsoleColour::Black, border_colour),
+					(true, _, _, _) | (_, true, _, _) => ConsoleChar::new(179, ConsoleColour::Black, border_colour),
+					_ => ConsoleChar::new(219, ConsoleColour::Black, self.get_block(draw_x - 1, draw_y - 1)),
+				};
+				*console_state.get_char_mut(screen_x, screen_y) = screen_char;
+			}
+		}
+	}
+
+	/// Draw the minimap (with its border) in the given `corner` of the board in `console_state`.
+	pub fn draw_in_corner(&self, corner: MinimapCorner, console_state: &mut ConsoleState) {
+		let right_x = BOARD_SCREEN_WIDTH.saturating_sub(self.width + 2);
+		let bottom_y = BOARD_SCREEN_HEIGHT.saturating_sub(self.height + 2);
+		let (x, y) = match corner {
+			MinimapCorner::TopLeft => (0, 0),
+			MinimapCorner::TopRight => (right_x, 0),
+			MinimapCorner::BottomLeft => (0, bottom_y),
+			MinimapCorner::BottomRight => (right_x, bottom_y),
+		};
+		self.draw(x, y, console_state);
+	}
+
+	/// Get the minimap as an image with one pixel per block, as (red, green, blue) bytes in rows from
+	/// the top-left. This is for exporting thumbnails of boards.
+	pub fn to_rgb_pixels(&self) -> Vec<u8> {
+		let mut pixels = Vec::with_capacity(self.blocks.len() * 3);
+		for colour in &self.blocks {
+			let (r, g, b) = colour.to_rgb();
+			pixels.extend_from_slice(&[r, g, b]);
+		}
+		pixels
+	}
+}
+
+/// Where and how big the `RuzztEngine` draws a minimap of the current board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapOptions {
+	pub corner: MinimapCorner,
+	/// The number of blocks in each row of the minimap.
+	pub width: usize,
+	/// The number of rows of blocks in the minimap.
+	pub height: usize,
+}
+
+impl MinimapOptions {
+	/// Make options for a minimap with the given size in the given `corner` of the board.
+	pub fn new(corner: MinimapCorner, width: usize, height: usize) -> MinimapOptions {
+		MinimapOptions {
+			corner,
+			width,
+			height,
+		}
+	}
+}
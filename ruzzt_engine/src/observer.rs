@@ -0,0 +1,71 @@
+use zzt_file_format::WorldHeader;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Receives notifications about things that happen in a game, so a front-end can implement
+/// achievements, timers and statistics without changing any behaviours. Register one with
+/// `RuzztEngine::add_observer`. All methods do nothing by default, so only the interesting ones
+/// need to be implemented.
+pub trait GameObserver {
+	/// The player's score changed from `old_score` to `new_score`.
+	fn on_score_change(&mut self, _old_score: i16, _new_score: i16) {}
+	/// The player entered the board with the given `board_index`, either by walking off the edge of
+	/// a board or by using a passage.
+	fn on_board_enter(&mut self, _board_index: i16) {}
+	/// The player picked up the key with the given `key_index` (0 is blue, 6 is white).
+	fn on_key_pickup(&mut self, _key_index: usize) {}
+	/// The player's health dropped to 0.
+	fn on_death(&mut self) {}
+}
+
+/// A `GameObserver` that can be shared between the engine and the front-end, so the front-end can
+/// read back whatever the observer collected.
+pub type SharedGameObserver = Rc<RefCell<dyn GameObserver>>;
+
+/// The parts of the game state that `GameObserver`s are notified about. The engine compares these
+/// before and after each step to work out which notifications to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedState {
+	score: i16,
+	board_index: i16,
+	keys: [bool; 7],
+	health: i16,
+}
+
+impl ObservedState {
+	/// Get the observed state from the given `world_header`.
+	pub fn from_world_header(world_header: &WorldHeader) -> ObservedState {
+		ObservedState {
+			score: world_header.player_score,
+			board_index: world_header.player_board,
+			keys: world_header.player_keys,
+			health: world_header.player_health,
+		}
+	}
+
+	/// Notify all the `observers` about the differences between this state and the `new_state`.
+	pub fn notify_changes(&self, new_state: &ObservedState, observers: &[SharedGameObserver]) {
+		for observer in observers {
+			let mut observer = observer.borrow_mut();
+
+			if new_state.score != self.score {
+				observer.on_score_change(self.score, new_state.score);
+			}
+
+			if new_state.board_index != self.board_index {
+				observer.on_board_enter(new_state.board_index);
+			}
+
+			for (key_index, (had_key, has_key)) in self.keys.iter().zip(new_state.keys.iter()).enumerate() {
+				if *has_key && !*had_key {
+					observer.on_key_pickup(key_index);
+				}
+			}
+
+			if new_state.health <= 0 && self.health > 0 {
+				observer.on_death();
+			}
+		}
+	}
+}
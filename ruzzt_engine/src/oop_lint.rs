@@ -0,0 +1,73 @@
+use crate::sounds::{process_notes_string_with_warnings, NotesWarning};
+
+use zzt_file_format::{CodeSource, World};
+
+/// A problem in a world's OOP code found by `lint_world` without running it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+	pub board_index: usize,
+	/// The status element that owns the code.
+	pub status_index: usize,
+	/// The 1-based line of the code the problem is on.
+	pub line_number: usize,
+	pub kind: LintWarningKind,
+}
+
+/// The kinds of problem `lint_world` looks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintWarningKind {
+	/// A `#play` command has a character in its notes that doesn't mean anything. The position of
+	/// the warning is within the notes, which start straight after `#play`.
+	PlayString(NotesWarning),
+}
+
+impl LintWarning {
+	/// Describe the problem, eg. "Board 1 status 2 line 3: #play: Unknown character 'z' at
+	/// position 4 of the notes".
+	pub fn description(&self) -> String {
+		let message = match self.kind {
+			LintWarningKind::PlayString(ref notes_warning) => format!("#play: {}", notes_warning.description()),
+		};
+		format!("Board {} status {} line {}: {}", self.board_index, self.status_index, self.line_number, message)
+	}
+}
+
+/// Find the problems in one status element's `code`, without the board and status indices filled
+/// in.
+fn lint_code(code: &[u8]) -> Vec<(usize, LintWarningKind)> {
+	let mut warnings = vec![];
+	for (line_index, line) in code.split(|&c| c == b'\r').enumerate() {
+		let command = b"#play";
+		if line.len() < command.len() || !line[.. command.len()].eq_ignore_ascii_case(command) {
+			continue;
+		}
+		let notes = &line[command.len() ..];
+		// Don't mistake commands like `#player` for `#play`.
+		if matches!(notes.first(), Some(c) if c.is_ascii_alphanumeric() || *c == b'_') {
+			continue;
+		}
+		let (_, notes_warnings) = process_notes_string_with_warnings(notes);
+		warnings.extend(notes_warnings.into_iter().map(|notes_warning| (line_index + 1, LintWarningKind::PlayString(notes_warning))));
+	}
+	warnings
+}
+
+/// Find the problems in the OOP code of every status element in `world` that can be spotted before
+/// the world runs, in board and status order. Code that is bound to another status element's code
+/// is only checked once.
+pub fn lint_world(world: &World) -> Vec<LintWarning> {
+	let mut warnings = vec![];
+	for (board_index, board) in world.boards.iter().enumerate() {
+		for (status_index, status) in board.status_elements.iter().enumerate() {
+			if let CodeSource::Owned(ref code) = status.code_source {
+				warnings.extend(lint_code(&code.data).into_iter().map(|(line_number, kind)| LintWarning {
+					board_index,
+					status_index,
+					line_number,
+					kind,
+				}));
+			}
+		}
+	}
+	warnings
+}
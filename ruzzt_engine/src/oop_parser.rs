@@ -3,15 +3,14 @@ use crate::board_message::*;
 use crate::board_simulator::*;
 use crate::direction::*;
 use crate::console::ConsoleColour;
+use crate::constants::SCROLL_LINE_WIDTH;
 use crate::sounds::*;
 
 use zzt_file_format::*;
 use zzt_file_format::dosstring::DosString;
+use zzt_file_format::extended_colour::{ExtendedColour, ExtendedColours};
 
-use rand::{self, Rng};
-use num::FromPrimitive;
 use std::borrow::Cow;
-use std;
 
 /// Describes a particular OOP operator, which is always determined by the value of the first
 /// character on a line.
@@ -50,7 +49,7 @@ fn search_tile_desc(tile_desc: TileTypeDesc, sim: &BoardSimulator, found_fn: &mu
 
 /// Create an `Action` that spawns a tile matching the given `tile_desc` at the given `x`/`y`
 /// position on the board.
-fn create_tile_action(tile_desc: &TileTypeDesc, x: u8, y: u8) -> Action {
+fn create_tile_action(tile_desc: &TileTypeDesc, x: u8, y: u8, sim: &BoardSimulator) -> Action {
 	let colour = tile_desc.colour.unwrap_or(0x0f);
 
 	let tile = BoardTile {
@@ -60,7 +59,7 @@ fn create_tile_action(tile_desc: &TileTypeDesc, x: u8, y: u8) -> Action {
 
 	let mut status_element = None;
 
-	if let Some(ty) = ElementType::from_u8(tile_desc.element_id) {
+	if let Some(ty) = sim.ruleset.element_type(tile_desc.element_id) {
 		match ty {
 			| ElementType::Bear
 			| ElementType::BlinkWall
@@ -206,8 +205,9 @@ pub struct OopExecutionState {
 	delete_after: bool,
 	/// The status to actually use. If not set, will use the current executing status.
 	override_working_status_index: Option<usize>,
-	/// The number of executed OOP operations. After a certain number, the OOP will be forced to
-	/// stop running, preventing the game from hanging.
+	/// The number of executed OOP operations, not counting lines of text. After
+	/// `SimulatorSettings::oop_instruction_limit` of them, the OOP will be forced to stop running
+	/// until the next step, preventing the game from hanging.
 	executed_operation_count: usize,
 	/// A single scroll will be created from all the lines of text read in one parsing session.
 	/// eg. if you have some text, then something like a #play command that doesn't halt the
@@ -237,7 +237,9 @@ impl OopExecutionState {
 	}
 
 	/// Returns true if the current execution should finish.
-	fn apply_outcome_result(&mut self, outcome_result: Result<ParseActionOutcome, DosString>, parser: &mut OopParser, actions: &mut Vec<Action>) -> bool {
+	/// Errors are handled like `OopError` in DOS ZZT: the error is shown at the bottom of the screen
+	/// with an "ERR:" prefix and a sound, and the program of the status at `status_index` halts.
+	fn apply_outcome_result(&mut self, outcome_result: Result<ParseActionOutcome, DosString>, status_index: usize, parser: &mut OopParser, actions: &mut Vec<Action>) -> bool {
 		let mut is_finished = false;
 		match outcome_result {
 			Ok(outcome) => {
@@ -255,11 +257,16 @@ impl OopExecutionState {
 				}
 			}
 			Err(error_string) => {
-				println!("OOP Error: {:?}", error_string);
+				let mut message = DosString::from_slice(b"ERR: ");
+				message += &error_string.data;
+				actions.push(Action::SendBoardMessage(BoardMessage::EngineLog(LogLevel::Warning,
+					format!("The status at index {} stopped because of an OOP error: {}", status_index, message.to_string(false)))));
 				actions.push(Action::SendBoardMessage(BoardMessage::OpenScroll {
 					title: DosString::new(),
-					content_lines: vec![error_string],
+					content_lines: vec![message],
 				}));
+				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(vec![SoundEntry{sound_code: 80, length_multiplier: 10}], SoundPriority::Level(5))));
+				parser.pos = -1;
 				is_finished = true;
 			}
 		}
@@ -307,7 +314,7 @@ impl ActionContinuation for OopExecutionState {
 					self.action_to_check_on_next_step = None;
 					if apply_action_report.move_was_blocked == BlockedStatus::Blocked {
 						let outcome_result = parser.parse_command(working_status_index, status, &mut actions, self, sim);
-						is_finished = self.apply_outcome_result(outcome_result, &mut parser, &mut actions);
+						is_finished = self.apply_outcome_result(outcome_result, working_status_index, &mut parser, &mut actions);
 					} else {
 						parser.read_to_end_of_line();
 						parser.skip_new_line();
@@ -331,7 +338,7 @@ impl ActionContinuation for OopExecutionState {
 								// so do nothing.
 							}
 						} else {
-							actions.push(create_tile_action(&tile_type, dest_x as u8, dest_y as u8));
+							actions.push(create_tile_action(&tile_type, dest_x as u8, dest_y as u8, sim));
 						}
 					} else {
 						// There's no tile at the destination...
@@ -346,7 +353,7 @@ impl ActionContinuation for OopExecutionState {
 					// either take the 20 ammo, or move south.
 					if apply_action_report.take_player_item_failed {
 						let outcome_result = parser.parse_command(working_status_index, status, &mut actions, self, sim);
-						is_finished = self.apply_outcome_result(outcome_result, &mut parser, &mut actions);
+						is_finished = self.apply_outcome_result(outcome_result, working_status_index, &mut parser, &mut actions);
 					} else {
 						parser.read_to_end_of_line();
 						parser.skip_new_line();
@@ -361,16 +368,25 @@ impl ActionContinuation for OopExecutionState {
 			// all the way back to the start of the `#take`.
 			self.current_start_of_action_pos = Some(parser.pos);
 
+			// Like in DOS ZZT, lines of text don't count as instructions, so long messages aren't
+			// cut off.
+			if parser.peek_operator() != OopOperator::Text {
+				self.executed_operation_count += 1;
+			}
+
 			let outcome_result = parser.parse_action(working_status_index, status, &mut actions, self, sim);
-			is_finished = self.apply_outcome_result(outcome_result, &mut parser, &mut actions);
+			is_finished = self.apply_outcome_result(outcome_result, working_status_index, &mut parser, &mut actions);
 		}
 
-		self.executed_operation_count += 1;
+		sim.record_profile(|profile| profile.oop_operations += 1);
 
 		// ZZT will excecute a maximum of 64 "instructions" (basically 64 lines of code, except for
 		// stuff like /s/s/s/s).
-		if self.executed_operation_count > 64 {
+		if self.executed_operation_count > sim.settings.oop_instruction_limit && !is_finished {
 			is_finished = true;
+			let name = OopParser::new(sim.get_status_code(status), 0).get_name().map(|name| format!("@{}", name.to_string(false))).unwrap_or_else(|| "(no name)".to_string());
+			actions.push(Action::SendBoardMessage(BoardMessage::EngineLog(LogLevel::Warning,
+				format!("The status at index {} ({}, at {}, {}) ran more than {} OOP instructions in one step, so it was stopped until its next step", working_status_index, name, status.location_x, status.location_y, sim.settings.oop_instruction_limit))));
 		}
 
 		if parser.pos != status.code_current_instruction {
@@ -538,8 +554,7 @@ impl<'code> OopParser<'code> {
 				// ZZT ignores new lines unless there is already something in the message.
 				if line.len() > 0 || state.text_message_content_lines.len() > 0 {
 					println!("Line: {:?}", line);
-					// Scrolls in ZZT probably use a 2D array of 50 x something chars.
-					line.data.truncate(50);
+					line.data.truncate(SCROLL_LINE_WIDTH);
 					state.text_message_content_lines.push(line);
 				}
 				self.skip_new_line();
@@ -555,6 +570,14 @@ impl<'code> OopParser<'code> {
 		Ok(outcome)
 	}
 
+	/// Get the operator at the current position like `parse_operator`, without moving past it.
+	pub fn peek_operator(&mut self) -> OopOperator {
+		let pos = self.pos;
+		let op = self.parse_operator();
+		self.pos = pos;
+		op
+	}
+
 	pub fn parse_operator(&mut self) -> OopOperator {
 		if self.pos as usize >= self.code.len() {
 			return OopOperator::Eof;
@@ -686,8 +709,7 @@ impl<'code> OopParser<'code> {
 				direction.opposite()
 			}
 			b"rndne" => {
-				let mut rng = rand::thread_rng();
-				let random_bool: bool = rng.gen();
+				let random_bool = sim.random_bool();
 				if random_bool {
 					Direction::North
 				} else {
@@ -695,8 +717,7 @@ impl<'code> OopParser<'code> {
 				}
 			}
 			b"rndns" => {
-				let mut rng = rand::thread_rng();
-				let random_bool: bool = rng.gen();
+				let random_bool = sim.random_bool();
 				if random_bool {
 					Direction::North
 				} else {
@@ -706,8 +727,7 @@ impl<'code> OopParser<'code> {
 			b"rndp" => {
 				self.skip_spaces();
 				let direction = self.parse_direction(status, sim)?;
-				let mut rng = rand::thread_rng();
-				let random_bool: bool = rng.gen();
+				let random_bool = sim.random_bool();
 				match direction {
 					Direction::North | Direction::South => {
 						if random_bool {
@@ -812,6 +832,7 @@ impl<'code> OopParser<'code> {
 			b"gems" => PlayerItemType::Gems,
 			b"health" => PlayerItemType::Health,
 			b"score" => PlayerItemType::Score,
+			b"z" => PlayerItemType::Stones,
 			b"time" => PlayerItemType::Time,
 			_ => {
 				let mut error_msg = DosString::from_slice(b"Bad item: ");
@@ -823,7 +844,7 @@ impl<'code> OopParser<'code> {
 		Ok(player_item_type)
 	}
 
-	fn parse_tile_type_desc(&mut self) -> Result<TileTypeDesc, DosString> {
+	fn parse_tile_type_desc(&mut self, sim: &BoardSimulator) -> Result<TileTypeDesc, DosString> {
 		let pos_before_colour = self.pos;
 
 		let colour = match self.parse_colour() {
@@ -838,7 +859,7 @@ impl<'code> OopParser<'code> {
 		};
 
 		let element_id = match self.parse_type() {
-			Ok(ty) => ty as u8,
+			Ok(ty) => sim.element_id(ty),
 			Err(err) => return Err(err),
 		};
 
@@ -859,7 +880,7 @@ impl<'code> OopParser<'code> {
 			}
 			b"any" => {
 				self.skip_spaces();
-				let find_desc = self.parse_tile_type_desc()?;
+				let find_desc = self.parse_tile_type_desc(sim)?;
 				let mut found_any = false;
 				for tile in &sim.tiles {
 					if find_desc.matches(*tile) {
@@ -890,13 +911,12 @@ impl<'code> OopParser<'code> {
 			}
 			flag_name => {
 				// TODO: Unnecessary DosString creation here.
-				let flag_is_set = sim.world_header.last_matching_flag(DosString::from_slice(flag_name)).is_some();
-				Ok(flag_is_set)
+				Ok(sim.flag_is_set(&DosString::from_slice(flag_name)))
 			}
 		}
 	}
 
-	fn apply_message_desc_label_operation(&mut self, message_desc: MessageDesc, label_op: LabelOperation, status_index: usize, actions: &mut Vec<Action>) {
+	fn apply_message_desc_label_operation(&mut self, message_desc: MessageDesc, label_op: LabelOperation, status_index: usize, sim: &BoardSimulator, actions: &mut Vec<Action>) {
 		let mut includes_myself = false;
 		let mut includes_others = false;
 
@@ -928,6 +948,7 @@ impl<'code> OopParser<'code> {
 
 		if includes_myself {
 			self.apply_label_operation(receiver_name_opt.as_ref(), &message_desc.label, label_op);
+			sim.record_message_delivery(Some(status_index), status_index, &message_desc.label, label_op);
 		}
 
 		if includes_others {
@@ -962,6 +983,22 @@ impl<'code> OopParser<'code> {
 		}
 	}
 
+	/// Parse the colour of a `#colour` command, which is either one number for one of the 256
+	/// indexed colours, or three numbers for the red, green and blue values of a 24-bit colour.
+	fn parse_extended_colour(&mut self) -> Option<ExtendedColour> {
+		let mut values = vec![];
+		while let Ok(value) = self.parse_number() {
+			values.push(value);
+			self.skip_spaces();
+		}
+		let to_u8 = |value: &isize| if *value < 256 { Some(*value as u8) } else { None };
+		match values.as_slice() {
+			[index] => to_u8(index).map(ExtendedColour::Indexed),
+			[red, green, blue] => Some(ExtendedColour::Rgb(to_u8(red)?, to_u8(green)?, to_u8(blue)?)),
+			_ => None,
+		}
+	}
+
 	/// Returns false if the command should not progress, and the code position should be returned
 	/// to the command's # character.
 	fn parse_command(&mut self, status_index: usize, status: &StatusElement, actions: &mut Vec<Action>, state: &mut OopExecutionState, sim: &BoardSimulator) -> Result<ParseActionOutcome, DosString> {
@@ -975,7 +1012,7 @@ impl<'code> OopParser<'code> {
 		if message_desc.receiver != ReceiverDesc::Myself {
 			// TODO: Should this skip spaces before skipping new line?
 			self.skip_new_line();
-			self.apply_message_desc_label_operation(message_desc, LabelOperation::Jump, status_index, actions);
+			self.apply_message_desc_label_operation(message_desc, LabelOperation::Jump, status_index, sim, actions);
 		} else {
 			// If it's a "myself" message description ("thing", not "blah:thing"), that means
 			// there's only one word by itself without a colon, so treat it as the name of the
@@ -991,10 +1028,10 @@ impl<'code> OopParser<'code> {
 					// See the comment on #die.
 					if state.text_message_content_lines.len() <= 1 {
 						self.skip_spaces();
-						let become_desc = self.parse_tile_type_desc()?;
+						let become_desc = self.parse_tile_type_desc(sim)?;
 						self.read_to_end_of_line();
 						self.skip_new_line();
-						actions.push(create_tile_action(&become_desc, status.location_x, status.location_y));
+						actions.push(create_tile_action(&become_desc, status.location_x, status.location_y, sim));
 						outcome.finish_immediately = true;
 					} else {
 						// When a scroll is going to open, don't execute the die command just yet,
@@ -1035,14 +1072,14 @@ impl<'code> OopParser<'code> {
 					// what seems to be bad pointer accesses, which we are not going to bother
 					// replicating because it's basically impossible.
 					self.skip_spaces();
-					let from_desc = self.parse_tile_type_desc()?;
+					let from_desc = self.parse_tile_type_desc(sim)?;
 					self.skip_spaces();
-					let mut to_desc = self.parse_tile_type_desc()?;
+					let mut to_desc = self.parse_tile_type_desc(sim)?;
 					self.read_to_end_of_line();
 					self.skip_new_line();
 					search_tile_desc(from_desc, sim, &mut |x, y, tile| {
 						to_desc.colour = Some(tile.colour);
-						actions.push(create_tile_action(&to_desc, x as u8, y as u8));
+						actions.push(create_tile_action(&to_desc, x as u8, y as u8, sim));
 					});
 				}
 				b"char" => {
@@ -1063,6 +1100,33 @@ impl<'code> OopParser<'code> {
 					//println!("#clear {:?}", flag_name);
 					actions.push(Action::ClearFlag(flag_name));
 				}
+				b"colour" if sim.settings.extended_colours_enabled => {
+					// `#colour 196` or `#colour 255 128 0` sets the colour the object is drawn
+					// with, and `#colour back ...` sets the colour behind it. Anything else (eg.
+					// `#colour off`) turns the extended colours off again.
+					self.skip_spaces();
+					let layer = self.read_word().to_lower();
+					self.skip_spaces();
+					let colour_opt = self.parse_extended_colour();
+					self.read_to_end_of_line();
+					self.skip_new_line();
+
+					let current_colours = status.extended_colours.unwrap_or_default();
+					let extended_colours = match (layer.data.as_slice(), colour_opt) {
+						(b"", Some(colour)) => Some(ExtendedColours{foreground: Some(colour), ..current_colours}),
+						(b"back", Some(colour)) => Some(ExtendedColours{background: Some(colour), ..current_colours}),
+						_ => None,
+					};
+					actions.push(Action::SetExtendedColours{status_index, extended_colours});
+
+					// Give the tile the closest standard colour, so the object looks about the
+					// same when the world is saved and played in DOS ZZT.
+					let x = status.location_x as i16;
+					let y = status.location_y as i16;
+					if let (Some(tile), Some(extended_colours)) = (sim.get_tile(x, y), extended_colours) {
+						actions.push(Action::SetColour{x, y, colour: extended_colours.to_standard_colour(tile.colour)});
+					}
+				}
 				b"cycle" => {
 					self.skip_spaces();
 					if let Ok(cycle_num) = self.parse_number() {
@@ -1111,12 +1175,8 @@ impl<'code> OopParser<'code> {
 					self.pos = -1;
 					//outcome.dont_progress = true;
 				}
-				b"endgame" => {
-					actions.push(Action::ModifyPlayerItem{
-						item_type: PlayerItemType::Health,
-						offset: -sim.world_header.player_health,
-						require_exact_amount: false,
-					});
+				b"endgame" if sim.settings.compatibility_version.has_endgame_command() => {
+					actions.push(Action::EndGame);
 					self.read_to_end_of_line();
 					self.skip_new_line();
 					// Surprisingly, #endgame actually keeps executing the script, so you can make a
@@ -1133,13 +1193,13 @@ impl<'code> OopParser<'code> {
 						if item_type == PlayerItemType::Time {
 							actions.push(Action::ModifyPlayerItem{
 								item_type,
-								offset: -(give_num as i16),
+								offset: -(give_num as i32),
 								require_exact_amount: true,
 							});
 						} else {
 							actions.push(Action::ModifyPlayerItem{
 								item_type,
-								offset: give_num as i16,
+								offset: give_num as i32,
 								require_exact_amount: false,
 							});
 						}
@@ -1202,13 +1262,19 @@ impl<'code> OopParser<'code> {
 				b"play" => {
 					let notes = self.read_to_end_of_line();
 					self.skip_new_line();
-					actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(&notes.data), SoundPriority::Music)));
+					let (note_events, notes_warnings) = process_notes_string_with_warnings(&notes.data);
+					for notes_warning in notes_warnings {
+						actions.push(Action::SendBoardMessage(BoardMessage::EngineLog(LogLevel::Warning,
+							format!("The status at index {} ({}, {}) played \"{}\": {}", status_index, status.location_x, status.location_y, notes.to_string(false), notes_warning.description()))));
+					}
+					let sound_entries = note_events.iter().map(NoteEvent::to_sound_entry).collect();
+					actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(sound_entries, SoundPriority::Music)));
 				}
 				b"put" => {
 					self.skip_spaces();
 					let direction = self.parse_direction(status, sim)?;
 					self.skip_spaces();
-					let put_desc = self.parse_tile_type_desc()?;
+					let put_desc = self.parse_tile_type_desc(sim)?;
 					self.read_to_end_of_line();
 					self.skip_new_line();
 
@@ -1277,7 +1343,7 @@ impl<'code> OopParser<'code> {
 					self.read_to_end_of_line();
 					self.skip_new_line();
 
-					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::RestoreZztStyle, status_index, actions);
+					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::RestoreZztStyle, status_index, sim, actions);
 				}
 				b"send" => {
 					self.skip_spaces();
@@ -1285,7 +1351,7 @@ impl<'code> OopParser<'code> {
 					self.read_to_end_of_line();
 					self.skip_new_line();
 
-					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::Jump, status_index, actions);
+					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::Jump, status_index, sim, actions);
 				}
 				b"set" => {
 					self.skip_spaces();
@@ -1305,10 +1371,10 @@ impl<'code> OopParser<'code> {
 
 					let shoot_x = status.location_x as i16 + shoot_step_x;
 					let shoot_y = status.location_y as i16 + shoot_step_y;
-					let fired_shot = sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, false, false, actions);
+					let fired_shot = sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, false, ShotSource::Enemy, actions);
 					if fired_shot {
 						actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(
-							process_notes_string(b"tc-f#"), SoundPriority::Level(2))));
+							process_notes_string_to_sound_entries(b"tc-f#"), SoundPriority::Level(2))));
 					}
 					outcome.finish_immediately = true;
 				}
@@ -1326,13 +1392,13 @@ impl<'code> OopParser<'code> {
 						if item_type == PlayerItemType::Time {
 							actions.push(Action::ModifyPlayerItem{
 								item_type,
-								offset: take_num as i16,
+								offset: take_num as i32,
 								require_exact_amount: false,
 							});
 						} else {
 							actions.push(Action::ModifyPlayerItem{
 								item_type,
-								offset: -(take_num as i16),
+								offset: -(take_num as i32),
 								require_exact_amount: true,
 							});
 						}
@@ -1356,7 +1422,7 @@ impl<'code> OopParser<'code> {
 						let dest_x = status.location_x as i16 + offset_x;
 						let dest_y = status.location_y as i16 + offset_y;
 						// param2 starts at 255 for #put [dir] star, but starts at 99 for #throwstar.
-						sim.make_shoot_actions(dest_x, dest_y, offset_x, offset_y, true, false, actions);
+						sim.make_shoot_actions(dest_x, dest_y, offset_x, offset_y, true, ShotSource::Enemy, actions);
 					}
 				}
 				b"try" => {
@@ -1404,19 +1470,23 @@ impl<'code> OopParser<'code> {
 					self.read_to_end_of_line();
 					self.skip_new_line();
 
-					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::Zap, status_index, actions);
+					self.apply_message_desc_label_operation(send_message_desc, LabelOperation::Zap, status_index, sim, actions);
 				}
 				_ => {
-					// TODO: Check what happens when the label doesn't exist. Does it skip the new
-					// line char?
-
 					// The name after the hash can only be a "myself" message at this point, so
 					// directly jumping to label is fine.
 					let jump_worked = self.jump_to_label(&command_name);
-					if !jump_worked {
-						// TODO "Unknown command: {:?}"
-						let mut error_string = DosString::from_slice(b"Unknown command: ");
-						error_string += &command_name.data;
+					if jump_worked {
+						sim.record_message_delivery(Some(status_index), status_index, &command_name, LabelOperation::Jump);
+					} else {
+						let arguments = self.read_to_end_of_line();
+						if sim.ruleset.oop_command(&command_name, &arguments, status_index, sim, actions) {
+							self.skip_new_line();
+							return Ok(outcome);
+						}
+						// Like DOS ZZT, the program halts, so it doesn't matter where `pos` is.
+						let mut error_string = DosString::from_slice(b"Bad command ");
+						error_string += &command_name.to_upper().data;
 						return Err(error_string);
 					}
 				}
@@ -1473,6 +1543,35 @@ impl<'code> OopParser<'code> {
 		None
 	}
 
+	/// Get every label in the code that can currently be sent to (not zapped ones), in the order
+	/// they appear, with the position that `find_label` would jump to for each of them. Labels on
+	/// the first line are skipped because they can't be sent to (see `find_label`).
+	pub fn labels(&self) -> Vec<(DosString, i16)> {
+		let mut labels = vec![];
+		let mut parser = OopParser::new(self.code.as_ref(), 0);
+
+		while parser.pos < parser.code.len() as i16 {
+			parser.read_to_end_of_line();
+			parser.skip_new_line();
+
+			if let OopOperator::Label = parser.parse_operator() {
+				let mut label = DosString::new();
+				while parser.pos < parser.code.len() as i16 {
+					let c = parser.code[parser.pos as usize];
+					if c.is_ascii_alphanumeric() || c == b'_' {
+						label.push(c);
+						parser.pos += 1;
+					} else {
+						break;
+					}
+				}
+				parser.read_to_end_of_line();
+				labels.push((label, parser.pos));
+			}
+		}
+		labels
+	}
+
 	// Returns true if the label was found and jumped to.
 	pub fn jump_to_label(&mut self, label: &DosString) -> bool {
 		if let Some(label_pos) = self.find_label(label) {
@@ -0,0 +1,212 @@
+use crate::board_simulator::*;
+use crate::console::*;
+
+use zzt_file_format::ElementType;
+
+use num::FromPrimitive;
+
+/// The RGB values used to display each of the 16 `ConsoleColour`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+	/// The (red, green, blue) values, indexed by `ConsoleColour as usize`.
+	pub colours: [(u8, u8, u8); 16],
+}
+
+impl Palette {
+	/// The standard EGA palette, as returned by `ConsoleColour::to_rgb`.
+	pub fn standard() -> Palette {
+		let mut colours = [(0, 0, 0); 16];
+		for (index, colour) in colours.iter_mut().enumerate() {
+			*colour = ConsoleColour::from_u8(index as u8).unwrap().to_rgb();
+		}
+		Palette {
+			colours,
+		}
+	}
+
+	/// A palette where the dark colours are brighter and the light colours are fully saturated, so
+	/// that everything stands out more against the black background.
+	pub fn high_contrast() -> Palette {
+		Palette {
+			colours: [
+				(0x00, 0x00, 0x00),
+				(0x30, 0x30, 0xFF),
+				(0x00, 0xC0, 0x00),
+				(0x00, 0xC0, 0xC0),
+				(0xD0, 0x00, 0x00),
+				(0xC0, 0x00, 0xC0),
+				(0xC0, 0x70, 0x00),
+				(0xC8, 0xC8, 0xC8),
+				(0x80, 0x80, 0x80),
+				(0x80, 0x80, 0xFF),
+				(0x00, 0xFF, 0x00),
+				(0x00, 0xFF, 0xFF),
+				(0xFF, 0x40, 0x40),
+				(0xFF, 0x40, 0xFF),
+				(0xFF, 0xFF, 0x00),
+				(0xFF, 0xFF, 0xFF),
+			],
+		}
+	}
+
+	/// A palette based on the Okabe-Ito colours, which keeps the red/green pairs distinguishable
+	/// for people with the most common forms of colour blindness.
+	pub fn colour_blind() -> Palette {
+		Palette {
+			colours: [
+				(0x00, 0x00, 0x00),
+				(0x00, 0x48, 0xA0),
+				(0x00, 0x80, 0x60),
+				(0x40, 0x90, 0xC0),
+				(0xB0, 0x50, 0x00),
+				(0x90, 0x50, 0x80),
+				(0xA0, 0x80, 0x00),
+				(0xAA, 0xAA, 0xAA),
+				(0x55, 0x55, 0x55),
+				(0x56, 0xB4, 0xE9),
+				(0x00, 0x9E, 0x73),
+				(0x9E, 0xE0, 0xFF),
+				(0xD5, 0x5E, 0x00),
+				(0xCC, 0x79, 0xA7),
+				(0xF0, 0xE4, 0x42),
+				(0xFF, 0xFF, 0xFF),
+			],
+		}
+	}
+
+	/// Get the (red, green, blue) values for the given `colour` in this palette.
+	pub fn to_rgb(&self, colour: ConsoleColour) -> (u8, u8, u8) {
+		self.colours[colour as usize]
+	}
+}
+
+/// Replaces the appearance of every tile of a particular `element_type` on the board. Any value
+/// that is None is left as the engine rendered it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementOverride {
+	pub element_type: ElementType,
+	pub char_code: Option<u8>,
+	pub background: Option<ConsoleColour>,
+	pub foreground: Option<ConsoleColour>,
+}
+
+/// Get the character used to mark a key or door with the given key index (0 is blue, 6 is white).
+fn key_marker_char(key_index: u8) -> Option<u8> {
+	b"BGCRPYW".get(key_index as usize).cloned()
+}
+
+/// Options for changing how the board is displayed, to make worlds that rely on colour playable
+/// for people who can't easily tell the colours apart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayOptions {
+	/// The palette the front-end should use to turn `ConsoleColour`s into RGB values.
+	pub palette: Palette,
+	/// When true, keys and doors are drawn with the first letter of their colour name instead of
+	/// their usual character, so they can be matched up without relying on colour.
+	pub mark_key_colours: bool,
+	/// Appearance overrides for specific element types. These are applied after the key markers,
+	/// and later entries take priority over earlier ones.
+	pub element_overrides: Vec<ElementOverride>,
+}
+
+impl Default for DisplayOptions {
+	fn default() -> DisplayOptions {
+		DisplayOptions::new()
+	}
+}
+
+impl DisplayOptions {
+	/// The display options matching the original game.
+	pub fn new() -> DisplayOptions {
+		DisplayOptions {
+			palette: Palette::standard(),
+			mark_key_colours: false,
+			element_overrides: vec![],
+		}
+	}
+
+	/// Display options using the `Palette::high_contrast` palette.
+	pub fn high_contrast() -> DisplayOptions {
+		DisplayOptions {
+			palette: Palette::high_contrast(),
+			.. DisplayOptions::new()
+		}
+	}
+
+	/// Display options using the `Palette::colour_blind` palette, with keys and doors marked with
+	/// the letter of their colour.
+	pub fn colour_blind() -> DisplayOptions {
+		DisplayOptions {
+			palette: Palette::colour_blind(),
+			mark_key_colours: true,
+			.. DisplayOptions::new()
+		}
+	}
+
+	/// Add an override for the appearance of all tiles of the given `element_type`.
+	pub fn set_element_override(&mut self, element_override: ElementOverride) {
+		self.element_overrides.retain(|existing| existing.element_type != element_override.element_type);
+		self.element_overrides.push(element_override);
+	}
+
+	/// True if `apply` would change anything in the console.
+	pub fn modifies_board(&self) -> bool {
+		self.mark_key_colours || !self.element_overrides.is_empty()
+	}
+
+	/// Post-process the board area of `console_state` (which must already have the board drawn in
+	/// it) by applying the key markers and element overrides. `is_visible` is called with the
+	/// screen x/y position of each tile, and should return false for tiles that are hidden by the
+	/// darkness, so they aren't given away by the overrides.
+	pub fn apply(&self, sim: &BoardSimulator, console_state: &mut ConsoleState, is_visible: &dyn Fn(usize, usize) -> bool) {
+		if !self.modifies_board() {
+			return;
+		}
+
+		for y in 0 .. BOARD_HEIGHT - 2 {
+			for x in 0 .. BOARD_WIDTH - 2 {
+				let tile = sim.get_tile(x as i16 + 1, y as i16 + 1).unwrap();
+				let element_type = if let Some(element_type) = ElementType::from_u8(tile.element_id) {
+					element_type
+				} else {
+					continue;
+				};
+
+				if !is_visible(x, y) {
+					continue;
+				}
+
+				let screen_char = console_state.get_char_mut(x, y);
+
+				if self.mark_key_colours {
+					let key_index = match element_type {
+						ElementType::Key => tile.colour.checked_sub(9),
+						ElementType::Door => ((tile.colour & 0xf0) >> 4).checked_sub(1),
+						_ => None,
+					};
+
+					if let Some(marker) = key_index.and_then(key_marker_char) {
+						screen_char.char_code = marker;
+					}
+				}
+
+				for element_override in &self.element_overrides {
+					if element_override.element_type == element_type {
+						if let Some(char_code) = element_override.char_code {
+							screen_char.char_code = char_code;
+						}
+						if let Some(background) = element_override.background {
+							screen_char.background = background;
+						}
+						if let Some(foreground) = element_override.foreground {
+							screen_char.foreground = foreground;
+						}
+						if element_override.background.is_some() || element_override.foreground.is_some() {
+							screen_char.extended_colours = None;
+						}
+					}
+				}
+			}
+		}
+	}
+}
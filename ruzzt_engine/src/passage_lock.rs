@@ -0,0 +1,47 @@
+use zzt_file_format::dosstring::DosString;
+
+/// What a locked passage needs before the player can go through it. Passages never run their
+/// code, so with `ExtensionLevel::Ruzzt` a passage can be locked by putting one of these commands
+/// in its code, and DOS ZZT just ignores it:
+///
+/// - `#lock NAME`: The passage can only be used while the flag `NAME` is set.
+/// - `#password TEXT`: The player has to type `TEXT` into the side bar to use the passage. Case
+///   doesn't matter. Once it is typed in, the passage's code is cleared so it stays unlocked.
+///
+/// See `SimulatorSettings::locked_passages_enabled`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassageLock {
+	/// The name of the flag that has to be set.
+	Flag(DosString),
+	/// The password that has to be typed in.
+	Password(DosString),
+}
+
+impl PassageLock {
+	/// Get the lock from the first `#lock` or `#password` line of a passage's `code`, or None if it
+	/// doesn't have one.
+	pub fn from_code(code: &DosString) -> Option<PassageLock> {
+		for line in code.data.split(|c| *c == b'\r') {
+			let mut words = line.splitn(2, |c| *c == b' ');
+			let command = words.next().unwrap_or(&[]).to_ascii_lowercase();
+			let argument = DosString::from_slice(words.next().unwrap_or(&[]).trim_ascii());
+			if argument.is_empty() {
+				continue;
+			}
+			match command.as_slice() {
+				b"#lock" => return Some(PassageLock::Flag(argument.to_upper())),
+				b"#password" => return Some(PassageLock::Password(argument)),
+				_ => {}
+			}
+		}
+		None
+	}
+
+	/// True if `text` is the password of a `Password` lock, ignoring case.
+	pub fn password_matches(&self, text: &DosString) -> bool {
+		match self {
+			PassageLock::Flag(_) => false,
+			PassageLock::Password(password) => password.data.eq_ignore_ascii_case(&text.data),
+		}
+	}
+}
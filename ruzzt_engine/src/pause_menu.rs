@@ -0,0 +1,142 @@
+use crate::board_message::*;
+use crate::console::*;
+use crate::event::*;
+
+/// The options that can be picked in the pause menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseMenuItem {
+	Resume,
+	Save,
+	Restore,
+	Settings,
+	Quit,
+}
+
+impl PauseMenuItem {
+	/// All the items, in the order they are listed in the menu.
+	pub fn all() -> [PauseMenuItem; 5] {
+		[PauseMenuItem::Resume, PauseMenuItem::Save, PauseMenuItem::Restore, PauseMenuItem::Settings, PauseMenuItem::Quit]
+	}
+
+	/// The text shown for the item in the menu.
+	pub fn label(self) -> &'static [u8] {
+		match self {
+			PauseMenuItem::Resume => b"Resume",
+			PauseMenuItem::Save => b"Save game",
+			PauseMenuItem::Restore => b"Restore game",
+			PauseMenuItem::Settings => b"Settings",
+			PauseMenuItem::Quit => b"Quit",
+		}
+	}
+
+	/// The board message that does what the item says, which is sent after the menu is closed.
+	/// Resume doesn't need to do anything besides closing the menu.
+	fn board_message(self) -> Option<BoardMessage> {
+		match self {
+			PauseMenuItem::Resume => None,
+			PauseMenuItem::Save => Some(BoardMessage::OpenSaveGameInput),
+			PauseMenuItem::Restore => Some(BoardMessage::OpenSaveSelection),
+			PauseMenuItem::Settings => Some(BoardMessage::OpenSettings),
+			PauseMenuItem::Quit => Some(BoardMessage::OpenEndGameConfirmation),
+		}
+	}
+}
+
+/// The width of the menu box, including its border.
+const MENU_WIDTH: usize = 20;
+/// The x position on the screen of the left side of the menu box.
+const MENU_X: usize = 30 - MENU_WIDTH / 2;
+/// The y position on the screen of the top of the menu box.
+const MENU_Y: usize = 7;
+
+/// The state of the in-game pause menu, which lets every action that usually needs its own key be
+/// picked with just the direction keys, enter and escape (eg. from a gamepad). It is drawn over the
+/// middle of the board.
+#[derive(Clone)]
+pub struct PauseMenuState {
+	/// The index within `PauseMenuItem::all()` of the highlighted item.
+	current_index: usize,
+}
+
+impl Default for PauseMenuState {
+	fn default() -> PauseMenuState {
+		PauseMenuState::new()
+	}
+}
+
+impl PauseMenuState {
+	/// Make a new pause menu with "Resume" highlighted.
+	pub fn new() -> PauseMenuState {
+		PauseMenuState {
+			current_index: 0,
+		}
+	}
+
+	/// Get the highlighted item.
+	pub fn current_item(&self) -> PauseMenuItem {
+		PauseMenuItem::all()[self.current_index]
+	}
+
+	/// Execute a single simulation step on the pause menu, with the given input `event`.
+	pub fn step(&mut self, event: Event) -> Vec<BoardMessage> {
+		let mut board_messages = vec![];
+		let item_count = PauseMenuItem::all().len();
+		match event {
+			Event::Escape => {
+				board_messages.push(BoardMessage::ClosePauseMenu);
+			}
+			Event::Enter => {
+				board_messages.push(BoardMessage::ClosePauseMenu);
+				board_messages.extend(self.current_item().board_message());
+			}
+			Event::Up => {
+				self.current_index = (self.current_index + item_count - 1) % item_count;
+			}
+			Event::Down => {
+				self.current_index = (self.current_index + 1) % item_count;
+			}
+			_ => {}
+		}
+		board_messages
+	}
+
+	/// Renders the pause menu over the middle of the board.
+	pub fn draw_pause_menu(&self, console_state: &mut ConsoleState) {
+		let bg = ConsoleColour::Blue;
+		let items = PauseMenuItem::all();
+		let menu_height = items.len() + 4;
+
+		for y in 0 .. menu_height {
+			for x in 0 .. MENU_WIDTH {
+				let char_code = match (x, y) {
+					(0, 0) => 0xc9,
+					(x, 0) if x == MENU_WIDTH - 1 => 0xbb,
+					(0, y) if y == menu_height - 1 => 0xc8,
+					(x, y) if x == MENU_WIDTH - 1 && y == menu_height - 1 => 0xbc,
+					(0, _) => 0xba,
+					(x, _) if x == MENU_WIDTH - 1 => 0xba,
+					(_, 0) => 0xcd,
+					(_, y) if y == menu_height - 1 => 0xcd,
+					_ => 0,
+				};
+				*console_state.get_char_mut(MENU_X + x, MENU_Y + y) = ConsoleChar::new(char_code, bg, ConsoleColour::White);
+			}
+		}
+
+		let title = b"Paused";
+		console_state.draw_text_at(MENU_X + (MENU_WIDTH - title.len()) / 2, MENU_Y + 1, title, bg, ConsoleColour::Yellow);
+
+		for (index, item) in items.iter().enumerate() {
+			let y = MENU_Y + 3 + index;
+			if index == self.current_index {
+				for x in MENU_X + 2 .. MENU_X + MENU_WIDTH - 2 {
+					*console_state.get_char_mut(x, y) = ConsoleChar::new(0, ConsoleColour::Cyan, ConsoleColour::Black);
+				}
+				*console_state.get_char_mut(MENU_X + 2, y) = ConsoleChar::new(0x10, ConsoleColour::Cyan, ConsoleColour::Black);
+				console_state.draw_text_at(MENU_X + 4, y, item.label(), ConsoleColour::Cyan, ConsoleColour::Black);
+			} else {
+				console_state.draw_text_at(MENU_X + 4, y, item.label(), bg, ConsoleColour::LightCyan);
+			}
+		}
+	}
+}
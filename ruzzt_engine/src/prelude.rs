@@ -0,0 +1,10 @@
+pub use crate::board_message::{BoardMessage, LogLevel};
+pub use crate::board_simulator::{BoardPos, BoardSimulator, ItemOverflowPolicy, KeyColourPolicy, SimPos};
+pub use crate::console::{ConsoleChar, ConsoleColour, ConsoleState, ScreenMode, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use crate::engine::{RuzztEngine, StartState};
+pub use crate::event::{Event, TypingEvent};
+pub use crate::palette::DisplayOptions;
+pub use crate::sounds::{SoundEntry, SoundPriority};
+
+pub use zzt_file_format::World;
+pub use zzt_file_format::dosstring::DosString;
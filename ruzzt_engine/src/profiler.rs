@@ -0,0 +1,64 @@
+use zzt_file_format::ElementType;
+
+use num::FromPrimitive;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// The time spent processing the statuses of one element type.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElementStepTime {
+	/// The number of times a status of this element type was processed.
+	pub status_count: usize,
+	/// The total time spent processing them, including applying the actions they made.
+	pub total_time: Duration,
+}
+
+/// Counters for working out what makes a world slow to simulate. These are collected by the
+/// `BoardSimulator` while profiling is turned on (see `RuzztEngine::set_profiling_enabled`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepProfile {
+	/// The number of complete simulation steps the counters cover.
+	pub step_count: usize,
+	/// The time spent processing statuses, keyed by the element ID of the tile each status was on.
+	pub element_step_times: BTreeMap<u8, ElementStepTime>,
+	/// The number of actions applied with `BoardSimulator::apply_action`.
+	pub actions_applied: usize,
+	/// The number of OOP commands executed.
+	pub oop_operations: usize,
+	/// The number of times an `ActionContinuation` was continued.
+	pub continuation_iterations: usize,
+}
+
+impl StepProfile {
+	/// Add the time taken to process a status on a tile with the given `element_id`.
+	pub fn add_status_time(&mut self, element_id: u8, time: Duration) {
+		let element_step_time = self.element_step_times.entry(element_id).or_default();
+		element_step_time.status_count += 1;
+		element_step_time.total_time += time;
+	}
+}
+
+impl fmt::Display for StepProfile {
+	/// Write the counters as a table, with the slowest element types first.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "{:<16} {:>10} {:>12} {:>10}", "Element", "Statuses", "Total (ms)", "Each (us)")?;
+
+		let mut element_step_times: Vec<_> = self.element_step_times.iter().collect();
+		element_step_times.sort_by_key(|(_, element_step_time)| std::cmp::Reverse(element_step_time.total_time));
+		for (element_id, element_step_time) in element_step_times {
+			let name = match ElementType::from_u8(*element_id) {
+				Some(element_type) => format!("{:?}", element_type),
+				None => format!("Unknown ({})", element_id),
+			};
+			let each_us = element_step_time.total_time.as_secs_f64() * 1_000_000. / element_step_time.status_count.max(1) as f64;
+			writeln!(f, "{:<16} {:>10} {:>12.3} {:>10.2}", name, element_step_time.status_count, element_step_time.total_time.as_secs_f64() * 1000., each_us)?;
+		}
+
+		writeln!(f)?;
+		writeln!(f, "Steps: {}", self.step_count)?;
+		writeln!(f, "Actions applied: {}", self.actions_applied)?;
+		writeln!(f, "OOP operations: {}", self.oop_operations)?;
+		write!(f, "Continuation iterations: {}", self.continuation_iterations)
+	}
+}
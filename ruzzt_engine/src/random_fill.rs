@@ -0,0 +1,112 @@
+use crate::board_simulator::{default_status_element_limit, BoardPos, BOARD_HEIGHT, BOARD_WIDTH};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde_derive::{Serialize, Deserialize};
+use zzt_file_format::{Board, BoardTile, ElementType, StatusElement, WorldType};
+
+/// One kind of tile that `random_fill` scatters over a board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomFillEntry {
+	/// The tile to place.
+	pub tile: BoardTile,
+	/// The status element to give each placed tile (eg. for creatures), or None for tiles without
+	/// one. Its location is set to where the tile is placed.
+	pub status: Option<StatusElement>,
+	/// The chance (from 0 to 1) that each empty tile becomes this tile. For example, 0.05 fills
+	/// about 5% of the empty tiles.
+	pub density: f64,
+}
+
+impl RandomFillEntry {
+	/// Make an entry that places `tile` without a status element.
+	pub fn new(tile: BoardTile, density: f64) -> RandomFillEntry {
+		RandomFillEntry {
+			tile,
+			status: None,
+			density,
+		}
+	}
+
+	/// Make an entry that places `tile` with a copy of `status` on each one.
+	pub fn with_status(tile: BoardTile, status: StatusElement, density: f64) -> RandomFillEntry {
+		RandomFillEntry {
+			tile,
+			status: Some(status),
+			density,
+		}
+	}
+}
+
+/// What `random_fill` scatters over a board, and how.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomFillOptions {
+	/// The kinds of tiles to place. Their densities must not add up to more than 1.
+	pub entries: Vec<RandomFillEntry>,
+	/// The seed for the random number generator, so the same options always fill a board the same
+	/// way.
+	pub seed: u64,
+	/// Tiles with status elements are only placed while the board has fewer status elements than
+	/// this (including the player).
+	pub status_element_limit: usize,
+}
+
+impl RandomFillOptions {
+	/// Make options that place the given `entries`, with the same status element limit as DOS ZZT.
+	pub fn new(entries: Vec<RandomFillEntry>, seed: u64) -> RandomFillOptions {
+		RandomFillOptions {
+			entries,
+			seed,
+			status_element_limit: default_status_element_limit(WorldType::Zzt),
+		}
+	}
+}
+
+/// Scatter the tiles from `options` at random over the empty tiles of `board`, to quickly make
+/// boards for testing or as a starting point for editing. Tiles that aren't empty, or that have a
+/// status element on them, are left alone. Returns the number of tiles that were placed.
+pub fn random_fill(board: &mut Board, options: &RandomFillOptions) -> Result<usize, String> {
+	let total_density: f64 = options.entries.iter().map(|entry| entry.density).sum();
+	if options.entries.iter().any(|entry| entry.density < 0.) || total_density > 1. {
+		return Err(format!("The densities must be positive and add up to at most 1, not {}", total_density));
+	}
+
+	let mut rng = StdRng::seed_from_u64(options.seed);
+	let mut placed_count = 0;
+	for y in 0 .. BOARD_HEIGHT as i16 - 2 {
+		for x in 0 .. BOARD_WIDTH as i16 - 2 {
+			let board_pos = BoardPos::new(x, y);
+			let sim_pos = board_pos.to_sim_pos();
+			let tile_index = board_pos.tile_index().unwrap();
+			let has_status = board.status_elements.iter().any(|status| status.location_x as i16 == sim_pos.x && status.location_y as i16 == sim_pos.y);
+			if board.tiles[tile_index].element_id != ElementType::Empty as u8 || has_status {
+				continue;
+			}
+
+			// Pick an entry by where the roll lands among the densities, so eg. entries with
+			// densities 0.1 and 0.2 are picked for rolls under 0.1 and from 0.1 to 0.3.
+			let roll: f64 = rng.gen();
+			let mut density_so_far = 0.;
+			let picked_entry = options.entries.iter().find(|entry| {
+				density_so_far += entry.density;
+				roll < density_so_far
+			});
+
+			if let Some(entry) = picked_entry {
+				if let Some(ref status) = entry.status {
+					if board.status_elements.len() >= options.status_element_limit {
+						continue;
+					}
+					board.status_elements.push(StatusElement {
+						location_x: sim_pos.x as u8,
+						location_y: sim_pos.y as u8,
+						.. status.clone()
+					});
+				}
+				board.tiles[tile_index] = entry.tile;
+				placed_count += 1;
+			}
+		}
+	}
+	Ok(placed_count)
+}
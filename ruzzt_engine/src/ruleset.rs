@@ -0,0 +1,143 @@
+use crate::behaviour::Action;
+use crate::board_simulator::BoardSimulator;
+use crate::console::ConsoleState;
+use crate::direction::Direction;
+use crate::engine::super_zzt_element_type_to_char_code;
+use crate::side_bar::{SideBar, SideBarModel};
+use crate::zzt_behaviours;
+
+use zzt_file_format::{ElementType, SuperZztElementType, WorldType};
+use zzt_file_format::dosstring::DosString;
+
+use num::FromPrimitive;
+
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// Everything that makes up the rules of a game running on the engine: what each element ID is,
+/// how the elements behave, how the side bar is laid out and any extra OOP commands. The stock
+/// rules are `ZztRuleset` and `SuperZztRuleset`, and the engine picks between them with the world
+/// type unless another ruleset is set with `RuzztEngine::set_ruleset`. This lets total conversions
+/// (eg. ZZT with diagonal movement) live in their own crates instead of forks of the engine.
+///
+/// Rulesets that change a few things can wrap one of the stock rulesets and forward the rest of
+/// the methods to it.
+pub trait RulesetPlugin: Debug {
+	/// The name of the ruleset, for showing to the player.
+	fn name(&self) -> &str;
+
+	/// Get the `ElementType` that `element_id` is drawn as, or None if it isn't an element this
+	/// ruleset knows about (these are drawn as blank).
+	fn element_type(&self, element_id: u8) -> Option<ElementType>;
+
+	/// Get the ID that this ruleset uses for `element_type`, for putting new tiles of that type on
+	/// the board (eg. the stars and bullets that get shot). Returns None if the ruleset has no
+	/// element like `element_type`.
+	fn element_id(&self, element_type: ElementType) -> Option<u8> {
+		Some(element_type as u8)
+	}
+
+	/// Get the direction that a tile with the given `element_id` carries the player standing on it,
+	/// or None if it doesn't carry the player anywhere.
+	fn current_direction(&self, _element_id: u8) -> Option<Direction> {
+		None
+	}
+
+	/// Get the character that `element_id` is always drawn with in the tile's colour, for elements
+	/// that aren't drawn like any `ElementType`. Returns None to draw the element as its
+	/// `element_type`.
+	fn element_char_code(&self, _element_id: u8) -> Option<u8> {
+		None
+	}
+
+	/// Set the behaviours of every element ID in `sim`, which starts with none.
+	fn load_behaviours(&self, sim: &mut BoardSimulator);
+
+	/// Add the actions that hurt the player to `actions`, for everything that hurts them (see
+	/// `BoardSimulator::add_player_hurt_actions`). The stock rulesets hurt the player like DOS ZZT
+	/// does, with `BoardSimulator::add_stock_player_hurt_actions`.
+	fn add_player_hurt_actions(&self, sim: &BoardSimulator, actions: &mut Vec<Action>) {
+		sim.add_stock_player_hurt_actions(actions);
+	}
+
+	/// Draw the side bar showing `model`. The arguments are the same as for
+	/// `SideBar::draw_side_bar`, which draws the stock side bar.
+	fn draw_side_bar(&self, side_bar: &SideBar, model: &SideBarModel, in_title_screen: bool, console_state: &mut ConsoleState, cycle: usize, game_cycle: usize) {
+		side_bar.draw_side_bar(model, in_title_screen, console_state, cycle, game_cycle);
+	}
+
+	/// Run the OOP command `#command_name` for the status element at `status_index`, where
+	/// `arguments` is the rest of the line after the command name. This is only called for commands
+	/// that the stock OOP doesn't have and that aren't labels in the object's code. Returns true if
+	/// the command was handled, after pushing the actions it does to `actions`, or false to give
+	/// the usual "Bad command" error.
+	fn oop_command(&self, _command_name: &DosString, _arguments: &DosString, _status_index: usize, _sim: &BoardSimulator, _actions: &mut Vec<Action>) -> bool {
+		false
+	}
+}
+
+/// The rules of ZZT.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZztRuleset;
+
+impl RulesetPlugin for ZztRuleset {
+	fn name(&self) -> &str {
+		"ZZT"
+	}
+
+	fn element_type(&self, element_id: u8) -> Option<ElementType> {
+		ElementType::from_u8(element_id)
+	}
+
+	fn load_behaviours(&self, sim: &mut BoardSimulator) {
+		zzt_behaviours::load_zzt_behaviours(sim);
+	}
+}
+
+/// The rules of Super ZZT, which has its own element IDs (see `SuperZztElementType`).
+///
+/// NOTE: The simulator is the size of a ZZT board, so only the top left 60x25 tiles of each Super
+/// ZZT board (which are 96x80) are played, and there's no camera to scroll around a bigger board.
+/// The elements that Super ZZT shares with ZZT also behave like they do in ZZT.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SuperZztRuleset;
+
+impl RulesetPlugin for SuperZztRuleset {
+	fn name(&self) -> &str {
+		"Super ZZT"
+	}
+
+	fn element_type(&self, element_id: u8) -> Option<ElementType> {
+		SuperZztElementType::from_u8(element_id).and_then(|ty| ty.to_element_type())
+	}
+
+	fn element_id(&self, element_type: ElementType) -> Option<u8> {
+		(0 ..= 255).find(|&element_id| self.element_type(element_id) == Some(element_type))
+	}
+
+	fn current_direction(&self, element_id: u8) -> Option<Direction> {
+		match SuperZztElementType::from_u8(element_id)? {
+			SuperZztElementType::WaterN => Some(Direction::North),
+			SuperZztElementType::WaterS => Some(Direction::South),
+			SuperZztElementType::WaterW => Some(Direction::West),
+			SuperZztElementType::WaterE => Some(Direction::East),
+			_ => None,
+		}
+	}
+
+	fn element_char_code(&self, element_id: u8) -> Option<u8> {
+		SuperZztElementType::from_u8(element_id).and_then(super_zzt_element_type_to_char_code)
+	}
+
+	fn load_behaviours(&self, sim: &mut BoardSimulator) {
+		zzt_behaviours::load_super_zzt_behaviours(sim);
+	}
+}
+
+/// Get the stock ruleset for worlds of the given `world_type`.
+pub fn builtin_ruleset(world_type: WorldType) -> Rc<dyn RulesetPlugin> {
+	match world_type {
+		WorldType::Zzt => Rc::new(ZztRuleset),
+		WorldType::SuperZzt => Rc::new(SuperZztRuleset),
+	}
+}
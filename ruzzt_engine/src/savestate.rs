@@ -0,0 +1,168 @@
+use crate::board_message::OneTimeNotification;
+use crate::caption::CaptionState;
+use crate::scroll::ScrollState;
+use crate::sounds::SoundQueue;
+
+use serde_derive::{Serialize, Deserialize};
+use zzt_file_format::World;
+use zzt_file_format::padding::ParseOptions;
+
+use std::collections::BTreeSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// The number of savestate slots in a `RuzztEngine`.
+pub const SAVESTATE_SLOT_COUNT: usize = 10;
+
+/// The version of the format written by `Savestate::write_ui_state`. This goes up whenever the
+/// format changes in a way that older versions of RUZZT can't read. Fields that are added later
+/// have defaults, so files written by older versions can still be read.
+pub const SAVESTATE_FORMAT_VERSION: u32 = 1;
+
+/// The bytes at the start of a compressed save (see `compress_save`). World files start with a
+/// negative 16-bit number, so they can never start with these.
+pub const COMPRESSED_SAVE_MAGIC: &[u8; 4] = b"RZSZ";
+
+/// The zlib compression level used for compressed saves, from 0 (none) to 10 (smallest).
+const COMPRESSED_SAVE_LEVEL: u8 = 6;
+
+/// Put the bytes of a save file (eg. from `World::write`) in a compressed save container, which is
+/// `COMPRESSED_SAVE_MAGIC` followed by the bytes compressed with zlib. Most of a world is runs of
+/// the same tiles and padding, so this makes savestates of big worlds much smaller.
+pub fn compress_save(bytes: &[u8]) -> Vec<u8> {
+	let mut compressed = COMPRESSED_SAVE_MAGIC.to_vec();
+	compressed.extend(miniz_oxide::deflate::compress_to_vec_zlib(bytes, COMPRESSED_SAVE_LEVEL));
+	compressed
+}
+
+/// Get the bytes of a save file back out of a compressed save container (see `compress_save`).
+/// Bytes that aren't in a container are returned as they are, so saves can be read the same way
+/// whether they were compressed or not.
+pub fn decompress_save(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+	match bytes.strip_prefix(COMPRESSED_SAVE_MAGIC) {
+		Some(compressed) => miniz_oxide::inflate::decompress_to_vec_zlib(compressed).map_err(|err| format!("Couldn't decompress the save: {}", err)),
+		None => Ok(bytes),
+	}
+}
+
+/// A copy of a game in progress that can be restored later with `RuzztEngine::load_savestate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Savestate {
+	/// The world, including the state of the current board and the player's inventory.
+	pub world: World,
+	/// The `RuzztEngine::global_cycle` when the savestate was made, which decides which statuses
+	/// are processed in the next step.
+	pub global_cycle: usize,
+	/// The one-time notifications that had already been shown, so they aren't shown again.
+	pub shown_one_time_notifications: BTreeSet<OneTimeNotification>,
+	/// The caption that was showing.
+	pub caption_state: Option<CaptionState>,
+	/// The scroll that was open, including which line was selected.
+	pub scroll_state: Option<ScrollState>,
+	/// The sounds that were playing and waiting to be played (see `RuzztEngine::sound_queue`), or
+	/// None for savestates that were made without sound.
+	pub sound_queue: Option<SoundQueue>,
+}
+
+/// Everything in a `Savestate` apart from the world, as it is written by
+/// `Savestate::write_ui_state`.
+#[derive(Serialize, Deserialize)]
+struct SavestateUiState {
+	/// The `SAVESTATE_FORMAT_VERSION` the file was written with.
+	format_version: u32,
+	global_cycle: usize,
+	shown_one_time_notifications: BTreeSet<OneTimeNotification>,
+	caption_state: Option<CaptionState>,
+	scroll_state: Option<ScrollState>,
+	sound_queue: Option<SoundQueue>,
+}
+
+impl Savestate {
+	/// Make a savestate of `world` with no captions, scrolls or sounds showing.
+	pub fn new(world: World, global_cycle: usize) -> Savestate {
+		Savestate {
+			world,
+			global_cycle,
+			shown_one_time_notifications: BTreeSet::new(),
+			caption_state: None,
+			scroll_state: None,
+			sound_queue: None,
+		}
+	}
+
+	/// Write the world in the savestate to `stream`. The same format as a saved game file is used,
+	/// so savestates written to disk can also be loaded like saved games. Everything else is
+	/// written separately with `write_ui_state`.
+	pub fn write(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		self.world.write(stream)
+	}
+
+	/// Write the world in the savestate to `stream` like `write`, but in a compressed save
+	/// container (see `compress_save`). This can't be loaded as a saved game by other ZZT
+	/// implementations.
+	pub fn write_compressed(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		let mut bytes = vec![];
+		self.world.write(&mut bytes)?;
+		stream.write_all(&compress_save(&bytes)).map_err(|err| err.to_string())
+	}
+
+	/// Read a savestate written with `write` or `write_compressed`, working out which one it was
+	/// from the bytes. Everything apart from the world starts out empty until `parse_ui_state` is
+	/// used.
+	pub fn parse(stream: &mut dyn std::io::Read) -> Result<Savestate, String> {
+		let mut bytes = vec![];
+		stream.read_to_end(&mut bytes).map_err(|err| err.to_string())?;
+		let bytes = decompress_save(bytes)?;
+		// The world is read leniently, like worlds that are opened to be played, so boards that DOS
+		// ZZT tolerates (eg. with a status count of -1) don't stop the savestate loading.
+		Ok(Savestate::new(World::parse_with_options(&mut Cursor::new(bytes), ParseOptions::lenient())?, 1))
+	}
+
+	/// Write everything in the savestate apart from the world (the global cycle, notifications,
+	/// caption, scroll and sounds) to `stream` as JSON, with the `SAVESTATE_FORMAT_VERSION`.
+	pub fn write_ui_state(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		let ui_state = SavestateUiState {
+			format_version: SAVESTATE_FORMAT_VERSION,
+			global_cycle: self.global_cycle,
+			shown_one_time_notifications: self.shown_one_time_notifications.clone(),
+			caption_state: self.caption_state.clone(),
+			scroll_state: self.scroll_state.clone(),
+			sound_queue: self.sound_queue.clone(),
+		};
+		serde_json::to_writer_pretty(stream, &ui_state).map_err(|err| err.to_string())
+	}
+
+	/// Read what was written with `write_ui_state` into this savestate. Files written by newer
+	/// versions of the format can't be read.
+	pub fn parse_ui_state(&mut self, stream: &mut dyn std::io::Read) -> Result<(), String> {
+		let ui_state: SavestateUiState = serde_json::from_reader(stream).map_err(|err| err.to_string())?;
+		if ui_state.format_version > SAVESTATE_FORMAT_VERSION {
+			return Err(format!("The savestate format version {} is newer than this version of RUZZT supports ({})",
+				ui_state.format_version, SAVESTATE_FORMAT_VERSION));
+		}
+
+		self.global_cycle = ui_state.global_cycle;
+		self.shown_one_time_notifications = ui_state.shown_one_time_notifications;
+		self.caption_state = ui_state.caption_state;
+		self.scroll_state = ui_state.scroll_state;
+		self.sound_queue = ui_state.sound_queue;
+		Ok(())
+	}
+
+	/// Get the path of the file that the savestate in `slot` is kept in, within `directory`.
+	pub fn slot_file_path(directory: &Path, slot: usize) -> PathBuf {
+		directory.join(format!("SLOT{}.SAV", slot))
+	}
+
+	/// Get the path of the file that the savestate in `slot` is kept in when it is compressed (see
+	/// `RuzztEngine::compress_savestates`), within `directory`.
+	pub fn slot_compressed_file_path(directory: &Path, slot: usize) -> PathBuf {
+		directory.join(format!("SLOT{}.SAVZ", slot))
+	}
+
+	/// Get the path of the file that the rest of the savestate in `slot` (see `write_ui_state`) is
+	/// kept in, within `directory`.
+	pub fn slot_ui_state_file_path(directory: &Path, slot: usize) -> PathBuf {
+		directory.join(format!("SLOT{}.JSON", slot))
+	}
+}
@@ -0,0 +1,75 @@
+use crate::ansi_art::console_chars_to_ansi;
+use crate::console::ConsoleState;
+
+use std::path::{Path, PathBuf};
+
+/// The most screenshots `next_screenshot_path` numbers before it starts overwriting the last one.
+#[cfg(feature = "file-io")]
+const MAX_SCREENSHOT_NUMBER: usize = 99;
+
+/// The kind of file a screenshot is saved as (see `RuzztEngine::screenshot_format`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenshotFormat {
+	/// ANSI art of the whole screen, with colours (see `ansi_art::console_chars_to_ansi`).
+	Ansi,
+	/// The characters on the screen without any colours, one line per row.
+	Text,
+	/// An image of the screen drawn with the DOS font. The engine has no way of drawing images, so
+	/// the front-end has to save these itself.
+	Png,
+}
+
+impl ScreenshotFormat {
+	/// The extension of files saved in this format.
+	pub fn file_extension(self) -> &'static str {
+		match self {
+			ScreenshotFormat::Ansi => "ANS",
+			ScreenshotFormat::Text => "TXT",
+			ScreenshotFormat::Png => "PNG",
+		}
+	}
+
+	/// Get the format of the file at `path` from its extension, ignoring case.
+	pub fn from_path(path: &Path) -> Option<ScreenshotFormat> {
+		let extension = path.extension()?.to_string_lossy().to_ascii_uppercase();
+		[ScreenshotFormat::Ansi, ScreenshotFormat::Text, ScreenshotFormat::Png].iter().cloned()
+			.find(|format| format.file_extension() == extension)
+	}
+
+	/// Get the contents of a screenshot of `console_state` in this format, or None if it is an image
+	/// format that the engine can't make.
+	pub fn screenshot_data(self, console_state: &ConsoleState) -> Option<Vec<u8>> {
+		match self {
+			ScreenshotFormat::Ansi => {
+				let rows: Vec<_> = (0 .. console_state.height()).map(|y| {
+					(0 .. ConsoleState::SCREEN_WIDTH).map(|x| console_state.get_char(x, y)).collect()
+				}).collect();
+				Some(console_chars_to_ansi(&rows))
+			}
+			ScreenshotFormat::Text => Some(console_state.to_plain_text().into_bytes()),
+			ScreenshotFormat::Png => None,
+		}
+	}
+}
+
+/// Get the path in `directory` of the screenshot in `format` with the given `number`, like
+/// `SCREEN00.ANS`.
+fn numbered_screenshot_path(directory: &Path, format: ScreenshotFormat, number: usize) -> PathBuf {
+	directory.join(format!("SCREEN{:02}.{}", number, format.file_extension()))
+}
+
+/// Get the path in `directory` to save the next screenshot in `format` to, like `SCREEN00.ANS`,
+/// using the first number that isn't taken yet.
+#[cfg(feature = "file-io")]
+pub fn next_screenshot_path(directory: &Path, format: ScreenshotFormat) -> PathBuf {
+	(0 .. MAX_SCREENSHOT_NUMBER).map(|number| numbered_screenshot_path(directory, format, number))
+		.find(|path| !path.exists())
+		.unwrap_or_else(|| numbered_screenshot_path(directory, format, MAX_SCREENSHOT_NUMBER))
+}
+
+/// Without the `file-io` feature there's no way to tell which numbers are taken, so this is always
+/// the first one.
+#[cfg(not(feature = "file-io"))]
+pub fn next_screenshot_path(directory: &Path, format: ScreenshotFormat) -> PathBuf {
+	numbered_screenshot_path(directory, format, 0)
+}
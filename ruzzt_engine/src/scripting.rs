@@ -0,0 +1,195 @@
+use crate::board_message::BoardMessage;
+use crate::engine::RuzztEngine;
+use crate::event::Event;
+
+use rhai::{AST, Scope};
+
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+/// Get the `Event` with the given name, as used by the `send_event` script function.
+pub fn event_from_name(name: &str) -> Option<Event> {
+	let event = match name {
+		"left" => Event::Left,
+		"right" => Event::Right,
+		"up" => Event::Up,
+		"down" => Event::Down,
+		"shoot_left" => Event::ShootLeft,
+		"shoot_right" => Event::ShootRight,
+		"shoot_up" => Event::ShootUp,
+		"shoot_down" => Event::ShootDown,
+		"shoot" => Event::ShootFlow,
+		"torch" => Event::LightTorch,
+		"enter" => Event::Enter,
+		"escape" => Event::Escape,
+		"play" => Event::PlayGame,
+		"about" => Event::OpenAbout,
+		"high_scores" => Event::OpenHighScores,
+		"speed" => Event::ChangeGameSpeed,
+		"keyboard" => Event::ChangeKeyboardConfig,
+		"pause" => Event::PauseGame,
+		"map" => Event::OpenWorldMap,
+		"log" => Event::OpenMessageLog,
+		"quick_save" => Event::QuickSave,
+		"quick_load" => Event::QuickLoad,
+		"page_up" => Event::PageUp,
+		"page_down" => Event::PageDown,
+		_ => return None,
+	};
+	Some(event)
+}
+
+/// Runs a rhai script alongside a `RuzztEngine`, so the script can read the state of the game and
+/// send it input. This is useful for bots, automated testing of worlds and accessibility tools.
+///
+/// Scripts can call these functions:
+/// - `tile_element(x, y)` and `tile_colour(x, y)`: Get the element ID/colour of a tile on the
+///   current board, or -1 if the position is outside the board.
+/// - `player_x()`, `player_y()`, `board()`, `health()`, `ammo()`, `gems()`, `torches()`,
+///   `score()`: Get the player's position and inventory.
+/// - `has_key(index)` and `has_flag(name)`: Check if the player has a key or a flag is set.
+/// - `send_event(name)`: Use the named event (see `event_from_name`) in the next step, unless
+///   the front-end sends its own event.
+///
+/// Scripts can define these functions, which the host calls:
+/// - `on_step()`: Called before each step.
+/// - `on_board_message(name)`: Called with the name of each `BoardMessage` the engine sends.
+pub struct ScriptHost {
+	/// The engine being scripted. This is shared with the functions registered with the script
+	/// engine.
+	ruzzt_engine: Rc<RefCell<RuzztEngine>>,
+	/// The event sent by the script with `send_event`.
+	pending_event: Rc<RefCell<Option<Event>>>,
+	script_engine: rhai::Engine,
+	ast: AST,
+	/// The script's global variables, which are kept between calls.
+	scope: Scope<'static>,
+}
+
+impl ScriptHost {
+	/// Compile the given `script` and run its top-level statements, ready to script `ruzzt_engine`.
+	pub fn new(ruzzt_engine: RuzztEngine, script: &str) -> Result<ScriptHost, String> {
+		let ruzzt_engine = Rc::new(RefCell::new(ruzzt_engine));
+		let pending_event = Rc::new(RefCell::new(None));
+		let mut script_engine = rhai::Engine::new();
+
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("tile_element", move |x: i64, y: i64| -> i64 {
+				ruzzt_engine.borrow().board_simulator.get_tile(x as i16, y as i16).map_or(-1, |tile| tile.element_id as i64)
+			});
+		}
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("tile_colour", move |x: i64, y: i64| -> i64 {
+				ruzzt_engine.borrow().board_simulator.get_tile(x as i16, y as i16).map_or(-1, |tile| tile.colour as i64)
+			});
+		}
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("player_x", move || -> i64 {
+				ruzzt_engine.borrow().board_simulator.get_player_location().0 as i64
+			});
+		}
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("player_y", move || -> i64 {
+				ruzzt_engine.borrow().board_simulator.get_player_location().1 as i64
+			});
+		}
+
+		macro_rules! register_header_value {
+			($name:expr, |$world_header:ident| $value:expr) => {
+				let ruzzt_engine = ruzzt_engine.clone();
+				script_engine.register_fn($name, move || -> i64 {
+					let $world_header = &ruzzt_engine.borrow().board_simulator.world_header;
+					$value as i64
+				});
+			}
+		}
+
+		register_header_value!("board", |world_header| world_header.player_board);
+		register_header_value!("health", |world_header| world_header.player_health);
+		register_header_value!("ammo", |world_header| world_header.player_ammo);
+		register_header_value!("gems", |world_header| world_header.player_gems);
+		register_header_value!("torches", |world_header| world_header.player_torches.unwrap_or(0));
+		register_header_value!("score", |world_header| world_header.player_score);
+
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("has_key", move |key_index: i64| -> bool {
+				let world_header = &ruzzt_engine.borrow().board_simulator.world_header;
+				world_header.player_keys.get(key_index as usize).cloned().unwrap_or(false)
+			});
+		}
+		{
+			let ruzzt_engine = ruzzt_engine.clone();
+			script_engine.register_fn("has_flag", move |flag_name: &str| -> bool {
+				let world_header = &ruzzt_engine.borrow().board_simulator.world_header;
+				world_header.flag_names.iter().any(|set_flag| set_flag.to_string(false).eq_ignore_ascii_case(flag_name))
+			});
+		}
+		{
+			let pending_event = pending_event.clone();
+			script_engine.register_fn("send_event", move |event_name: &str| -> bool {
+				if let Some(event) = event_from_name(event_name) {
+					*pending_event.borrow_mut() = Some(event);
+					true
+				} else {
+					false
+				}
+			});
+		}
+
+		let ast = script_engine.compile(script).map_err(|err| err.to_string())?;
+		let mut scope = Scope::new();
+		script_engine.run_ast_with_scope(&mut scope, &ast).map_err(|err| err.to_string())?;
+
+		Ok(ScriptHost {
+			ruzzt_engine,
+			pending_event,
+			script_engine,
+			ast,
+			scope,
+		})
+	}
+
+	/// Get the engine being scripted.
+	pub fn engine(&self) -> RefMut<'_, RuzztEngine> {
+		self.ruzzt_engine.borrow_mut()
+	}
+
+	/// True if the script defines a function with the given `name`.
+	fn has_function(&self, name: &str) -> bool {
+		self.ast.iter_functions().any(|function| function.name == name)
+	}
+
+	/// Call the script's function with the given `name` and `args`, if it defines one.
+	fn call_function(&mut self, name: &str, args: impl rhai::FuncArgs) -> Result<(), String> {
+		if self.has_function(name) {
+			// Whatever the function returns is ignored.
+			let _: rhai::Dynamic = self.script_engine.call_fn(&mut self.scope, &self.ast, name, args).map_err(|err| err.to_string())?;
+		}
+		Ok(())
+	}
+
+	/// Simulate a single step like `RuzztEngine::step`, giving the script a chance to react before
+	/// and after. If `event` is `Event::None`, the event sent by the script is used instead.
+	pub fn step(&mut self, event: Event, global_time_passed_seconds: f64) -> Result<Vec<BoardMessage>, String> {
+		self.call_function("on_step", ())?;
+
+		let script_event = self.pending_event.borrow_mut().take();
+		let event = match (event, script_event) {
+			(Event::None, Some(script_event)) => script_event,
+			_ => event,
+		};
+
+		let board_messages = self.ruzzt_engine.borrow_mut().step(event, global_time_passed_seconds);
+
+		for board_message in &board_messages {
+			self.call_function("on_board_message", (board_message.name().to_string(),))?;
+		}
+
+		Ok(board_messages)
+	}
+}
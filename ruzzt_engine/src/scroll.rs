@@ -1,6 +1,7 @@
 use crate::event::*;
 use crate::board_message::*;
 use crate::console::*;
+use serde_derive::{Serialize, Deserialize};
 use zzt_file_format::dosstring::DosString;
 
 /// A scroll has a few horizontal borders in it that are all drawn in a similar manner. This
@@ -32,15 +33,107 @@ enum ScrollTextRowType {
 	Yellow,
 }
 
+/// A line of text in a scroll, parsed from the syntax that ZZT uses in the lines of object code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrollLine {
+	/// Yellow text with a small indent.
+	Text(DosString),
+	/// White text centred in the scroll, from lines that start with `$`.
+	Centered(DosString),
+	/// A link that sends the `label` to the object (or opens a world) when enter is pressed, from
+	/// lines like `!label;text`. When there is no `;`, the label is everything after the `!`, and
+	/// the `text` is the whole line including the `!`.
+	Link{label: DosString, text: DosString},
+}
+
+impl ScrollLine {
+	/// Parse a single line of scroll content.
+	pub fn parse(line: &[u8]) -> ScrollLine {
+		match line.first() {
+			Some(b'$') => ScrollLine::Centered(DosString::from_slice(&line[1 ..])),
+			Some(b'!') => {
+				match line.iter().position(|&char_code| char_code == b';') {
+					Some(separator_index) => ScrollLine::Link {
+						label: DosString::from_slice(&line[1 .. separator_index]),
+						text: DosString::from_slice(&line[separator_index + 1 ..]),
+					},
+					None => ScrollLine::Link {
+						label: DosString::from_slice(&line[1 ..]),
+						text: DosString::from_slice(line),
+					},
+				}
+			}
+			_ => ScrollLine::Text(DosString::from_slice(line)),
+		}
+	}
+
+	/// Get the text that is shown for the line.
+	pub fn text(&self) -> &DosString {
+		match self {
+			ScrollLine::Text(text) | ScrollLine::Centered(text) | ScrollLine::Link{text, ..} => text,
+		}
+	}
+
+	/// Get the line back in the syntax it was parsed from.
+	pub fn to_dos_string(&self) -> DosString {
+		match self {
+			ScrollLine::Text(text) => text.clone(),
+			ScrollLine::Centered(text) => {
+				let mut line = DosString::from_slice(b"$");
+				line += &text.data;
+				line
+			}
+			ScrollLine::Link{label, text} => {
+				let mut line = DosString::from_slice(b"!");
+				line += &label.data;
+				// Links without a `;` show the whole line.
+				if *text != line {
+					line.push(b';');
+					line += &text.data;
+				}
+				line
+			}
+		}
+	}
+}
+
+/// Scroll lines are serialized as the lines they were parsed from, so the format is the same as
+/// when scrolls kept their content that way.
+mod scroll_lines_serde {
+	use super::ScrollLine;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+	use zzt_file_format::dosstring::DosString;
+
+	pub fn serialize<S>(lines: &[ScrollLine], serializer: S) -> Result<S::Ok, S::Error> where
+		S: Serializer
+	{
+		let content_lines: Vec<DosString> = lines.iter().map(|line| line.to_dos_string()).collect();
+		content_lines.serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<ScrollLine>, D::Error> where
+		D: Deserializer<'de>
+	{
+		let content_lines = Vec::<DosString>::deserialize(deserializer)?;
+		Ok(content_lines.iter().map(|line| ScrollLine::parse(&line.data)).collect())
+	}
+}
+
 /// The current state of a scroll.
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScrollState {
 	/// The title of the scroll, displayed persistently at the top.
 	title: DosString,
 	/// The lines of text in the scroll content area.
-	content_lines: Vec<DosString>,
-	/// The line index within `content_lines` that the user currently has selected.
+	#[serde(rename = "content_lines", with = "scroll_lines_serde")]
+	lines: Vec<ScrollLine>,
+	/// The line index within `lines` that the user currently has selected.
 	current_line: isize,
+	/// True when each line is the name of a board, and pressing enter warps to the selected board.
+	is_board_list: bool,
+	/// True when each line is a flag slot, and pressing enter toggles the selected flag.
+	#[serde(default)]
+	is_flag_list: bool,
 }
 
 impl ScrollState {
@@ -48,8 +141,34 @@ impl ScrollState {
 	pub fn new_title_content(title: DosString, content_lines: Vec<DosString>) -> ScrollState {
 		ScrollState {
 			title,
-			content_lines,
+			lines: content_lines.iter().map(|line| ScrollLine::parse(&line.data)).collect(),
 			current_line: 0,
+			is_board_list: false,
+			is_flag_list: false,
+		}
+	}
+
+	/// Make a scroll listing the given `board_names` in order, so the selected board's index is
+	/// sent in a `WarpToBoard` message when enter is pressed.
+	pub fn new_board_list(board_names: Vec<DosString>) -> ScrollState {
+		ScrollState {
+			title: DosString::from_slice(b"Warp to board"),
+			lines: board_names.iter().map(|board_name| ScrollLine::parse(&board_name.data)).collect(),
+			current_line: 0,
+			is_board_list: true,
+			is_flag_list: false,
+		}
+	}
+
+	/// Make a scroll listing the world's flag slots (see `RuzztEngine::open_flag_list`), so the
+	/// selected line's index is sent in a `ToggleListedFlag` message when enter is pressed.
+	pub fn new_flag_list(flag_lines: Vec<DosString>) -> ScrollState {
+		ScrollState {
+			title: DosString::from_slice(b"Flags"),
+			lines: flag_lines.iter().map(|flag_line| ScrollLine::parse(&flag_line.data)).collect(),
+			current_line: 0,
+			is_board_list: false,
+			is_flag_list: true,
 		}
 	}
 
@@ -57,31 +176,42 @@ impl ScrollState {
 	pub fn new_empty() -> ScrollState {
 		ScrollState {
 			title: DosString::new(),
-			content_lines: vec![],
+			lines: vec![],
 			current_line: 0,
+			is_board_list: false,
+			is_flag_list: false,
 		}
 	}
 
+	/// Get the title displayed at the top of the scroll.
+	pub fn title(&self) -> &DosString {
+		&self.title
+	}
+
+	/// Get the parsed lines of text in the scroll, so frontends can draw links their own way.
+	pub fn lines(&self) -> &[ScrollLine] {
+		&self.lines
+	}
+
+	/// Get the index within `lines` of the selected line.
+	pub fn current_line(&self) -> usize {
+		self.current_line.max(0) as usize
+	}
+
+	/// Select the line at `line_index`, or the last line if there aren't that many.
+	pub fn select_line(&mut self, line_index: usize) {
+		self.current_line = (line_index as isize).min(self.lines.len() as isize - 1).max(0);
+	}
+
 	/// If the current line represents a link, this will return the target string for that link.
 	/// For example, if the line of text is `!thing;Hello!`, this will return "thing".
-	fn hovering_link(&self) -> Option<&[u8]> {
-		if self.current_line >= 0 {
-			let content_line = &self.content_lines[self.current_line as usize];
-
-			if content_line.get(0) == Some(&b'!') {
-				for (i, char_code) in content_line[1..].iter().enumerate() {
-					if *char_code == b';' {
-						return Some(&content_line[1..i + 1]);
-					}
-				}
-				// When there is no ; in the line, the link still works, but the text shown is the
-				// whole line including the !
-				Some(&content_line[1..])
-			} else {
-				None
-			}
-		} else {
-			None
+	fn hovering_link(&self) -> Option<&DosString> {
+		if self.current_line < 0 {
+			return None;
+		}
+		match self.lines.get(self.current_line as usize) {
+			Some(ScrollLine::Link{label, ..}) => Some(label),
+			_ => None,
 		}
 	}
 
@@ -93,10 +223,21 @@ impl ScrollState {
 			Event::Escape => {
 				board_messages.push(BoardMessage::CloseScroll);
 			}
+			Event::Enter if self.is_board_list => {
+				board_messages.push(BoardMessage::CloseScroll);
+				if self.current_line >= 0 {
+					board_messages.push(BoardMessage::WarpToBoard{board_index: self.current_line as usize});
+				}
+			}
+			Event::Enter if self.is_flag_list => {
+				board_messages.push(BoardMessage::CloseScroll);
+				if self.current_line >= 0 {
+					board_messages.push(BoardMessage::ToggleListedFlag{line_index: self.current_line as usize});
+				}
+			}
 			Event::Enter => {
 				if let Some(hovering_link_label) = self.hovering_link() {
-					let label = DosString::from_slice(hovering_link_label);
-					board_messages.push(BoardMessage::LinkClicked(label));
+					board_messages.push(BoardMessage::LinkClicked(hovering_link_label.clone()));
 					board_messages.push(BoardMessage::CloseScroll);
 				} else {
 					board_messages.push(BoardMessage::CloseScroll);
@@ -109,7 +250,7 @@ impl ScrollState {
 				}
 			}
 			Event::Down => {
-				if self.current_line < self.content_lines.len() as isize - 1 {
+				if self.current_line < self.lines.len() as isize - 1 {
 					self.current_line += 1;
 				}
 			}
@@ -121,8 +262,8 @@ impl ScrollState {
 			}
 			Event::PageDown => {
 				self.current_line += page_size;
-				if self.current_line > self.content_lines.len() as isize - 1 {
-					self.current_line = self.content_lines.len() as isize - 1;
+				if self.current_line > self.lines.len() as isize - 1 {
+					self.current_line = self.lines.len() as isize - 1;
 				}
 			}
 			_ => {}
@@ -236,30 +377,14 @@ impl ScrollState {
 		for row in 6 ..= 20 {
 			let content_line_index = row - 13 + self.current_line;
 
-			let in_bounds = content_line_index >= 0 && content_line_index < self.content_lines.len() as isize;
+			let in_bounds = content_line_index >= 0 && content_line_index < self.lines.len() as isize;
 			let (line_text, line_type): (&[u8], ScrollTextRowType) = if in_bounds {
-				let content_line = &self.content_lines[content_line_index as usize].data;
-				if content_line.len() >= 1 {
-					match content_line[0] {
-						b'$' => (&content_line[1..], ScrollTextRowType::Centred),
-						b'!' => {
-							// The default is 0, so when there is no ; it draws text from the !
-							// onwards.
-							let mut link_text_start = 0;
-							for (i, char_code) in content_line.iter().enumerate() {
-								if *char_code == b';' {
-									link_text_start = i + 1;
-									break;
-								}
-							}
-							(&content_line[link_text_start..], ScrollTextRowType::Link)
-						}
-						_ => (content_line, ScrollTextRowType::Normal)
-					}
-				} else {
-					(content_line, ScrollTextRowType::Normal)
+				match self.lines[content_line_index as usize] {
+					ScrollLine::Text(ref text) => (&text.data, ScrollTextRowType::Normal),
+					ScrollLine::Centered(ref text) => (&text.data, ScrollTextRowType::Centred),
+					ScrollLine::Link{ref text, ..} => (&text.data, ScrollTextRowType::Link),
 				}
-			} else if content_line_index == -1 || content_line_index == self.content_lines.len() as isize {
+			} else if content_line_index == -1 || content_line_index == self.lines.len() as isize {
 				(b"    \x07    \x07    \x07    \x07    \x07    \x07    \x07    \x07    \x07",
 					ScrollTextRowType::Yellow)
 			} else {
@@ -0,0 +1,84 @@
+use crate::event::Event;
+
+use zzt_file_format::dosstring::DosString;
+
+/// The number of steps the title screen has to be left alone before the order form is shown.
+pub const TITLE_IDLE_NAG_STEPS: usize = 600;
+
+/// Get the lines of the order form scroll shown by the shareware flavour (see
+/// `SharewareFlavour`). This is written in the style of the scrolls that shareware DOS ZZT showed,
+/// but RUZZT itself is free, so there is nothing to order.
+pub fn order_form_lines() -> Vec<DosString> {
+	let lines = [
+		"$Thank you for playing!",
+		"",
+		"This is the shareware version of ZZT. It",
+		"comes with one world to explore. Three more",
+		"worlds are sent to registered players, with",
+		"new puzzles, creatures and secrets.",
+		"",
+		"$How to register",
+		"",
+		"Send the order form in the documentation",
+		"along with your payment, and your worlds will",
+		"be sent to you on a floppy disk.",
+		"",
+		"Please share the shareware version with your",
+		"friends, but not the registered worlds!",
+		"",
+		"$(Shown by RUZZT for preservation. RUZZT is",
+		"$free, and there is nothing to order.)",
+	];
+	lines.iter().map(|line| DosString::from_str(line)).collect()
+}
+
+/// Reproduces the nagging of shareware DOS ZZT, for preservation: the order form scroll is shown
+/// when the title screen is left alone for `TITLE_IDLE_NAG_STEPS` steps, and when the player quits
+/// (which then happens when the scroll is closed). See `RuzztEngine::shareware_flavour`.
+#[derive(Debug, Clone, Default)]
+pub struct SharewareFlavour {
+	/// The number of steps since the last event in the title screen.
+	idle_steps: usize,
+	/// True if the order form was opened because the player quit, so quitting should carry on
+	/// when it is closed.
+	quit_after_scroll: bool,
+}
+
+impl SharewareFlavour {
+	pub fn new() -> SharewareFlavour {
+		SharewareFlavour::default()
+	}
+
+	/// Count a step of the title screen with the given `event`. Returns true if the order form
+	/// should be shown.
+	pub fn title_step(&mut self, event: Event) -> bool {
+		if event != Event::None {
+			self.idle_steps = 0;
+			return false;
+		}
+		self.idle_steps += 1;
+		if self.idle_steps >= TITLE_IDLE_NAG_STEPS {
+			self.idle_steps = 0;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Called when the player has confirmed they want to quit. Returns true if the order form
+	/// should be shown first, in which case the quit is held back until `scroll_closed`.
+	pub fn hold_back_quit(&mut self) -> bool {
+		if self.quit_after_scroll {
+			false
+		} else {
+			self.quit_after_scroll = true;
+			true
+		}
+	}
+
+	/// Called when a scroll is closed. Returns true if the order form shown when quitting was
+	/// closed, so the game should quit now.
+	pub fn scroll_closed(&mut self) -> bool {
+		std::mem::replace(&mut self.quit_after_scroll, false)
+	}
+}
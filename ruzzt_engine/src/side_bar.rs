@@ -11,14 +11,18 @@ use num::FromPrimitive;
 pub enum TextInputMode {
 	SaveFile,
 	Debug,
+	/// The name of a new world.
+	NewWorld,
+	/// The password of the locked passage at `status_index` (see `PassageLock`).
+	PassagePassword{status_index: usize},
 }
 
 impl TextInputMode {
 	/// The maximum number of characters allowed in the input mode.
 	fn max_char_len(&self) -> usize {
 		match self {
-			TextInputMode::SaveFile => 8,
-			TextInputMode::Debug => 11,
+			TextInputMode::SaveFile | TextInputMode::NewWorld => 8,
+			TextInputMode::Debug | TextInputMode::PassagePassword{..} => 11,
 		}
 	}
 
@@ -26,15 +30,25 @@ impl TextInputMode {
 	fn suffix(&self) -> &'static [u8] {
 		match self {
 			TextInputMode::SaveFile => b".SAV",
-			TextInputMode::Debug => b"",
+			TextInputMode::NewWorld => b".ZZT",
+			TextInputMode::Debug | TextInputMode::PassagePassword{..} => b"",
+		}
+	}
+
+	/// The text shown above the input box.
+	fn label(&self) -> &'static [u8] {
+		match self {
+			TextInputMode::NewWorld => b"New world name:",
+			TextInputMode::PassagePassword{..} => b"Password:",
+			TextInputMode::SaveFile | TextInputMode::Debug => b"",
 		}
 	}
 
 	/// Whether the input text is forced to be upper-case.
 	fn force_upper(&self) -> bool {
 		match self {
-			TextInputMode::SaveFile => true,
-			TextInputMode::Debug => false,
+			TextInputMode::SaveFile | TextInputMode::NewWorld => true,
+			TextInputMode::Debug | TextInputMode::PassagePassword{..} => false,
 		}
 	}
 }
@@ -52,13 +66,133 @@ struct TextInputState {
 pub enum YesNoMode {
 	EndGame,
 	Quit,
+	ResumeAutosave,
+	/// Asks whether to save the changes to the world called `world_name` before sending `then`,
+	/// which closes the world (see `BoardMessage::closes_world`). Escape cancels `then`.
+	SaveChanges{world_name: DosString, then: Box<BoardMessage>},
+}
+
+/// If there is a slider open in the side bar, this represents the purpose of that slider.
+#[derive(Clone)]
+pub enum SliderMode {
+	/// Picks the game speed, from fastest (0) to slowest (8).
+	GameSpeed,
+	/// Picks the number of steps a key has to be held before it starts repeating.
+	KeyRepeatDelay,
+}
+
+impl SliderMode {
+	/// The text shown above the slider.
+	fn label(&self) -> &'static [u8] {
+		match self {
+			SliderMode::GameSpeed => b"Game speed:",
+			SliderMode::KeyRepeatDelay => b"Key repeat delay:",
+		}
+	}
+
+	/// The slider as it is drawn, with a letter at each end to say what the lowest and highest
+	/// values mean.
+	fn slider_text(&self) -> &'static [u8] {
+		match self {
+			SliderMode::GameSpeed => b"F....:....S",
+			SliderMode::KeyRepeatDelay => b"S....:....L",
+		}
+	}
 }
 
+/// A SliderMode and the value picked in the slider, if one is open in the side bar.
+#[derive(Clone)]
+struct SliderInputState {
+	mode: SliderMode,
+	value: u8,
+}
+
+/// The highest value that can be picked in a slider. The lowest is 0.
+pub const SLIDER_MAX_VALUE: u8 = 8;
+
+/// When the player's health is this or lower, the health count flashes in the side bar.
+pub const LOW_HEALTH_WARNING: i16 = 20;
+/// When a lit torch has this many cycles left or fewer (the last segment of the torch meter), the
+/// torch meter flashes in the side bar.
+pub const TORCH_RUNNING_OUT_WARNING: i16 = 40;
+
 /// If a text-based input is open in the side bar, this contains the state of that input.
 #[derive(Clone)]
 enum InputState {
 	Text(TextInputState),
 	YesNo(YesNoMode),
+	Slider(SliderInputState),
+}
+
+/// The information shown in the side bar, separate from how it is drawn. Frontends with their own
+/// UI can show this instead of reading the side bar out of the `ConsoleState`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideBarModel {
+	/// The name of the world, shown in the title screen.
+	pub world_name: DosString,
+	/// True if the game is paused.
+	pub is_paused: bool,
+	/// The seconds left before the player starts getting hurt, or None if the board has no time
+	/// limit.
+	pub time_left: Option<i16>,
+	pub health: i16,
+	pub ammo: i16,
+	/// The number of torches the player has, or None if the world doesn't have torches.
+	pub torches: Option<i16>,
+	/// The game cycles left for the lit torch, or None if the world doesn't have torches. The torch
+	/// meter has four segments, with one lit for every 40 cycles.
+	pub torch_cycles: Option<i16>,
+	/// The number of stones of power the player has, or None if the world doesn't have them. Super
+	/// ZZT only shows the count once it isn't negative.
+	pub stones: Option<i16>,
+	pub gems: i16,
+	pub score: i16,
+	/// Which of the seven coloured keys the player has, in the same order as `WorldHeader`.
+	pub keys: [bool; 7],
+	/// The most player bullets that can be on the board at once, where 0 means the player can't
+	/// shoot.
+	pub max_shots: u8,
+	/// True if the movement keys shoot instead of moving (see `RuzztEngine::shoot_lock`).
+	pub shoot_lock: bool,
+}
+
+impl SideBarModel {
+	/// Get the side bar information for a game with the given `world_header`, on a board with the
+	/// given `board_meta_data`.
+	pub fn new(world_header: &WorldHeader, board_meta_data: &BoardMetaData, is_paused: bool) -> SideBarModel {
+		let time_left = if board_meta_data.time_limit > 0 {
+			Some(board_meta_data.time_limit.wrapping_sub(world_header.time_passed))
+		} else {
+			None
+		};
+
+		SideBarModel {
+			world_name: world_header.world_name.clone(),
+			is_paused,
+			time_left,
+			health: world_header.player_health,
+			ammo: world_header.player_ammo,
+			torches: world_header.player_torches,
+			torch_cycles: world_header.torch_cycles,
+			stones: world_header.player_stones,
+			gems: world_header.player_gems,
+			score: world_header.player_score,
+			keys: world_header.player_keys,
+			max_shots: board_meta_data.max_player_shots,
+			shoot_lock: false,
+		}
+	}
+
+	/// True if the player's health is low enough to warn them about it (see `LOW_HEALTH_WARNING`).
+	pub fn health_is_low(&self) -> bool {
+		self.health > 0 && self.health <= LOW_HEALTH_WARNING
+	}
+
+	/// True if the player has a lit torch that is about to burn out (see
+	/// `TORCH_RUNNING_OUT_WARNING`).
+	pub fn torch_is_running_out(&self) -> bool {
+		self.torch_cycles.is_some_and(|torch_cycles| torch_cycles > 0 && torch_cycles <= TORCH_RUNNING_OUT_WARNING)
+	}
 }
 
 /// The state of the side bar.
@@ -83,11 +217,10 @@ impl SideBar {
 		self.input_state.is_some()
 	}
 
-	/// If `in_typing_mode()` returns true, this should be called with incoming typing events, and
-	/// also the `world_header` instance because it for some actions.
+	/// If `in_typing_mode()` returns true, this should be called with incoming typing events.
 	/// Returns `BoardMessage`s if the input is accepted (eg. when you press enter in the save game
 	/// name input box)
-	pub fn process_typing(&mut self, event: TypingEvent, world_header: &WorldHeader) -> Vec<BoardMessage> {
+	pub fn process_typing(&mut self, event: TypingEvent) -> Vec<BoardMessage> {
 		let mut board_messages = vec![];
 
 		let mut close_input = false;
@@ -118,10 +251,20 @@ impl SideBar {
 								TextInputMode::Debug => {
 									board_messages.push(BoardMessage::DebugCommand(std::mem::replace(&mut text_input_state.text, DosString::new())));
 								}
+								TextInputMode::NewWorld => {
+									let name = std::mem::replace(&mut text_input_state.text, DosString::new());
+									if !name.data.is_empty() {
+										board_messages.push(BoardMessage::CreateWorld{name});
+									}
+								}
+								TextInputMode::PassagePassword{status_index} => {
+									let password = std::mem::replace(&mut text_input_state.text, DosString::new());
+									board_messages.push(BoardMessage::PassagePasswordEntered{status_index, password});
+								}
 							}
 							close_input = true;
 						}
-						TypingEvent::Backspace => {
+						TypingEvent::Backspace | TypingEvent::Left => {
 							text_input_state.text.data.pop();
 						}
 						_ => {}
@@ -132,19 +275,54 @@ impl SideBar {
 						TypingEvent::Char(b'y') | TypingEvent::Char(b'Y') => {
 							match mode {
 								YesNoMode::EndGame => {
-									board_messages.push(BoardMessage::ReturnToTitleScreen);
-									let mut filename = world_header.world_name.clone();
-									filename = filename.to_upper();
-									filename += b".ZZT";
-									board_messages.push(BoardMessage::OpenWorld{filename});
+									board_messages.push(BoardMessage::EndGame);
 								}
 								YesNoMode::Quit => {
 									board_messages.push(BoardMessage::Quit);
 								}
+								YesNoMode::ResumeAutosave => {
+									board_messages.push(BoardMessage::ResumeAutosave);
+								}
+								YesNoMode::SaveChanges{ref then, ..} => {
+									board_messages.push(BoardMessage::SaveWorld);
+									board_messages.push((**then).clone());
+								}
 							}
 							close_input = true;
 						}
-						TypingEvent::Escape | TypingEvent::Char(b'n') | TypingEvent::Char(b'N') => {
+						TypingEvent::Char(b'n') | TypingEvent::Char(b'N') => {
+							if let YesNoMode::SaveChanges{ref then, ..} = mode {
+								board_messages.push(BoardMessage::DiscardWorldChanges);
+								board_messages.push((**then).clone());
+							}
+							close_input = true;
+						}
+						TypingEvent::Escape => {
+							close_input = true;
+						}
+						_ => {}
+					}
+				}
+				InputState::Slider(ref mut slider_input_state) => {
+					match event {
+						TypingEvent::Left | TypingEvent::Backspace => {
+							slider_input_state.value = slider_input_state.value.saturating_sub(1);
+						}
+						TypingEvent::Right => {
+							slider_input_state.value = (slider_input_state.value + 1).min(SLIDER_MAX_VALUE);
+						}
+						TypingEvent::Char(c @ b'1' ..= b'9') => {
+							slider_input_state.value = c - b'1';
+						}
+						TypingEvent::Enter => {
+							let value = slider_input_state.value;
+							board_messages.push(match slider_input_state.mode {
+								SliderMode::GameSpeed => BoardMessage::SetGameSpeed(value),
+								SliderMode::KeyRepeatDelay => BoardMessage::SetKeyRepeatDelay(value),
+							});
+							close_input = true;
+						}
+						TypingEvent::Escape => {
 							close_input = true;
 						}
 						_ => {}
@@ -175,6 +353,22 @@ impl SideBar {
 		self.input_state = Some(InputState::YesNo(mode));
 	}
 
+	/// Open a slider in the side bar with the given `mode`, starting at the given `value`. See
+	/// `SliderMode`.
+	pub fn open_slider_input(&mut self, mode: SliderMode, value: u8) {
+		self.input_state = Some(InputState::Slider(SliderInputState {
+			mode,
+			value: value.min(SLIDER_MAX_VALUE),
+		}));
+	}
+
+	/// Draw a slider's `text` at the given `x`/`y` position in the console, with an arrow pointing
+	/// at the given `value` on the line above it.
+	fn draw_slider_at(&self, x: usize, y: usize, text: &[u8], value: u8, console_state: &mut ConsoleState) {
+		console_state.draw_text_at(x, y, text, ConsoleColour::Blue, ConsoleColour::White);
+		*console_state.get_char_mut(x + 1 + value as usize, y - 1) = ConsoleChar::new(0x1f, ConsoleColour::Blue, ConsoleColour::White);
+	}
+
 	/// Draw `num` as a decimal number at the given `x`/`y` position in the console, with the given
 	/// `foreground` and `background` colours on each character that is drawn.
 	fn draw_num_at(&self, x: usize, y: usize, num: isize, background: ConsoleColour, foreground: ConsoleColour, console_state: &mut ConsoleState) {
@@ -196,7 +390,7 @@ impl SideBar {
 
 	/// Draw the blue background of the side bar.
 	fn draw_background(&self, console_state: &mut ConsoleState) {
-		for y in 0..25 {
+		for y in 0 .. console_state.height() {
 			for x in 60..80 {
 				*console_state.get_char_mut(x, y) = ConsoleChar::new(0, ConsoleColour::Blue, ConsoleColour::Black);
 			}
@@ -220,25 +414,42 @@ impl SideBar {
 						text.push(*c);
 					}
 
+					console_state.draw_text_at(63, 3, text_input_state.mode.label(), Blue, Yellow);
 					console_state.draw_text_at(63, 5, &text.data, Black, White);
 					*console_state.get_char_mut(63 + text_input_state.text.len(), 4) = ConsoleChar::new(0x1f, Blue, White);
 				}
 				InputState::YesNo(ref mode) => {
+					let save_changes_message;
 					let message: &[u8] = match mode {
 						YesNoMode::EndGame => b"End this game?",
 						YesNoMode::Quit => b"Quit RUZZT?",
+						YesNoMode::ResumeAutosave => b"Load autosave?",
+						YesNoMode::SaveChanges{ref world_name, ..} => {
+							// The world name goes on its own line, shortened so the question mark
+							// and cursor still fit.
+							console_state.draw_text_at(63, 4, b"Save changes to", Blue, White);
+							let name = if world_name.data.is_empty() { &b"UNTITLED"[..] } else { &world_name.data[.. world_name.len().min(14)] };
+							save_changes_message = [name, b"?"].concat();
+							&save_changes_message
+						}
 					};
 					console_state.draw_text_at(63, 5, message, Blue, White);
 					if cycle % 6 < 3 {
 						*console_state.get_char_mut(63 + message.len() + 1, 5) = ConsoleChar::new(0x5f, Blue, White);
 					}
 				}
+				InputState::Slider(ref slider_input_state) => {
+					let mode = &slider_input_state.mode;
+					console_state.draw_text_at(63, 3, mode.label(), Blue, Yellow);
+					self.draw_slider_at(63, 5, mode.slider_text(), slider_input_state.value, console_state);
+				}
 			}
 		}
 	}
 
-	/// Draw the side bar in the console.
-	pub fn draw_side_bar(&self, world_header: &WorldHeader, current_board_meta_data: &BoardMetaData, is_paused: bool, in_title_screen: bool, console_state: &mut ConsoleState, cycle: usize) {
+	/// Draw the side bar showing `model` in the console. `cycle` makes text input cursors blink,
+	/// and `game_cycle` makes the low health and torch warnings flash.
+	pub fn draw_side_bar(&self, model: &SideBarModel, in_title_screen: bool, console_state: &mut ConsoleState, cycle: usize, game_cycle: usize) {
 		use self::ConsoleColour::*;
 
 		self.draw_background(console_state);
@@ -247,16 +458,16 @@ impl SideBar {
 		console_state.draw_text_at(65, 2, b"- - - - -", Blue, White);
 
 		if in_title_screen {
-			self.draw_title_content(world_header, console_state);
+			self.draw_title_content(model, console_state);
 		} else {
-			self.draw_game_content(world_header, current_board_meta_data, is_paused, console_state);
+			self.draw_game_content(model, console_state, game_cycle);
 		}
 
 		self.draw_input(console_state, cycle);
 	}
 
 	/// Draw the side bar in the title screen mode.
-	fn draw_title_content(&self, world_header: &WorldHeader, console_state: &mut ConsoleState) {
+	fn draw_title_content(&self, model: &SideBarModel, console_state: &mut ConsoleState) {
 		use self::ConsoleColour::*;
 
 		if self.input_state.is_none() {
@@ -264,10 +475,10 @@ impl SideBar {
 		}
 
 		self.draw_hotkey(62, 07, b" W ", b"World:", false, true, console_state);
-		let world_name = if world_header.world_name.len() == 0 {
+		let world_name = if model.world_name.len() == 0 {
 			b"Untitled"
 		} else {
-			world_header.world_name.data.as_slice()
+			model.world_name.data.as_slice()
 		};
 		console_state.draw_text_at(69, 08, world_name, Blue, White);
 
@@ -275,43 +486,59 @@ impl SideBar {
 		self.draw_hotkey(62, 12, b" R ", b"Restore game", false, true, console_state);
 		self.draw_hotkey(62, 13, b" Q ", b"Quit", true, true, console_state);
 
-		//self.draw_hotkey(62, 16, b" A ", b"About ZZT!", false, false, console_state);
-		//self.draw_hotkey(62, 17, b" H ", b"High Scores", true, true, console_state);
-		//self.draw_hotkey(62, 18, b" E ", b"Board Editor", false, true, console_state);
+		self.draw_hotkey(62, 16, b" A ", b"About RUZZT!", false, false, console_state);
+		self.draw_hotkey(62, 17, b" H ", b"High Scores", true, true, console_state);
+		self.draw_hotkey(62, 18, b" E ", b"New world", false, true, console_state);
 
-		//self.draw_hotkey(62, 20, b" S ", b"Game speed:", true, true, console_state);
+		self.draw_hotkey(62, 19, b" S ", b"Game speed:", true, true, console_state);
+		self.draw_hotkey(62, 23, b" K ", b"Keyboard", false, true, console_state);
+
+		let version_text = format!("v{}", crate::engine::ENGINE_VERSION);
+		console_state.draw_text_at(79 - version_text.len(), 24, version_text.as_bytes(), Blue, LightBlue);
+	}
+
+	/// Draw the current `game_speed` under the game speed hotkey in the title screen side bar.
+	pub fn draw_title_game_speed(&self, game_speed: u8, console_state: &mut ConsoleState) {
+		self.draw_slider_at(66, 21, SliderMode::GameSpeed.slider_text(), game_speed, console_state);
 	}
 
 	/// Draw the side bar in the in-game mode.
-	fn draw_game_content(&self, world_header: &WorldHeader, current_board_meta_data: &BoardMetaData, is_paused: bool, console_state: &mut ConsoleState) {
+	fn draw_game_content(&self, model: &SideBarModel, console_state: &mut ConsoleState, game_cycle: usize) {
 		use self::ConsoleColour::*;
 
-		if is_paused {
+		// Warnings flash at the same rate as the player does while the game is paused.
+		let warning_flash_on = game_cycle % 10 < 5;
+
+		if model.is_paused {
 			console_state.draw_text_at(64, 5, b"Pausing...", Blue, White);
 		}
 
-		if current_board_meta_data.time_limit > 0 {
-			let time_left = current_board_meta_data.time_limit - world_header.time_passed;
+		if let Some(time_left) = model.time_left {
 			console_state.draw_text_at(64, 06, b"   Time:", Blue, Yellow);
 			self.draw_num_at(72, 06, time_left as isize, Blue, Yellow, console_state);
 		}
 
 		*console_state.get_char_mut(62, 07) = ConsoleChar::new(0x02, Blue, White);
 		console_state.draw_text_at(64, 07, b" Health:", Blue, Yellow);
-		self.draw_num_at(72, 07, world_header.player_health as isize, Blue, Yellow, console_state);
+		let health_colour = if model.health_is_low() && warning_flash_on {
+			LightRed
+		} else {
+			Yellow
+		};
+		self.draw_num_at(72, 07, model.health as isize, Blue, health_colour, console_state);
 
 		*console_state.get_char_mut(62, 08) = ConsoleChar::new(0x84, Blue, LightCyan);
 		console_state.draw_text_at(64, 08, b"   Ammo:", Blue, Yellow);
-		self.draw_num_at(72, 08, world_header.player_ammo as isize, Blue, Yellow, console_state);
+		self.draw_num_at(72, 08, model.ammo as isize, Blue, Yellow, console_state);
 
-		if let Some(player_torches) = world_header.player_torches {
+		if let Some(player_torches) = model.torches {
 			*console_state.get_char_mut(62, 09) = ConsoleChar::new(0x9D, Blue, Brown);
 			console_state.draw_text_at(64, 09, b"Torches:", Blue, Yellow);
 			self.draw_num_at(72, 09, player_torches as isize, Blue, Yellow, console_state);
 		}
 
-		if let Some(torch_cycles) = world_header.torch_cycles {
-			if torch_cycles != 0 {
+		if let Some(torch_cycles) = model.torch_cycles {
+			if torch_cycles != 0 && (warning_flash_on || !model.torch_is_running_out()) {
 				for i in 0..4 {
 					let char_code = if i < torch_cycles / 40 {
 						0xb1
@@ -323,26 +550,45 @@ impl SideBar {
 			}
 		}
 
+		if let Some(player_stones) = model.stones {
+			if player_stones >= 0 {
+				*console_state.get_char_mut(62, 9) = ConsoleChar::new(0x5A, Blue, White);
+				console_state.draw_text_at(64, 9, b" Stones:", Blue, Yellow);
+				self.draw_num_at(72, 9, player_stones as isize, Blue, Yellow, console_state);
+			}
+		}
+
 		*console_state.get_char_mut(62, 10) = ConsoleChar::new(0x04, Blue, LightCyan);
 		console_state.draw_text_at(64, 10, b"   Gems:", Blue, Yellow);
-		self.draw_num_at(72, 10, world_header.player_gems as isize, Blue, Yellow, console_state);
+		self.draw_num_at(72, 10, model.gems as isize, Blue, Yellow, console_state);
 
 		console_state.draw_text_at(64, 11, b"  Score:", Blue, Yellow);
-		self.draw_num_at(72, 11, world_header.player_score as isize, Blue, Yellow, console_state);
+		self.draw_num_at(72, 11, model.score as isize, Blue, Yellow, console_state);
 
 		*console_state.get_char_mut(62, 12) = ConsoleChar::new(0x0C, Blue, White);
 		console_state.draw_text_at(64, 12, b"   Keys:", Blue, Yellow);
 		for i in 0 .. 7 {
-			if world_header.player_keys[i] {
+			if model.keys[i] {
 				*console_state.get_char_mut(72 + i, 12) = ConsoleChar::new(0x0C, Blue, ConsoleColour::from_u8(i as u8 + 9).unwrap());
 			}
 		}
 
+		console_state.draw_text_at(64, 13, b"  Shots:", Blue, Yellow);
+		if model.max_shots == 0 {
+			console_state.draw_text_at(72, 13, b"None", Blue, Yellow);
+		} else {
+			self.draw_num_at(72, 13, model.max_shots as isize, Blue, Yellow, console_state);
+		}
+
 		self.draw_hotkey(62, 14, b" T ", b"Torch", true, false, console_state);
 		self.draw_hotkey(62, 15, b" B ", b"Be quiet", false, false, console_state);
 		self.draw_hotkey(62, 16, b" H ", b"Help", true, false, console_state);
 
-		self.draw_hotkey(67, 18, b" \x18\x19\x1A\x1B", b"Move", false, false, console_state);
+		if model.shoot_lock {
+			self.draw_hotkey(67, 18, b" \x18\x19\x1A\x1B", b"Shoot", false, true, console_state);
+		} else {
+			self.draw_hotkey(67, 18, b" \x18\x19\x1A\x1B", b"Move", false, false, console_state);
+		}
 		self.draw_hotkey(61, 19, b" Shift \x18\x19\x1A\x1B", b"Shoot", true, false, console_state);
 
 		self.draw_hotkey(62, 21, b" S ", b"Save game", true, false, console_state);
@@ -0,0 +1,175 @@
+use crate::board_simulator::SimPos;
+use crate::compatibility::step_cycle;
+use crate::engine::RuzztEngine;
+use crate::event::Event;
+
+use zzt_file_format::World;
+use zzt_file_format::dosstring::DosString;
+use zzt_file_format::padding::ParseOptions;
+
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+
+/// The number of game cycles `smoke_test_world` runs on each board by default.
+pub const DEFAULT_SMOKE_TEST_STEPS: usize = 100;
+
+/// What happened when one board of a world was run by `smoke_test_world`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardSmokeResult {
+	pub board_index: usize,
+	pub board_name: DosString,
+	/// The number of game cycles that finished before the engine panicked, or all of them if it
+	/// didn't.
+	pub steps_run: usize,
+	/// The panic message if the engine panicked while loading or running the board.
+	pub panic_message: Option<String>,
+	/// The warnings the engine logged while running the board (see `BoardMessage::EngineLog`).
+	pub warnings: Vec<String>,
+}
+
+impl BoardSmokeResult {
+	/// True if the board ran without panicking or logging any warnings.
+	pub fn passed(&self) -> bool {
+		self.panic_message.is_none() && self.warnings.is_empty()
+	}
+
+	/// Describe the result in a line for each problem, eg. "Board 3 (Town): Panicked after 12
+	/// steps: ...". Boards that passed give a single "OK" line.
+	pub fn description_lines(&self) -> Vec<String> {
+		let prefix = format!("Board {} ({})", self.board_index, self.board_name.to_string(false));
+		if self.passed() {
+			return vec![format!("{}: OK", prefix)];
+		}
+
+		let mut lines = vec![];
+		if let Some(ref panic_message) = self.panic_message {
+			lines.push(format!("{}: Panicked after {} steps: {}", prefix, self.steps_run, panic_message));
+		}
+		lines.extend(self.warnings.iter().map(|warning| format!("{}: {}", prefix, warning)));
+		lines
+	}
+}
+
+/// Get the message a panic was started with, if it was a string.
+fn panic_payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		message.to_string()
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		"(no message)".to_string()
+	}
+}
+
+/// Check that every board in `world` loads and runs: start a new game on each board in turn and
+/// run it for `steps_per_board` game cycles with no input, closing any scrolls that open. The
+/// random number generator is seeded with `seed` for each board, so a failure can be reproduced.
+/// A panic only stops the board it happened on. This catches problems that unit tests miss, so
+/// it's worth running over a collection of worlds after changing behaviours.
+///
+/// The panic message is also printed by the panic hook as usual.
+pub fn smoke_test_world(world: &World, steps_per_board: usize, seed: u64) -> Vec<BoardSmokeResult> {
+	let mut results = vec![];
+	for (board_index, board) in world.boards.iter().enumerate() {
+		let mut steps_run = 0;
+		let mut warnings = vec![];
+		let run = panic::catch_unwind(AssertUnwindSafe(|| {
+			let mut engine = RuzztEngine::new();
+			if let Err(err) = engine.load_world(world.clone(), Some(board_index as i16)) {
+				warnings.push(err);
+				return;
+			}
+			engine.set_in_title_screen(false);
+			engine.is_paused = false;
+			engine.seed_rng(seed);
+			for _ in 0 .. steps_per_board {
+				warnings.extend(step_cycle(&mut engine, Event::None));
+				steps_run += 1;
+			}
+		}));
+
+		results.push(BoardSmokeResult {
+			board_index,
+			board_name: board.meta_data.board_name.clone(),
+			steps_run,
+			panic_message: run.err().map(|payload| panic_payload_message(payload.as_ref())),
+			warnings,
+		});
+	}
+	results
+}
+
+/// The events that `fuzz_world_data` picks from. Events that can write files (eg. saving the game)
+/// are left out.
+const FUZZ_EVENTS: [Event; 23] = [
+	Event::None,
+	Event::Left,
+	Event::Right,
+	Event::Up,
+	Event::Down,
+	Event::PageUp,
+	Event::PageDown,
+	Event::Enter,
+	Event::Escape,
+	Event::ShootFlow,
+	Event::ShootLeft,
+	Event::ShootRight,
+	Event::ShootUp,
+	Event::ShootDown,
+	Event::LightTorch,
+	Event::PauseGame,
+	Event::Debug,
+	Event::ToggleExecutionOrder,
+	Event::ToggleShootLock,
+	Event::OpenWorldMap,
+	Event::OpenMessageLog,
+	Event::PlayGame,
+	Event::Quit,
+];
+
+/// Get the event for three bytes of `fuzz_world_data`'s data. The first byte picks one of
+/// `FUZZ_EVENTS`, and anything past the end of them is a click at the position in the other two
+/// bytes, which can be a little outside of the board.
+fn fuzz_event(event_data: &[u8]) -> Event {
+	let byte = |index: usize| event_data.get(index).cloned().unwrap_or(0);
+	match FUZZ_EVENTS.get(byte(0) as usize) {
+		Some(&event) => event,
+		None => Event::Click {
+			pos: SimPos {
+				x: (byte(1) % 64) as i16 - 2,
+				y: (byte(2) % 32) as i16 - 2,
+			},
+			shoot: byte(0) % 2 == 1,
+		},
+	}
+}
+
+/// Start a game of a world made from arbitrary `data`, and step it with events also made from
+/// `data`, for finding panics with a fuzzer (see `fuzz/fuzz_targets/step_loop.rs`). The first byte
+/// is the number of events, which each take three bytes (see `fuzz_event`), and the rest is the
+/// world file, which is parsed leniently. Data that isn't a world is ignored. The engine should
+/// never panic, however broken the world is.
+pub fn fuzz_world_data(data: &[u8]) {
+	let event_count = match data.first() {
+		Some(&event_count) => event_count as usize,
+		None => return,
+	};
+	let (event_data, world_data) = data[1 ..].split_at((event_count * 3).min(data.len() - 1));
+
+	let world = match World::parse_with_options(&mut Cursor::new(world_data), ParseOptions::lenient()) {
+		Ok(world) => world,
+		Err(_) => return,
+	};
+	let mut engine = RuzztEngine::new();
+	if engine.load_world(world, None).is_err() {
+		return;
+	}
+	engine.set_in_title_screen(false);
+	engine.is_paused = false;
+	engine.seed_rng(0);
+
+	for (step_index, event_data) in event_data.chunks(3).enumerate() {
+		// A tenth of a second passes each step, so time limits run out too.
+		engine.step(fuzz_event(event_data), step_index as f64 / 10.);
+	}
+}
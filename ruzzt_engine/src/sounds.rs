@@ -1,4 +1,7 @@
 use lazy_static::lazy_static;
+use serde_derive::{Serialize, Deserialize};
+
+use std::collections::VecDeque;
 
 lazy_static! {
 	/// When playing a sound effect, it takes one of these arrays depending on the selected sound effect
@@ -21,7 +24,7 @@ lazy_static! {
 /// The priority of a sound that will be added to the sound player. Music is appended to whatever is
 /// currently playing. Sounds with higher levels will replace currently playing sounds, and lower
 /// levels will be ignored.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SoundPriority {
 	Music,
 	Level(usize),
@@ -41,8 +44,20 @@ impl SoundPriority {
 	}
 }
 
+/// What the engine does with sounds while the game is being simulated fast (see
+/// `RuzztEngine::should_simulate_fast`), when lots of steps happen in a short time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FastSimulationSoundPolicy {
+	/// Play every sound, the same as when simulating normally.
+	PlayAll,
+	/// Only play the first sound sent in each full step of the board.
+	Compress,
+	/// Don't play any sounds.
+	Drop,
+}
+
 /// A single note or sound effect that can be stringed together to make game sounds.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SoundEntry {
 	/// The code of the sound to play. 0-239 are notes, and 240-255 are sound effects from the
 	/// `SOUND_EFFECT_WAVES` list.
@@ -51,13 +66,110 @@ pub struct SoundEntry {
 	pub length_multiplier: u8,
 }
 
-/// Get a notes string as written in ZZT OOP, and convert it to a list of `SoundEntry` (which is
-/// what the sound player actually accepts).
-pub fn process_notes_string(notes_string: &[u8]) -> Vec<SoundEntry> {
+impl SoundEntry {
+	/// Get how long the entry plays for, in milliseconds.
+	pub fn duration_ms(&self) -> f64 {
+		WHOLE_NOTE_MS / 32. * self.length_multiplier as f64
+	}
+}
+
+/// The frequency in Hz of the timer that drives the PC speaker. A note's frequency is this divided
+/// by the note's divisor.
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+/// Get the frequency in Hz of the note with the given `sound_code` (the octave times 16, plus the
+/// note within the octave, where 0 is C). Sound codes that aren't notes have a frequency of 0.
+pub fn sound_code_frequency(sound_code: u8) -> u16 {
+	let octave = (sound_code / 16) as i32;
+	let note = (sound_code % 16) as i32;
+	if octave < 1 || note >= 12 {
+		return 0;
+	}
+	(64. * 2f64.powi(octave - 1) * 2f64.powf(note as f64 / 12.)).floor() as u16
+}
+
+/// Get the divisor the PC speaker's timer is set to to play the given `frequency`, or 0 for
+/// silence.
+pub fn frequency_divisor(frequency: u16) -> u16 {
+	if frequency == 0 {
+		0
+	} else {
+		(PIT_FREQUENCY / frequency as u32).min(u16::MAX as u32) as u16
+	}
+}
+
+/// What a `NoteEvent` plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoteKind {
+	/// Silence (`x` in a notes string).
+	Rest,
+	/// A note with the given `sound_code` (see `sound_code_frequency`), which plays at `frequency`
+	/// Hz by setting the PC speaker's timer to `divisor`.
+	Tone {
+		sound_code: u8,
+		frequency: u16,
+		divisor: u16,
+	},
+	/// The drum with the given index in `SOUND_EFFECT_WAVES` (0-9).
+	Drum(u8),
+}
+
+/// A single note, rest or drum from a notes string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteEvent {
+	pub kind: NoteKind,
+	/// How long the note lasts, in ticks of the DOS timer (about 18.2 per second). A 32nd note is 1
+	/// tick and a whole note is 32 ticks.
+	pub duration_ticks: u8,
+}
+
+impl NoteEvent {
+	/// Get the `SoundEntry` that plays this note.
+	pub fn to_sound_entry(&self) -> SoundEntry {
+		let sound_code = match self.kind {
+			NoteKind::Rest => 0,
+			NoteKind::Tone{sound_code, ..} => sound_code,
+			NoteKind::Drum(drum_index) => drum_index + 240,
+		};
+		SoundEntry {
+			sound_code,
+			length_multiplier: self.duration_ticks,
+		}
+	}
+}
+
+/// A character in a notes string that doesn't mean anything, which DOS ZZT skips without
+/// complaining.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotesWarning {
+	/// The index of the character in the notes string.
+	pub position: usize,
+	pub char_code: u8,
+}
+
+impl NotesWarning {
+	/// Describe the problem, eg. "Unknown character 'z' at position 3 of the notes".
+	pub fn description(&self) -> String {
+		format!("Unknown character '{}' at position {} of the notes", std::ascii::escape_default(self.char_code), self.position)
+	}
+}
+
+/// Get a notes string as written in ZZT OOP, and convert it to a list of `NoteEvent`s. Characters
+/// that don't mean anything are skipped, like DOS ZZT does (see `process_notes_string_with_warnings`
+/// to find them).
+pub fn process_notes_string(notes_string: &[u8]) -> Vec<NoteEvent> {
+	process_notes_string_with_warnings(notes_string).0
+}
+
+/// Like `process_notes_string`, but also get a warning for each character that was skipped because
+/// it doesn't mean anything. Spaces are skipped without a warning. A `#` or `!` that isn't straight
+/// after a note is also meaningless.
+pub fn process_notes_string_with_warnings(notes_string: &[u8]) -> (Vec<NoteEvent>, Vec<NotesWarning>) {
 	let mut current_note_index = 0;
-	let mut octave_offset = 3;
+	let mut octave_offset: u8 = 3;
 	let mut length_multiplier = 1;
 	let mut result = vec![];
+	let mut warnings = vec![];
 
 	while current_note_index < notes_string.len() {
 		match notes_string[current_note_index].to_ascii_lowercase() {
@@ -96,9 +208,9 @@ pub fn process_notes_string(notes_string: &[u8]) -> Vec<SoundEntry> {
 				}
 			}
 			b'x' => {
-				result.push(SoundEntry{
-					sound_code: 0,
-					length_multiplier,
+				result.push(NoteEvent {
+					kind: NoteKind::Rest,
+					duration_ticks: length_multiplier,
 				});
 			}
 			note_name @ b'a' ..= b'g' => {
@@ -119,28 +231,230 @@ pub fn process_notes_string(notes_string: &[u8]) -> Vec<SoundEntry> {
 					}
 				}
 
-				let sound_code = octave_offset * 16 + scale_index;
+				let sound_code = (octave_offset * 16).wrapping_add(scale_index);
+				let frequency = sound_code_frequency(sound_code);
 
-				result.push(SoundEntry{
-					sound_code,
-					length_multiplier,
+				result.push(NoteEvent {
+					kind: NoteKind::Tone {
+						sound_code,
+						frequency,
+						divisor: frequency_divisor(frequency),
+					},
+					duration_ticks: length_multiplier,
 				});
 			}
 			// This doesn't include b'3', which is matched above.
 			sound_effect_char @ b'0'..= b'9' => {
-				let sound_effect_index = sound_effect_char - b'0';
-				let sound_code = sound_effect_index + 240;
-
-				result.push(SoundEntry{
-					sound_code,
-					length_multiplier,
+				result.push(NoteEvent {
+					kind: NoteKind::Drum(sound_effect_char - b'0'),
+					duration_ticks: length_multiplier,
+				});
+			}
+			b' ' => {}
+			char_code => {
+				warnings.push(NotesWarning {
+					position: current_note_index,
+					char_code,
 				});
 			}
-			_ => {}
 		}
 
 		current_note_index += 1;
 	}
 
-	result
+	(result, warnings)
+}
+
+/// Get a notes string as written in ZZT OOP, and convert it to a list of `SoundEntry` (which is
+/// what the sound player actually accepts). This is `process_notes_string` for code that still
+/// works with `SoundEntry`s.
+pub fn process_notes_string_to_sound_entries(notes_string: &[u8]) -> Vec<SoundEntry> {
+	process_notes_string(notes_string).iter().map(NoteEvent::to_sound_entry).collect()
+}
+
+/// The most bytes of sound that DOS ZZT's sound buffer can hold. Each `SoundEntry` takes 2 bytes.
+/// Music that would make the buffer longer than this is dropped instead of being appended.
+pub const SOUND_QUEUE_MAX_BYTES: usize = 255;
+/// How long a whole note (a `length_multiplier` of 32) plays for, in milliseconds.
+pub const WHOLE_NOTE_MS: f64 = 1800.;
+
+/// What `SoundQueue::queue` did with a sound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundQueueResult {
+	/// The sound was dropped.
+	Ignored,
+	/// The sound will play after the sounds that are already queued.
+	Appended,
+	/// The sound replaced whatever was playing, so front-ends should stop the current entry and
+	/// start on the next one straight away.
+	Replaced,
+}
+
+/// The queue of sounds waiting to be played, which decides which sounds get played the same way as
+/// DOS ZZT does. The engine keeps the queue that is actually heard in `RuzztEngine::sound_queue`
+/// and moves it along with `advance`, so front-ends only need to play the `playing_entry`. Other
+/// users can instead take the next entry with `next_entry` whenever the last one has finished
+/// playing.
+///
+/// The rules are:
+/// - When nothing is playing, any sound is played.
+/// - Sounds with a `Level` at least as high as the currently playing sound's level replace it.
+/// - Music is appended to whatever is playing, unless that would go over `SOUND_QUEUE_MAX_BYTES`.
+/// - While music is playing (that didn't get appended to a sound effect), sound effects are
+///   ignored.
+/// - The ambient loop (see `set_ambient_loop`) plays whenever nothing else is, and any other sound
+///   replaces it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoundQueue {
+	/// The entries that haven't started playing yet.
+	entries: VecDeque<SoundEntry>,
+	/// The priority of the sound that is currently playing.
+	current_priority: SoundPriority,
+	/// True while the entry most recently returned from `next_entry` is still meant to be playing.
+	is_entry_playing: bool,
+	/// The sound that is repeated while nothing else is playing. Empty if there isn't one.
+	ambient_loop: Vec<SoundEntry>,
+	/// True while the sound that is playing is the `ambient_loop`.
+	is_ambient_playing: bool,
+	/// The entry that `advance` is playing.
+	#[serde(default)]
+	playing_entry: Option<SoundEntry>,
+	/// How many more milliseconds the `playing_entry` plays for.
+	#[serde(default)]
+	playing_entry_remaining_ms: f64,
+}
+
+impl Default for SoundQueue {
+	fn default() -> SoundQueue {
+		SoundQueue::new()
+	}
+}
+
+impl SoundQueue {
+	/// Make an empty sound queue.
+	pub fn new() -> SoundQueue {
+		SoundQueue {
+			entries: VecDeque::new(),
+			current_priority: SoundPriority::Level(0),
+			is_entry_playing: false,
+			ambient_loop: vec![],
+			is_ambient_playing: false,
+			playing_entry: None,
+			playing_entry_remaining_ms: 0.,
+		}
+	}
+
+	/// True if there is a sound playing or waiting to be played.
+	pub fn is_playing(&self) -> bool {
+		self.is_entry_playing || !self.entries.is_empty()
+	}
+
+	/// Get the priority of the sound that is currently playing.
+	pub fn current_priority(&self) -> SoundPriority {
+		self.current_priority
+	}
+
+	/// Try to queue up the given `sound_entries` with the given `priority`, following the priority
+	/// rules (see `SoundQueue`).
+	pub fn queue(&mut self, sound_entries: Vec<SoundEntry>, priority: SoundPriority) -> SoundQueueResult {
+		// The ambient loop doesn't count as playing, so anything can replace it.
+		let is_playing = self.is_playing() && !self.is_ambient_playing;
+
+		if is_playing && !priority.is_higher_priority_than(&self.current_priority) {
+			return SoundQueueResult::Ignored;
+		}
+
+		if priority == SoundPriority::Music && is_playing {
+			if (self.entries.len() + sound_entries.len()) * 2 >= SOUND_QUEUE_MAX_BYTES {
+				return SoundQueueResult::Ignored;
+			}
+			self.entries.extend(sound_entries);
+			SoundQueueResult::Appended
+		} else {
+			self.current_priority = priority;
+			self.entries.clear();
+			self.entries.extend(sound_entries);
+			self.is_entry_playing = false;
+			self.is_ambient_playing = false;
+			self.playing_entry_remaining_ms = 0.;
+			SoundQueueResult::Replaced
+		}
+	}
+
+	/// Take the next entry to play, or None if the sound has finished. This should be called each
+	/// time the previous entry finishes.
+	pub fn next_entry(&mut self) -> Option<SoundEntry> {
+		if self.entries.is_empty() && !self.ambient_loop.is_empty() {
+			self.entries.extend(self.ambient_loop.iter().cloned());
+			self.current_priority = SoundPriority::Level(0);
+			self.is_ambient_playing = true;
+		}
+
+		let next_entry = self.entries.pop_front();
+		self.is_entry_playing = next_entry.is_some();
+		next_entry
+	}
+
+	/// Stop all sound. The ambient loop starts again from the beginning the next time
+	/// `next_entry` is called.
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.is_entry_playing = false;
+		self.is_ambient_playing = false;
+		self.playing_entry_remaining_ms = 0.;
+	}
+
+	/// Get the entry that is playing at the current point of `advance`, or None if it's silent.
+	pub fn playing_entry(&self) -> Option<&SoundEntry> {
+		self.playing_entry.as_ref()
+	}
+
+	/// Get the number of milliseconds until `advance` moves on from the `playing_entry`, or None
+	/// if nothing is playing.
+	pub fn ms_until_next_entry(&self) -> Option<f64> {
+		self.playing_entry.as_ref().map(|_| self.playing_entry_remaining_ms)
+	}
+
+	/// Play the queue for `elapsed_ms` milliseconds, moving on to the next entries as each one
+	/// finishes. Returns true if the `playing_entry` changed (including when an entry finished and
+	/// the same one started again), so front-ends know to start playing it from the beginning.
+	pub fn advance(&mut self, elapsed_ms: f64) -> bool {
+		let mut remaining_ms = elapsed_ms;
+		let mut changed = false;
+		loop {
+			if self.playing_entry.is_some() {
+				if self.playing_entry_remaining_ms > remaining_ms {
+					self.playing_entry_remaining_ms -= remaining_ms;
+					return changed;
+				}
+				remaining_ms -= self.playing_entry_remaining_ms;
+				self.playing_entry = None;
+				self.playing_entry_remaining_ms = 0.;
+				changed = true;
+			}
+
+			match self.next_entry() {
+				Some(entry) => {
+					self.playing_entry_remaining_ms = entry.duration_ms();
+					self.playing_entry = Some(entry);
+					changed = true;
+				}
+				None => return changed,
+			}
+		}
+	}
+
+	/// Set the sound that is repeated whenever nothing else is playing (see
+	/// `BoardMessage::SetAmbientMusic`). An empty `sound_entries` stops the ambient sound.
+	pub fn set_ambient_loop(&mut self, sound_entries: Vec<SoundEntry>) {
+		if self.is_ambient_playing {
+			self.clear();
+		}
+		// A loop that takes no time would keep `advance` going forever, so it's left silent.
+		if sound_entries.iter().all(|entry| entry.length_multiplier == 0) {
+			self.ambient_loop = vec![];
+		} else {
+			self.ambient_loop = sound_entries;
+		}
+	}
 }
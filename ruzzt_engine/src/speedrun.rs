@@ -0,0 +1,228 @@
+use crate::console::*;
+
+use zzt_file_format::WorldHeader;
+
+/// The number of game cycles simulated per second at normal speed, used to turn the number of
+/// cycles into an in-game time.
+pub const CYCLES_PER_SECOND: f64 = 9.3;
+
+/// What has to happen for a `Split` to be reached.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitCondition {
+	/// The player is on the board with the given index.
+	EnterBoard(i16),
+	/// The flag with the given name is set (ignoring case).
+	SetFlag(String),
+}
+
+impl SplitCondition {
+	/// True if the condition is met in the state described by `world_header`.
+	pub fn is_met(&self, world_header: &WorldHeader) -> bool {
+		match self {
+			SplitCondition::EnterBoard(board_index) => world_header.player_board == *board_index,
+			SplitCondition::SetFlag(flag_name) => {
+				world_header.flag_names.iter().any(|set_flag| set_flag.to_string(false).eq_ignore_ascii_case(flag_name))
+			}
+		}
+	}
+}
+
+/// A point in a speedrun that the time is recorded at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Split {
+	/// The name of the split, shown in LiveSplit.
+	pub name: String,
+	/// When the split is reached.
+	pub condition: SplitCondition,
+	/// The real time in seconds since the run started when the split was reached, or None if it
+	/// hasn't been reached yet.
+	pub real_time_seconds: Option<f64>,
+	/// The number of game cycles since the run started when the split was reached, or None if it
+	/// hasn't been reached yet.
+	pub game_cycles: Option<usize>,
+}
+
+impl Split {
+	/// Make a split that hasn't been reached yet.
+	pub fn new(name: String, condition: SplitCondition) -> Split {
+		Split {
+			name,
+			condition,
+			real_time_seconds: None,
+			game_cycles: None,
+		}
+	}
+}
+
+/// Parse a list of split definitions. Each line is either `board <index> <name>` or
+/// `flag <flag name> <name>`. Empty lines and lines starting with `#` are ignored.
+pub fn parse_splits(text: &str) -> Result<Vec<Split>, String> {
+	let mut splits = vec![];
+	for (line_index, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let mut parts = line.splitn(3, char::is_whitespace);
+		let kind = parts.next().unwrap_or("");
+		let value = parts.next().ok_or_else(|| format!("Line {}: Missing split condition", line_index + 1))?;
+		let name = parts.next().unwrap_or(value).trim().to_string();
+
+		let condition = match kind {
+			"board" => {
+				let board_index = value.parse().map_err(|_| format!("Line {}: Invalid board index: {}", line_index + 1, value))?;
+				SplitCondition::EnterBoard(board_index)
+			}
+			"flag" => SplitCondition::SetFlag(value.to_string()),
+			_ => return Err(format!("Line {}: Unknown split type: {}", line_index + 1, kind)),
+		};
+
+		splits.push(Split::new(name, condition));
+	}
+	Ok(splits)
+}
+
+/// Format a number of `seconds` like a speedrun timer, eg. `1:23.4` or `1:02:03.4`.
+fn format_timer(seconds: f64) -> String {
+	let tenths = (seconds * 10.) as u64;
+	let hours = tenths / 36000;
+	let minutes = (tenths / 600) % 60;
+	let seconds = (tenths / 10) % 60;
+	if hours > 0 {
+		format!("{}:{:02}:{:02}.{}", hours, minutes, seconds, tenths % 10)
+	} else {
+		format!("{}:{:02}.{}", minutes, seconds, tenths % 10)
+	}
+}
+
+/// Format a number of `seconds` the way LiveSplit stores times, eg. `00:01:23.4000000`.
+fn format_livesplit_time(seconds: f64) -> String {
+	let total_ticks = (seconds * 10_000_000.).round() as u64;
+	let whole_seconds = total_ticks / 10_000_000;
+	format!("{:02}:{:02}:{:02}.{:07}", whole_seconds / 3600, (whole_seconds / 60) % 60, whole_seconds % 60, total_ticks % 10_000_000)
+}
+
+/// Escape the characters that can't appear in XML text.
+fn escape_xml(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Times a speedrun in both real time and in-game time, recording the time of each `Split` as it is
+/// reached.
+#[derive(Debug, Clone)]
+pub struct SpeedrunTimer {
+	/// The splits of the run, in the order they have to be reached.
+	pub splits: Vec<Split>,
+	/// The index within `splits` of the next split to be reached.
+	current_split: usize,
+	/// The real time in seconds (as passed to `update`) when the run started, or None if the run
+	/// hasn't started yet.
+	start_time_seconds: Option<f64>,
+	/// The `global_cycle` when the run started.
+	start_cycle: usize,
+	/// The real time in seconds since the run started.
+	pub real_time_seconds: f64,
+	/// The number of game cycles since the run started.
+	pub game_cycles: usize,
+}
+
+impl SpeedrunTimer {
+	/// Make a timer that will record the given `splits`.
+	pub fn new(splits: Vec<Split>) -> SpeedrunTimer {
+		SpeedrunTimer {
+			splits,
+			current_split: 0,
+			start_time_seconds: None,
+			start_cycle: 0,
+			real_time_seconds: 0.,
+			game_cycles: 0,
+		}
+	}
+
+	/// True when every split has been reached. The timer stops once the run is finished.
+	pub fn is_finished(&self) -> bool {
+		!self.splits.is_empty() && self.current_split >= self.splits.len()
+	}
+
+	/// The in-game time in seconds since the run started.
+	pub fn game_time_seconds(&self) -> f64 {
+		self.game_cycles as f64 / CYCLES_PER_SECOND
+	}
+
+	/// Update the timer after a step. The run starts the first time this is called while `playing`
+	/// is true (ie. the game isn't paused or in the title screen). `global_cycle` and
+	/// `global_time_passed_seconds` are the same as in the `RuzztEngine`.
+	pub fn update(&mut self, world_header: &WorldHeader, playing: bool, global_cycle: usize, global_time_passed_seconds: f64) {
+		if self.is_finished() {
+			return;
+		}
+
+		let start_time_seconds = match self.start_time_seconds {
+			Some(start_time_seconds) => start_time_seconds,
+			None if playing => {
+				self.start_time_seconds = Some(global_time_passed_seconds);
+				self.start_cycle = global_cycle;
+				global_time_passed_seconds
+			}
+			None => return,
+		};
+
+		self.real_time_seconds = global_time_passed_seconds - start_time_seconds;
+		// Loading a savestate from before the run started can take the cycle back past the start.
+		self.game_cycles = global_cycle.saturating_sub(self.start_cycle);
+
+		if let Some(split) = self.splits.get_mut(self.current_split) {
+			if split.condition.is_met(world_header) {
+				split.real_time_seconds = Some(self.real_time_seconds);
+				split.game_cycles = Some(self.game_cycles);
+				self.current_split += 1;
+			}
+		}
+	}
+
+	/// Draw the real time and in-game time at the top of the side bar.
+	pub fn draw(&self, console_state: &mut ConsoleState) {
+		let colour = if self.is_finished() { ConsoleColour::LightGreen } else { ConsoleColour::White };
+		console_state.draw_text_at(62, 3, b"RTA", ConsoleColour::Blue, ConsoleColour::Yellow);
+		console_state.draw_text_at(66, 3, format_timer(self.real_time_seconds).as_bytes(), ConsoleColour::Blue, colour);
+		console_state.draw_text_at(62, 4, b"IGT", ConsoleColour::Blue, ConsoleColour::Yellow);
+		console_state.draw_text_at(66, 4, format_timer(self.game_time_seconds()).as_bytes(), ConsoleColour::Blue, colour);
+	}
+
+	/// Get the splits in the LiveSplit `.lss` format, with the recorded times as the personal best.
+	pub fn to_livesplit(&self, game_name: &str, category_name: &str) -> String {
+		let mut result = String::new();
+		result += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+		result += "<Run version=\"1.7.0\">\n";
+		result += "  <GameIcon />\n";
+		result += &format!("  <GameName>{}</GameName>\n", escape_xml(game_name));
+		result += &format!("  <CategoryName>{}</CategoryName>\n", escape_xml(category_name));
+		result += "  <Offset>00:00:00</Offset>\n";
+		result += "  <AttemptCount>1</AttemptCount>\n";
+		result += "  <AttemptHistory />\n";
+		result += "  <Segments>\n";
+		for split in &self.splits {
+			result += "    <Segment>\n";
+			result += &format!("      <Name>{}</Name>\n", escape_xml(&split.name));
+			result += "      <Icon />\n";
+			result += "      <SplitTimes>\n";
+			if let (Some(real_time_seconds), Some(game_cycles)) = (split.real_time_seconds, split.game_cycles) {
+				result += "        <SplitTime name=\"Personal Best\">\n";
+				result += &format!("          <RealTime>{}</RealTime>\n", format_livesplit_time(real_time_seconds));
+				result += &format!("          <GameTime>{}</GameTime>\n", format_livesplit_time(game_cycles as f64 / CYCLES_PER_SECOND));
+				result += "        </SplitTime>\n";
+			} else {
+				result += "        <SplitTime name=\"Personal Best\" />\n";
+			}
+			result += "      </SplitTimes>\n";
+			result += "      <BestSegmentTime />\n";
+			result += "      <SegmentHistory />\n";
+			result += "    </Segment>\n";
+		}
+		result += "  </Segments>\n";
+		result += "  <AutoSplitterSettings />\n";
+		result += "</Run>\n";
+		result
+	}
+}
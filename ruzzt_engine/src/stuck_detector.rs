@@ -0,0 +1,122 @@
+use crate::board_simulator::*;
+
+use zzt_file_format::{BoardTile, ElementType};
+
+use num::FromPrimitive;
+
+use std::collections::VecDeque;
+
+/// True if the player can walk onto `tile`, either because it is empty or because touching it
+/// removes it (eg. picking up an item or clearing a forest).
+fn is_walkable(tile: BoardTile, sim: &BoardSimulator) -> bool {
+	match ElementType::from_u8(tile.element_id) {
+		Some(ElementType::Empty) | Some(ElementType::Fake) | Some(ElementType::Forest)
+		| Some(ElementType::Ammo) | Some(ElementType::Torch) | Some(ElementType::Gem)
+		| Some(ElementType::Key) | Some(ElementType::Energizer) | Some(ElementType::Player) => true,
+		Some(ElementType::Door) => {
+			sim.key_index_for_colour(tile.colour >> 4).is_some_and(|key_index| sim.has_key(key_index))
+		}
+		_ => false,
+	}
+}
+
+/// True if touching or shooting `tile` could change something on the board, which means the player
+/// still has something to do.
+fn is_interactive(tile: BoardTile, sim: &BoardSimulator) -> bool {
+	match ElementType::from_u8(tile.element_id) {
+		Some(ElementType::Ammo) | Some(ElementType::Torch) | Some(ElementType::Gem)
+		| Some(ElementType::Key) | Some(ElementType::Energizer) | Some(ElementType::Scroll)
+		| Some(ElementType::Passage) | Some(ElementType::Object) | Some(ElementType::Bomb)
+		| Some(ElementType::Transporter) | Some(ElementType::Boulder) | Some(ElementType::SliderNS)
+		| Some(ElementType::SliderEW) | Some(ElementType::Forest) => true,
+		Some(ElementType::Door) => is_walkable(tile, sim),
+		Some(ElementType::Breakable) => {
+			sim.world_header.player_ammo > 0 && sim.board_meta_data.max_player_shots > 0
+		}
+		_ => false,
+	}
+}
+
+/// Find every tile the player can reach from where they are standing without any help, by walking
+/// over empty tiles, items, forests and doors they have the key for. The result has an entry for
+/// each tile (including the board edges), in rows from the top-left.
+pub fn reachable_tiles(sim: &BoardSimulator) -> Vec<bool> {
+	let mut reachable = vec![false; BOARD_WIDTH * BOARD_HEIGHT];
+	let (player_x, player_y) = sim.get_player_location();
+	let mut to_visit = VecDeque::new();
+	to_visit.push_back((player_x, player_y));
+	reachable[player_y as usize * BOARD_WIDTH + player_x as usize] = true;
+
+	while let Some((x, y)) = to_visit.pop_front() {
+		for &(off_x, off_y) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+			let (next_x, next_y) = (x + off_x, y + off_y);
+			if let Some(tile) = sim.get_tile(next_x, next_y) {
+				let index = next_y as usize * BOARD_WIDTH + next_x as usize;
+				if !reachable[index] && is_walkable(tile, sim) {
+					reachable[index] = true;
+					to_visit.push_back((next_x, next_y));
+				}
+			}
+		}
+	}
+
+	reachable
+}
+
+/// True if the board edge at `x`/`y` leads to another board.
+fn is_exit(x: i16, y: i16, sim: &BoardSimulator) -> bool {
+	let meta_data = &sim.board_meta_data;
+	let exit = if y == 0 {
+		meta_data.exit_north
+	} else if y == BOARD_HEIGHT as i16 - 1 {
+		meta_data.exit_south
+	} else if x == 0 {
+		meta_data.exit_west
+	} else {
+		meta_data.exit_east
+	};
+	exit != 0
+}
+
+/// True if there is an object on the board that is still running code, which means a script might
+/// still open up a way out for the player.
+fn has_running_object(sim: &BoardSimulator) -> bool {
+	sim.status_elements.iter().any(|status| {
+		let is_object = sim.get_status_tile(status).is_some_and(|tile| tile.element_id == ElementType::Object as u8);
+		is_object && status.code_current_instruction >= 0 && !sim.get_status_code(status).is_empty()
+	})
+}
+
+/// Guess whether the player is stuck on the current board with no way to make progress: nothing
+/// they can reach can be picked up, touched, pushed or shot, they don't have the keys for any doors
+/// they can reach, they can't walk off the board, and no object is still running a script that
+/// could get them out. This is only a heuristic, so it is meant for hinting and playtesting tools.
+pub fn player_appears_stuck(sim: &BoardSimulator) -> bool {
+	if has_running_object(sim) {
+		return false;
+	}
+
+	let reachable = reachable_tiles(sim);
+	for y in 0 .. BOARD_HEIGHT as i16 {
+		for x in 0 .. BOARD_WIDTH as i16 {
+			if !reachable[y as usize * BOARD_WIDTH + x as usize] {
+				continue;
+			}
+
+			for &(off_x, off_y) in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+				let (next_x, next_y) = (x + off_x, y + off_y);
+				if let Some(tile) = sim.get_tile(next_x, next_y) {
+					if tile.element_id == ElementType::BoardEdge as u8 {
+						if is_exit(next_x, next_y, sim) {
+							return false;
+						}
+					} else if is_interactive(tile, sim) {
+						return false;
+					}
+				}
+			}
+		}
+	}
+
+	true
+}
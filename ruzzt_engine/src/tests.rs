@@ -3,3 +3,14 @@
 mod world_tester;
 mod basic;
 mod oop;
+mod display;
+#[cfg(feature = "file-io")]
+mod dos_files;
+mod observer;
+mod sounds;
+mod compatibility;
+mod stuck_detector;
+mod serialization;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod robustness;
@@ -1,4 +1,6 @@
 use crate::tests::world_tester::*;
+use crate::board_message::BoardMessage;
+use crate::event::KeyRepeatState;
 
 #[test]
 fn player_move() {
@@ -21,7 +23,7 @@ fn push_blocks() {
 	let mut world = TestWorld::new_with_player(1, 1);
 	
 	let mut tile_set = TileSet::new();
-	tile_set.add('>', BoardTile::new(ElementType::Pusher, 0xff), Some(StatusElement {
+	tile_set.add('>', BoardTile::new(ElementType::Pusher, 0x0f), Some(StatusElement {
 		cycle: 3,
 		step_x: 1,
 		.. StatusElement::default()
@@ -49,11 +51,11 @@ fn centipede_form_heads() {
 	let mut world = TestWorld::new_with_player(1, 1);
 	
 	let mut tile_set = TileSet::new();
-	tile_set.add('O', BoardTile::new(ElementType::Segment, 0xff), Some(StatusElement {
+	tile_set.add('O', BoardTile::new(ElementType::Segment, 0x0f), Some(StatusElement {
 		cycle: 1,
 		.. StatusElement::default()
 	}));
-	tile_set.add('@', BoardTile::new(ElementType::Head, 0xff), Some(StatusElement {
+	tile_set.add('@', BoardTile::new(ElementType::Head, 0x0f), Some(StatusElement {
 		cycle: 1,
 		.. StatusElement::default()
 	}));
@@ -101,11 +103,11 @@ fn centipede_walk() {
 		let mut world = TestWorld::new_with_player(1, 1);
 		
 		let mut tile_set = TileSet::new();
-		tile_set.add('O', BoardTile::new(ElementType::Segment, 0xff), Some(StatusElement {
+		tile_set.add('O', BoardTile::new(ElementType::Segment, 0x0f), Some(StatusElement {
 			cycle: 1,
 			.. StatusElement::default()
 		}));
-		tile_set.add('@', BoardTile::new(ElementType::Head, 0xff), Some(StatusElement {
+		tile_set.add('@', BoardTile::new(ElementType::Head, 0x0f), Some(StatusElement {
 			cycle: 1,
 			.. StatusElement::default()
 		}));
@@ -161,3 +163,1471 @@ fn centipede_walk() {
 		assert!(world.current_board_tiles_equals(expected_step3));
 	}
 }
+
+#[test]
+fn super_zzt_player_picks_up_stone() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	let sim = &mut world.engine.board_simulator;
+	sim.world_header.world_type = WorldType::SuperZzt;
+	sim.world_header.player_stones = Some(0);
+	crate::zzt_behaviours::load_behaviours(sim);
+	sim.set_tile(21, 20, BoardTile { element_id: SuperZztElementType::Stone as u8, colour: 0x0f });
+	// Lava blocks the player.
+	sim.set_tile(19, 20, BoardTile { element_id: SuperZztElementType::Lava as u8, colour: 0x4e });
+
+	world.event = Event::Left;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (20, 20));
+
+	world.event = Event::Right;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (21, 20));
+	assert_eq!(world.world_header().player_stones, Some(1));
+}
+
+#[test]
+fn super_zzt_shots_and_water_currents() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	let sim = &mut world.engine.board_simulator;
+	sim.world_header.world_type = WorldType::SuperZzt;
+	sim.world_header.player_ammo = 10;
+	sim.ruleset = crate::ruleset::builtin_ruleset(WorldType::SuperZzt);
+	sim.load_ruleset_behaviours();
+
+	// Shots use Super ZZT's bullet ID.
+	world.event = Event::ShootUp;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_tile(20, 19).unwrap().element_id, SuperZztElementType::Bullet as u8);
+	assert_eq!(world.engine.board_simulator.player_bullet_count(), 1);
+	while world.engine.is_part_way_though_step() {
+		world.engine.step(Event::None, 0.);
+	}
+
+	// The player is carried along by the currents until there aren't any more.
+	world.engine.board_simulator.set_tile(21, 20, BoardTile { element_id: SuperZztElementType::WaterE as u8, colour: 0x1f });
+	world.engine.board_simulator.set_tile(22, 20, BoardTile { element_id: SuperZztElementType::WaterS as u8, colour: 0x1f });
+	world.event = Event::Right;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (21, 20));
+	world.event = Event::None;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (22, 20));
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (22, 21));
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (22, 21));
+	assert_eq!(world.engine.board_simulator.get_tile(21, 20).unwrap().element_id, SuperZztElementType::WaterE as u8);
+}
+
+#[test]
+fn compatibility_version_shooting_over_water() {
+	let run = |compatibility_version: CompatibilityVersion| {
+		let mut world = TestWorld::new_with_player(20, 20);
+		world.engine.set_compatibility_version(compatibility_version);
+		world.engine.board_simulator.world_header.player_ammo = 10;
+		world.engine.board_simulator.set_tile(20, 17, BoardTile { element_id: ElementType::Water as u8, colour: 0x9f });
+
+		world.event = Event::ShootUp;
+		world.simulate(1);
+		world.event = Event::None;
+		world.simulate(2);
+		let water_tile = world.engine.board_simulator.get_tile(20, 17).unwrap();
+		(water_tile.element_id, world.engine.board_simulator.player_bullet_count())
+	};
+
+	assert_eq!(run(CompatibilityVersion::Zzt32), (ElementType::Bullet as u8, 1));
+	// ZZT 3.0 bullets stop at water like it is a wall.
+	assert_eq!(run(CompatibilityVersion::Zzt30), (ElementType::Water as u8, 0));
+}
+
+#[test]
+fn status_element_limit() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+	world.engine.board_simulator.status_element_limit = Some(1);
+
+	// There is no room for a bullet's status, so it isn't shot.
+	let board_messages = world.engine.step(Event::ShootRight, 0.);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 1);
+	assert!(board_messages.iter().any(|message| matches!(message, crate::board_message::BoardMessage::EngineLog(..))));
+
+	// Finish the step that was paused by the board message.
+	while world.engine.is_part_way_though_step() {
+		world.engine.step(Event::None, 0.);
+	}
+
+	world.engine.set_unlimited_status_elements(true);
+	world.engine.step(Event::ShootRight, 0.);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 2);
+}
+
+#[test]
+fn step_whole_cycle() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+	world.engine.board_simulator.status_element_limit = Some(1);
+
+	// The log message pauses the step, but the whole cycle is still simulated.
+	let start_cycle = world.engine.global_cycle;
+	let mut logged = false;
+	world.engine.step_whole_cycle(Event::ShootRight, 0., |engine, board_messages| {
+		for board_message in board_messages {
+			logged |= matches!(board_message, crate::board_message::BoardMessage::EngineLog(..));
+			engine.process_board_message(board_message);
+		}
+	});
+	assert!(logged);
+	assert!(!world.engine.is_part_way_though_step());
+	assert_eq!(world.engine.global_cycle, start_cycle + 1);
+}
+
+#[test]
+fn board_edge() {
+	let mut world = TestWorld::new_with_player(1, 10);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+	world.engine.board_simulator.set_tile(60, 10, BoardTile::new(ElementType::Boulder, 0x0f));
+	// Positions off the side of the board don't wrap around to the next row.
+	assert_eq!(world.engine.board_simulator.get_tile(-1, 11), None);
+	assert_eq!(world.engine.board_simulator.get_tile(62, 10), None);
+
+	// Shots into the edge don't leave a bullet behind.
+	world.event = Event::ShootLeft;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 1);
+	assert_eq!(world.engine.board_simulator.get_tile(0, 10).unwrap().element_id, ElementType::BoardEdge as u8);
+
+	// Pushing something into the edge fails.
+	world.engine.board_simulator.move_tile(1, 10, 59, 10);
+	world.event = Event::Right;
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (59, 10));
+	assert_eq!(world.engine.board_simulator.get_tile(60, 10).unwrap().element_id, ElementType::Boulder as u8);
+
+	// Nothing can be moved onto the edge directly either.
+	world.engine.board_simulator.move_tile(60, 10, 61, 10);
+	assert_eq!(world.engine.board_simulator.get_tile(61, 10).unwrap().element_id, ElementType::BoardEdge as u8);
+}
+
+#[test]
+fn player_hurt() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.set_tile(11, 10, BoardTile::new(ElementType::Lion, 0x0c));
+	world.engine.board_simulator.status_elements.push(StatusElement {
+		location_x: 11,
+		location_y: 10,
+		cycle: 0,
+		.. StatusElement::default()
+	});
+	
+	// Walking into a monster kills it and hurts the player once.
+	world.event = Event::Right;
+	world.simulate(1);
+	assert_eq!(world.world_header().player_health, 90);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 1);
+	
+	// Nothing hurts the player while they are energized.
+	let sim = &mut world.engine.board_simulator;
+	let mut accumulated_data = AccumulatedActionData::new();
+	sim.world_header.energy_cycles = 5;
+	sim.hurt_player(0, &mut accumulated_data);
+	assert_eq!(sim.world_header.player_health, 90);
+}
+
+#[test]
+fn ruleset_player_hurt() {
+	use crate::behaviour::{Action, PlayerItemType};
+	use crate::board_simulator::BoardSimulator;
+	use crate::ruleset::{RulesetPlugin, ZztRuleset};
+	use std::rc::Rc;
+	
+	// A ruleset that is the same as ZZT, but where getting hurt only takes away ammo.
+	#[derive(Debug)]
+	struct AmmoHurtRuleset;
+	
+	impl RulesetPlugin for AmmoHurtRuleset {
+		fn name(&self) -> &str {
+			"Ammo hurt"
+		}
+		
+		fn element_type(&self, element_id: u8) -> Option<ElementType> {
+			ZztRuleset.element_type(element_id)
+		}
+		
+		fn load_behaviours(&self, sim: &mut BoardSimulator) {
+			ZztRuleset.load_behaviours(sim);
+		}
+		
+		fn add_player_hurt_actions(&self, _sim: &BoardSimulator, actions: &mut Vec<Action>) {
+			actions.push(Action::ModifyPlayerItem{item_type: PlayerItemType::Ammo, offset: -1, require_exact_amount: false});
+		}
+	}
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.set_ruleset(Some(Rc::new(AmmoHurtRuleset)));
+	let sim = &mut world.engine.board_simulator;
+	sim.world_header.player_ammo = 5;
+	let health = sim.world_header.player_health;
+	
+	// Everything that hurts the player goes through the ruleset, including running out of time.
+	let mut accumulated_data = AccumulatedActionData::new();
+	sim.hurt_player(0, &mut accumulated_data);
+	sim.board_meta_data.time_limit = 1;
+	sim.world_header.time_passed = 1;
+	sim.on_time_limit_second_passed(0, &mut accumulated_data);
+	assert_eq!(sim.world_header.player_ammo, 3);
+	assert_eq!(sim.world_header.player_health, health);
+	assert!(accumulated_data.board_messages.is_empty());
+}
+
+#[test]
+fn time_limit() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let sim = &mut world.engine.board_simulator;
+	let mut accumulated_data = AccumulatedActionData::new();
+	sim.board_meta_data.time_limit = 12;
+	sim.world_header.time_passed = 0;
+	
+	sim.on_time_limit_second_passed(0, &mut accumulated_data);
+	assert_eq!(sim.time_left(), Some(11));
+	assert!(accumulated_data.board_messages.is_empty());
+	sim.on_time_limit_second_passed(0, &mut accumulated_data);
+	assert_eq!(accumulated_data.board_messages.len(), 1);
+	
+	// Once the time has run out, the player is hurt every second.
+	for _ in 0 .. 13 {
+		sim.on_time_limit_second_passed(0, &mut accumulated_data);
+	}
+	assert_eq!(sim.time_left(), Some(-3));
+	assert_eq!(sim.world_header.player_health, 70);
+	
+	// With restart on zap, the player goes back to where they entered the board, the timer is
+	// reset, and the game is paused.
+	sim.board_meta_data.restart_on_zap = true;
+	sim.board_meta_data.player_enter_x = 5;
+	sim.board_meta_data.player_enter_y = 6;
+	accumulated_data.board_messages.clear();
+	sim.on_time_limit_second_passed(0, &mut accumulated_data);
+	assert_eq!(sim.world_header.player_health, 60);
+	assert_eq!(sim.get_player_location(), (5, 6));
+	assert_eq!(sim.time_left(), Some(12));
+	assert!(accumulated_data.board_messages.iter().any(|message| matches!(message, BoardMessage::PauseGame)));
+}
+
+#[test]
+fn set_board_time_left() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	assert_eq!(world.engine.board_time_left(), None);
+	assert!(world.engine.set_board_time_left(30).is_err());
+	
+	world.engine.board_simulator.board_meta_data.time_limit = 60;
+	world.engine.set_time_passed(50);
+	assert_eq!(world.engine.board_time_left(), Some(10));
+	
+	// Extra time comes off the time passed, and the board's time limit stays the same.
+	world.engine.set_board_time_left(100).unwrap();
+	assert_eq!(world.engine.board_time_left(), Some(100));
+	assert_eq!(world.engine.time_passed(), -40);
+	assert_eq!(world.engine.board_simulator.board_meta_data.time_limit, 60);
+}
+
+#[test]
+fn savestates() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_health = 50;
+	
+	// Loading an empty slot leaves the game alone.
+	world.event = Event::QuickLoad;
+	world.simulate(1);
+	assert_eq!(world.world_header().player_health, 50);
+	
+	world.event = Event::SelectSavestateSlot(3);
+	world.simulate(1);
+	world.event = Event::QuickSave;
+	world.simulate(1);
+	assert!(world.engine.savestate_slots[3].is_some());
+	
+	world.engine.board_simulator.world_header.player_health = 20;
+	world.event = Event::QuickLoad;
+	world.simulate(1);
+	assert_eq!(world.world_header().player_health, 50);
+	assert!(world.engine.load_savestate(0).is_err());
+}
+
+#[test]
+fn speedrun_timer_after_loading_savestate() {
+	use crate::speedrun::SpeedrunTimer;
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.event = Event::QuickSave;
+	world.simulate(1);
+	
+	world.engine.speedrun_timer = Some(SpeedrunTimer::new(vec![]));
+	world.event = Event::None;
+	world.simulate(10);
+	assert!(world.engine.speedrun_timer.as_ref().unwrap().game_cycles >= 9);
+	
+	// The savestate is from before the run started, so the time can't go any lower than 0.
+	world.event = Event::QuickLoad;
+	world.simulate(1);
+	assert_eq!(world.engine.speedrun_timer.as_ref().unwrap().game_cycles, 0);
+}
+
+#[test]
+fn seeded_simulation_is_deterministic() {
+	let mut tile_set = TileSet::new();
+	tile_set.add('L', BoardTile::new(ElementType::Lion, 0x0c), Some(StatusElement {
+		cycle: 2,
+		param1: 4,
+		.. StatusElement::default()
+	}));
+	tile_set.add('T', BoardTile::new(ElementType::Tiger, 0x0b), Some(StatusElement {
+		cycle: 2,
+		param1: 4,
+		param2: 3,
+		.. StatusElement::default()
+	}));
+	tile_set.add('R', BoardTile::new(ElementType::Ruffian, 0x0d), Some(StatusElement {
+		cycle: 1,
+		param1: 4,
+		param2: 4,
+		.. StatusElement::default()
+	}));
+	tile_set.add_object('O', "#go rndp n\n#go rndne\n#restart");
+	tile_set.add('.', BoardTile::new(ElementType::Empty, 0), None);
+	let template = TileTemplate::from_text(&tile_set, "
+		L.T.R.O
+		.......
+		O.R.T.L
+	");
+	
+	let run = |seed: u64| {
+		let mut world = TestWorld::new_with_player(30, 12);
+		world.insert_template(&template, 5, 5);
+		world.insert_template(&template, 40, 18);
+		world.engine.board_simulator.world_header.energy_cycles = i16::MAX;
+		world.engine.seed_rng(seed);
+		world.simulate(10000);
+		world.engine.board_simulator.state_hash()
+	};
+	
+	assert_eq!(run(1234), run(1234));
+	assert_ne!(run(1234), run(5678));
+}
+
+#[test]
+fn debug_commands() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let run_command = |world: &mut TestWorld, command: &[u8]| {
+		let board_messages = world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_slice(command)));
+		assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::PlaySoundArray(..))));
+	};
+	
+	run_command(&mut world, b"TORCHES");
+	run_command(&mut world, b"keys");
+	assert_eq!(world.world_header().player_torches, Some(3));
+	assert_eq!(world.world_header().player_keys, [true; 7]);
+	
+	run_command(&mut world, b"+secret");
+	assert!(world.world_header().last_matching_flag(DosString::from_slice(b"SECRET")).is_some());
+	run_command(&mut world, b"-secret");
+	assert!(world.world_header().last_matching_flag(DosString::from_slice(b"SECRET")).is_none());
+	
+	// Setting or clearing the DARK flag also changes whether the board is dark.
+	run_command(&mut world, b"+dark");
+	assert!(world.engine.board_simulator.board_meta_data.is_dark);
+	run_command(&mut world, b"-dark");
+	assert!(!world.engine.board_simulator.board_meta_data.is_dark);
+}
+
+#[test]
+fn campaign_carry_over() {
+	let carry_over = crate::campaign::CampaignCarryOver::parse("# Comment\nflags: secret, done\nscore\n").unwrap();
+	assert!(crate::campaign::CampaignCarryOver::parse("lives").is_err());
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_score = 120;
+	world.engine.board_simulator.world_header.player_ammo = 7;
+	world.engine.board_simulator.world_header.flag_names[0] = DosString::from_slice(b"SECRET");
+	world.engine.board_simulator.world_header.flag_names[1] = DosString::from_slice(b"OTHER");
+	let mut next_world = world.engine.world.clone();
+	
+	// Nothing is carried over by default.
+	world.engine.apply_campaign_carry_over(&mut next_world);
+	assert_eq!(next_world.world_header.player_score, 0);
+	
+	world.engine.campaign_carry_over = Some(carry_over);
+	world.engine.apply_campaign_carry_over(&mut next_world);
+	assert_eq!(next_world.world_header.player_score, 120);
+	assert_eq!(next_world.world_header.player_ammo, 0);
+	assert!(next_world.world_header.last_matching_flag(DosString::from_slice(b"SECRET")).is_some());
+	assert!(next_world.world_header.last_matching_flag(DosString::from_slice(b"OTHER")).is_none());
+	
+	// Scroll links to other worlds open them.
+	let board_messages = world.engine.process_board_message(BoardMessage::LinkClicked(DosString::from_slice(b"-next.zzt")));
+	assert!(matches!(board_messages.as_slice(), [BoardMessage::OpenWorld{filename}] if filename.data == b"NEXT.ZZT"));
+}
+
+#[test]
+fn find_tiles() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.set_tile(20, 5, BoardTile::new(ElementType::Lion, 0x0c));
+	world.engine.board_simulator.status_elements.push(StatusElement {
+		location_x: 20,
+		location_y: 5,
+		param1: 3,
+		.. StatusElement::default()
+	});
+	world.engine.board_simulator.set_tile(4, 8, BoardTile::new(ElementType::Lion, 0x0c));
+	
+	let sim = &world.engine.board_simulator;
+	let lions: Vec<_> = sim.find_tiles(ElementType::Lion as u8).map(|(x, y, _, status)| (x, y, status.map(|status| status.param1))).collect();
+	assert_eq!(lions, vec![(20, 5, Some(3)), (4, 8, None)]);
+	assert_eq!(sim.count_tiles(ElementType::Lion as u8), 2);
+	assert_eq!(sim.board_tiles().count(), (BOARD_WIDTH - 2) * (BOARD_HEIGHT - 2));
+}
+
+#[test]
+fn uncommon_key_colours() {
+	// Touching keys and doors opens a scroll, which pauses the step part way through.
+	fn step_right(world: &mut TestWorld) {
+		world.engine.step(Event::Right, 0.);
+		while world.engine.is_part_way_though_step() {
+			world.engine.step(Event::None, 0.);
+		}
+	}
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_gems = 5;
+	// A dark blue key is a blue key, and a door with a blinking blue background is a blue door.
+	world.engine.board_simulator.set_tile(11, 10, BoardTile::new(ElementType::Key, 0x01));
+	world.engine.board_simulator.set_tile(12, 10, BoardTile::new(ElementType::Door, 0x9f));
+	// Black keys and doors use the high byte of the gem count.
+	world.engine.board_simulator.set_tile(13, 10, BoardTile::new(ElementType::Key, 0x08));
+	world.engine.board_simulator.set_tile(14, 10, BoardTile::new(ElementType::Door, 0x0f));
+	world.engine.board_simulator.set_tile(15, 10, BoardTile::new(ElementType::Key, 0x00));
+	
+	step_right(&mut world);
+	assert!(world.world_header().player_keys[0]);
+	step_right(&mut world);
+	assert!(!world.world_header().player_keys[0]);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (12, 10));
+	
+	step_right(&mut world);
+	assert_eq!(world.world_header().player_gems, 261);
+	step_right(&mut world);
+	assert_eq!(world.world_header().player_gems, 5);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (14, 10));
+	
+	world.engine.set_key_colour_policy(KeyColourPolicy::Sanitized);
+	step_right(&mut world);
+	assert_eq!(world.world_header().player_gems, 5);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (14, 10));
+}
+
+#[test]
+fn under_tiles() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+	world.engine.board_simulator.set_tile(12, 10, BoardTile::new(ElementType::Fake, 0x2e));
+	world.engine.board_simulator.set_tile(14, 10, BoardTile::new(ElementType::Water, 0x9f));
+	
+	// The bullet leaves the fake wall and water behind as it passes over them.
+	world.event = Event::ShootRight;
+	world.simulate(60);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 1);
+	assert_eq!(world.engine.board_simulator.get_tile(12, 10), Some(BoardTile::new(ElementType::Fake, 0x2e)));
+	assert_eq!(world.engine.board_simulator.get_tile(14, 10), Some(BoardTile::new(ElementType::Water, 0x9f)));
+	
+	// An object walking over a fake wall takes its background colour, then puts it back.
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "/s/s\n");
+	world.insert_tile_and_status(tile_set.get('O'), 12, 9);
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_tile(12, 10), Some(BoardTile::new(ElementType::Object, 0x2f)));
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_tile(12, 10), Some(BoardTile::new(ElementType::Fake, 0x2e)));
+	assert_eq!(world.engine.board_simulator.get_tile(12, 11), Some(BoardTile::new(ElementType::Object, 0x0f)));
+	
+	// When a status replaces another one, it is on top of whatever the old one was on.
+	world.engine.board_simulator.set_tile(20, 10, BoardTile::new(ElementType::Fake, 0x2e));
+	tile_set.add_object('B', "/s#become lion\n");
+	world.insert_tile_and_status(tile_set.get('B'), 20, 9);
+	world.simulate(2);
+	let (_, lion) = world.engine.board_simulator.get_first_status_for_pos(20, 10).unwrap();
+	assert_eq!((lion.under_element_id, lion.under_colour), (ElementType::Fake as u8, 0x2e));
+}
+
+#[test]
+fn step_profile() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#cycle 1\n/i\n");
+	world.insert_tile_and_status(tile_set.get('O'), 20, 10);
+	assert_eq!(world.engine.take_profile(), None);
+	
+	world.engine.set_profiling_enabled(true);
+	world.simulate(3);
+	let profile = world.engine.take_profile().unwrap();
+	assert_eq!(profile.step_count, 3);
+	assert_eq!(profile.element_step_times[&(ElementType::Object as u8)].status_count, 3);
+	assert_eq!(profile.element_step_times[&(ElementType::Player as u8)].status_count, 3);
+	assert!(profile.oop_operations >= 3);
+	assert!(profile.actions_applied > 0);
+	assert!(profile.continuation_iterations >= profile.oop_operations);
+	assert!(profile.to_string().contains("Object"));
+	
+	// Taking the profile starts counting again.
+	assert_eq!(world.engine.take_profile().unwrap().step_count, 0);
+}
+
+#[test]
+fn enter_dismisses_caption() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.caption_state = Some(crate::caption::CaptionState::new(DosString::from_slice(b"Hello")));
+	let expected = world.clone();
+	
+	// Enter closes the caption, and the player doesn't see it at all.
+	world.event = Event::Enter;
+	world.simulate(1);
+	assert!(world.engine.caption_state.is_none());
+	assert!(world.current_board_equals(expected));
+}
+
+#[test]
+fn link_board_exits() {
+	use crate::board_links::*;
+	use crate::direction::Direction;
+	
+	let mut world = World::zzt_default();
+	for _ in 0 .. 4 {
+		world.boards.push(world.boards[0].clone());
+	}
+	
+	let layout = BoardLayout::parse("# Board 1 is above 3.\n1 .\n3 4 # comment\n2\n").unwrap();
+	link_exits(&mut world, &layout).unwrap();
+	let exits = |board_index: usize| {
+		let meta_data = &world.boards[board_index].meta_data;
+		[meta_data.exit_north, meta_data.exit_south, meta_data.exit_west, meta_data.exit_east]
+	};
+	assert_eq!(exits(1), [0, 3, 0, 0]);
+	assert_eq!(exits(3), [1, 2, 0, 4]);
+	assert_eq!(exits(4), [0, 0, 3, 0]);
+	assert_eq!(exits(2), [3, 0, 0, 0]);
+	assert_eq!(check_exit_reciprocity(&world), vec![]);
+	
+	// A one-way exit is reported.
+	world.boards[4].meta_data.exit_east = 1;
+	assert_eq!(check_exit_reciprocity(&world), vec![ExitMismatch {
+		board_index: 4,
+		direction: Direction::East,
+		destination: 1,
+		return_exit: Some(0),
+	}]);
+	
+	assert!(link_exits(&mut world, &BoardLayout::grid(0, 2, 4)).is_err());
+	assert!(link_exits(&mut world, &BoardLayout::parse("1 1").unwrap()).is_err());
+	assert!(BoardLayout::parse("1 x").is_err());
+}
+
+#[test]
+fn trim_world() {
+	use crate::world_trim::*;
+	
+	let mut world = World::zzt_default();
+	for _ in 0 .. 4 {
+		world.boards.push(world.boards[0].clone());
+	}
+	world.world_header.num_boards_except_title = 4;
+	world.world_header.player_board = 1;
+	// Board 1 leads to board 3 through an exit, and board 3 leads to board 4 through a passage.
+	// Nothing leads to board 2.
+	world.boards[1].meta_data.exit_east = 3;
+	world.boards[3].tiles[(5 - 1) * 60 + 10 - 1] = BoardTile::new(ElementType::Passage, 0x0f);
+	world.boards[3].status_elements.push(StatusElement {
+		location_x: 10,
+		location_y: 5,
+		param3: 4,
+		.. StatusElement::default()
+	});
+	world.boards[4].status_elements.push(StatusElement {
+		location_x: 20,
+		location_y: 5,
+		code_source: CodeSource::Owned(DosString::from_str("@obj\n#end\nnever\n#die\n:touch\nHi\n#END\n'zapped\n")),
+		.. StatusElement::default()
+	});
+	world.boards[4].status_elements.push(StatusElement {
+		location_x: 21,
+		location_y: 5,
+		code_source: CodeSource::Bound(1),
+		code_current_instruction: 24,
+		.. StatusElement::default()
+	});
+	
+	assert_eq!(reachable_boards(&world), vec![true, true, false, true, true]);
+	assert_eq!(dead_code_ranges(b"#end\rdead\r:label\r#end\r"), vec![5 .. 10]);
+	
+	let report = trim_world(&mut world).unwrap();
+	assert_eq!(report.removed_boards, vec![2]);
+	assert_eq!(report.removed_code, vec![RemovedCode{board_index: 3, status_index: 1, byte_count: 11}]);
+	assert_eq!(world.boards.len(), 4);
+	assert_eq!(world.boards[1].meta_data.exit_east, 2);
+	assert_eq!(world.boards[2].status_elements[1].param3, 3);
+	let trimmed_board = &world.boards[3];
+	assert_eq!(trimmed_board.status_code(1).unwrap().data, b"@obj\r#end\r:touch\rHi\r#END\r'zapped\r");
+	// The bound status element still points at the same instruction.
+	assert_eq!(trimmed_board.status_elements[2].code_current_instruction, 13);
+	world.validate().unwrap();
+}
+
+#[test]
+fn savestate_restores_ui_state() {
+	use crate::board_message::OneTimeNotification;
+	use crate::caption::CaptionState;
+	use crate::savestate::Savestate;
+	use crate::scroll::ScrollState;
+	use crate::sounds::{SoundEntry, SoundPriority, SoundQueue, WHOLE_NOTE_MS};
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.shown_one_time_notifications.insert(OneTimeNotification::NoAmmo);
+	world.engine.caption_state = Some(CaptionState::new(DosString::from_str("Hello")));
+	world.engine.scroll_state = Some(ScrollState::new_title_content(DosString::from_str("Scroll"), vec![DosString::from_str("Line")]));
+	world.engine.process_board_message(BoardMessage::PlaySoundArray(vec![SoundEntry{sound_code: 48, length_multiplier: 2}], SoundPriority::Music));
+	world.engine.advance_sound(10.);
+	let sound_queue = world.engine.sound_queue.clone();
+	world.engine.save_savestate(0).unwrap();
+	
+	let savestate = world.engine.savestate_slots[0].clone().unwrap();
+	world.engine.shown_one_time_notifications.clear();
+	world.engine.caption_state = None;
+	world.engine.scroll_state = None;
+	world.engine.sound_queue = SoundQueue::new();
+	world.engine.load_savestate(0).unwrap();
+	assert!(world.engine.shown_one_time_notifications.contains(&OneTimeNotification::NoAmmo));
+	assert_eq!(world.engine.caption_state, savestate.caption_state);
+	assert_eq!(world.engine.scroll_state, savestate.scroll_state);
+	let board_messages = world.engine.step(Event::None, 0.);
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::RestoreSoundQueue(queue) if *queue == sound_queue)));
+	assert_eq!(world.engine.sound_queue, sound_queue);
+	assert_eq!(world.engine.sound_queue.ms_until_next_entry(), Some(WHOLE_NOTE_MS / 16. - 10.));
+	
+	// Everything apart from the world can be written out and read back in.
+	let mut ui_state_data = vec![];
+	savestate.write_ui_state(&mut ui_state_data).unwrap();
+	let mut read_savestate = Savestate::new(savestate.world.clone(), 1);
+	read_savestate.parse_ui_state(&mut ui_state_data.as_slice()).unwrap();
+	assert_eq!(read_savestate, savestate);
+	
+	// Newer versions of the format are rejected.
+	let newer_data = String::from_utf8(ui_state_data).unwrap().replacen("\"format_version\": 1", "\"format_version\": 99", 1);
+	assert!(read_savestate.parse_ui_state(&mut newer_data.as_bytes()).is_err());
+}
+
+#[test]
+fn override_behaviour() {
+	use crate::behaviour::{Action, ActionResult, Behaviour};
+	
+	/// A lion that doesn't move, and turns yellow instead.
+	#[derive(Debug)]
+	struct YellowLion;
+	
+	impl Behaviour for YellowLion {
+		fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, _sim: &BoardSimulator) -> ActionResult {
+			ActionResult::with_actions(vec![Action::SetColour {
+				x: status.location_x as i16,
+				y: status.location_y as i16,
+				colour: 0x0e,
+			}])
+		}
+	}
+	
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add('L', BoardTile::new(ElementType::Lion, 0x0c), Some(StatusElement {
+		cycle: 1,
+		.. StatusElement::default()
+	}));
+	world.insert_tile_and_status(tile_set.get('L'), 20, 10);
+	
+	world.engine.override_behaviour(ElementType::Lion, Box::new(YellowLion));
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_tile(20, 10), Some(BoardTile::new(ElementType::Lion, 0x0e)));
+	
+	// The override stays when a world is loaded.
+	world.engine.sync_world();
+	let game_world = world.engine.world.clone();
+	world.engine.load_world(game_world, None).unwrap();
+	assert_eq!(format!("{:?}", world.engine.board_simulator.behaviour_for_pos(20, 10)), "YellowLion");
+}
+
+#[test]
+fn copy_and_paste_board_region() {
+	use crate::board_region::BoardRegion;
+	
+	let mut board = Board::zzt_default(DosString::from_str("Test"));
+	board.tiles[(5 - 1) * 60 + 10 - 1] = BoardTile::new(ElementType::Object, 0x5d);
+	board.tiles[(5 - 1) * 60 + 11 - 1] = BoardTile::new(ElementType::Object, 0x2a);
+	board.tiles[(6 - 1) * 60 + 10 - 1] = BoardTile::new(ElementType::Breakable, 0x4e);
+	board.status_elements.push(StatusElement {
+		location_x: 10,
+		location_y: 5,
+		param1: 2,
+		cycle: 3,
+		code_source: CodeSource::Owned(DosString::from_str("@Bob\r#end\r")),
+		.. StatusElement::default()
+	});
+	board.status_elements.push(StatusElement {
+		location_x: 11,
+		location_y: 5,
+		param1: 1,
+		code_source: CodeSource::Bound(1),
+		.. StatusElement::default()
+	});
+	
+	let region = BoardRegion::copy(&board, 10, 5, 2, 2).unwrap();
+	let region = BoardRegion::from_json(&region.to_json()).unwrap();
+	region.paste(&mut board, 30, 20).unwrap();
+	
+	assert_eq!(board.tiles[(20 - 1) * 60 + 30 - 1], BoardTile::new(ElementType::Object, 0x5d));
+	assert_eq!(board.tiles[(20 - 1) * 60 + 31 - 1], BoardTile::new(ElementType::Object, 0x2a));
+	assert_eq!(board.tiles[(21 - 1) * 60 + 30 - 1], BoardTile::new(ElementType::Breakable, 0x4e));
+	assert_eq!(board.status_elements.len(), 5);
+	let pasted = &board.status_elements[3];
+	assert_eq!((pasted.location_x, pasted.location_y, pasted.param1, pasted.cycle), (30, 20, 2, 3));
+	// The bound object gets its own copy of the code.
+	assert_eq!(board.status_code(4), Some(&DosString::from_str("@Bob\r#end\r")));
+	assert_eq!(board.status_elements[4].code_source, CodeSource::Owned(DosString::from_str("@Bob\r#end\r")));
+	
+	// Pasting over the originals replaces them.
+	region.paste(&mut board, 10, 5).unwrap();
+	assert_eq!(board.status_elements.len(), 5);
+	assert!(BoardRegion::copy(&board, 60, 1, 2, 1).is_err());
+}
+
+#[test]
+fn restart_on_zap_after_loading_mid_board() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.board_meta_data.restart_on_zap = true;
+	world.engine.board_simulator.board_meta_data.player_enter_x = 5;
+	world.engine.board_simulator.board_meta_data.player_enter_y = 5;
+	
+	// Restoring a savestate enters the board where the player is standing.
+	world.engine.save_savestate(0).unwrap();
+	world.engine.load_savestate(0).unwrap();
+	let meta_data = &world.engine.board_simulator.board_meta_data;
+	assert_eq!((meta_data.player_enter_x, meta_data.player_enter_y), (10, 10));
+	
+	world.engine.board_simulator.move_tile(10, 10, 20, 12);
+	world.engine.board_simulator.hurt_player(1, &mut AccumulatedActionData::new());
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	
+	// Loading a world that was saved mid-board does the same, once the game starts.
+	world.engine.board_simulator.move_tile(10, 10, 30, 15);
+	world.engine.sync_world();
+	let saved_world = world.engine.world.clone();
+	world.engine.set_in_title_screen(true);
+	world.engine.load_world(saved_world, None).unwrap();
+	world.engine.set_in_title_screen(false);
+	world.engine.board_simulator.move_tile(30, 15, 40, 20);
+	world.engine.board_simulator.hurt_player(1, &mut AccumulatedActionData::new());
+	assert_eq!(world.engine.board_simulator.get_player_location(), (30, 15));
+}
+
+#[test]
+fn step_outcome() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	
+	world.engine.step(Event::Right, 0.);
+	let outcome = world.engine.last_step_outcome().clone();
+	assert!(outcome.completed_full_step);
+	assert_eq!(outcome.global_cycle, world.engine.global_cycle);
+	assert!(outcome.player_moved);
+	assert!(!outcome.player_hurt);
+	assert!(!outcome.board_changed);
+	assert!(outcome.actions_applied > 0);
+	
+	world.engine.board_simulator.hurt_player(1, &mut AccumulatedActionData::new());
+	world.engine.step(Event::None, 0.);
+	let outcome = world.engine.last_step_outcome().clone();
+	assert!(outcome.player_hurt);
+	assert!(!outcome.player_moved);
+	
+	// Nothing is simulated while the game is paused.
+	world.engine.is_paused = true;
+	world.engine.step(Event::None, 0.);
+	let outcome = world.engine.last_step_outcome().clone();
+	assert!(!outcome.completed_full_step);
+	assert!(!outcome.player_hurt);
+	assert_eq!(outcome.actions_applied, 0);
+}
+
+#[test]
+fn queued_captions() {
+	use crate::caption::CAPTION_REPLACEMENT_TIME;
+	use crate::constants::CAPTION_TIME;
+	
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.queue_caption(DosString::from_str("You now have the Blue key."));
+	world.engine.queue_caption(DosString::from_str("The Blue door is now open."));
+	world.engine.queue_caption(DosString::from_str("The Blue door is now open."));
+	assert_eq!(world.engine.pending_caption_count(), 1);
+	
+	// The first caption is cut short, then the second one is shown for the usual time.
+	world.simulate(CAPTION_REPLACEMENT_TIME as usize);
+	let caption_state = world.engine.caption_state.clone().unwrap();
+	assert!(caption_state.has_text(&DosString::from_str("The Blue door is now open.")));
+	assert_eq!(caption_state.time_left, CAPTION_TIME);
+	assert_eq!(world.engine.pending_caption_count(), 0);
+	
+	// Showing the same caption again restarts it.
+	world.simulate(5);
+	world.engine.queue_caption(DosString::from_str("The Blue door is now open."));
+	assert_eq!(world.engine.caption_state.as_ref().unwrap().time_left, CAPTION_TIME);
+	assert_eq!(world.engine.pending_caption_count(), 0);
+	
+	// Enter skips to the next caption.
+	world.engine.queue_caption(DosString::from_str("Ouch!"));
+	world.event = Event::Enter;
+	world.simulate(1);
+	assert!(world.engine.caption_state.as_ref().unwrap().has_text(&DosString::from_str("Ouch!")));
+}
+
+#[test]
+fn board_invariant_violations() {
+	use crate::board_invariants::find_invariant_violations;
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.simulate(3);
+	assert!(find_invariant_violations(&world.engine.board_simulator).is_empty());
+	
+	let mut tile_set = TileSet::new();
+	tile_set.add('O', BoardTile::new(ElementType::Object, 0x0f), Some(StatusElement {
+		under_element_id: ElementType::Player as u8,
+		code_source: CodeSource::Bound(5),
+		.. StatusElement::default()
+	}));
+	world.insert_tile_and_status(tile_set.get('O'), 20, 10);
+	world.engine.board_simulator.set_tile(10, 10, BoardTile::new(ElementType::Empty, 0));
+	assert_eq!(find_invariant_violations(&world.engine.board_simulator).len(), 3);
+}
+
+#[test]
+fn board_and_sim_positions() {
+	use crate::board_simulator::{screen_pos_to_sim_pos, BoardPos, SimPos};
+	
+	let board_pos = BoardPos::new(0, 0);
+	assert_eq!(SimPos::from(board_pos), SimPos::new(1, 1));
+	assert_eq!(BoardPos::from(SimPos::new(60, 25)).tile_index(), Some(60 * 25 - 1));
+	assert_eq!(SimPos::new(0, 5).to_board_pos().tile_index(), None);
+	assert!(SimPos::new(61, 5).is_board_edge());
+	assert_eq!(screen_pos_to_sim_pos(0, 0), Some(SimPos::new(1, 1)));
+	assert_eq!(screen_pos_to_sim_pos(60, 0), None);
+	
+	// Clicking to the right of the player moves them one step towards the click.
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.event = Event::Click{pos: screen_pos_to_sim_pos(20, 9).unwrap(), shoot: false};
+	world.simulate(1);
+	assert_eq!(SimPos::of_status(&world.engine.board_simulator.status_elements[0]), SimPos::new(11, 10));
+}
+
+#[test]
+fn random_fill_board() {
+	use crate::random_fill::*;
+	
+	let mut board = Board::zzt_default(DosString::from_str("Test"));
+	let lion_status = StatusElement {
+		cycle: 2,
+		param1: 3,
+		.. StatusElement::default()
+	};
+	let mut options = RandomFillOptions::new(vec![
+		RandomFillEntry::new(BoardTile::new(ElementType::Breakable, 0x0e), 0.2),
+		RandomFillEntry::with_status(BoardTile::new(ElementType::Lion, 0x0c), lion_status, 0.05),
+	], 1234);
+	options.status_element_limit = 20;
+	
+	let mut same_board = board.clone();
+	let placed_count = random_fill(&mut board, &options).unwrap();
+	assert_eq!(random_fill(&mut same_board, &options).unwrap(), placed_count);
+	assert_eq!(board, same_board);
+	
+	// The player's tile isn't touched, and every lion gets a status up to the limit.
+	let walls = board.tiles.iter().filter(|tile| tile.element_id == ElementType::Breakable as u8).count();
+	let lions = board.tiles.iter().filter(|tile| tile.element_id == ElementType::Lion as u8).count();
+	assert!(walls > 150 && walls < 450);
+	assert_eq!(lions, 19);
+	assert_eq!(walls + lions, placed_count);
+	assert_eq!(board.status_elements.len(), 20);
+	let player = &board.status_elements[0];
+	assert_eq!(board.tiles[(player.location_y as usize - 1) * 60 + player.location_x as usize - 1].element_id, ElementType::Player as u8);
+	
+	options.entries[0].density = 0.99;
+	assert!(random_fill(&mut board, &options).is_err());
+}
+
+#[test]
+fn shot_source_survives_ricochets() {
+	use crate::behaviour::ShotSource;
+	
+	for &source in &[ShotSource::Player, ShotSource::Enemy] {
+		let mut world = TestWorld::new_with_player(30, 20);
+		let mut tile_set = TileSet::new();
+		tile_set.add('*', BoardTile::new(ElementType::Bullet, 0x0f), Some(StatusElement {
+			cycle: 1,
+			step_x: 1,
+			param1: source.param1(),
+			.. StatusElement::default()
+		}));
+		tile_set.add('R', BoardTile::new(ElementType::Ricochet, 0x0a), None);
+		tile_set.add('G', BoardTile::new(ElementType::Gem, 0x0b), None);
+		world.insert_template(&TileTemplate::from_text(&tile_set, "G..*R"), 10, 10);
+		
+		// The bullet bounces off the ricochet and comes back to the gem, which only the player's
+		// shots can destroy.
+		world.simulate(4);
+		let gem_destroyed = world.engine.board_simulator.get_tile(10, 10).unwrap().element_id == ElementType::Empty as u8;
+		assert_eq!(gem_destroyed, source == ShotSource::Player);
+	}
+}
+
+#[test]
+fn shot_source_survives_duplication() {
+	use crate::behaviour::ShotSource;
+	
+	for &source in &[ShotSource::Player, ShotSource::Enemy] {
+		let mut world = TestWorld::new_with_player(30, 20);
+		let mut tile_set = TileSet::new();
+		// The bullet doesn't move, so it stays in front of the duplicator.
+		tile_set.add('*', BoardTile::new(ElementType::Bullet, 0x0f), Some(StatusElement {
+			cycle: 0,
+			param1: source.param1(),
+			.. StatusElement::default()
+		}));
+		tile_set.add('D', BoardTile::new(ElementType::Duplicator, 0x0f), Some(StatusElement {
+			cycle: 3,
+			step_x: 1,
+			param1: 4,
+			param2: 8,
+			.. StatusElement::default()
+		}));
+		world.insert_template(&TileTemplate::from_text(&tile_set, ".D*"), 10, 10);
+		
+		world.simulate(3);
+		assert_eq!(world.engine.board_simulator.get_tile(10, 10).unwrap().element_id, ElementType::Bullet as u8);
+		let (_, copied_status) = world.engine.board_simulator.get_first_status_for_pos(10, 10).unwrap();
+		assert_eq!(ShotSource::from_status(copied_status), source);
+	}
+}
+
+#[test]
+fn flag_list_toggles_flags() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.set_flag(&DosString::from_str("door"));
+	world.engine.set_flag(&DosString::from_str("KEY"));
+	world.engine.clear_flag(&DosString::from_str("door"));
+	assert_eq!(world.engine.get_flags(), vec![(1, DosString::from_str("KEY"))]);
+	
+	world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_str("flags")));
+	assert!(world.engine.scroll_state.is_some());
+	
+	// Enter on the second slot clears KEY, and it stays in the list so it can be set again.
+	let mut board_messages = world.engine.step(Event::Down, 0.);
+	board_messages.extend(world.engine.step(Event::Enter, 0.));
+	for board_message in board_messages {
+		world.engine.process_board_message(board_message);
+	}
+	assert!(world.engine.get_flags().is_empty());
+	assert_eq!(world.engine.scroll_state.as_ref().unwrap().current_line(), 1);
+	for board_message in world.engine.step(Event::Enter, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	assert_eq!(world.engine.get_flags(), vec![(0, DosString::from_str("KEY"))]);
+}
+
+#[test]
+fn tile_changes_are_recorded() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.board_simulator.tile_changes = Some(vec![]);
+	
+	world.event = Event::Right;
+	world.simulate(1);
+	let tile_changes = world.engine.board_simulator.take_tile_changes();
+	let changed_positions: Vec<(i16, i16)> = tile_changes.iter().map(|tile_change| (tile_change.x, tile_change.y)).collect();
+	assert_eq!(changed_positions, vec![(21, 20), (20, 20)]);
+	assert_eq!(tile_changes[0].new.element_id, ElementType::Player as u8);
+	assert_eq!(tile_changes[1].old.element_id, ElementType::Player as u8);
+	assert_eq!(tile_changes[1].new.element_id, ElementType::Empty as u8);
+	
+	// Nothing changes while the player stands still.
+	world.simulate(1);
+	assert!(world.engine.board_simulator.take_tile_changes().is_empty());
+}
+
+#[test]
+fn quick_key_presses_are_buffered() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	
+	// Two taps within the same step both move the player, one step after the other.
+	world.engine.key_repeat.press(Event::Right);
+	world.engine.key_repeat.release(Event::Right);
+	world.engine.key_repeat.press(Event::Down);
+	world.engine.key_repeat.release(Event::Down);
+	for _ in 0 .. 2 {
+		let event = world.engine.key_repeat.next_event();
+		world.engine.step(event, 0.);
+	}
+	assert_eq!(world.engine.board_simulator.get_player_location(), (21, 21));
+	assert_eq!(world.engine.key_repeat.next_event(), Event::None);
+}
+
+#[test]
+fn held_directions_are_resolved() {
+	let mut key_repeat = KeyRepeatState::new();
+	key_repeat.repeat_delay = 1;
+	
+	// Holding a second direction moves in that direction only, until it is let go of.
+	key_repeat.press(Event::Right);
+	key_repeat.press(Event::Up);
+	assert_eq!(key_repeat.next_event(), Event::Right);
+	assert_eq!(key_repeat.next_event(), Event::Up);
+	assert_eq!(key_repeat.next_event(), Event::Up);
+	key_repeat.release(Event::Up);
+	assert_eq!(key_repeat.next_event(), Event::Right);
+	
+	// Opposite directions cancel out, including the press that was still waiting.
+	key_repeat.release_all();
+	key_repeat.press(Event::Left);
+	key_repeat.press(Event::Right);
+	assert_eq!(key_repeat.next_event(), Event::None);
+	assert_eq!(key_repeat.next_event(), Event::None);
+	key_repeat.release(Event::Left);
+	assert_eq!(key_repeat.next_event(), Event::Right);
+	
+	// A direction pressed while holding opposites together isn't cancelled.
+	key_repeat.press(Event::Left);
+	key_repeat.press(Event::Down);
+	assert_eq!(key_repeat.next_event(), Event::Down);
+	assert_eq!(key_repeat.next_event(), Event::Down);
+}
+
+#[test]
+fn player_not_status_zero() {
+	let mut world = TestWorld::new();
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	world.add_player(20, 20);
+	
+	// Like DOS ZZT, status element 0 is still the player, and a warning is logged when the board is
+	// entered.
+	assert_eq!(world.engine.board_simulator.player_status_index(), Some(0));
+	assert!(world.engine.board_simulator.player_is_misplaced());
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	world.engine.sync_world();
+	world.engine.set_in_title_screen(false);
+	let board_messages = world.engine.step(Event::None, 0.);
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(_, text) if text.contains("status element 0"))));
+	
+	// A board with no status elements runs without a player instead of waiting for one.
+	world.engine.board_simulator.status_elements.clear();
+	assert_eq!(world.engine.board_simulator.player_status_index(), None);
+	world.engine.sync_world();
+	world.engine.set_in_title_screen(false);
+	assert!(!world.engine.is_paused);
+	let board_messages = world.engine.step(Event::Right, 0.);
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(..))));
+}
+
+#[test]
+fn restore_saved_game() {
+	let save_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/SAVED.SAV");
+	let saved_game = World::parse(&mut std::fs::File::open(save_path).unwrap()).unwrap();
+	let mut engine = RuzztEngine::new();
+	engine.restore_saved_game(saved_game).unwrap();
+	
+	// The game carries on straight away on the board it was saved on.
+	assert!(!engine.in_title_screen);
+	assert!(!engine.is_paused);
+	assert_eq!(engine.board_simulator.world_header.player_board, 2);
+	assert_eq!(engine.board_simulator.world_header.player_health, 50);
+	assert_eq!(engine.board_simulator.get_player_location(), (30, 12));
+	
+	// The board's timer doesn't count the time between saving and restoring the game.
+	let mut seconds = 30.;
+	for _ in 0 .. 10 {
+		engine.step(Event::None, seconds);
+		seconds += 0.1;
+	}
+	assert_eq!(engine.board_simulator.world_header.time_passed, 42);
+	for _ in 0 .. 10 {
+		engine.step(Event::None, seconds);
+		seconds += 0.1;
+	}
+	assert_eq!(engine.board_simulator.world_header.time_passed, 43);
+}
+
+#[test]
+fn event_help() {
+	use crate::event::{EventContext, EVENT_HELP};
+	
+	// Each event is only listed once.
+	for event_help in EVENT_HELP {
+		assert_eq!(event_help.event.help(), Some(event_help));
+	}
+	assert_eq!(Event::LightTorch.help().unwrap().default_key, "T");
+	assert_eq!(Event::PlayGame.help().unwrap().context, EventContext::TitleScreen);
+	assert_eq!(Event::SelectSavestateSlot(3).help().unwrap().default_key, "0-9");
+	assert_eq!(Event::None.help(), None);
+}
+
+#[test]
+fn machine_speed() {
+	use crate::machine_speed::MachineSpeed;
+	
+	let mut world = TestWorld::new_with_player(1, 1);
+	let normal_step_ms = world.engine.step_length_ms();
+	assert!((normal_step_ms - 1000. / 9.3).abs() < 0.001);
+	
+	// A quiet board runs at the same speed on an XT.
+	world.engine.machine_speed = MachineSpeed::from_name("Turbo-XT").unwrap();
+	assert_eq!(world.engine.step_length_ms(), normal_step_ms);
+	
+	// A busy board is slowed down by how long the XT takes to process it, but not on a 386.
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "");
+	for x in 0 .. 59 {
+		world.insert_tile_and_status(tile_set.get('O'), 2 + x, 10);
+	}
+	assert!(world.engine.step_length_ms() > normal_step_ms * 1.5);
+	world.engine.machine_speed = MachineSpeed::I386;
+	assert_eq!(world.engine.step_length_ms(), normal_step_ms);
+}
+
+#[test]
+fn compact_idle_boards() {
+	let world_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT");
+	let world = World::parse(&mut std::fs::File::open(world_path).unwrap()).unwrap();
+	let mut engine = RuzztEngine::new();
+	engine.set_compact_idle_boards(true);
+	engine.load_world(world.clone(), None).unwrap();
+	let mut expanded_engine = RuzztEngine::new();
+	expanded_engine.load_world(world, None).unwrap();
+	
+	// Only the board that isn't loaded is compacted, but it can still be read.
+	assert_eq!(engine.board_simulator.world_header.player_board, 1);
+	assert!(engine.world.boards[2].tiles.is_empty());
+	assert!(!engine.world.boards[1].tiles.is_empty());
+	assert_eq!(engine.board(2).unwrap().tiles, expanded_engine.world.boards[2].tiles);
+	
+	// Going to the board expands it, and compacts the board that was left.
+	engine.process_board_message(BoardMessage::WarpToBoard{board_index: 2});
+	expanded_engine.process_board_message(BoardMessage::WarpToBoard{board_index: 2});
+	assert!(engine.world.boards[1].tiles.is_empty());
+	assert!(!engine.world.boards[2].tiles.is_empty());
+	
+	// A copy of the world has every board, without expanding the engine's boards.
+	assert_eq!(engine.world_copy(), expanded_engine.world_copy());
+	assert!(engine.world.boards[1].tiles.is_empty());
+
+	// Syncing the world expands every board, so it is the same as without compaction.
+	assert_eq!(engine.sync_world(), expanded_engine.sync_world());
+}
+
+#[test]
+fn message_timeline() {
+	use crate::behaviour::LabelOperation;
+	
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('L', "@lever\n#send door:open\n#end\n:done\n#zap done\n#end");
+	tile_set.add_object('D', "@door\n#end\n:open\n#send lever:done\n#end");
+	world.insert_template(&TileTemplate::from_text(&tile_set, "LD"), 10, 10);
+	world.engine.set_message_timeline_enabled(true);
+	
+	world.engine.step(Event::None, 0.);
+	world.engine.step(Event::None, 0.);
+	let message_timeline = world.engine.message_timeline().unwrap();
+	let deliveries: Vec<_> = message_timeline.deliveries.iter()
+		.map(|delivery| (delivery.cycle, delivery.sender_index, delivery.receiver_index, delivery.label.to_string(false), delivery.operation))
+		.collect();
+	assert_eq!(deliveries, vec![
+		(1, Some(1), 2, "open".to_string(), LabelOperation::Jump),
+		(1, Some(2), 1, "done".to_string(), LabelOperation::Jump),
+		(2, Some(1), 1, "done".to_string(), LabelOperation::Zap),
+	]);
+	assert_eq!(message_timeline.deliveries[0].description(), "   1 @lever -> @door #send open");
+	assert!(message_timeline.to_json().contains("\"receiver_name\": \"door\""));
+	
+	world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_str("messages")));
+	assert!(world.engine.scroll_state.is_some());
+}
+
+#[test]
+fn board_without_status_elements() {
+	let mut world = World::zzt_default();
+	world.boards.push(Board::zzt_default(DosString::from_slice(b"Corrupt")));
+	world.world_header.num_boards_except_title = 1;
+	world.world_header.player_board = 1;
+	world.boards[1].status_elements.clear();
+	
+	// The player gets a status element on their tile, so the board can be played.
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world, None).unwrap();
+	engine.set_in_title_screen(false);
+	assert_eq!(engine.board_simulator.player_status_index(), Some(0));
+	assert_eq!(engine.board_simulator.get_player_location(), (30, 12));
+	engine.sync_world();
+	assert!(engine.world.validate().is_ok());
+}
+
+#[test]
+fn board_rng_seed_repeats_randomness() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_rng_seed = Some(1234);
+	let mut rolls_on_entering = || {
+		world.engine.process_board_message(BoardMessage::WarpToBoard{board_index: 1});
+		(0 .. 8).map(|_| world.engine.board_simulator.random_range(0, 1000)).collect::<Vec<i32>>()
+	};
+	
+	// The randomness is the same every time the board is entered.
+	let first_rolls = rolls_on_entering();
+	assert_eq!(rolls_on_entering(), first_rolls);
+	
+	// Each board and world seed gets different randomness.
+	assert_ne!(board_rng_seed(1234, 1), board_rng_seed(1234, 2));
+	assert_ne!(board_rng_seed(1234, 1), board_rng_seed(1235, 1));
+}
+
+#[test]
+fn locked_passages() {
+	use crate::compatibility::step_cycle;
+	use crate::engine::ExtensionLevel;
+	use crate::event::TypingEvent;
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	let passage_status = |code: &str| StatusElement {
+		param3: 1,
+		code_source: CodeSource::Owned(DosString::from_str(code)),
+		.. StatusElement::default()
+	};
+	let mut tile_set = TileSet::new();
+	tile_set.add('1', BoardTile::new(ElementType::Passage, 0x1f), Some(passage_status("#lock opened")));
+	tile_set.add('2', BoardTile::new(ElementType::Passage, 0x1e), Some(passage_status("'Passage to the vault\n#password XyZzY")));
+	world.insert_template(&TileTemplate::from_text(&tile_set, "1"), 11, 10);
+	world.insert_template(&TileTemplate::from_text(&tile_set, "2"), 10, 11);
+	let mut dos_world = world.clone();
+	
+	world.engine.set_extension_level(ExtensionLevel::Ruzzt);
+	step_cycle(&mut world.engine, Event::Right);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	// The passage leads back to the same board, so the player ends up on top of it.
+	world.engine.board_simulator.world_header.flag_names[0] = DosString::from_str("OPENED");
+	step_cycle(&mut world.engine, Event::Right);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (11, 10));
+	
+	let type_password = |world: &mut TestWorld, password: &[u8]| {
+		step_cycle(&mut world.engine, Event::Down);
+		assert!(world.engine.in_typing_mode());
+		for &c in password {
+			world.engine.process_typing(TypingEvent::Char(c));
+		}
+		let mut board_messages = world.engine.process_typing(TypingEvent::Enter);
+		while !board_messages.is_empty() {
+			let board_message = board_messages.remove(0);
+			board_messages.extend(world.engine.process_board_message(board_message));
+		}
+	};
+	world.engine.board_simulator.move_tile(11, 10, 10, 10);
+	type_password(&mut world, b"plugh");
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	type_password(&mut world, b"xyzzy");
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 11));
+	// It stays unlocked.
+	assert_eq!(world.engine.board_simulator.status_elements[2].code_source, CodeSource::Owned(DosString::new()));
+	
+	// Worlds made for DOS ZZT can have anything in the code of a passage.
+	step_cycle(&mut dos_world.engine, Event::Right);
+	assert_eq!(dos_world.engine.board_simulator.get_player_location(), (11, 10));
+}
+
+#[test]
+fn load_world_with_start_state() {
+	let world_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT");
+	let world = World::parse(&mut std::fs::File::open(world_path).unwrap()).unwrap();
+	assert_eq!(world.world_header.player_board, 1);
+	let start_player = world.boards[2].status_elements[0].clone();
+	let ammo = world.world_header.player_ammo;
+	
+	let mut start_state = crate::engine::StartState::new();
+	start_state.board = Some(2);
+	start_state.health = Some(42);
+	start_state.gems = Some(7);
+	start_state.keys = Some([true, false, false, false, false, false, true]);
+	start_state.flags = vec![DosString::from_slice(b"practice")];
+	let mut engine = RuzztEngine::new();
+	engine.load_world_with_state(world, &start_state).unwrap();
+	
+	// The game starts paused on the start board, without the title screen.
+	assert!(!engine.in_title_screen);
+	assert!(engine.is_paused);
+	let world_header = &engine.board_simulator.world_header;
+	assert_eq!(world_header.player_board, 2);
+	assert_eq!(world_header.player_health, 42);
+	assert_eq!(world_header.player_gems, 7);
+	assert_eq!(world_header.player_keys, [true, false, false, false, false, false, true]);
+	assert!(world_header.last_matching_flag(DosString::from_slice(b"PRACTICE")).is_some());
+	
+	// The player starts wherever they stand on the board, and enters the board there.
+	let start_location = (start_player.location_x as i16, start_player.location_y as i16);
+	assert_eq!(engine.board_simulator.get_player_location(), start_location);
+	let meta_data = &engine.board_simulator.board_meta_data;
+	assert_eq!((meta_data.player_enter_x, meta_data.player_enter_y), (start_player.location_x, start_player.location_y));
+	
+	// Things the start state doesn't override are left as they are in the world.
+	assert_eq!(engine.board_simulator.world_header.player_ammo, ammo);
+}
+
+#[test]
+fn warp_to_board() {
+	let world_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT");
+	let mut world = World::parse(&mut std::fs::File::open(world_path).unwrap()).unwrap();
+	// Make board 2's enter position an empty tile away from where the player stands.
+	let (board_width, _) = WorldType::Zzt.board_size();
+	let board = &mut world.boards[2];
+	let player_index = board.status_elements[0].location_x as usize - 1 + (board.status_elements[0].location_y as usize - 1) * board_width;
+	let enter_index = board.tiles.iter().enumerate()
+		.position(|(index, tile)| index != player_index && tile.element_id == ElementType::Empty as u8)
+		.unwrap();
+	let enter_pos = ((enter_index % board_width + 1) as u8, (enter_index / board_width + 1) as u8);
+	board.meta_data.player_enter_x = enter_pos.0;
+	board.meta_data.player_enter_y = enter_pos.1;
+	let board_count = world.boards.len();
+	
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world, None).unwrap();
+	engine.set_in_title_screen(false);
+	engine.is_paused = false;
+	
+	// The player is moved to the enter position of the board, and the game is paused.
+	engine.process_board_message(BoardMessage::WarpToBoard{board_index: 2});
+	assert_eq!(engine.board_simulator.world_header.player_board, 2);
+	assert_eq!(engine.board_simulator.get_player_location(), (enter_pos.0 as i16, enter_pos.1 as i16));
+	assert_eq!(engine.board_simulator.get_tile(enter_pos.0 as i16, enter_pos.1 as i16).unwrap().element_id, ElementType::Player as u8);
+	let start_pos = ((player_index % board_width + 1) as i16, (player_index / board_width + 1) as i16);
+	assert_eq!(engine.board_simulator.get_tile(start_pos.0, start_pos.1).unwrap().element_id, ElementType::Empty as u8);
+	assert!(engine.is_paused);
+	
+	// Boards that don't exist are ignored.
+	engine.is_paused = false;
+	engine.process_board_message(BoardMessage::WarpToBoard{board_index: board_count});
+	assert_eq!(engine.board_simulator.world_header.player_board, 2);
+	assert_eq!(engine.board_simulator.get_player_location(), (enter_pos.0 as i16, enter_pos.1 as i16));
+	assert!(!engine.is_paused);
+}
+
+#[test]
+fn cutscene() {
+	use crate::cutscene::CutsceneCommand;
+	
+	let mut world = TestWorld::new_with_player(10, 10);
+	let logged_lines = |world: &TestWorld| -> Vec<String> {
+		world.engine.message_log.scroll_lines().iter().map(|line| line.to_string(false)).collect()
+	};
+	world.engine.play_cutscene(vec![
+		CutsceneCommand::ShowCaption(DosString::from_str("One")),
+		CutsceneCommand::Wait(2),
+		CutsceneCommand::MoveCamera{x: 20, y: 10},
+		CutsceneCommand::play_sound(b"c"),
+		CutsceneCommand::OpenScroll{title: DosString::from_str("Scroll"), content_lines: vec![DosString::from_str("Hello")]},
+		CutsceneCommand::ShowCaption(DosString::from_str("Two")),
+	]);
+	
+	// The commands run in order, and the player can't move while the cutscene runs.
+	world.engine.step(Event::Right, 0.);
+	assert_eq!(logged_lines(&world), vec!["One"]);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	world.engine.step(Event::Right, 0.);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	
+	let board_messages = world.engine.step(Event::Right, 0.);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (20, 10));
+	assert!(board_messages.iter().any(|board_message| matches!(board_message, BoardMessage::PlaySoundArray(..))));
+	assert!(world.engine.scroll_state.is_some());
+	
+	// The cutscene waits for the scroll to be closed.
+	world.engine.step(Event::None, 0.);
+	assert_eq!(logged_lines(&world), vec!["One", "", "$Scroll", "Hello"]);
+	for board_message in world.engine.step(Event::Escape, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	assert!(world.engine.scroll_state.is_none());
+	world.engine.step(Event::None, 0.);
+	assert_eq!(logged_lines(&world), vec!["One", "", "$Scroll", "Hello", "", "Two"]);
+	assert!(!world.engine.cutscene_state.is_active());
+	
+	// Once it is over, the player can move again.
+	world.engine.step(Event::Right, 0.);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (21, 10));
+	
+	// Escape skips the rest of a cutscene.
+	world.engine.play_cutscene(vec![
+		CutsceneCommand::Wait(10),
+		CutsceneCommand::ShowCaption(DosString::from_str("Skipped")),
+	]);
+	world.engine.step(Event::Escape, 0.);
+	assert!(!world.engine.cutscene_state.is_active());
+	world.engine.step(Event::None, 0.);
+	assert_eq!(logged_lines(&world).last().unwrap(), "Two");
+}
+
+#[test]
+fn agent_env() {
+	use crate::agent_env::*;
+	use crate::direction::Direction;
+	
+	// Lions move randomly, so episodes are only the same when they are seeded.
+	let mut world = TestWorld::new_with_player(30, 12);
+	for x in 5 .. 10 {
+		world.insert_tile_and_status(&TileAndStatus {
+			tile: BoardTile::new(ElementType::Lion, 0x0c),
+			status: Some(StatusElement {
+				cycle: 1,
+				param1: 4,
+				.. StatusElement::default()
+			}),
+		}, x, 5);
+	}
+	world.engine.sync_world();
+	let mut env = EngineEnv::new(world.engine.world.clone());
+	let mut play_episode = |seed: u64| {
+		env.seed = Some(seed);
+		let mut observations = vec![env.reset()];
+		for _ in 0 .. 20 {
+			observations.push(env.step(AgentAction::Idle).0);
+		}
+		observations
+	};
+	let first_episode = play_episode(1234);
+	assert_eq!(play_episode(1234), first_episode);
+	assert_ne!(play_episode(5678), first_episode);
+	
+	// Picking up a gem gives 10 points and 1 health, and the episode ends after `max_steps`.
+	let mut world = TestWorld::new_with_player(30, 12);
+	world.engine.board_simulator.set_tile(31, 12, BoardTile::new(ElementType::Gem, 0x0d));
+	world.engine.sync_world();
+	let mut env = EngineEnv::new(world.engine.world.clone());
+	env.max_steps = Some(2);
+	env.health_reward_scale = 2.;
+	let first_observation = env.reset();
+	assert_eq!((first_observation.player_x, first_observation.player_y), (30, 12));
+	let (observation, reward, done) = env.step(AgentAction::Move(Direction::East));
+	assert_eq!((observation.player_x, observation.gems), (31, first_observation.gems + 1));
+	assert_eq!(reward, 12.);
+	assert!(!done);
+	let (_, reward, done) = env.step(AgentAction::Idle);
+	assert_eq!(reward, 0.);
+	assert!(done);
+	
+	// Resetting starts the world again.
+	assert_eq!(env.reset(), first_observation);
+	assert_eq!(env.step_count, 0);
+}
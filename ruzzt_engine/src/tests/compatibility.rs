@@ -0,0 +1,147 @@
+use crate::attract_mode::AttractMode;
+use crate::compatibility::*;
+use crate::engine::RuzztEngine;
+use crate::event::Event;
+
+use zzt_file_format::World;
+
+use std::path::Path;
+
+fn load_world(path: &Path) -> World {
+	let mut file = std::fs::File::open(path).unwrap();
+	World::parse(&mut file).unwrap()
+}
+
+#[test]
+fn fixture_round_trip() {
+	let world = load_world(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT"));
+	let events = [Event::Right, Event::None, Event::Down, Event::ShootLeft, Event::None];
+	let fixture = CompatibilityFixture::record(&world, "FSLIME.ZZT", &events);
+
+	let parsed_fixture = CompatibilityFixture::parse(&fixture.to_text()).unwrap();
+	assert_eq!(parsed_fixture, fixture);
+	assert_eq!(parsed_fixture.replay(&world), Ok(()));
+
+	let mut wrong_fixture = fixture.clone();
+	wrong_fixture.steps[2].board_hash ^= 1;
+	assert_eq!(wrong_fixture.replay(&world).unwrap_err().step_index, 2);
+}
+
+#[test]
+fn replay_screenshots() {
+	let world = load_world(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT"));
+	let events = [Event::Right, Event::None, Event::Down, Event::None, Event::None];
+	let fixture = CompatibilityFixture::record(&world, "FSLIME.ZZT", &events);
+
+	let mut screenshots = vec![];
+	fixture.replay_screenshots(&world, 2, |steps_played, console_state| {
+		let cells: Vec<_> = console_state.iter_cells().map(|(_, _, console_char)| *console_char).collect();
+		screenshots.push((steps_played, cells));
+	});
+	let steps_played: Vec<usize> = screenshots.iter().map(|(steps_played, _)| *steps_played).collect();
+	assert_eq!(steps_played, vec![0, 2, 4]);
+
+	// The player moved in between the first two screenshots.
+	assert_ne!(screenshots[0].1, screenshots[1].1);
+}
+
+#[test]
+fn state_hash() {
+	let world = load_world(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT"));
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world, None).unwrap();
+	let sim = &mut engine.board_simulator;
+	let state_hash = sim.state_hash();
+	let initial_board_hash = board_hash(sim);
+	assert_eq!(sim.state_hash(), state_hash);
+
+	// The state hash includes the player's inventory, which DOS ZZT doesn't keep on the board.
+	sim.world_header.player_gems += 1;
+	assert_ne!(sim.state_hash(), state_hash);
+	assert_eq!(board_hash(sim), initial_board_hash);
+}
+
+#[test]
+fn comparison_runner() {
+	let world = load_world(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT"));
+	let mut fixture = CompatibilityFixture::record(&world, "FSLIME.ZZT", &[Event::Right, Event::None]);
+
+	// Fill in the board bytes, with one tile changed in the first step.
+	let mut runner = ComparisonRunner::new(&world, fixture.clone());
+	for step in &mut fixture.steps {
+		runner.step();
+		step.board_bytes = Some(board_hash_bytes(&runner.engine.board_simulator));
+	}
+	fixture.steps[0].board_bytes.as_mut().unwrap()[0] ^= 1;
+	assert_eq!(CompatibilityFixture::parse(&fixture.to_text()).unwrap(), fixture);
+
+	let mut runner = ComparisonRunner::new(&world, fixture);
+	assert!(runner.reference_board_chars().is_none());
+	assert!(runner.step());
+	assert_eq!(runner.divergent_tiles(), vec![(0, 0)]);
+	assert_eq!(runner.reference_board_chars().unwrap().len(), 25);
+	assert!(runner.step());
+	assert!(runner.divergent_tiles().is_empty());
+	assert!(!runner.step());
+	assert_eq!((runner.steps_played(), runner.first_mismatch()), (2, None));
+}
+
+/// Run every board of every world in `src/tests/data` for a while (see `smoke_test_world`).
+#[test]
+fn smoke_test_worlds() {
+	let data_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data");
+	for entry in std::fs::read_dir(&data_dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path.extension().is_none_or(|extension| !extension.eq_ignore_ascii_case("zzt")) {
+			continue;
+		}
+
+		let world = load_world(&path);
+		let results = crate::smoke_test::smoke_test_world(&world, 20, 0);
+		assert_eq!(results.len(), world.boards.len());
+		for result in results {
+			assert!(result.panic_message.is_none(), "{}: {:?}", path.display(), result.description_lines());
+			assert_eq!(result.steps_run, 20);
+		}
+	}
+}
+
+#[test]
+fn attract_mode() {
+	let world = load_world(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/data/FSLIME.ZZT"));
+	let demo = CompatibilityFixture::record(&world, "FSLIME.ZZT", &[Event::Right, Event::None]);
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world, None).unwrap();
+	engine.attract_mode = Some(AttractMode::new(demo, 3));
+	let (start_x, start_y) = engine.board_simulator.get_player_location();
+
+	// Pressing a key in the title screen starts the wait again.
+	for event in [Event::None, Event::None, Event::Down, Event::None, Event::None] {
+		engine.step(event, 0.);
+	}
+	assert!(engine.in_title_screen);
+	engine.step(Event::None, 0.);
+	assert!(!engine.in_title_screen);
+	assert!(engine.is_playing_attract_demo());
+
+	// The demo moves the player to the right.
+	engine.step(Event::None, 0.);
+	assert_eq!(engine.board_simulator.get_player_location(), (start_x + 1, start_y));
+
+	// Any key goes back to the title screen.
+	engine.step(Event::Up, 0.);
+	assert!(engine.in_title_screen);
+	assert!(!engine.is_playing_attract_demo());
+
+	// The demo plays from the start again, and the title screen comes back when it ends.
+	for _ in 0 .. 3 {
+		engine.step(Event::None, 0.);
+	}
+	assert!(engine.is_playing_attract_demo());
+	assert_eq!(engine.board_simulator.get_player_location(), (start_x, start_y));
+	for _ in 0 .. 3 {
+		engine.step(Event::None, 0.);
+	}
+	assert!(engine.in_title_screen);
+	assert!(!engine.is_playing_attract_demo());
+}
@@ -0,0 +1,901 @@
+use crate::tests::world_tester::*;
+use crate::palette::DisplayOptions;
+use crate::board_message::{BoardMessage, LogLevel};
+use crate::console::{ConsoleChar, ConsoleColour, ConsoleState, ScreenMode};
+use crate::event::TypingEvent;
+use crate::ansi_art::{board_to_ansi, import_ansi_art, render_board};
+use crate::engine::{about_scroll_lines, ENGINE_VERSION};
+use crate::world_map::WorldMap;
+use crate::screenshot::ScreenshotFormat;
+use crate::shareware::{SharewareFlavour, TITLE_IDLE_NAG_STEPS};
+use crate::minimap::{Minimap, MinimapCorner};
+use crate::world_browser::{WorldBrowserEntry, WorldBrowserState};
+
+#[test]
+fn colour_blind_marks_keys_and_doors() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	world.engine.board_simulator.set_tile(10, 10, BoardTile::new(ElementType::Key, 0x0c));
+	world.engine.board_simulator.set_tile(11, 10, BoardTile::new(ElementType::Door, 0x4f));
+
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(9, 9).char_code, 0x0c);
+	assert_eq!(world.engine.console_state.get_char(10, 9).char_code, 0x0a);
+
+	world.engine.display_options = DisplayOptions::colour_blind();
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(9, 9).char_code, b'R');
+	assert_eq!(world.engine.console_state.get_char(10, 9).char_code, b'R');
+}
+
+#[test]
+fn screen_as_text() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	world.engine.board_simulator.world_header.player_health = 42;
+	world.engine.board_simulator.set_tile(3, 1, BoardTile::new(ElementType::Gem, 0x0a));
+
+	let lines = world.screen_lines();
+	assert_eq!(lines.len(), 25);
+	assert!(lines[0].starts_with("\u{263B}\u{2593}\u{2666}"));
+	assert!(lines[7].ends_with("Health:42"));
+
+	let ansi = world.engine.console_state.to_ansi_string();
+	// The player is white on blue.
+	assert!(ansi.starts_with("\x1b[44;97m\u{263B}"));
+}
+
+#[test]
+fn colour_parts() {
+	use crate::console::{Colour, ConsoleColour};
+
+	let colour = Colour::from_u8(0x9e);
+	assert_eq!(colour, Colour::new(ConsoleColour::Yellow, ConsoleColour::Blue, true));
+	assert_eq!(colour.to_u8(), 0x9e);
+	assert_eq!(colour.console_background(), ConsoleColour::LightBlue);
+	assert_eq!(Colour::from_console_colours(ConsoleColour::Yellow, ConsoleColour::LightBlue), colour);
+}
+
+#[test]
+fn message_log() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	world.engine.message_log.capacity = 2;
+
+	for text in ["First", "Second", "Third"] {
+		world.engine.process_board_message(BoardMessage::OpenScroll{title: DosString::new(), content_lines: vec![DosString::from_str(text)]});
+	}
+	world.engine.process_board_message(BoardMessage::OpenScroll{
+		title: DosString::from_str("Sign"),
+		content_lines: vec![DosString::from_str("Read me"), DosString::from_str("!go;Go somewhere")],
+	});
+
+	let lines: Vec<String> = world.engine.message_log.scroll_lines().iter().map(|line| line.to_string(false)).collect();
+	assert_eq!(lines, vec!["Third", "", "$Sign", "Read me", "  Go somewhere"]);
+
+	world.engine.process_board_message(BoardMessage::OpenMessageLog);
+	assert!(world.engine.scroll_state.is_some());
+	assert_eq!(world.engine.message_log.messages.len(), 2);
+}
+
+#[test]
+fn world_map_scroll() {
+	let mut world = TestWorld::new_with_player(60, 10);
+	assert_eq!(WorldMap::new().scroll_lines(&world.engine.world.boards, 1)[0].to_string(false), "No boards have been visited yet.");
+	let first_board_name = world.engine.world.boards[1].meta_data.board_name.to_string(false);
+	let mut east_board = Board::zzt_default(DosString::from_str("East"));
+	east_board.meta_data.exit_west = 1;
+	// The default board has a wall around it, so make a gap for the player to come in through.
+	let (board_width, _) = WorldType::Zzt.board_size();
+	east_board.tiles[9 * board_width] = BoardTile::new(ElementType::Empty, 0);
+	world.engine.world.boards.push(east_board);
+	world.engine.board_simulator.board_meta_data.exit_east = 2;
+
+	// Walking off the east edge visits the board to the east.
+	for board_message in world.engine.step(Event::Right, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	assert_eq!(world.world_header().player_board, 2);
+	assert_eq!(world.engine.world_map.visited_boards.keys().cloned().collect::<Vec<_>>(), vec![1, 2]);
+
+	// After the step that the player left the first board in is finished, walking back records
+	// the exit that was added to the first board.
+	world.engine.step(Event::None, 0.);
+	for board_message in world.engine.step(Event::Left, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	assert_eq!(world.world_header().player_board, 1);
+	let lines: Vec<String> = world.engine.world_map.scroll_lines(&world.engine.world.boards, 1).iter().map(|line| line.to_string(false)).collect();
+	assert_eq!(lines, vec![
+		format!("${} (You are here)", first_board_name),
+		"  East:  East".to_string(),
+		"".to_string(),
+		"$East".to_string(),
+		format!("  West:  {}", first_board_name),
+	]);
+
+	world.engine.process_board_message(BoardMessage::OpenWorldMap);
+	assert!(world.engine.scroll_state.is_some());
+}
+
+#[test]
+fn pause_menu() {
+	use crate::compatibility::step_cycle;
+	use crate::pause_menu::PauseMenuItem;
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	step_cycle(&mut world.engine, Event::Escape);
+	assert!(world.engine.pause_menu_state.is_some());
+	let lines = world.screen_lines();
+	assert!(lines[8].contains("Paused"));
+	assert!(lines[10].contains("Resume"));
+	assert!(lines[14].contains("Quit"));
+
+	// The game doesn't run while the menu is open, and the direction keys pick an item.
+	step_cycle(&mut world.engine, Event::Right);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (10, 10));
+	step_cycle(&mut world.engine, Event::Down);
+	step_cycle(&mut world.engine, Event::Up);
+	assert_eq!(world.engine.pause_menu_state.as_ref().unwrap().current_item(), PauseMenuItem::Resume);
+
+	// Choosing Resume closes the menu and the game carries on.
+	step_cycle(&mut world.engine, Event::Enter);
+	assert!(world.engine.pause_menu_state.is_none());
+	assert!(!world.screen_lines()[8].contains("Paused"));
+	step_cycle(&mut world.engine, Event::Right);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (11, 10));
+
+	// Choosing another item closes the menu and does what it says.
+	step_cycle(&mut world.engine, Event::Escape);
+	step_cycle(&mut world.engine, Event::Down);
+	assert_eq!(world.engine.pause_menu_state.as_ref().unwrap().current_item(), PauseMenuItem::Save);
+	step_cycle(&mut world.engine, Event::Enter);
+	assert!(world.engine.pause_menu_state.is_none());
+	assert!(world.engine.in_typing_mode());
+}
+
+#[test]
+fn no_shooting_notification() {
+	use crate::compatibility::step_cycle;
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+	world.engine.board_simulator.board_meta_data.max_player_shots = 0;
+	assert!(world.screen_lines()[13].contains("Shots:None"));
+	let notification_count = |world: &TestWorld| {
+		world.engine.message_log.scroll_lines().iter().filter(|line| line.to_string(false) == "Can't shoot in this place!").count()
+	};
+
+	// The notification is shown the first time the player tries to shoot on the board.
+	step_cycle(&mut world.engine, Event::ShootRight);
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 1);
+	assert_eq!(world.engine.board_simulator.world_header.player_ammo, 10);
+	assert!(world.screen_lines()[24].contains("Can't shoot in this place!"));
+	assert_eq!(notification_count(&world), 1);
+
+	// It isn't shown again on the same board.
+	world.engine.caption_state = None;
+	step_cycle(&mut world.engine, Event::ShootRight);
+	assert!(!world.screen_lines()[24].contains("Can't shoot in this place!"));
+	assert_eq!(notification_count(&world), 1);
+
+	// Entering the board again shows it again.
+	world.engine.process_board_message(BoardMessage::WarpToBoard{board_index: 1});
+	world.engine.is_paused = false;
+	step_cycle(&mut world.engine, Event::ShootRight);
+	assert_eq!(notification_count(&world), 2);
+
+	// Boards that allow shooting show how many shots there can be.
+	world.engine.board_simulator.board_meta_data.max_player_shots = 3;
+	assert!(world.screen_lines()[13].contains("Shots:3"));
+}
+
+#[test]
+fn board_info_panel() {
+	use crate::board_info::{board_info_lines, BoardInfoField};
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	let first_board_name = world.engine.world.boards[1].meta_data.board_name.to_string(false);
+	let meta_data = &mut world.engine.board_simulator.board_meta_data;
+	meta_data.max_player_shots = 5;
+	meta_data.exit_north = 1;
+	BoardInfoField::IsDark.set_value(meta_data, "yes").unwrap();
+	BoardInfoField::from_name("time_limit").unwrap().set_value(meta_data, " 60 ").unwrap();
+	assert!(BoardInfoField::ExitSouth.set_value(meta_data, "north").is_err());
+	assert!(BoardInfoField::RestartOnZap.set_value(meta_data, "maybe").is_err());
+
+	let lines: Vec<String> = board_info_lines(meta_data, &world.engine.world.boards).iter().map(|line| line.to_string(false)).collect();
+	assert_eq!(lines, vec![
+		format!("Title: {}", first_board_name),
+		"Can fire: 5 shots".to_string(),
+		"Board is dark: Yes".to_string(),
+		format!("Board north: {}", first_board_name),
+		"Board south: None".to_string(),
+		"Board west: None".to_string(),
+		"Board east: None".to_string(),
+		"Re-enter when zapped: No".to_string(),
+		"Time limit: 60 sec".to_string(),
+	]);
+
+	// The debug command shows the panel in a scroll.
+	world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_slice(b"info")));
+	let screen_lines = world.screen_lines();
+	assert!(screen_lines.iter().any(|line| line.contains("Board Info")));
+	assert!(screen_lines.iter().any(|line| line.contains("Can fire: 5 shots")));
+	assert!(!screen_lines.iter().any(|line| line.contains("Time limit: 60 sec")));
+	// Scrolling down shows the rest of the fields.
+	for _ in 0 .. 8 {
+		world.engine.step(Event::Down, 0.);
+	}
+	assert!(world.screen_lines().iter().any(|line| line.contains("Time limit: 60 sec")));
+}
+
+#[test]
+fn about_scroll() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.set_in_title_screen(true);
+	world.engine.update_screen();
+	assert!(world.screen_lines()[16].contains("About RUZZT!"));
+
+	for board_message in world.engine.step(Event::OpenAbout, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	assert!(world.engine.scroll_state.is_some());
+	assert!(about_scroll_lines()[1].to_string(false).ends_with(ENGINE_VERSION));
+}
+
+#[test]
+fn console_cells() {
+	let mut console_state = ConsoleState::new();
+	console_state[(3, 2)] = ConsoleChar::new(b'A', ConsoleColour::Blue, ConsoleColour::White);
+	assert_eq!(console_state[(3, 2)].char_code, b'A');
+
+	let cells: Vec<_> = console_state.iter_cells().collect();
+	assert_eq!(cells.len(), ConsoleState::SCREEN_WIDTH * ConsoleState::SCREEN_HEIGHT);
+	assert_eq!(cells[2 * ConsoleState::SCREEN_WIDTH + 3], (3, 2, &console_state[(3, 2)]));
+}
+
+#[test]
+fn shareware_flavour_order_form() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.shareware_flavour = Some(SharewareFlavour::new());
+	world.engine.set_in_title_screen(true);
+	for _ in 0 .. TITLE_IDLE_NAG_STEPS {
+		world.engine.step(Event::None, 0.);
+	}
+	assert!(world.engine.scroll_state.is_some());
+	world.engine.process_board_message(BoardMessage::CloseScroll);
+
+	// Quitting shows the order form first, then quits once it is closed.
+	world.engine.process_board_message(BoardMessage::OpenQuitConfirmation);
+	assert!(world.engine.process_typing(TypingEvent::Char(b'y')).is_empty());
+	assert!(world.engine.scroll_state.is_some());
+	let board_messages = world.engine.process_board_message(BoardMessage::CloseScroll);
+	assert!(matches!(board_messages[..], [BoardMessage::Quit]));
+}
+
+#[test]
+fn side_bar_model() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let sim = &mut world.engine.board_simulator;
+	sim.world_header.player_health = 42;
+	sim.world_header.player_keys[2] = true;
+	sim.board_meta_data.time_limit = 30;
+	sim.world_header.time_passed = 5;
+
+	let model = world.engine.side_bar_model();
+	assert_eq!(model.health, 42);
+	assert_eq!(model.time_left, Some(25));
+	assert!(model.keys[2]);
+
+	// The side bar is drawn from the same information.
+	world.engine.update_screen();
+	let health_text: Vec<u8> = (72 .. 74).map(|x| world.engine.console_state.get_char(x, 7).char_code).collect();
+	assert_eq!(health_text, b"42");
+}
+
+#[test]
+fn super_zzt_stones_in_side_bar() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	let sim = &mut world.engine.board_simulator;
+	sim.world_header.world_type = WorldType::SuperZzt;
+	sim.world_header.player_torches = None;
+	sim.world_header.torch_cycles = None;
+	sim.world_header.player_stones = Some(-1);
+	crate::zzt_behaviours::load_behaviours(sim);
+	sim.set_tile(21, 20, BoardTile { element_id: SuperZztElementType::Stone as u8, colour: 0x0f });
+
+	world.engine.update_screen();
+	assert!(!world.screen_lines()[9].contains("Stones:"));
+
+	// The first stone makes the count 1, even though it started negative.
+	world.event = Event::Right;
+	world.simulate(1);
+	assert_eq!(world.world_header().player_stones, Some(1));
+	world.engine.update_screen();
+	assert!(world.screen_lines()[9].contains("Stones:1"));
+}
+
+#[test]
+fn side_bar_health_after_endgame() {
+	use crate::behaviour::{Action, ApplyActionResultReport, PlayerItemType};
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_health = 42;
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#endgame\n:loop\n#play c\n/i\n#loop\n");
+	world.insert_tile_and_status(tile_set.get('O'), 20, 10);
+
+	let mut sound_count = 0;
+	for _ in 0 .. 10 {
+		loop {
+			let board_messages = world.engine.step(Event::None, 0.);
+			sound_count += board_messages.iter().filter(|message| matches!(message, BoardMessage::PlaySoundArray(..))).count();
+			if !world.engine.is_part_way_though_step() {
+				break;
+			}
+		}
+	}
+
+	// Only the game over tune is played, and the side bar still shows the health from before
+	// `#endgame`, like in DOS ZZT.
+	assert!(world.engine.is_end_of_game());
+	assert_eq!(sound_count, 1);
+	assert_eq!(world.engine.side_bar_model().health, 42);
+
+	// Giving the player health redraws the side bar in DOS ZZT.
+	world.engine.board_simulator.apply_action(0, 0, Action::ModifyPlayerItem{item_type: PlayerItemType::Health, offset: 5, require_exact_amount: false}, 0, None, &mut AccumulatedActionData::new(), &mut ApplyActionResultReport::new());
+	assert_eq!(world.engine.side_bar_model().health, 5);
+}
+
+#[test]
+fn world_load_failed() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.process_board_message(BoardMessage::WorldLoadFailed{
+		filename: DosString::from_slice(b"TOWN.ZZT"),
+		error: "File not found".to_string(),
+	});
+	assert!(world.engine.in_title_screen);
+	assert!(world.engine.scroll_state.is_some());
+
+	world.engine.update_screen();
+	assert!(world.screen_lines().iter().any(|line| line.contains("Can't load TOWN.ZZT")));
+}
+
+#[test]
+fn low_resource_warnings() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.player_health = 10;
+	world.engine.board_simulator.world_header.torch_cycles = Some(30);
+
+	// Each warning sound plays once, when the health or torch first gets low.
+	let is_sound = |message: &BoardMessage| matches!(message, BoardMessage::PlaySoundArray(..));
+	assert_eq!(world.engine.step(Event::None, 0.).iter().filter(|message| is_sound(message)).count(), 2);
+	assert_eq!(world.engine.step(Event::None, 0.).iter().filter(|message| is_sound(message)).count(), 0);
+
+	// The health count and torch meter flash.
+	world.engine.global_cycle = 0;
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(72, 7).foreground, ConsoleColour::LightRed);
+	assert_eq!(world.engine.console_state.get_char(75, 9).char_code, 0xb0);
+	world.engine.global_cycle = 5;
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(72, 7).foreground, ConsoleColour::Yellow);
+	assert_ne!(world.engine.console_state.get_char(75, 9).char_code, 0xb0);
+}
+
+#[test]
+fn new_world_input() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.process_board_message(BoardMessage::OpenNewWorldInput);
+	assert!(world.engine.in_typing_mode());
+
+	for &c in b"quest" {
+		world.engine.process_typing(TypingEvent::Char(c));
+	}
+	assert!(world.screen_lines().iter().any(|line| line.contains("New world name:")));
+
+	let board_messages = world.engine.process_typing(TypingEvent::Enter);
+	assert!(matches!(&board_messages[..], [BoardMessage::CreateWorld{name}] if name.data == b"QUEST"));
+}
+
+#[test]
+fn execution_order() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('1', "#end");
+	tile_set.add('2', BoardTile::new(ElementType::Object, 0x0f), Some(StatusElement {
+		cycle: 2,
+		.. StatusElement::default()
+	}));
+	world.insert_template(&TileTemplate::from_text(&tile_set, "112"), 20, 5);
+
+	world.engine.step(Event::ToggleExecutionOrder, 0.);
+	assert!(world.engine.show_execution_order);
+
+	// The object with cycle 2 is status 3, so it is only processed in odd cycles.
+	world.engine.global_cycle = 2;
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(9, 9).char_code, b'1');
+	let order_chars: Vec<u8> = (19 .. 21).map(|x| world.engine.console_state.get_char(x, 4).char_code).collect();
+	assert_eq!(order_chars, b"23");
+	assert_eq!(world.engine.console_state.get_char(21, 4).foreground, ConsoleColour::DarkGray);
+
+	world.engine.global_cycle = 3;
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(21, 4).char_code, b'4');
+}
+
+#[test]
+fn shoot_lock() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.board_simulator.world_header.player_ammo = 10;
+
+	world.engine.step(Event::ToggleShootLock, 0.);
+	assert!(world.engine.shoot_lock);
+	world.engine.update_screen();
+	assert!(world.engine.console_state.to_plain_text().lines().nth(18).unwrap().contains("Shoot"));
+
+	// The movement keys shoot instead of moving the player.
+	world.engine.step(Event::Right, 0.);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (20, 20));
+	assert_eq!(world.engine.board_simulator.status_elements.len(), 2);
+	assert_eq!(world.engine.board_simulator.world_header.player_ammo, 9);
+
+	world.engine.step(Event::ToggleShootLock, 0.);
+	world.engine.step(Event::Right, 0.);
+	assert_eq!(world.engine.board_simulator.get_player_location(), (21, 20));
+}
+
+#[test]
+fn screenshot() {
+	let mut world = TestWorld::new_with_player(20, 20);
+	world.engine.update_screen();
+
+	let board_messages = world.engine.step(Event::Screenshot, 0.);
+	let path = board_messages.iter().find_map(|board_message| match board_message {
+		BoardMessage::SaveScreenshot{path} => Some(path.clone()),
+		_ => None,
+	}).unwrap();
+	assert_eq!(ScreenshotFormat::from_path(&path), Some(ScreenshotFormat::Ansi));
+	assert!(path.file_name().unwrap().to_string_lossy().starts_with("SCREEN"));
+
+	let console_state = &world.engine.console_state;
+	let text = ScreenshotFormat::Text.screenshot_data(console_state).unwrap();
+	assert_eq!(text, console_state.to_plain_text().into_bytes());
+	let ansi = ScreenshotFormat::Ansi.screenshot_data(console_state).unwrap();
+	assert_eq!(ansi.windows(2).filter(|bytes| bytes == b"\r\n").count(), console_state.height());
+	assert_eq!(ScreenshotFormat::Png.screenshot_data(console_state), None);
+
+	// Screenshots go in the screenshot directory, and failing to write one is logged.
+	let directory = std::env::temp_dir().join(format!("ruzzt_missing_screenshots_{}", std::process::id()));
+	world.engine.screenshot_directory = directory.clone();
+	let board_messages = world.engine.step(Event::Screenshot, 0.);
+	let path = board_messages.iter().find_map(|board_message| match board_message {
+		BoardMessage::SaveScreenshot{path} => Some(path.clone()),
+		_ => None,
+	}).unwrap();
+	assert_eq!(path.parent(), Some(directory.as_path()));
+	let board_messages = world.engine.process_board_message(BoardMessage::SaveScreenshot{path});
+	assert!(board_messages.iter().any(|board_message| matches!(board_message, BoardMessage::EngineLog(LogLevel::Warning, _))));
+}
+
+#[test]
+fn ansi_art_export() {
+	let mut world = TestWorld::new_with_player(30, 10);
+	world.engine.board_simulator.set_tile(5, 5, BoardTile::new(ElementType::Line, 0x0f));
+	world.engine.board_simulator.set_tile(6, 5, BoardTile::new(ElementType::Line, 0x0f));
+	world.engine.board_simulator.set_tile(8, 5, BoardTile::new(ElementType::Normal, 0x9e));
+	world.engine.sync_world();
+	let board_index = world.engine.board_simulator.world_header.player_board as usize;
+
+	// Line walls join up like they do in the game.
+	let rows = render_board(&world.engine.world, board_index).unwrap();
+	assert_eq!((rows.len(), rows[0].len()), (25, 60));
+	assert_eq!((rows[4][4].char_code, rows[4][5].char_code), (0xc6, 0xb5));
+
+	// The bright yellow foreground is bold, and the blinking blue background blinks.
+	let ansi = board_to_ansi(&world.engine.world, board_index).unwrap();
+	let blinking_wall = b"\x1b[0;1;5;33;44m\xb2";
+	assert!(ansi.windows(blinking_wall.len()).any(|window| window == blinking_wall));
+	assert_eq!(ansi.iter().filter(|&&byte| byte == b'\n').count(), 25);
+	assert!(board_to_ansi(&world.engine.world, 100).is_err());
+}
+
+#[test]
+fn ansi_art_import() {
+	let mut board = zzt_file_format::Board::zzt_default(DosString::from_slice(b"Art"));
+	import_ansi_art(&mut board, b"Hi\x1b[1;33m\xdb\x1b[0;31;44m\xb2\x1b[42m \r\n\x1b[2C\x1b[47m?\x1b[0m\r\n\x1a\x1b[31mSAUCE");
+	let tile_at = |x: usize, y: usize| board.tiles[y * 60 + x];
+
+	assert_eq!(tile_at(0, 0), BoardTile::new(ElementType::TextBlack, b'H'));
+	assert_eq!(tile_at(2, 0), BoardTile::new(ElementType::Solid, 0x0e));
+	// Red and blue are swapped between ANSI and DOS colours.
+	assert_eq!(tile_at(3, 0), BoardTile::new(ElementType::Normal, 0x14));
+	assert_eq!(tile_at(4, 0), BoardTile::new(ElementType::Solid, 0x02));
+	assert_eq!(tile_at(2, 1), BoardTile::new(ElementType::TextBlack, b'?'));
+	assert_eq!(tile_at(0, 2), BoardTile::new(ElementType::Empty, 0));
+	// The border is replaced, but the player is kept.
+	assert_eq!(tile_at(59, 24), BoardTile::new(ElementType::Empty, 0));
+	assert_eq!(tile_at(29, 11).element_id, ElementType::Player as u8);
+}
+
+#[test]
+fn status_code_editor() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('1', "@bob\n#end\n");
+	world.insert_template(&TileTemplate::from_text(&tile_set, "1"), 11, 10);
+
+	// The `code` debug command edits the code of the object next to the player.
+	let board_messages = world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_str("code")));
+	for board_message in board_messages {
+		world.engine.process_board_message(board_message);
+	}
+	assert!(world.engine.in_typing_mode());
+
+	for event in [TypingEvent::Down, TypingEvent::End, TypingEvent::Enter, TypingEvent::Char(b'h'), TypingEvent::Char(0x01)] {
+		world.engine.process_typing(event);
+	}
+	assert!(world.screen_lines().iter().any(|line| line.contains("Edit code of status 1")));
+
+	// Escape confirms the new code.
+	for board_message in world.engine.process_typing(TypingEvent::Escape) {
+		world.engine.process_board_message(board_message);
+	}
+	assert!(!world.engine.in_typing_mode());
+	assert_eq!(world.status_at(11, 10).code_source, CodeSource::Owned(DosString::from_slice(b"@bob\r#end\rh\x01\r")));
+}
+
+#[test]
+fn screen_mode_80x50() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.update_screen();
+	let board_text = world.screen_lines()[.. 25].to_vec();
+	world.engine.console_state.take_dirty_cells();
+
+	world.engine.set_screen_mode(ScreenMode::Text80x50);
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.height(), 50);
+	assert_eq!(world.engine.console_state.take_dirty_cells().len(), 80 * 50);
+	// The board is drawn in the same place, and the side bar goes all the way down.
+	assert_eq!(world.screen_lines()[.. 25].to_vec(), board_text);
+	assert_eq!(world.engine.console_state.get_char(70, 49).background, ConsoleColour::Blue);
+	assert_eq!(ScreenMode::Text80x50.pixel_size(), (640, 400));
+	assert_eq!(ScreenMode::Text80x50.pixel_to_screen_pos(639, 399), Some((79, 49)));
+	assert_eq!(ScreenMode::Text80x25.pixel_to_screen_pos(639, 399), None);
+
+	world.engine.set_screen_mode(ScreenMode::Text80x25);
+	assert_eq!(world.engine.console_state.height(), 25);
+}
+
+#[test]
+fn blinking_follows_blink_time() {
+	use crate::console::ConsoleChar;
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.set_tile(20, 10, BoardTile::new(ElementType::Normal, 0x8e));
+	world.engine.is_paused = false;
+	world.engine.update_screen();
+	world.engine.console_state.take_dirty_cells();
+
+	let blinking_char = world.engine.console_state.get_char(19, 9);
+	assert!(blinking_char.is_blinking());
+	world.engine.console_state.set_blink_time_ms(100);
+	assert!(world.engine.console_state.blink_visible());
+	assert_eq!(world.engine.console_state.ms_until_blink_change(), 125);
+	assert_eq!(world.engine.console_state.take_dirty_cells(), vec![]);
+
+	// Blinking carries on without any steps, and only the blinking characters need redrawing.
+	world.engine.console_state.set_blink_time_ms(300);
+	assert!(!world.engine.console_state.blink_visible());
+	assert_eq!(world.engine.console_state.take_dirty_cells(), vec![(19, 9, blinking_char)]);
+	assert_eq!(world.engine.console_state.blinked_char(blinking_char), ConsoleChar{char_code: 0, .. blinking_char});
+	let still_char = ConsoleChar::new(b'A', ConsoleColour::Blue, ConsoleColour::White);
+	assert_eq!(world.engine.console_state.blinked_char(still_char), still_char);
+}
+
+#[test]
+fn parsed_scroll_lines() {
+	use crate::scroll::{ScrollLine, ScrollState};
+
+	let content_lines: Vec<DosString> = ["Plain", "$Middle", "!shop;Go shopping", "!nolabel"].iter().map(|line| DosString::from_str(line)).collect();
+	let mut scroll = ScrollState::new_title_content(DosString::from_str("Title"), content_lines.clone());
+	assert_eq!(scroll.lines(), &[
+		ScrollLine::Text(DosString::from_str("Plain")),
+		ScrollLine::Centered(DosString::from_str("Middle")),
+		ScrollLine::Link{label: DosString::from_str("shop"), text: DosString::from_str("Go shopping")},
+		ScrollLine::Link{label: DosString::from_str("nolabel"), text: DosString::from_str("!nolabel")},
+	]);
+	let round_trip: Vec<DosString> = scroll.lines().iter().map(|line| line.to_dos_string()).collect();
+	assert_eq!(round_trip, content_lines);
+
+	// Scrolls are still serialized with the unparsed lines.
+	let json = serde_json::to_string(&scroll).unwrap();
+	assert!(json.contains("content_lines"));
+	assert_eq!(serde_json::from_str::<ScrollState>(&json).unwrap(), scroll);
+
+	scroll.step(Event::Down);
+	scroll.step(Event::Down);
+	assert_eq!(scroll.current_line(), 2);
+	let board_messages = scroll.step(Event::Enter);
+	assert!(matches!(board_messages[0], BoardMessage::LinkClicked(ref label) if label.data == b"shop"));
+}
+
+#[test]
+fn extended_characters_in_text_input() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.process_board_message(BoardMessage::OpenNewWorldInput);
+	world.engine.process_typing(TypingEvent::Char(0xe8));
+
+	world.engine.process_typing(TypingEvent::OpenCharPicker);
+	assert!(world.screen_lines().iter().any(|line| line.contains("Pick a character")));
+	world.engine.process_typing(TypingEvent::Char(0x80));
+	world.engine.process_typing(TypingEvent::Right);
+	assert_eq!(world.engine.char_picker_state.as_ref().map(|picker| picker.selected()), Some(0x81));
+	world.engine.process_typing(TypingEvent::Enter);
+	assert!(world.engine.char_picker_state.is_none());
+	assert!(world.engine.in_typing_mode());
+
+	let board_messages = world.engine.process_typing(TypingEvent::Enter);
+	assert!(matches!(&board_messages[..], [BoardMessage::CreateWorld{name}] if name.data == [0xe8, 0x81]));
+}
+
+#[test]
+fn dirty_rect_covers_changed_characters() {
+	use crate::console::ScreenRect;
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.update_screen();
+	let screen_rect = world.engine.console_state.screen_rect();
+	assert_eq!(world.engine.console_state.take_dirty_rect(screen_rect), Some(ScreenRect::new(0, 0, 80, 25)));
+	assert_eq!(world.engine.console_state.take_dirty_rect(screen_rect), None);
+
+	world.engine.board_simulator.set_tile(5, 3, BoardTile::new(ElementType::Gem, 0x0a));
+	world.engine.board_simulator.set_tile(8, 6, BoardTile::new(ElementType::Gem, 0x0a));
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.take_dirty_rect(screen_rect), Some(ScreenRect::new(4, 2, 4, 4)));
+
+	// Changes outside the region are left out.
+	world.engine.board_simulator.set_tile(5, 3, BoardTile::new(ElementType::Empty, 0));
+	world.engine.board_simulator.set_tile(30, 20, BoardTile::new(ElementType::Gem, 0x0a));
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.take_dirty_rect(ScreenRect::new(20, 10, 20, 15)), Some(ScreenRect::new(29, 19, 1, 1)));
+	assert_eq!(ScreenRect::new(0, 0, 10, 10).intersection(ScreenRect::new(10, 0, 5, 5)), None);
+}
+
+#[test]
+fn screen_dirty_until_taken() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.update_screen();
+	assert!(world.engine.screen_dirty());
+	world.engine.console_state.take_dirty_cells();
+	assert!(!world.engine.screen_dirty());
+
+	world.engine.update_screen();
+	assert!(world.engine.screen_dirty());
+	world.engine.console_state.take_dirty_cells();
+
+	// Blinking characters need drawing again when they are shown or hidden.
+	world.engine.console_state.set_blink_time_ms(1000);
+	assert!(!world.engine.screen_dirty());
+	world.engine.board_simulator.set_tile(5, 3, BoardTile::new(ElementType::Gem, 0x8a));
+	world.engine.update_screen();
+	world.engine.console_state.take_dirty_cells();
+	world.engine.console_state.set_blink_time_ms(1000 + crate::console::BLINK_HALF_PERIOD_MS);
+	assert!(world.engine.screen_dirty());
+}
+
+#[test]
+fn end_game_and_quit_confirmations() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.world_name = DosString::from_str("Town");
+
+	// Q while playing asks to end the game, which goes back to the title screen and reloads the world.
+	let board_messages = world.engine.step(Event::Quit, 0.);
+	assert!(matches!(&board_messages[..], [BoardMessage::OpenEndGameConfirmation]));
+	// The step stops part way through when a board message is sent.
+	while world.engine.is_part_way_though_step() {
+		world.engine.step(Event::None, 0.);
+	}
+	world.engine.process_board_message(BoardMessage::OpenEndGameConfirmation);
+	assert!(world.screen_lines().iter().any(|line| line.contains("End this game?")));
+	let board_messages = world.engine.process_typing(TypingEvent::Char(b'y'));
+	assert!(matches!(&board_messages[..], [BoardMessage::EndGame]));
+	let board_messages = world.engine.process_board_message(BoardMessage::EndGame);
+	assert!(world.engine.in_title_screen);
+	assert!(matches!(&board_messages[..], [BoardMessage::OpenWorld{filename}] if filename.data == b"TOWN.ZZT"));
+
+	// Q in the title screen asks to quit RUZZT instead.
+	let board_messages = world.engine.step(Event::Quit, 0.);
+	assert!(matches!(&board_messages[..], [BoardMessage::OpenQuitConfirmation]));
+	world.engine.process_board_message(BoardMessage::OpenQuitConfirmation);
+	assert!(world.screen_lines().iter().any(|line| line.contains("Quit RUZZT?")));
+	assert!(world.engine.process_typing(TypingEvent::Char(b'n')).is_empty());
+	assert!(!world.engine.in_typing_mode());
+	world.engine.process_board_message(BoardMessage::OpenQuitConfirmation);
+	let board_messages = world.engine.process_typing(TypingEvent::Char(b'Y'));
+	assert!(matches!(&board_messages[..], [BoardMessage::Quit]));
+}
+
+#[test]
+fn modern_lighting_passages_light_dark_boards() {
+	use crate::lighting::LightingOptions;
+
+	let mut world = TestWorld::new_with_player(1, 1);
+	world.engine.board_simulator.board_meta_data.is_dark = true;
+	world.engine.board_simulator.world_header.torch_cycles = Some(0);
+	world.insert_tile_and_status(&TileAndStatus {
+		tile: BoardTile::new(ElementType::Passage, 0x1c),
+		status: Some(StatusElement::default()),
+	}, 30, 10);
+
+	// Without the lighting mode, the board is as dark as in DOS ZZT.
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(31, 9).char_code, 0xb0);
+
+	world.engine.lighting_options = Some(LightingOptions {
+		passage_radius: 3,
+		coloured_passages: true,
+		.. LightingOptions::new()
+	});
+	world.engine.update_screen();
+	let lit_char = world.engine.console_state.get_char(31, 9);
+	assert_eq!(lit_char.char_code, 0xfa);
+	assert_eq!(lit_char.foreground, ConsoleColour::LightRed);
+	assert_eq!(world.engine.console_state.get_char(28, 10).char_code, 0xfa);
+	assert_eq!(world.engine.console_state.get_char(33, 9).char_code, 0xb0);
+	assert_eq!(world.engine.console_state.get_char(29, 12).char_code, 0xb0);
+}
+
+#[test]
+fn status_animation_frames_come_from_behaviours() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	world.insert_tile_and_status(&TileAndStatus {
+		tile: BoardTile::new(ElementType::Counter, 0x0f),
+		status: Some(StatusElement {
+			cycle: 2,
+			.. StatusElement::default()
+		}),
+	}, 10, 10);
+
+	let behaviour = world.engine.board_simulator.behaviour_for_pos(10, 10);
+	let frames: Vec<Option<u8>> = (0 .. 8).map(|global_cycle| behaviour.status_char_code(world.engine.board_simulator.get_first_status_for_pos(10, 10).unwrap().1, global_cycle)).collect();
+	assert_eq!(frames, vec![Some(0x5c), Some(0x5c), Some(0xc4), Some(0xc4), Some(0x2f), Some(0x2f), Some(0xb3), Some(0xb3)]);
+
+	world.engine.update_screen();
+	assert_eq!(world.engine.console_state.get_char(9, 9).char_code, frames[world.engine.global_cycle % 8].unwrap());
+}
+
+#[test]
+fn unsaved_changes_prompt() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.world_name = DosString::from_str("Town");
+	assert!(!world.engine.is_dirty());
+	world.engine.process_board_message(BoardMessage::SetStatusCode{status_index: 0, code: DosString::from_str("#end\n")});
+	assert!(world.engine.is_dirty());
+
+	// Quitting with unsaved changes asks about saving them first.
+	world.engine.process_board_message(BoardMessage::OpenQuitConfirmation);
+	assert!(world.engine.process_typing(TypingEvent::Char(b'y')).is_empty());
+	let screen_lines = world.screen_lines();
+	assert!(screen_lines.iter().any(|line| line.contains("Save changes to")));
+	assert!(screen_lines.iter().any(|line| line.contains("Town?")));
+
+	// Escape cancels quitting.
+	assert!(world.engine.process_typing(TypingEvent::Escape).is_empty());
+	assert!(world.engine.is_dirty());
+
+	// No throws the changes away and quits.
+	world.engine.process_board_message(BoardMessage::OpenQuitConfirmation);
+	world.engine.process_typing(TypingEvent::Char(b'y'));
+	let board_messages = world.engine.process_typing(TypingEvent::Char(b'n'));
+	assert!(matches!(&board_messages[..], [BoardMessage::DiscardWorldChanges, BoardMessage::Quit]));
+	world.engine.process_board_message(BoardMessage::DiscardWorldChanges);
+	assert!(!world.engine.is_dirty());
+
+	// Without unsaved changes, nothing is held back.
+	let board_messages = world.engine.process_board_message(BoardMessage::EndGame);
+	assert!(matches!(&board_messages[..], [BoardMessage::OpenWorld{..}]));
+}
+
+#[test]
+fn watches() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#set secret\n#end");
+	world.insert_tile_and_status(tile_set.get('O'), 20, 5);
+	
+	let run_command = |world: &mut TestWorld, command: &str| {
+		world.engine.process_board_message(BoardMessage::DebugCommand(DosString::from_str(command)))
+	};
+	run_command(&mut world, "watch status 1 x");
+	run_command(&mut world, "break flag secret");
+	let board_messages = run_command(&mut world, "watch status 1 colour");
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(..))));
+	assert_eq!(world.engine.watches.len(), 2);
+	
+	// The flag is set in the first step, which pauses the game.
+	world.engine.step(Event::None, 0.);
+	assert!(world.engine.is_paused);
+	assert_eq!(world.engine.watches[1].last_value, Some(1));
+	world.engine.update_screen();
+	let screen_text = world.engine.console_state.to_plain_text();
+	let mut lines = screen_text.lines();
+	assert!(lines.next().unwrap().starts_with("status 1 x = 20"));
+	assert!(lines.next().unwrap().starts_with("*flag SECRET = 1"));
+	
+	run_command(&mut world, "unwatch");
+	assert!(world.engine.watches.is_empty());
+}
+
+#[test]
+fn window_title_shows_world_and_board() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.world_header.world_name = DosString::from_str("TOWN");
+	world.engine.board_simulator.board_meta_data.board_name = DosString::from_str("The Armory");
+	assert_eq!(world.engine.window_title(), "RUZZT — TOWN: The Armory");
+
+	world.engine.in_title_screen = true;
+	assert_eq!(world.engine.window_title(), "RUZZT — TOWN");
+
+	world.engine.board_simulator.world_header.world_name = DosString::new();
+	assert_eq!(world.engine.window_title(), "RUZZT");
+}
+
+#[test]
+fn minimap_blocks_and_corners() {
+	let mut world = TestWorld::new_with_player(60, 25);
+	// Fill the top-left quarter of the board with yellow solids.
+	for y in 1 ..= 12 {
+		for x in 1 ..= 30 {
+			world.engine.board_simulator.set_tile(x, y, BoardTile::new(ElementType::Solid, 0x0e));
+		}
+	}
+	world.engine.update_screen();
+
+	let minimap = Minimap::from_console_state(&world.engine.console_state, 2, 2);
+	assert_eq!(minimap.get_block(0, 0), ConsoleColour::Yellow);
+	assert_eq!(minimap.get_block(1, 0), ConsoleColour::Black);
+	assert_eq!(minimap.get_block(0, 1), ConsoleColour::Black);
+	assert_eq!(minimap.get_block(1, 1), ConsoleColour::Black);
+
+	let side_bar_char = world.engine.console_state.get_char(60, 0);
+	minimap.draw_in_corner(MinimapCorner::TopRight, &mut world.engine.console_state);
+	assert_eq!(world.engine.console_state.get_char(56, 0).char_code, 218);
+	assert_eq!(world.engine.console_state.get_char(59, 0).char_code, 191);
+	assert_eq!(world.engine.console_state.get_char(59, 3).char_code, 217);
+	assert_eq!(world.engine.console_state.get_char(57, 1), ConsoleChar::new(219, ConsoleColour::Black, ConsoleColour::Yellow));
+	assert_eq!(world.engine.console_state.get_char(60, 0), side_bar_char);
+
+	// Drawing past the edge of the board is cut off before the side bar.
+	let side_bar_char = world.engine.console_state.get_char(60, 23);
+	minimap.draw(58, 23, &mut world.engine.console_state);
+	assert_eq!(world.engine.console_state.get_char(58, 23).char_code, 218);
+	assert_eq!(world.engine.console_state.get_char(59, 24).char_code, 219);
+	assert_eq!(world.engine.console_state.get_char(60, 23), side_bar_char);
+}
+
+#[test]
+fn world_browser_preview_follows_highlight() {
+	let first_world = World::zzt_default();
+	let mut second_world = World::zzt_default();
+	second_world.boards.push(second_world.boards[0].clone());
+	second_world.boards[0].tiles[0] = BoardTile::new(ElementType::Gem, 0x0a);
+
+	let second_entry = WorldBrowserEntry::new(DosString::from_slice(b"SECOND"), DosString::new(), &second_world);
+	assert_eq!(second_entry.board_count, 2);
+	assert_eq!(second_entry.title_world.as_ref().unwrap().boards.len(), 1);
+
+	let entries = vec![
+		WorldBrowserEntry::new(DosString::from_slice(b"FIRST"), DosString::new(), &first_world),
+		second_entry,
+	];
+	let mut world_browser_state = WorldBrowserState::new(DosString::from_slice(b"Worlds"), entries);
+	let mut console_state = ConsoleState::new();
+
+	// The top-left of the preview shows the top-left of the highlighted world's title board.
+	world_browser_state.draw_world_browser(&mut console_state);
+	assert_ne!(console_state.get_char(24, 8).char_code, 4);
+
+	world_browser_state.step(Event::Down);
+	world_browser_state.draw_world_browser(&mut console_state);
+	assert_eq!(console_state.get_char(24, 8), ConsoleChar::new(4, ConsoleColour::Black, ConsoleColour::LightGreen));
+}
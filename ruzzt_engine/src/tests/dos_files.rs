@@ -0,0 +1,210 @@
+use crate::autosave::*;
+use crate::board_message::{BoardMessage, LogLevel};
+use crate::dos_files::*;
+use crate::event::{Event, TypingEvent};
+use crate::file_browser::*;
+use crate::high_scores::*;
+use crate::savestate::*;
+use crate::scroll::ScrollState;
+use crate::tests::world_tester::TestWorld;
+use crate::weave::*;
+use crate::zzt_config::*;
+
+use zzt_file_format::World;
+use zzt_file_format::dosstring::DosString;
+
+use std::path::Path;
+
+#[test]
+fn find_files_ignoring_case() {
+	assert_eq!(dos_file_name("Town of ZZT", "zzt"), "TOWNOFZZ.ZZT");
+	assert_eq!(dos_file_name("saved", "sav"), "SAVED.SAV");
+
+	let directory = std::env::temp_dir().join(format!("ruzzt_dos_files_{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	for file_name in &["town.zzt", "Town.Mus", "CAVERNSO.ZZT", "notes.txt"] {
+		std::fs::write(directory.join(file_name), b"").unwrap();
+	}
+
+	assert_eq!(find_file(&directory, "TOWN.ZZT"), Some(directory.join("town.zzt")));
+	// Long names are looked up by their DOS 8.3 name too.
+	assert_eq!(find_file(&directory, "CAVERNSOFZZT.ZZT"), Some(directory.join("CAVERNSO.ZZT")));
+	assert_eq!(find_file(&directory, "DEMO.ZZT"), None);
+	assert_eq!(find_related_file(&directory.join("town.zzt"), "MUS"), Some(directory.join("Town.Mus")));
+	assert_eq!(files_with_extension(&directory, "ZZT"), vec![directory.join("CAVERNSO.ZZT"), directory.join("town.zzt")]);
+	assert!(find_related_file(Path::new("DOESNOTEXIST.ZZT"), "HI").is_none());
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn weave_sidecars() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_weave_{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	let mut palette_data = vec![0u8; 48];
+	palette_data[45 .. 48].copy_from_slice(&[63, 32, 0]);
+	std::fs::write(directory.join("WEAVE.PAL"), &palette_data).unwrap();
+	std::fs::write(directory.join("weave.cfg"), "; Comment\nhacks = off\nlegacy_tick\n").unwrap();
+	let mut font_data = vec![0u8; 256 * 14];
+	font_data[b'A' as usize * 14] = 0x81;
+	std::fs::write(directory.join("WEAVE.CHR"), &font_data).unwrap();
+
+	let sidecars = WeaveSidecars::load(&directory.join("WEAVE.ZZT")).unwrap();
+	assert_eq!(sidecars.palette.unwrap().colours[15], (0xff, 0x82, 0x00));
+	let config = sidecars.config.unwrap();
+	assert!(config.flag("LEGACY_TICK"));
+	assert!(!config.flag("hacks"));
+	let font = sidecars.font.unwrap();
+	assert_eq!(font.char_height, 14);
+	assert!(font.pixel_is_set(b'A', 0, 0) && font.pixel_is_set(b'A', 7, 0) && !font.pixel_is_set(b'A', 1, 0));
+
+	// A world without any sidecar files is fine, but a broken one is an error.
+	assert_eq!(WeaveSidecars::load(&directory.join("OTHER.ZZT")), Ok(WeaveSidecars::default()));
+	std::fs::write(directory.join("BROKEN.PAL"), b"short").unwrap();
+	assert!(WeaveSidecars::load(&directory.join("BROKEN.ZZT")).is_err());
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn file_browser_navigation() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_file_browser_{}", std::process::id()));
+	std::fs::create_dir_all(directory.join("Games")).unwrap();
+	let mut world_data = vec![];
+	World::zzt_default().write(&mut world_data).unwrap();
+	std::fs::write(directory.join("Games").join("town.zzt"), &world_data).unwrap();
+	// Files that aren't worlds are left out.
+	std::fs::write(directory.join("Games").join("BROKEN.ZZT"), b"junk").unwrap();
+
+	let mut file_browser = FileBrowser::new(DosString::from_str("Worlds"), &directory, "ZZT");
+	let kinds: Vec<FileBrowserEntryKind> = file_browser.entries().iter().map(|entry| entry.kind.clone()).collect();
+	assert_eq!(kinds, vec![FileBrowserEntryKind::ParentDirectory, FileBrowserEntryKind::Directory]);
+	assert_eq!(file_browser.entries()[1].browser_entry.file_name, DosString::from_str("GAMES\\"));
+
+	assert_eq!(file_browser.select(1), FileBrowserSelection::ChangedDirectory);
+	assert_eq!(file_browser.directory(), directory.join("Games"));
+	assert_eq!(file_browser.entries().len(), 2);
+	assert_eq!(file_browser.entries()[1].browser_entry.file_name, DosString::from_str("TOWN"));
+	assert_eq!(file_browser.select(1), FileBrowserSelection::File(directory.join("Games").join("town.zzt")));
+	assert_eq!(file_browser.select(5), FileBrowserSelection::None);
+
+	// ".." goes back up.
+	assert_eq!(file_browser.select(0), FileBrowserSelection::ChangedDirectory);
+	assert_eq!(file_browser.directory().canonicalize().unwrap(), directory.canonicalize().unwrap());
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn autosave_after_boards_entered() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_autosave_{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.autosave = Some(Autosave::new(directory.clone(), AutosaveOptions {
+		boards_between_autosaves: 1,
+		minutes_between_autosaves: 0.,
+	}));
+	assert!(!world.engine.offer_autosave_resume());
+
+	world.engine.step(Event::None, 0.);
+	world.engine.process_board_message(BoardMessage::TeleportToBoard{destination_board_index: 1, passage_colour: 0});
+	// Autosaves are rate-limited.
+	world.engine.step(Event::None, 5.);
+	assert!(Autosave::find(&directory).is_none());
+	world.engine.step(Event::None, 15.);
+	assert_eq!(Autosave::find(&directory), Some(Autosave::file_path(&directory)));
+	let autosaved_world = World::parse(&mut std::fs::File::open(Autosave::file_path(&directory)).unwrap()).unwrap();
+	assert_eq!(autosaved_world.boards[1].status_elements[0].location_x, 10);
+
+	assert!(world.engine.offer_autosave_resume());
+	assert!(matches!(&world.engine.process_typing(TypingEvent::Char(b'y'))[..], [BoardMessage::ResumeAutosave]));
+
+	Autosave::remove_files(&directory);
+	assert!(Autosave::find(&directory).is_none());
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn autosave_failure_is_logged() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_missing_autosave_{}", std::process::id()));
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.autosave = Some(Autosave::new(directory.clone(), AutosaveOptions {
+		boards_between_autosaves: 1,
+		minutes_between_autosaves: 0.,
+	}));
+
+	world.engine.step(Event::None, 0.);
+	world.engine.process_board_message(BoardMessage::TeleportToBoard{destination_board_index: 1, passage_colour: 0});
+	let board_messages = world.engine.step(Event::None, 15.);
+	assert!(board_messages.iter().any(|board_message| matches!(board_message, BoardMessage::EngineLog(LogLevel::Warning, message) if message.starts_with("Couldn't autosave"))));
+	assert!(!directory.exists());
+}
+
+#[test]
+fn compressed_savestate_files() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_savestates_{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.savestate_directory = Some(directory.clone());
+	world.engine.compress_savestates = true;
+	world.engine.save_savestate(1).unwrap();
+
+	let compressed_path = Savestate::slot_compressed_file_path(&directory, 1);
+	let compressed_bytes = std::fs::read(&compressed_path).unwrap();
+	let mut plain_bytes = vec![];
+	world.engine.savestate_slots[1].as_ref().unwrap().write(&mut plain_bytes).unwrap();
+	assert!(compressed_bytes.starts_with(COMPRESSED_SAVE_MAGIC));
+	assert!(compressed_bytes.len() < plain_bytes.len() / 2);
+	assert_eq!(decompress_save(compressed_bytes).unwrap(), plain_bytes);
+	assert_eq!(decompress_save(plain_bytes.clone()).unwrap(), plain_bytes);
+
+	// The savestate is read back from the file when it isn't in memory.
+	world.engine.savestate_slots[1] = None;
+	world.engine.board_simulator.world_header.player_gems = 5;
+	world.engine.load_savestate(1).unwrap();
+	assert_eq!(world.engine.board_simulator.world_header.player_gems, 0);
+
+	// Saving the slot without compression replaces the compressed file.
+	world.engine.compress_savestates = false;
+	world.engine.save_savestate(1).unwrap();
+	assert!(!compressed_path.exists());
+	assert!(Savestate::slot_file_path(&directory, 1).exists());
+	std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn zzt_config_and_high_scores() {
+	let directory = std::env::temp_dir().join(format!("ruzzt_zzt_config_{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	std::fs::write(directory.join("zzt.cfg"), "*CAVES\r\nJoe Bloggs\r\n").unwrap();
+	let mut high_score_data = vec![0u8; 30 * 53];
+	high_score_data[0] = 3;
+	high_score_data[1 .. 4].copy_from_slice(b"Ann");
+	high_score_data[51 .. 53].copy_from_slice(&120i16.to_le_bytes());
+	std::fs::write(directory.join("CAVES.HI"), &high_score_data).unwrap();
+
+	let zzt_config = ZztConfig::load(&directory).unwrap().unwrap();
+	assert_eq!(zzt_config.world_file_name(), Some("CAVES.ZZT".to_string()));
+	assert!(!zzt_config.editor_enabled);
+	assert_eq!(zzt_config.registration, Some("Joe Bloggs".to_string()));
+	assert_eq!(ZztConfig::parse("").world_file_name(), None);
+
+	let high_scores = HighScoreList::load(&directory.join("CAVES.ZZT")).unwrap().unwrap();
+	assert_eq!(high_scores.entries, vec![HighScoreEntry{name: DosString::from_str("Ann"), score: 120}]);
+	assert_eq!(HighScoreList::load(&directory.join("TOWN.ZZT")), Ok(None));
+	assert!(HighScoreList::parse(b"short").is_err());
+	assert_eq!(high_scores.scroll_lines().last(), Some(&DosString::from_str("  120  Ann")));
+
+	// The title screen shows them in a scroll.
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.high_scores = high_scores.clone();
+	world.engine.set_in_title_screen(true);
+	for board_message in world.engine.step(Event::OpenHighScores, 0.) {
+		world.engine.process_board_message(board_message);
+	}
+	let mut title = DosString::from_str("High scores for ");
+	title += world.engine.world.world_header.world_name.data.as_slice();
+	assert_eq!(world.engine.scroll_state, Some(ScrollState::new_title_content(title, high_scores.scroll_lines())));
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
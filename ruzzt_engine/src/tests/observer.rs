@@ -0,0 +1,41 @@
+use crate::tests::world_tester::*;
+use crate::observer::GameObserver;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct RecordingObserver {
+	scores: Vec<i16>,
+	keys: Vec<usize>,
+}
+
+impl GameObserver for RecordingObserver {
+	fn on_score_change(&mut self, _old_score: i16, new_score: i16) {
+		self.scores.push(new_score);
+	}
+
+	fn on_key_pickup(&mut self, key_index: usize) {
+		self.keys.push(key_index);
+	}
+}
+
+#[test]
+fn observer_sees_pickups() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.set_tile(11, 10, BoardTile::new(ElementType::Key, 0x0c));
+	world.engine.board_simulator.set_tile(12, 10, BoardTile::new(ElementType::Gem, 0x0a));
+
+	let observer = Rc::new(RefCell::new(RecordingObserver::default()));
+	world.engine.add_observer(observer.clone());
+
+	// Picking up an item plays a sound, which pauses the step part way through, so it takes two
+	// calls to `step` to finish each move.
+	world.event = Event::Right;
+	world.simulate(2);
+	world.event = Event::Right;
+	world.simulate(2);
+
+	assert_eq!(observer.borrow().keys, vec![3]);
+	assert_eq!(observer.borrow().scores, vec![10]);
+}
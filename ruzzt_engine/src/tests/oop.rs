@@ -1,4 +1,8 @@
 use crate::tests::world_tester::*;
+use crate::board_message::{BoardMessage, LogLevel};
+use crate::engine::ExtensionLevel;
+
+use zzt_file_format::extended_colour::{ExtendedColour, ExtendedColours};
 
 #[test]
 fn set_flag() {
@@ -83,3 +87,324 @@ fn go_i_doesnt_progress() {
 }
 
 // "A\n/i\nB\n/s\nC\n?i\nD\n?s\nE\n#set a\n/i\nF\n#send g\n:g\nG\n/i\nH\n#go i\nI\n/i\nJ\n#go s\nK\n/i\nL\n#try i\nM\n/i\nN\n#try s\nO\n/i\n"
+
+#[test]
+fn dry_run_object_leaves_world_alone() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#set a\n/e\n");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	
+	let board_index = world.world_header().player_board as usize;
+	let result = world.engine.dry_run_object(board_index, 1, 3).unwrap();
+	assert!(result.actions.iter().any(|action| if let crate::behaviour::Action::SetFlag(name) = action { name.data == b"a" } else { false }));
+	assert_eq!(result.board.status_elements[1].location_x, 11);
+	
+	assert_eq!(world.world_header().last_matching_flag(DosString::from_str("a")), None);
+	assert_eq!(world.status_at(10, 10).location_x, 10);
+}
+
+#[test]
+fn dry_run_object_stops_when_it_dies() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('D', "#die\n");
+	tile_set.add_object('B', "#set b\n");
+	world.insert_tile_and_status(tile_set.get('D'), 10, 10);
+	world.insert_tile_and_status(tile_set.get('B'), 12, 10);
+	
+	// The object after the one that died moves into its place in the list, but it isn't run.
+	let board_index = world.world_header().player_board as usize;
+	let result = world.engine.dry_run_object(board_index, 1, 3).unwrap();
+	assert_eq!(result.steps_simulated, 1);
+	assert!(!result.actions.iter().any(|action| matches!(action, crate::behaviour::Action::SetFlag(..))));
+	assert_eq!(result.board.status_elements.len(), 2);
+}
+
+#[test]
+fn bound_code_survives_removal_and_save() {
+	use crate::behaviour::{Action, ApplyActionResultReport};
+
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('A', "@a\n");
+	tile_set.add_object('D', "@d\n");
+	for x in 10 .. 13 {
+		world.insert_tile_and_status(tile_set.get('A'), x, 10);
+	}
+	world.insert_tile_and_status(tile_set.get('D'), 13, 10);
+	// Status 2 is bound to status 1, and status 3 is bound to status 2.
+	world.engine.board_simulator.status_elements[2].code_source = CodeSource::Bound(1);
+	world.engine.board_simulator.status_elements[3].code_source = CodeSource::Bound(2);
+
+	// Remove the middle of the chain, and then the status that owns the code.
+	for &x in &[11, 10] {
+		let action = Action::SetTile {
+			x,
+			y: 10,
+			tile: BoardTile::new(ElementType::Empty, 0),
+			status_element: None,
+		};
+		let mut accumulated_data = AccumulatedActionData::new();
+		world.engine.board_simulator.apply_action(0, 0, action, 0, None, &mut accumulated_data, &mut ApplyActionResultReport::new());
+	}
+
+	world.engine.sync_world();
+	let mut saved_bytes = vec![];
+	world.engine.world.write(&mut saved_bytes).unwrap();
+	let loaded_world = World::parse(&mut std::io::Cursor::new(saved_bytes)).unwrap();
+
+	let board = &loaded_world.boards[loaded_world.world_header.player_board as usize];
+	assert_eq!(board.status_elements.len(), 3);
+	assert_eq!(board.status_code(1), Some(&DosString::from_str("@a\n")));
+	assert_eq!(board.status_code(2), Some(&DosString::from_str("@d\n")));
+}
+
+#[test]
+fn labels_and_send_label() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "@obj\n#end\n:touch\n#set a\n'zapped\n:l_1\n");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	
+	let label_names: Vec<DosString> = world.engine.labels_of(1).into_iter().map(|(label, _)| label).collect();
+	assert_eq!(label_names, vec![DosString::from_str("touch"), DosString::from_str("l_1")]);
+	assert!(world.engine.labels_of(2).is_empty());
+	
+	assert!(world.engine.send_label(1, &DosString::from_str("nothing")).is_err());
+	world.engine.send_label(1, &DosString::from_str("TOUCH")).unwrap();
+	world.simulate(1);
+	assert_eq!(world.world_header().last_matching_flag(DosString::from_str("a")), Some(0));
+}
+
+#[test]
+fn walk_and_thud_order() {
+	// The object's code runs before it walks, so it moves in the same step it starts walking.
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#walk e\n");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	world.simulate(1);
+	assert_eq!(world.engine.board_simulator.get_first_status_for_pos(11, 10).map(|(status_index, _)| status_index), Some(1));
+	
+	// When it's blocked it is sent to :thud, which runs in its next step, once per blocked step.
+	tile_set.add_object('T', "#end\n:thud\n#give gems 1\n#end\n");
+	tile_set.add_object('B', "#end\n");
+	tile_set.add('#', BoardTile::new(ElementType::Normal, 0x0e), None);
+	for blocker in &['#', '&', 'B'] {
+		let mut world = if *blocker == '&' {
+			TestWorld::new_with_player(11, 10)
+		} else {
+			let mut world = TestWorld::new_with_player(1, 1);
+			world.insert_tile_and_status(tile_set.get(*blocker), 11, 10);
+			world
+		};
+		world.insert_tile_and_status(tile_set.get('T'), 10, 10);
+		world.status_at(10, 10).step_x = 1;
+		
+		for expected_gems in 0 .. 3 {
+			world.simulate(1);
+			assert_eq!(world.world_header().player_gems, expected_gems, "Blocked by {}", blocker);
+		}
+	}
+}
+
+#[test]
+fn bad_command_halts() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#set a\n#jmup done\n#set b\n:done\n#set c\n");
+	tile_set.add_object('S', "#send nolabel\n#set d\n");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	world.insert_tile_and_status(tile_set.get('S'), 12, 10);
+	
+	let board_messages = world.engine.step(Event::None, 0.);
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(LogLevel::Warning, _))));
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::OpenScroll{content_lines, ..} if content_lines[0].data == b"ERR: Bad command JMUP")));
+	assert_eq!(world.status_at(10, 10).code_current_instruction, -1);
+	
+	let is_set = |world: &TestWorld, flag: &str| world.world_header().last_matching_flag(DosString::from_str(flag)).is_some();
+	assert!(is_set(&world, "a"));
+	assert!(!is_set(&world, "b"));
+	assert!(!is_set(&world, "c"));
+	
+	// Sending to a label that doesn't exist does nothing, like in DOS ZZT.
+	world.simulate(1);
+	assert!(is_set(&world, "d"));
+}
+
+#[test]
+fn reserved_flags() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('1', "#if ruzzt set found\n#clear ruzzt\n#if ruzzt set stillset\n#end");
+	world.insert_template(&TileTemplate::from_text(&tile_set, "1"), 20, 5);
+	let mut dos_world = world.clone();
+	
+	world.engine.set_extension_level(ExtensionLevel::Ruzzt);
+	world.simulate(1);
+	// The reserved flags can't be cleared, and don't use up the world's flags.
+	assert!(world.world_header().last_matching_flag(DosString::from_slice(b"FOUND")).is_some());
+	assert!(world.world_header().last_matching_flag(DosString::from_slice(b"STILLSET")).is_some());
+	assert!(world.world_header().last_matching_flag(DosString::from_slice(b"RUZZT")).is_none());
+	
+	dos_world.simulate(1);
+	assert!(dos_world.world_header().last_matching_flag(DosString::from_slice(b"FOUND")).is_none());
+}
+
+#[test]
+fn extended_colours() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('1', "#colour 255 128 0\n#colour back 17\n#end");
+	tile_set.add_object('2', "#colour 196\n#colour off\n#end");
+	world.insert_template(&TileTemplate::from_text(&tile_set, "12"), 20, 5);
+	let mut dos_world = world.clone();
+	
+	world.engine.set_extension_level(ExtensionLevel::Ruzzt);
+	world.simulate(1);
+	let expected_colours = ExtendedColours {
+		foreground: Some(ExtendedColour::Rgb(255, 128, 0)),
+		background: Some(ExtendedColour::Indexed(17)),
+	};
+	assert_eq!(world.status_at(20, 5).extended_colours, Some(expected_colours));
+	assert_eq!(world.status_at(21, 5).extended_colours, None);
+	// The tile gets the closest standard colours, for when the world is played in DOS ZZT.
+	assert_eq!(world.engine.board_simulator.get_tile(20, 5).unwrap().colour, 0x16);
+	
+	world.engine.update_screen();
+	let screen_char = world.engine.console_state.get_char(19, 4);
+	assert_eq!(screen_char.extended_colours, Some(expected_colours));
+	assert_eq!(screen_char.extended_foreground_rgb(), Some((255, 128, 0)));
+	assert_eq!(world.engine.console_state.get_char(20, 4).extended_colours, None);
+	
+	// Without RUZZT's extensions, `#colour` jumps to a label like any other unknown command.
+	dos_world.simulate(1);
+	assert_eq!(dos_world.status_at(20, 5).extended_colours, None);
+	assert_eq!(dos_world.engine.board_simulator.get_tile(20, 5).unwrap().colour, 0x0f);
+}
+
+#[test]
+fn oop_instruction_limit() {
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('L', "@loop\n:a\n#give gems 1\n#send a\n");
+	let long_text: String = (0 .. 80).map(|line| format!("Line {}\n", line)).collect();
+	tile_set.add_object('T', &long_text);
+	world.insert_tile_and_status(tile_set.get('L'), 10, 10);
+	world.insert_tile_and_status(tile_set.get('T'), 12, 10);
+	world.engine.set_oop_instruction_limit(10);
+	
+	let board_messages = world.engine.step(Event::None, 0.);
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(LogLevel::Warning, text) if text.contains("@loop"))));
+	let gems = world.world_header().player_gems;
+	assert!(gems > 0 && gems <= 10);
+	
+	let mut board_messages = vec![];
+	for _ in 0 .. 3 {
+		board_messages.extend(world.engine.step(Event::None, 0.));
+	}
+	
+	// Lines of text don't count towards the limit.
+	assert!(board_messages.iter().any(|message| matches!(message, BoardMessage::OpenScroll{content_lines, ..} if content_lines.len() == 80)));
+}
+
+#[test]
+fn item_overflow_policy() {
+	let run = |policy: ItemOverflowPolicy| {
+		let mut world = TestWorld::new_with_player(1, 1);
+		let mut tile_set = TileSet::new();
+		tile_set.add_object('O', "#give score 30000\n#give score 30000\n#end\n");
+		world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+		world.engine.set_item_overflow_policy(policy);
+		world.engine.board_simulator.world_header.player_score = 0;
+		
+		let mut board_messages = vec![];
+		for _ in 0 .. 3 {
+			board_messages.extend(world.engine.step(Event::None, 0.));
+		}
+		let warned = board_messages.iter().any(|message| matches!(message, BoardMessage::EngineLog(LogLevel::Warning, _)));
+		(world.world_header().player_score, warned)
+	};
+	
+	assert_eq!(run(ItemOverflowPolicy::Wrap), (60000u16 as i16, false));
+	assert_eq!(run(ItemOverflowPolicy::Saturate), (i16::MAX, false));
+	assert_eq!(run(ItemOverflowPolicy::Error), (30000, true));
+}
+
+#[test]
+fn ruleset_oop_command() {
+	use crate::behaviour::{Action, PlayerItemType};
+	use crate::board_simulator::BoardSimulator;
+	use crate::ruleset::{RulesetPlugin, ZztRuleset};
+	use zzt_file_format::ElementType;
+	use std::rc::Rc;
+	
+	// A ruleset that is the same as ZZT, but with a `#heal` command.
+	#[derive(Debug)]
+	struct HealRuleset;
+	
+	impl RulesetPlugin for HealRuleset {
+		fn name(&self) -> &str {
+			"Heal"
+		}
+		
+		fn element_type(&self, element_id: u8) -> Option<ElementType> {
+			ZztRuleset.element_type(element_id)
+		}
+		
+		fn load_behaviours(&self, sim: &mut BoardSimulator) {
+			ZztRuleset.load_behaviours(sim);
+		}
+		
+		fn oop_command(&self, command_name: &DosString, arguments: &DosString, _status_index: usize, _sim: &BoardSimulator, actions: &mut Vec<Action>) -> bool {
+			if command_name.data != b"heal" {
+				return false;
+			}
+			let offset = arguments.to_string(false).trim().parse().unwrap_or(0);
+			actions.push(Action::ModifyPlayerItem{item_type: PlayerItemType::Health, offset, require_exact_amount: false});
+			true
+		}
+	}
+	
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#heal 5\n#set done\n");
+	world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+	let health = world.world_header().player_health;
+	
+	// Without the ruleset, `#heal` is a bad command.
+	let mut stock_world = world.clone();
+	stock_world.simulate(1);
+	assert_eq!(stock_world.world_header().player_health, health);
+	assert_eq!(stock_world.world_header().last_matching_flag(DosString::from_str("done")), None);
+	
+	world.engine.set_ruleset(Some(Rc::new(HealRuleset)));
+	world.simulate(1);
+	assert_eq!(world.world_header().player_health, health + 5);
+	assert_eq!(world.world_header().last_matching_flag(DosString::from_str("done")), Some(0));
+	assert_eq!(world.engine.board_simulator.ruleset.name(), "Heal");
+}
+
+#[test]
+fn compatibility_version_endgame() {
+	let run = |compatibility_version: CompatibilityVersion| {
+		let mut world = TestWorld::new_with_player(1, 1);
+		let mut tile_set = TileSet::new();
+		tile_set.add_object('O', "#endgame\n#end\n:endgame\n#set jumped\n#end\n");
+		world.insert_tile_and_status(tile_set.get('O'), 10, 10);
+		world.engine.set_compatibility_version(compatibility_version);
+		world.engine.board_simulator.world_header.player_health = 100;
+		
+		world.engine.step(Event::None, 0.);
+		let jumped = world.world_header().last_matching_flag(DosString::from_slice(b"JUMPED")).is_some();
+		(world.world_header().player_health, jumped)
+	};
+	
+	assert_eq!(run(CompatibilityVersion::Zzt32), (0, false));
+	// ZZT 3.0 has no `#endgame`, so it sends the object to its `:endgame` label.
+	assert_eq!(run(CompatibilityVersion::Zzt30), (100, true));
+}
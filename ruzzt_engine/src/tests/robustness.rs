@@ -0,0 +1,104 @@
+use crate::tests::world_tester::*;
+use crate::savestate::Savestate;
+use crate::smoke_test::fuzz_world_data;
+
+use zzt_file_format::padding::ParseOptions;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use std::io::Cursor;
+
+const DEFAULT_WORLD: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/data/DEFAULT.ZZT"));
+const FSLIME_WORLD: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/data/FSLIME.ZZT"));
+
+#[test]
+fn load_world_rejects_missing_player_board() {
+	let mut world = World::parse(&mut Cursor::new(DEFAULT_WORLD)).unwrap();
+	world.world_header.player_board = 5;
+
+	let mut engine = RuzztEngine::new();
+	assert_eq!(engine.load_world(world, None), Err("Can't start on board 5, because the world has 2 boards".to_string()));
+}
+
+#[test]
+fn largest_status_count_is_an_error() {
+	let mut data = vec![];
+	World::zzt_default().write(&mut data).unwrap();
+	// The title board has one status element without any code, so the status count is just before
+	// the last status element.
+	let count_pos = data.len() - 33 - 2;
+	assert_eq!(&data[count_pos .. count_pos + 2], &[0, 0]);
+	data[count_pos .. count_pos + 2].copy_from_slice(&i16::MAX.to_le_bytes());
+
+	// Adding 1 to the count used to overflow.
+	assert!(World::parse(&mut Cursor::new(data.as_slice())).is_err());
+	let options = ParseOptions { lenient: true, .. ParseOptions::default() };
+	assert!(World::parse_with_options(&mut Cursor::new(data.as_slice()), options).is_err());
+}
+
+#[test]
+fn savestate_with_status_count_of_minus_one() {
+	let mut data = vec![];
+	World::zzt_default().write(&mut data).unwrap();
+	// Remove the title board's only status element (the last 33 bytes) and make the count -1.
+	data.truncate(data.len() - 33);
+	let count_pos = data.len() - 2;
+	data[count_pos ..].copy_from_slice(&(-1i16).to_le_bytes());
+	let board_size_pos = 512;
+	let board_size = u16::from_le_bytes([data[board_size_pos], data[board_size_pos + 1]]) - 33;
+	data[board_size_pos .. board_size_pos + 2].copy_from_slice(&board_size.to_le_bytes());
+
+	// Only lenient parsing accepts the board, so savestates are read that way.
+	assert!(World::parse(&mut Cursor::new(data.as_slice())).is_err());
+	let savestate = Savestate::parse(&mut Cursor::new(data.as_slice())).unwrap();
+	assert_eq!(savestate.world.boards[0].status_elements.len(), 1);
+}
+
+#[test]
+fn code_bound_to_itself_gets_empty_code() {
+	let mut world = World::parse(&mut Cursor::new(DEFAULT_WORLD)).unwrap();
+	world.boards[1].tiles[9 + 9*60] = BoardTile::new(ElementType::Object, 0x0f);
+	let status_index = world.boards[1].status_elements.len();
+	world.boards[1].status_elements.push(StatusElement {
+		location_x: 10,
+		location_y: 10,
+		cycle: 1,
+		code_source: CodeSource::Bound(status_index),
+		.. StatusElement::default()
+	});
+
+	let mut engine = RuzztEngine::new();
+	engine.load_world(world, None).unwrap();
+	engine.set_in_title_screen(false);
+	engine.is_paused = false;
+	assert_eq!(engine.board_simulator.status_elements[status_index].code_source, CodeSource::Owned(DosString::new()));
+
+	// Following the binding used to never finish.
+	for _ in 0 .. 10 {
+		engine.step(Event::None, 0.);
+	}
+}
+
+// Mutated worlds often start out with broken boards, which the `check-invariants` feature would
+// panic on straight away (see `find_invariant_violations`).
+#[cfg(not(feature = "check-invariants"))]
+#[test]
+fn mutated_worlds_dont_panic() {
+	for seed in 0 .. 200 {
+		let mut rng = StdRng::seed_from_u64(seed);
+		let world_data = if seed % 2 == 0 { DEFAULT_WORLD } else { FSLIME_WORLD };
+
+		let event_count: u8 = rng.gen_range(50, 255);
+		let mut data = vec![event_count];
+		data.extend((0 .. event_count as usize * 3).map(|_| rng.gen::<u8>() % 32));
+		let world_start = data.len();
+		data.extend_from_slice(world_data);
+		for _ in 0 .. rng.gen_range(1, 40) {
+			let index = rng.gen_range(world_start, data.len());
+			data[index] = rng.gen();
+		}
+
+		fuzz_world_data(&data);
+	}
+}
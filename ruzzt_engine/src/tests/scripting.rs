@@ -0,0 +1,19 @@
+use crate::tests::world_tester::*;
+use crate::scripting::ScriptHost;
+
+#[test]
+fn script_drives_player() {
+	let world = TestWorld::new_with_player(10, 10);
+	let script = "
+		fn on_step() {
+			if player_x() < 12 {
+				send_event(\"right\");
+			}
+		}
+	";
+	let mut host = ScriptHost::new(world.engine, script).unwrap();
+	for _ in 0 .. 4 {
+		host.step(Event::None, 0.).unwrap();
+	}
+	assert_eq!(host.engine().board_simulator.get_player_location(), (12, 10));
+}
@@ -0,0 +1,280 @@
+use crate::behaviour::*;
+use crate::board_message::*;
+use crate::direction::Direction;
+use crate::sounds::*;
+
+use zzt_file_format::*;
+use zzt_file_format::dosstring::DosString;
+use zzt_file_format::extended_colour::{ExtendedColour, ExtendedColours};
+
+use std::path::PathBuf;
+
+// These matches have no wildcard arm, so adding a variant doesn't compile until it gets a sample
+// below. Each sample must be serializable, and the formats checked in `schema_stability` must not
+// change, so that recorded traces keep loading.
+
+fn action_variant_index(action: &Action) -> usize {
+	match action {
+		Action::SetColour{..} => 0,
+		Action::SetLeader{..} => 1,
+		Action::SetFollower{..} => 2,
+		Action::SetStep{..} => 3,
+		Action::PushTile{..} => 4,
+		Action::MoveTile{..} => 5,
+		Action::SetCodeCurrentInstruction{..} => 6,
+		Action::SetCode{..} => 7,
+		Action::BindCodeToIndex{..} => 8,
+		Action::SetTile{..} => 9,
+		Action::SetTileElementIdAndColour{..} => 10,
+		Action::SetAsPlayerTile{..} => 11,
+		Action::OthersApplyLabelOperation{..} => 12,
+		Action::SendBoardMessage(..) => 13,
+		Action::ModifyPlayerItem{..} => 14,
+		Action::ModifyPlayerKeys{..} => 15,
+		Action::SetTorchCycles(..) => 16,
+		Action::SetEnergyCycles(..) => 17,
+		Action::SetFlag(..) => 18,
+		Action::ClearFlag(..) => 19,
+		Action::SetStatusLocation{..} => 20,
+		Action::SetStatusParam1{..} => 21,
+		Action::SetStatusParam2{..} => 22,
+		Action::SetStatusParam3{..} => 23,
+		Action::ReprocessSameStatusIndexOnRemoval => 24,
+		Action::CheckRestartOnZapped => 25,
+		Action::CheckTimeElapsed => 26,
+		Action::SetCycle{..} => 27,
+		Action::EndGame => 28,
+		Action::SetExtendedColours{..} => 29,
+	}
+}
+
+fn sample_actions() -> Vec<Action> {
+	vec![
+		Action::SetColour{x: 1, y: 2, colour: 0x1f},
+		Action::SetLeader{status_index: 1, leader: -1},
+		Action::SetFollower{status_index: 1, follower: 2},
+		Action::SetStep{status_index: 1, step_x: 0, step_y: -1},
+		Action::PushTile{x: 1, y: 2, offset_x: 1, offset_y: 0},
+		Action::MoveTile{from_x: 1, from_y: 2, to_x: 2, to_y: 2, offset_x: 1, offset_y: 0, check_push: true, is_player: false},
+		Action::SetCodeCurrentInstruction{status_index: 1, code_current_instruction: 10},
+		Action::SetCode{status_index: 1, code: DosString::from_str("@bob\r#end\r")},
+		Action::BindCodeToIndex{status_index: 2, bind_to_index: 1},
+		Action::SetTile{
+			x: 1,
+			y: 2,
+			tile: BoardTile::new(ElementType::Object, 0x0f),
+			status_element: Some(StatusElement {
+				location_x: 1,
+				location_y: 2,
+				code_source: CodeSource::Owned(DosString::from_str("#end")),
+				.. StatusElement::default()
+			}),
+		},
+		Action::SetTileElementIdAndColour{x: 1, y: 2, element_id: Some(ElementType::Gem as u8), colour: None},
+		Action::SetAsPlayerTile{x: 1, y: 2},
+		Action::OthersApplyLabelOperation{
+			current_status_index: Some(1),
+			receiver_name_opt: Some(DosString::from_str("bob")),
+			label: DosString::from_str("touch"),
+			operation: LabelOperation::Zap,
+		},
+		Action::SendBoardMessage(BoardMessage::PauseGame),
+		Action::ModifyPlayerItem{item_type: PlayerItemType::Gems, offset: -5, require_exact_amount: true},
+		Action::ModifyPlayerKeys{index: 1, value: true},
+		Action::SetTorchCycles(200),
+		Action::SetEnergyCycles(75),
+		Action::SetFlag(DosString::from_str("DOOR")),
+		Action::ClearFlag(DosString::from_str("DOOR")),
+		Action::SetStatusLocation{x: 1, y: 2, status_index: 1},
+		Action::SetStatusParam1{value: 1, status_index: 1},
+		Action::SetStatusParam2{value: 2, status_index: 1},
+		Action::SetStatusParam3{value: 3, status_index: 1},
+		Action::ReprocessSameStatusIndexOnRemoval,
+		Action::CheckRestartOnZapped,
+		Action::CheckTimeElapsed,
+		Action::SetCycle{status_index: 1, cycle: 3},
+		Action::EndGame,
+		Action::SetExtendedColours{
+			status_index: 1,
+			extended_colours: Some(ExtendedColours{foreground: Some(ExtendedColour::Rgb(255, 128, 0)), background: Some(ExtendedColour::Indexed(17))}),
+		},
+	]
+}
+
+fn board_message_variant_index(board_message: &BoardMessage) -> usize {
+	match board_message {
+		BoardMessage::SwitchBoard{..} => 0,
+		BoardMessage::TeleportToBoard{..} => 1,
+		BoardMessage::ShowOneTimeNotification(..) => 2,
+		BoardMessage::OpenScroll{..} => 3,
+		BoardMessage::CloseScroll => 4,
+		BoardMessage::OpenWorldMap => 5,
+		BoardMessage::OpenMessageLog => 6,
+		BoardMessage::EnterPressedInScroll{..} => 7,
+		BoardMessage::PlaySoundArray(..) => 8,
+		BoardMessage::ClearPlayingSound => 9,
+		BoardMessage::SetAmbientMusic(..) => 10,
+		BoardMessage::OpenSaveGameInput => 11,
+		BoardMessage::SaveGameToFile(..) => 12,
+		BoardMessage::OpenDebugInput => 13,
+		BoardMessage::OpenBoardList => 14,
+		BoardMessage::WarpToBoard{..} => 15,
+		BoardMessage::DebugCommand(..) => 16,
+		BoardMessage::LinkClicked(..) => 17,
+		BoardMessage::PauseGame => 18,
+		BoardMessage::PlayGame => 19,
+		BoardMessage::OpenWorldSelection => 20,
+		BoardMessage::OpenAbout => 21,
+		BoardMessage::OpenSaveSelection => 22,
+		BoardMessage::OpenWorld{..} => 23,
+		BoardMessage::WorldLoadFailed{..} => 24,
+		BoardMessage::CloseWorldBrowser => 25,
+		BoardMessage::WorldBrowserEntrySelected{..} => 26,
+		BoardMessage::OpenPauseMenu => 27,
+		BoardMessage::ClosePauseMenu => 28,
+		BoardMessage::OpenSettings => 29,
+		BoardMessage::OpenGameSpeedInput => 30,
+		BoardMessage::SetGameSpeed(..) => 31,
+		BoardMessage::OpenKeyboardConfigInput => 32,
+		BoardMessage::SetKeyRepeatDelay(..) => 33,
+		BoardMessage::PlayerAppearsStuck => 34,
+		BoardMessage::EngineLog(..) => 35,
+		BoardMessage::OpenEndGameConfirmation => 36,
+		BoardMessage::OpenQuitConfirmation => 37,
+		BoardMessage::ReturnToTitleScreen => 38,
+		BoardMessage::Quit => 39,
+		BoardMessage::OpenNewWorldInput => 40,
+		BoardMessage::CreateWorld{..} => 41,
+		BoardMessage::OpenStatusCodeEditor{..} => 42,
+		BoardMessage::OpenDebugConsole => 43,
+		BoardMessage::SetStatusCode{..} => 44,
+		BoardMessage::CloseTextEditor => 45,
+		BoardMessage::RestoreSoundQueue(..) => 46,
+		BoardMessage::FileBrowserFileSelected{..} => 47,
+		BoardMessage::ResumeAutosave => 48,
+		BoardMessage::EndGame => 49,
+		BoardMessage::OpenHighScores => 50,
+		BoardMessage::ToggleListedFlag{..} => 51,
+		BoardMessage::SaveWorld => 52,
+		BoardMessage::DiscardWorldChanges => 53,
+		BoardMessage::SaveScreenshot{..} => 54,
+		BoardMessage::OpenPassagePasswordInput{..} => 55,
+		BoardMessage::PassagePasswordEntered{..} => 56,
+	}
+}
+
+fn sample_board_messages() -> Vec<BoardMessage> {
+	let sound = SoundEntry{sound_code: 39, length_multiplier: 4};
+	vec![
+		BoardMessage::SwitchBoard{new_board_index: 3, direction: Direction::North},
+		BoardMessage::TeleportToBoard{destination_board_index: 2, passage_colour: 0x1f},
+		BoardMessage::ShowOneTimeNotification(OneTimeNotification::NoAmmo),
+		BoardMessage::OpenScroll{title: DosString::from_str("Title"), content_lines: vec![DosString::from_str("Line")]},
+		BoardMessage::CloseScroll,
+		BoardMessage::OpenWorldMap,
+		BoardMessage::OpenMessageLog,
+		BoardMessage::EnterPressedInScroll{line_index: 4},
+		BoardMessage::PlaySoundArray(vec![sound.clone()], SoundPriority::Level(2)),
+		BoardMessage::ClearPlayingSound,
+		BoardMessage::SetAmbientMusic(vec![sound]),
+		BoardMessage::OpenSaveGameInput,
+		BoardMessage::SaveGameToFile(DosString::from_str("SAVED.SAV")),
+		BoardMessage::OpenDebugInput,
+		BoardMessage::OpenBoardList,
+		BoardMessage::WarpToBoard{board_index: 5},
+		BoardMessage::DebugCommand(DosString::from_str("ammo")),
+		BoardMessage::LinkClicked(DosString::from_str("-NEXT.ZZT")),
+		BoardMessage::PauseGame,
+		BoardMessage::PlayGame,
+		BoardMessage::OpenWorldSelection,
+		BoardMessage::OpenAbout,
+		BoardMessage::OpenSaveSelection,
+		BoardMessage::OpenWorld{filename: DosString::from_str("TOWN.ZZT")},
+		BoardMessage::WorldLoadFailed{filename: DosString::from_str("TOWN.ZZT"), error: "Bad world".to_string()},
+		BoardMessage::CloseWorldBrowser,
+		BoardMessage::WorldBrowserEntrySelected{entry_index: 1},
+		BoardMessage::OpenPauseMenu,
+		BoardMessage::ClosePauseMenu,
+		BoardMessage::OpenSettings,
+		BoardMessage::OpenGameSpeedInput,
+		BoardMessage::SetGameSpeed(4),
+		BoardMessage::OpenKeyboardConfigInput,
+		BoardMessage::SetKeyRepeatDelay(2),
+		BoardMessage::PlayerAppearsStuck,
+		BoardMessage::EngineLog(LogLevel::Warning, "Too many statuses".to_string()),
+		BoardMessage::OpenEndGameConfirmation,
+		BoardMessage::OpenQuitConfirmation,
+		BoardMessage::ReturnToTitleScreen,
+		BoardMessage::Quit,
+		BoardMessage::OpenNewWorldInput,
+		BoardMessage::CreateWorld{name: DosString::from_str("NEWWORLD")},
+		BoardMessage::OpenStatusCodeEditor{status_index: 1},
+		BoardMessage::OpenDebugConsole,
+		BoardMessage::SetStatusCode{status_index: 1, code: DosString::from_str("#end\n")},
+		BoardMessage::CloseTextEditor,
+		BoardMessage::RestoreSoundQueue(SoundQueue::new()),
+		BoardMessage::FileBrowserFileSelected{file_path: PathBuf::from("TOWN.ZZT")},
+		BoardMessage::ResumeAutosave,
+		BoardMessage::EndGame,
+		BoardMessage::OpenHighScores,
+		BoardMessage::ToggleListedFlag{line_index: 2},
+		BoardMessage::SaveWorld,
+		BoardMessage::DiscardWorldChanges,
+		BoardMessage::SaveScreenshot{path: PathBuf::from("SCREEN00.ANS")},
+		BoardMessage::OpenPassagePasswordInput{status_index: 3},
+		BoardMessage::PassagePasswordEntered{status_index: 3, password: DosString::from_str("xyzzy")},
+	]
+}
+
+#[test]
+fn every_variant_round_trips() {
+	let actions = sample_actions();
+	assert_eq!(actions.iter().map(action_variant_index).collect::<Vec<_>>(), (0 .. actions.len()).collect::<Vec<_>>());
+	for action in &actions {
+		let json = serde_json::to_string(action).unwrap();
+		let parsed: Action = serde_json::from_str(&json).unwrap();
+		assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+	}
+
+	let board_messages = sample_board_messages();
+	assert_eq!(board_messages.iter().map(board_message_variant_index).collect::<Vec<_>>(), (0 .. board_messages.len()).collect::<Vec<_>>());
+	for board_message in &board_messages {
+		let json = serde_json::to_string(board_message).unwrap();
+		let parsed: BoardMessage = serde_json::from_str(&json).unwrap();
+		assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+	}
+}
+
+#[test]
+fn board_message_names() {
+	// Serde names each variant after itself, so the name is at the start of the JSON.
+	for board_message in &sample_board_messages() {
+		let json = serde_json::to_string(board_message).unwrap();
+		let json_name = json.split(|c: char| !c.is_alphanumeric()).find(|part| !part.is_empty()).unwrap();
+		assert_eq!(board_message.name(), json_name);
+	}
+}
+
+#[test]
+fn schema_stability() {
+	let actions = sample_actions();
+	let board_messages = sample_board_messages();
+	for (index, expected) in &[
+		(0, r#"{"SetColour":{"x":1,"y":2,"colour":31}}"#),
+		(9, r##"{"SetTile":{"x":1,"y":2,"tile":{"element_id":"Object","colour":15},"status_element":{"location_x":1,"location_y":2,"step_x":0,"step_y":0,"cycle":1,"param1":0,"param2":0,"param3":0,"follower":-1,"leader":-1,"under_element_id":"Empty","under_colour":0,"code_current_instruction":0,"code_source":{"Owned":"#end"}}}}"##),
+		(13, r#"{"SendBoardMessage":"PauseGame"}"#),
+		(14, r#"{"ModifyPlayerItem":{"item_type":"Gems","offset":-5,"require_exact_amount":true}}"#),
+		(24, r#""ReprocessSameStatusIndexOnRemoval""#),
+	] {
+		assert_eq!(serde_json::to_string(&actions[*index]).unwrap(), *expected);
+	}
+	for (index, expected) in &[
+		(0, r#"{"SwitchBoard":{"new_board_index":3,"direction":"North"}}"#),
+		(2, r#"{"ShowOneTimeNotification":"NoAmmo"}"#),
+		(8, r#"{"PlaySoundArray":[[{"sound_code":39,"length_multiplier":4}],{"Level":2}]}"#),
+		(12, r#"{"SaveGameToFile":"SAVED.SAV"}"#),
+		(35, r#"{"EngineLog":["Warning","Too many statuses"]}"#),
+	] {
+		assert_eq!(serde_json::to_string(&board_messages[*index]).unwrap(), *expected);
+	}
+}
@@ -0,0 +1,163 @@
+use crate::sounds::*;
+
+#[test]
+fn sound_queue_priorities() {
+	let mut queue = SoundQueue::new();
+	let notes = process_notes_string_to_sound_entries(b"cdef");
+
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Level(2)), SoundQueueResult::Replaced);
+	// Lower levels are ignored, and equal or higher levels replace the current sound.
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Level(1)), SoundQueueResult::Ignored);
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Level(2)), SoundQueueResult::Replaced);
+	// Music is appended to sound effects, which doesn't change the current priority.
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Music), SoundQueueResult::Appended);
+	assert_eq!(queue.current_priority(), SoundPriority::Level(2));
+
+	for _ in 0 .. 8 {
+		assert!(queue.next_entry().is_some());
+	}
+	assert!(queue.is_playing());
+	assert_eq!(queue.next_entry(), None);
+	assert!(!queue.is_playing());
+
+	// Sound effects are ignored while music plays.
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Music), SoundQueueResult::Replaced);
+	assert_eq!(queue.queue(notes.clone(), SoundPriority::Level(3)), SoundQueueResult::Ignored);
+
+	// Music that doesn't fit in the buffer is dropped.
+	let long_notes = process_notes_string_to_sound_entries(&[b'c'; 130]);
+	assert_eq!(queue.queue(long_notes, SoundPriority::Music), SoundQueueResult::Ignored);
+}
+
+#[test]
+fn note_events() {
+	let events = process_notes_string(b"qcx+i1");
+
+	assert_eq!(events.len(), 3);
+	assert_eq!(events[0], NoteEvent {
+		kind: NoteKind::Tone { sound_code: 48, frequency: 256, divisor: 4660 },
+		duration_ticks: 8,
+	});
+	assert_eq!(events[1].kind, NoteKind::Rest);
+	assert_eq!(events[2], NoteEvent { kind: NoteKind::Drum(1), duration_ticks: 4 });
+	assert_eq!(events[2].to_sound_entry(), SoundEntry { sound_code: 241, length_multiplier: 4 });
+}
+
+#[test]
+fn fast_simulation_sound_policy() {
+	use crate::board_message::BoardMessage;
+	use crate::tests::world_tester::*;
+
+	let mut world = TestWorld::new_with_player(1, 1);
+	let mut tile_set = TileSet::new();
+	tile_set.add_object('O', "#play c\n/i\n#restart\n");
+	for x in 10 .. 14 {
+		world.insert_tile_and_status(tile_set.get('O'), x, 10);
+	}
+	// Sounds are muted when the game is over, so the fast simulation is started directly.
+	world.engine.board_should_simulate_fast = true;
+	world.simulate(1);
+
+	let count_sounds = |world: &mut TestWorld, policy| {
+		world.engine.fast_simulation_sound_policy = policy;
+		let mut sound_count = 0;
+		for _ in 0 .. 4 {
+			loop {
+				let board_messages = world.engine.step(Event::None, 0.);
+				sound_count += board_messages.iter().filter(|message| matches!(message, BoardMessage::PlaySoundArray(..))).count();
+				if !world.engine.is_part_way_though_step() {
+					break;
+				}
+			}
+		}
+		sound_count
+	};
+
+	assert!(count_sounds(&mut world, FastSimulationSoundPolicy::PlayAll) > 4);
+	assert_eq!(count_sounds(&mut world, FastSimulationSoundPolicy::Compress), 4);
+	assert_eq!(count_sounds(&mut world, FastSimulationSoundPolicy::Drop), 0);
+}
+
+#[test]
+fn ambient_music() {
+	use crate::ambient_music::parse_ambient_music;
+	use crate::board_message::BoardMessage;
+	use crate::tests::world_tester::*;
+
+	let board_music = parse_ambient_music("# Town music\n\n1 cde\n3  tg\n").unwrap();
+	assert_eq!(board_music.get(&1), Some(&DosString::from_str("cde")));
+	assert_eq!(board_music.get(&3), Some(&DosString::from_str("tg")));
+	assert!(parse_ambient_music("x cde").is_err());
+
+	let mut world = TestWorld::new_with_player(10, 10);
+	let board_index = world.world_header().player_board as u8;
+	world.engine.board_ambient_music = board_music.clone();
+	world.engine.board_ambient_music.insert(board_index, DosString::from_str("cde"));
+
+	let is_set_ambient_music = |board_message: &BoardMessage| matches!(board_message, BoardMessage::SetAmbientMusic(entries) if entries.len() == 3);
+	assert_eq!(world.engine.step(Event::None, 0.).iter().filter(|board_message| is_set_ambient_music(board_message)).count(), 1);
+	assert_eq!(world.engine.step(Event::None, 0.).iter().filter(|board_message| is_set_ambient_music(board_message)).count(), 0);
+
+	// The ambient loop repeats while nothing else plays, and anything else replaces it.
+	let mut queue = SoundQueue::new();
+	queue.set_ambient_loop(process_notes_string_to_sound_entries(b"cd"));
+	assert_eq!(queue.next_entry().unwrap().sound_code, 48);
+	assert_eq!(queue.next_entry().unwrap().sound_code, 50);
+	assert_eq!(queue.next_entry().unwrap().sound_code, 48);
+	assert_eq!(queue.queue(process_notes_string_to_sound_entries(b"e"), SoundPriority::Level(1)), SoundQueueResult::Replaced);
+	assert_eq!(queue.next_entry().unwrap().sound_code, 52);
+	queue.set_ambient_loop(vec![]);
+	assert_eq!(queue.next_entry(), None);
+}
+
+#[test]
+fn sound_queue_advance() {
+	let mut queue = SoundQueue::new();
+	assert!(!queue.advance(100.));
+	assert_eq!(queue.playing_entry(), None);
+
+	// "cd" as 32nd notes, so each entry lasts a 32nd of a whole note.
+	let note_ms = WHOLE_NOTE_MS / 32.;
+	queue.queue(vec![SoundEntry{sound_code: 48, length_multiplier: 1}, SoundEntry{sound_code: 50, length_multiplier: 1}], SoundPriority::Level(1));
+	assert!(queue.advance(0.));
+	assert_eq!(queue.playing_entry().unwrap().sound_code, 48);
+	assert!(!queue.advance(note_ms / 2.));
+	assert_eq!(queue.ms_until_next_entry(), Some(note_ms / 2.));
+	assert!(queue.advance(note_ms));
+	assert_eq!(queue.playing_entry().unwrap().sound_code, 50);
+	assert!(queue.advance(note_ms));
+	assert_eq!(queue.playing_entry(), None);
+	assert!(!queue.is_playing());
+
+	// A sound that replaces the playing one starts on the next advance.
+	queue.queue(vec![SoundEntry{sound_code: 48, length_multiplier: 4}], SoundPriority::Level(1));
+	queue.advance(note_ms);
+	queue.queue(vec![SoundEntry{sound_code: 52, length_multiplier: 4}], SoundPriority::Level(2));
+	assert!(queue.advance(0.));
+	assert_eq!(queue.playing_entry().unwrap().sound_code, 52);
+
+	// An ambient loop that takes no time is left silent instead of repeating forever.
+	queue.clear();
+	queue.set_ambient_loop(vec![SoundEntry{sound_code: 48, length_multiplier: 0}]);
+	assert!(queue.advance(note_ms));
+	assert_eq!(queue.playing_entry(), None);
+}
+
+#[test]
+fn notes_warnings() {
+	let (note_events, warnings) = process_notes_string_with_warnings(b"c z#e!");
+	assert_eq!(note_events, process_notes_string(b"ce!"));
+	assert_eq!(warnings, vec![
+		NotesWarning{position: 2, char_code: b'z'},
+		NotesWarning{position: 3, char_code: b'#'},
+	]);
+
+	let mut world = zzt_file_format::World::zzt_default();
+	world.boards[0].status_elements.push(zzt_file_format::StatusElement {
+		code_source: zzt_file_format::CodeSource::Owned(zzt_file_format::dosstring::DosString::from_str("@tune\n#player\n#PLAY ck\n")),
+		.. zzt_file_format::StatusElement::default()
+	});
+	let warnings = crate::oop_lint::lint_world(&world);
+	assert_eq!(warnings.len(), 1);
+	assert_eq!(warnings[0].description(), "Board 0 status 1 line 3: #play: Unknown character 'k' at position 2 of the notes");
+}
@@ -0,0 +1,21 @@
+use crate::tests::world_tester::*;
+use crate::stuck_detector::player_appears_stuck;
+
+#[test]
+fn walled_in_player_is_stuck() {
+	let mut world = TestWorld::new_with_player(10, 10);
+	world.engine.board_simulator.board_meta_data.exit_north = 0;
+	world.engine.board_simulator.board_meta_data.exit_south = 0;
+	world.engine.board_simulator.board_meta_data.exit_west = 0;
+	world.engine.board_simulator.board_meta_data.exit_east = 0;
+	for &(x, y) in &[(9, 10), (11, 10), (10, 9), (10, 11)] {
+		world.engine.board_simulator.set_tile(x, y, BoardTile::new(ElementType::Solid, 0x0e));
+	}
+	assert!(player_appears_stuck(&world.engine.board_simulator));
+
+	// A door the player has the key for is a way out.
+	world.engine.board_simulator.set_tile(11, 10, BoardTile::new(ElementType::Door, 0x1f));
+	assert!(player_appears_stuck(&world.engine.board_simulator));
+	world.engine.board_simulator.world_header.player_keys[0] = true;
+	assert!(!player_appears_stuck(&world.engine.board_simulator));
+}
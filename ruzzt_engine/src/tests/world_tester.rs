@@ -19,12 +19,14 @@ impl TestWorld {
 		let mut world = World::parse(&mut cursor).unwrap();
 		// Remove the player.
 		world.boards[1].status_elements.clear();
-		world.boards[1].tiles[29 + 11*BOARD_WIDTH] = BoardTile::new(ElementType::Empty, 0);
+		world.boards[1].tiles[29 + 11*WorldType::Zzt.board_size().0] = BoardTile::new(ElementType::Empty, 0);
 
 		let mut engine = RuzztEngine::new();
-		engine.load_world(world, None);
+		engine.load_world(world, None).unwrap();
 		engine.set_in_title_screen(false);
 		engine.is_paused = false;
+		// Tests add the player themselves, so the warning about there being no player is dropped.
+		engine.accumulated_data.board_messages.clear();
 		
 		TestWorld {
 			engine,
@@ -166,6 +168,12 @@ impl TestWorld {
 	pub fn world_header(&self) -> &WorldHeader {
 		&self.engine.board_simulator.world_header
 	}
+	
+	/// Draw the screen and get the lines of text on it (see `ConsoleState::to_plain_text`).
+	pub fn screen_lines(&mut self) -> Vec<String> {
+		self.engine.update_screen();
+		self.engine.console_state.to_plain_text().lines().map(|line| line.to_string()).collect()
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -190,7 +198,7 @@ impl TileSet {
 	}
 	
 	pub fn add_object(&mut self, c: char, code: &str) {
-		self.add(c, BoardTile::new(ElementType::Object, 0xff), Some(StatusElement {
+		self.add(c, BoardTile::new(ElementType::Object, 0x0f), Some(StatusElement {
 			cycle: 1,
 			code_source: CodeSource::Owned(DosString::from_str(code)),
 			.. StatusElement::default()
@@ -0,0 +1,213 @@
+use crate::board_message::*;
+use crate::console::*;
+use crate::event::*;
+use crate::scroll::ScrollState;
+
+use zzt_file_format::dosstring::DosString;
+
+/// The number of characters that fit on each line of the text editor. Lines can't be typed any
+/// longer than this.
+pub const TEXT_EDITOR_LINE_WIDTH: usize = 42;
+/// The number of lines of text shown at once.
+const VISIBLE_LINE_COUNT: usize = 15;
+/// The y position on the screen of the first line of text.
+const FIRST_TEXT_ROW: usize = 6;
+/// The x position on the screen of the first character of each line.
+const TEXT_X: usize = 9;
+/// The number of lines that page up and page down move the cursor.
+const PAGE_SIZE: usize = 14;
+
+/// What the text in a `TextEditorState` is for, which decides what happens when it is confirmed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextEditorPurpose {
+	/// The code of the status element at `status_index` on the current board.
+	StatusCode{status_index: usize},
+	/// Debug commands (see `RuzztEngine::process_debug_command`), one per line, which are run in
+	/// order.
+	DebugConsole,
+}
+
+/// The state of a multi-line text editor, which is drawn over the board in a scroll window. Lines
+/// are stored without their line endings, and any CP437 character can be typed (frontends usually
+/// let Alt and the number pad type characters by their code, like DOS). Pressing Escape confirms
+/// the text, like the object code editor in DOS ZZT.
+#[derive(Clone)]
+pub struct TextEditorState {
+	/// The title displayed at the top of the editor.
+	title: DosString,
+	/// What the text is for.
+	purpose: TextEditorPurpose,
+	/// The text being edited. There is always at least one line.
+	lines: Vec<DosString>,
+	/// The index of the character in the current line that the cursor is before.
+	cursor_x: usize,
+	/// The index within `lines` of the line the cursor is on.
+	cursor_y: usize,
+	/// The index within `lines` of the line shown at the top of the editor.
+	scroll_y: usize,
+}
+
+impl TextEditorState {
+	/// Make a new editor with the given `title` and `purpose`, starting with the given `text`, which
+	/// has a carriage return at the end of each line (like ZZT code).
+	pub fn new(title: DosString, purpose: TextEditorPurpose, text: &DosString) -> TextEditorState {
+		let mut lines: Vec<DosString> = text.data.split(|&char_code| char_code == b'\r').map(DosString::from_slice).collect();
+		// The last line ending doesn't start a new line.
+		if lines.len() > 1 && lines.last().is_some_and(|line| line.data.is_empty()) {
+			lines.pop();
+		}
+
+		TextEditorState {
+			title,
+			purpose,
+			lines,
+			cursor_x: 0,
+			cursor_y: 0,
+			scroll_y: 0,
+		}
+	}
+
+	/// What the text is for.
+	pub fn purpose(&self) -> &TextEditorPurpose {
+		&self.purpose
+	}
+
+	/// Get the lines of text being edited.
+	pub fn lines(&self) -> &[DosString] {
+		&self.lines
+	}
+
+	/// Get the `x`/`y` position of the cursor within `lines()`.
+	pub fn cursor(&self) -> (usize, usize) {
+		(self.cursor_x, self.cursor_y)
+	}
+
+	/// Get the text being edited, with a carriage return at the end of each line.
+	pub fn text(&self) -> DosString {
+		let mut text = DosString::new();
+		for line in &self.lines {
+			text += &line.data;
+			text.push(b'\r');
+		}
+		text
+	}
+
+	/// Move the cursor to the given `line_index`, keeping it within the line and scrolling so it is
+	/// visible.
+	fn move_to_line(&mut self, line_index: usize) {
+		self.cursor_y = line_index.min(self.lines.len() - 1);
+		self.cursor_x = self.cursor_x.min(self.lines[self.cursor_y].len());
+		if self.cursor_y < self.scroll_y {
+			self.scroll_y = self.cursor_y;
+		} else if self.cursor_y >= self.scroll_y + VISIBLE_LINE_COUNT {
+			self.scroll_y = self.cursor_y + 1 - VISIBLE_LINE_COUNT;
+		}
+	}
+
+	/// Get the board messages that apply the text, for when it is confirmed.
+	fn confirm(&self) -> Vec<BoardMessage> {
+		let mut board_messages = vec![BoardMessage::CloseTextEditor];
+		match self.purpose {
+			TextEditorPurpose::StatusCode{status_index} => {
+				board_messages.push(BoardMessage::SetStatusCode{status_index, code: self.text()});
+			}
+			TextEditorPurpose::DebugConsole => {
+				for line in self.lines.iter().filter(|line| !line.data.is_empty()) {
+					board_messages.push(BoardMessage::DebugCommand(line.clone()));
+				}
+			}
+		}
+		board_messages
+	}
+
+	/// Edit the text with a single typing `event`. Returns `BoardMessage`s when the text is
+	/// confirmed.
+	pub fn process_typing(&mut self, event: TypingEvent) -> Vec<BoardMessage> {
+		let mut board_messages = vec![];
+		match event {
+			TypingEvent::Char(char_code) => {
+				let line = &mut self.lines[self.cursor_y];
+				// Carriage returns separate the lines when the text is saved, so they can't be typed.
+				if char_code != b'\r' && line.len() < TEXT_EDITOR_LINE_WIDTH {
+					line.data.insert(self.cursor_x, char_code);
+					self.cursor_x += 1;
+				}
+			}
+			TypingEvent::Enter => {
+				let rest_of_line = self.lines[self.cursor_y].data.split_off(self.cursor_x);
+				self.lines.insert(self.cursor_y + 1, DosString::from_slice(&rest_of_line));
+				self.cursor_x = 0;
+				self.move_to_line(self.cursor_y + 1);
+			}
+			TypingEvent::Backspace => {
+				if self.cursor_x > 0 {
+					self.cursor_x -= 1;
+					self.lines[self.cursor_y].data.remove(self.cursor_x);
+				} else if self.cursor_y > 0 {
+					let line = self.lines.remove(self.cursor_y);
+					self.cursor_x = self.lines[self.cursor_y - 1].len();
+					self.lines[self.cursor_y - 1] += &line.data;
+					self.move_to_line(self.cursor_y - 1);
+				}
+			}
+			TypingEvent::Delete => {
+				if self.cursor_x < self.lines[self.cursor_y].len() {
+					self.lines[self.cursor_y].data.remove(self.cursor_x);
+				} else if self.cursor_y + 1 < self.lines.len() {
+					let next_line = self.lines.remove(self.cursor_y + 1);
+					self.lines[self.cursor_y] += &next_line.data;
+				}
+			}
+			TypingEvent::Left => {
+				if self.cursor_x > 0 {
+					self.cursor_x -= 1;
+				} else if self.cursor_y > 0 {
+					self.cursor_x = usize::MAX;
+					self.move_to_line(self.cursor_y - 1);
+				}
+			}
+			TypingEvent::Right => {
+				if self.cursor_x < self.lines[self.cursor_y].len() {
+					self.cursor_x += 1;
+				} else if self.cursor_y + 1 < self.lines.len() {
+					self.cursor_x = 0;
+					self.move_to_line(self.cursor_y + 1);
+				}
+			}
+			TypingEvent::Up => self.move_to_line(self.cursor_y.saturating_sub(1)),
+			TypingEvent::Down => self.move_to_line(self.cursor_y + 1),
+			TypingEvent::PageUp => self.move_to_line(self.cursor_y.saturating_sub(PAGE_SIZE)),
+			TypingEvent::PageDown => self.move_to_line(self.cursor_y + PAGE_SIZE),
+			TypingEvent::Home => self.cursor_x = 0,
+			TypingEvent::End => self.cursor_x = self.lines[self.cursor_y].len(),
+			TypingEvent::Escape => {
+				board_messages = self.confirm();
+			}
+			TypingEvent::OpenCharPicker | TypingEvent::None => {}
+		}
+		board_messages
+	}
+
+	/// Renders the editor, with the text and cursor, to the console.
+	pub fn draw_text_editor(&self, console_state: &mut ConsoleState) {
+		// The editor looks like a scroll, so draw an empty one for the borders and title.
+		ScrollState::new_title_content(self.title.clone(), vec![DosString::new()]).draw_scroll(console_state);
+
+		for row in 0 .. VISIBLE_LINE_COUNT {
+			let y = FIRST_TEXT_ROW + row;
+			for x in TEXT_X - 2 .. TEXT_X + TEXT_EDITOR_LINE_WIDTH + 1 {
+				*console_state.get_char_mut(x, y) = ConsoleChar::new(0, ConsoleColour::Blue, ConsoleColour::Yellow);
+			}
+
+			if let Some(line) = self.lines.get(self.scroll_y + row) {
+				let visible_text = &line.data[.. line.len().min(TEXT_EDITOR_LINE_WIDTH)];
+				console_state.draw_text_at(TEXT_X, y, visible_text, ConsoleColour::Blue, ConsoleColour::Yellow);
+			}
+		}
+
+		let cursor_x = self.cursor_x.min(TEXT_EDITOR_LINE_WIDTH - 1);
+		let cursor_char = self.lines[self.cursor_y].data.get(cursor_x).cloned().unwrap_or(b' ');
+		let cursor_row = FIRST_TEXT_ROW + self.cursor_y - self.scroll_y;
+		*console_state.get_char_mut(TEXT_X + cursor_x, cursor_row) = ConsoleChar::new(cursor_char, ConsoleColour::LightGray, ConsoleColour::Black);
+	}
+}
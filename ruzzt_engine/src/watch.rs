@@ -0,0 +1,194 @@
+use crate::board_simulator::BoardSimulator;
+
+use zzt_file_format::StatusElement;
+use zzt_file_format::dosstring::DosString;
+
+/// A value of a status element that a watch can show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusField {
+	X,
+	Y,
+	StepX,
+	StepY,
+	Cycle,
+	Param1,
+	Param2,
+	Param3,
+	Follower,
+	Leader,
+	/// The position in the code of the next instruction (`code_current_instruction`).
+	Instruction,
+}
+
+impl StatusField {
+	/// Every field, in the order they are listed in errors.
+	const ALL: [StatusField; 11] = [
+		StatusField::X,
+		StatusField::Y,
+		StatusField::StepX,
+		StatusField::StepY,
+		StatusField::Cycle,
+		StatusField::Param1,
+		StatusField::Param2,
+		StatusField::Param3,
+		StatusField::Follower,
+		StatusField::Leader,
+		StatusField::Instruction,
+	];
+
+	/// The name that the field is typed as, eg. "param1".
+	pub fn name(self) -> &'static str {
+		match self {
+			StatusField::X => "x",
+			StatusField::Y => "y",
+			StatusField::StepX => "stepx",
+			StatusField::StepY => "stepy",
+			StatusField::Cycle => "cycle",
+			StatusField::Param1 => "param1",
+			StatusField::Param2 => "param2",
+			StatusField::Param3 => "param3",
+			StatusField::Follower => "follower",
+			StatusField::Leader => "leader",
+			StatusField::Instruction => "instruction",
+		}
+	}
+
+	/// Get the field with the given `name` (see `name`), ignoring case.
+	pub fn from_name(name: &str) -> Option<StatusField> {
+		StatusField::ALL.iter().cloned().find(|field| field.name().eq_ignore_ascii_case(name))
+	}
+
+	/// Get the value of this field in `status`.
+	pub fn value(self, status: &StatusElement) -> i32 {
+		match self {
+			StatusField::X => status.location_x as i32,
+			StatusField::Y => status.location_y as i32,
+			StatusField::StepX => status.step_x as i32,
+			StatusField::StepY => status.step_y as i32,
+			StatusField::Cycle => status.cycle as i32,
+			StatusField::Param1 => status.param1 as i32,
+			StatusField::Param2 => status.param2 as i32,
+			StatusField::Param3 => status.param3 as i32,
+			StatusField::Follower => status.follower as i32,
+			StatusField::Leader => status.leader as i32,
+			StatusField::Instruction => status.code_current_instruction as i32,
+		}
+	}
+}
+
+/// What a `Watch` shows the value of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchTarget {
+	/// A field of the status element at `status_index` on the current board.
+	Status{status_index: usize, field: StatusField},
+	/// Whether the flag with the given name is set, as 1 or 0.
+	Flag(DosString),
+	/// The player's health.
+	Health,
+	/// The player's ammo.
+	Ammo,
+	/// The player's gems.
+	Gems,
+	/// The player's torches.
+	Torches,
+	/// The player's score.
+	Score,
+	/// The number of seconds that have passed on a board with a time limit.
+	Time,
+}
+
+impl WatchTarget {
+	/// Parse a watch typed into the debug prompt, like "status 12 param1", "flag secret" or
+	/// "health". Returns a description of what is wrong with it if it can't be parsed.
+	pub fn parse(text: &str) -> Result<WatchTarget, String> {
+		let words: Vec<String> = text.split_whitespace().map(|word| word.to_ascii_lowercase()).collect();
+		let words: Vec<&str> = words.iter().map(|word| word.as_str()).collect();
+		match words.as_slice() {
+			["status", status_index, field] => {
+				let status_index = status_index.parse().map_err(|_| format!("Invalid status index: {}", status_index))?;
+				let field = StatusField::from_name(field).ok_or_else(|| {
+					let field_names: Vec<&str> = StatusField::ALL.iter().map(|field| field.name()).collect();
+					format!("Unknown status field {} (expected one of {})", field, field_names.join(", "))
+				})?;
+				Ok(WatchTarget::Status{status_index, field})
+			}
+			["flag", flag_name] => Ok(WatchTarget::Flag(DosString::from_str(flag_name).to_upper())),
+			["health"] => Ok(WatchTarget::Health),
+			["ammo"] => Ok(WatchTarget::Ammo),
+			["gems"] => Ok(WatchTarget::Gems),
+			["torches"] => Ok(WatchTarget::Torches),
+			["score"] => Ok(WatchTarget::Score),
+			["time"] => Ok(WatchTarget::Time),
+			_ => Err(format!("Can't watch \"{}\" (expected eg. \"status 12 param1\", \"flag NAME\" or \"health\")", text.trim())),
+		}
+	}
+
+	/// Get the current value of the target in `sim`, or None if it doesn't exist (eg. a status
+	/// index past the end of the board's status elements, or torches in Super ZZT).
+	pub fn value(&self, sim: &BoardSimulator) -> Option<i32> {
+		let world_header = &sim.world_header;
+		match self {
+			WatchTarget::Status{status_index, field} => sim.status_elements.get(*status_index).map(|status| field.value(status)),
+			WatchTarget::Flag(flag_name) => Some(sim.flag_is_set(flag_name) as i32),
+			WatchTarget::Health => Some(world_header.player_health as i32),
+			WatchTarget::Ammo => Some(world_header.player_ammo as i32),
+			WatchTarget::Gems => Some(world_header.player_gems as i32),
+			WatchTarget::Torches => world_header.player_torches.map(|torches| torches as i32),
+			WatchTarget::Score => Some(world_header.player_score as i32),
+			WatchTarget::Time => Some(world_header.time_passed as i32),
+		}
+	}
+
+	/// Describe the target the same way it is typed, eg. "status 12 param1".
+	pub fn description(&self) -> String {
+		match self {
+			WatchTarget::Status{status_index, field} => format!("status {} {}", status_index, field.name()),
+			WatchTarget::Flag(flag_name) => format!("flag {}", flag_name.to_string(false)),
+			WatchTarget::Health => "health".to_string(),
+			WatchTarget::Ammo => "ammo".to_string(),
+			WatchTarget::Gems => "gems".to_string(),
+			WatchTarget::Torches => "torches".to_string(),
+			WatchTarget::Score => "score".to_string(),
+			WatchTarget::Time => "time".to_string(),
+		}
+	}
+}
+
+/// A value shown in the debug overlay every frame (see `RuzztEngine::watches`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watch {
+	/// What the watch shows.
+	pub target: WatchTarget,
+	/// When true, the game is paused when the value changes, so the change can be looked at
+	/// before anything else happens.
+	pub break_on_change: bool,
+	/// The value when the watch was last updated.
+	pub last_value: Option<i32>,
+}
+
+impl Watch {
+	/// Make a watch of `target`, starting with its current value in `sim`.
+	pub fn new(target: WatchTarget, break_on_change: bool, sim: &BoardSimulator) -> Watch {
+		let last_value = target.value(sim);
+		Watch {
+			target,
+			break_on_change,
+			last_value,
+		}
+	}
+
+	/// The line shown for the watch in the overlay, eg. "status 12 param1 = 3". Watches that break
+	/// on change are marked with a `*`.
+	pub fn overlay_line(&self) -> String {
+		let marker = if self.break_on_change { "*" } else { "" };
+		format!("{}{} = {}", marker, self.target.description(), format_watch_value(self.last_value))
+	}
+}
+
+/// Format a watched value, showing a value that doesn't exist as "-".
+pub fn format_watch_value(value: Option<i32>) -> String {
+	match value {
+		Some(value) => value.to_string(),
+		None => "-".to_string(),
+	}
+}
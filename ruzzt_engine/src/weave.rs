@@ -0,0 +1,194 @@
+use crate::palette::{DisplayOptions, Palette};
+
+use zzt_file_format::World;
+use zzt_file_format::metadata::WorldMetadata;
+#[cfg(feature = "file-io")]
+use zzt_file_format::padding::ParseOptions;
+
+use std::collections::BTreeMap;
+#[cfg(feature = "file-io")]
+use std::path::Path;
+
+/// The file extension of Weave ZZT config files, eg. `TOWN.CFG` next to `TOWN.ZZT`.
+pub const WEAVE_CONFIG_FILE_EXTENSION: &str = "CFG";
+/// The file extension of Weave ZZT palette files.
+pub const WEAVE_PALETTE_FILE_EXTENSION: &str = "PAL";
+/// The file extension of Weave ZZT font files.
+pub const WEAVE_FONT_FILE_EXTENSION: &str = "CHR";
+
+/// The settings from a Weave ZZT `.CFG` file.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct WeaveConfig {
+	/// The value of each setting, keyed by the setting name in upper case. Settings without a value
+	/// (flags) have an empty value.
+	pub settings: BTreeMap<String, String>,
+}
+
+impl WeaveConfig {
+	/// Parse a Weave config file. Blank lines and lines starting with `;` or `#` are ignored. Every
+	/// other line is either `NAME=VALUE` or just `NAME` to turn on a flag.
+	pub fn parse(text: &str) -> Result<WeaveConfig, String> {
+		let mut config = WeaveConfig::default();
+
+		for (line_index, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+				continue;
+			}
+
+			let (name, value) = match line.split_once('=') {
+				Some((name, value)) => (name.trim(), value.trim()),
+				None => (line, ""),
+			};
+			if name.is_empty() {
+				return Err(format!("Line {}: Expected a setting name", line_index + 1));
+			}
+			config.settings.insert(name.to_ascii_uppercase(), value.to_string());
+		}
+
+		Ok(config)
+	}
+
+	/// Get the value of the setting with the given `name` (ignoring case), or None if it isn't set.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.settings.get(&name.to_ascii_uppercase()).map(|value| value.as_str())
+	}
+
+	/// True if the flag with the given `name` is turned on, meaning it is set without a value or to
+	/// something other than `0`, `OFF` or `FALSE`.
+	pub fn flag(&self, name: &str) -> bool {
+		match self.get(name) {
+			Some(value) => !["0", "OFF", "FALSE"].iter().any(|off| value.eq_ignore_ascii_case(off)),
+			None => false,
+		}
+	}
+}
+
+/// Parse a Weave ZZT `.PAL` file, which has a red, green and blue byte for each of the 16 colours.
+/// Like the VGA hardware, each byte is from 0 to 63.
+pub fn parse_weave_palette(data: &[u8]) -> Result<Palette, String> {
+	if data.len() != 16 * 3 {
+		return Err(format!("A palette should be 48 bytes long, but it is {} bytes", data.len()));
+	}
+
+	let mut colours = [(0, 0, 0); 16];
+	for (colour, rgb) in colours.iter_mut().zip(data.chunks(3)) {
+		if let Some(bad_value) = rgb.iter().find(|&&value| value > 63) {
+			return Err(format!("Palette values must be from 0 to 63, but one is {}", bad_value));
+		}
+		// Scale the 6 bit values up to 8 bits, so 63 becomes 255.
+		let scale = |value: u8| (value << 2) | (value >> 4);
+		*colour = (scale(rgb[0]), scale(rgb[1]), scale(rgb[2]));
+	}
+
+	Ok(Palette {
+		colours,
+	})
+}
+
+/// A font from a Weave ZZT `.CHR` file. Each of the 256 characters is 8 pixels wide, with one byte
+/// per row where the highest bit is the leftmost pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeaveFont {
+	/// The height of each character in pixels. DOS ZZT uses 14.
+	pub char_height: usize,
+	/// The rows of each character, one character after another.
+	pub glyph_rows: Vec<u8>,
+}
+
+impl WeaveFont {
+	/// Parse a font file. The character height is worked out from the size of the file.
+	pub fn parse(data: &[u8]) -> Result<WeaveFont, String> {
+		let char_height = data.len() / 256;
+		if !data.len().is_multiple_of(256) || char_height == 0 || char_height > 32 {
+			return Err(format!("A font should have 256 characters of 1 to 32 bytes each, but it is {} bytes", data.len()));
+		}
+
+		Ok(WeaveFont {
+			char_height,
+			glyph_rows: data.to_vec(),
+		})
+	}
+
+	/// True if the pixel at `x`/`y` in the character with the given `char_code` is drawn in the
+	/// foreground colour.
+	pub fn pixel_is_set(&self, char_code: u8, x: usize, y: usize) -> bool {
+		let row = self.glyph_rows[char_code as usize * self.char_height + y];
+		row & (0x80 >> x) != 0
+	}
+}
+
+/// The Weave ZZT files that can be found next to a world. Each of them is optional.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct WeaveSidecars {
+	/// The settings from the `.CFG` file.
+	pub config: Option<WeaveConfig>,
+	/// The palette from the `.PAL` file, which replaces the standard EGA colours.
+	pub palette: Option<Palette>,
+	/// The font from the `.CHR` file, which front-ends should draw the screen with.
+	pub font: Option<WeaveFont>,
+}
+
+impl WeaveSidecars {
+	/// Read the Weave files next to the world at `world_path` (eg. `TOWN.CFG`, `TOWN.PAL` and
+	/// `TOWN.CHR` for `TOWN.ZZT`), ignoring case. Files that don't exist are left as None. Returns an
+	/// error if one of them exists but can't be read.
+	#[cfg(feature = "file-io")]
+	pub fn load(world_path: &Path) -> Result<WeaveSidecars, String> {
+		use crate::dos_files::find_related_file;
+
+		let read_related = |extension: &str| -> Result<Option<(std::path::PathBuf, Vec<u8>)>, String> {
+			match find_related_file(world_path, extension) {
+				Some(path) => {
+					let data = std::fs::read(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+					Ok(Some((path, data)))
+				}
+				None => Ok(None),
+			}
+		};
+
+		let mut sidecars = WeaveSidecars::default();
+		if let Some((path, data)) = read_related(WEAVE_CONFIG_FILE_EXTENSION)? {
+			let text = String::from_utf8_lossy(&data);
+			sidecars.config = Some(WeaveConfig::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))?);
+		}
+		if let Some((path, data)) = read_related(WEAVE_PALETTE_FILE_EXTENSION)? {
+			sidecars.palette = Some(parse_weave_palette(&data).map_err(|err| format!("{}: {}", path.display(), err))?);
+		}
+		if let Some((path, data)) = read_related(WEAVE_FONT_FILE_EXTENSION)? {
+			sidecars.font = Some(WeaveFont::parse(&data).map_err(|err| format!("{}: {}", path.display(), err))?);
+		}
+		Ok(sidecars)
+	}
+
+	/// Use the palette from the `.PAL` file in `display_options`, if there is one.
+	pub fn apply_display_options(&self, display_options: &mut DisplayOptions) {
+		if let Some(ref palette) = self.palette {
+			display_options.palette = palette.clone();
+		}
+	}
+}
+
+/// A world together with the Weave ZZT files and metadata file that came with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldBundle {
+	pub world: World,
+	pub weave: WeaveSidecars,
+	/// The world's metadata file, if it has one. See `WorldMetadata`.
+	pub metadata: Option<WorldMetadata>,
+}
+
+impl WorldBundle {
+	/// Read the world at `world_path` and the Weave and metadata files next to it (see
+	/// `WeaveSidecars::load` and `dos_files::load_world_metadata`).
+	#[cfg(feature = "file-io")]
+	pub fn load(world_path: &Path) -> Result<WorldBundle, String> {
+		let mut file = std::fs::File::open(world_path).map_err(|err| err.to_string())?;
+		let world = World::parse_with_options(&mut file, ParseOptions::lenient())?;
+		Ok(WorldBundle {
+			world,
+			weave: WeaveSidecars::load(world_path)?,
+			metadata: crate::dos_files::load_world_metadata(world_path)?,
+		})
+	}
+}
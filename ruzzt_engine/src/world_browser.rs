@@ -0,0 +1,271 @@
+use crate::ansi_art::render_board;
+use crate::board_message::*;
+use crate::console::*;
+use crate::event::*;
+
+use zzt_file_format::World;
+use zzt_file_format::metadata::WorldMetadata;
+use zzt_file_format::dosstring::DosString;
+
+/// The width of the title board preview in characters.
+pub const PREVIEW_WIDTH: usize = 30;
+/// The height of the title board preview in characters.
+pub const PREVIEW_HEIGHT: usize = 12;
+
+/// The x position on the screen of the left side of the list of worlds.
+const LIST_X: usize = 2;
+/// The number of characters of each world's file name that fit in the list.
+const LIST_WIDTH: usize = 17;
+/// The y position on the screen of the first row of the list of worlds.
+const LIST_Y: usize = 3;
+/// The number of rows in the list of worlds.
+const LIST_HEIGHT: usize = 20;
+/// The x position on the screen of the left side of the information about the selected world.
+const INFO_X: usize = 22;
+/// The number of characters that fit in each line of the information about the selected world.
+const INFO_WIDTH: usize = 36;
+
+/// Draw `text` at the given `x`/`y` position, cut off so it is no more than `max_width` characters
+/// long.
+fn draw_clipped_text(x: usize, y: usize, text: &[u8], max_width: usize, background: ConsoleColour, foreground: ConsoleColour, console_state: &mut ConsoleState) {
+	let text = &text[.. text.len().min(max_width)];
+	console_state.draw_text_at(x, y, text, background, foreground);
+}
+
+/// Information about a single world file that can be picked in the `WorldBrowserState`.
+#[derive(Clone)]
+pub struct WorldBrowserEntry {
+	/// The name of the file, without the extension.
+	pub file_name: DosString,
+	/// The name stored in the world's header.
+	pub world_name: DosString,
+	/// A short description of the world, or empty if there isn't one.
+	pub description: DosString,
+	/// The number of boards in the world, including the title board.
+	pub board_count: usize,
+	/// A copy of the world with only its title board, which the preview is drawn from when the
+	/// entry is highlighted. This is None for directories.
+	pub title_world: Option<World>,
+	/// True if the entry is a directory (or drive) to move into instead of a world (see
+	/// `file_browser`).
+	pub is_directory: bool,
+	/// The world's metadata file, if it has one. Its description is shown instead of
+	/// `description`.
+	pub metadata: Option<WorldMetadata>,
+}
+
+impl WorldBrowserEntry {
+	/// Make an entry for the given `world`, which was loaded from `file_name`. Only the title board
+	/// is kept, for the preview.
+	pub fn new(file_name: DosString, description: DosString, world: &World) -> WorldBrowserEntry {
+		let mut title_world_header = world.world_header.clone();
+		title_world_header.num_boards_except_title = 0;
+		title_world_header.player_board = 0;
+		let title_world = World {
+			world_header: title_world_header,
+			boards: world.boards.iter().take(1).cloned().collect(),
+			trailing_data: None,
+		};
+
+		WorldBrowserEntry {
+			file_name,
+			world_name: world.world_header.world_name.clone(),
+			description,
+			board_count: world.boards.len(),
+			title_world: Some(title_world),
+			is_directory: false,
+			metadata: None,
+		}
+	}
+
+	/// Render a shrunk down copy of how the title board looks, `PREVIEW_WIDTH` characters wide and
+	/// `PREVIEW_HEIGHT` high, in rows from the top-left. Returns None for directories and worlds
+	/// whose title board can't be loaded.
+	pub fn render_preview(&self) -> Option<Vec<ConsoleChar>> {
+		let rows = render_board(self.title_world.as_ref()?, 0).ok()?;
+
+		// Take every second character in each direction, so the preview is half the size of the
+		// board.
+		let mut preview = Vec::with_capacity(PREVIEW_WIDTH * PREVIEW_HEIGHT);
+		for y in 0 .. PREVIEW_HEIGHT {
+			for x in 0 .. PREVIEW_WIDTH {
+				preview.push(rows[y * 2][x * 2]);
+			}
+		}
+		Some(preview)
+	}
+
+	/// Make an entry for a directory called `file_name`, which has no world information or preview.
+	pub fn directory(file_name: DosString) -> WorldBrowserEntry {
+		WorldBrowserEntry {
+			file_name,
+			world_name: DosString::new(),
+			description: DosString::new(),
+			board_count: 0,
+			title_world: None,
+			is_directory: true,
+			metadata: None,
+		}
+	}
+}
+
+/// The state of the world browser, which lists world files along with some information about the
+/// highlighted world and a preview of its title board. It is drawn over the board, like a scroll.
+#[derive(Clone)]
+pub struct WorldBrowserState {
+	/// The title displayed at the top of the browser.
+	title: DosString,
+	/// The worlds that can be picked.
+	entries: Vec<WorldBrowserEntry>,
+	/// The index within `entries` of the highlighted world.
+	current_index: usize,
+	/// The preview of the highlighted world (see `WorldBrowserEntry::render_preview`). It is only
+	/// rendered when the highlight moves, so listing a lot of worlds stays quick.
+	preview: Option<Vec<ConsoleChar>>,
+}
+
+impl WorldBrowserState {
+	/// Make a new world browser with the given `title`, listing the given `entries`.
+	pub fn new(title: DosString, entries: Vec<WorldBrowserEntry>) -> WorldBrowserState {
+		let mut world_browser_state = WorldBrowserState {
+			title,
+			entries,
+			current_index: 0,
+			preview: None,
+		};
+		world_browser_state.update_preview();
+		world_browser_state
+	}
+
+	/// Render the preview of the highlighted world.
+	fn update_preview(&mut self) {
+		self.preview = self.current_entry().and_then(|entry| entry.render_preview());
+	}
+
+	/// Get the highlighted world, if there are any worlds in the list.
+	pub fn current_entry(&self) -> Option<&WorldBrowserEntry> {
+		self.entries.get(self.current_index)
+	}
+
+	/// Execute a single simulation step on the world browser, with the given input `event`.
+	pub fn step(&mut self, event: Event) -> Vec<BoardMessage> {
+		let mut board_messages = vec![];
+		let last_index = self.entries.len().saturating_sub(1);
+		let old_index = self.current_index;
+		match event {
+			Event::Escape => {
+				board_messages.push(BoardMessage::CloseWorldBrowser);
+			}
+			Event::Enter => {
+				board_messages.push(BoardMessage::CloseWorldBrowser);
+				if !self.entries.is_empty() {
+					board_messages.push(BoardMessage::WorldBrowserEntrySelected{entry_index: self.current_index});
+				}
+			}
+			Event::Up => {
+				self.current_index = self.current_index.saturating_sub(1);
+			}
+			Event::Down => {
+				self.current_index = (self.current_index + 1).min(last_index);
+			}
+			Event::PageUp => {
+				self.current_index = self.current_index.saturating_sub(LIST_HEIGHT);
+			}
+			Event::PageDown => {
+				self.current_index = (self.current_index + LIST_HEIGHT).min(last_index);
+			}
+			_ => {}
+		}
+		if self.current_index != old_index {
+			self.update_preview();
+		}
+		board_messages
+	}
+
+	/// Renders the world browser over the board area of the console.
+	pub fn draw_world_browser(&self, console_state: &mut ConsoleState) {
+		let bg = ConsoleColour::Black;
+
+		// Clear the board area and draw a double-lined frame around it.
+		for y in 0 .. 25 {
+			for x in 0 .. 60 {
+				let char_code = match (x, y) {
+					(0, 0) => 0xc9,
+					(59, 0) => 0xbb,
+					(0, 24) => 0xc8,
+					(59, 24) => 0xbc,
+					(0, _) | (59, _) => 0xba,
+					(_, 0) | (_, 24) => 0xcd,
+					_ => 0,
+				};
+				*console_state.get_char_mut(x, y) = ConsoleChar::new(char_code, bg, ConsoleColour::White);
+			}
+		}
+
+		let title_x = 30 - self.title.len().min(56) / 2;
+		draw_clipped_text(title_x, 1, &self.title, 56, bg, ConsoleColour::Yellow, console_state);
+
+		// Scroll the list so the highlighted world is always in view.
+		let first_index = if self.current_index >= LIST_HEIGHT {
+			self.current_index + 1 - LIST_HEIGHT
+		} else {
+			0
+		};
+
+		for (row, (index, entry)) in self.entries.iter().enumerate().skip(first_index).take(LIST_HEIGHT).enumerate() {
+			let (row_bg, row_fg) = if index == self.current_index {
+				(ConsoleColour::Cyan, ConsoleColour::Black)
+			} else {
+				(bg, ConsoleColour::LightCyan)
+			};
+			for x in LIST_X - 1 .. LIST_X + LIST_WIDTH + 1 {
+				*console_state.get_char_mut(x, LIST_Y + row) = ConsoleChar::new(0, row_bg, row_fg);
+			}
+			draw_clipped_text(LIST_X, LIST_Y + row, &entry.file_name, LIST_WIDTH, row_bg, row_fg, console_state);
+		}
+
+		if let Some(entry) = self.current_entry().filter(|entry| entry.is_directory) {
+			draw_clipped_text(INFO_X, 3, &entry.file_name, INFO_WIDTH, bg, ConsoleColour::White, console_state);
+			draw_clipped_text(INFO_X, 4, b"Directory", INFO_WIDTH, bg, ConsoleColour::Yellow, console_state);
+		} else if let Some(entry) = self.current_entry() {
+			draw_clipped_text(INFO_X, 3, &entry.world_name, INFO_WIDTH, bg, ConsoleColour::White, console_state);
+			let metadata = entry.metadata.clone().unwrap_or_default();
+			match metadata.description {
+				Some(ref description) => draw_clipped_text(INFO_X, 4, &DosString::from_str(description), INFO_WIDTH, bg, ConsoleColour::Yellow, console_state),
+				None => draw_clipped_text(INFO_X, 4, &entry.description, INFO_WIDTH, bg, ConsoleColour::Yellow, console_state),
+			}
+			let board_count_text = format!("Boards: {}", entry.board_count);
+			draw_clipped_text(INFO_X, 5, board_count_text.as_bytes(), INFO_WIDTH, bg, ConsoleColour::LightGray, console_state);
+			if let Some(credit_line) = metadata.credit_line() {
+				draw_clipped_text(INFO_X, 6, &DosString::from_str(&credit_line), INFO_WIDTH, bg, ConsoleColour::LightCyan, console_state);
+			}
+			if !metadata.content_warnings.is_empty() {
+				let warnings_text = format!("Warning: {}", metadata.content_warnings.join(", "));
+				draw_clipped_text(INFO_X, 7, &DosString::from_str(&warnings_text), INFO_WIDTH, bg, ConsoleColour::LightRed, console_state);
+			}
+
+			if let Some(ref preview) = self.preview {
+				let preview_x = INFO_X + 2;
+				let preview_y = 8;
+				for y in 0 .. PREVIEW_HEIGHT {
+					for x in 0 .. PREVIEW_WIDTH {
+						*console_state.get_char_mut(preview_x + x, preview_y + y) = preview[y * PREVIEW_WIDTH + x];
+					}
+				}
+			}
+
+			let recommended_settings: Vec<String> = metadata.recommended_palette.iter().map(|palette| format!("Palette: {}", palette))
+				.chain(metadata.recommended_font.iter().map(|font| format!("Font: {}", font)))
+				.chain(metadata.machine_speed.iter().map(|machine_speed| format!("Machine: {}", machine_speed)))
+				.collect();
+			if !recommended_settings.is_empty() {
+				let recommended_text = recommended_settings.join("  ");
+				draw_clipped_text(INFO_X, 20, &DosString::from_str(&recommended_text), INFO_WIDTH, bg, ConsoleColour::LightGray, console_state);
+			}
+		} else {
+			draw_clipped_text(INFO_X, 3, b"No worlds found", INFO_WIDTH, bg, ConsoleColour::White, console_state);
+		}
+
+		draw_clipped_text(INFO_X, 22, b"Enter: select   Esc: cancel", INFO_WIDTH, bg, ConsoleColour::LightGray, console_state);
+	}
+}
@@ -0,0 +1,115 @@
+use crate::board_simulator::*;
+
+use zzt_file_format::{Board, ElementType};
+use zzt_file_format::dosstring::DosString;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What is known about a board the player has visited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisitedBoard {
+	/// The board index reached by walking off the north, south, west and east edges of the board,
+	/// in that order. 0 means there is no exit in that direction.
+	pub exits: [u8; 4],
+	/// The destination board indices of every passage on the board.
+	pub passage_destinations: BTreeSet<u8>,
+}
+
+impl VisitedBoard {
+	/// Get the exits and passages of the board currently loaded in `sim`.
+	pub fn from_board_simulator(sim: &BoardSimulator) -> VisitedBoard {
+		let meta_data = &sim.board_meta_data;
+		let mut passage_destinations = BTreeSet::new();
+		for status in &sim.status_elements {
+			if let Some(tile) = sim.get_status_tile(status) {
+				if tile.element_id == ElementType::Passage as u8 {
+					passage_destinations.insert(status.param3);
+				}
+			}
+		}
+
+		VisitedBoard {
+			exits: [meta_data.exit_north, meta_data.exit_south, meta_data.exit_west, meta_data.exit_east],
+			passage_destinations,
+		}
+	}
+}
+
+/// Keeps track of the boards the player has visited and how they link to each other, so a map of
+/// the world can be shown in a scroll.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WorldMap {
+	/// The boards that have been visited, keyed by board index.
+	pub visited_boards: BTreeMap<u8, VisitedBoard>,
+}
+
+impl WorldMap {
+	/// Make a map where no boards have been visited.
+	pub fn new() -> WorldMap {
+		WorldMap {
+			visited_boards: BTreeMap::new(),
+		}
+	}
+
+	/// Record that the player is on the board currently loaded in `sim`. This replaces what was
+	/// known about the board before, because its passages may have changed.
+	pub fn visit_board(&mut self, sim: &BoardSimulator) {
+		let board_index = sim.world_header.player_board as u8;
+		self.visited_boards.insert(board_index, VisitedBoard::from_board_simulator(sim));
+	}
+
+	/// True if the board with the given `board_index` has been visited.
+	pub fn is_visited(&self, board_index: u8) -> bool {
+		self.visited_boards.contains_key(&board_index)
+	}
+
+	/// Get the name of the board with the given `board_index` as it is shown on the map. Boards that
+	/// haven't been visited are shown as `???`, so the map doesn't give away where exits lead.
+	fn board_label(&self, board_index: u8, boards: &[Board]) -> DosString {
+		match boards.get(board_index as usize) {
+			Some(board) if self.is_visited(board_index) => board.meta_data.board_name.clone(),
+			_ => DosString::from_slice(b"???"),
+		}
+	}
+
+	/// Get the lines of a scroll showing every visited board, with the boards each one leads to
+	/// through its edges and passages. `boards` are the boards of the world, used for their names,
+	/// and `current_board_index` is marked as the board the player is on.
+	pub fn scroll_lines(&self, boards: &[Board], current_board_index: u8) -> Vec<DosString> {
+		let mut lines = vec![];
+		let direction_names: [&[u8]; 4] = [b"  North: ", b"  South: ", b"  West:  ", b"  East:  "];
+
+		for (board_index, visited_board) in &self.visited_boards {
+			if !lines.is_empty() {
+				lines.push(DosString::new());
+			}
+
+			let mut heading = DosString::from_slice(b"$");
+			heading += &self.board_label(*board_index, boards).data;
+			if *board_index == current_board_index {
+				heading += b" (You are here)";
+			}
+			lines.push(heading);
+
+			for (direction_name, exit) in direction_names.iter().zip(visited_board.exits.iter()) {
+				if *exit != 0 {
+					let mut line = DosString::from_slice(direction_name);
+					line += &self.board_label(*exit, boards).data;
+					lines.push(line);
+				}
+			}
+
+			for destination in &visited_board.passage_destinations {
+				let mut line = DosString::from_slice(b"  Passage: ");
+				line += &self.board_label(*destination, boards).data;
+				lines.push(line);
+			}
+		}
+
+		if lines.is_empty() {
+			lines.push(DosString::from_slice(b"No boards have been visited yet."));
+		}
+
+		lines
+	}
+}
@@ -0,0 +1,170 @@
+use crate::board_links::{get_exit, EXIT_DIRECTIONS};
+
+use zzt_file_format::{Board, CodeSource, World};
+
+use std::ops::Range;
+
+/// Code that `trim_world` removed from a status element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemovedCode {
+	/// The index of the board the status element is on, after the unreachable boards were removed.
+	pub board_index: usize,
+	pub status_index: usize,
+	/// The number of bytes of code that were removed.
+	pub byte_count: usize,
+}
+
+/// What `trim_world` removed from a world.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrimReport {
+	/// The indices of the boards that were removed, from before any were removed.
+	pub removed_boards: Vec<usize>,
+	/// The dead code that was removed from each status element that had some.
+	pub removed_code: Vec<RemovedCode>,
+}
+
+impl TrimReport {
+	/// Describe everything that was removed, one line for each board and each status element's
+	/// code.
+	pub fn description_lines(&self) -> Vec<String> {
+		let board_lines = self.removed_boards.iter().map(|board_index| format!("Removed board {}, which can't be reached", board_index));
+		let code_lines = self.removed_code.iter().map(|removed_code| format!("Board {}: Removed {} bytes of dead code from status element {}",
+			removed_code.board_index, removed_code.byte_count, removed_code.status_index));
+		board_lines.chain(code_lines).collect()
+	}
+}
+
+/// Find which boards of `world` can be reached from the title board or the board the player
+/// starts on, by following board exits and passages. OOP can't send the player to another board
+/// (passages made with `#put` or `#become` always lead to the title board), so nothing else leads
+/// anywhere. Returns true for each board that can be reached.
+pub fn reachable_boards(world: &World) -> Vec<bool> {
+	let world_type = world.world_header.world_type;
+	let mut reachable = vec![false; world.boards.len()];
+	let mut boards_to_visit = vec![0, world.world_header.player_board.max(0) as usize];
+	while let Some(board_index) = boards_to_visit.pop() {
+		match reachable.get_mut(board_index) {
+			Some(is_reachable) if !*is_reachable => *is_reachable = true,
+			_ => continue,
+		}
+
+		let board = &world.boards[board_index];
+		boards_to_visit.extend(EXIT_DIRECTIONS.iter().map(|&direction| get_exit(&board.meta_data, direction) as usize));
+		boards_to_visit.extend((0 .. board.status_elements.len())
+			.filter(|&status_index| board.status_is_passage(status_index, world_type))
+			.map(|status_index| board.status_elements[status_index].param3 as usize));
+	}
+	reachable
+}
+
+/// Find the parts of `code` that can never run: the lines after a line that is only `#end`, up to
+/// the next label. Zapped labels (starting with `'`) count as labels, because `#restore` can bring
+/// them back. Returns the byte ranges of the dead code, including the line endings.
+pub fn dead_code_ranges(code: &[u8]) -> Vec<Range<usize>> {
+	let mut dead_ranges = vec![];
+	let mut dead_start = None;
+	let mut line_start = 0;
+	for line in code.split(|&c| c == b'\r') {
+		let next_line_start = (line_start + line.len() + 1).min(code.len());
+		if line.first() == Some(&b':') || line.first() == Some(&b'\'') {
+			if let Some(start) = dead_start.take() {
+				if start < line_start {
+					dead_ranges.push(start .. line_start);
+				}
+			}
+		} else if dead_start.is_none() && line.trim_ascii_end().eq_ignore_ascii_case(b"#end") {
+			dead_start = Some(next_line_start);
+		}
+		line_start = next_line_start;
+	}
+	if let Some(start) = dead_start {
+		if start < code.len() {
+			dead_ranges.push(start .. code.len());
+		}
+	}
+	dead_ranges
+}
+
+/// Get the index of the status element that owns the code of the status element at
+/// `status_index`, following any `#bind`s.
+fn code_owner(board: &Board, status_index: usize) -> Option<usize> {
+	let mut current_index = status_index;
+	for _ in 0 ..= board.status_elements.len() {
+		match board.status_elements.get(current_index)?.code_source {
+			CodeSource::Owned(_) => return Some(current_index),
+			CodeSource::Bound(bound_index) => current_index = bound_index,
+		}
+	}
+	None
+}
+
+/// Remove the dead code (see `dead_code_ranges`) of every status element on `board`, keeping any
+/// that a status element is part way through running. Returns the status index and number of
+/// bytes removed for each status element that had dead code.
+fn remove_dead_code(board: &mut Board) -> Vec<(usize, usize)> {
+	let mut removed = vec![];
+	for owner_index in 0 .. board.status_elements.len() {
+		let code = match board.status_elements[owner_index].code_source {
+			CodeSource::Owned(ref code) => code.clone(),
+			CodeSource::Bound(_) => continue,
+		};
+		let sharing_indices: Vec<usize> = (0 .. board.status_elements.len())
+			.filter(|&status_index| code_owner(board, status_index) == Some(owner_index))
+			.collect();
+		let dead_ranges: Vec<Range<usize>> = dead_code_ranges(&code.data).into_iter()
+			.filter(|range| sharing_indices.iter().all(|&status_index| {
+				let instruction = board.status_elements[status_index].code_current_instruction;
+				instruction < 0 || !range.contains(&(instruction as usize))
+			}))
+			.collect();
+		if dead_ranges.is_empty() {
+			continue;
+		}
+
+		for &status_index in &sharing_indices {
+			let status = &mut board.status_elements[status_index];
+			if status.code_current_instruction >= 0 {
+				let instruction = status.code_current_instruction as usize;
+				let removed_before: usize = dead_ranges.iter().filter(|range| range.end <= instruction).map(|range| range.len()).sum();
+				status.code_current_instruction -= removed_before as i16;
+			}
+		}
+
+		let mut trimmed_code = code.clone();
+		trimmed_code.data = code.data.iter().enumerate()
+			.filter(|(byte_index, _)| !dead_ranges.iter().any(|range| range.contains(byte_index)))
+			.map(|(_, &c)| c)
+			.collect();
+		removed.push((owner_index, code.len() - trimmed_code.len()));
+		board.status_elements[owner_index].code_source = CodeSource::Owned(trimmed_code);
+	}
+	removed
+}
+
+/// Remove the boards of `world` that can't be reached (see `reachable_boards`) and the code of
+/// status elements that can never run (see `dead_code_ranges`), and report what was removed. The
+/// links between the remaining boards still lead to the same boards.
+pub fn trim_world(world: &mut World) -> Result<TrimReport, String> {
+	let mut report = TrimReport::default();
+
+	let reachable = reachable_boards(world);
+	for board_index in (0 .. world.boards.len()).rev() {
+		if !reachable[board_index] {
+			world.remove_board(board_index)?;
+			report.removed_boards.push(board_index);
+		}
+	}
+	report.removed_boards.reverse();
+
+	for (board_index, board) in world.boards.iter_mut().enumerate() {
+		for (status_index, byte_count) in remove_dead_code(board) {
+			report.removed_code.push(RemovedCode {
+				board_index,
+				status_index,
+				byte_count,
+			});
+		}
+	}
+
+	Ok(report)
+}
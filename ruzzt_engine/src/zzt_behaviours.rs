@@ -2,13 +2,56 @@ use crate::board_simulator::*;
 
 use zzt_file_format::*;
 
+use num::FromPrimitive;
+
+use std::rc::Rc;
+
 mod centipede;
 mod creatures;
 mod items;
 mod misc;
 mod monster_interactions;
+mod super_zzt;
 mod terrains;
 
+/// Load the behaviours for the type of world the `sim`'s world header says it is.
+pub fn load_behaviours(sim: &mut BoardSimulator) {
+	match sim.world_header.world_type {
+		WorldType::Zzt => load_zzt_behaviours(sim),
+		WorldType::SuperZzt => load_super_zzt_behaviours(sim),
+	}
+}
+
+/// Load the behaviours for Super ZZT's element IDs (see `SuperZztElementType`). The elements that
+/// are shared with ZZT behave the same as they do in ZZT.
+pub fn load_super_zzt_behaviours(sim: &mut BoardSimulator) {
+	// Load the ZZT behaviours, and then move each one to the Super ZZT ID of the same element.
+	load_zzt_behaviours(sim);
+	let zzt_behaviours = std::mem::take(&mut sim.behaviours);
+	for element_id in 0 ..= 255 {
+		let element_type_opt = SuperZztElementType::from_u8(element_id).and_then(|ty| ty.to_element_type());
+		if let Some(element_type) = element_type_opt {
+			if let Some(Some(behaviour)) = zzt_behaviours.get(element_type as usize) {
+				sim.set_behaviour_for_id(element_id, behaviour.clone());
+			}
+		}
+	}
+
+	sim.set_behaviour_for_id(SuperZztElementType::Lava as u8, Rc::new(super_zzt::LavaBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::Floor as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::WaterN as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::WaterS as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::WaterW as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::WaterE as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::Web as u8, Rc::new(super_zzt::FloorBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::Stone as u8, Rc::new(super_zzt::StoneBehaviour));
+	
+	sim.set_behaviour_for_id(SuperZztElementType::Roton as u8, Rc::new(super_zzt::RotonBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::DragonPup as u8, Rc::new(super_zzt::DragonPupBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::Pairer as u8, Rc::new(super_zzt::PairerBehaviour));
+	sim.set_behaviour_for_id(SuperZztElementType::Spider as u8, Rc::new(super_zzt::SpiderBehaviour));
+}
+
 pub fn load_zzt_behaviours(sim: &mut BoardSimulator) {
 	sim.set_behaviour(ElementType::Player, Box::new(items::PlayerBehaviour));
 	sim.set_behaviour(ElementType::Ammo, Box::new(items::AmmoBehaviour));
@@ -3,12 +3,20 @@ use crate::board_simulator::*;
 use crate::event::*;
 use crate::direction::*;
 
-use rand::Rng;
-
 use zzt_file_format::*;
 
 use crate::zzt_behaviours::monster_interactions::*;
 
+/// Get the index of the segment following `status` in a centipede, if it has one. Corrupt worlds
+/// can have followers that don't exist, which are treated as the end of the centipede.
+fn follower_index(status: &StatusElement, sim: &BoardSimulator) -> Option<usize> {
+	if status.follower >= 0 && (status.follower as usize) < sim.status_elements.len() {
+		Some(status.follower as usize)
+	} else {
+		None
+	}
+}
+
 struct HeadStepContext<'l> {
 	new_step_x: i16,
 	new_step_y: i16,
@@ -79,7 +87,7 @@ impl<'l> HeadStepContext<'l> {
 					follower: current_status.leader,
 				});
 
-				if current_status.follower >= 0 {
+				if let Some(follower_index) = follower_index(current_status, self.sim) {
 					self.actions.push(Action::SetStep{
 						status_index: current_status_index,
 						step_x: -current_status.step_x,
@@ -88,7 +96,7 @@ impl<'l> HeadStepContext<'l> {
 
 					before_end_of_tail_x = current_status.location_x as i16;
 					before_end_of_tail_y = current_status.location_y as i16;
-					current_status_index = current_status.follower as usize;
+					current_status_index = follower_index;
 				} else {
 					// This will be the new head.
 					self.actions.push(Action::SetTileElementIdAndColour {
@@ -130,14 +138,12 @@ impl<'l> HeadStepContext<'l> {
 	// Randomly change direction sometimes according to AI settings.
 	fn do_intelligence_and_deviance(&mut self) {
 		// NOTE: This logic was derived from the ZZT.EXE disassembly.
-		let mut rng = rand::thread_rng();
-
 		let (player_x, player_y) = self.sim.get_player_location();
 
 		let mut changed_direction = false;
 		// Check aligned on the X axis.
 		if self.status.location_x as i16 == player_x {
-			let random_int: u8 = rng.gen_range(0, 10);
+			let random_int: u8 = self.sim.random_range(0, 10);
 			if self.status.param1 > random_int {
 				self.new_step_x = 0;
 				self.new_step_y = (player_y - self.status.location_y as i16).signum();
@@ -148,7 +154,7 @@ impl<'l> HeadStepContext<'l> {
 		if !changed_direction {
 			// Check aligned on the Y axis.
 			if self.status.location_y as i16 == player_y {
-				let random_int: u8 = rng.gen_range(0, 10);
+				let random_int: u8 = self.sim.random_range(0, 10);
 				if self.status.param1 > random_int {
 					self.new_step_x = (player_x - self.status.location_x as i16).signum();
 					self.new_step_y = 0;
@@ -159,7 +165,7 @@ impl<'l> HeadStepContext<'l> {
 
 		if !changed_direction {
 			// Check deviance.
-			let random_int: u8 = rng.gen_range(0, 10) * 4;
+			let random_int: u8 = self.sim.random_range(0, 10) * 4;
 			if self.status.param2 > random_int {
 				let (rand_step_x, rand_step_y) = self.sim.get_random_step();
 				self.new_step_x = rand_step_x;
@@ -172,8 +178,6 @@ impl<'l> HeadStepContext<'l> {
 		// When there is a segment by itself, it sets the leader to -2 for some reason, then becomes
 		// a head.
 
-		let mut rng = rand::thread_rng();
-
 		if self.new_step_x == 0 && self.new_step_y == 0 {
 			// If a head has (0, 0) step, it sets the step to the direction of a random non-blocked path.
 			// If there are no non-blocked paths: if there are no followers, just sit there. If there
@@ -193,7 +197,7 @@ impl<'l> HeadStepContext<'l> {
 				// If there are no free directions, do nothing.
 			} else {
 				// This does not take intelligence into account.
-				let random_int: usize = rng.gen_range(0, free_dirs.len());
+				let random_int: usize = self.sim.random_range(0, free_dirs.len());
 
 				let new_step = free_dirs[random_int].to_offset();
 				self.new_step_x = new_step.0;
@@ -223,8 +227,7 @@ impl<'l> HeadStepContext<'l> {
 						self.new_step_y = cw_step_y;
 					}
 					(false, false) => {
-						let mut rng = rand::thread_rng();
-						let random_bool: bool = rng.gen();
+						let random_bool = self.sim.random_bool();
 						if random_bool {
 							self.new_step_x = cw_step_x;
 							self.new_step_y = cw_step_y;
@@ -245,8 +248,8 @@ impl<'l> HeadStepContext<'l> {
 			if self.sim.has_player_at_location(dest_x, dest_y) {
 				head_died = true;
 				add_monster_touch_player_actions(self.status.location_x as i16, self.status.location_y as i16, &mut self.actions, self.sim);
-				if self.status.follower >= 0 {
-					let follower_status = &self.sim.status_elements[self.status.follower as usize];
+				if let Some(follower_index) = follower_index(self.status, self.sim) {
+					let follower_status = &self.sim.status_elements[follower_index];
 					self.actions.push(Action::SetTileElementIdAndColour {
 						x: follower_status.location_x as i16,
 						y: follower_status.location_y as i16,
@@ -265,10 +268,10 @@ impl<'l> HeadStepContext<'l> {
 				let mut before_end_of_tail_y = self.status.location_y as i16 + self.new_step_y;
 				loop {
 					let current_status = &self.sim.status_elements[end_of_tail_index];
-					if current_status.follower >= 0 {
+					if let Some(follower_index) = follower_index(current_status, self.sim) {
 						before_end_of_tail_x = current_status.location_x as i16;
 						before_end_of_tail_y = current_status.location_y as i16;
-						end_of_tail_index = current_status.follower as usize;
+						end_of_tail_index = follower_index;
 					} else {
 						break;
 					}
@@ -444,17 +447,17 @@ impl ActionContinuation for CentipedeMovementContinuation {
 			is_player: false,
 		});
 
-		let mut current_tail_index = status.follower;
+		let mut current_tail_index_opt = follower_index(status, sim);
 		let mut prev_x = status.location_x;
 		let mut prev_y = status.location_y;
-		while current_tail_index >= 0 {
-			let tail_status = &sim.status_elements[current_tail_index as usize];
+		while let Some(current_tail_index) = current_tail_index_opt {
+			let tail_status = &sim.status_elements[current_tail_index];
 
 			let offset_x = prev_x as i16 - tail_status.location_x as i16;
 			let offset_y = prev_y as i16 - tail_status.location_y as i16;
 
 			actions.push(Action::SetStep {
-				status_index: current_tail_index as usize,
+				status_index: current_tail_index,
 				step_x: offset_x,
 				step_y: offset_y,
 			});
@@ -471,7 +474,7 @@ impl ActionContinuation for CentipedeMovementContinuation {
 			});
 			prev_x = tail_status.location_x;
 			prev_y = tail_status.location_y;
-			current_tail_index = tail_status.follower;
+			current_tail_index_opt = follower_index(tail_status, sim);
 		}
 
 		ActionContinuationResult {
@@ -3,8 +3,6 @@ use crate::board_simulator::*;
 use crate::event::*;
 use crate::oop_parser::*;
 
-use rand::Rng;
-
 use zzt_file_format::*;
 use zzt_file_format::dosstring::DosString;
 
@@ -29,12 +27,13 @@ pub struct BearBehaviour;
 impl Behaviour for BearBehaviour {
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
+
 		let (player_x, player_y) = sim.get_player_location();
 
 		let diff_x = status.location_x as i16 - player_x;
 		let diff_y = status.location_y as i16 - player_y;
 
-		let allowed_diff = (8 - status.param1) as i16;
+		let allowed_diff = 8 - status.param1 as i16;
 		if diff_x.abs() <= allowed_diff || diff_y.abs() <= allowed_diff {
 			let off_x;
 			let off_y;
@@ -127,10 +126,9 @@ impl Behaviour for RuffianBehaviour {
 
 		let mut do_move_tile = true;
 
-		let mut rng = rand::thread_rng();
 		if step_x == 0 && step_y == 0 {
-			if status.param2 + 8 <= rng.gen_range(0, 17) {
-				if status.param1 >= rng.gen_range(0, 9) {
+			if status.param2 + 8 <= sim.random_range(0, 17) {
+				if status.param1 >= sim.random_range(0, 9) {
 					let (seek_x, seek_y) = sim.seek_direction(status.location_x as i16, status.location_y as i16).to_offset();
 					step_x = seek_x;
 					step_y = seek_y;
@@ -144,7 +142,7 @@ impl Behaviour for RuffianBehaviour {
 			do_move_tile = false;
 		} else {
 			if status.location_x as i16 == player_x || status.location_y as i16 == player_y {
-				if status.param1 >= rng.gen_range(0, 9) {
+				if status.param1 >= sim.random_range(0, 9) {
 					let (seek_x, seek_y) = sim.seek_direction(status.location_x as i16, status.location_y as i16).to_offset();
 					step_x = seek_x;
 					step_y = seek_y;
@@ -182,7 +180,7 @@ impl Behaviour for RuffianBehaviour {
 						is_player: false,
 					});
 
-					if status.param2 + 8 <= rng.gen_range(0, 17) {
+					if status.param2 + 8 <= sim.random_range(0, 17) {
 						actions.push(Action::SetStep {
 							status_index,
 							step_x: 0,
@@ -219,6 +217,10 @@ impl Behaviour for RuffianBehaviour {
 pub struct ObjectBehaviour;
 
 impl Behaviour for ObjectBehaviour {
+	fn status_char_code(&self, status: &StatusElement, _global_cycle: usize) -> Option<u8> {
+		Some(status.param1)
+	}
+
 	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
 		let mut actions = vec![];
 		if is_player {
@@ -271,20 +273,56 @@ impl Behaviour for ObjectBehaviour {
 		}
 	}
 
-	fn step(&self, _event: Event, status: &StatusElement, status_index: usize, sim: &BoardSimulator) -> ActionResult {
-		let mut actions = vec![];
+	fn step(&self, _event: Event, _status: &StatusElement, _status_index: usize, _sim: &BoardSimulator) -> ActionResult {
+		ActionResult {
+			actions: vec![],
+			continuation: Some(Box::new(ObjectTickState::new())),
+		}
+	}
 
-		let parser = OopParser::new(&sim.get_status_code(status), status.code_current_instruction);
+	fn locked(&self, status: &StatusElement) -> bool {
+		status.param2 > 0
+	}
+}
+
+/// How far through its step an object is. See `ObjectTickState`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObjectTickPhase {
+	RunningOop,
+	Walking,
+}
+
+/// The continuation for an object's step. Like DOS ZZT, the object's OOP runs first, then if it is
+/// walking it moves one step, or if it is blocked it is sent to its `:thud` label, which runs in its
+/// next step.
+#[derive(Debug)]
+struct ObjectTickState {
+	oop_state: OopExecutionState,
+	phase: ObjectTickPhase,
+	oop_finalised: bool,
+}
+
+impl ObjectTickState {
+	fn new() -> ObjectTickState {
+		ObjectTickState {
+			oop_state: OopExecutionState::new(false, None),
+			phase: ObjectTickPhase::RunningOop,
+			oop_finalised: false,
+		}
+	}
+
+	/// Get the actions that make the object at `status_index` walk in the direction of its step, or
+	/// jump to its `:thud` label if it is blocked.
+	fn walk_actions(status_index: usize, status: &StatusElement, sim: &BoardSimulator) -> Vec<Action> {
+		let mut actions = vec![];
 
-		// Make object walk.
 		if status.step_x != 0 || status.step_y != 0 {
 			let dest_behaviour = sim.behaviour_for_pos(status.location_x as i16 + status.step_x, status.location_y as i16 + status.step_y);
 			if dest_behaviour.blocked(false) == BlockedStatus::Blocked {
-				if !self.locked(status) {
-					if let Some(thud_label_pos) = parser.find_label(&DosString::from_slice(b"thud")) {
-						println!("Finding thud: {}", thud_label_pos);
-						actions.insert(0, Action::SetCodeCurrentInstruction{status_index, code_current_instruction: thud_label_pos});
-					}
+				// DOS ZZT sends THUD to the object itself, which ignores whether it is locked.
+				let parser = OopParser::new(&sim.get_status_code(status), status.code_current_instruction);
+				if let Some(thud_label_pos) = parser.find_label(&DosString::from_slice(b"thud")) {
+					actions.push(Action::SetCodeCurrentInstruction{status_index, code_current_instruction: thud_label_pos});
 				}
 			} else {
 				actions.push(Action::MoveTile {
@@ -301,16 +339,40 @@ impl Behaviour for ObjectBehaviour {
 			}
 		}
 
-		let continuation: Option<Box<dyn ActionContinuation>> = Some(Box::new(OopExecutionState::new(false, None)));
+		actions
+	}
+}
 
-		ActionResult {
-			actions,
-			continuation,
+impl ActionContinuation for ObjectTickState {
+	fn next_step(&mut self, apply_action_report: ApplyActionResultReport, status_index: usize, status: &StatusElement, sim: &BoardSimulator) -> ActionContinuationResult {
+		match self.phase {
+			ObjectTickPhase::RunningOop => {
+				let mut result = self.oop_state.next_step(apply_action_report, status_index, status, sim);
+				if result.finished {
+					// The OOP is finished, so show any text it produced before walking, like DOS ZZT.
+					result.actions.extend(self.oop_state.finalise(Some(status), sim));
+					self.oop_finalised = true;
+					// The walk happens in the next call, after the OOP's last actions are applied.
+					self.phase = ObjectTickPhase::Walking;
+					result.finished = false;
+				}
+				result
+			}
+			ObjectTickPhase::Walking => {
+				ActionContinuationResult {
+					actions: ObjectTickState::walk_actions(status_index, status, sim),
+					finished: true,
+				}
+			}
 		}
 	}
 
-	fn locked(&self, status: &StatusElement) -> bool {
-		status.param2 > 0
+	fn finalise(&mut self, status_opt: Option<&StatusElement>, sim: &BoardSimulator) -> Vec<Action> {
+		if self.oop_finalised {
+			vec![]
+		} else {
+			self.oop_state.finalise(status_opt, sim)
+		}
 	}
 }
 
@@ -348,6 +410,8 @@ impl Behaviour for SlimeBehaviour {
 					let adj_x = slime_x + off_x;
 					let adj_y = slime_y + off_y;
 					if sim.behaviour_for_pos(adj_x, adj_y).blocked(false) == BlockedStatus::NotBlocked {
+						// The new slime is on top of whatever it spread onto (eg. a fake wall).
+						let under_tile = sim.get_tile(adj_x, adj_y).unwrap_or(BoardTile { element_id: ElementType::Empty as u8, colour: 0 });
 						actions.push(Action::SetTile {
 							x: adj_x,
 							y: adj_y,
@@ -356,6 +420,8 @@ impl Behaviour for SlimeBehaviour {
 								location_x: adj_x as u8,
 								location_y: adj_y as u8,
 								param1: 0,
+								under_element_id: under_tile.element_id,
+								under_colour: under_tile.colour,
 								.. status.clone()
 							}),
 						});
@@ -384,14 +450,13 @@ pub struct SharkBehaviour;
 impl Behaviour for SharkBehaviour {
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
-		let mut rng = rand::thread_rng();
 
 		let off_x;
 		let off_y;
 
 		// If param1 (intelligence) is 0, then it should always randomise, and when 8 it
 		// should ALMOST always seek.
-		let should_randomise: bool = rng.gen_range(0, 9) >= status.param1;
+		let should_randomise: bool = sim.random_range(0, 9) >= status.param1;
 		if should_randomise {
 			let (rand_step_x, rand_step_y) = sim.get_random_step();
 			off_x = rand_step_x;
@@ -445,6 +510,10 @@ impl Behaviour for SharkBehaviour {
 pub struct SpinningGunBehaviour;
 
 impl Behaviour for SpinningGunBehaviour {
+	fn status_char_code(&self, status: &StatusElement, global_cycle: usize) -> Option<u8> {
+		Some([0x18, 0x1a, 0x19, 0x1b][animation_frame(global_cycle, status.cycle)])
+	}
+
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		// param1 is the intelligence (0-8)
 		// param2 is a combination between the firing rate and the firing type: the first 7 bits are
@@ -455,16 +524,14 @@ impl Behaviour for SpinningGunBehaviour {
 		let firing_rate = status.param2 & 0b01111111;
 		let shoot_stars = (status.param2 & 0b10000000) != 0;
 
-		let mut rng = rand::thread_rng();
-
-		let try_shoot_bullet = firing_rate > rng.gen_range(0, 9);
+		let try_shoot_bullet = firing_rate > sim.random_range(0, 9);
 		if try_shoot_bullet {
 			let shoot_step_x;
 			let shoot_step_y;
 
 			// If param1 (intelligence) is 0, then it should always randomise, and when 8 it
 			// should ALWAYS shoot towards the player.
-			let should_randomise: bool = rng.gen_range(0, 9) > status.param1;
+			let should_randomise: bool = sim.random_range(0, 9) > status.param1;
 			if should_randomise {
 				let (rand_step_x, rand_step_y) = sim.get_random_step();
 				shoot_step_x = rand_step_x;
@@ -494,7 +561,7 @@ impl Behaviour for SpinningGunBehaviour {
 				let shoot_x = status.location_x as i16 + shoot_step_x;
 				let shoot_y = status.location_y as i16 + shoot_step_y;
 
-				sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, shoot_stars, false, &mut actions);
+				sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, shoot_stars, ShotSource::Enemy, &mut actions);
 			}
 		}
 
@@ -506,6 +573,15 @@ impl Behaviour for SpinningGunBehaviour {
 pub struct PusherBehaviour;
 
 impl Behaviour for PusherBehaviour {
+	fn status_char_code(&self, status: &StatusElement, _global_cycle: usize) -> Option<u8> {
+		Some(match (status.step_x, status.step_y) {
+			(1, 0) => 0x10,
+			(-1, 0) => 0x11,
+			(0, -1) => 0x1e,
+			_ => 0x1f,
+		})
+	}
+
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, _sim: &BoardSimulator) -> ActionResult {
 		ActionResult::with_actions(vec![
 			Action::MoveTile {
@@ -528,14 +604,13 @@ pub struct LionBehaviour;
 impl Behaviour for LionBehaviour {
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
-		let mut rng = rand::thread_rng();
 
 		let off_x;
 		let off_y;
 
 		// If param1 (intelligence) is 0, then it should always randomise, and when 8 it
 		// should ALMOST always seek.
-		let should_randomise: bool = rng.gen_range(0, 9) >= status.param1;
+		let should_randomise: bool = sim.random_range(0, 9) >= status.param1;
 		if should_randomise {
 			let (rand_step_x, rand_step_y) = sim.get_random_step();
 			off_x = rand_step_x;
@@ -594,7 +669,6 @@ pub struct TigerBehaviour;
 impl Behaviour for TigerBehaviour {
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
-		let mut rng = rand::thread_rng();
 
 		let (player_x, player_y) = sim.get_player_location();
 
@@ -604,11 +678,10 @@ impl Behaviour for TigerBehaviour {
 		let diff_y = status.location_y as i16 - player_y;
 		let allowed_diff = 2 as i16;
 		if diff_x.abs() <= allowed_diff || diff_y.abs() <= allowed_diff {
-			let mut rng = rand::thread_rng();
 			let firing_rate = status.param2 & 0b01111111;
 			let shoot_stars = (status.param2 & 0b10000000) != 0;
 
-			shot_bullet = rng.gen_range(0, 25) < (firing_rate + 2);
+			shot_bullet = sim.random_range(0, 25) < (firing_rate + 2);
 			if shot_bullet {
 				let shoot_off_x;
 				let shoot_off_y;
@@ -624,7 +697,7 @@ impl Behaviour for TigerBehaviour {
 
 				let shoot_x = status.location_x as i16 + shoot_off_x;
 				let shoot_y = status.location_y as i16 + shoot_off_y;
-				sim.make_shoot_actions(shoot_x, shoot_y, shoot_off_x, shoot_off_y, shoot_stars, false, &mut actions);
+				sim.make_shoot_actions(shoot_x, shoot_y, shoot_off_x, shoot_off_y, shoot_stars, ShotSource::Enemy, &mut actions);
 			}
 		} else {
 			shot_bullet = false;
@@ -636,7 +709,7 @@ impl Behaviour for TigerBehaviour {
 
 			// If param1 (intelligence) is 0, then it should always randomise, and when 8 it
 			// should ALMOST always seek.
-			let should_randomise: bool = rng.gen_range(0, 9) >= status.param1;
+			let should_randomise: bool = sim.random_range(0, 9) >= status.param1;
 			if should_randomise {
 				let (rand_step_x, rand_step_y) = sim.get_random_step();
 				off_x = rand_step_x;
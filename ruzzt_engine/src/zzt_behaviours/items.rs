@@ -1,12 +1,13 @@
 use crate::behaviour::*;
 use crate::board_message::*;
 use crate::board_simulator::*;
+use crate::constants::*;
 use crate::event::*;
 use crate::direction::*;
 use crate::oop_parser::*;
+use crate::passage_lock::PassageLock;
 use crate::sounds::*;
 
-use rand::Rng;
 
 use zzt_file_format::*;
 use zzt_file_format::dosstring::DosString;
@@ -49,7 +50,7 @@ impl Behaviour for PlayerBehaviour {
 						let shoot_x = status.location_x as i16 + shoot_step_x;
 						let shoot_y = status.location_y as i16 + shoot_step_y;
 
-						let fired_shot = sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, false, true, &mut actions);
+						let fired_shot = sim.make_shoot_actions(shoot_x, shoot_y, shoot_step_x, shoot_step_y, false, ShotSource::Player, &mut actions);
 
 						if fired_shot {
 							actions.push(Action::ModifyPlayerItem {
@@ -58,7 +59,7 @@ impl Behaviour for PlayerBehaviour {
 								require_exact_amount: false,
 							});
 							actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(
-								process_notes_string(b"t+c-c-c"), SoundPriority::Level(2))));
+								process_notes_string_to_sound_entries(b"t+c-c-c"), SoundPriority::Level(2))));
 						}
 					} else {
 						actions.push(Action::SendBoardMessage(BoardMessage::ShowOneTimeNotification(OneTimeNotification::NoAmmo)));
@@ -71,7 +72,7 @@ impl Behaviour for PlayerBehaviour {
 						if torch_cycles == 0 {
 							if player_torches > 0 {
 								if sim.board_meta_data.is_dark {
-									actions.push(Action::SetTorchCycles(200));
+									actions.push(Action::SetTorchCycles(TORCH_CYCLES));
 									actions.push(Action::ModifyPlayerItem {
 										item_type: PlayerItemType::Torches,
 										offset: -1,
@@ -98,13 +99,18 @@ impl Behaviour for PlayerBehaviour {
 			Event::Debug => {
 				actions.push(Action::SendBoardMessage(BoardMessage::OpenDebugInput));
 			}
+			Event::OpenWorldMap => {
+				actions.push(Action::SendBoardMessage(BoardMessage::OpenWorldMap));
+			}
+			Event::OpenMessageLog => {
+				actions.push(Action::SendBoardMessage(BoardMessage::OpenMessageLog));
+			}
+			Event::Escape if !is_end_of_game => {
+				actions.push(Action::SendBoardMessage(BoardMessage::OpenPauseMenu));
+			}
 			Event::Quit | Event::Escape => {
 				if is_end_of_game {
-					actions.push(Action::SendBoardMessage(BoardMessage::ReturnToTitleScreen));
-					let mut filename = sim.world_header.world_name.clone();
-					filename = filename.to_upper();
-					filename += b".ZZT";
-					actions.push(Action::SendBoardMessage(BoardMessage::OpenWorld{filename}));
+					actions.push(Action::SendBoardMessage(BoardMessage::EndGame));
 				} else {
 					actions.push(Action::SendBoardMessage(BoardMessage::OpenEndGameConfirmation));
 				}
@@ -112,6 +118,11 @@ impl Behaviour for PlayerBehaviour {
 			_ => {}
 		};
 
+		// Water currents (in Super ZZT) carry the player along when they aren't moving by themselves.
+		if move_direction_opt.is_none() && !is_end_of_game {
+			move_direction_opt = sim.ruleset.current_direction(status.under_element_id);
+		}
+
 		actions.push(Action::SetAsPlayerTile {
 			x: status.location_x as i16,
 			y: status.location_y as i16,
@@ -129,9 +140,9 @@ impl Behaviour for PlayerBehaviour {
 			let to_x = status.location_x as i16 + off_x;
 			let to_y = status.location_y as i16 + off_y;
 
-			// ZZT always moves status element 0, resulting in some very weird behaviours when there
-			// are multiple players.
-			let status_to_move = &sim.status_elements[0];
+			// ZZT always moves status element 0 (see `player_status_index`), resulting in some very
+			// weird behaviours when there are multiple players, or status element 0 isn't a player.
+			let status_to_move = &sim.status_elements[sim.player_status_index().unwrap_or(status_index)];
 			let from_x = status_to_move.location_x as i16;
 			let from_y = status_to_move.location_y as i16;
 
@@ -156,7 +167,7 @@ impl Behaviour for PlayerBehaviour {
 
 		if sim.world_header.energy_cycles > 0 {
 			if sim.world_header.energy_cycles == 10 {
-				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"s.-c-a#gf#fd#c"), SoundPriority::Level(9))));
+				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"s.-c-a#gf#fd#c"), SoundPriority::Level(9))));
 			}
 			actions.push(Action::SetEnergyCycles(sim.world_header.energy_cycles - 1));
 		}
@@ -210,16 +221,7 @@ impl Behaviour for PlayerBehaviour {
 	fn damage(&self, _x: i16, _y: i16, _damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
 		// NOTE: Players can shoot themselves. Proof is that when one bounces off a ricochet, it
 		// comes back and hurts the player.
-		if sim.world_header.energy_cycles <= 0 {
-			actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"--c+c-d#+d#"), SoundPriority::Level(2))));
-			actions.push(Action::SendBoardMessage(BoardMessage::OpenScroll{title: DosString::new(), content_lines: vec![DosString::from_slice(b"Ouch!")]}));
-			actions.push(Action::ModifyPlayerItem {
-				item_type: PlayerItemType::Health,
-				offset: -10,
-				require_exact_amount: false,
-			});
-			actions.push(Action::CheckRestartOnZapped);
-		}
+		sim.add_player_hurt_actions(actions);
 		DamageResult::None
 	}
 }
@@ -233,7 +235,7 @@ impl Behaviour for AmmoBehaviour {
 			PushResult {
 				blocked: BlockedStatus::NotBlocked,
 				action_result: ActionResult::with_actions(vec![
-					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"cc#d"), SoundPriority::Level(2))),
+					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"cc#d"), SoundPriority::Level(2))),
 					Action::SetTile {
 						x,
 						y,
@@ -278,7 +280,7 @@ impl Behaviour for TorchBehaviour {
 			PushResult {
 				blocked: BlockedStatus::NotBlocked,
 				action_result: ActionResult::with_actions(vec![
-					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"case"), SoundPriority::Level(3))),
+					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"case"), SoundPriority::Level(3))),
 					Action::SetTile {
 						x,
 						y,
@@ -307,7 +309,7 @@ impl Behaviour for GemBehaviour {
 			PushResult {
 				blocked: BlockedStatus::NotBlocked,
 				action_result: ActionResult::with_actions(vec![
-					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"+c-gec"), SoundPriority::Level(2))),
+					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"+c-gec"), SoundPriority::Level(2))),
 					Action::SetTile {
 						x,
 						y,
@@ -367,7 +369,7 @@ impl Behaviour for GemBehaviour {
 	}
 
 	fn damage(&self, x: i16, y: i16, damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
-		if damage_type == (DamageType::Shot{by_player: false}) {
+		if damage_type == (DamageType::Shot{source: ShotSource::Enemy}) {
 			// Although gems are destructable, they will only die when shot by a player.
 			DamageResult::None
 		} else {
@@ -379,16 +381,19 @@ impl Behaviour for GemBehaviour {
 #[derive(Debug, Clone)]
 pub struct KeyBehaviour;
 
+/// Get the name of the key with the given index (see `BoardSimulator::key_index_for_colour`).
+/// DOS ZZT shows whatever happens to be in memory before its list of colour names for black keys
+/// and doors, so they are just called "Black" here.
 fn get_key_name(index: u8) -> &'static [u8] {
 	match index {
-		0 => b"Blue",
-		1 => b"Green",
-		2 => b"Cyan",
-		3 => b"Red",
-		4 => b"Purple",
-		5 => b"Yellow",
-		6 => b"White",
-		_ => b"?",
+		1 => b"Blue",
+		2 => b"Green",
+		3 => b"Cyan",
+		4 => b"Red",
+		5 => b"Purple",
+		6 => b"Yellow",
+		7 => b"White",
+		_ => b"Black",
 	}
 }
 
@@ -396,20 +401,18 @@ impl Behaviour for KeyBehaviour {
 	fn push(&self, x: i16, y: i16, push_off_x: i16, push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
 		if is_player {
 			if let Some(tile) = sim.get_tile(x, y) {
-				let key_index = tile.colour as isize - 9;
-				println!("{:?}", tile);
-				if key_index >= 0 && key_index < 7 {
-					let current_has_key = sim.world_header.player_keys[key_index as usize];
+				if let Some(key_index) = sim.key_index_for_colour(tile.colour & 0x0f) {
+					let current_has_key = sim.has_key(key_index);
 					if !current_has_key {
 						let mut message_str = DosString::new();
 						message_str += b"You now have the ";
-						message_str += get_key_name(key_index as u8);
+						message_str += get_key_name(key_index);
 						message_str += b" key";
 
 						PushResult {
 							blocked: BlockedStatus::NotBlocked,
 							action_result: ActionResult::with_actions(vec![
-								Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"t+cegcegcegs+c"), SoundPriority::Level(2))),
+								Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"t+cegcegcegs+c"), SoundPriority::Level(2))),
 								Action::SendBoardMessage(BoardMessage::OpenScroll {
 									title: DosString::new(),
 									content_lines: vec![message_str],
@@ -421,7 +424,7 @@ impl Behaviour for KeyBehaviour {
 									status_element: None,
 								},
 								Action::ModifyPlayerKeys {
-									index: key_index as u8,
+									index: key_index,
 									value: true,
 								},
 							]),
@@ -429,7 +432,7 @@ impl Behaviour for KeyBehaviour {
 					} else {
 						let mut message_str = DosString::new();
 						message_str += b"You already have the ";
-						message_str += get_key_name(key_index as u8);
+						message_str += get_key_name(key_index);
 						message_str += b" key!";
 
 						// TODO: Play sound
@@ -480,9 +483,8 @@ impl Behaviour for DoorBehaviour {
 
 		if is_player {
 			if let Some(tile) = sim.get_tile(x, y) {
-				let key_index = ((tile.colour & 0xf0) >> 4) as isize - 1;
-				if key_index >= 0 && key_index < 7 {
-					let has_key = sim.world_header.player_keys[key_index as usize];
+				if let Some(key_index) = sim.key_index_for_colour(tile.colour >> 4) {
+					let has_key = sim.has_key(key_index);
 
 					if has_key {
 						actions.push(Action::SetTile {
@@ -492,25 +494,25 @@ impl Behaviour for DoorBehaviour {
 							status_element: None,
 						});
 						actions.push(Action::ModifyPlayerKeys {
-							index: key_index as u8,
+							index: key_index,
 							value: false,
 						});
-						actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"tcgbcgbi+c"), SoundPriority::Level(3))));
+						actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"tcgbcgbi+c"), SoundPriority::Level(3))));
 
 						let mut message_str = DosString::new();
 						message_str += b"The ";
-						message_str += get_key_name(key_index as u8);
+						message_str += get_key_name(key_index);
 						message_str += b" door is now open.";
 						actions.push(Action::SendBoardMessage(BoardMessage::OpenScroll {
 							title: DosString::new(),
 							content_lines: vec![message_str],
 						}));
 					} else {
-						actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"--tgc"), SoundPriority::Level(3))));
+						actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"--tgc"), SoundPriority::Level(3))));
 
 						let mut message_str = DosString::new();
 						message_str += b"The ";
-						message_str += get_key_name(key_index as u8);
+						message_str += get_key_name(key_index);
 						message_str += b" door is locked!";
 						actions.push(Action::SendBoardMessage(BoardMessage::OpenScroll {
 							title: DosString::new(),
@@ -537,7 +539,8 @@ impl Behaviour for ScrollBehaviour {
 		if let Some(tile) = tile_opt {
 			let bg = tile.colour >> 4;
 			let fg = tile.colour & 0b1111;
-			let new_fg = ((fg - 8) % 7) + 9;
+			// A dark colour is moved into the bright colours instead of overflowing.
+			let new_fg = (((fg as i16 - 8) % 7) + 9) as u8;
 			let new_colour = (bg << 4) + new_fg;
 
 			ActionResult::with_actions(vec![
@@ -562,7 +565,7 @@ impl Behaviour for ScrollBehaviour {
 			if let Some((status_index, _status)) = status_element_opt {
 				continuation = Some(Box::new(OopExecutionState::new(true, Some(status_index))));
 
-				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"tc-c+d-d+e-e+f-f+g-g"), SoundPriority::Level(2))));
+				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"tc-c+d-d+e-e+f-f+g-g"), SoundPriority::Level(2))));
 			}
 		} else {
 			actions.push(Action::MoveTile{
@@ -613,7 +616,7 @@ impl Behaviour for PassageBehaviour {
 		if is_player {
 			let status_element_opt = sim.get_first_status_for_pos(x, y);
 
-			if let Some((_, status_element)) = status_element_opt {
+			if let Some((status_index, status_element)) = status_element_opt {
 				let tile_opt = sim.get_tile(x, y);
 				let colour = if let Some(tile) = tile_opt {
 					tile.colour
@@ -621,11 +624,40 @@ impl Behaviour for PassageBehaviour {
 					0
 				};
 
+				let lock_opt = if sim.settings.locked_passages_enabled {
+					PassageLock::from_code(sim.get_status_code(status_element))
+				} else {
+					None
+				};
+				match lock_opt {
+					Some(PassageLock::Flag(ref flag_name)) if !sim.flag_is_set(flag_name) => {
+						return PushResult {
+							blocked: BlockedStatus::Blocked,
+							action_result: ActionResult::with_actions(vec![
+								Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"--tgc"), SoundPriority::Level(3))),
+								Action::SendBoardMessage(BoardMessage::OpenScroll {
+									title: DosString::new(),
+									content_lines: vec![DosString::from_slice(b"The passage is locked!")],
+								}),
+							]),
+						};
+					}
+					Some(PassageLock::Password(_)) => {
+						return PushResult {
+							blocked: BlockedStatus::Blocked,
+							action_result: ActionResult::with_actions(vec![
+								Action::SendBoardMessage(BoardMessage::OpenPassagePasswordInput{status_index}),
+							]),
+						};
+					}
+					_ => {}
+				}
+
 				PushResult {
 					blocked: BlockedStatus::Blocked,
 					action_result: ActionResult::with_actions(vec![
 						Action::SendBoardMessage(BoardMessage::PlaySoundArray(
-							process_notes_string(b"tcegc#fg#df#ad#ga#eg#+c"), SoundPriority::Level(4))),
+							process_notes_string_to_sound_entries(b"tcegc#fg#df#ad#ga#eg#+c"), SoundPriority::Level(4))),
 						Action::SendBoardMessage(BoardMessage::TeleportToBoard {
 							destination_board_index: status_element.param3,
 							passage_colour: colour,
@@ -661,6 +693,11 @@ impl ActionContinuation for DuplicatorContinuation {
 				if let Some(ref mut duplicated_status) = duplicated_status_opt {
 					duplicated_status.location_x = dest_x as u8;
 					duplicated_status.location_y = dest_y as u8;
+					// The copy is on top of the destination, not whatever the original is on.
+					if let Some(dest_tile) = sim.get_tile(dest_x, dest_y) {
+						duplicated_status.under_element_id = dest_tile.element_id;
+						duplicated_status.under_colour = dest_tile.colour;
+					}
 				}
 
 				actions.push(Action::SetTile {
@@ -670,10 +707,10 @@ impl ActionContinuation for DuplicatorContinuation {
 					status_element: duplicated_status_opt,
 				});
 
-				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"scdefg"), SoundPriority::Level(3))));
+				actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"scdefg"), SoundPriority::Level(3))));
 			}
 		} else {
-			actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"--g#f#"), SoundPriority::Level(3))));
+			actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"--g#f#"), SoundPriority::Level(3))));
 		}
 
 		ActionContinuationResult {
@@ -687,6 +724,18 @@ impl ActionContinuation for DuplicatorContinuation {
 pub struct DuplicatorBehaviour;
 
 impl Behaviour for DuplicatorBehaviour {
+	fn status_char_code(&self, status: &StatusElement, _global_cycle: usize) -> Option<u8> {
+		Some(match status.param1 {
+			0 => 0xfa,
+			1 => 0xf9,
+			2 => 0xf8,
+			3 => 0x6f,
+			4 => 0x4f,
+			// TODO: Check this with ZZT:
+			_ => 0,
+		})
+	}
+
 	fn step(&self, _event: Event, status: &StatusElement, status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		// param1 is the progress (0-4)
 		// param2 is the progression speed (0-8). This value is used to set the cycle every step.
@@ -744,6 +793,15 @@ pub struct BombBehaviour;
 
 // Bombable types: gem, bear, ruffian, lion, tiger, head, segment, breakable
 impl Behaviour for BombBehaviour {
+	fn status_char_code(&self, status: &StatusElement, _global_cycle: usize) -> Option<u8> {
+		// A lit bomb shows how long it has left.
+		if status.param1 > 1 {
+			Some(b'0' + status.param1)
+		} else {
+			None
+		}
+	}
+
 	fn push(&self, x: i16, y: i16, push_off_x: i16, push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
 		let mut actions = vec![];
 		let mut blocked = BlockedStatus::Blocked;
@@ -817,8 +875,7 @@ impl Behaviour for BombBehaviour {
 										let damage_result = behaviour.damage(x, y, DamageType::Bombed, sim, &mut actions);
 
 										if damage_result == DamageResult::Died {
-											let mut rng = rand::thread_rng();
-											let rand_colour: u8 = rng.gen_range(9, 16);
+											let rand_colour: u8 = sim.random_range(9, 16);
 											actions.push(Action::SetTile {
 												x,
 												y,
@@ -872,14 +929,13 @@ pub struct EnergizerBehaviour;
 
 impl Behaviour for EnergizerBehaviour {
 	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, _sim: &BoardSimulator) -> PushResult {
-		// The energizer blinks for 75 cycles.
 		if is_player {
 			PushResult {
 				blocked: BlockedStatus::NotBlocked,
 				action_result: ActionResult::with_actions(vec![
-					Action::SetEnergyCycles(75),
+					Action::SetEnergyCycles(ENERGIZER_CYCLES),
 					Action::SendBoardMessage(BoardMessage::PlaySoundArray(
-						process_notes_string(b"s.-cd#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c"),
+						process_notes_string_to_sound_entries(b"s.-cd#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c+c-d#ef+f-fd#c"),
 						SoundPriority::Level(9)
 					)),
 					Action::SendBoardMessage(BoardMessage::ShowOneTimeNotification(OneTimeNotification::PickUpEnergizer)),
@@ -913,6 +969,15 @@ pub struct ConveyorBehaviour {
 }
 
 impl Behaviour for ConveyorBehaviour {
+	fn status_char_code(&self, status: &StatusElement, global_cycle: usize) -> Option<u8> {
+		let frames = if self.clockwise {
+			[0x2f, 0xc4, 0x5c, 0xb3]
+		} else {
+			[0x5c, 0xc4, 0x2f, 0xb3]
+		};
+		Some(frames[animation_frame(global_cycle, status.cycle)])
+	}
+
 	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
 
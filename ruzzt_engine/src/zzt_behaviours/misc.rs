@@ -74,6 +74,12 @@ impl Behaviour for MonitorBehaviour {
 			Event::Quit | Event::Escape => actions.push(Action::SendBoardMessage(BoardMessage::OpenQuitConfirmation)),
 			Event::OpenWorldSelection => actions.push(Action::SendBoardMessage(BoardMessage::OpenWorldSelection)),
 			Event::RestoreGame => actions.push(Action::SendBoardMessage(BoardMessage::OpenSaveSelection)),
+			Event::OpenAbout => actions.push(Action::SendBoardMessage(BoardMessage::OpenAbout)),
+			Event::OpenHighScores => actions.push(Action::SendBoardMessage(BoardMessage::OpenHighScores)),
+			// There is no editor yet, so this just makes a new empty world.
+			Event::OpenEditor => actions.push(Action::SendBoardMessage(BoardMessage::OpenNewWorldInput)),
+			Event::ChangeGameSpeed => actions.push(Action::SendBoardMessage(BoardMessage::OpenGameSpeedInput)),
+			Event::ChangeKeyboardConfig => actions.push(Action::SendBoardMessage(BoardMessage::OpenKeyboardConfigInput)),
 			_ => {}
 		}
 
@@ -106,7 +112,7 @@ impl Behaviour for BulletBehaviour {
 					new_step_y *= -1;
 				} else {
 					let dest_behaviour = sim.behaviour_for_pos(next_x, next_y);
-					if dest_behaviour.blocked_for_bullets() == BlockedStatus::Blocked {
+					if dest_behaviour.blocked_for_bullets(sim) == BlockedStatus::Blocked {
 						let cw_next_x = status.location_x as i16 + new_step_y;
 						let cw_next_y = status.location_y as i16 + new_step_x;
 						if let Some(cw_dest_tile) = sim.get_tile(cw_next_x, cw_next_y) {
@@ -138,7 +144,7 @@ impl Behaviour for BulletBehaviour {
 			let next_y = status.location_y as i16 + new_step_y;
 
 			let dest_behaviour = sim.behaviour_for_pos(next_x, next_y);
-			if dest_behaviour.blocked_for_bullets() == BlockedStatus::Blocked {
+			if dest_behaviour.blocked_for_bullets(sim) == BlockedStatus::Blocked {
 				actions.push(Action::SetTile {
 					x: status.location_x as i16,
 					y: status.location_y as i16,
@@ -150,8 +156,7 @@ impl Behaviour for BulletBehaviour {
 				});
 
 				if dest_behaviour.destructable() {
-					let by_player = status.param1 == 0;
-					dest_behaviour.damage(next_x, next_y, DamageType::Shot{by_player}, sim, &mut actions);
+					dest_behaviour.damage(next_x, next_y, DamageType::Shot{source: ShotSource::from_status(status)}, sim, &mut actions);
 				}
 
 				// If there are a row of bullets shot going east: *****, and the rightmost bullet
@@ -223,6 +228,12 @@ for the case where it is a star being fired (where param2 is the countdown).
 pub struct StarBehaviour;
 
 impl Behaviour for StarBehaviour {
+	fn status_char_code(&self, status: &StatusElement, global_cycle: usize) -> Option<u8> {
+		//let frame_offset = (global_cycle + (status.param2 as usize)) % 2;
+		//let frame_index = ((global_cycle & !1) + frame_offset) % 4;
+		Some([0x2f, 0xc4, 0x5c, 0xb3][animation_frame(global_cycle, status.cycle)])
+	}
+
 	fn step(&self, _event: Event, status: &StatusElement, status_index: usize, sim: &BoardSimulator) -> ActionResult {
 		let mut actions = vec![];
 
@@ -253,7 +264,8 @@ impl Behaviour for StarBehaviour {
 					if let Some(tile) = tile_opt {
 						let bg = tile.colour >> 4;
 						let fg = tile.colour & 0b1111;
-						let new_fg = ((fg - 8) % 7) + 9;
+						// A dark colour is moved into the bright colours instead of overflowing.
+						let new_fg = (((fg as i16 - 8) % 7) + 9) as u8;
 						let new_colour = (bg << 4) + new_fg;
 
 						actions.push(Action::SetColour {
@@ -264,7 +276,7 @@ impl Behaviour for StarBehaviour {
 					}
 
 					let dest_behaviour = sim.behaviour_for_pos(dest_x, dest_y);
-					if dest_behaviour.blocked_for_bullets() == BlockedStatus::NotBlocked {
+					if dest_behaviour.blocked_for_bullets(sim) == BlockedStatus::NotBlocked {
 						actions.push(Action::MoveTile {
 							from_x: status.location_x as i16,
 							from_y: status.location_y as i16,
@@ -15,19 +15,10 @@ pub fn add_monster_touch_player_actions(x: i16, y: i16, actions: &mut Vec<Action
 			status_element: None,
 		});
 
+		// The player's damage function hurts them (see `BoardSimulator::add_player_hurt_actions`).
 		let (player_x, player_y) = sim.get_player_location();
 		let behaviour = sim.behaviour_for_pos(player_x, player_y);
 		behaviour.damage(player_x, player_y, DamageType::Other, sim, actions);
-
-		if sim.world_header.energy_cycles <= 0 {
-			actions.push(Action::ModifyPlayerItem {
-				item_type: PlayerItemType::Health,
-				offset: -10,
-				require_exact_amount: false,
-			});
-			actions.push(Action::CheckRestartOnZapped);
-		}
-		// TODO: Play sound
 	} else {
 		// TODO: Do monsters hurt when they don't have a status?
 	}
@@ -58,7 +49,7 @@ pub fn monster_damage(behaviour: &dyn Behaviour, x: i16, y: i16, damage_type: Da
 		});
 
 		actions.push(Action::SendBoardMessage(BoardMessage::PlaySoundArray(
-			process_notes_string(b"c--c++++c--c"),
+			process_notes_string_to_sound_entries(b"c--c++++c--c"),
 			SoundPriority::Level(3)
 		)));
 
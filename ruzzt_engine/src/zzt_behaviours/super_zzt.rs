@@ -0,0 +1,207 @@
+use crate::behaviour::*;
+use crate::board_message::*;
+use crate::board_simulator::*;
+use crate::event::*;
+use crate::sounds::*;
+
+
+use zzt_file_format::*;
+
+use crate::zzt_behaviours::monster_interactions::*;
+
+/// Lava blocks the player like water does in ZZT, but bullets can fly over it.
+#[derive(Debug, Clone)]
+pub struct LavaBehaviour;
+
+impl Behaviour for LavaBehaviour {
+	fn blocked_for_bullets(&self, _sim: &BoardSimulator) -> BlockedStatus {
+		BlockedStatus::NotBlocked
+	}
+}
+
+/// Floors, webs and water currents can be walked over by anything, and are put back when whatever
+/// is standing on them moves off. Water currents also carry the player along in their direction
+/// (see `RulesetPlugin::current_direction`).
+#[derive(Debug, Clone)]
+pub struct FloorBehaviour;
+
+impl Behaviour for FloorBehaviour {
+	fn push(&self, _x: i16, _y: i16, _push_off_x: i16, _push_off_y: i16, _is_player: bool, _sim: &BoardSimulator) -> PushResult {
+		PushResult::do_nothing_not_blocked()
+	}
+
+	fn blocked(&self, _is_player: bool) -> BlockedStatus {
+		BlockedStatus::NotBlocked
+	}
+}
+
+/// Stones of power are picked up like gems, and counted in the world header's `player_stones`.
+/// The count is negative until the first stone is picked up, which hides it in the side bar, so
+/// the first stone always makes it 1.
+#[derive(Debug, Clone)]
+pub struct StoneBehaviour;
+
+impl Behaviour for StoneBehaviour {
+	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
+		if is_player {
+			let player_stones = sim.world_header.player_stones.unwrap_or(0);
+			PushResult {
+				blocked: BlockedStatus::NotBlocked,
+				action_result: ActionResult::with_actions(vec![
+					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"+c-gec"), SoundPriority::Level(2))),
+					Action::SetTile {
+						x,
+						y,
+						tile: BoardTile { element_id: SuperZztElementType::Empty as u8, colour: 0 },
+						status_element: None,
+					},
+					Action::ModifyPlayerItem {
+						item_type: PlayerItemType::Stones,
+						offset: 1 - player_stones.min(0) as i32,
+						require_exact_amount: false,
+					},
+				]),
+			}
+		} else {
+			PushResult::do_nothing_blocked()
+		}
+	}
+}
+
+/// Move the creature with the given `status` one step, either towards the player if `seek` is true,
+/// or in a random direction. If `web_only` is true, it can only move onto webs (like spiders).
+/// Touching the player hurts them, like it does for lions.
+fn creature_step(status: &StatusElement, seek: bool, web_only: bool, sim: &BoardSimulator) -> ActionResult {
+	let mut actions = vec![];
+
+	let (off_x, off_y) = if seek {
+		sim.seek_direction(status.location_x as i16, status.location_y as i16).to_offset()
+	} else {
+		sim.get_random_step()
+	};
+
+	let dest_x = status.location_x as i16 + off_x;
+	let dest_y = status.location_y as i16 + off_y;
+	if sim.has_player_at_location(dest_x, dest_y) {
+		add_monster_touch_player_actions(status.location_x as i16, status.location_y as i16, &mut actions, sim);
+	} else {
+		let can_move = if web_only {
+			sim.get_tile(dest_x, dest_y).is_some_and(|tile| tile.element_id == SuperZztElementType::Web as u8)
+		} else {
+			sim.behaviour_for_pos(dest_x, dest_y).blocked(false) == BlockedStatus::NotBlocked
+		};
+		if can_move {
+			actions.push(Action::MoveTile {
+				from_x: status.location_x as i16,
+				from_y: status.location_y as i16,
+				to_x: dest_x,
+				to_y: dest_y,
+				offset_x: off_x,
+				offset_y: off_y,
+				check_push: true,
+				is_player: false,
+			});
+		}
+	}
+
+	ActionResult::with_actions(actions)
+}
+
+/// True if a creature with the given `intelligence` (0 to 8, usually param1) should seek the player
+/// this step instead of moving randomly.
+fn should_seek(intelligence: u8, sim: &BoardSimulator) -> bool {
+	sim.random_range(0, 9) < intelligence
+}
+
+/*
+param1 is the intelligence (0 is random movement, 8 is ALMOST always seek).
+*/
+#[derive(Debug, Clone)]
+pub struct RotonBehaviour;
+
+impl Behaviour for RotonBehaviour {
+	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
+		creature_step(status, should_seek(status.param1, sim), false, sim)
+	}
+
+	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
+		monster_push(x, y, is_player, sim)
+	}
+
+	fn damage(&self, x: i16, y: i16, damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
+		monster_damage(self, x, y, damage_type, sim, actions)
+	}
+
+	fn destructable(&self) -> bool {
+		true
+	}
+}
+
+/// Dragon pups wander around randomly.
+#[derive(Debug, Clone)]
+pub struct DragonPupBehaviour;
+
+impl Behaviour for DragonPupBehaviour {
+	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
+		creature_step(status, false, false, sim)
+	}
+
+	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
+		monster_push(x, y, is_player, sim)
+	}
+
+	fn damage(&self, x: i16, y: i16, damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
+		monster_damage(self, x, y, damage_type, sim, actions)
+	}
+
+	fn destructable(&self) -> bool {
+		true
+	}
+}
+
+/// Pairers always head straight for the player.
+#[derive(Debug, Clone)]
+pub struct PairerBehaviour;
+
+impl Behaviour for PairerBehaviour {
+	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
+		creature_step(status, true, false, sim)
+	}
+
+	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
+		monster_push(x, y, is_player, sim)
+	}
+
+	fn damage(&self, x: i16, y: i16, damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
+		monster_damage(self, x, y, damage_type, sim, actions)
+	}
+
+	fn destructable(&self) -> bool {
+		true
+	}
+}
+
+/*
+param1 is the intelligence (0 is random movement, 8 is ALMOST always seek).
+Spiders can only move along webs.
+*/
+#[derive(Debug, Clone)]
+pub struct SpiderBehaviour;
+
+impl Behaviour for SpiderBehaviour {
+	fn step(&self, _event: Event, status: &StatusElement, _status_index: usize, sim: &BoardSimulator) -> ActionResult {
+		creature_step(status, should_seek(status.param1, sim), true, sim)
+	}
+
+	fn push(&self, x: i16, y: i16, _push_off_x: i16, _push_off_y: i16, is_player: bool, sim: &BoardSimulator) -> PushResult {
+		monster_push(x, y, is_player, sim)
+	}
+
+	fn damage(&self, x: i16, y: i16, damage_type: DamageType, sim: &BoardSimulator, actions: &mut Vec<Action>) -> DamageResult {
+		monster_damage(self, x, y, damage_type, sim, actions)
+	}
+
+	fn destructable(&self) -> bool {
+		true
+	}
+}
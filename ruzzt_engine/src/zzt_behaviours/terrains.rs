@@ -1,6 +1,7 @@
 use crate::behaviour::*;
 use crate::board_message::*;
 use crate::board_simulator::*;
+use crate::constants::PLAYER_HURT_DAMAGE;
 use crate::event::*;
 use crate::sounds::*;
 
@@ -23,8 +24,12 @@ impl Behaviour for WaterBehaviour {
 		}
 	}
 
-	fn blocked_for_bullets(&self) -> BlockedStatus {
-		BlockedStatus::NotBlocked
+	fn blocked_for_bullets(&self, sim: &BoardSimulator) -> BlockedStatus {
+		if sim.settings.compatibility_version.bullets_fly_over_water() {
+			BlockedStatus::NotBlocked
+		} else {
+			BlockedStatus::Blocked
+		}
 	}
 }
 
@@ -37,7 +42,7 @@ impl Behaviour for ForestBehaviour {
 			PushResult {
 				blocked: BlockedStatus::NotBlocked,
 				action_result: ActionResult::with_actions(vec![
-					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"ta"), SoundPriority::Level(3))),
+					Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"ta"), SoundPriority::Level(3))),
 					Action::SendBoardMessage(BoardMessage::ShowOneTimeNotification(OneTimeNotification::ForestCleared)),
 					Action::SetTile {
 						x,
@@ -215,8 +220,9 @@ impl Behaviour for BlinkWallBehaviour {
 		// every time it hits 1, it toggles the wall by checking the first tile adjacent to it to
 		// see if the wall is there or not.
 
-		let period = status.param2 + 1;
-		let start_countdown = (period * 2) - 1;
+		// These wrap around like the bytes they are stored in.
+		let period = status.param2.wrapping_add(1);
+		let start_countdown = period.wrapping_mul(2).wrapping_sub(1);
 		let mut countdown = status.param3;
 
 		if countdown == 0 {
@@ -234,9 +240,13 @@ impl Behaviour for BlinkWallBehaviour {
 				current_x += status.step_x;
 				current_y += status.step_y;
 				let behaviour = sim.behaviour_for_pos(current_x, current_y);
-				let current_tile = sim.get_tile(current_x, current_y).unwrap();
+				// A step that leads off the board stops the ray at the edge.
+				let current_tile = match sim.get_tile(current_x, current_y) {
+					Some(current_tile) => current_tile,
+					None => break,
+				};
 
-				let is_blink_ray = current_tile.element_id == ElementType::BlinkRayVertical as u8 || current_tile.element_id == ElementType::BlinkRayHorizontal as u8;
+				let is_blink_ray = current_tile.element_id == sim.element_id(ElementType::BlinkRayVertical) || current_tile.element_id == sim.element_id(ElementType::BlinkRayHorizontal);
 				let is_player = current_tile.element_id == ElementType::Player as u8;
 
 				let is_adding_wall = *is_adding_wall_opt.get_or_insert(!is_blink_ray);
@@ -271,7 +281,7 @@ impl Behaviour for BlinkWallBehaviour {
 							// It should be fine to just end the game at this point.
 							actions.push(Action::ModifyPlayerItem{
 								item_type: PlayerItemType::Health,
-								offset: -sim.world_header.player_health,
+								offset: -(sim.world_header.player_health as i32),
 								require_exact_amount: false,
 							});
 							break;
@@ -293,7 +303,7 @@ impl Behaviour for BlinkWallBehaviour {
 
 						actions.push(Action::ModifyPlayerItem{
 							item_type: PlayerItemType::Health,
-							offset: -10,
+							offset: -(PLAYER_HURT_DAMAGE as i32),
 							require_exact_amount: false,
 						});
 						actions.push(Action::CheckRestartOnZapped);
@@ -312,7 +322,7 @@ impl Behaviour for BlinkWallBehaviour {
 					x: current_x,
 					y: current_y,
 					tile: BoardTile {
-						element_id: element_type as u8,
+						element_id: sim.element_id(element_type),
 						colour: wall_tile.colour,
 					},
 					status_element: None,
@@ -394,6 +404,21 @@ impl ActionContinuation for TransporterContinuation {
 pub struct TransporterBehaviour;
 
 impl Behaviour for TransporterBehaviour {
+	fn status_char_code(&self, status: &StatusElement, global_cycle: usize) -> Option<u8> {
+		// ZZT shows weird animations for this when the step_x/y is > 1.
+		let frames = match (status.step_x, status.step_y) {
+			(-1, 0) => [0x3c, 0x28, 0xb3],
+			(0, -1) => [0x7e, 0x5e, 0xc4],
+			(0, 1) => [0x5f, 0x76, 0xc4],
+			_ => [0x3e, 0x29, 0xb3],
+		};
+		let frame_index = match animation_frame(global_cycle, status.cycle) {
+			3 => 1,
+			frame_index => frame_index,
+		};
+		Some(frames[frame_index])
+	}
+
 	fn step(&self, _event: Event, _status: &StatusElement, _status_index: usize, _sim: &BoardSimulator) -> ActionResult {
 		ActionResult::do_nothing()
 	}
@@ -406,7 +431,7 @@ impl Behaviour for TransporterBehaviour {
 						blocked: BlockedStatus::Blocked,
 						action_result: ActionResult {
 							actions: vec![
-								Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string(b"sc+d-e+f#-g#+a#c+d"), SoundPriority::Level(3))),
+								Action::SendBoardMessage(BoardMessage::PlaySoundArray(process_notes_string_to_sound_entries(b"sc+d-e+f#-g#+a#c+d"), SoundPriority::Level(3))),
 								Action::MoveTile {
 									from_x: x - status.step_x,
 									from_y: y - status.step_y,
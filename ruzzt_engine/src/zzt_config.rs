@@ -0,0 +1,55 @@
+#[cfg(feature = "file-io")]
+use std::path::Path;
+
+/// The name of the config file that DOS ZZT reads from the directory it is started in.
+pub const ZZT_CONFIG_FILE_NAME: &str = "ZZT.CFG";
+/// The extension of world files, which `ZZT.CFG` leaves off the world's name.
+const WORLD_FILE_EXTENSION: &str = "ZZT";
+
+/// The settings from a `ZZT.CFG` file, like the ones that came with registered copies of ZZT.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZztConfig {
+	/// The name of the world to load on startup, without the extension (the first line).
+	pub world_name: Option<String>,
+	/// False if the world name started with `*`, which turns the editor off.
+	pub editor_enabled: bool,
+	/// The name the game is registered to (the second line).
+	pub registration: Option<String>,
+}
+
+impl ZztConfig {
+	/// Parse the text of a `ZZT.CFG` file.
+	pub fn parse(text: &str) -> ZztConfig {
+		let mut lines = text.lines().map(str::trim);
+		let mut world_name = lines.next().unwrap_or("");
+		let editor_enabled = !world_name.starts_with('*');
+		if !editor_enabled {
+			world_name = &world_name[1 ..];
+		}
+		let registration = lines.next().unwrap_or("");
+
+		ZztConfig {
+			world_name: Some(world_name.to_string()).filter(|name| !name.is_empty()),
+			editor_enabled,
+			registration: Some(registration.to_string()).filter(|registration| !registration.is_empty()),
+		}
+	}
+
+	/// Get the file name of the world to load on startup (eg. `TOWN.ZZT`), if there is one.
+	pub fn world_file_name(&self) -> Option<String> {
+		self.world_name.as_ref().map(|world_name| format!("{}.{}", world_name, WORLD_FILE_EXTENSION))
+	}
+
+	/// Read `ZZT_CONFIG_FILE_NAME` from `directory`, ignoring case. Returns None if there isn't one,
+	/// or an error if it can't be read.
+	#[cfg(feature = "file-io")]
+	pub fn load(directory: &Path) -> Result<Option<ZztConfig>, String> {
+		match crate::dos_files::find_file(directory, ZZT_CONFIG_FILE_NAME) {
+			Some(path) => {
+				let data = std::fs::read(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+				Ok(Some(ZztConfig::parse(&String::from_utf8_lossy(&data))))
+			}
+			None => Ok(None),
+		}
+	}
+}
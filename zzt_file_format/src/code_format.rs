@@ -0,0 +1,140 @@
+use crate::dosstring::DosString;
+use crate::{CodeSource, World};
+
+/// The widest line that fits in the scroll that object code text is shown in. Longer lines are
+/// cut off when they are shown.
+pub const MAX_CODE_LINE_LEN: usize = 50;
+
+/// The line ending used in object code.
+const CODE_LINE_END: u8 = b'\r';
+
+/// Options for `normalize_code`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodeFormatOptions {
+	/// Lines longer than this are reported, or wrapped when `wrap_long_lines` is set.
+	pub max_line_len: usize,
+	/// Wrap long lines of text onto the next line instead of only reporting them. Lines that are
+	/// commands, labels or movements can't be split, so these are only ever reported.
+	pub wrap_long_lines: bool,
+}
+
+impl Default for CodeFormatOptions {
+	fn default() -> CodeFormatOptions {
+		CodeFormatOptions::new()
+	}
+}
+
+impl CodeFormatOptions {
+	/// Make options that report lines longer than `MAX_CODE_LINE_LEN` without wrapping them.
+	pub fn new() -> CodeFormatOptions {
+		CodeFormatOptions {
+			max_line_len: MAX_CODE_LINE_LEN,
+			wrap_long_lines: false,
+		}
+	}
+}
+
+/// Split `line` into pieces no longer than `max_line_len`, breaking after the last space that
+/// fits where there is one. Centred text lines (starting with `$`) stay centred on every piece.
+fn wrap_line(line: &[u8], max_line_len: usize) -> Vec<Vec<u8>> {
+	let (prefix, mut rest): (&[u8], &[u8]) = match line.first() {
+		Some(b'$') => (b"$", &line[1 ..]),
+		_ => (b"", line),
+	};
+	let piece_len = max_line_len.saturating_sub(prefix.len()).max(1);
+
+	let mut pieces = vec![];
+	while rest.len() > piece_len {
+		let split_at = match rest[.. piece_len + 1].iter().rposition(|&c| c == b' ') {
+			Some(space_index) if space_index > 0 => space_index,
+			_ => piece_len,
+		};
+		pieces.push([prefix, &rest[.. split_at]].concat());
+		rest = &rest[split_at ..];
+		while rest.first() == Some(&b' ') {
+			rest = &rest[1 ..];
+		}
+	}
+	pieces.push([prefix, rest].concat());
+	pieces
+}
+
+/// True if `line` is shown as text, so it can be wrapped without changing what the code does.
+fn is_text_line(line: &[u8]) -> bool {
+	!matches!(line.first(), Some(b'#') | Some(b':') | Some(b'/') | Some(b'?') | Some(b'\'') | Some(b'@') | Some(b'!'))
+}
+
+/// Convert the line endings of `code` to the `\r` that ZZT uses, treating `\r\n` and `\n` (from
+/// code written in modern editors) as a single line ending, and deal with lines that are too long
+/// for the scroll as `options` says. Returns the new code and a description of each line that is
+/// still too long, eg. "Line 3 is 62 characters long (max 50)".
+pub fn normalize_code(code: &DosString, options: CodeFormatOptions) -> (DosString, Vec<String>) {
+	let mut lines = vec![];
+	let mut line = vec![];
+	let mut bytes = code.data.iter().peekable();
+	while let Some(&c) = bytes.next() {
+		match c {
+			b'\r' => {
+				if bytes.peek() == Some(&&b'\n') {
+					bytes.next();
+				}
+				lines.push(std::mem::take(&mut line));
+			}
+			b'\n' => lines.push(std::mem::take(&mut line)),
+			_ => line.push(c),
+		}
+	}
+	// Code that ends with a line ending has no line after it.
+	let ends_with_line_end = matches!(code.data.last(), Some(b'\r') | Some(b'\n'));
+	if !ends_with_line_end {
+		lines.push(line);
+	}
+
+	let mut data = vec![];
+	let mut warnings = vec![];
+	let mut line_number = 0;
+	for line in lines {
+		let pieces = if options.wrap_long_lines && is_text_line(&line) {
+			wrap_line(&line, options.max_line_len)
+		} else {
+			vec![line]
+		};
+		for piece in pieces {
+			line_number += 1;
+			if piece.len() > options.max_line_len {
+				warnings.push(format!("Line {} is {} characters long (max {})", line_number, piece.len(), options.max_line_len));
+			}
+			data.extend_from_slice(&piece);
+			data.push(CODE_LINE_END);
+		}
+	}
+	if !ends_with_line_end {
+		data.pop();
+	}
+
+	(DosString { data }, warnings)
+}
+
+impl World {
+	/// Normalize the code of every status element in the world with `normalize_code`, returning
+	/// the warnings for each code prefixed with the board and status element it belongs to.
+	pub fn normalize_code(&mut self, options: CodeFormatOptions) -> Vec<String> {
+		let mut warnings = vec![];
+		for (board_index, board) in self.boards.iter_mut().enumerate() {
+			for (status_index, status) in board.status_elements.iter_mut().enumerate() {
+				if let CodeSource::Owned(ref mut code) = status.code_source {
+					let (normalized_code, code_warnings) = normalize_code(code, options);
+					if normalized_code != *code {
+						*code = normalized_code;
+						// The old position may not be the start of a line any more.
+						if status.code_current_instruction > 0 {
+							status.code_current_instruction = 0;
+						}
+					}
+					warnings.extend(code_warnings.into_iter().map(|warning| format!("Board {} status {}: {}", board_index, status_index, warning)));
+				}
+			}
+		}
+		warnings
+	}
+}
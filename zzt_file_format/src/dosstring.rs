@@ -122,6 +122,11 @@ impl<'de> de::Deserialize<'de> for DosString {
     }
 }
 
+/// Get the unicode character that looks like the given DOS (code page 437) character.
+pub fn dos_char_to_char(c: u8) -> char {
+	CP437[c as usize]
+}
+
 pub fn char_to_dos_char(c: char) -> Option<u8> {
 	for dos_char in 0 .. CP437.len() {
 		let unicode = CP437[dos_char];
@@ -0,0 +1,102 @@
+use serde_derive::{Serialize, Deserialize};
+
+/// The red, green and blue values of the 16 colours that ZZT can show, as the EGA shows them by
+/// default.
+pub const STANDARD_COLOUR_RGB: [(u8, u8, u8); 16] = [
+	(0x00, 0x00, 0x00),
+	(0x00, 0x00, 0xAA),
+	(0x00, 0xAA, 0x00),
+	(0x00, 0xAA, 0xAA),
+	(0xAA, 0x00, 0x00),
+	(0xAA, 0x00, 0xAA),
+	(0xAA, 0x55, 0x00),
+	(0xAA, 0xAA, 0xAA),
+	(0x55, 0x55, 0x55),
+	(0x55, 0x55, 0xFF),
+	(0x55, 0xFF, 0x55),
+	(0x55, 0xFF, 0xFF),
+	(0xFF, 0x55, 0x55),
+	(0xFF, 0x55, 0xFF),
+	(0xFF, 0xFF, 0x55),
+	(0xFF, 0xFF, 0xFF),
+];
+
+/// A colour beyond the 16 that ZZT can show (see `ExtendedColours`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtendedColour {
+	/// One of the 256 colours of the xterm palette: the 16 standard colours, then a 6x6x6 cube of
+	/// colours, then 24 shades of grey.
+	Indexed(u8),
+	/// A 24-bit colour, as its red, green and blue values.
+	Rgb(u8, u8, u8),
+}
+
+impl ExtendedColour {
+	/// Get the (red, green, blue) values of the colour.
+	pub fn to_rgb(self) -> (u8, u8, u8) {
+		match self {
+			ExtendedColour::Indexed(index) => indexed_colour_rgb(index),
+			ExtendedColour::Rgb(red, green, blue) => (red, green, blue),
+		}
+	}
+
+	/// Get the index of the standard colour (0 to 15) that looks the most like this colour, for
+	/// showing it where only the standard colours can be used.
+	pub fn nearest_standard_colour(self) -> u8 {
+		nearest_colour_index(self.to_rgb(), &STANDARD_COLOUR_RGB)
+	}
+
+	/// Get the index of the standard colour (0 to 7) that looks the most like this colour, out of
+	/// the colours that can be used as a background.
+	pub fn nearest_background_colour(self) -> u8 {
+		nearest_colour_index(self.to_rgb(), &STANDARD_COLOUR_RGB[.. 8])
+	}
+}
+
+/// Get the (red, green, blue) values of colour `index` in the xterm 256 colour palette.
+fn indexed_colour_rgb(index: u8) -> (u8, u8, u8) {
+	match index {
+		0 ..= 15 => STANDARD_COLOUR_RGB[index as usize],
+		16 ..= 231 => {
+			let cube_index = index - 16;
+			let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+			(level(cube_index / 36), level(cube_index / 6 % 6), level(cube_index % 6))
+		}
+		_ => {
+			let grey = 8 + (index - 232) * 10;
+			(grey, grey, grey)
+		}
+	}
+}
+
+/// Get the index of the colour in `colours` that is closest to `rgb`.
+fn nearest_colour_index(rgb: (u8, u8, u8), colours: &[(u8, u8, u8)]) -> u8 {
+	let distance = |other: &(u8, u8, u8)| {
+		let difference = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+		difference(rgb.0, other.0) + difference(rgb.1, other.1) + difference(rgb.2, other.2)
+	};
+	colours.iter().enumerate().min_by_key(|(_, colour)| distance(colour)).map_or(0, |(index, _)| index as u8)
+}
+
+/// The extended colours of a status element, which RUZZT draws it with instead of the colour of its
+/// tile when its extensions are turned on. These aren't part of the world file format, so they are
+/// left out when a world is written (see `WriteWarningKind::FieldDropped`), and the tile's colour
+/// is shown instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedColours {
+	/// The colour the character is drawn with, or None to use the tile's foreground colour.
+	pub foreground: Option<ExtendedColour>,
+	/// The colour behind the character, or None to use the tile's background colour.
+	pub background: Option<ExtendedColour>,
+}
+
+impl ExtendedColours {
+	/// Get the ZZT colour attribute byte that looks the most like these colours, using the colours
+	/// from `fallback` (eg. the tile's colour) for any that aren't set. The blink bit of `fallback`
+	/// is kept.
+	pub fn to_standard_colour(self, fallback: u8) -> u8 {
+		let foreground = self.foreground.map_or(fallback & 0x0f, ExtendedColour::nearest_standard_colour);
+		let background = self.background.map_or((fallback >> 4) & 0x07, ExtendedColour::nearest_background_colour);
+		(fallback & 0x80) | (background << 4) | foreground
+	}
+}
@@ -1,6 +1,18 @@
+pub mod code_format;
 pub mod dosstring;
+pub mod extended_colour;
+pub mod merge;
+pub mod metadata;
+pub mod padding;
+pub mod params;
+pub mod rzt;
+pub mod world_parser;
+pub mod write_warnings;
 
 use crate::dosstring::DosString;
+use crate::extended_colour::ExtendedColours;
+use crate::padding::{ParseOptions, PaddingReader, PaddingWriter, RawPadding};
+use crate::write_warnings::WriteWarning;
 
 use serde_derive::{Serialize, Deserialize};
 use num_derive::FromPrimitive;
@@ -8,6 +20,8 @@ use num_derive::FromPrimitive;
 use num::FromPrimitive;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
+use std::io::Write;
+
 // See: http://www.shikadi.net/moddingwiki/ZZT_Format
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -81,6 +95,10 @@ impl Highscores {
 pub struct World {
 	pub world_header: WorldHeader,
 	pub boards: Vec<Board>,
+	/// Whatever came after the last board in the world file, if it was kept when the world was
+	/// read (see `ParseOptions::preserve_padding`). This is written back after the last board.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub trailing_data: Option<Vec<u8>>,
 }
 
 impl World {
@@ -88,33 +106,64 @@ impl World {
 		World {
 			world_header: WorldHeader::zzt_default(),
 			boards: vec![Board::zzt_default(DosString::from_slice(b"Title screen"))],
+			trailing_data: None,
 		}
 	}
 
+	/// Read a world file, or a world in the RZT container (see `rzt`).
+	/// See `world_parser::WorldParser` for reading a world a board at a time.
 	pub fn parse<S: std::io::Read + std::io::Seek>(stream: &mut S) -> Result<World, String> {
-		let world_header = WorldHeader::parse(stream).map_err(|e| format!("WorldHeader: {}", e))?;
+		world_parser::WorldParser::new(stream)?.finish()
+	}
 
-		let board_offset = match world_header.world_type {
-			WorldType::Zzt => 0x200,
-			WorldType::SuperZzt => 0x400,
-		};
+	/// Read a world like `parse`, with the given `options`.
+	///
+	/// Writing a world always puts the same bytes in the places that hold its data, so a world
+	/// read from a file made by DOS ZZT is written back exactly as it was. Without
+	/// `ParseOptions::preserve_padding`, the unused bytes between them are written as zeros, which
+	/// some files made by other tools don't have. With it, those bytes are kept in the world's
+	/// `RawPadding` fields (which are also kept when converting to and from JSON), so the world is
+	/// written back byte for byte, as long as it wasn't changed.
+	pub fn parse_with_options<S: std::io::Read + std::io::Seek>(stream: &mut S, options: ParseOptions) -> Result<World, String> {
+		world_parser::WorldParser::with_options(stream, options)?.finish()
+	}
 
-		stream.seek(std::io::SeekFrom::Start(board_offset)).map_err(|e| format!("Failed to seek to {}: {}", board_offset, e))?;
-		let mut boards = vec![];
-		for _ in 0 .. (world_header.num_boards_except_title + 1) {
-			let board = Board::parse(stream, world_header.world_type).map_err(|e| format!("Board: {}", e))?;
-			boards.push(board);
-		}
+	/// Write the world to `stream` in the ZZT or Super ZZT file format. Returns an error without
+	/// writing anything if the world can't be saved as it is (see `validate`). Use
+	/// `write_with_options` to find out about anything that was left out.
+	pub fn write(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		self.write_with_options(stream, WriteOptions::default()).map(|_| ())
+	}
 
-		Ok(World {
-			world_header,
-			boards,
-		})
+	/// Write the world to `stream` like `write`, with the given `options`. Returns a warning for
+	/// everything that was changed or left out to write the world (see `write_warnings`), which is
+	/// empty if reading the file back gives the same world.
+	pub fn write_with_options(&self, stream: &mut dyn std::io::Write, options: WriteOptions) -> Result<Vec<WriteWarning>, String> {
+		let world = if options.truncate || options.normalize_code {
+			let mut world = self.clone();
+			if options.truncate {
+				world.truncate_to_fit();
+			}
+			if options.normalize_code {
+				world.normalize_code(code_format::CodeFormatOptions::new());
+			}
+			std::borrow::Cow::Owned(world)
+		} else {
+			std::borrow::Cow::Borrowed(self)
+		};
+
+		world.validate()?;
+		// The whole file is put together before any of it is written, so a board that turns out to
+		// be too big doesn't leave half a file behind.
+		let mut file_data = vec![];
+		world.write_validated(&mut file_data)?;
+		stream.write_all(&file_data).map_err(|e| format!("Failed to write world: {}", e))?;
+		Ok(self.write_warnings(options))
 	}
 
-	pub fn write(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+	fn write_validated(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
 		let mut header_buf = vec![];
-		self.world_header.write(&mut header_buf).map_err(|e| format!("WorldHeader: {}", e))?;
+		let header_blob_count = self.world_header.write(&mut header_buf).map_err(|e| format!("WorldHeader: {}", e))?;
 		stream.write(&header_buf).map_err(|e| format!("Failed to write world header data: {}", e))?;
 
 		let board_offset = match self.world_header.world_type {
@@ -123,17 +172,144 @@ impl World {
 		};
 
 		let padding_count = board_offset - header_buf.len();
+		PaddingWriter::starting_at(self.world_header.padding.as_ref(), header_blob_count).write(stream, padding_count)?;
+
+		for (board_index, board) in self.boards.iter().enumerate() {
+			board.write(stream, self.world_header.world_type).map_err(|e| format!("Board {}: {}", board_index, e))?;
+		}
+
+		if let Some(ref trailing_data) = self.trailing_data {
+			stream.write_all(trailing_data).map_err(|e| format!("Failed to write trailing data: {}", e))?;
+		}
+
+		Ok(())
+	}
+
+	/// Check that the world can be written without producing an invalid file, for example because a
+	/// name is too long to fit in its field. The error says which board and field is the problem.
+	/// Whether each board fits in the maximum board size is only known once it is put together,
+	/// which `write` does before it writes anything.
+	pub fn validate(&self) -> Result<(), String> {
+		self.world_header.validate().map_err(|e| format!("WorldHeader: {}", e))?;
+
+		if self.boards.len() != self.world_header.num_boards_except_title as usize + 1 {
+			return Err(format!("WorldHeader: Number of boards is {} (expected {})", self.boards.len(), self.world_header.num_boards_except_title as i32 + 1));
+		}
+
+		for (board_index, board) in self.boards.iter().enumerate() {
+			board.validate(self.world_header.world_type).map_err(|e| format!("Board {}: {}", board_index, e))?;
+		}
+
+		Ok(())
+	}
+
+	/// Shorten the world name, flag names, board names and board messages that are too long to be
+	/// written, and add or remove flags at the end so there are the right number for the world
+	/// type, so they don't cause `validate` to fail.
+	pub fn truncate_to_fit(&mut self) {
+		self.world_header.world_name.data.truncate(MAX_WORLD_NAME_LEN);
+		let flag_names_count = self.world_header.flag_names_count();
+		self.world_header.flag_names.resize(flag_names_count, DosString::new());
+		for flag_name in &mut self.world_header.flag_names {
+			flag_name.data.truncate(MAX_FLAG_NAME_LEN);
+		}
 
-		for _ in 0 .. padding_count {
-			stream.write_u8(0).map_err(|e| format!("Failed to write padding: {}", e))?;
+		let max_board_name_len = max_board_name_len(self.world_header.world_type);
+		for board in &mut self.boards {
+			board.meta_data.board_name.data.truncate(max_board_name_len);
+			if let Some(ref mut message) = board.meta_data.message {
+				message.data.truncate(MAX_BOARD_MESSAGE_LEN);
+			}
+		}
+	}
+
+	/// Give every status element in the world that is bound to another status element's code (with
+	/// `#bind`) its own copy of that code. See `Board::materialize_bound_code`.
+	pub fn materialize_bound_code(&mut self) {
+		for board in &mut self.boards {
+			board.materialize_bound_code();
 		}
+	}
 
-		for board in &self.boards {
-			board.write(stream, self.world_header.world_type).map_err(|e| format!("Board: {}", e))?;
+	/// Insert `board` so it has the given `board_index`, moving the boards after it along by one.
+	/// The board exits, passage destinations and current board of the world are updated so they
+	/// still lead to the same boards as before, but the inserted board is left as it is. The title
+	/// board can't be moved, so `board_index` must be at least 1.
+	pub fn insert_board(&mut self, board_index: usize, board: Board) -> Result<(), String> {
+		if board_index == 0 || board_index > self.boards.len() {
+			return Err(format!("Can't insert a board at index {} (there are {} boards)", board_index, self.boards.len()));
+		}
+		if self.boards.len() > u8::MAX as usize {
+			return Err(format!("Can't have more than {} boards", u8::MAX as usize + 1));
 		}
 
+		self.remap_board_indices(|index| if index as usize >= board_index { index + 1 } else { index });
+		self.boards.insert(board_index, board);
+		self.world_header.num_boards_except_title += 1;
 		Ok(())
 	}
+
+	/// Remove the board with the given `board_index`, moving the boards after it back by one. The
+	/// board exits, passage destinations and current board of the world are updated so they still
+	/// lead to the same boards as before. Anything that led to the removed board leads to board 0
+	/// instead, which means there is no exit. The title board can't be removed.
+	pub fn remove_board(&mut self, board_index: usize) -> Result<Board, String> {
+		if board_index == 0 || board_index >= self.boards.len() {
+			return Err(format!("Can't remove board {} (there are {} boards)", board_index, self.boards.len()));
+		}
+
+		let board = self.boards.remove(board_index);
+		self.world_header.num_boards_except_title -= 1;
+		self.remap_board_indices(|index| match (index as usize).cmp(&board_index) {
+			std::cmp::Ordering::Less => index,
+			std::cmp::Ordering::Equal => 0,
+			std::cmp::Ordering::Greater => index - 1,
+		});
+		Ok(board)
+	}
+
+	/// Change every board index in the world (board exits, passage destinations and the current
+	/// board) with `remap`.
+	fn remap_board_indices(&mut self, remap: impl Fn(u8) -> u8) {
+		let world_type = self.world_header.world_type;
+		for board in &mut self.boards {
+			board.remap_board_indices(world_type, &remap);
+		}
+
+		if self.world_header.player_board >= 0 {
+			self.world_header.player_board = remap(self.world_header.player_board as u8) as i16;
+		}
+	}
+}
+
+/// Options for `World::write_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WriteOptions {
+	/// Shorten names and messages that are too long to be saved, and fix the number of flags,
+	/// instead of failing to write them (see `World::truncate_to_fit`).
+	pub truncate: bool,
+	/// Convert the line endings of all the object code to the `\r` that ZZT uses (see
+	/// `code_format::normalize_code`). Long lines are left as they are.
+	pub normalize_code: bool,
+}
+
+/// The file extension of standalone board files, which hold a single board (see
+/// `Board::parse_brd`).
+pub const BRD_FILE_EXTENSION: &str = "BRD";
+
+/// The longest world name that can be saved.
+pub const MAX_WORLD_NAME_LEN: usize = 20;
+/// The longest flag name that can be saved.
+pub const MAX_FLAG_NAME_LEN: usize = 20;
+/// The longest board message that can be saved (only ZZT boards have a message).
+pub const MAX_BOARD_MESSAGE_LEN: usize = 58;
+
+/// Get the longest board name that can be saved in a world of type `world_type`.
+pub fn max_board_name_len(world_type: WorldType) -> usize {
+	match world_type {
+		WorldType::Zzt => 50,
+		WorldType::SuperZzt => 60,
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -144,6 +320,24 @@ pub enum WorldType {
 	SuperZzt,
 }
 
+impl WorldType {
+	/// Get the extension of world files of this type, without the dot.
+	pub fn file_extension(&self) -> &'static str {
+		match self {
+			WorldType::Zzt => "ZZT",
+			WorldType::SuperZzt => "SZT",
+		}
+	}
+
+	/// Get the width and height of boards in worlds of this type, in tiles.
+	pub fn board_size(&self) -> (usize, usize) {
+		match self {
+			WorldType::Zzt => (60, 25),
+			WorldType::SuperZzt => (96, 80),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorldHeader {
 	pub world_type: WorldType,
@@ -174,6 +368,10 @@ pub struct WorldHeader {
 	pub locked: bool,
 	/// SZT only.
 	pub player_stones: Option<i16>,
+	/// The unused bytes of the header, including the bytes between the header and the first board,
+	/// if they were kept when the world was read (see `ParseOptions::preserve_padding`).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub padding: Option<RawPadding>,
 }
 
 impl WorldHeader {
@@ -196,10 +394,19 @@ impl WorldHeader {
 			time_passed_ticks: 0,
 			locked: false,
 			player_stones: None,
+			padding: None,
 		}
 	}
 
 	pub fn parse(stream: &mut dyn std::io::Read) -> Result<WorldHeader, String> {
+		WorldHeader::parse_with_options(stream, ParseOptions::default())
+	}
+
+	/// Read the header like `parse`, with the given `options`. This doesn't read the bytes between
+	/// the header and the first board.
+	pub fn parse_with_options(stream: &mut dyn std::io::Read, options: ParseOptions) -> Result<WorldHeader, String> {
+		let mut padding = PaddingReader::new(options.preserve_padding);
+
 		let world_type_num = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read world type: {}", e))?;
 		let world_type = match world_type_num {
 			-1 => WorldType::Zzt,
@@ -230,7 +437,7 @@ impl WorldHeader {
 				(Some(player_torches), Some(torch_cycles))
 			}
 			WorldType::SuperZzt => {
-				let _padding = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read padding: {}", e))?;
+				padding.read(stream, 2)?;
 				(None, None)
 			}
 		};
@@ -238,13 +445,13 @@ impl WorldHeader {
 		let (energy_cycles, player_score) = match world_type {
 			WorldType::Zzt => {
 				let energy_cycles = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read energy cycles: {}", e))?;
-				let _padding = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read padding: {}", e))?;
+				padding.read(stream, 2)?;
 				let player_score = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read player score: {}", e))?;
 				(energy_cycles, player_score)
 			}
 			WorldType::SuperZzt => {
 				let player_score = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read player score: {}", e))?;
-				let _padding = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read padding: {}", e))?;
+				padding.read(stream, 2)?;
 				let energy_cycles = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read energy cycles: {}", e))?;
 				(energy_cycles, player_score)
 			}
@@ -252,12 +459,16 @@ impl WorldHeader {
 
 		let world_name_len = stream.read_u8().map_err(|e| format!("Failed to read world name length: {}", e))?;
 		let mut world_name = DosString::new();
+		let mut world_name_tail = vec![];
 		for i in 0 .. 20 {
 			let c = stream.read_u8().map_err(|e| format!("Failed to read world name: {}", e))?;
 			if i < world_name_len {
 				world_name.push(c);
+			} else {
+				world_name_tail.push(c);
 			}
 		}
+		padding.keep(world_name_tail);
 
 		let mut flag_names = vec![];
 		let flag_names_count = match world_type {
@@ -267,12 +478,16 @@ impl WorldHeader {
 		for _ in 0 .. flag_names_count {
 			let flag_name_len = stream.read_u8().map_err(|e| format!("Failed to read flag name length: {}", e))?;
 			let mut flag_name = DosString::new();
+			let mut flag_name_tail = vec![];
 			for i in 0 .. 20 {
 				let c = stream.read_u8().map_err(|e| format!("Failed to read flag name: {}", e))?;
 				if i < flag_name_len {
 					flag_name.push(c);
+				} else {
+					flag_name_tail.push(c);
 				}
 			}
+			padding.keep(flag_name_tail);
 			flag_names.push(flag_name);
 		}
 
@@ -283,17 +498,13 @@ impl WorldHeader {
 
 		let player_stones = match world_type {
 			WorldType::Zzt => {
-				for _ in 0 .. 14 {
-					stream.read_u8().map_err(|e| format!("Failed to read padding bytes: {}", e))?;
-				}
+				padding.read(stream, 14)?;
 				None
 			}
 			WorldType::SuperZzt => {
 				let player_stones = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read player stones: {}", e))?;
 
-				for _ in 0 .. 11 {
-					stream.read_u8().map_err(|e| format!("Failed to read padding bytes: {}", e))?;
-				}
+				padding.read(stream, 11)?;
 				Some(player_stones)
 			}
 		};
@@ -316,10 +527,54 @@ impl WorldHeader {
 			time_passed_ticks,
 			locked,
 			player_stones,
+			padding: padding.finish(),
 		})
 	}
 
-	fn write(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+	fn flag_names_count(&self) -> usize {
+		match self.world_type {
+			WorldType::Zzt => 10,
+			WorldType::SuperZzt => 16,
+		}
+	}
+
+	/// Check that the header can be written without producing an invalid file.
+	pub fn validate(&self) -> Result<(), String> {
+		if self.world_name.len() > MAX_WORLD_NAME_LEN {
+			return Err(format!("World name is {} bytes long (max {})", self.world_name.len(), MAX_WORLD_NAME_LEN));
+		}
+
+		if self.flag_names.len() != self.flag_names_count() {
+			return Err(format!("Wrong number of flags: {} (expected {})", self.flag_names.len(), self.flag_names_count()));
+		}
+
+		for (flag_index, flag_name) in self.flag_names.iter().enumerate() {
+			if flag_name.len() > MAX_FLAG_NAME_LEN {
+				return Err(format!("Flag {} name is {} bytes long (max {})", flag_index, flag_name.len(), MAX_FLAG_NAME_LEN));
+			}
+		}
+
+		match self.world_type {
+			WorldType::Zzt => {
+				if self.player_torches.is_none() || self.torch_cycles.is_none() {
+					return Err("Player torches and torch cycles must be set for ZZT worlds".to_string());
+				}
+			}
+			WorldType::SuperZzt => {
+				if self.player_stones.is_none() {
+					return Err("Player stones must be set for Super ZZT worlds".to_string());
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Write the header, not including the bytes between the header and the first board. Returns
+	/// the number of blobs of `padding` that were for the header itself.
+	fn write(&self, stream: &mut dyn std::io::Write) -> Result<usize, String> {
+		let mut padding = PaddingWriter::new(self.padding.as_ref());
+
 		let world_type_num = match self.world_type {
 			WorldType::Zzt => -1,
 			WorldType::SuperZzt => -2,
@@ -347,37 +602,28 @@ impl WorldHeader {
 				stream.write_i16::<LittleEndian>(torch_cycles).map_err(|e| format!("Failed to write torch cycles: {}", e))?;
 			}
 			WorldType::SuperZzt => {
-				stream.write_i16::<LittleEndian>(0).map_err(|e| format!("Failed to write padding: {}", e))?;
+				padding.write(stream, 2)?;
 			}
 		};
 
 		match self.world_type {
 			WorldType::Zzt => {
 				stream.write_i16::<LittleEndian>(self.energy_cycles).map_err(|e| format!("Failed to write energy cycles: {}", e))?;
-				stream.write_i16::<LittleEndian>(0).map_err(|e| format!("Failed to write padding: {}", e))?;
+				padding.write(stream, 2)?;
 				stream.write_i16::<LittleEndian>(self.player_score).map_err(|e| format!("Failed to write player score: {}", e))?;
 			}
 			WorldType::SuperZzt => {
 				stream.write_i16::<LittleEndian>(self.player_score).map_err(|e| format!("Failed to write player score: {}", e))?;
-				stream.write_i16::<LittleEndian>(0).map_err(|e| format!("Failed to write padding: {}", e))?;
+				padding.write(stream, 2)?;
 				stream.write_i16::<LittleEndian>(self.energy_cycles).map_err(|e| format!("Failed to write energy cycles: {}", e))?;
 			}
 		}
 
 		stream.write_u8(self.world_name.len() as u8).map_err(|e| format!("Failed to write world name length: {}", e))?;
-		for i in 0 .. 20 {
-			let c = if i < self.world_name.len() {
-				self.world_name.data[i]
-			} else {
-				0
-			};
-			stream.write_u8(c).map_err(|e| format!("Failed to write world name: {}", e))?;
-		}
+		stream.write_all(&self.world_name.data).map_err(|e| format!("Failed to write world name: {}", e))?;
+		padding.write(stream, 20 - self.world_name.len())?;
 
-		let flag_names_count = match self.world_type {
-			WorldType::Zzt => 10,
-			WorldType::SuperZzt => 16,
-		};
+		let flag_names_count = self.flag_names_count();
 
 		if self.flag_names.len() != flag_names_count {
 			return Err(format!("Wrong number of flags: {} (expected {})", self.flag_names.len(), flag_names_count));
@@ -385,14 +631,8 @@ impl WorldHeader {
 
 		for flag_name in &self.flag_names {
 			stream.write_u8(flag_name.len() as u8).map_err(|e| format!("Failed to write flag name length: {}", e))?;
-			for i in 0 .. 20 {
-				let c = if i < flag_name.len() {
-					flag_name.data[i]
-				} else {
-					0
-				};
-				stream.write_u8(c).map_err(|e| format!("Failed to write flag name: {}", e))?;
-			}
+			stream.write_all(&flag_name.data).map_err(|e| format!("Failed to write flag name: {}", e))?;
+			padding.write(stream, 20 - flag_name.len())?;
 		}
 
 		stream.write_i16::<LittleEndian>(self.time_passed).map_err(|e| format!("Failed to write time passed: {}", e))?;
@@ -401,21 +641,17 @@ impl WorldHeader {
 
 		match self.world_type {
 			WorldType::Zzt => {
-				for _ in 0 .. 14 {
-					stream.write_u8(0).map_err(|e| format!("Failed to write padding bytes: {}", e))?;
-				}
+				padding.write(stream, 14)?;
 			}
 			WorldType::SuperZzt => {
 				let player_stones = self.player_stones.ok_or_else(|| format!("Can't write player stones: not set"))?;
 				stream.write_i16::<LittleEndian>(player_stones).map_err(|e| format!("Failed to write player stones: {}", e))?;
 
-				for _ in 0 .. 11 {
-					stream.write_u8(0).map_err(|e| format!("Failed to write padding bytes: {}", e))?;
-				}
+				padding.write(stream, 11)?;
 			}
 		};
 
-		Ok(())
+		Ok(padding.blobs_written())
 	}
 
 	pub fn first_empty_flag(&self) -> Option<usize> {
@@ -499,6 +735,140 @@ pub enum ElementType {
 	TextBlack,
 }
 
+/// The element IDs used in Super ZZT worlds. Most elements have the same ID as in ZZT, but Super
+/// ZZT drops some of them (eg. Torch and Water), moves others (eg. Bullet and Star), and adds its
+/// own elements.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(FromPrimitive)]
+#[repr(u8)]
+pub enum SuperZztElementType {
+	Empty = 0,
+	BoardEdge,
+	Messenger,
+	Monitor,
+	Player,
+	Ammo,
+	Gem = 7,
+	Key,
+	Door,
+	Scroll,
+	Passage,
+	Duplicator,
+	Bomb,
+	Energizer,
+	Clockwise = 16,
+	Counter,
+	Lava = 19,
+	Forest,
+	Solid,
+	Normal,
+	Breakable,
+	Boulder,
+	SliderNS,
+	SliderEW,
+	Fake,
+	Invisible,
+	BlinkWall,
+	Transporter,
+	Line,
+	Ricochet,
+	Bear = 34,
+	Ruffian,
+	Object,
+	Slime,
+	SpinningGun = 39,
+	Pusher,
+	Lion,
+	Tiger,
+	Head = 44,
+	Segment,
+	Floor = 47,
+	WaterN,
+	WaterS,
+	WaterW,
+	WaterE,
+	Roton = 59,
+	DragonPup,
+	Pairer,
+	Spider,
+	Web,
+	Stone,
+	Bullet = 69,
+	BlinkRayHorizontal,
+	BlinkRayVertical,
+	Star,
+	TextBlue,
+	TextGreen,
+	TextCyan,
+	TextRed,
+	TextPurple,
+	TextBrown,
+	TextBlack,
+}
+
+impl SuperZztElementType {
+	/// Get the ZZT element that this element is the same as, or None if it only exists in Super
+	/// ZZT. Note that the returned element's ID isn't always the same as this element's ID.
+	pub fn to_element_type(self) -> Option<ElementType> {
+		use self::SuperZztElementType::*;
+		let element_type = match self {
+			Empty => ElementType::Empty,
+			BoardEdge => ElementType::BoardEdge,
+			Messenger => ElementType::Messenger,
+			Monitor => ElementType::Monitor,
+			Player => ElementType::Player,
+			Ammo => ElementType::Ammo,
+			Gem => ElementType::Gem,
+			Key => ElementType::Key,
+			Door => ElementType::Door,
+			Scroll => ElementType::Scroll,
+			Passage => ElementType::Passage,
+			Duplicator => ElementType::Duplicator,
+			Bomb => ElementType::Bomb,
+			Energizer => ElementType::Energizer,
+			Clockwise => ElementType::Clockwise,
+			Counter => ElementType::Counter,
+			Forest => ElementType::Forest,
+			Solid => ElementType::Solid,
+			Normal => ElementType::Normal,
+			Breakable => ElementType::Breakable,
+			Boulder => ElementType::Boulder,
+			SliderNS => ElementType::SliderNS,
+			SliderEW => ElementType::SliderEW,
+			Fake => ElementType::Fake,
+			Invisible => ElementType::Invisible,
+			BlinkWall => ElementType::BlinkWall,
+			Transporter => ElementType::Transporter,
+			Line => ElementType::Line,
+			Ricochet => ElementType::Ricochet,
+			Bear => ElementType::Bear,
+			Ruffian => ElementType::Ruffian,
+			Object => ElementType::Object,
+			Slime => ElementType::Slime,
+			SpinningGun => ElementType::SpinningGun,
+			Pusher => ElementType::Pusher,
+			Lion => ElementType::Lion,
+			Tiger => ElementType::Tiger,
+			Head => ElementType::Head,
+			Segment => ElementType::Segment,
+			Bullet => ElementType::Bullet,
+			BlinkRayHorizontal => ElementType::BlinkRayHorizontal,
+			BlinkRayVertical => ElementType::BlinkRayVertical,
+			Star => ElementType::Star,
+			TextBlue => ElementType::TextBlue,
+			TextGreen => ElementType::TextGreen,
+			TextCyan => ElementType::TextCyan,
+			TextRed => ElementType::TextRed,
+			TextPurple => ElementType::TextPurple,
+			TextBrown => ElementType::TextBrown,
+			TextBlack => ElementType::TextBlack,
+			Lava | Floor | WaterN | WaterS | WaterW | WaterE | Roton | DragonPup | Pairer | Spider
+			| Web | Stone => return None,
+		};
+		Some(element_type)
+	}
+}
+
 /// Turn element IDs into strings that are either an entry from ElementType, or a stringified number
 /// if there is no corresponding entry in the enum.
 mod element_id_serde {
@@ -584,6 +954,10 @@ pub struct BoardMetaData {
 	pub camera_y: Option<i16>,
 	/// The time limit of the board, in seconds.
 	pub time_limit: i16,
+	/// The unused bytes of the board's properties, if they were kept when the board was read (see
+	/// `ParseOptions::preserve_padding`).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub padding: Option<RawPadding>,
 }
 
 impl Default for BoardMetaData {
@@ -603,6 +977,7 @@ impl Default for BoardMetaData {
 			camera_x: None,
 			camera_y: None,
 			time_limit: 0,
+			padding: None,
 		}
 	}
 }
@@ -634,9 +1009,27 @@ impl Default for Board {
 }
 
 impl Board {
+	/// Change the board exits and passage destinations of the board with `remap`.
+	pub(crate) fn remap_board_indices(&mut self, world_type: WorldType, remap: impl Fn(u8) -> u8) {
+		let meta_data = &mut self.meta_data;
+		meta_data.exit_north = remap(meta_data.exit_north);
+		meta_data.exit_south = remap(meta_data.exit_south);
+		meta_data.exit_west = remap(meta_data.exit_west);
+		meta_data.exit_east = remap(meta_data.exit_east);
+
+		let passage_indices: Vec<usize> = (0 .. self.status_elements.len())
+			.filter(|&status_index| self.status_is_passage(status_index, world_type))
+			.collect();
+		for status_index in passage_indices {
+			let status = &mut self.status_elements[status_index];
+			status.param3 = remap(status.param3);
+		}
+	}
+
 	pub fn zzt_default(name: DosString) -> Board {
 		let mut board = Board::default();
 		board.meta_data.board_name = name;
+		board.meta_data.message = Some(DosString::new());
 
 		board.status_elements.push(StatusElement {
 			location_x: 30,
@@ -664,25 +1057,158 @@ impl Board {
 		board
 	}
 
+	/// Get the type of the element under the status element at `status_index` on a board from a
+	/// world of the given `world_type`, or an error if there is no such status element or it isn't
+	/// on a known element. Super ZZT elements are given as the ZZT element they are the same as.
+	pub fn status_element_type(&self, status_index: usize, world_type: WorldType) -> Result<ElementType, String> {
+		let (board_width, _) = world_type.board_size();
+		let status = self.status_elements.get(status_index)
+			.ok_or_else(|| format!("There is no status element {}", status_index))?;
+		// Status element locations are 1-based.
+		let (x, y) = (status.location_x as usize, status.location_y as usize);
+		let tile = if x >= 1 && y >= 1 && x <= board_width {
+			self.tiles.get((y - 1) * board_width + (x - 1))
+		} else {
+			None
+		}.ok_or_else(|| format!("Status element {} is outside the board", status_index))?;
+		let element_type = match world_type {
+			WorldType::Zzt => ElementType::from_u8(tile.element_id),
+			WorldType::SuperZzt => SuperZztElementType::from_u8(tile.element_id).and_then(|ty| ty.to_element_type()),
+		};
+		element_type.ok_or_else(|| format!("Status element {} is on an unknown element", status_index))
+	}
+
+	/// Get the code that the status element at `status_index` runs, following `#bind`s to the
+	/// status element that owns the code. Returns None if there is no status element at the index,
+	/// or if the bindings don't lead to any code (eg. they loop back on themselves).
+	pub fn status_code(&self, status_index: usize) -> Option<&DosString> {
+		let mut current_index = status_index;
+		// A chain of bindings can't be longer than the number of status elements, unless it loops.
+		for _ in 0 ..= self.status_elements.len() {
+			match self.status_elements.get(current_index)?.code_source {
+				CodeSource::Owned(ref code) => return Some(code),
+				CodeSource::Bound(bound_index) => current_index = bound_index,
+			}
+		}
+		None
+	}
+
+	/// True if the status element at `status_index` is on a passage, in which case its `param3` is
+	/// the board the passage leads to.
+	pub fn status_is_passage(&self, status_index: usize, world_type: WorldType) -> bool {
+		let passage_id = match world_type {
+			WorldType::Zzt => ElementType::Passage as u8,
+			WorldType::SuperZzt => SuperZztElementType::Passage as u8,
+		};
+		let (board_width, _) = world_type.board_size();
+		let status = match self.status_elements.get(status_index) {
+			Some(status) => status,
+			None => return false,
+		};
+		// Status locations are 1-based.
+		let (x, y) = (status.location_x as usize, status.location_y as usize);
+		x >= 1 && y >= 1 && x <= board_width && self.tiles.get((y - 1) * board_width + x - 1).map(|tile| tile.element_id) == Some(passage_id)
+	}
+
+	/// Get the 1-based location of the first player tile on the board, or the first monitor tile
+	/// (which is where the player is on title boards) if there are no player tiles.
+	pub fn player_tile_location(&self, world_type: WorldType) -> Option<(u8, u8)> {
+		let (board_width, _) = world_type.board_size();
+		// The player and monitor have the same element IDs in ZZT and Super ZZT.
+		let tile_index_of = |element_type: ElementType| self.tiles.iter().position(|tile| tile.element_id == element_type as u8);
+		let tile_index = tile_index_of(ElementType::Player).or_else(|| tile_index_of(ElementType::Monitor))?;
+		Some(((tile_index % board_width + 1) as u8, (tile_index / board_width + 1) as u8))
+	}
+
+	/// Get the index of the status element that is the player. Like DOS ZZT, this is always status
+	/// element 0, even on broken boards where it isn't on a player tile. Returns None if the board
+	/// has no status elements.
+	pub fn player_status_index(&self) -> Option<usize> {
+		if self.status_elements.is_empty() {
+			None
+		} else {
+			Some(0)
+		}
+	}
+
+	/// Add a status element for the player to a board that doesn't have any, so it can be played
+	/// and written. DOS ZZT plays these boards with the player's status element left over from the
+	/// last board. The status is put on the `player_tile_location`, or in the middle of the board if
+	/// there isn't one (the engine puts a player or monitor tile under it when the board is played).
+	/// Returns true if a status element was added.
+	pub fn add_missing_player_status(&mut self, world_type: WorldType) -> bool {
+		if !self.status_elements.is_empty() {
+			return false;
+		}
+
+		let (board_width, board_height) = world_type.board_size();
+		let (x, y) = self.player_tile_location(world_type).unwrap_or((board_width as u8 / 2, board_height as u8 / 2));
+		self.status_elements.push(StatusElement {
+			location_x: x,
+			location_y: y,
+			.. StatusElement::default()
+		});
+		true
+	}
+
+	/// Give every status element that is bound to another status element's code (with `#bind`) its
+	/// own copy of that code, so status elements can be removed or reordered without worrying
+	/// about the bound indices. Bindings that don't lead to any code become empty code.
+	pub fn materialize_bound_code(&mut self) {
+		let codes: Vec<DosString> = (0 .. self.status_elements.len())
+			.map(|status_index| self.status_code(status_index).cloned().unwrap_or_else(DosString::new))
+			.collect();
+		for (status, code) in self.status_elements.iter_mut().zip(codes) {
+			if let CodeSource::Bound(_) = status.code_source {
+				status.code_source = CodeSource::Owned(code);
+			}
+		}
+	}
+
+	/// Read a standalone board file (`.BRD`, see `BRD_FILE_EXTENSION`) for a world of type
+	/// `world_type`. These hold a single board exactly as it is stored in a world file.
+	pub fn parse_brd(stream: &mut dyn std::io::Read, world_type: WorldType) -> Result<Board, String> {
+		Board::parse(stream, world_type)
+	}
+
+	/// Write the board to `stream` as a standalone board file (`.BRD`) for a world of type
+	/// `world_type`, which editors like KevEdit can import. Returns an error without writing
+	/// anything if the board can't be saved as it is (see `validate`).
+	pub fn write_brd(&self, stream: &mut dyn std::io::Write, world_type: WorldType) -> Result<(), String> {
+		self.validate(world_type)?;
+		self.write(stream, world_type)
+	}
+
 	pub fn parse(stream: &mut dyn std::io::Read, world_type: WorldType) -> Result<Board, String> {
+		Board::parse_with_options(stream, world_type, ParseOptions::default())
+	}
+
+	/// Read a board like `parse`, with the given `options`.
+	pub fn parse_with_options(stream: &mut dyn std::io::Read, world_type: WorldType, options: ParseOptions) -> Result<Board, String> {
 		// Board header:
 		let board_size = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read board size: {}", e))?;
+		if board_size < 0 {
+			return Err("Board size can't be less than 0".into());
+		}
+		Board::parse_body(stream, world_type, options)
+	}
+
+	/// Read everything in a board after its size.
+	fn parse_body(stream: &mut dyn std::io::Read, world_type: WorldType, options: ParseOptions) -> Result<Board, String> {
+		let mut padding = PaddingReader::new(options.preserve_padding);
+
 		let board_name_len = stream.read_u8().map_err(|e| format!("Failed to read board name length: {}", e))?;
 		let mut board_name = DosString::new();
-		let max_board_name_bytes = match world_type {
-			WorldType::Zzt => 50,
-			WorldType::SuperZzt => 60,
-		};
-		for i in 0 .. max_board_name_bytes {
+		let mut board_name_tail = vec![];
+		for i in 0 .. max_board_name_len(world_type) as u8 {
 			let c = stream.read_u8().map_err(|e| format!("Failed to read board name: {}", e))?;
 			if i < board_name_len {
 				board_name.push(c);
+			} else {
+				board_name_tail.push(c);
 			}
 		}
-
-		if board_size < 0 {
-			return Err("Board size can't be less than 0".into());
-		}
+		padding.keep(board_name_tail);
 
 		let tile_count = match world_type {
 			WorldType::Zzt => 60 * 25,
@@ -730,12 +1256,16 @@ impl Board {
 			WorldType::Zzt => {
 				let message_len = stream.read_u8().map_err(|e| format!("Failed to read message length: {}", e))?;
 				let mut message = DosString::new();
+				let mut message_tail = vec![];
 				for i in 0 .. 58 {
 					let c = stream.read_u8().map_err(|e| format!("Failed to read message: {}", e))?;
 					if i < message_len {
 						message.push(c);
+					} else {
+						message_tail.push(c);
 					}
 				}
+				padding.keep(message_tail);
 				Some(message)
 			}
 			WorldType::SuperZzt => {
@@ -770,19 +1300,21 @@ impl Board {
 			WorldType::Zzt => 16,
 			WorldType::SuperZzt => 14,
 		};
-		for _ in 0 .. padding_byte_count {
-			let _padding_byte = stream.read_u8().map_err(|e| format!("Failed to read padding bytes: {}", e))?;
-		}
+		padding.read(stream, padding_byte_count)?;
 
 		let stat_element_count_minus_one = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read status element count: {}", e))?;
+		if stat_element_count_minus_one < 0 && !options.lenient {
+			return Err(format!("Can't have less than 1 status element (the count is {})", stat_element_count_minus_one as i32 + 1));
+		}
 
 		let mut status_elements = vec![];
-		for _ in 0 .. (stat_element_count_minus_one + 1) {
-			let status_element = StatusElement::parse(stream, world_type).map_err(|e| format!("StatusElement: {}", e))?;
+		// This is done as an i32 because the count can be 32767 in a broken file.
+		for _ in 0 .. (stat_element_count_minus_one as i32 + 1) {
+			let status_element = StatusElement::parse(stream, world_type, options).map_err(|e| format!("StatusElement: {}", e))?;
 			status_elements.push(status_element);
 		}
 
-		Ok(Board {
+		let mut board = Board {
 			tiles,
 			status_elements,
 			meta_data: BoardMetaData {
@@ -800,29 +1332,93 @@ impl Board {
 				camera_x,
 				camera_y,
 				time_limit,
+				padding: padding.finish(),
 			}
-		})
+		};
+		if options.lenient {
+			board.add_missing_player_status(world_type);
+		}
+		Ok(board)
+	}
+
+	/// Check that the board can be written to a world of type `world_type` without producing an
+	/// invalid file. This doesn't check that the board fits in the maximum board size, which is only
+	/// known once it is written.
+	pub fn validate(&self, world_type: WorldType) -> Result<(), String> {
+		let max_board_name_len = max_board_name_len(world_type);
+		if self.meta_data.board_name.len() > max_board_name_len {
+			return Err(format!("Board name is {} bytes long (max {})", self.meta_data.board_name.len(), max_board_name_len));
+		}
+
+		match world_type {
+			WorldType::Zzt => {
+				let message = self.meta_data.message.as_ref().ok_or_else(|| "Message must be set for ZZT boards".to_string())?;
+				if message.len() > MAX_BOARD_MESSAGE_LEN {
+					return Err(format!("Message is {} bytes long (max {})", message.len(), MAX_BOARD_MESSAGE_LEN));
+				}
+			}
+			WorldType::SuperZzt => {
+				if self.meta_data.camera_x.is_none() || self.meta_data.camera_y.is_none() {
+					return Err("Camera position must be set for Super ZZT boards".to_string());
+				}
+			}
+		}
+
+		let tile_count = match world_type {
+			WorldType::Zzt => 60 * 25,
+			WorldType::SuperZzt => 96 * 80,
+		};
+		if self.tiles.len() != tile_count {
+			return Err(format!("Wrong number of tiles: {} (expected {})", self.tiles.len(), tile_count));
+		}
+
+		if self.status_elements.is_empty() {
+			return Err("Can't have less than 1 status element".to_string());
+		} else if self.status_elements.len() > i16::MAX as usize {
+			return Err(format!("Can't have more than {} status elements", i16::MAX));
+		}
+
+		for (status_index, status_element) in self.status_elements.iter().enumerate() {
+			match status_element.code_source {
+				CodeSource::Owned(ref code) => {
+					if code.len() > i16::MAX as usize {
+						return Err(format!("StatusElement {}: Code is {} bytes long (max {})", status_index, code.len(), i16::MAX));
+					}
+				}
+				CodeSource::Bound(bound_index) => {
+					if bound_index >= self.status_elements.len() {
+						return Err(format!("StatusElement {}: Bound to status element {} which doesn't exist", status_index, bound_index));
+					}
+				}
+			}
+		}
+
+		Ok(())
 	}
 
 	fn write(&self, final_stream: &mut dyn std::io::Write, world_type: WorldType) -> Result<(), String> {
 		// Need to buffer the whole board before writing it so the board_size can be calculated then
 		// written out first:
+		let stream = self.write_body(world_type)?;
+		if stream.len() > i16::MAX as usize {
+			return Err(format!("Board is {} bytes long (max {})", stream.len(), i16::MAX));
+		}
+
+		final_stream.write_i16::<LittleEndian>(stream.len() as i16).map_err(|e| format!("Failed to write board size: {}", e))?;
+		final_stream.write(&stream).map_err(|e| format!("Failed to write board data: {}", e))?;
+
+		Ok(())
+	}
+
+	/// Get everything in the board after its size, as it is written to a file.
+	fn write_body(&self, world_type: WorldType) -> Result<Vec<u8>, String> {
 		let mut stream = vec![];
 
-		stream.write_u8(self.meta_data.board_name.len() as u8).map_err(|e| format!("Failed to write board name length: {}", e))?;
+		let mut padding = PaddingWriter::new(self.meta_data.padding.as_ref());
 
-		let max_board_name_bytes = match world_type {
-			WorldType::Zzt => 50,
-			WorldType::SuperZzt => 60,
-		};
-		for i in 0 .. max_board_name_bytes {
-			let c = if i < self.meta_data.board_name.len() {
-				self.meta_data.board_name.data[i]
-			} else {
-				0
-			};
-			stream.write_u8(c).map_err(|e| format!("Failed to write board name: {}", e))?;
-		}
+		stream.write_u8(self.meta_data.board_name.len() as u8).map_err(|e| format!("Failed to write board name length: {}", e))?;
+		stream.write_all(&self.meta_data.board_name.data).map_err(|e| format!("Failed to write board name: {}", e))?;
+		padding.write(&mut stream, max_board_name_len(world_type) - self.meta_data.board_name.len())?;
 
 		let tile_count = match world_type {
 			WorldType::Zzt => 60 * 25,
@@ -897,15 +1493,9 @@ impl Board {
 			WorldType::Zzt => {
 				let message = self.meta_data.message.as_ref().ok_or_else(|| format!("Can't write message: not set"))?;
 
-				stream.write_u8(message.len() as u8).map_err(|e| format!("Failed to write world name length: {}", e))?;
-				for i in 0 .. 58 {
-					let c = if i < message.len() {
-						message.data[i]
-					} else {
-						0
-					};
-					stream.write_u8(c).map_err(|e| format!("Failed to write message: {}", e))?;
-				}
+				stream.write_u8(message.len() as u8).map_err(|e| format!("Failed to write message length: {}", e))?;
+				stream.write_all(&message.data).map_err(|e| format!("Failed to write message: {}", e))?;
+				padding.write(&mut stream, 58 - message.len())?;
 			}
 			WorldType::SuperZzt => {}
 		}
@@ -935,9 +1525,7 @@ impl Board {
 			WorldType::Zzt => 16,
 			WorldType::SuperZzt => 14,
 		};
-		for _ in 0 .. padding_byte_count {
-			stream.write_u8(0).map_err(|e| format!("Failed to write padding bytes: {}", e))?;
-		}
+		padding.write(&mut stream, padding_byte_count)?;
 
 		if self.status_elements.len() < 1 {
 			return Err(format!("Can't have less than 1 status element"));
@@ -951,16 +1539,7 @@ impl Board {
 			status_element.write(&mut stream, world_type).map_err(|e| format!("StatusElement: {}", e))?;
 		}
 
-		// Now write out the board size and content:
-
-		if self.status_elements.len() > i16::max_value() as usize {
-			return Err(format!("Can't have board size greater than than than {}", i16::max_value()));
-		}
-
-		final_stream.write_i16::<LittleEndian>(stream.len() as i16).map_err(|e| format!("Failed to write board size: {}", e))?;
-		final_stream.write(&stream).map_err(|e| format!("Failed to write board data: {}", e))?;
-
-		Ok(())
+		Ok(stream)
 	}
 }
 
@@ -1008,10 +1587,22 @@ pub struct StatusElement {
 	// This becomes -1 when an error returns, so the program stops running.
 	pub code_current_instruction: i16,
 	pub code_source: CodeSource,
+	/// The unused bytes of the status element, including the code pointer that DOS ZZT only uses
+	/// while the world is loaded, if they were kept when the board was read (see
+	/// `ParseOptions::preserve_padding`).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub padding: Option<RawPadding>,
+	/// The colours the status is drawn with when RUZZT's extensions are turned on, which can be
+	/// any of 256 indexed colours or a 24-bit colour. These aren't written to world files (see
+	/// `ExtendedColours`).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub extended_colours: Option<ExtendedColours>,
 }
 
 impl StatusElement {
-	fn parse(stream: &mut dyn std::io::Read, world_type: WorldType) -> Result<StatusElement, String> {
+	fn parse(stream: &mut dyn std::io::Read, world_type: WorldType, options: ParseOptions) -> Result<StatusElement, String> {
+		let mut padding = PaddingReader::new(options.preserve_padding);
+
 		let location_x = stream.read_u8().map_err(|e| format!("Failed to read X location: {}", e))?;
 		let location_y = stream.read_u8().map_err(|e| format!("Failed to read Y location: {}", e))?;
 
@@ -1025,22 +1616,20 @@ impl StatusElement {
 		let leader = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read leader: {}", e))?;
 		let under_element_id = stream.read_u8().map_err(|e| format!("Failed to read under ID: {}", e))?;
 		let under_colour = stream.read_u8().map_err(|e| format!("Failed to read under colour: {}", e))?;
-		let _internal_code_pointer = stream.read_i32::<LittleEndian>().map_err(|e| format!("Failed to read internal code pointer: {}", e))?;
+		padding.read(stream, 4).map_err(|e| format!("Failed to read internal code pointer: {}", e))?;
 		let code_current_instruction = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read current code instruction: {}", e))?;
 		let code_length = stream.read_i16::<LittleEndian>().map_err(|e| format!("Failed to read code length: {}", e))?;
 
 		match world_type {
 			WorldType::Zzt => {
-				for _ in 0 .. 8 {
-					let _padding_byte = stream.read_u8().map_err(|e| format!("Failed to read padding bytes: {}", e))?;
-				}
+				padding.read(stream, 8)?;
 			}
 			_ => {}
 		}
 
 		let code_source;
 		if code_length < 0 {
-			code_source = CodeSource::Bound((-code_length) as usize);
+			code_source = CodeSource::Bound(code_length.unsigned_abs() as usize);
 		} else {
 			let mut code = DosString::new();
 			for _ in 0 .. code_length {
@@ -1065,10 +1654,14 @@ impl StatusElement {
 			under_colour,
 			code_current_instruction,
 			code_source,
+			padding: padding.finish(),
+			extended_colours: None,
 		})
 	}
 
 	fn write(&self, stream: &mut dyn std::io::Write, world_type: WorldType) -> Result<(), String> {
+		let mut padding = PaddingWriter::new(self.padding.as_ref());
+
 		stream.write_u8(self.location_x).map_err(|e| format!("Failed to write X location: {}", e))?;
 		stream.write_u8(self.location_y).map_err(|e| format!("Failed to write Y location: {}", e))?;
 		stream.write_i16::<LittleEndian>(self.step_x).map_err(|e| format!("Failed to write X step: {}", e))?;
@@ -1081,15 +1674,13 @@ impl StatusElement {
 		stream.write_i16::<LittleEndian>(self.leader).map_err(|e| format!("Failed to write leader: {}", e))?;
 		stream.write_u8(self.under_element_id).map_err(|e| format!("Failed to write under ID: {}", e))?;
 		stream.write_u8(self.under_colour).map_err(|e| format!("Failed to write under colour: {}", e))?;
-		stream.write_i32::<LittleEndian>(0).map_err(|e| format!("Failed to write pointer: {}", e))?;
+		padding.write(stream, 4).map_err(|e| format!("Failed to write pointer: {}", e))?;
 		stream.write_i16::<LittleEndian>(self.code_current_instruction).map_err(|e| format!("Failed to write current code instruction: {}", e))?;
 		stream.write_i16::<LittleEndian>(self.code_source.get_save_code_length()).map_err(|e| format!("Failed to write code length: {}", e))?;
 
 		match world_type {
 			WorldType::Zzt => {
-				for _ in 0 .. 8 {
-					stream.write_u8(0).map_err(|e| format!("Failed to write padding bytes: {}", e))?;
-				}
+				padding.write(stream, 8)?;
 			}
 			_ => {}
 		}
@@ -1102,6 +1693,26 @@ impl StatusElement {
 
 		Ok(())
 	}
+
+	/// Get the value of the parameter with the given `name` (see `params::param_fields`) for a
+	/// status on an element of type `element_type`, or None if that type has no such parameter.
+	pub fn param_value(&self, element_type: ElementType, name: &str) -> Option<u8> {
+		params::param_field(element_type, name).map(|field| field.get(self))
+	}
+
+	/// Set the value of the parameter with the given `name` for a status on an element of type
+	/// `element_type`.
+	pub fn set_param_value(&mut self, element_type: ElementType, name: &str, value: u8) -> Result<(), String> {
+		let field = params::param_field(element_type, name)
+			.ok_or_else(|| format!("{:?} has no parameter called {}", element_type, name))?;
+		field.set(self, value)
+	}
+
+	/// Get the name and value of every parameter used by statuses on elements of type
+	/// `element_type`.
+	pub fn named_params(&self, element_type: ElementType) -> Vec<(&'static str, u8)> {
+		params::param_fields(element_type).iter().map(|field| (field.name, field.get(self))).collect()
+	}
 }
 
 impl Default for StatusElement {
@@ -1121,6 +1732,8 @@ impl Default for StatusElement {
 			under_colour: 0,
 			code_current_instruction: 0,
 			code_source: CodeSource::Owned(DosString::new()),
+			padding: None,
+			extended_colours: None,
 		}
 	}
 }
@@ -1131,6 +1744,7 @@ mod tests {
 
 	use std::path::Path;
 	use std::io::Cursor;
+	use crate::params::param_fields;
 
 	#[test] fn basic_save_load() {
 		let zzt_file_path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/BASIC.ZZT"));
@@ -1146,4 +1760,324 @@ mod tests {
 
 		assert_eq!(world, world_reloaded);
 	}
+
+	#[test] fn named_params() {
+		let mut tiger = StatusElement {
+			param1: 3,
+			param2: 0x85,
+			.. StatusElement::default()
+		};
+
+		assert_eq!(tiger.named_params(ElementType::Tiger), vec![("intelligence", 3), ("firing_rate", 5), ("fires_stars", 1)]);
+		tiger.set_param_value(ElementType::Tiger, "fires_stars", 0).unwrap();
+		assert_eq!(tiger.param2, 0x05);
+		assert!(tiger.set_param_value(ElementType::Tiger, "intelligence", 9).is_err());
+		assert_eq!(tiger.param_value(ElementType::Lion, "firing_rate"), None);
+	}
+
+	#[test] fn status_element_type() {
+		// Super ZZT boards are 96 tiles wide, and their stars have a different ID to ZZT's.
+		let (width, height) = WorldType::SuperZzt.board_size();
+		let mut board = Board {
+			tiles: vec![BoardTile { element_id: 0, colour: 0 }; width * height],
+			.. Board::default()
+		};
+		board.tiles[2 * width + 80].element_id = SuperZztElementType::Star as u8;
+		board.status_elements.push(StatusElement {
+			location_x: 81,
+			location_y: 3,
+			.. StatusElement::default()
+		});
+		assert_eq!(board.status_element_type(0, WorldType::SuperZzt), Ok(ElementType::Star));
+		assert!(board.status_element_type(0, WorldType::Zzt).is_err());
+		assert_eq!(param_fields(ElementType::Star).iter().map(|field| field.name).collect::<Vec<_>>(), vec!["shot_by_enemy", "time_left"]);
+	}
+
+	#[test] fn normalize_code_line_endings() {
+		use crate::code_format::{normalize_code, CodeFormatOptions};
+
+		let code = DosString::from_slice(b"@Bob\r\n#end\nHello\r");
+		let (normalized, warnings) = normalize_code(&code, CodeFormatOptions::new());
+		assert_eq!(normalized.data, b"@Bob\r#end\rHello\r".to_vec());
+		assert!(warnings.is_empty());
+
+		let long_text = [&b"$"[..], &[b'a'; 30], b" ", &[b'b'; 30]].concat();
+		let long_command = [&b"#send "[..], &[b'c'; 50]].concat();
+		let code = DosString::from_slice(&[&long_text[..], b"\n", &long_command].concat());
+		let (_, warnings) = normalize_code(&code, CodeFormatOptions::new());
+		assert_eq!(warnings, vec!["Line 1 is 62 characters long (max 50)", "Line 2 is 56 characters long (max 50)"]);
+
+		let options = CodeFormatOptions {
+			wrap_long_lines: true,
+			.. CodeFormatOptions::new()
+		};
+		let (wrapped, warnings) = normalize_code(&code, options);
+		let expected = [&b"$"[..], &[b'a'; 30], b"\r$", &[b'b'; 30], b"\r", &long_command].concat();
+		assert_eq!(wrapped.data, expected);
+		assert_eq!(warnings, vec!["Line 3 is 56 characters long (max 50)"]);
+	}
+
+	#[test] fn write_validation() {
+		let mut world = World::zzt_default();
+		world.boards[0].meta_data.board_name = DosString::from_slice(&[b'a'; 60]);
+
+		let mut out_buf = vec![];
+		let err = world.write(&mut out_buf).unwrap_err();
+		assert_eq!(err, "Board 0: Board name is 60 bytes long (max 50)");
+		assert!(out_buf.is_empty());
+
+		world.write_with_options(&mut out_buf, WriteOptions { truncate: true, .. WriteOptions::default() }).unwrap();
+		let world_reloaded = World::parse(&mut Cursor::new(out_buf.as_slice())).unwrap();
+		assert_eq!(world_reloaded.boards[0].meta_data.board_name.len(), 50);
+
+		world.world_header.num_boards_except_title = 1;
+		assert_eq!(world.validate().unwrap_err(), "WorldHeader: Number of boards is 1 (expected 2)");
+		world.world_header.num_boards_except_title = 0;
+		world.boards[0].meta_data.board_name = DosString::from_slice(b"Big");
+
+		// The board is only found to be too big once it is put together, but nothing is written.
+		for x in 1 ..= 2 {
+			world.boards[0].status_elements.push(StatusElement {
+				location_x: x,
+				location_y: 1,
+				code_source: CodeSource::Owned(DosString::from_slice(&[b'a'; 20000])),
+				.. StatusElement::default()
+			});
+		}
+		let mut out_buf = vec![];
+		assert!(world.write(&mut out_buf).unwrap_err().starts_with("Board 0: Board is "));
+		assert!(out_buf.is_empty());
+	}
+
+	#[test] fn write_warnings() {
+		let mut world = World::zzt_default();
+		world.world_header.flag_names.pop();
+		world.boards[0].meta_data.board_name = DosString::from_slice(&[b'a'; 60]);
+		world.boards[0].meta_data.camera_x = Some(5);
+		world.boards[0].status_elements[0].code_source = CodeSource::Owned(DosString::from_slice(b"@thing\n#end"));
+
+		let options = WriteOptions { truncate: true, normalize_code: true };
+		let mut out_buf = vec![];
+		let warnings = world.write_with_options(&mut out_buf, options).unwrap();
+		let kinds: Vec<_> = warnings.iter().map(|warning| (warning.board_index, warning.kind.clone())).collect();
+		assert_eq!(kinds, vec![
+			(None, write_warnings::WriteWarningKind::FlagCountChanged{count: 9, expected: 10}),
+			(Some(0), write_warnings::WriteWarningKind::FieldDropped{field: "Camera position"}),
+			(Some(0), write_warnings::WriteWarningKind::CodeNormalized{status_index: 0}),
+			(Some(0), write_warnings::WriteWarningKind::Truncated{field: "Board name".to_string(), len: 60, max_len: 50}),
+		]);
+		assert_eq!(warnings[3].to_string(), "Board 0: Board name shortened from 60 to 50 bytes");
+
+		// A world that can be read back the same has no warnings.
+		let world_reloaded = World::parse(&mut Cursor::new(out_buf.as_slice())).unwrap();
+		assert!(world_reloaded.write_with_options(&mut vec![], options).unwrap().is_empty());
+	}
+
+	#[test] fn extended_colours() {
+		use crate::extended_colour::{ExtendedColour, ExtendedColours};
+
+		assert_eq!(ExtendedColour::Indexed(12).to_rgb(), (0xFF, 0x55, 0x55));
+		assert_eq!(ExtendedColour::Indexed(196).to_rgb(), (255, 0, 0));
+		assert_eq!(ExtendedColour::Indexed(232).to_rgb(), (8, 8, 8));
+		assert_eq!(ExtendedColour::Rgb(250, 240, 60).nearest_standard_colour(), 0x0e);
+		assert_eq!(ExtendedColour::Rgb(250, 240, 60).nearest_background_colour(), 0x07);
+
+		let colours = ExtendedColours {
+			foreground: Some(ExtendedColour::Rgb(0, 0, 180)),
+			background: None,
+		};
+		assert_eq!(colours.to_standard_colour(0xaf), 0xa1);
+
+		// Extended colours aren't written to world files.
+		let mut world = World::zzt_default();
+		world.boards[0].status_elements[0].extended_colours = Some(colours);
+		let mut out_buf = vec![];
+		let warnings = world.write_with_options(&mut out_buf, WriteOptions::default()).unwrap();
+		assert_eq!(warnings[0].kind, write_warnings::WriteWarningKind::FieldDropped{field: "Extended colours"});
+		let world_reloaded = World::parse(&mut Cursor::new(out_buf.as_slice())).unwrap();
+		assert_eq!(world_reloaded.boards[0].status_elements[0].extended_colours, None);
+	}
+
+	#[test] fn lenient_missing_player_status() {
+		let board = Board::zzt_default(DosString::from_slice(b"Corrupt"));
+		let mut board_data = vec![];
+		board.write_brd(&mut board_data, WorldType::Zzt).unwrap();
+		// Remove the player's status element (the last 33 bytes) and make the count -1.
+		board_data.truncate(board_data.len() - 33);
+		let count_pos = board_data.len() - 2;
+		board_data[count_pos ..].copy_from_slice(&(-1i16).to_le_bytes());
+
+		let err = Board::parse(&mut Cursor::new(board_data.as_slice()), WorldType::Zzt).unwrap_err();
+		assert!(err.starts_with("Can't have less than 1 status element"));
+		// The player's status element is added back on the player tile.
+		let lenient_board = Board::parse_with_options(&mut Cursor::new(board_data.as_slice()), WorldType::Zzt, padding::ParseOptions::lenient()).unwrap();
+		assert_eq!(lenient_board, board);
+
+		// Without a player tile, it goes in the middle of the board.
+		let mut empty_board = Board::default();
+		assert!(empty_board.add_missing_player_status(WorldType::Zzt));
+		assert_eq!((empty_board.status_elements[0].location_x, empty_board.status_elements[0].location_y), (30, 12));
+		assert!(!empty_board.add_missing_player_status(WorldType::Zzt));
+	}
+
+	#[test] fn brd_insert_board() {
+		let mut world = World::zzt_default();
+		world.boards.push(Board::zzt_default(DosString::from_slice(b"First")));
+		world.boards.push(Board::zzt_default(DosString::from_slice(b"Second")));
+		world.world_header.num_boards_except_title = 2;
+		world.world_header.player_board = 2;
+		world.boards[1].meta_data.exit_east = 2;
+		world.boards[1].tiles[(5 - 1) * 60 + 10 - 1] = BoardTile::new(ElementType::Passage, 0x0f);
+		world.boards[1].status_elements.push(StatusElement {
+			location_x: 10,
+			location_y: 5,
+			param3: 2,
+			.. StatusElement::default()
+		});
+
+		let mut brd_data = vec![];
+		world.boards[2].write_brd(&mut brd_data, WorldType::Zzt).unwrap();
+		let brd_board = Board::parse_brd(&mut Cursor::new(brd_data.as_slice()), WorldType::Zzt).unwrap();
+		assert_eq!(brd_board, world.boards[2]);
+
+		// Links to the boards after the inserted board still lead to the same boards.
+		world.insert_board(2, brd_board).unwrap();
+		assert_eq!(world.boards.len(), 4);
+		assert_eq!(world.world_header.num_boards_except_title, 3);
+		assert_eq!(world.world_header.player_board, 3);
+		assert_eq!(world.boards[1].meta_data.exit_east, 3);
+		assert_eq!(world.boards[1].status_elements[1].param3, 3);
+		world.validate().unwrap();
+
+		assert!(world.insert_board(0, Board::zzt_default(DosString::new())).is_err());
+
+		// Removing it again puts the links back, and links to a removed board lead nowhere.
+		assert_eq!(world.remove_board(2).unwrap(), world.boards[2]);
+		assert_eq!(world.world_header.player_board, 2);
+		assert_eq!(world.boards[1].meta_data.exit_east, 2);
+		assert_eq!(world.boards[1].status_elements[1].param3, 2);
+		world.remove_board(2).unwrap();
+		assert_eq!(world.world_header.num_boards_except_title, 1);
+		assert_eq!(world.world_header.player_board, 0);
+		assert_eq!(world.boards[1].meta_data.exit_east, 0);
+		assert_eq!(world.boards[1].status_elements[1].param3, 0);
+		world.validate().unwrap();
+		assert!(world.remove_board(0).is_err());
+	}
+
+	#[test] fn rzt_large_boards() {
+		let mut world = World::zzt_default();
+		world.world_header.world_name = DosString::from_slice(b"Big");
+		for (x, code_len) in [(2u8, 20000), (3, 15000), (4, 100)] {
+			world.boards[0].status_elements.push(StatusElement {
+				location_x: x,
+				location_y: 1,
+				code_source: CodeSource::Owned(DosString::from_slice(&vec![b'#'; code_len])),
+				.. StatusElement::default()
+			});
+		}
+		assert!(world.write(&mut vec![]).unwrap_err().contains("max 32767"));
+
+		let mut rzt_data = vec![];
+		world.write_rzt(&mut rzt_data).unwrap();
+		assert!(rzt::is_rzt(&rzt_data));
+		assert_eq!(World::parse(&mut Cursor::new(rzt_data.as_slice())).unwrap(), world);
+
+		// Only the largest code has to go for the board to fit.
+		let mut zzt_data = vec![];
+		let losses = world.write_vanilla(&mut zzt_data).unwrap();
+		assert_eq!(losses, vec!["Board 0: Removed the code of status element 1 (20000 bytes)".to_string()]);
+		let vanilla_world = World::parse(&mut Cursor::new(zzt_data.as_slice())).unwrap();
+		assert_eq!(vanilla_world.boards[0].status_code(2).map(|code| code.len()), Some(15000));
+	}
+
+	#[test] fn parse_with_progress() {
+		let mut world = World::zzt_default();
+		world.insert_board(1, Board::zzt_default(DosString::from_slice(b"Second"))).unwrap();
+		let mut data = vec![];
+		world.write(&mut data).unwrap();
+
+		let mut progress_reports = vec![];
+		let parsed_world = World::parse_with_progress(&mut Cursor::new(data.as_slice()), &mut |progress| {
+			progress_reports.push((progress.boards_parsed, progress.total_boards));
+			true
+		}).unwrap();
+		assert_eq!(parsed_world, world);
+		assert_eq!(progress_reports, vec![(1, 2), (2, 2)]);
+
+		assert!(World::parse_with_progress(&mut Cursor::new(data.as_slice()), &mut |_| false).is_err());
+	}
+
+	#[test] fn preserve_padding() {
+		let mut data = vec![];
+		World::zzt_default().write(&mut data).unwrap();
+		// The padding after the energy cycles, the end of the world name, the gap before the first
+		// board and some data after the last board.
+		data[25] = 0xab;
+		data[49] = 0xcd;
+		data[0x1f0] = 0xef;
+		data.extend_from_slice(b"hidden");
+
+		let options = padding::ParseOptions { preserve_padding: true, .. padding::ParseOptions::default() };
+		let world = World::parse_with_options(&mut Cursor::new(data.as_slice()), options).unwrap();
+		let json = serde_json::to_string(&world).unwrap();
+		let world_from_json: World = serde_json::from_str(&json).unwrap();
+		let mut out_buf = vec![];
+		world_from_json.write(&mut out_buf).unwrap();
+		assert_eq!(out_buf, data);
+
+		let mut out_buf = vec![];
+		World::parse(&mut Cursor::new(data.as_slice())).unwrap().write(&mut out_buf).unwrap();
+		assert_eq!(out_buf.len(), data.len() - b"hidden".len());
+		assert_eq!((out_buf[25], out_buf[49], out_buf[0x1f0]), (0, 0, 0));
+	}
+
+	#[test] fn world_metadata() {
+		let metadata = metadata::WorldMetadata::parse(r#"{"author": "Tim Sweeney", "year": 1991, "content_warnings": ["Flashing colours"]}"#).unwrap();
+		assert_eq!(metadata.author.as_deref(), Some("Tim Sweeney"));
+		assert_eq!(metadata.description, None);
+		assert_eq!(metadata.credit_line(), Some("By Tim Sweeney (1991)".to_string()));
+
+		let mut data = vec![];
+		metadata.write(&mut data).unwrap();
+		assert_eq!(metadata::WorldMetadata::parse(&String::from_utf8(data).unwrap()).unwrap(), metadata);
+		assert!(metadata::WorldMetadata::parse("not json").is_err());
+	}
+
+	#[test] fn merge_worlds() {
+		let code_status = |x: u8, code: &[u8]| StatusElement {
+			location_x: x,
+			location_y: 1,
+			code_source: CodeSource::Owned(DosString::from_slice(code)),
+			.. StatusElement::default()
+		};
+
+		let mut world = World::zzt_default();
+		world.boards[0].status_elements.push(code_status(2, b"@a\r#set door\r#set ruzztlock"));
+
+		let mut other = World::zzt_default();
+		other.insert_board(1, Board::zzt_default(DosString::from_slice(b"First"))).unwrap();
+		other.insert_board(2, Board::zzt_default(DosString::from_slice(b"Second"))).unwrap();
+		other.insert_board(3, Board::zzt_default(DosString::from_slice(b"Left out"))).unwrap();
+		other.boards[1].meta_data.exit_east = 2;
+		other.boards[1].meta_data.exit_west = 3;
+		other.boards[1].status_elements.push(code_status(2, b"#if not Door clear key\r#if blocked opp cw seek set door\r#if any blue key #take gems 5 #clear door\r#clear ruzztlock"));
+
+		let report = world.merge(&other, &[1, 2], true).unwrap();
+		assert_eq!(report.new_board_indices, vec![1, 2]);
+		assert_eq!(report.renamed_flags, vec![(DosString::from_slice(b"DOOR"), DosString::from_slice(b"DOOR2"))]);
+		assert_eq!(world.boards.len(), 3);
+		assert_eq!(world.world_header.num_boards_except_title, 2);
+		assert_eq!(world.boards[2].meta_data.board_name, DosString::from_slice(b"Second"));
+		assert_eq!((world.boards[1].meta_data.exit_east, world.boards[1].meta_data.exit_west), (2, 0));
+		assert_eq!(world.boards[1].status_code(1), Some(&DosString::from_slice(b"#if not DOOR2 clear key\r#if blocked opp cw seek set DOOR2\r#if any blue key #take gems 5 #clear DOOR2\r#clear ruzztlock")));
+		world.validate().unwrap();
+
+		// Without remapping, exits are kept as they are.
+		let report = world.merge(&other, &[1], false).unwrap();
+		assert_eq!(report.new_board_indices, vec![3]);
+		assert_eq!((world.boards[3].meta_data.exit_east, world.boards[3].meta_data.exit_west), (2, 3));
+
+		assert!(world.merge(&other, &[4], true).unwrap_err().contains("no board 4"));
+	}
 }
@@ -0,0 +1,252 @@
+use crate::dosstring::DosString;
+use crate::{CodeSource, World, MAX_FLAG_NAME_LEN};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+/// What `World::merge` did.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MergeReport {
+	/// The index each merged board was given, in the same order as the boards that were asked for.
+	pub new_board_indices: Vec<usize>,
+	/// The flags that were renamed in the code of the merged boards, because the world they were
+	/// merged into already used them, as (old name, new name) pairs.
+	pub renamed_flags: Vec<(DosString, DosString)>,
+}
+
+impl MergeReport {
+	/// Describe what was done, one line for each board and each renamed flag.
+	pub fn description_lines(&self, board_indices: &[usize]) -> Vec<String> {
+		let board_lines = board_indices.iter().zip(&self.new_board_indices).map(|(board_index, new_board_index)| {
+			format!("Merged board {} as board {}", board_index, new_board_index)
+		});
+		let flag_lines = self.renamed_flags.iter().map(|(old_name, new_name)| {
+			format!("Renamed flag {} to {} in the merged boards", old_name.to_string(false), new_name.to_string(false))
+		});
+		board_lines.chain(flag_lines).collect()
+	}
+}
+
+/// The conditions of `#if` that aren't flags.
+const BUILT_IN_CONDITIONS: [&[u8]; 6] = [b"NOT", b"ALLIGNED", b"CONTACT", b"BLOCKED", b"ENERGIZED", b"ANY"];
+
+/// The directions that can be used on their own, eg. in `#if blocked seek`.
+const DIRECTIONS: [&[u8]; 15] = [b"N", b"NORTH", b"S", b"SOUTH", b"E", b"EAST", b"W", b"WEST", b"I", b"IDLE", b"SEEK", b"FLOW", b"RND", b"RNDNS", b"RNDNE"];
+
+/// The words that change the direction after them, eg. `cw` in `#if blocked cw seek`.
+const DIRECTION_MODIFIERS: [&[u8]; 4] = [b"CW", b"CCW", b"RNDP", b"OPP"];
+
+/// The colours that can come before the element in `#if any`, eg. `#if any blue key`.
+const COLOURS: [&[u8]; 7] = [b"BLUE", b"GREEN", b"CYAN", b"RED", b"PURPLE", b"YELLOW", b"WHITE"];
+
+/// Reads the words of a line of OOP, the way ZZT reads the name of a command or a flag.
+struct LineWords<'a> {
+	line: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> LineWords<'a> {
+	/// Get the range of the next word in the line, skipping the spaces and the `#` in front of it.
+	fn next_word(&mut self) -> Option<Range<usize>> {
+		while self.line.get(self.pos) == Some(&b' ') {
+			self.pos += 1;
+		}
+		if self.line.get(self.pos) == Some(&b'#') {
+			self.pos += 1;
+		}
+		let start = self.pos;
+		while self.line.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_' || *c == b':') {
+			self.pos += 1;
+		}
+		if self.pos > start {
+			Some(start .. self.pos)
+		} else {
+			None
+		}
+	}
+
+	/// Get the next word in upper case.
+	fn next_upper_word(&mut self) -> Option<(Range<usize>, Vec<u8>)> {
+		self.next_word().map(|range| {
+			let word = self.line[range.clone()].to_ascii_uppercase();
+			(range, word)
+		})
+	}
+
+	/// Skip a direction, which can have any number of modifiers in front of it (eg. `opp cw seek`).
+	/// Returns false if it isn't a direction.
+	fn skip_direction(&mut self) -> bool {
+		while let Some((_, word)) = self.next_upper_word() {
+			if DIRECTIONS.contains(&word.as_slice()) {
+				return true;
+			} else if !DIRECTION_MODIFIERS.contains(&word.as_slice()) {
+				return false;
+			}
+		}
+		false
+	}
+
+	/// Skip the kind of element in `#if any`, which is an element name with an optional colour in
+	/// front of it. Returns false if there isn't one.
+	fn skip_element(&mut self) -> bool {
+		match self.next_upper_word() {
+			Some((_, ref word)) if COLOURS.contains(&word.as_slice()) => self.next_word().is_some(),
+			Some(_) => true,
+			None => false,
+		}
+	}
+}
+
+/// Get the ranges of `code` that are flag names: the words after `#set` and `#clear`, and the
+/// conditions of `#if` that aren't built in. The commands that `#if` and `#take` run are read too,
+/// so `#if not door set open` has the flags `door` and `open`, and `#if blocked cw seek set stuck`
+/// has the flag `stuck`.
+fn flag_name_ranges(code: &[u8]) -> Vec<Range<usize>> {
+	let mut ranges = vec![];
+	let mut line_start = 0;
+	for line in code.split(|c| *c == b'\r') {
+		let mut words = LineWords{line, pos: 0};
+		let mut add_range = |range: Range<usize>| ranges.push(line_start + range.start .. line_start + range.end);
+		if line.first() == Some(&b'#') {
+			while let Some((_, command)) = words.next_upper_word() {
+				match command.as_slice() {
+					b"SET" | b"CLEAR" => {
+						if let Some(range) = words.next_word() {
+							add_range(range);
+						}
+						break;
+					}
+					b"IF" => {
+						let mut condition_opt = words.next_upper_word();
+						while condition_opt.as_ref().is_some_and(|(_, condition)| condition == b"NOT") {
+							condition_opt = words.next_upper_word();
+						}
+						// The rest of the line is the command to run if the condition is true.
+						let condition_is_whole = match condition_opt {
+							Some((_, ref condition)) if condition == b"BLOCKED" => words.skip_direction(),
+							Some((_, ref condition)) if condition == b"ANY" => words.skip_element(),
+							Some((_, ref condition)) if BUILT_IN_CONDITIONS.contains(&condition.as_slice()) => true,
+							Some((range, _)) => {
+								add_range(range);
+								true
+							}
+							None => false,
+						};
+						if !condition_is_whole {
+							break;
+						}
+					}
+					b"TAKE" => {
+						// `#take gems 5 set poor` runs the command after the amount if there isn't
+						// enough to take.
+						if words.next_word().is_none() || words.next_word().is_none() {
+							break;
+						}
+					}
+					_ => break,
+				}
+			}
+		}
+		line_start += line.len() + 1;
+	}
+	ranges
+}
+
+/// Get the names of the flags used in `code`, in upper case (see `flag_name_ranges`).
+fn code_flag_names(code: &DosString) -> BTreeSet<Vec<u8>> {
+	flag_name_ranges(&code.data).into_iter().map(|range| code.data[range].to_ascii_uppercase()).collect()
+}
+
+/// Rename the flags in `code` that are in `renames`, which maps upper case names to new names.
+fn rename_code_flags(code: &mut DosString, renames: &BTreeMap<Vec<u8>, Vec<u8>>) {
+	// Going from the end means the ranges before each one don't move.
+	for range in flag_name_ranges(&code.data).into_iter().rev() {
+		if let Some(new_name) = renames.get(&code.data[range.clone()].to_ascii_uppercase()) {
+			code.data.splice(range, new_name.iter().cloned());
+		}
+	}
+}
+
+/// Make a name for `flag_name` that isn't in `taken`, by putting a number on the end of it.
+fn unused_flag_name(flag_name: &[u8], taken: &BTreeSet<Vec<u8>>) -> Vec<u8> {
+	(2 ..).map(|number: usize| {
+		let suffix = number.to_string();
+		let mut new_name = flag_name[.. flag_name.len().min(MAX_FLAG_NAME_LEN - suffix.len())].to_vec();
+		new_name.extend_from_slice(suffix.as_bytes());
+		new_name
+	}).find(|new_name| !taken.contains(new_name)).unwrap()
+}
+
+impl World {
+	/// Get the names of the flags used in the code of the boards at `board_indices`, in upper case.
+	fn boards_flag_names(&self, board_indices: impl Iterator<Item = usize>) -> BTreeSet<Vec<u8>> {
+		board_indices.flat_map(|board_index| self.boards[board_index].status_elements.iter())
+			.filter_map(|status| match status.code_source {
+				CodeSource::Owned(ref code) => Some(code_flag_names(code)),
+				CodeSource::Bound(_) => None,
+			})
+			.flatten()
+			.collect()
+	}
+
+	/// Copy the boards at `board_indices` in `other` to the end of this world, eg. to put together a
+	/// pack of worlds. `other` must be the same type of world.
+	///
+	/// When `remap_exits` is true, the board exits and passages of the merged boards that lead to
+	/// other merged boards are changed to lead to their new indices, and the ones that lead to boards
+	/// that weren't merged are changed to lead nowhere (to the title board for passages). Otherwise
+	/// they are kept as they are, eg. for boards that were made to lead into this world's boards.
+	///
+	/// Flags that are used in the code of both the merged boards and this world are renamed in the
+	/// merged boards, so the two don't interfere with each other. Flags starting with `RUZZT` are
+	/// left alone, because RUZZT sets them itself so worlds can detect it.
+	pub fn merge(&mut self, other: &World, board_indices: &[usize], remap_exits: bool) -> Result<MergeReport, String> {
+		let world_type = self.world_header.world_type;
+		if other.world_header.world_type != world_type {
+			return Err(format!("Can't merge a {:?} world into a {:?} world", other.world_header.world_type, world_type));
+		}
+		if let Some(board_index) = board_indices.iter().find(|&&board_index| board_index >= other.boards.len()) {
+			return Err(format!("There is no board {} in the world being merged (it has {} boards)", board_index, other.boards.len()));
+		}
+		if self.boards.len() + board_indices.len() > u8::MAX as usize + 1 {
+			return Err(format!("Can't have more than {} boards", u8::MAX as usize + 1));
+		}
+
+		let first_new_index = self.boards.len();
+		// Board 0 means there is no exit, so it stays the same even when the title board is merged.
+		let new_board_index = |board_index: u8| match board_indices.iter().position(|&merged_index| merged_index == board_index as usize) {
+			Some(position) if board_index != 0 => (first_new_index + position) as u8,
+			_ => 0,
+		};
+
+		let used_flags = self.boards_flag_names(0 .. self.boards.len());
+		let merged_flags = other.boards_flag_names(board_indices.iter().cloned());
+		let mut taken_flags: BTreeSet<Vec<u8>> = used_flags.union(&merged_flags).cloned().collect();
+		let mut renames = BTreeMap::new();
+		for flag_name in used_flags.intersection(&merged_flags).filter(|flag_name| !flag_name.starts_with(b"RUZZT")) {
+			let new_name = unused_flag_name(flag_name, &taken_flags);
+			taken_flags.insert(new_name.clone());
+			renames.insert(flag_name.clone(), new_name);
+		}
+
+		let mut report = MergeReport::default();
+		for &board_index in board_indices {
+			let mut board = other.boards[board_index].clone();
+			if remap_exits {
+				board.remap_board_indices(world_type, new_board_index);
+			}
+			for status in &mut board.status_elements {
+				if let CodeSource::Owned(ref mut code) = status.code_source {
+					rename_code_flags(code, &renames);
+				}
+			}
+			report.new_board_indices.push(self.boards.len());
+			self.boards.push(board);
+			self.world_header.num_boards_except_title += 1;
+		}
+		report.renamed_flags = renames.into_iter()
+			.map(|(old_name, new_name)| (DosString::from_slice(&old_name), DosString::from_slice(&new_name)))
+			.collect();
+		Ok(report)
+	}
+}
@@ -0,0 +1,56 @@
+use serde_derive::{Serialize, Deserialize};
+
+/// The file extension of the metadata file that can go next to a world (eg. `TOWN.META` for
+/// `TOWN.ZZT`). It holds a `WorldMetadata` as JSON.
+pub const WORLD_METADATA_FILE_EXTENSION: &str = "META";
+
+/// Information about a world that doesn't fit in the world file, like who made it. This is kept in
+/// its own file next to the world (see `WORLD_METADATA_FILE_EXTENSION`), so the world file is
+/// exactly the same as it would be without it. Everything is optional, and fields that are missing
+/// from the file are left empty.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldMetadata {
+	/// The name of the person (or people) who made the world.
+	pub author: Option<String>,
+	/// The year the world was released.
+	pub year: Option<u16>,
+	/// A short description of the world.
+	pub description: Option<String>,
+	/// Things the player might want to know about before playing, eg. "flashing colours".
+	pub content_warnings: Vec<String>,
+	/// The name of the palette the world is meant to be played with.
+	pub recommended_palette: Option<String>,
+	/// The name of the font the world is meant to be played with.
+	pub recommended_font: Option<String>,
+	/// The name of the DOS machine the world is meant to be played on, for worlds that rely on how
+	/// fast ZZT ran on it, eg. "turbo-xt" or "386".
+	pub machine_speed: Option<String>,
+	/// The seed that the randomness on each board is worked out from, for worlds that want random
+	/// elements to behave the same way every time a board is attempted.
+	pub board_rng_seed: Option<u64>,
+}
+
+impl WorldMetadata {
+	/// Read metadata from the JSON `text` of a metadata file.
+	pub fn parse(text: &str) -> Result<WorldMetadata, String> {
+		serde_json::from_str(text).map_err(|e| format!("Failed to parse world metadata: {}", e))
+	}
+
+	/// Write the metadata to `stream` as JSON, in the format read by `parse`.
+	pub fn write(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		serde_json::to_writer_pretty(&mut *stream, self).map_err(|e| format!("Failed to write world metadata: {}", e))?;
+		stream.write_all(b"\n").map_err(|e| format!("Failed to write world metadata: {}", e))
+	}
+
+	/// Get the line shown under the world's name, eg. "By Tim Sweeney (1991)", or None if neither
+	/// the author nor the year is known.
+	pub fn credit_line(&self) -> Option<String> {
+		match (&self.author, self.year) {
+			(Some(author), Some(year)) => Some(format!("By {} ({})", author, year)),
+			(Some(author), None) => Some(format!("By {}", author)),
+			(None, Some(year)) => Some(format!("Made in {}", year)),
+			(None, None) => None,
+		}
+	}
+}
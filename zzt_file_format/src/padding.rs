@@ -0,0 +1,117 @@
+use serde_derive::{Serialize, Deserialize};
+
+use std::io::{Read, Write};
+
+/// Options for reading a world file (see `World::parse_with_options`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ParseOptions {
+	/// When true, the bytes that don't hold any of the world's data are kept in `RawPadding`
+	/// fields, so that writing the world again produces exactly the same file. This includes
+	/// padding, the bytes left over after each string in its fixed-size field, the code pointer of
+	/// each status element and anything after the last board. Some tools hide data in these bytes,
+	/// so this is useful for archiving worlds.
+	pub preserve_padding: bool,
+	/// When true, boards that DOS ZZT tolerates but that can't be written back as they are, are
+	/// fixed up instead of being an error. A board with a status element count below zero (so it
+	/// has no status element for the player) is given one with `Board::add_missing_player_status`.
+	/// Archived worlds that were corrupted or made by buggy editors sometimes have these.
+	pub lenient: bool,
+}
+
+impl ParseOptions {
+	/// Options for opening a world to play or edit: `lenient`, so archived worlds open, and without
+	/// keeping the padding.
+	pub fn lenient() -> ParseOptions {
+		ParseOptions {
+			lenient: true,
+			.. ParseOptions::default()
+		}
+	}
+}
+
+/// The bytes of a world file that don't hold any of the world's data, kept when the world is read
+/// with `ParseOptions::preserve_padding`. Each blob is one stretch of unused bytes, in the order
+/// they appear in the file.
+///
+/// When writing, each blob is only used if it is still the right length for its stretch, so eg.
+/// the bytes left over after a name are dropped once the name's length changes. Stretches without
+/// a usable blob are written as zeros, like when there is no `RawPadding` at all.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct RawPadding {
+	pub blobs: Vec<Vec<u8>>,
+}
+
+/// Collects the unused bytes of a part of a world file as it is read, if they are being kept.
+pub(crate) struct PaddingReader {
+	/// The blobs read so far, or None if the unused bytes aren't being kept.
+	blobs: Option<Vec<Vec<u8>>>,
+}
+
+impl PaddingReader {
+	/// Start collecting the unused bytes if `preserve_padding` is true.
+	pub fn new(preserve_padding: bool) -> PaddingReader {
+		PaddingReader {
+			blobs: if preserve_padding { Some(vec![]) } else { None },
+		}
+	}
+
+	/// Read a stretch of `len` unused bytes from `stream`.
+	pub fn read(&mut self, stream: &mut dyn Read, len: usize) -> Result<(), String> {
+		let mut blob = vec![0; len];
+		stream.read_exact(&mut blob).map_err(|e| format!("Failed to read padding bytes: {}", e))?;
+		self.keep(blob);
+		Ok(())
+	}
+
+	/// Keep a stretch of unused bytes that has already been read.
+	pub fn keep(&mut self, blob: Vec<u8>) {
+		if let Some(ref mut blobs) = self.blobs {
+			blobs.push(blob);
+		}
+	}
+
+	/// Get the collected bytes, or None if they weren't being kept.
+	pub fn finish(self) -> Option<RawPadding> {
+		self.blobs.map(|blobs| RawPadding {
+			blobs,
+		})
+	}
+}
+
+/// Writes the stretches of unused bytes of a part of a world file in order, using the blobs from
+/// a `RawPadding` where they fit.
+pub(crate) struct PaddingWriter<'a> {
+	padding: Option<&'a RawPadding>,
+	/// The index of the blob for the next stretch.
+	next_index: usize,
+}
+
+impl<'a> PaddingWriter<'a> {
+	pub fn new(padding: Option<&'a RawPadding>) -> PaddingWriter<'a> {
+		PaddingWriter::starting_at(padding, 0)
+	}
+
+	/// Make a writer that starts with the blob at `first_index`, for when the blobs before it were
+	/// written by another writer.
+	pub fn starting_at(padding: Option<&'a RawPadding>, first_index: usize) -> PaddingWriter<'a> {
+		PaddingWriter {
+			padding,
+			next_index: first_index,
+		}
+	}
+
+	/// Get the number of stretches written so far, including any skipped with `starting_at`.
+	pub fn blobs_written(&self) -> usize {
+		self.next_index
+	}
+
+	/// Write the next stretch of unused bytes, which is `len` bytes long.
+	pub fn write(&mut self, stream: &mut dyn Write, len: usize) -> Result<(), String> {
+		let blob = self.padding.and_then(|padding| padding.blobs.get(self.next_index)).filter(|blob| blob.len() == len);
+		self.next_index += 1;
+		match blob {
+			Some(blob) => stream.write_all(blob),
+			None => stream.write_all(&vec![0; len]),
+		}.map_err(|e| format!("Failed to write padding bytes: {}", e))
+	}
+}
@@ -0,0 +1,149 @@
+use crate::{ElementType, StatusElement};
+
+/// One of the three generic parameters of a `StatusElement`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamIndex {
+	Param1,
+	Param2,
+	Param3,
+}
+
+impl ParamIndex {
+	/// The name of the parameter's field in `StatusElement` (and its JSON), eg. "param1".
+	pub fn field_name(self) -> &'static str {
+		match self {
+			ParamIndex::Param1 => "param1",
+			ParamIndex::Param2 => "param2",
+			ParamIndex::Param3 => "param3",
+		}
+	}
+}
+
+/// The meaning of a status element's parameter (or some of its bits) for a particular element type,
+/// so it can be shown and edited by name instead of as a raw param byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamField {
+	/// The name of the value, eg. "intelligence".
+	pub name: &'static str,
+	/// The parameter the value is stored in.
+	pub param: ParamIndex,
+	/// The bits of the parameter that hold the value. Most values use the whole byte, but eg. a
+	/// tiger's firing rate and firing type share param2.
+	pub mask: u8,
+	/// The largest value the game expects the field to have.
+	pub max: u8,
+}
+
+impl ParamField {
+	const fn new(name: &'static str, param: ParamIndex, max: u8) -> ParamField {
+		ParamField {
+			name,
+			param,
+			mask: 0xff,
+			max,
+		}
+	}
+
+	const fn with_mask(name: &'static str, param: ParamIndex, mask: u8, max: u8) -> ParamField {
+		ParamField {
+			name,
+			param,
+			mask,
+			max,
+		}
+	}
+
+	/// Get the value of the field in `status`.
+	pub fn get(&self, status: &StatusElement) -> u8 {
+		let param = match self.param {
+			ParamIndex::Param1 => status.param1,
+			ParamIndex::Param2 => status.param2,
+			ParamIndex::Param3 => status.param3,
+		};
+		(param & self.mask) >> self.mask.trailing_zeros()
+	}
+
+	/// Set the value of the field in `status`, leaving the other bits of the parameter alone.
+	/// Returns an error if `value` doesn't fit in the field.
+	pub fn set(&self, status: &mut StatusElement, value: u8) -> Result<(), String> {
+		if value > self.max {
+			return Err(format!("{} must be from 0 to {}", self.name, self.max));
+		}
+
+		let param = match self.param {
+			ParamIndex::Param1 => &mut status.param1,
+			ParamIndex::Param2 => &mut status.param2,
+			ParamIndex::Param3 => &mut status.param3,
+		};
+		*param = (*param & !self.mask) | ((value << self.mask.trailing_zeros()) & self.mask);
+		Ok(())
+	}
+}
+
+const INTELLIGENCE: ParamField = ParamField::new("intelligence", ParamIndex::Param1, 8);
+const FIRING_RATE: ParamField = ParamField::with_mask("firing_rate", ParamIndex::Param2, 0x7f, 8);
+const FIRES_STARS: ParamField = ParamField::with_mask("fires_stars", ParamIndex::Param2, 0x80, 1);
+const SHOT_BY_ENEMY: ParamField = ParamField::new("shot_by_enemy", ParamIndex::Param1, 1);
+
+/// Get the meanings of the parameters for statuses of the given `element_type`. Parameters that
+/// aren't listed aren't used by that element type.
+pub fn param_fields(element_type: ElementType) -> &'static [ParamField] {
+	use self::ParamIndex::*;
+
+	match element_type {
+		ElementType::Object => const { &[
+			ParamField::new("char", Param1, 255),
+			ParamField::new("locked", Param2, 1),
+		] },
+		ElementType::Bear => const { &[
+			ParamField::new("sensitivity", Param1, 8),
+		] },
+		ElementType::Ruffian => const { &[
+			INTELLIGENCE,
+			ParamField::new("resting_time", Param2, 8),
+		] },
+		ElementType::Shark | ElementType::Lion => const { &[
+			INTELLIGENCE,
+		] },
+		ElementType::SpinningGun | ElementType::Tiger => const { &[
+			INTELLIGENCE,
+			FIRING_RATE,
+			FIRES_STARS,
+		] },
+		ElementType::Head => const { &[
+			INTELLIGENCE,
+			ParamField::new("deviance", Param2, 8),
+		] },
+		ElementType::Slime => const { &[
+			ParamField::new("progress", Param1, 255),
+			ParamField::new("speed", Param2, 8),
+		] },
+		ElementType::Duplicator => const { &[
+			ParamField::new("progress", Param1, 4),
+			ParamField::new("speed", Param2, 8),
+		] },
+		ElementType::Bomb => const { &[
+			ParamField::new("countdown", Param1, 9),
+		] },
+		ElementType::BlinkWall => const { &[
+			ParamField::new("start_time", Param1, 8),
+			ParamField::new("period", Param2, 8),
+		] },
+		ElementType::Passage => const { &[
+			ParamField::new("destination", Param3, 255),
+		] },
+		ElementType::Bullet => const { &[
+			SHOT_BY_ENEMY,
+		] },
+		ElementType::Star => const { &[
+			SHOT_BY_ENEMY,
+			ParamField::new("time_left", Param2, 255),
+		] },
+		_ => &[],
+	}
+}
+
+/// Get the field of `element_type` with the given `name`, or None if it doesn't have one.
+pub fn param_field(element_type: ElementType, name: &str) -> Option<ParamField> {
+	param_fields(element_type).iter().find(|field| field.name == name).copied()
+}
@@ -0,0 +1,168 @@
+use crate::dosstring::DosString;
+use crate::padding::ParseOptions;
+use crate::{Board, CodeSource, World, WorldHeader, WorldType, WriteOptions};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::Read;
+
+/// The file extension of worlds in the RZT container, which holds a ZZT or Super ZZT world without
+/// the original file format's limit on how big each board can be (see `World::write_rzt`).
+/// `World::parse` reads these as well as world files, and `World::write_vanilla` makes a world file
+/// from a world that doesn't fit, reporting what had to be left out.
+pub const RZT_FILE_EXTENSION: &str = "RZT";
+/// The bytes an RZT file starts with. World files start with a negative world type, so these can't
+/// be mistaken for one.
+pub const RZT_MAGIC: &[u8; 4] = b"RZT\x1a";
+/// The version of the RZT format written by `World::write_rzt`.
+pub const RZT_FORMAT_VERSION: u16 = 1;
+
+/// The biggest a board can be in the original world file format, in bytes.
+const MAX_VANILLA_BOARD_SIZE: usize = i16::MAX as usize;
+
+/// Check if `data` is the start of an RZT file.
+pub fn is_rzt(data: &[u8]) -> bool {
+	data.starts_with(RZT_MAGIC)
+}
+
+impl World {
+	/// Read a world written with `write_rzt`.
+	pub fn parse_rzt(stream: &mut dyn std::io::Read) -> Result<World, String> {
+		let world_header = parse_rzt_header(stream)?;
+
+		let mut boards = vec![];
+		for board_index in 0 .. (world_header.num_boards_except_title as usize + 1) {
+			boards.push(parse_rzt_board(stream, world_header.world_type, board_index)?);
+		}
+
+		Ok(World {
+			world_header,
+			boards,
+			trailing_data: None,
+		})
+	}
+
+	/// Write the world to `stream` in the RZT container, which has no limit on the size of boards.
+	/// Returns an error without writing anything if the world can't be saved as it is (see
+	/// `validate`).
+	///
+	/// The file has the `RZT_MAGIC` bytes, the format version (u16), the size of the world header
+	/// (u32), the world header as it is in a world file, then each board's size (u32) and content
+	/// as it is in a world file, all little-endian. The code of a single status element is still
+	/// limited to 32767 bytes, because status elements are stored the same way as in a world file.
+	pub fn write_rzt(&self, stream: &mut dyn std::io::Write) -> Result<(), String> {
+		self.validate()?;
+
+		let mut header_data = vec![];
+		self.world_header.write(&mut header_data).map_err(|e| format!("WorldHeader: {}", e))?;
+		let mut board_datas = vec![];
+		for (board_index, board) in self.boards.iter().enumerate() {
+			board_datas.push(board.write_body(self.world_header.world_type).map_err(|e| format!("Board {}: {}", board_index, e))?);
+		}
+
+		stream.write_all(RZT_MAGIC).map_err(|e| format!("Failed to write magic: {}", e))?;
+		stream.write_u16::<LittleEndian>(RZT_FORMAT_VERSION).map_err(|e| format!("Failed to write format version: {}", e))?;
+		write_chunk(stream, &header_data).map_err(|e| format!("WorldHeader: {}", e))?;
+		for (board_index, board_data) in board_datas.iter().enumerate() {
+			write_chunk(stream, board_data).map_err(|e| format!("Board {}: {}", board_index, e))?;
+		}
+		Ok(())
+	}
+
+	/// Write the world to `stream` in the original world file format, leaving out whatever doesn't
+	/// fit. Names and messages that are too long are shortened, and the code of the objects with
+	/// the most code is removed from boards that are too big until they fit. Returns a description
+	/// of everything that was left out, which is empty if nothing was.
+	pub fn write_vanilla(&self, stream: &mut dyn std::io::Write) -> Result<Vec<String>, String> {
+		let mut world = self.clone();
+		let mut losses = vec![];
+
+		world.truncate_to_fit();
+		if world.world_header != self.world_header {
+			losses.push("The world name or flag names were shortened".to_string());
+		}
+		for (board_index, (board, original_board)) in world.boards.iter_mut().zip(&self.boards).enumerate() {
+			if board.meta_data != original_board.meta_data {
+				losses.push(format!("Board {}: The board name or message was shortened", board_index));
+			}
+			losses.extend(board.shrink_to_vanilla_size(world.world_header.world_type)?.into_iter().map(|loss| format!("Board {}: {}", board_index, loss)));
+		}
+
+		world.write_with_options(stream, WriteOptions::default())?;
+		Ok(losses)
+	}
+}
+
+impl Board {
+	/// Remove the code of the status elements with the most code until the board fits in
+	/// `MAX_VANILLA_BOARD_SIZE`, returning a description of each removed code.
+	fn shrink_to_vanilla_size(&mut self, world_type: WorldType) -> Result<Vec<String>, String> {
+		let mut losses = vec![];
+		// Removing code that other status elements are bound to would change their code too.
+		self.materialize_bound_code();
+		loop {
+			let board_size = self.write_body(world_type)?.len();
+			if board_size <= MAX_VANILLA_BOARD_SIZE {
+				return Ok(losses);
+			}
+
+			let largest_code = self.status_elements.iter().enumerate()
+				.filter_map(|(status_index, status)| match status.code_source {
+					CodeSource::Owned(ref code) if !code.data.is_empty() => Some((status_index, code.len())),
+					_ => None,
+				})
+				.max_by_key(|&(_, code_len)| code_len);
+			match largest_code {
+				Some((status_index, code_len)) => {
+					self.status_elements[status_index].code_source = CodeSource::Owned(DosString::new());
+					self.status_elements[status_index].code_current_instruction = 0;
+					losses.push(format!("Removed the code of status element {} ({} bytes)", status_index, code_len));
+				}
+				None => return Err(format!("Board is {} bytes long (max {}) even without any code", board_size, MAX_VANILLA_BOARD_SIZE)),
+			}
+		}
+	}
+}
+
+/// Read the start of an RZT file up to the end of the world header.
+pub(crate) fn parse_rzt_header(stream: &mut dyn std::io::Read) -> Result<WorldHeader, String> {
+	let mut magic = [0; 4];
+	stream.read_exact(&mut magic).map_err(|e| format!("Failed to read magic: {}", e))?;
+	if !is_rzt(&magic) {
+		return Err("Not an RZT file".to_string());
+	}
+	let version = stream.read_u16::<LittleEndian>().map_err(|e| format!("Failed to read format version: {}", e))?;
+	if version > RZT_FORMAT_VERSION {
+		return Err(format!("The RZT format version {} is newer than this version supports ({})", version, RZT_FORMAT_VERSION));
+	}
+
+	let header_size = stream.read_u32::<LittleEndian>().map_err(|e| format!("Failed to read world header size: {}", e))?;
+	let header_data = read_chunk(stream, header_size).map_err(|e| format!("WorldHeader: {}", e))?;
+	WorldHeader::parse(&mut header_data.as_slice()).map_err(|e| format!("WorldHeader: {}", e))
+}
+
+/// Read the next board of an RZT file, which is the board with the given `board_index`.
+pub(crate) fn parse_rzt_board(stream: &mut dyn std::io::Read, world_type: WorldType, board_index: usize) -> Result<Board, String> {
+	let board_size = stream.read_u32::<LittleEndian>().map_err(|e| format!("Board {}: Failed to read board size: {}", board_index, e))?;
+	let board_data = read_chunk(stream, board_size).map_err(|e| format!("Board {}: {}", board_index, e))?;
+	Board::parse_body(&mut board_data.as_slice(), world_type, ParseOptions::default()).map_err(|e| format!("Board {}: {}", board_index, e))
+}
+
+/// Read a chunk of `size` bytes from `stream`.
+fn read_chunk(stream: &mut dyn std::io::Read, size: u32) -> Result<Vec<u8>, String> {
+	let mut data = vec![];
+	Read::take(stream, size as u64).read_to_end(&mut data).map_err(|e| format!("Failed to read data: {}", e))?;
+	if data.len() != size as usize {
+		return Err(format!("Expected {} bytes of data but only found {}", size, data.len()));
+	}
+	Ok(data)
+}
+
+/// Write the size of `data` (u32) and then `data` to `stream`.
+fn write_chunk(stream: &mut dyn std::io::Write, data: &[u8]) -> Result<(), String> {
+	if data.len() > u32::MAX as usize {
+		return Err(format!("Data is {} bytes long (max {})", data.len(), u32::MAX));
+	}
+	stream.write_u32::<LittleEndian>(data.len() as u32).map_err(|e| format!("Failed to write size: {}", e))?;
+	stream.write_all(data).map_err(|e| format!("Failed to write data: {}", e))
+}
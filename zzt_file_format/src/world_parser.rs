@@ -0,0 +1,134 @@
+use crate::rzt::{self, parse_rzt_board, parse_rzt_header};
+use crate::padding::ParseOptions;
+use crate::{Board, World, WorldHeader, WorldType};
+
+/// How far `WorldParser` has got through a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseProgress {
+	/// The number of boards that have been read so far.
+	pub boards_parsed: usize,
+	/// The number of boards in the world, including the title board.
+	pub total_boards: usize,
+}
+
+/// Reads a world file (or a world in the RZT container) one board at a time, so something can be
+/// shown while a big world is loading on a slow device, and loading can be stopped part way
+/// through by dropping the parser. `World::parse` reads the whole world at once with one of these.
+pub struct WorldParser<S: std::io::Read + std::io::Seek> {
+	/// The stream the world is read from, positioned at the start of the next board.
+	stream: S,
+	/// True if the world is in the RZT container.
+	is_rzt: bool,
+	/// The world header, which is read when the parser is made.
+	world_header: WorldHeader,
+	/// The boards read so far.
+	boards: Vec<Board>,
+	/// How the world is read.
+	options: ParseOptions,
+}
+
+impl<S: std::io::Read + std::io::Seek> WorldParser<S> {
+	/// Start reading the world in `stream`, by reading its header.
+	pub fn new(stream: S) -> Result<WorldParser<S>, String> {
+		WorldParser::with_options(stream, ParseOptions::default())
+	}
+
+	/// Start reading the world in `stream` like `new`, with the given `options`.
+	pub fn with_options(mut stream: S, options: ParseOptions) -> Result<WorldParser<S>, String> {
+		let start = stream.stream_position().map_err(|e| format!("Failed to find the start of the world: {}", e))?;
+		let mut magic = [0; 4];
+		let is_rzt = stream.read_exact(&mut magic).is_ok() && rzt::is_rzt(&magic);
+		stream.seek(std::io::SeekFrom::Start(start)).map_err(|e| format!("Failed to seek to {}: {}", start, e))?;
+
+		let world_header = if is_rzt {
+			parse_rzt_header(&mut stream)?
+		} else {
+			let mut world_header = WorldHeader::parse_with_options(&mut stream, options).map_err(|e| format!("WorldHeader: {}", e))?;
+
+			let board_offset: u64 = match world_header.world_type {
+				WorldType::Zzt => 0x200,
+				WorldType::SuperZzt => 0x400,
+			};
+			if let Some(ref mut padding) = world_header.padding {
+				// Keep the bytes between the header and the first board too.
+				let header_end = stream.stream_position().map_err(|e| format!("Failed to find the end of the world header: {}", e))?;
+				let mut gap = vec![0; board_offset.saturating_sub(header_end - start) as usize];
+				stream.read_exact(&mut gap).map_err(|e| format!("Failed to read padding: {}", e))?;
+				padding.blobs.push(gap);
+			}
+			stream.seek(std::io::SeekFrom::Start(start + board_offset)).map_err(|e| format!("Failed to seek to {}: {}", board_offset, e))?;
+			world_header
+		};
+
+		Ok(WorldParser {
+			stream,
+			is_rzt,
+			world_header,
+			boards: vec![],
+			options,
+		})
+	}
+
+	/// Get how many boards have been read, out of how many there are.
+	pub fn progress(&self) -> ParseProgress {
+		ParseProgress {
+			boards_parsed: self.boards.len(),
+			total_boards: (self.world_header.num_boards_except_title as i32 + 1).max(0) as usize,
+		}
+	}
+
+	/// True if every board has been read, so `finish` won't have to read any more.
+	pub fn is_finished(&self) -> bool {
+		let progress = self.progress();
+		progress.boards_parsed >= progress.total_boards
+	}
+
+	/// Read the next board, if there are any left.
+	pub fn parse_next_board(&mut self) -> Result<ParseProgress, String> {
+		if !self.is_finished() {
+			let board = if self.is_rzt {
+				parse_rzt_board(&mut self.stream, self.world_header.world_type, self.boards.len())?
+			} else {
+				Board::parse_with_options(&mut self.stream, self.world_header.world_type, self.options).map_err(|e| format!("Board: {}", e))?
+			};
+			self.boards.push(board);
+		}
+		Ok(self.progress())
+	}
+
+	/// Read the rest of the boards, and get the world.
+	pub fn finish(mut self) -> Result<World, String> {
+		while !self.is_finished() {
+			self.parse_next_board()?;
+		}
+
+		let trailing_data = if self.options.preserve_padding && !self.is_rzt {
+			let mut trailing_data = vec![];
+			self.stream.read_to_end(&mut trailing_data).map_err(|e| format!("Failed to read trailing data: {}", e))?;
+			Some(trailing_data)
+		} else {
+			None
+		};
+
+		Ok(World {
+			world_header: self.world_header,
+			boards: self.boards,
+			trailing_data,
+		})
+	}
+}
+
+impl World {
+	/// Read a world like `parse`, calling `on_progress` after each board is read. If `on_progress`
+	/// returns false, the rest of the world isn't read and an error is returned.
+	pub fn parse_with_progress<S: std::io::Read + std::io::Seek>(stream: &mut S, on_progress: &mut dyn FnMut(ParseProgress) -> bool) -> Result<World, String> {
+		let mut parser = WorldParser::new(stream)?;
+		while !parser.is_finished() {
+			let progress = parser.parse_next_board()?;
+			if !on_progress(progress) {
+				return Err(format!("Cancelled after reading {} of {} boards", progress.boards_parsed, progress.total_boards));
+			}
+		}
+		parser.finish()
+	}
+}
@@ -0,0 +1,141 @@
+use crate::code_format::{normalize_code, CodeFormatOptions};
+use crate::dosstring::DosString;
+use crate::{max_board_name_len, CodeSource, World, WorldType, WriteOptions, MAX_BOARD_MESSAGE_LEN, MAX_FLAG_NAME_LEN, MAX_WORLD_NAME_LEN};
+
+use std::fmt;
+
+/// Something that was changed or left out when a world was written, so reading the file back
+/// won't give exactly the same world (see `World::write_with_options`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteWarning {
+	/// The index of the board that was changed, or None if it was the world header.
+	pub board_index: Option<usize>,
+	/// What was changed.
+	pub kind: WriteWarningKind,
+}
+
+/// The kinds of `WriteWarning`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteWarningKind {
+	/// A name or message (eg. "Board name" or "Flag 3 name") was shortened from `len` bytes to
+	/// `max_len` bytes (see `WriteOptions::truncate`).
+	Truncated{field: String, len: usize, max_len: usize},
+	/// The world had `count` flags instead of the `expected` number for its world type, so empty
+	/// flags were added or the flags at the end were left out (see `WriteOptions::truncate`).
+	FlagCountChanged{count: usize, expected: usize},
+	/// The `field` was set, but worlds of this type don't have it, so it was left out. For example
+	/// Super ZZT boards don't have a message, and no world files have extended colours.
+	FieldDropped{field: &'static str},
+	/// The code of the status element at `status_index` was changed by
+	/// `WriteOptions::normalize_code`.
+	CodeNormalized{status_index: usize},
+}
+
+impl fmt::Display for WriteWarning {
+	/// Write the warning as one line, eg. "Board 2: Board name shortened from 60 to 50 bytes".
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.board_index {
+			Some(board_index) => write!(f, "Board {}: ", board_index)?,
+			None => write!(f, "WorldHeader: ")?,
+		}
+		match self.kind {
+			WriteWarningKind::Truncated{ref field, len, max_len} => write!(f, "{} shortened from {} to {} bytes", field, len, max_len),
+			WriteWarningKind::FlagCountChanged{count, expected} => write!(f, "Number of flags changed from {} to {}", count, expected),
+			WriteWarningKind::FieldDropped{field} => write!(f, "{} left out, because this world type doesn't have it", field),
+			WriteWarningKind::CodeNormalized{status_index} => write!(f, "Code of status element {} normalized", status_index),
+		}
+	}
+}
+
+/// Push a `Truncated` warning to `warnings` if `text` is longer than `max_len`.
+fn check_len(warnings: &mut Vec<WriteWarning>, board_index: Option<usize>, field: &str, text: &DosString, max_len: usize) {
+	if text.len() > max_len {
+		warnings.push(WriteWarning {
+			board_index,
+			kind: WriteWarningKind::Truncated{field: field.to_string(), len: text.len(), max_len},
+		});
+	}
+}
+
+impl World {
+	/// Get everything that would be changed or left out when writing the world with `options`.
+	/// Anything that would stop the world from being written at all (see `validate`) isn't
+	/// included.
+	pub fn write_warnings(&self, options: WriteOptions) -> Vec<WriteWarning> {
+		let mut warnings = vec![];
+		let world_header = &self.world_header;
+		let world_type = world_header.world_type;
+		let header_warning = |kind| WriteWarning {
+			board_index: None,
+			kind,
+		};
+
+		if options.truncate {
+			check_len(&mut warnings, None, "World name", &world_header.world_name, MAX_WORLD_NAME_LEN);
+			for (flag_index, flag_name) in world_header.flag_names.iter().enumerate() {
+				check_len(&mut warnings, None, &format!("Flag {} name", flag_index), flag_name, MAX_FLAG_NAME_LEN);
+			}
+			if world_header.flag_names.len() != world_header.flag_names_count() {
+				warnings.push(header_warning(WriteWarningKind::FlagCountChanged{count: world_header.flag_names.len(), expected: world_header.flag_names_count()}));
+			}
+		}
+
+		match world_type {
+			WorldType::Zzt => {
+				if world_header.player_stones.is_some() {
+					warnings.push(header_warning(WriteWarningKind::FieldDropped{field: "Player stones"}));
+				}
+			}
+			WorldType::SuperZzt => {
+				if world_header.player_torches.is_some() || world_header.torch_cycles.is_some() {
+					warnings.push(header_warning(WriteWarningKind::FieldDropped{field: "Player torches"}));
+				}
+			}
+		}
+
+		for (board_index, board) in self.boards.iter().enumerate() {
+			let meta_data = &board.meta_data;
+			let mut board_warning = |kind| warnings.push(WriteWarning {
+				board_index: Some(board_index),
+				kind,
+			});
+			match world_type {
+				WorldType::Zzt => {
+					if meta_data.camera_x.is_some() || meta_data.camera_y.is_some() {
+						board_warning(WriteWarningKind::FieldDropped{field: "Camera position"});
+					}
+				}
+				WorldType::SuperZzt => {
+					if meta_data.message.is_some() {
+						board_warning(WriteWarningKind::FieldDropped{field: "Message"});
+					}
+					if meta_data.is_dark {
+						board_warning(WriteWarningKind::FieldDropped{field: "Is dark"});
+					}
+				}
+			}
+			if board.status_elements.iter().any(|status| status.extended_colours.is_some()) {
+				board_warning(WriteWarningKind::FieldDropped{field: "Extended colours"});
+			}
+
+			if options.normalize_code {
+				for (status_index, status) in board.status_elements.iter().enumerate() {
+					if let CodeSource::Owned(ref code) = status.code_source {
+						if normalize_code(code, CodeFormatOptions::new()).0 != *code {
+							board_warning(WriteWarningKind::CodeNormalized{status_index});
+						}
+					}
+				}
+			}
+
+			if options.truncate {
+				check_len(&mut warnings, Some(board_index), "Board name", &meta_data.board_name, max_board_name_len(world_type));
+				if let Some(ref message) = meta_data.message {
+					check_len(&mut warnings, Some(board_index), "Message", message, MAX_BOARD_MESSAGE_LEN);
+				}
+			}
+		}
+
+		warnings
+	}
+}
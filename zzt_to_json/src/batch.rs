@@ -0,0 +1,168 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use zzt_file_format::World;
+use zzt_file_format::padding::ParseOptions;
+
+/// The file extensions that batch mode checks, in upper case.
+const WORLD_EXTENSIONS: &[&str] = &["ZZT", "SZT", "SAV"];
+
+/// What happened when a world was written back out and read again.
+enum RoundTrip {
+	/// The written file has exactly the same bytes as the original.
+	Identical,
+	/// The bytes are different (eg. because of unused padding), but it reads back as the same world.
+	Equivalent,
+	/// The world that was read back isn't the same as the original, or couldn't be read at all.
+	Changed,
+}
+
+impl RoundTrip {
+	fn name(&self) -> &'static str {
+		match self {
+			RoundTrip::Identical => "identical",
+			RoundTrip::Equivalent => "equivalent",
+			RoundTrip::Changed => "changed",
+		}
+	}
+}
+
+/// The result of checking one file.
+struct FileReport {
+	path: PathBuf,
+	/// The number of boards, if the file could be read.
+	board_count: Option<usize>,
+	/// The offset in the file where reading stopped, and the error, if it couldn't be read (or
+	/// written back out).
+	error: Option<(u64, String)>,
+	round_trip: Option<RoundTrip>,
+}
+
+/// Get every file under `directory` (including in sub-directories) with one of the
+/// `WORLD_EXTENSIONS`, sorted by path.
+fn find_world_files(directory: &Path, world_paths: &mut Vec<PathBuf>) -> Result<(), String> {
+	let entries = std::fs::read_dir(directory).map_err(|e| format!("{}: {}", directory.display(), e))?;
+	for entry in entries.flatten() {
+		let path = entry.path();
+		if path.is_dir() {
+			find_world_files(&path, world_paths)?;
+		} else {
+			let extension = path.extension().map(|extension| extension.to_string_lossy().to_ascii_uppercase()).unwrap_or_default();
+			if WORLD_EXTENSIONS.contains(&extension.as_str()) {
+				world_paths.push(path);
+			}
+		}
+	}
+	world_paths.sort();
+	Ok(())
+}
+
+/// Read the file at `path`, then write it back out and read it again to check nothing is lost. If
+/// `output_path` is set, the written copy is saved there. Anything that goes wrong is put in the
+/// report, so one bad file doesn't stop the rest from being checked.
+fn check_file(path: &Path, output_path: Option<&Path>) -> FileReport {
+	let mut report = FileReport {
+		path: path.to_path_buf(),
+		board_count: None,
+		error: None,
+		round_trip: None,
+	};
+	let data = match std::fs::read(path) {
+		Ok(data) => data,
+		Err(error) => {
+			report.error = Some((0, format!("Read failed: {}", error)));
+			return report;
+		}
+	};
+
+	let mut cursor = Cursor::new(&data);
+	// Boards that DOS ZZT plays are fixed up instead of reported as errors. The written copy is still
+	// read strictly, so the fixed up world has to be valid.
+	let world = match World::parse_with_options(&mut cursor, ParseOptions::lenient()) {
+		Ok(world) => world,
+		Err(error) => {
+			report.error = Some((cursor.position(), error));
+			return report;
+		}
+	};
+	report.board_count = Some(world.boards.len());
+
+	let mut written = vec![];
+	if let Err(error) = world.write(&mut written) {
+		report.error = Some((0, format!("Write failed: {}", error)));
+		return report;
+	}
+	report.round_trip = Some(if written == data {
+		RoundTrip::Identical
+	} else if World::parse(&mut Cursor::new(&written)).is_ok_and(|reread_world| reread_world == world) {
+		RoundTrip::Equivalent
+	} else {
+		RoundTrip::Changed
+	});
+
+	if let Some(output_path) = output_path {
+		let saved = match output_path.parent() {
+			Some(parent) => std::fs::create_dir_all(parent),
+			None => Ok(()),
+		}.and_then(|_| std::fs::write(output_path, &written));
+		if let Err(error) = saved {
+			report.error = Some((0, format!("Saving the copy to {} failed: {}", output_path.display(), error)));
+		}
+	}
+
+	report
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_field(text: &str) -> String {
+	if text.contains([',', '"', '\n']) {
+		format!("\"{}\"", text.replace('"', "\"\""))
+	} else {
+		text.to_string()
+	}
+}
+
+/// Check every world and saved game under `directory`. Files that can't be read, or that change when
+/// they are written back out, count as failures. They are reported on stderr (with the offset where
+/// reading stopped, if it did), and a CSV summary of every file is written to
+/// `csv_path` (or stdout if it is None). If `output_directory` is set, the round-tripped copy of
+/// each file is written there, at the same path relative to `directory`.
+pub fn run_batch(directory: &Path, output_directory: Option<&Path>, csv_path: Option<&Path>) -> Result<(), String> {
+	let mut world_paths = vec![];
+	find_world_files(directory, &mut world_paths)?;
+
+	let mut csv = String::from("path,boards,status,offset,error,round_trip\n");
+	let mut failure_count = 0;
+	for path in &world_paths {
+		let output_path = output_directory.map(|output_directory| output_directory.join(path.strip_prefix(directory).unwrap_or(path)));
+		let report = check_file(path, output_path.as_deref());
+
+		let (status, offset, error) = match (&report.error, &report.round_trip) {
+			(Some((offset, error)), _) => {
+				eprintln!("{}: {} (at offset {})", report.path.display(), error, offset);
+				failure_count += 1;
+				("failed", offset.to_string(), error.as_str())
+			}
+			(None, Some(RoundTrip::Changed)) => {
+				eprintln!("{}: The world is different after writing it back out", report.path.display());
+				failure_count += 1;
+				("failed", String::new(), "")
+			}
+			(None, _) => ("ok", String::new(), ""),
+		};
+		csv += &format!("{},{},{},{},{},{}\n",
+			csv_field(&report.path.to_string_lossy()),
+			report.board_count.map(|count| count.to_string()).unwrap_or_default(),
+			status,
+			offset,
+			csv_field(error),
+			report.round_trip.as_ref().map_or("", |round_trip| round_trip.name()));
+	}
+
+	match csv_path {
+		Some(csv_path) => std::fs::write(csv_path, csv).map_err(|e| format!("{}: {}", csv_path.display(), e))?,
+		None => print!("{}", csv),
+	}
+
+	eprintln!("Checked {} files, {} failed", world_paths.len(), failure_count);
+	Ok(())
+}
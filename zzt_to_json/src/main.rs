@@ -1,10 +1,16 @@
+mod batch;
+
 use std::path::Path;
-use zzt_file_format::World;
+use zzt_file_format::{World, WriteOptions};
+use zzt_file_format::code_format::CodeFormatOptions;
+use zzt_file_format::padding::ParseOptions;
 
 #[derive(Debug, PartialEq)]
 enum FileType {
 	Zzt,
 	Json,
+	Ans,
+	Brd,
 }
 
 impl FileType {
@@ -12,44 +18,209 @@ impl FileType {
 		match type_str {
 			"zzt" => Ok(FileType::Zzt),
 			"json" => Ok(FileType::Json),
+			"ans" => Ok(FileType::Ans),
+			"brd" => Ok(FileType::Brd),
 			_ => Err(type_str.into())
 		}
 	}
 }
 
+/// Get the board number from the `--board` option, or the title board if it isn't given.
+fn board_index_arg(matches: &clap::ArgMatches) -> Result<usize, String> {
+	match matches.value_of("board") {
+		Some(board_str) => board_str.parse().map_err(|_| format!("Invalid board number: {}", board_str)),
+		None => Ok(0),
+	}
+}
+
 fn main() -> Result<(), String> {
 	let matches = clap::App::new("zzt_to_json")
-		.about("Converts between ZZT and JSON formats")
+		.about("Converts between ZZT and JSON formats, converts boards to and from ANSI art, and imports and exports single boards")
 		.arg(clap::Arg::with_name("INPUT_TYPE")
-			.help("The type of the input file: \"zzt\" or \"json\"")
-			.required(true)
+			.help("The type of the input file: \"zzt\", \"json\", \"ans\" (ANSI art or plain text, which is put on the title board of a new world) or \"brd\" (a single board, which is added to a new world)")
+			.required_unless("batch")
 			.index(1))
 		.arg(clap::Arg::with_name("OUTPUT_TYPE")
-			.help("The type of the output file: \"zzt\" or \"json\"")
-			.required(true)
+			.help("The type of the output file: \"zzt\", \"json\", \"ans\" or \"brd\"")
+			.required_unless("batch")
 			.index(2))
 		.arg(clap::Arg::with_name("INPUT")
 			.help("The input file")
-			.required(true)
+			.required_unless("batch")
 			.index(3))
+		.arg(clap::Arg::with_name("board")
+			.long("board")
+			.value_name("BOARD")
+			.help("The board to export when the output type is \"ans\" or \"brd\", or to put the art on when the input type is \"ans\" (the title board by default)"))
+		.arg(clap::Arg::with_name("insert-board")
+			.long("insert-board")
+			.value_name("BRD_FILE")
+			.help("Inserts the board in a .BRD file into the world, at the index given by --insert-at (the end by default). Links to the boards after it are updated"))
+		.arg(clap::Arg::with_name("insert-at")
+			.long("insert-at")
+			.value_name("BOARD")
+			.requires("insert-board")
+			.help("The board index to insert the board from --insert-board at"))
+		.arg(clap::Arg::with_name("merge-world")
+			.long("merge-world")
+			.value_name("WORLD")
+			.help("Adds the boards of another ZZT world to the end of the world. Exits and passages between the added boards are updated, and flags that both worlds use are renamed in the added boards"))
+		.arg(clap::Arg::with_name("merge-boards")
+			.long("merge-boards")
+			.value_name("BOARDS")
+			.requires("merge-world")
+			.help("The boards of the world from --merge-world to add, separated by commas (every board except the title board by default)"))
+		.arg(clap::Arg::with_name("keep-exits")
+			.long("keep-exits")
+			.requires("merge-world")
+			.help("Keeps the exits and passages of the boards from --merge-world as they are, instead of updating them"))
+		.arg(clap::Arg::with_name("into-world")
+			.long("into-world")
+			.value_name("WORLD")
+			.help("When the input type is \"ans\", puts the art on a board of this ZZT world instead of a new world (the board is chosen with --board)"))
+		.arg(clap::Arg::with_name("link-exits")
+			.long("link-exits")
+			.value_name("LAYOUT")
+			.help("Sets the board exits from a layout file, which has a row of board numbers (or \".\" for a gap) on each line, so each board leads to the boards next to it"))
+		.arg(clap::Arg::with_name("check-exits")
+			.long("check-exits")
+			.help("Prints every board exit that doesn't lead back the way it came to stderr"))
+		.arg(clap::Arg::with_name("lint")
+			.long("lint")
+			.help("Prints the problems in the world's object code that can be found without running it, like invalid notes in #play commands, to stderr"))
+		.arg(clap::Arg::with_name("normalize-code")
+			.long("normalize-code")
+			.help("Converts the line endings of the world's object code to ZZT's (for code edited in the JSON with a modern editor), wraps text lines that are too wide for the scroll, and prints the lines that are still too wide to stderr"))
+		.arg(clap::Arg::with_name("truncate")
+			.long("truncate")
+			.help("When the output type is \"zzt\", shortens names and messages that are too long to be saved and fixes the number of flags, instead of failing"))
+		.arg(clap::Arg::with_name("trim")
+			.long("trim")
+			.help("Removes the boards that can't be reached from the title board or starting board, and object code that can never run, and prints what was removed to stderr"))
+		.arg(clap::Arg::with_name("preserve-padding")
+			.long("preserve-padding")
+			.help("When the input type is \"zzt\", keeps the unused bytes of the world (padding, leftover bytes in names and messages and anything after the last board), so converting back to ZZT gives exactly the same file"))
+		.arg(clap::Arg::with_name("lenient")
+			.long("lenient")
+			.help("When the input type is \"zzt\", reads boards that DOS ZZT tolerates but that can't be written back as they are (eg. boards without a player status element) and fixes them up"))
+		.arg(clap::Arg::with_name("batch")
+			.long("batch")
+			.value_name("DIRECTORY")
+			.conflicts_with_all(&["INPUT_TYPE", "OUTPUT_TYPE", "INPUT"])
+			.help("Checks every .ZZT, .SZT and .SAV file in the directory and its sub-directories, and prints a CSV summary"))
+		.arg(clap::Arg::with_name("round-trip-dir")
+			.long("round-trip-dir")
+			.value_name("DIRECTORY")
+			.requires("batch")
+			.help("In batch mode, writes a copy of each file that was read and written back out to this directory"))
+		.arg(clap::Arg::with_name("csv")
+			.long("csv")
+			.value_name("FILE")
+			.requires("batch")
+			.help("In batch mode, writes the CSV summary to this file instead of stdout"))
 		.get_matches();
 	
+	if let Some(batch_directory) = matches.value_of("batch") {
+		return batch::run_batch(Path::new(batch_directory), matches.value_of("round-trip-dir").map(Path::new), matches.value_of("csv").map(Path::new));
+	}
+	
 	let input_type = FileType::parse(matches.value_of("INPUT_TYPE").unwrap())?;
 	let output_type = FileType::parse(matches.value_of("OUTPUT_TYPE").unwrap())?;
 	let input_file_path = Path::new(matches.value_of("INPUT").unwrap());
 	let mut input_file = std::fs::File::open(input_file_path).map_err(|e| format!("{:?}", e))?;
 	
-	let loaded_world;
+	let mut loaded_world;
 	
 	eprintln!("Loading...");
 	
 	match input_type  {
 		FileType::Zzt => {
-			loaded_world = Some(World::parse(&mut input_file)?);
+			let options = ParseOptions {
+				preserve_padding: matches.is_present("preserve-padding"),
+				lenient: matches.is_present("lenient"),
+			};
+			loaded_world = Some(World::parse_with_options(&mut input_file, options)?);
 		}
 		FileType::Json => {
 			loaded_world = Some(serde_json::from_reader(input_file).map_err(|e| format!("{:?}", e))?);
 		}
+		FileType::Ans => {
+			let mut art_data = vec![];
+			std::io::Read::read_to_end(&mut input_file, &mut art_data).map_err(|e| format!("{:?}", e))?;
+			let mut world = match matches.value_of("into-world") {
+				Some(world_path) => {
+					let mut world_file = std::fs::File::open(world_path).map_err(|e| format!("{:?}", e))?;
+					World::parse(&mut world_file)?
+				}
+				None => World::zzt_default(),
+			};
+			let board_index = board_index_arg(&matches)?;
+			let board = world.boards.get_mut(board_index).ok_or_else(|| format!("There is no board {}", board_index))?;
+			ruzzt_engine::ansi_art::import_ansi_art(board, &art_data);
+			loaded_world = Some(world);
+		}
+		FileType::Brd => {
+			let mut world = World::zzt_default();
+			let board = zzt_file_format::Board::parse_brd(&mut input_file, world.world_header.world_type)?;
+			world.insert_board(1, board)?;
+			loaded_world = Some(world);
+		}
+	}
+	
+	if let Some(ref mut world) = loaded_world {
+		if let Some(brd_path) = matches.value_of("insert-board") {
+			let mut brd_file = std::fs::File::open(brd_path).map_err(|e| format!("{}: {}", brd_path, e))?;
+			let board = zzt_file_format::Board::parse_brd(&mut brd_file, world.world_header.world_type)?;
+			let insert_index = match matches.value_of("insert-at") {
+				Some(index_str) => index_str.parse().map_err(|_| format!("Invalid board number: {}", index_str))?,
+				None => world.boards.len(),
+			};
+			world.insert_board(insert_index, board)?;
+		}
+		if let Some(merge_path) = matches.value_of("merge-world") {
+			let mut merge_file = std::fs::File::open(merge_path).map_err(|e| format!("{}: {}", merge_path, e))?;
+			let other_world = World::parse(&mut merge_file)?;
+			let board_indices: Vec<usize> = match matches.value_of("merge-boards") {
+				Some(boards_str) => boards_str.split(',')
+					.map(|index_str| index_str.trim().parse().map_err(|_| format!("Invalid board number: {}", index_str)))
+					.collect::<Result<_, String>>()?,
+				None => (1 .. other_world.boards.len()).collect(),
+			};
+			let report = world.merge(&other_world, &board_indices, !matches.is_present("keep-exits"))?;
+			for line in report.description_lines(&board_indices) {
+				eprintln!("{}", line);
+			}
+		}
+		if let Some(layout_path) = matches.value_of("link-exits") {
+			let layout_text = std::fs::read_to_string(layout_path).map_err(|e| format!("{}: {}", layout_path, e))?;
+			let layout = ruzzt_engine::board_links::BoardLayout::parse(&layout_text)?;
+			ruzzt_engine::board_links::link_exits(world, &layout)?;
+		}
+		if matches.is_present("normalize-code") {
+			let options = CodeFormatOptions {
+				wrap_long_lines: true,
+				.. CodeFormatOptions::new()
+			};
+			for warning in world.normalize_code(options) {
+				eprintln!("{}", warning);
+			}
+		}
+		if matches.is_present("trim") {
+			let report = ruzzt_engine::world_trim::trim_world(world)?;
+			for line in report.description_lines() {
+				eprintln!("{}", line);
+			}
+		}
+		if matches.is_present("check-exits") {
+			for mismatch in ruzzt_engine::board_links::check_exit_reciprocity(world) {
+				eprintln!("{}", mismatch.description());
+			}
+		}
+		if matches.is_present("lint") {
+			for warning in ruzzt_engine::oop_lint::lint_world(world) {
+				eprintln!("{}", warning.description());
+			}
+		}
 	}
 	
 	eprintln!("Saving...");
@@ -60,7 +231,23 @@ fn main() -> Result<(), String> {
 				println!("{}", json_str);
 			}
 			FileType::Zzt => {
-				world.write(&mut std::io::stdout()).map_err(|e| format!("Write failed: {:?}", e))?;
+				let options = WriteOptions {
+					truncate: matches.is_present("truncate"),
+					.. WriteOptions::default()
+				};
+				let warnings = world.write_with_options(&mut std::io::stdout(), options).map_err(|e| format!("Write failed: {:?}", e))?;
+				for warning in warnings {
+					eprintln!("{}", warning);
+				}
+			}
+			FileType::Ans => {
+				let ansi_art = ruzzt_engine::ansi_art::board_to_ansi(&world, board_index_arg(&matches)?)?;
+				std::io::Write::write_all(&mut std::io::stdout(), &ansi_art).map_err(|e| format!("Write failed: {:?}", e))?;
+			}
+			FileType::Brd => {
+				let board_index = board_index_arg(&matches)?;
+				let board = world.boards.get(board_index).ok_or_else(|| format!("There is no board {}", board_index))?;
+				board.write_brd(&mut std::io::stdout(), world.world_header.world_type).map_err(|e| format!("Write failed: {}", e))?;
 			}
 		}
 	}
@@ -0,0 +1,177 @@
+use ruzzt_engine::constants::PLAYER_COLOUR;
+use ruzzt_engine::engine::element_type_to_char_code;
+use zzt_file_format::{BoardTile, ElementType};
+use zzt_file_format::params::{param_fields, ParamField};
+
+use num::FromPrimitive;
+
+/// How the colour of a newly placed element is picked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColourMode {
+	/// The element always uses its `default_colour`.
+	Fixed,
+	/// The element uses the colour picked in the editor, on a black background.
+	OnBlack,
+	/// The element is white on the background colour picked in the editor (doors and passages).
+	WhiteOnChoice,
+}
+
+impl ColourMode {
+	/// The name of the mode, as it appears in the element table JSON.
+	pub fn name(self) -> &'static str {
+		match self {
+			ColourMode::Fixed => "fixed",
+			ColourMode::OnBlack => "on_black",
+			ColourMode::WhiteOnChoice => "white_on_choice",
+		}
+	}
+}
+
+/// What the editor needs to know about an element type to place and edit it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementInfo {
+	pub element_type: ElementType,
+	/// The colour the element is placed with when `colour_mode` is `Fixed`.
+	pub default_colour: u8,
+	pub colour_mode: ColourMode,
+	/// The character the element is drawn with when nothing about the tile changes it.
+	pub default_char_code: u8,
+	/// True if the element needs a status element to do anything.
+	pub has_status: bool,
+	/// The meanings of the status parameters the element uses (see `zzt_file_format::params`).
+	pub params: &'static [ParamField],
+}
+
+/// Get the character an element is drawn with by default. This is the same as the engine uses,
+/// except for the elements that the engine draws based on the tile or status.
+fn default_char_code(element_type: ElementType) -> u8 {
+	match element_type {
+		ElementType::BoardEdge | ElementType::Messenger => 0x20,
+		ElementType::Object => 0x02,
+		ElementType::Transporter => 0xc5,
+		ElementType::Line => 0xce,
+		ElementType::SpinningGun => 0x18,
+		ElementType::Pusher => 0x10,
+		ElementType::Star => 0x2f,
+		ElementType::Duplicator => 0xfa,
+		ElementType::TextBlue | ElementType::TextGreen | ElementType::TextCyan | ElementType::TextRed
+		| ElementType::TextPurple | ElementType::TextBrown | ElementType::TextBlack => 0x20,
+		_ => element_type_to_char_code(element_type),
+	}
+}
+
+/// Get the editor information about the given `element_type`. The default colours are the same as
+/// in ZZT's own editor.
+pub fn element_info(element_type: ElementType) -> ElementInfo {
+	use self::ElementType::*;
+	use self::ColourMode::*;
+
+	let (default_colour, colour_mode, has_status) = match element_type {
+		Empty => (0x70, Fixed, false),
+		BoardEdge => (0x00, Fixed, false),
+		Messenger => (0x00, Fixed, true),
+		Monitor => (0x07, Fixed, true),
+		Player => (PLAYER_COLOUR, Fixed, true),
+		Ammo => (0x03, Fixed, false),
+		Torch => (0x06, Fixed, false),
+		Gem | Key | Solid | Normal | Breakable | Boulder | SliderNS | SliderEW | Fake | Invisible => (0x0f, OnBlack, false),
+		Door => (0x0f, WhiteOnChoice, false),
+		Scroll => (0x0f, Fixed, true),
+		Passage => (0x0f, WhiteOnChoice, true),
+		Duplicator => (0x0f, Fixed, true),
+		Bomb => (0x0f, OnBlack, true),
+		Energizer => (0x05, Fixed, false),
+		Star => (0x0f, Fixed, true),
+		Clockwise | Counter => (0x0f, OnBlack, true),
+		Bullet => (0x0f, Fixed, true),
+		Water => (0xf9, Fixed, false),
+		Forest => (0x20, Fixed, false),
+		BlinkWall => (0x0f, OnBlack, true),
+		Transporter => (0x0f, OnBlack, true),
+		Line => (0x0f, OnBlack, false),
+		Ricochet => (0x0a, Fixed, false),
+		BlinkRayHorizontal | BlinkRayVertical => (0x0f, OnBlack, false),
+		Bear => (0x06, Fixed, true),
+		Ruffian => (0x0d, Fixed, true),
+		Object => (0x0f, OnBlack, true),
+		Slime => (0x0f, OnBlack, true),
+		Shark => (0x07, Fixed, true),
+		SpinningGun => (0x0f, OnBlack, true),
+		Pusher => (0x0f, OnBlack, true),
+		Lion => (0x0c, Fixed, true),
+		Tiger => (0x0b, Fixed, true),
+		Head => (0x0f, OnBlack, true),
+		Segment => (0x0f, OnBlack, true),
+		TextBlue => (0x1f, Fixed, false),
+		TextGreen => (0x2f, Fixed, false),
+		TextCyan => (0x3f, Fixed, false),
+		TextRed => (0x4f, Fixed, false),
+		TextPurple => (0x5f, Fixed, false),
+		TextBrown => (0x6f, Fixed, false),
+		TextBlack => (0x0f, Fixed, false),
+	};
+
+	ElementInfo {
+		element_type,
+		default_colour,
+		colour_mode,
+		default_char_code: default_char_code(element_type),
+		has_status,
+		params: param_fields(element_type),
+	}
+}
+
+/// Get the text element that shows typed characters in the foreground colour of `colour` (the
+/// colour picked in the editor), the same way as text entry mode in ZZT's editor. Text elements
+/// always have a white foreground, so the foreground colour picks the background instead: blue
+/// gives `TextBlue`, through to brown giving `TextBrown`. White, grey and black give `TextBlack`.
+pub fn text_element_for_colour(colour: u8) -> ElementType {
+	match colour & 0x07 {
+		1 => ElementType::TextBlue,
+		2 => ElementType::TextGreen,
+		3 => ElementType::TextCyan,
+		4 => ElementType::TextRed,
+		5 => ElementType::TextPurple,
+		6 => ElementType::TextBrown,
+		_ => ElementType::TextBlack,
+	}
+}
+
+/// Get the tile that text entry mode places when `char_code` is typed with `colour` picked in the
+/// editor (see `text_element_for_colour`). Text elements keep the character in the tile's colour.
+pub fn text_tile(char_code: u8, colour: u8) -> BoardTile {
+	BoardTile {
+		element_id: text_element_for_colour(colour) as u8,
+		colour: char_code,
+	}
+}
+
+/// Get the information about every element type, ordered by element ID.
+pub fn all_element_info() -> Vec<ElementInfo> {
+	(0 ..= 255u8).filter_map(ElementType::from_u8).map(element_info).collect()
+}
+
+/// Get the element table as JSON, so the editor UI can build its element palette and status
+/// parameter editors from it.
+pub fn element_table_json() -> String {
+	let elements: Vec<serde_json::Value> = all_element_info().iter().map(|info| {
+		let params: Vec<serde_json::Value> = info.params.iter().map(|field| {
+			serde_json::json!({
+				"name": field.name,
+				"param": field.param.field_name(),
+				"mask": field.mask,
+				"max": field.max,
+			})
+		}).collect();
+		serde_json::json!({
+			"id": info.element_type as u8,
+			"name": format!("{:?}", info.element_type),
+			"default_colour": info.default_colour,
+			"colour_mode": info.colour_mode.name(),
+			"default_char_code": info.default_char_code,
+			"has_status": info.has_status,
+			"params": params,
+		})
+	}).collect();
+	serde_json::to_string_pretty(&elements).unwrap()
+}
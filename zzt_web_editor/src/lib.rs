@@ -1,7 +1,23 @@
-use zzt_file_format::{World, BoardTile};
-use ruzzt_engine::console::{ConsoleColour, SCREEN_WIDTH, SCREEN_HEIGHT};
+pub mod elements;
+pub mod touch;
+
+pub use touch::TouchControl;
+
+use zzt_file_format::{World, Board, BoardMetaData, BoardTile, CodeSource, StatusElement};
+use zzt_file_format::code_format::{normalize_code, CodeFormatOptions};
+use zzt_file_format::dosstring::DosString;
+use zzt_file_format::padding::ParseOptions;
+use zzt_file_format::world_parser::WorldParser;
+use ruzzt_engine::board_info::{board_info_lines, BoardInfoField};
+use ruzzt_engine::board_message::BoardMessage;
+use ruzzt_engine::board_links::{check_exit_reciprocity, link_exits, BoardLayout};
+use ruzzt_engine::board_region::BoardRegion;
+use ruzzt_engine::board_simulator::{self, default_status_element_limit, screen_pos_to_sim_pos};
+use ruzzt_engine::compatibility::board_hash;
+use ruzzt_engine::console::{pixel_to_screen_pos, Colour, ScreenRect, CHAR_WIDTH};
 use ruzzt_engine::engine::RuzztEngine;
-use num::FromPrimitive;
+use ruzzt_engine::event::Event;
+use ruzzt_engine::random_fill::{random_fill, RandomFillEntry, RandomFillOptions};
 
 use wasm_bindgen::prelude::*;
 
@@ -18,7 +34,7 @@ pub fn zzt_to_json(zzt_data: &[u8]) -> Result<String, JsValue> {
 
 pub fn zzt_to_json_impl(zzt_data: &[u8]) -> Result<String, String> {
 	let mut cursor = std::io::Cursor::new(zzt_data);
-	let world = World::parse(&mut cursor)?;
+	let world = World::parse_with_options(&mut cursor, ParseOptions::lenient())?;
 	let json_str = serde_json::to_string_pretty(&world).map_err(|e| format!("{:?}", e))?;
 	Ok(json_str)
 }
@@ -36,34 +52,15 @@ pub struct FgBgRgb {
 }
 
 impl FgBgRgb {
-	fn from_console_colours(fg: ConsoleColour, bg: ConsoleColour) -> FgBgRgb {
-		let mut blinking = false;
-		let mut back_num = bg as u8;
-		if back_num >= 8 {
-			back_num -= 8;
-			blinking = true;
-		}
-	
-		let real_bg = ruzzt_engine::console::ConsoleColour::from_u8(back_num).unwrap();
-		let (fg_r, fg_g, fg_b) = fg.to_rgb();
-		let (bg_r, bg_g, bg_b) = real_bg.to_rgb();
-		FgBgRgb{fg_r, fg_g, fg_b, bg_r, bg_g, bg_b, blinking}
+	fn from_colour(colour: Colour) -> FgBgRgb {
+		let ((fg_r, fg_g, fg_b), (bg_r, bg_g, bg_b)) = colour.to_rgb();
+		FgBgRgb{fg_r, fg_g, fg_b, bg_r, bg_g, bg_b, blinking: colour.blinking}
 	}
 }
 
 #[wasm_bindgen]
 pub fn zzt_colour_to_rgb(zzt_colour: u8) -> FgBgRgb {
-	let mut blinking = false;
-	let mut bg_col = (zzt_colour & 0xF) >> 4;
-	let fg_col = zzt_colour & 0xF;
-	if bg_col >= 8 {
-		bg_col -= 8;
-		blinking = true;
-	}
-	
-	let (fg_r, fg_g, fg_b) = ConsoleColour::from_u8(fg_col).unwrap().to_rgb();
-	let (bg_r, bg_g, bg_b) = ConsoleColour::from_u8(bg_col).unwrap().to_rgb();
-	FgBgRgb{fg_r, fg_g, fg_b, bg_r, bg_g, bg_b, blinking}
+	FgBgRgb::from_colour(Colour::from_u8(zzt_colour))
 }
 
 #[wasm_bindgen]
@@ -72,9 +69,184 @@ pub struct ScreenChar {
 	pub colour: FgBgRgb,
 }
 
+/// A character on the screen that changed since the last render.
+#[wasm_bindgen]
+pub struct ScreenCharChange {
+	pub x: usize,
+	pub y: usize,
+	pub char_code: u8,
+	pub colour: FgBgRgb,
+}
+
+/// The number of bytes for each character in `RenderedRect::cells`: the character code, then the
+/// red, green and blue of the foreground, then the red, green and blue of the background.
+pub const RENDERED_CELL_BYTES: usize = 7;
+
+/// The characters in a rectangle of the visible region that changed since the last render (see
+/// `WorldState::render_dirty_rect`). The position is relative to the top-left of the visible region.
+#[wasm_bindgen]
+pub struct RenderedRect {
+	pub x: usize,
+	pub y: usize,
+	pub width: usize,
+	pub height: usize,
+	cells: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl RenderedRect {
+	/// Every character in the rectangle, in rows from the top-left, with `RENDERED_CELL_BYTES`
+	/// bytes for each one. Blinking characters are blank while they are hidden.
+	#[wasm_bindgen(getter)]
+	pub fn cells(&self) -> Vec<u8> {
+		self.cells.clone()
+	}
+}
+
+/// Where the visible region is drawn on a canvas that may not have the same aspect ratio (see
+/// `WorldState::letterbox`). The region is scaled by `scale` and centred, with bars either side.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct Letterbox {
+	pub scale: f64,
+	pub offset_x: f64,
+	pub offset_y: f64,
+}
+
+/// A position in the simulator, which has a border of board edges around the board, so the
+/// top-left tile of the board is 1x1. Status element locations, `get_tile_at` and `set_tile` use
+/// these positions, but the tiles in `get_board_json` start at 0x0.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct SimPos {
+	pub x: i16,
+	pub y: i16,
+}
+
+impl From<board_simulator::SimPos> for SimPos {
+	fn from(pos: board_simulator::SimPos) -> SimPos {
+		SimPos{x: pos.x, y: pos.y}
+	}
+}
+
+/// Get the position of the tile under the given pixel position on the rendered screen, where each
+/// character is 8x14 pixels. Returns undefined if the pixel isn't over the board.
+#[wasm_bindgen]
+pub fn pixel_to_sim_pos(pixel_x: i32, pixel_y: i32) -> Option<SimPos> {
+	let (screen_x, screen_y) = pixel_to_screen_pos(pixel_x, pixel_y)?;
+	screen_pos_to_sim_pos(screen_x, screen_y).map(SimPos::from)
+}
+
+/// Get a JSON description of the on-screen touch controls (a D-pad and a shoot button), so the
+/// host page can draw them without duplicating the layout.
+#[wasm_bindgen]
+pub fn get_touch_overlay_json() -> String {
+	touch::overlay_json()
+}
+
+/// Get a JSON array describing every element type: its ID, name, default colour and character,
+/// whether it has a status element and what its status parameters mean.
+#[wasm_bindgen]
+pub fn get_element_table_json() -> String {
+	elements::element_table_json()
+}
+
+/// Get the event for the given `code`, as used by `WorldState::advance`:
+///
+/// | Code | Event | Code | Event |
+/// |------|-------|------|-------|
+/// | 0 | None | 8 | Shoot south |
+/// | 1 | Move west | 9 | Shoot the way the player is moving |
+/// | 2 | Move east | 10 | Light a torch |
+/// | 3 | Move north | 11 | Enter |
+/// | 4 | Move south | 12 | Escape |
+/// | 5 | Shoot west | 13 | Pause |
+/// | 6 | Shoot east | 14 | Page up |
+/// | 7 | Shoot north | 15 | Page down |
+///
+/// Returns None for any other code.
+pub fn event_from_code(code: u8) -> Option<Event> {
+	Some(match code {
+		0 => Event::None,
+		1 => Event::Left,
+		2 => Event::Right,
+		3 => Event::Up,
+		4 => Event::Down,
+		5 => Event::ShootLeft,
+		6 => Event::ShootRight,
+		7 => Event::ShootUp,
+		8 => Event::ShootDown,
+		9 => Event::ShootFlow,
+		10 => Event::LightTorch,
+		11 => Event::Enter,
+		12 => Event::Escape,
+		13 => Event::PauseGame,
+		14 => Event::PageUp,
+		15 => Event::PageDown,
+		_ => return None,
+	})
+}
+
+/// Reads a world file a few boards at a time, so the editor can show a progress bar while a big
+/// world loads instead of freezing. Loading is cancelled by freeing the loader. Once it's done,
+/// give it to `WorldState::from_loader`.
+#[wasm_bindgen]
+pub struct WorldLoader {
+	parser: WorldParser<std::io::Cursor<Vec<u8>>>,
+}
+
+#[wasm_bindgen]
+impl WorldLoader {
+	/// Start loading the world in `zzt_file_data`. This only reads the world header.
+	pub fn new(zzt_file_data: Vec<u8>) -> Result<WorldLoader, JsValue> {
+		let parser = WorldParser::with_options(std::io::Cursor::new(zzt_file_data), ParseOptions::lenient())?;
+		Ok(WorldLoader {
+			parser,
+		})
+	}
+
+	/// Read up to `board_count` more boards. Returns true once every board has been read.
+	pub fn parse_boards(&mut self, board_count: usize) -> Result<bool, JsValue> {
+		for _ in 0 .. board_count {
+			if self.parser.is_finished() {
+				break;
+			}
+			self.parser.parse_next_board()?;
+		}
+		Ok(self.parser.is_finished())
+	}
+
+	/// Get the number of boards that have been read so far.
+	pub fn boards_parsed(&self) -> usize {
+		self.parser.progress().boards_parsed
+	}
+
+	/// Get the number of boards in the world, including the title board.
+	pub fn total_boards(&self) -> usize {
+		self.parser.progress().total_boards
+	}
+}
+
 #[wasm_bindgen]
 struct WorldState {
 	engine: RuzztEngine,
+	/// True while the touch shoot button is held, which makes the D-pad shoot instead of move.
+	touch_shoot_held: bool,
+	/// Counts every change made to the world, so the editor can ask for only what changed since a
+	/// revision it already has.
+	revision: u32,
+	/// The revision that the world header last changed at.
+	world_header_revision: u32,
+	/// The revision that each board last changed at.
+	board_revisions: Vec<u32>,
+	/// The part of the screen that is shown, eg. to follow the player with a camera. None shows the
+	/// whole screen.
+	visible_region: Option<ScreenRect>,
+	/// What was left out by the last call to `export_vanilla_zzt_bytes`.
+	downgrade_losses: Vec<String>,
+	/// The wall-clock time given to the last step. `advance` carries on from it, adding the length of
+	/// each cycle it runs (see `RuzztEngine::step_length_ms`).
+	time_passed_seconds: f64,
 }
 
 #[wasm_bindgen]
@@ -83,61 +255,698 @@ impl WorldState {
 		Self::from_file_data_impl(zzt_file_data).map_err(|err| err.into())
 	}
 	
+	/// Read any boards that `loader` hasn't read yet, and start editing the world.
+	pub fn from_loader(loader: WorldLoader) -> Result<WorldState, JsValue> {
+		let world = loader.parser.finish()?;
+		Self::from_world(world).map_err(|err| err.into())
+	}
+	
 	fn from_file_data_impl(zzt_file_data: &[u8]) -> Result<WorldState, String> {
 		let mut cursor = std::io::Cursor::new(zzt_file_data);
-		let world = World::parse(&mut cursor)?;
+		let world = World::parse_with_options(&mut cursor, ParseOptions::lenient())?;
+		Self::from_world(world)
+	}
+	
+	fn from_world(world: World) -> Result<WorldState, String> {
 		let mut engine = RuzztEngine::new();
-		engine.load_world(world, None);
+		engine.load_world(world, None)?;
 		engine.set_in_title_screen(false);
 		
+		let board_revisions = vec![0; engine.board_count()];
 		Ok(WorldState {
 			engine,
+			touch_shoot_held: false,
+			revision: 0,
+			world_header_revision: 0,
+			board_revisions,
+			visible_region: None,
+			downgrade_losses: vec![],
+			time_passed_seconds: 0.,
 		})
 	}
 	
 	pub fn get_world_json(&mut self) -> String {
-		self.engine.sync_world();
-		serde_json::to_string_pretty(&self.engine.world).unwrap()
+		serde_json::to_string_pretty(self.engine.sync_world()).unwrap()
 	}
 	
 	pub fn get_world_header_json(&mut self) -> String {
-		self.engine.sync_world();
-		serde_json::to_string_pretty(&self.engine.world.world_header).unwrap()
+		serde_json::to_string_pretty(&self.engine.sync_world().world_header).unwrap()
 	}
 	
 	pub fn get_current_board_index(&self) -> i16 {
-		self.engine.world.world_header.player_board
+		self.engine.board_simulator.world_header.player_board
 	}
 	
-	pub fn get_status_elements_json(&mut self, board_index: i16) -> String {
-		self.engine.sync_world();
-		serde_json::to_string_pretty(&self.engine.world.boards[board_index as usize].status_elements).unwrap()
+	pub fn get_status_elements_json(&mut self, board_index: i16) -> Result<String, JsValue> {
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		Ok(serde_json::to_string_pretty(&board.status_elements).unwrap())
 	}
 	
-	pub fn get_board_meta_data_json(&mut self, board_index: i16) -> String {
-		self.engine.sync_world();
-		serde_json::to_string_pretty(&self.engine.world.boards[board_index as usize].meta_data).unwrap()
+	pub fn get_board_meta_data_json(&mut self, board_index: i16) -> Result<String, JsValue> {
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		Ok(serde_json::to_string_pretty(&board.meta_data).unwrap())
+	}
+	
+	/// Get the board with the given `board_index` as JSON, without the rest of the world.
+	pub fn get_board_json(&mut self, board_index: i16) -> Result<String, JsValue> {
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		Ok(serde_json::to_string_pretty(board).unwrap())
+	}
+	
+	/// Get the board with the given `board_index` in the MessagePack format, which is faster to
+	/// make and smaller than JSON.
+	pub fn get_board_msgpack(&mut self, board_index: i16) -> Result<Vec<u8>, JsValue> {
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		rmp_serde::to_vec_named(board).map_err(|err| err.to_string().into())
+	}
+	
+	/// Get the current revision of the world. Every change made by editing or simulating the world
+	/// increases it.
+	pub fn get_revision(&self) -> u32 {
+		self.revision
+	}
+	
+	/// Record that the board with the given `board_index` changed.
+	fn mark_board_changed(&mut self, board_index: i16) {
+		self.revision += 1;
+		if let Some(board_revision) = self.board_revisions.get_mut(board_index as usize) {
+			*board_revision = self.revision;
+		}
+	}
+	
+	/// Record that the world header changed.
+	fn mark_world_header_changed(&mut self) {
+		self.revision += 1;
+		self.world_header_revision = self.revision;
+	}
+	
+	/// Get everything that changed after `since_revision`, in the form:
+	/// `{"revision": <current revision>, "world_header": <header or null>, "boards": [{"index": <board index>, "board": <board>}, ...]}`
+	fn changes_since(&mut self, since_revision: u32) -> serde_json::Value {
+		let world = self.engine.sync_world();
+		let world_header = if self.world_header_revision > since_revision {
+			serde_json::to_value(&world.world_header).unwrap()
+		} else {
+			serde_json::Value::Null
+		};
+		let boards: Vec<serde_json::Value> = self.board_revisions.iter().enumerate()
+			.filter(|(_, board_revision)| **board_revision > since_revision)
+			.map(|(board_index, _)| serde_json::json!({
+				"index": board_index,
+				"board": &world.boards[board_index],
+			}))
+			.collect();
+		serde_json::json!({
+			"revision": self.revision,
+			"world_header": world_header,
+			"boards": boards,
+		})
+	}
+	
+	/// Get the world header and boards that changed after `since_revision` as JSON. Pass the
+	/// `revision` from the result to the next call to only get newer changes.
+	pub fn get_changes_json(&mut self, since_revision: u32) -> String {
+		serde_json::to_string(&self.changes_since(since_revision)).unwrap()
 	}
 	
+	/// The same as `get_changes_json`, but in the MessagePack format.
+	pub fn get_changes_msgpack(&mut self, since_revision: u32) -> Result<Vec<u8>, JsValue> {
+		rmp_serde::to_vec_named(&self.changes_since(since_revision)).map_err(|err| err.to_string().into())
+	}
+	
+	/// Get the JSON for the tile at the given `x`/`y` position in the simulator (see `SimPos`).
 	pub fn get_tile_at(&mut self, x: i16, y: i16) -> String {
 		serde_json::to_string_pretty(&self.engine.board_simulator.get_tile(x, y)).unwrap()
 	}
 	
+	/// Apply `edit` to the board with the given `board_index` in the world. If it's the board being
+	/// simulated, the simulator is reloaded so the change shows up straight away.
+	fn edit_board<T>(&mut self, board_index: i16, edit: impl FnOnce(&mut Board) -> Result<T, String>) -> Result<T, String> {
+		let board = self.engine.sync_world_mut().boards.get_mut(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		let result = edit(board)?;
+		self.mark_board_changed(board_index);
+		self.engine.mark_dirty();
+		if board_index == self.engine.board_simulator.world_header.player_board {
+			let board = self.engine.board(board_index as usize).unwrap().into_owned();
+			self.engine.board_simulator.load_board(&board);
+		}
+		Ok(result)
+	}
+	
+	/// Set the tile at the given `x`/`y` position on a board. Positions are in the simulator, the
+	/// same as `get_tile_at` (see `SimPos`).
+	pub fn set_tile(&mut self, board_index: i16, x: i16, y: i16, element_id: u8, colour: u8) -> Result<(), JsValue> {
+		self.edit_board(board_index, |board| {
+			let tile_index = board_simulator::SimPos::new(x, y).to_board_pos().tile_index()
+				.ok_or_else(|| format!("{}, {} is outside the board", x, y))?;
+			board.tiles[tile_index] = BoardTile{element_id, colour};
+			Ok(())
+		}).map_err(|err| err.into())
+	}
+	
+	/// Type a character in text entry mode, placing a text element showing `char_code` at the given
+	/// `x`/`y` position (the same as `set_tile`). `colour` is the colour picked in the editor, which
+	/// chooses the kind of text element (see `elements::text_element_for_colour`). Returns the
+	/// element ID that was placed.
+	pub fn type_text_char(&mut self, board_index: i16, x: i16, y: i16, char_code: u8, colour: u8) -> Result<u8, JsValue> {
+		let tile = elements::text_tile(char_code, colour);
+		self.set_tile(board_index, x, y, tile.element_id, tile.colour)?;
+		Ok(tile.element_id)
+	}
+	
+	/// Add a status element described by `status_json` (in the same format as
+	/// `get_status_elements_json`) to the end of a board's list, and get its index.
+	pub fn add_status_element(&mut self, board_index: i16, status_json: &str) -> Result<usize, JsValue> {
+		let status_element: StatusElement = serde_json::from_str(status_json).map_err(|err| err.to_string())?;
+		self.edit_board(board_index, |board| {
+			board.status_elements.push(status_element);
+			Ok(board.status_elements.len() - 1)
+		}).map_err(|err| err.into())
+	}
+	
+	/// Replace the status element at `status_index` with the one described by `status_json`.
+	pub fn set_status_element(&mut self, board_index: i16, status_index: usize, status_json: &str) -> Result<(), JsValue> {
+		let status_element: StatusElement = serde_json::from_str(status_json).map_err(|err| err.to_string())?;
+		self.edit_board(board_index, |board| {
+			let existing_status = board.status_elements.get_mut(status_index)
+				.ok_or_else(|| format!("There is no status element {}", status_index))?;
+			*existing_status = status_element;
+			Ok(())
+		}).map_err(|err| err.into())
+	}
+	
+	/// Get the parameters of the status element at `status_index` by name (eg. `{"intelligence": 4}`),
+	/// based on the type of the element it is on. See `zzt_file_format::params`.
+	pub fn get_status_params_json(&mut self, board_index: i16, status_index: usize) -> Result<String, JsValue> {
+		let world_type = self.engine.board_simulator.world_header.world_type;
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		let element_type = board.status_element_type(status_index, world_type)?;
+		let params: serde_json::Map<String, serde_json::Value> = board.status_elements[status_index].named_params(element_type).into_iter()
+			.map(|(name, value)| (name.to_string(), value.into()))
+			.collect();
+		Ok(serde_json::to_string_pretty(&params).unwrap())
+	}
+	
+	/// Set the parameter called `name` of the status element at `status_index`, as named by
+	/// `get_status_params_json`.
+	pub fn set_status_param(&mut self, board_index: i16, status_index: usize, name: &str, value: u8) -> Result<(), JsValue> {
+		let world_type = self.engine.board_simulator.world_header.world_type;
+		self.edit_board(board_index, |board| {
+			let element_type = board.status_element_type(status_index, world_type)?;
+			board.status_elements[status_index].set_param_value(element_type, name, value)
+		}).map_err(|err| err.into())
+	}
+	
+	/// Remove the status element at `status_index`. Status elements that were bound to its code
+	/// get their own copy of the code, and bindings to later status elements are renumbered. The
+	/// player's status element (index 0) can't be removed.
+	pub fn remove_status_element(&mut self, board_index: i16, status_index: usize) -> Result<(), JsValue> {
+		self.edit_board(board_index, |board| {
+			if status_index == 0 {
+				return Err("The player's status element can't be removed".to_string());
+			}
+			if status_index >= board.status_elements.len() {
+				return Err(format!("There is no status element {}", status_index));
+			}
+			let removed_status = board.status_elements.remove(status_index);
+			for status in &mut board.status_elements {
+				if status.code_source == CodeSource::Bound(status_index) {
+					status.code_source = removed_status.code_source.clone();
+				}
+				// This also renumbers the binding copied from the removed status element.
+				if let CodeSource::Bound(ref mut bound_index) = status.code_source {
+					if *bound_index > status_index {
+						*bound_index -= 1;
+					}
+				}
+			}
+			Ok(())
+		}).map_err(|err| err.into())
+	}
+	
+	/// Copy the `width` by `height` rectangle of a board with its top-left corner at `x`/`y` (see
+	/// `BoardRegion::copy`), and get it as JSON to put on the clipboard.
+	pub fn copy_region(&mut self, board_index: i16, x: usize, y: usize, width: usize, height: usize) -> Result<String, JsValue> {
+		let board = self.engine.sync_world().boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		Ok(BoardRegion::copy(board, x, y, width, height)?.to_json())
+	}
+	
+	/// Paste a region that was copied with `copy_region` onto a board with its top-left corner at
+	/// `x`/`y` (see `BoardRegion::paste`).
+	pub fn paste_region(&mut self, board_index: i16, x: usize, y: usize, region_json: &str) -> Result<(), JsValue> {
+		let region = BoardRegion::from_json(region_json)?;
+		self.edit_board(board_index, |board| region.paste(board, x, y)).map_err(|err| err.into())
+	}
+	
+	/// Scatter tiles at random over the empty tiles of a board (see `random_fill`), and get the
+	/// number of tiles placed. `entries_json` is a list of `RandomFillEntry`s, eg.
+	/// `[{"tile": {"element_id": 21, "colour": 14}, "status": null, "density": 0.1}]` for yellow
+	/// walls on about 10% of the board. The same `seed` always fills an empty board the same way.
+	pub fn random_fill(&mut self, board_index: i16, entries_json: &str, seed: u32) -> Result<usize, JsValue> {
+		let entries: Vec<RandomFillEntry> = serde_json::from_str(entries_json).map_err(|err| err.to_string())?;
+		let mut options = RandomFillOptions::new(entries, seed as u64);
+		options.status_element_limit = default_status_element_limit(self.engine.board_simulator.world_header.world_type);
+		self.edit_board(board_index, |board| random_fill(board, &options)).map_err(|err| err.into())
+	}
+	
+	/// Set the code of the status element at `status_index` (eg. an object's program or a scroll's
+	/// text). Lines are separated by `\n`, `\r\n` or `\r`. When `wrap_long_lines` is true (eg. for
+	/// pasted code), text lines too wide for the scroll are wrapped. Returns the lines that are
+	/// still too wide (see `code_format::normalize_code`), separated by `\n`.
+	pub fn set_object_code(&mut self, board_index: i16, status_index: usize, code: &str, wrap_long_lines: bool) -> Result<String, JsValue> {
+		// `DosString::from_str` only understands `\n` line endings.
+		let code = DosString::from_str(&code.replace("\r\n", "\n").replace('\r', "\n"));
+		let options = CodeFormatOptions {
+			wrap_long_lines,
+			.. CodeFormatOptions::new()
+		};
+		let (code, warnings) = normalize_code(&code, options);
+		self.edit_board(board_index, |board| {
+			let status = board.status_elements.get_mut(status_index)
+				.ok_or_else(|| format!("There is no status element {}", status_index))?;
+			status.code_source = CodeSource::Owned(code);
+			status.code_current_instruction = 0;
+			Ok(())
+		})?;
+		Ok(warnings.join("\n"))
+	}
+	
+	/// Replace a board's meta data (name, exits, etc.) with the one described by `meta_data_json`, in
+	/// the same format as `get_board_meta_data_json`.
+	pub fn set_board_meta_data(&mut self, board_index: i16, meta_data_json: &str) -> Result<(), JsValue> {
+		let meta_data: BoardMetaData = serde_json::from_str(meta_data_json).map_err(|err| err.to_string())?;
+		self.edit_board(board_index, |board| {
+			board.meta_data = meta_data;
+			Ok(())
+		}).map_err(|err| err.into())
+	}
+	
+	/// Get the lines of the board information panel for the board (see
+	/// `ruzzt_engine::board_info`), separated by `\n`.
+	pub fn get_board_info_text(&mut self, board_index: i16) -> Result<String, JsValue> {
+		let world = self.engine.sync_world();
+		let board = world.boards.get(board_index as usize)
+			.ok_or_else(|| format!("There is no board {}", board_index))?;
+		let lines: Vec<String> = board_info_lines(&board.meta_data, &world.boards).iter()
+			.map(|line| line.to_string(false))
+			.collect();
+		Ok(lines.join("\n"))
+	}
+	
+	/// Set a single board information field, named like `BoardInfoField::name` (eg. "max_shots"),
+	/// to the given `value`.
+	pub fn set_board_info_field(&mut self, board_index: i16, field_name: &str, value: &str) -> Result<(), JsValue> {
+		let field = BoardInfoField::from_name(field_name)
+			.ok_or_else(|| format!("There is no board info field called {}", field_name))?;
+		self.edit_board(board_index, |board| {
+			field.set_value(&mut board.meta_data, value)
+		}).map_err(|err| err.into())
+	}
+	
+	/// Set the exits of the boards in `layout_text` (in the format read by `BoardLayout::parse`) so
+	/// that they lead to the boards next to them.
+	pub fn link_board_exits(&mut self, layout_text: &str) -> Result<(), JsValue> {
+		let layout = BoardLayout::parse(layout_text)?;
+		link_exits(self.engine.sync_world_mut(), &layout)?;
+		self.engine.mark_dirty();
+		for board_index in layout.rows.iter().flatten().flatten() {
+			self.mark_board_changed(*board_index as i16);
+		}
+		let current_board_index = self.engine.board_simulator.world_header.player_board;
+		let current_board = self.engine.board(current_board_index as usize).unwrap().into_owned();
+		self.engine.board_simulator.load_board(&current_board);
+		Ok(())
+	}
+	
+	/// Get a description of every exit that doesn't lead back the way it came (see
+	/// `ruzzt_engine::board_links::check_exit_reciprocity`), separated by `\n`.
+	pub fn get_exit_mismatches_text(&mut self) -> String {
+		let descriptions: Vec<String> = check_exit_reciprocity(self.engine.sync_world()).iter()
+			.map(|mismatch| mismatch.description())
+			.collect();
+		descriptions.join("\n")
+	}
+	
+	/// True if the world has been edited since it was loaded or `mark_saved` was last called, so
+	/// the editor should ask before throwing it away.
+	pub fn is_dirty(&self) -> bool {
+		self.engine.is_dirty()
+	}
+	
+	/// Note that the world has been saved (eg. after writing `to_zzt_bytes` to a file), so it no
+	/// longer has unsaved changes.
+	pub fn mark_saved(&mut self) {
+		self.engine.mark_saved();
+	}
+	
+	/// Get a copy of the world including any edits, without having to sync the engine's world.
+	fn edited_world(&self) -> World {
+		self.engine.world_copy()
+	}
+
+	/// Get the world in the .ZZT file format, including any edits.
+	pub fn to_zzt_bytes(&self) -> Result<Vec<u8>, JsValue> {
+		let mut zzt_data = vec![];
+		self.edited_world().write(&mut zzt_data)?;
+		Ok(zzt_data)
+	}
+
+	/// Get the world in the .RZT container, including any edits. Unlike `to_zzt_bytes`, this works
+	/// even when boards are too big for the .ZZT file format.
+	pub fn to_rzt_bytes(&self) -> Result<Vec<u8>, JsValue> {
+		let mut rzt_data = vec![];
+		self.edited_world().write_rzt(&mut rzt_data)?;
+		Ok(rzt_data)
+	}
+
+	/// Get the world in the .ZZT file format like `to_zzt_bytes`, leaving out whatever doesn't fit
+	/// (see `World::write_vanilla`). Call `get_downgrade_losses_text` afterwards to find out what was
+	/// left out.
+	pub fn export_vanilla_zzt_bytes(&mut self) -> Result<Vec<u8>, JsValue> {
+		let mut zzt_data = vec![];
+		self.downgrade_losses = self.edited_world().write_vanilla(&mut zzt_data)?;
+		Ok(zzt_data)
+	}
+
+	/// Describe what was left out by the last call to `export_vanilla_zzt_bytes`, one thing per line.
+	pub fn get_downgrade_losses_text(&self) -> String {
+		self.downgrade_losses.join("\n")
+	}
+	
+	/// Handle a touch starting at the given pixel position on the unscaled screen. Touching a
+	/// control presses it until `touch_end` is called with the returned control, and touching the
+	/// board moves (or shoots while the shoot button is held) towards the touched tile. While a
+	/// scroll is open, touching anywhere else is the same as pressing Enter.
+	pub fn touch_start(&mut self, pixel_x: i32, pixel_y: i32) -> Option<TouchControl> {
+		if let Some(control) = TouchControl::at_pixel(pixel_x, pixel_y) {
+			if control == TouchControl::Shoot {
+				self.touch_shoot_held = true;
+			}
+			self.engine.key_repeat.press(control.event(self.touch_shoot_held));
+			Some(control)
+		} else if self.engine.scroll_state.is_some() {
+			// Tapping a scroll is the same as pressing Enter, so it follows the selected link or
+			// closes the scroll.
+			self.engine.key_repeat.press(Event::Enter);
+			None
+		} else {
+			let sim_pos = pixel_to_screen_pos(pixel_x, pixel_y)
+				.and_then(|(screen_x, screen_y)| screen_pos_to_sim_pos(screen_x, screen_y));
+			if let Some(pos) = sim_pos {
+				self.engine.key_repeat.press(Event::Click{pos, shoot: self.touch_shoot_held});
+			}
+			None
+		}
+	}
+
+	/// Handle a touch that was started on `control` being lifted.
+	pub fn touch_end(&mut self, control: TouchControl) {
+		if control == TouchControl::Shoot {
+			self.touch_shoot_held = false;
+		}
+		self.engine.key_repeat.release(control.event(self.touch_shoot_held));
+	}
+
+	/// Handle a swipe that travelled `delta_x`/`delta_y` pixels on the unscaled screen.
+	pub fn swipe(&mut self, delta_x: i32, delta_y: i32) {
+		let event = touch::swipe_event(delta_x, delta_y, self.touch_shoot_held);
+		if event != Event::None {
+			self.engine.key_repeat.press(event);
+		}
+	}
+
+	/// Set the wall-clock time in milliseconds that blinking characters are shown and hidden by
+	/// (see `ConsoleState::set_blink_time_ms`). `step` does this too, but this keeps characters
+	/// blinking between steps when the game is slowed down.
+	pub fn set_blink_time(&mut self, time_ms: f64) {
+		self.engine.console_state.set_blink_time_ms(time_ms.max(0.) as usize);
+	}
+	
+	/// Simulate a single game step using the latest touch input. `global_time_passed_seconds` is
+	/// the wall-clock time passed since the game started.
+	pub fn step(&mut self, global_time_passed_seconds: f64) {
+		self.set_blink_time(global_time_passed_seconds * 1000.);
+		self.time_passed_seconds = global_time_passed_seconds;
+		self.track_step_changes(|world_state| {
+			let event = world_state.engine.key_repeat.next_event();
+			let board_messages = world_state.engine.step(event, global_time_passed_seconds);
+			WorldState::process_board_messages(&mut world_state.engine, board_messages);
+		});
+	}
+	
+	/// Run `steps` whole game cycles straight away with the input given by `event_code` (see
+	/// `event_from_code`), so pages can play back recorded input without calling `step` at exactly
+	/// the right times. Movement and shooting events are given to every cycle, like a key held
+	/// down, and other events only to the first. Nothing happens while the game is paused (see
+	/// `set_paused`). Returns everything the game sent in the form:
+	/// `{"sounds": [{"entries": [<sound entry>, ...], "priority": <priority>}, ...], "messages": [<board message>, ...]}`
+	/// where `messages` has every board message apart from the sounds.
+	pub fn advance(&mut self, steps: u32, event_code: u8) -> Result<String, JsValue> {
+		let mut event = event_from_code(event_code).ok_or_else(|| format!("There is no event with the code {}", event_code))?;
+		let (sounds, messages) = self.track_step_changes(|world_state| {
+			let mut sounds = vec![];
+			let mut messages = vec![];
+			for _ in 0 .. steps {
+				// Time passes by the length of a cycle each time, like it would if the page called
+				// `step` at the right times.
+				world_state.time_passed_seconds += world_state.engine.step_length_ms() / 1000.;
+				world_state.set_blink_time(world_state.time_passed_seconds * 1000.);
+				world_state.engine.step_whole_cycle(event, world_state.time_passed_seconds, |engine, board_messages| {
+					for board_message in WorldState::process_board_messages(engine, board_messages) {
+						match board_message {
+							BoardMessage::PlaySoundArray(entries, priority) => sounds.push(serde_json::json!({
+								"entries": entries,
+								"priority": priority,
+							})),
+							board_message => messages.push(board_message),
+						}
+					}
+				});
+				if !event.is_repeatable() {
+					event = Event::None;
+				}
+			}
+			(sounds, messages)
+		});
+		
+		Ok(serde_json::to_string(&serde_json::json!({
+			"sounds": sounds,
+			"messages": messages,
+		})).unwrap())
+	}
+	
+	/// Pause or unpause the game, like pressing P.
+	pub fn set_paused(&mut self, paused: bool) {
+		self.engine.is_paused = paused;
+	}
+	
+	/// Get the number of seconds left on the current board's time limit, or undefined if it doesn't
+	/// have one.
+	pub fn get_board_time_left(&self) -> Option<i16> {
+		self.engine.board_time_left()
+	}
+	
+	/// Set the number of seconds left on the current board's time limit, eg. to give the player extra
+	/// time. Fails if the board doesn't have a time limit.
+	pub fn set_board_time_left(&mut self, time_left: i16) -> Result<(), JsValue> {
+		self.engine.set_board_time_left(time_left)?;
+		self.mark_world_header_changed();
+		Ok(())
+	}
+	
+	/// Get the number of seconds that have passed since the player entered the current board.
+	pub fn get_time_passed(&self) -> i16 {
+		self.engine.time_passed()
+	}
+	
+	/// Set the number of seconds that have passed since the player entered the current board.
+	pub fn set_time_passed(&mut self, time_passed: i16) {
+		self.engine.set_time_passed(time_passed);
+		self.mark_world_header_changed();
+	}
+	
+	/// Apply each of `board_messages` to `engine`, along with any messages that applying them
+	/// sends. Returns every message that was applied. This takes the engine instead of the world
+	/// state, so it can be used while the engine is simulating a cycle (see
+	/// `RuzztEngine::step_whole_cycle`).
+	fn process_board_messages(engine: &mut RuzztEngine, mut board_messages: Vec<BoardMessage>) -> Vec<BoardMessage> {
+		let mut processed_board_messages = vec![];
+		while !board_messages.is_empty() {
+			let processing_board_messages = std::mem::take(&mut board_messages);
+			for board_message in processing_board_messages {
+				board_messages.extend(engine.process_board_message(board_message.clone()));
+				processed_board_messages.push(board_message);
+			}
+		}
+		processed_board_messages
+	}
+	
+	/// Simulate the game with `simulate`, and record the changes that it made to the world header
+	/// and boards, so that the boards it didn't change aren't sent again by `get_changes_json`.
+	fn track_step_changes<T>(&mut self, simulate: impl FnOnce(&mut WorldState) -> T) -> T {
+		let sim = &self.engine.board_simulator;
+		let start_board_index = sim.world_header.player_board;
+		let start_world_header = sim.world_header.clone();
+		let start_board_meta_data = sim.board_meta_data.clone();
+		// The status elements are compared as well as the hash, because the hash doesn't cover
+		// their code (eg. an object that does `#zap`).
+		let start_status_elements = sim.status_elements.clone();
+		let start_board_hash = board_hash(sim);
+		
+		let result = simulate(self);
+		
+		let sim = &self.engine.board_simulator;
+		let end_board_index = sim.world_header.player_board;
+		let world_header_changed = sim.world_header != start_world_header;
+		// When the player moves to another board, both boards could have been changed.
+		let board_changed = end_board_index != start_board_index
+			|| sim.board_meta_data != start_board_meta_data
+			|| sim.status_elements != start_status_elements
+			|| board_hash(sim) != start_board_hash;
+		if world_header_changed {
+			self.mark_world_header_changed();
+		}
+		if board_changed {
+			self.mark_board_changed(start_board_index);
+			if end_board_index != start_board_index {
+				self.mark_board_changed(end_board_index);
+			}
+		}
+		result
+	}
+
+	/// True if any characters might have changed since the last render, so the page can skip
+	/// rendering on frames where nothing happened.
+	pub fn screen_dirty(&self) -> bool {
+		self.engine.screen_dirty()
+	}
+
+	/// Get every character on the screen, in rows from the top-left. Blinking characters are blank
+	/// while they are hidden.
 	pub fn render_board(&mut self) -> js_sys::Array {
-		let mut result_screen = js_sys::Array::new();
+		let result_screen = js_sys::Array::new();
 		self.engine.sync_world();
 		self.engine.update_screen();
-		let ref screen_chars = self.engine.console_state.screen_chars;
-		for y in 0..SCREEN_HEIGHT {
-			for x in 0..SCREEN_WIDTH {
-				let ref c = screen_chars[y][x];
-				let screen_char = ScreenChar {
-					char_code: c.char_code,
-					colour: FgBgRgb::from_console_colours(c.foreground, c.background),
-				};
-				result_screen.push(&JsValue::from(screen_char));
-			}
+		self.engine.console_state.mark_all_dirty();
+		for (_, _, c) in self.engine.console_state.take_dirty_cells() {
+			let c = self.engine.console_state.blinked_char(c);
+			let screen_char = ScreenChar {
+				char_code: c.char_code,
+				colour: FgBgRgb::from_colour(Colour::from_console_colours(c.foreground, c.background)),
+			};
+			result_screen.push(&JsValue::from(screen_char));
 		}
 		result_screen
 	}
+	
+	/// Only show the `width` by `height` rectangle of the screen with its top-left corner at
+	/// `x`/`y` in `render_dirty_rect` and `letterbox`. It is cut down to fit on the screen, and the
+	/// next render redraws all of it.
+	pub fn set_visible_region(&mut self, x: usize, y: usize, width: usize, height: usize) {
+		let screen_rect = self.engine.console_state.screen_rect();
+		self.visible_region = Some(ScreenRect::new(x, y, width, height).intersection(screen_rect).unwrap_or(screen_rect));
+		self.engine.console_state.mark_all_dirty();
+	}
+
+	/// Show the whole screen again after `set_visible_region`.
+	pub fn clear_visible_region(&mut self) {
+		self.visible_region = None;
+		self.engine.console_state.mark_all_dirty();
+	}
+
+	/// Get the part of the screen that is shown (see `set_visible_region`).
+	fn visible_region(&self) -> ScreenRect {
+		self.visible_region.unwrap_or_else(|| self.engine.console_state.screen_rect())
+	}
+
+	/// Get the smallest rectangle of the visible region that contains every character that changed
+	/// since the last render, along with those characters, or undefined if nothing changed. The
+	/// first render draws the whole visible region. This lets a canvas redraw only what changed
+	/// without making an object for each character.
+	pub fn render_dirty_rect(&mut self) -> Option<RenderedRect> {
+		self.engine.sync_world();
+		self.engine.update_screen();
+		let region = self.visible_region();
+		let dirty_rect = self.engine.console_state.take_dirty_rect(region)?;
+
+		let mut cells = Vec::with_capacity(dirty_rect.width * dirty_rect.height * RENDERED_CELL_BYTES);
+		for y in dirty_rect.y .. dirty_rect.y + dirty_rect.height {
+			for x in dirty_rect.x .. dirty_rect.x + dirty_rect.width {
+				let c = self.engine.console_state.blinked_char(self.engine.console_state.get_char(x, y));
+				let ((fg_r, fg_g, fg_b), (bg_r, bg_g, bg_b)) = Colour::from_console_colours(c.foreground, c.background).to_rgb();
+				cells.extend_from_slice(&[c.char_code, fg_r, fg_g, fg_b, bg_r, bg_g, bg_b]);
+			}
+		}
+
+		Some(RenderedRect {
+			x: dirty_rect.x - region.x,
+			y: dirty_rect.y - region.y,
+			width: dirty_rect.width,
+			height: dirty_rect.height,
+			cells,
+		})
+	}
+
+	/// Get where to draw the visible region on a canvas that is `canvas_width` by `canvas_height`
+	/// pixels, so it is as big as possible without changing its aspect ratio.
+	pub fn letterbox(&self, canvas_width: f64, canvas_height: f64) -> Letterbox {
+		let region = self.visible_region();
+		let region_width = (region.width * CHAR_WIDTH) as f64;
+		let region_height = (region.height * self.engine.console_state.screen_mode().char_height()) as f64;
+		let scale = f64::min(canvas_width / region_width, canvas_height / region_height).max(0.);
+		Letterbox {
+			scale,
+			offset_x: (canvas_width - region_width * scale) / 2.,
+			offset_y: (canvas_height - region_height * scale) / 2.,
+		}
+	}
+
+	/// Get the board position of the tile under the given pixel position on a canvas that is
+	/// `canvas_width` by `canvas_height` pixels, with the visible region drawn on it as `letterbox`
+	/// says. Returns undefined if the pixel isn't over the board.
+	pub fn canvas_pixel_to_sim_pos(&self, pixel_x: f64, pixel_y: f64, canvas_width: f64, canvas_height: f64) -> Option<SimPos> {
+		let letterbox = self.letterbox(canvas_width, canvas_height);
+		if letterbox.scale <= 0. {
+			return None;
+		}
+		let region_pixel_x = ((pixel_x - letterbox.offset_x) / letterbox.scale).floor();
+		let region_pixel_y = ((pixel_y - letterbox.offset_y) / letterbox.scale).floor();
+		if region_pixel_x < 0. || region_pixel_y < 0. {
+			return None;
+		}
+
+		let region = self.visible_region();
+		let screen_x = region.x + region_pixel_x as usize / CHAR_WIDTH;
+		let screen_y = region.y + region_pixel_y as usize / self.engine.console_state.screen_mode().char_height();
+		if !region.contains(screen_x, screen_y) {
+			return None;
+		}
+		screen_pos_to_sim_pos(screen_x, screen_y).map(SimPos::from)
+	}
+
+	/// Get only the characters that changed since the last call to this or `render_board`, as
+	/// `ScreenCharChange`s.
+	pub fn render_board_changes(&mut self) -> js_sys::Array {
+		let result_changes = js_sys::Array::new();
+		self.engine.sync_world();
+		self.engine.update_screen();
+		for (x, y, c) in self.engine.console_state.take_dirty_cells() {
+			let c = self.engine.console_state.blinked_char(c);
+			let change = ScreenCharChange {
+				x,
+				y,
+				char_code: c.char_code,
+				colour: FgBgRgb::from_colour(Colour::from_console_colours(c.foreground, c.background)),
+			};
+			result_changes.push(&JsValue::from(change));
+		}
+		result_changes
+	}
 }
@@ -0,0 +1,126 @@
+use ruzzt_engine::console::{CHAR_WIDTH, CHAR_HEIGHT};
+use ruzzt_engine::event::Event;
+
+use wasm_bindgen::prelude::*;
+
+/// The minimum distance in screen pixels a swipe has to travel before it counts as a move.
+pub const SWIPE_MIN_DISTANCE: i32 = CHAR_WIDTH as i32 * 2;
+
+/// One of the buttons of the on-screen touch controls.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TouchControl {
+	Up,
+	Down,
+	Left,
+	Right,
+	Shoot,
+}
+
+impl TouchControl {
+	/// All the touch controls, in the order they are listed in the overlay description.
+	pub fn all() -> [TouchControl; 5] {
+		[TouchControl::Up, TouchControl::Down, TouchControl::Left, TouchControl::Right, TouchControl::Shoot]
+	}
+
+	/// The name of the control, as it appears in the overlay description.
+	pub fn name(self) -> &'static str {
+		match self {
+			TouchControl::Up => "up",
+			TouchControl::Down => "down",
+			TouchControl::Left => "left",
+			TouchControl::Right => "right",
+			TouchControl::Shoot => "shoot",
+		}
+	}
+
+	/// The DOS character code to draw on the button.
+	pub fn label_char_code(self) -> u8 {
+		match self {
+			TouchControl::Up => 24,
+			TouchControl::Down => 25,
+			TouchControl::Left => 27,
+			TouchControl::Right => 26,
+			TouchControl::Shoot => b'*',
+		}
+	}
+
+	/// The area of the screen the button covers, as (x, y, width, height) in characters. The
+	/// D-pad sits at the bottom of the side bar, with the shoot button to the right of it.
+	pub fn char_rect(self) -> (usize, usize, usize, usize) {
+		match self {
+			TouchControl::Up => (64, 19, 3, 2),
+			TouchControl::Down => (64, 23, 3, 2),
+			TouchControl::Left => (61, 21, 3, 2),
+			TouchControl::Right => (67, 21, 3, 2),
+			TouchControl::Shoot => (73, 21, 5, 2),
+		}
+	}
+
+	/// The area of the screen the button covers, as (x, y, width, height) in screen pixels.
+	pub fn pixel_rect(self) -> (i32, i32, i32, i32) {
+		let (x, y, width, height) = self.char_rect();
+		((x * CHAR_WIDTH) as i32, (y * CHAR_HEIGHT) as i32, (width * CHAR_WIDTH) as i32, (height * CHAR_HEIGHT) as i32)
+	}
+
+	/// Get the control under the given screen pixel position, if any.
+	pub fn at_pixel(pixel_x: i32, pixel_y: i32) -> Option<TouchControl> {
+		TouchControl::all().iter().cloned().find(|control| {
+			let (x, y, width, height) = control.pixel_rect();
+			pixel_x >= x && pixel_x < x + width && pixel_y >= y && pixel_y < y + height
+		})
+	}
+
+	/// Get the event sent when this control is pressed. Directions shoot instead of move while
+	/// `shoot_held` is true, the same as holding shift with the arrow keys.
+	pub fn event(self, shoot_held: bool) -> Event {
+		match (self, shoot_held) {
+			(TouchControl::Up, false) => Event::Up,
+			(TouchControl::Down, false) => Event::Down,
+			(TouchControl::Left, false) => Event::Left,
+			(TouchControl::Right, false) => Event::Right,
+			(TouchControl::Up, true) => Event::ShootUp,
+			(TouchControl::Down, true) => Event::ShootDown,
+			(TouchControl::Left, true) => Event::ShootLeft,
+			(TouchControl::Right, true) => Event::ShootRight,
+			(TouchControl::Shoot, _) => Event::ShootFlow,
+		}
+	}
+}
+
+/// Get the event for a swipe that travelled `delta_x`/`delta_y` screen pixels. The swipe moves
+/// along whichever axis it travelled furthest, or shoots that way if `shoot` is true. Swipes
+/// shorter than `SWIPE_MIN_DISTANCE` are ignored.
+pub fn swipe_event(delta_x: i32, delta_y: i32, shoot: bool) -> Event {
+	if delta_x.abs() < SWIPE_MIN_DISTANCE && delta_y.abs() < SWIPE_MIN_DISTANCE {
+		return Event::None;
+	}
+
+	let control = if delta_x.abs() >= delta_y.abs() {
+		if delta_x < 0 { TouchControl::Left } else { TouchControl::Right }
+	} else if delta_y < 0 {
+		TouchControl::Up
+	} else {
+		TouchControl::Down
+	};
+
+	control.event(shoot)
+}
+
+/// Get a JSON description of the touch controls, so the host page can draw them over the screen.
+/// Each entry has the control `name`, the `char_code` to draw on it, and its `x`, `y`, `width` and
+/// `height` in pixels on the unscaled 640x350 screen.
+pub fn overlay_json() -> String {
+	let controls: Vec<serde_json::Value> = TouchControl::all().iter().map(|control| {
+		let (x, y, width, height) = control.pixel_rect();
+		serde_json::json!({
+			"name": control.name(),
+			"char_code": control.label_char_code(),
+			"x": x,
+			"y": y,
+			"width": width,
+			"height": height,
+		})
+	}).collect();
+	serde_json::to_string_pretty(&controls).unwrap()
+}
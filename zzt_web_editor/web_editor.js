@@ -1,4 +1,4 @@
-import { zzt_to_json, zzt_colour_to_rgb, WorldState, default as init } from "/pkg/zzt_web_editor.js";
+import { zzt_to_json, zzt_colour_to_rgb, WorldLoader, WorldState, default as init } from "/pkg/zzt_web_editor.js";
 async function run() {
 	await init("/pkg/zzt_web_editor_bg.wasm");
 	//const result = zzt_to_json("asdf");
@@ -21,6 +21,7 @@ class Main {
 		
 		this.status_elements_div = document.getElementById("status_elements")
 		this.boards_list = document.getElementById("boards_list")
+		this.load_progress = document.getElementById("load_progress")
 		
 		this.current_board_index = 0
 	}
@@ -33,7 +34,25 @@ class Main {
 		reader.onload = () => {
 			let data = reader.result
 			let u8data = new Uint8Array(data)
-			this.world_state = WorldState.from_file_data(u8data)
+			this.load_world_in_chunks(WorldLoader.new(u8data))
+		}
+
+		reader.readAsArrayBuffer(file)
+	}
+	
+	// Read a few boards at a time so the progress bar can be drawn between them.
+	load_world_in_chunks(loader) {
+		this.load_progress.hidden = false
+		this.load_progress.max = loader.total_boards()
+		let load_chunk = () => {
+			let finished = loader.parse_boards(4)
+			this.load_progress.value = loader.boards_parsed()
+			if (!finished) {
+				setTimeout(load_chunk, 0)
+				return
+			}
+			this.load_progress.hidden = true
+			this.world_state = WorldState.from_loader(loader)
 			//this.world = JSON.parse(this.world_state.get_world_json())
 			//console.log(this.world)
 			this.status_elements = JSON.parse(this.world_state.get_status_elements_json(this.world_state.get_current_board_index()))
@@ -43,8 +62,7 @@ class Main {
 			console.log(this.status_elements)
 			this.render()
 		}
-
-		reader.readAsArrayBuffer(file)
+		load_chunk()
 	}
 	
 	create_status_editor(status, index) {